@@ -0,0 +1,741 @@
+//! Turning parsed statements into bytes.
+//!
+//! Assembly runs in passes. Each pass encodes the whole program, taking
+//! the value of a symbol from earlier in the same pass if it is defined
+//! there and from the previous pass otherwise (0 on the first). A symbol
+//! used before its definition can change what a later pass encodes, such
+//! as the width of an immediate, so passes repeat until no symbol moves.
+//!
+//! Branches to labels go through [`Encoder`] labels, which pick the short
+//! form for bound labels and patch forward branches once their label is
+//! bound.
+//!
+//! A RIP-relative memory operand gets the distance from the end of its
+//! instruction to the address it names. That distance is always a disp32,
+//! so the instruction's length is known before the displacement is.
+
+use crate::{
+    ast::{Addressing, BinOp, Datum, Expr, Insn, Mem, OpKind, Stmt, StmtKind},
+    lexer::error,
+    object::{Object, Relocation, RelocationKind, Section, Symbol, SymbolKind},
+};
+use rask_common::{RaskError, RaskResult};
+use rask_x86_64::{
+    encoder::Encoder,
+    features::CpuFeatures,
+    instruction::{Instruction, Mnemonic, Prefix},
+    mode::Mode,
+    operand::{AddrReg, Label, MemOperand, Operand},
+    registers::SegReg,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Passes after which assembly gives up on symbols settling.
+const MAX_PASSES: usize = 16;
+
+/// Every section after the first starts on a boundary of this many bytes.
+const SECTION_ALIGN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Def {
+    Label { section: usize },
+    Equ,
+    Extern,
+}
+
+/// Statements with the mode and `default` addressing they are assembled
+/// with.
+type Placed<'a> = Vec<(Mode, Addressing, &'a Stmt)>;
+
+/// What the statements define and where they go, worked out once before
+/// the passes.
+struct Layout<'a> {
+    defs: HashMap<&'a str, Def>,
+    /// Defined names in source order.
+    order: Vec<&'a str>,
+    globals: HashSet<&'a str>,
+    /// Each section's name and its statements.
+    sections: Vec<(&'a str, Placed<'a>)>,
+    origin: i64,
+}
+
+/// Assembles `stmts` into at most `max_output` bytes.
+pub(crate) fn assemble(
+    stmts: &[Stmt],
+    mode: Mode,
+    features: CpuFeatures,
+    max_output: u64,
+) -> RaskResult<Object> {
+    let layout = Layout::new(stmts, mode)?;
+    let mut values = HashMap::new();
+    for _ in 0..MAX_PASSES {
+        let mut pass = Pass::new(&layout, &values, features, max_output);
+        pass.run()?;
+        if pass.values == values {
+            return pass.finish();
+        }
+        values = pass.values;
+    }
+    Err(RaskError::Other(format!(
+        "symbol values did not settle after {MAX_PASSES} passes"
+    )))
+}
+
+impl<'a> Layout<'a> {
+    fn new(stmts: &'a [Stmt], mut mode: Mode) -> RaskResult<Self> {
+        let mut addressing = Addressing::default();
+        let mut layout = Layout {
+            defs: HashMap::new(),
+            order: Vec::new(),
+            globals: HashSet::new(),
+            sections: Vec::new(),
+            origin: 0,
+        };
+        let mut globals = Vec::new();
+        let mut origin = None;
+        let mut current = None;
+
+        for stmt in stmts {
+            let (line, column) = (stmt.line, stmt.column);
+            match &stmt.kind {
+                StmtKind::Section(name) => {
+                    current = Some(layout.section(name));
+                    continue;
+                }
+                StmtKind::Global(names) => {
+                    globals.extend(names.iter().map(|(name, column)| (name, line, *column)));
+                    continue;
+                }
+                StmtKind::Extern(names) => {
+                    for (name, column) in names {
+                        layout.define(name, Def::Extern, line, *column)?;
+                    }
+                    continue;
+                }
+                StmtKind::Bits(bits) => {
+                    mode = *bits;
+                    continue;
+                }
+                StmtKind::Default(default) => {
+                    addressing = *default;
+                    continue;
+                }
+                StmtKind::Org(expr) => {
+                    if origin.is_some() || layout.sections.iter().any(|(_, s)| !s.is_empty()) {
+                        return Err(error(line, column, "`org` must come once, before any code"));
+                    }
+                    let value = constant(expr).map_err(|msg| error(line, column, msg))?;
+                    origin = Some(value);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let section = *current.get_or_insert_with(|| layout.section(".text"));
+            match &stmt.kind {
+                StmtKind::Label(name) => {
+                    layout.define(name, Def::Label { section }, line, column)?
+                }
+                StmtKind::Equ(name, _) => layout.define(name, Def::Equ, line, column)?,
+                _ => {}
+            }
+            layout.sections[section].1.push((mode, addressing, stmt));
+        }
+        layout.origin = origin.unwrap_or(0);
+
+        for (name, line, column) in globals {
+            match layout.defs.get(name.as_str()) {
+                Some(Def::Extern) => {
+                    return Err(error(line, column, format!("`{name}` is declared extern")));
+                }
+                Some(_) => {
+                    layout.globals.insert(name);
+                }
+                None => return Err(error(line, column, format!("undefined symbol `{name}`"))),
+            }
+        }
+        for (_, stmts) in &layout.sections {
+            for (_, _, stmt) in stmts {
+                layout.check_symbols(stmt.line, &stmt.kind)?;
+            }
+        }
+        layout.check_cycles()?;
+        Ok(layout)
+    }
+
+    /// Returns the index of the section `name`, adding it if it is new.
+    fn section(&mut self, name: &'a str) -> usize {
+        match self.sections.iter().position(|(n, _)| *n == name) {
+            Some(index) => index,
+            None => {
+                self.sections.push((name, Vec::new()));
+                self.sections.len() - 1
+            }
+        }
+    }
+
+    fn define(&mut self, name: &'a str, def: Def, line: usize, column: usize) -> RaskResult<()> {
+        if self.defs.insert(name, def).is_some() {
+            return Err(error(
+                line,
+                column,
+                format!("symbol `{name}` is defined more than once"),
+            ));
+        }
+        self.order.push(name);
+        Ok(())
+    }
+
+    /// Fails if a statement refers to a symbol that is never defined.
+    fn check_symbols(&self, line: usize, kind: &StmtKind) -> RaskResult<()> {
+        let mut exprs = Vec::new();
+        match kind {
+            StmtKind::Equ(_, expr) | StmtKind::Align(expr) => exprs.push(expr),
+            StmtKind::Data(_, items) => exprs.extend(items.iter().filter_map(|item| match item {
+                Datum::Expr(expr) => Some(expr),
+                Datum::Bytes(_) => None,
+            })),
+            StmtKind::Times(count, kind) => {
+                exprs.push(count);
+                self.check_symbols(line, kind)?;
+            }
+            StmtKind::Insn(insn) => {
+                for op in &insn.operands {
+                    match &op.kind {
+                        OpKind::Imm(expr) => exprs.push(expr),
+                        OpKind::Mem(mem) => exprs.extend(&mem.disp),
+                        OpKind::Reg(_) => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let mut result = Ok(());
+        for expr in exprs {
+            expr.symbols(&mut |name, column| {
+                if result.is_ok() && !self.defs.contains_key(name) {
+                    result = Err(error(line, column, format!("undefined symbol `{name}`")));
+                }
+            });
+        }
+        result
+    }
+
+    /// Fails if a constant depends on itself, which no number of passes
+    /// can settle.
+    fn check_cycles(&self) -> RaskResult<()> {
+        let mut equs = HashMap::new();
+        for (_, stmts) in &self.sections {
+            for (_, _, stmt) in stmts {
+                if let StmtKind::Equ(name, expr) = &stmt.kind {
+                    equs.insert(name.as_str(), (*stmt, expr));
+                }
+            }
+        }
+
+        for &name in &self.order {
+            let Some(&(stmt, expr)) = equs.get(name) else {
+                continue;
+            };
+            let mut pending = vec![expr];
+            let mut seen = HashSet::new();
+            while let Some(expr) = pending.pop() {
+                let mut cyclic = false;
+                expr.symbols(&mut |symbol, _| {
+                    if symbol == name {
+                        cyclic = true;
+                    } else if let Some((&symbol, &(_, expr))) = equs.get_key_value(symbol)
+                        && seen.insert(symbol)
+                    {
+                        pending.push(expr);
+                    }
+                });
+                if cyclic {
+                    return Err(error(
+                        stmt.line,
+                        stmt.column,
+                        format!("`{name}` is defined in terms of itself"),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates the `org` address, an expression made of numbers only.
+fn constant(expr: &Expr) -> Result<i64, &'static str> {
+    match expr {
+        Expr::Int(value) => Ok(*value),
+        Expr::Neg(e) => Ok(constant(e)?.wrapping_neg()),
+        Expr::Not(e) => Ok(!constant(e)?),
+        Expr::Binary(op, a, b) => binary(*op, constant(a)?, constant(b)?),
+        Expr::Symbol { .. } | Expr::Here | Expr::SectionStart => {
+            Err("`org` needs a constant address")
+        }
+    }
+}
+
+fn binary(op: BinOp, a: i64, b: i64) -> Result<i64, &'static str> {
+    let shift = || match u32::try_from(b) {
+        Ok(count) if count < 64 => Ok(count),
+        _ => Err("shift count is outside 0..64"),
+    };
+    let value = match op {
+        BinOp::Or => Some(a | b),
+        BinOp::Xor => Some(a ^ b),
+        BinOp::And => Some(a & b),
+        BinOp::Shl => a.checked_shl(shift()?),
+        BinOp::Shr => (a as u64).checked_shr(shift()?).map(|v| v as i64),
+        BinOp::Add => a.checked_add(b),
+        BinOp::Sub => a.checked_sub(b),
+        BinOp::Mul => a.checked_mul(b),
+        BinOp::Div | BinOp::Rem if b == 0 => return Err("division by zero"),
+        BinOp::Div => a.checked_div(b),
+        BinOp::Rem => a.checked_rem(b),
+    };
+    value.ok_or("overflow")
+}
+
+/// The value of an expression: a number, possibly plus the address of an
+/// external symbol.
+struct Value<'e> {
+    value: i64,
+    external: Option<&'e str>,
+}
+
+/// One run over the whole program.
+struct Pass<'a> {
+    layout: &'a Layout<'a>,
+    previous: &'a HashMap<String, i64>,
+    values: HashMap<String, i64>,
+    enc: Encoder,
+    labels: HashMap<&'a str, Label>,
+    sections: Vec<Section>,
+    relocations: Vec<Relocation>,
+    /// Offset of the current section in the output.
+    section_start: usize,
+    /// True in sections whose name starts with `.text`, which `align`
+    /// pads with NOPs rather than zeros.
+    code: bool,
+    /// The `default` addressing of the current statement.
+    addressing: Addressing,
+    /// The most bytes the program may assemble to, so that a huge `times`
+    /// count or alignment fails instead of exhausting memory.
+    max_output: u64,
+    /// Line and column of the current statement.
+    at: (usize, usize),
+}
+
+impl<'a> Pass<'a> {
+    fn new(
+        layout: &'a Layout<'a>,
+        previous: &'a HashMap<String, i64>,
+        features: CpuFeatures,
+        max_output: u64,
+    ) -> Self {
+        let mut enc = Encoder::with_features(features);
+        let mut labels = HashMap::new();
+        for (&name, def) in &layout.defs {
+            if let Def::Label { .. } = def {
+                labels.insert(name, enc.create_label());
+            }
+        }
+        Self {
+            layout,
+            previous,
+            values: HashMap::new(),
+            enc,
+            labels,
+            sections: Vec::new(),
+            relocations: Vec::new(),
+            section_start: 0,
+            code: false,
+            addressing: Addressing::default(),
+            max_output,
+            at: (0, 0),
+        }
+    }
+
+    fn run(&mut self) -> RaskResult<()> {
+        for (index, (name, stmts)) in self.layout.sections.iter().enumerate() {
+            if index > 0 {
+                self.enc.align_with(SECTION_ALIGN, 0)?;
+            }
+            self.section_start = self.enc.position();
+            self.code = name.starts_with(".text");
+            for (mode, addressing, stmt) in stmts {
+                self.at = (stmt.line, stmt.column);
+                self.enc.set_mode(*mode);
+                self.addressing = *addressing;
+                self.stmt(&stmt.kind).map_err(|err| match err {
+                    err @ RaskError::Diagnostic { .. } => err,
+                    err => error(stmt.line, stmt.column, err.to_string()),
+                })?;
+            }
+            self.sections.push(Section {
+                name: name.to_string(),
+                offset: self.section_start,
+                size: self.enc.position() - self.section_start,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> RaskResult<Object> {
+        let symbols = self
+            .layout
+            .order
+            .iter()
+            .map(|&name| Symbol {
+                name: name.to_string(),
+                value: self.values.get(name).copied().unwrap_or(0),
+                kind: match self.layout.defs[name] {
+                    Def::Label { section } => SymbolKind::Label { section },
+                    Def::Equ => SymbolKind::Constant,
+                    Def::Extern => SymbolKind::Extern,
+                },
+                global: self.layout.globals.contains(name),
+            })
+            .collect();
+        Ok(Object {
+            bytes: self.enc.finish()?,
+            origin: self.layout.origin,
+            sections: self.sections,
+            symbols,
+            relocations: self.relocations,
+        })
+    }
+
+    /// Returns the address of the next byte.
+    fn here(&self) -> i64 {
+        self.layout.origin + self.enc.position() as i64
+    }
+
+    fn stmt(&mut self, kind: &'a StmtKind) -> RaskResult<()> {
+        match kind {
+            StmtKind::Label(name) => {
+                self.enc.bind_label(self.labels[name.as_str()])?;
+                self.values.insert(name.clone(), self.here());
+            }
+            StmtKind::Equ(name, expr) => {
+                let value = self.number(expr)?;
+                self.values.insert(name.clone(), value);
+            }
+            StmtKind::Align(expr) => {
+                let align = self.number(expr)?;
+                if align < 1 {
+                    return Err(self.error("alignment must be a power of two"));
+                }
+                let end = (self.enc.position() as u64).checked_next_multiple_of(align as u64);
+                self.check_output(end.unwrap_or(u64::MAX))?;
+                if self.code {
+                    self.enc.align(align as usize)?;
+                } else {
+                    self.enc.align_with(align as usize, 0)?;
+                }
+            }
+            StmtKind::Data(width, items) => {
+                for item in items {
+                    self.datum(*width, item)?;
+                }
+            }
+            StmtKind::Times(count, kind) => {
+                let count = self.number(count)?;
+                if count < 0 {
+                    return Err(self.error(format!("repeat count {count} is negative")));
+                }
+                for done in 1..=count {
+                    let start = self.enc.position();
+                    self.stmt(kind)?;
+                    // An iteration that emits nothing leaves `$` where it
+                    // was, so the rest emit nothing either.
+                    let size = (self.enc.position() - start) as u64;
+                    if size == 0 {
+                        break;
+                    }
+                    let rest = (count - done) as u64;
+                    self.check_output(
+                        rest.saturating_mul(size)
+                            .saturating_add(self.enc.position() as u64),
+                    )?;
+                }
+            }
+            StmtKind::Insn(insn) => self.insn(insn)?,
+            StmtKind::Section(_)
+            | StmtKind::Global(_)
+            | StmtKind::Extern(_)
+            | StmtKind::Bits(_)
+            | StmtKind::Default(_)
+            | StmtKind::Org(_) => {}
+        }
+        Ok(())
+    }
+
+    fn datum(&mut self, width: usize, item: &Datum) -> RaskResult<()> {
+        let value = match item {
+            Datum::Bytes(bytes) => {
+                self.enc.emit_all(bytes)?;
+                let pad = bytes.len().next_multiple_of(width) - bytes.len();
+                return self.enc.emit_all(&[0; 8][..pad]);
+            }
+            Datum::Expr(expr) => self.eval(expr)?,
+        };
+        if let Some(symbol) = value.external {
+            let kind = match width {
+                4 => RelocationKind::Abs32,
+                8 => RelocationKind::Abs64,
+                _ => return Err(self.external(symbol)),
+            };
+            self.relocations.push(Relocation {
+                offset: self.enc.position(),
+                symbol: symbol.to_string(),
+                kind,
+                addend: value.value,
+            });
+            return self.enc.emit_all(&[0; 8][..width]);
+        }
+
+        let bits = width as u32 * 8;
+        let value = value.value;
+        if bits < 64 && !(-(1 << (bits - 1))..1 << bits).contains(&value) {
+            return Err(self.error(format!("{value} does not fit in {bits} bits")));
+        }
+        self.enc.emit_all(&value.to_le_bytes()[..width])
+    }
+
+    fn insn(&mut self, insn: &'a Insn) -> RaskResult<()> {
+        let mnemonic = insn.mnemonic;
+        let branch =
+            matches!(mnemonic, Mnemonic::Jmp | Mnemonic::Call) || mnemonic.condition().is_some();
+        if branch
+            && let [op] = insn.operands.as_slice()
+            && let OpKind::Imm(target) = &op.kind
+        {
+            return self.branch(mnemonic, target, op.column);
+        }
+
+        let mut operands = Vec::new();
+        // The operand that addresses `target` relative to RIP, if any.
+        let mut relative = None;
+        for op in &insn.operands {
+            operands.push(match &op.kind {
+                OpKind::Reg(reg) => *reg,
+                OpKind::Imm(expr) => Operand::Imm(self.number(expr)?),
+                OpKind::Mem(mem) => {
+                    let disp = match &mem.disp {
+                        Some(expr) => self.number(expr)?,
+                        None => 0,
+                    };
+                    let mut operand = MemOperand {
+                        base: mem.base,
+                        index: mem.index,
+                        disp: 0,
+                        size: mem.size,
+                        segment: mem.segment,
+                    };
+                    if self.rip_relative(mem) {
+                        operand.base = Some(AddrReg::Rip);
+                        relative = Some((operands.len(), disp, op.column));
+                    } else if !(i64::from(i32::MIN)..=i64::from(u32::MAX)).contains(&disp) {
+                        return Err(error(
+                            self.at.0,
+                            op.column,
+                            format!("displacement {disp} does not fit in 32 bits"),
+                        ));
+                    } else {
+                        operand.disp = disp as i32;
+                    }
+                    Operand::Mem(operand)
+                }
+            });
+        }
+
+        let instruction = |operands: &[Operand]| {
+            let instruction = Instruction::from_operands(mnemonic, operands)?;
+            RaskResult::Ok(match insn.lock {
+                true => instruction.with_prefix(Prefix::Lock),
+                false => instruction,
+            })
+        };
+        if let Some((index, target, column)) = relative {
+            let end = self.here() + self.enc.encoded_len(&instruction(&operands)?)? as i64;
+            let disp = target.checked_sub(end).and_then(|d| i32::try_from(d).ok());
+            let Some(disp) = disp else {
+                return Err(error(
+                    self.at.0,
+                    column,
+                    format!("{target:#x} is out of reach of a RIP-relative displacement"),
+                ));
+            };
+            if let Operand::Mem(mem) = &mut operands[index] {
+                mem.disp = disp;
+            }
+        }
+        self.enc.encode(&instruction(&operands)?)
+    }
+
+    /// Returns true if `mem` addresses its displacement relative to RIP:
+    /// with a `rip` base when the displacement is an address, and, in
+    /// 64-bit mode, with neither base nor index as `rel`, `abs` or the
+    /// `default` say. An FS or GS override keeps the address absolute
+    /// unless `rel` asks otherwise, as those segments have their own base.
+    fn rip_relative(&self, mem: &Mem) -> bool {
+        let address = || mem.disp.as_ref().is_some_and(|disp| self.is_address(disp));
+        match (mem.base, mem.index) {
+            (Some(AddrReg::Rip), _) => address(),
+            (None, None) if self.enc.mode() == Mode::Long64 => match mem.addressing {
+                Some(Addressing::Rel) => true,
+                Some(Addressing::Abs) => false,
+                _ if matches!(mem.segment, Some(SegReg::FS | SegReg::GS)) => false,
+                _ => match self.addressing {
+                    Addressing::Auto => address(),
+                    Addressing::Rel => true,
+                    Addressing::Abs => false,
+                },
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns true if `expr` is an address in the program: a label, `$` or
+    /// `$$`, plus or minus a number. The difference of two addresses is a
+    /// number.
+    fn is_address(&self, expr: &Expr) -> bool {
+        fn terms(pass: &Pass<'_>, expr: &Expr) -> i64 {
+            match expr {
+                Expr::Symbol { name, .. } => match pass.layout.defs.get(name.as_str()) {
+                    Some(Def::Label { .. }) => 1,
+                    _ => 0,
+                },
+                Expr::Here | Expr::SectionStart => 1,
+                Expr::Neg(e) => -terms(pass, e),
+                Expr::Binary(BinOp::Add, a, b) => terms(pass, a) + terms(pass, b),
+                Expr::Binary(BinOp::Sub, a, b) => terms(pass, a) - terms(pass, b),
+                Expr::Int(_) | Expr::Not(_) | Expr::Binary(..) => 0,
+            }
+        }
+        terms(self, expr) == 1
+    }
+
+    /// Encodes a branch to a label, to `$`, or to an external symbol.
+    fn branch(&mut self, mnemonic: Mnemonic, target: &'a Expr, column: usize) -> RaskResult<()> {
+        let label = match target {
+            Expr::Symbol { name, .. } => match self.layout.defs[name.as_str()] {
+                Def::Label { .. } => self.labels[name.as_str()],
+                Def::Extern => return self.external_branch(mnemonic, name),
+                Def::Equ => {
+                    return Err(error(
+                        self.at.0,
+                        column,
+                        format!("branch target `{name}` is not a label"),
+                    ));
+                }
+            },
+            Expr::Here => {
+                let label = self.enc.create_label();
+                self.enc.bind_label(label)?;
+                label
+            }
+            _ => {
+                return Err(error(
+                    self.at.0,
+                    column,
+                    "a branch target must be a label, `$` or an external symbol",
+                ));
+            }
+        };
+        self.enc
+            .encode(&Instruction::with1(mnemonic, Operand::Label(label)))
+    }
+
+    /// Encodes a branch with a zero `rel32` and a relocation for it.
+    fn external_branch(&mut self, mnemonic: Mnemonic, symbol: &str) -> RaskResult<()> {
+        if self.enc.mode() == Mode::Real16 {
+            return Err(self.error("branches to external symbols need 32- or 64-bit mode"));
+        }
+        let mut bytes = match mnemonic.condition() {
+            Some(cond) => vec![0x0F, 0x80 | cond.code()],
+            None if mnemonic == Mnemonic::Call => vec![0xE8],
+            None => vec![0xE9],
+        };
+        self.relocations.push(Relocation {
+            offset: self.enc.position() + bytes.len(),
+            symbol: symbol.to_string(),
+            kind: RelocationKind::Rel32,
+            addend: -4,
+        });
+        bytes.extend_from_slice(&[0; 4]);
+        self.enc.emit_all(&bytes)
+    }
+
+    fn eval<'e>(&self, expr: &'e Expr) -> RaskResult<Value<'e>> {
+        let number = |value| Value {
+            value,
+            external: None,
+        };
+        Ok(match expr {
+            Expr::Int(value) => number(*value),
+            Expr::Symbol { name, .. } => match self.layout.defs.get(name.as_str()) {
+                Some(Def::Extern) => Value {
+                    value: 0,
+                    external: Some(name),
+                },
+                _ => number(
+                    self.values
+                        .get(name)
+                        .or_else(|| self.previous.get(name))
+                        .copied()
+                        .unwrap_or(0),
+                ),
+            },
+            Expr::Here => number(self.here()),
+            Expr::SectionStart => number(self.layout.origin + self.section_start as i64),
+            Expr::Neg(e) => number(self.number(e)?.wrapping_neg()),
+            Expr::Not(e) => number(!self.number(e)?),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (self.eval(a)?, self.eval(b)?);
+                let external = match (op, a.external, b.external) {
+                    (_, None, None) => None,
+                    (BinOp::Add, Some(symbol), None) | (BinOp::Add, None, Some(symbol)) => {
+                        Some(symbol)
+                    }
+                    (BinOp::Sub, Some(symbol), None) => Some(symbol),
+                    (_, Some(symbol), _) | (_, _, Some(symbol)) => {
+                        return Err(self.external(symbol));
+                    }
+                };
+                let value = binary(*op, a.value, b.value).map_err(|msg| self.error(msg))?;
+                Value { value, external }
+            }
+        })
+    }
+
+    /// Evaluates an expression that may not involve an external symbol.
+    fn number(&self, expr: &Expr) -> RaskResult<i64> {
+        let value = self.eval(expr)?;
+        match value.external {
+            Some(symbol) => Err(self.external(symbol)),
+            None => Ok(value.value),
+        }
+    }
+
+    /// Fails if the output would grow to `size` bytes.
+    fn check_output(&self, size: u64) -> RaskResult<()> {
+        if size > self.max_output {
+            return Err(self.error(format!("the output would exceed {} bytes", self.max_output)));
+        }
+        Ok(())
+    }
+
+    fn external(&self, symbol: &str) -> RaskError {
+        self.error(format!(
+            "external symbol `{symbol}` can only be a branch target or a `dd`/`dq` value"
+        ))
+    }
+
+    fn error(&self, message: impl Into<String>) -> RaskError {
+        error(self.at.0, self.at.1, message)
+    }
+}
@@ -0,0 +1,272 @@
+//! The parsed form of a source file, shared by the syntax front-ends.
+
+use crate::lexer::{Cursor, Tok, error};
+use rask_common::RaskResult;
+use rask_x86_64::{
+    instruction::Mnemonic,
+    mode::Mode,
+    operand::{AddrReg, MemSize, Operand, Scale},
+    registers::SegReg,
+};
+
+pub(crate) struct Stmt {
+    pub line: usize,
+    pub column: usize,
+    pub kind: StmtKind,
+}
+
+pub(crate) enum StmtKind {
+    /// `name:`
+    Label(String),
+    /// `name equ expr`
+    Equ(String, Expr),
+    Section(String),
+    Global(Vec<(String, usize)>),
+    Extern(Vec<(String, usize)>),
+    Bits(Mode),
+    /// `default rel` or `default abs`
+    Default(Addressing),
+    Org(Expr),
+    Align(Expr),
+    /// `db`, `dw`, `dd` or `dq`, with the item width in bytes.
+    Data(usize, Vec<Datum>),
+    Times(Expr, Box<StmtKind>),
+    Insn(Insn),
+}
+
+pub(crate) enum Datum {
+    Expr(Expr),
+    /// A string, padded to a whole number of items.
+    Bytes(Vec<u8>),
+}
+
+pub(crate) struct Insn {
+    pub lock: bool,
+    pub mnemonic: Mnemonic,
+    pub operands: Vec<Op>,
+}
+
+pub(crate) struct Op {
+    pub column: usize,
+    pub kind: OpKind,
+}
+
+pub(crate) enum OpKind {
+    Reg(Operand),
+    /// An immediate, or the target of a branch.
+    Imm(Expr),
+    Mem(Mem),
+}
+
+pub(crate) struct Mem {
+    pub size: Option<MemSize>,
+    pub segment: Option<SegReg>,
+    pub base: Option<AddrReg>,
+    pub index: Option<(AddrReg, Scale)>,
+    pub disp: Option<Expr>,
+    /// `rel` or `abs` written in the brackets, which overrides the
+    /// `default`.
+    pub addressing: Option<Addressing>,
+}
+
+/// How 64-bit code addresses a memory operand that has neither a base nor
+/// an index register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Addressing {
+    /// RIP-relative if the displacement is the address of a label or `$`,
+    /// and absolute if it is a number.
+    #[default]
+    Auto,
+    /// Always RIP-relative: `default rel` or `[rel x]`.
+    Rel,
+    /// Always absolute: `default abs` or `[abs x]`.
+    Abs,
+}
+
+impl Mem {
+    /// Fails unless the base and index registers have the same width; the
+    /// error is at `column`.
+    pub fn check_widths(&self, line: usize, column: usize) -> RaskResult<()> {
+        if let (Some(base), Some((index, _))) = (self.base, self.index)
+            && base.bits() != index.bits()
+        {
+            return Err(error(
+                line,
+                column,
+                "base and index registers must have the same width",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    Int(i64),
+    Symbol {
+        name: String,
+        column: usize,
+    },
+    /// The address of the current statement: `$` or `.`.
+    Here,
+    /// The address of the start of the current section: `$$`.
+    SectionStart,
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BinOp {
+    Or,
+    Xor,
+    And,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    /// Returns the operator at the next token with its precedence; higher
+    /// binds tighter.
+    fn peek(cursor: &Cursor<'_>) -> Option<(BinOp, u8)> {
+        Some(match cursor.peek()? {
+            Tok::Punct('|') => (BinOp::Or, 1),
+            Tok::Punct('^') => (BinOp::Xor, 2),
+            Tok::Punct('&') => (BinOp::And, 3),
+            Tok::Shl => (BinOp::Shl, 4),
+            Tok::Shr => (BinOp::Shr, 4),
+            Tok::Punct('+') => (BinOp::Add, 5),
+            Tok::Punct('-') => (BinOp::Sub, 5),
+            Tok::Punct('*') => (BinOp::Mul, 6),
+            Tok::Punct('/') => (BinOp::Div, 6),
+            Tok::Punct('%') => (BinOp::Rem, 6),
+            _ => return None,
+        })
+    }
+}
+
+impl Expr {
+    /// Calls `f` on every symbol the expression refers to.
+    pub fn symbols(&self, f: &mut impl FnMut(&str, usize)) {
+        match self {
+            Expr::Symbol { name, column } => f(name, *column),
+            Expr::Neg(e) | Expr::Not(e) => e.symbols(f),
+            Expr::Binary(_, a, b) => {
+                a.symbols(f);
+                b.symbols(f);
+            }
+            Expr::Int(_) | Expr::Here | Expr::SectionStart => {}
+        }
+    }
+}
+
+/// How the front-ends differ in expressions.
+pub(crate) trait ExprSyntax {
+    /// Parses an atom starting with `tok` at `column` that is specific to
+    /// the syntax, such as `$`; `tok` has already been consumed. Returns
+    /// `None` if `tok` does not start one.
+    fn atom(&self, tok: &Tok, column: usize, cursor: &mut Cursor<'_>) -> Option<Expr>;
+
+    /// Returns the full name of the symbol written as `name`.
+    fn symbol(&self, name: &str) -> String;
+}
+
+/// Parses an expression, stopping at the first token that cannot continue
+/// it.
+pub(crate) fn parse_expr(cursor: &mut Cursor<'_>, syntax: &impl ExprSyntax) -> RaskResult<Expr> {
+    parse_binary(cursor, syntax, 0)
+}
+
+/// Parses an expression of multiplications, divisions and remainders only,
+/// which is a single term of a sum such as a memory address.
+pub(crate) fn parse_product(cursor: &mut Cursor<'_>, syntax: &impl ExprSyntax) -> RaskResult<Expr> {
+    parse_binary(cursor, syntax, 5)
+}
+
+fn parse_binary(cursor: &mut Cursor<'_>, syntax: &impl ExprSyntax, min: u8) -> RaskResult<Expr> {
+    let mut lhs = parse_unary(cursor, syntax)?;
+    while let Some((op, prec)) = BinOp::peek(cursor)
+        && prec > min
+    {
+        cursor.next();
+        let rhs = parse_binary(cursor, syntax, prec)?;
+        lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_unary(cursor: &mut Cursor<'_>, syntax: &impl ExprSyntax) -> RaskResult<Expr> {
+    let column = cursor.column();
+    let Some(tok) = cursor.next() else {
+        return Err(cursor.error("expected an expression"));
+    };
+    Ok(match tok {
+        Tok::Int(value) => Expr::Int(*value),
+        Tok::Str(bytes) if bytes.len() <= 8 => {
+            let mut value = [0; 8];
+            value[..bytes.len()].copy_from_slice(bytes);
+            Expr::Int(i64::from_le_bytes(value))
+        }
+        Tok::Punct('-') => Expr::Neg(Box::new(parse_unary(cursor, syntax)?)),
+        Tok::Punct('+') => parse_unary(cursor, syntax)?,
+        Tok::Punct('~') => Expr::Not(Box::new(parse_unary(cursor, syntax)?)),
+        Tok::Punct('(') => {
+            let expr = parse_expr(cursor, syntax)?;
+            cursor.expect(')')?;
+            expr
+        }
+        tok => match syntax.atom(tok, column, cursor) {
+            Some(expr) => expr,
+            None => match tok {
+                Tok::Ident(name) => Expr::Symbol {
+                    name: syntax.symbol(name),
+                    column,
+                },
+                _ => {
+                    return Err(error(cursor.line, column, "expected an expression"));
+                }
+            },
+        },
+    })
+}
+
+/// Returns the memory size named by `word`, such as `qword`.
+pub(crate) fn mem_size(word: &str) -> Option<MemSize> {
+    match word.to_ascii_lowercase().as_str() {
+        "byte" => Some(MemSize::Byte),
+        "word" => Some(MemSize::Word),
+        "dword" => Some(MemSize::Dword),
+        "qword" => Some(MemSize::Qword),
+        _ => None,
+    }
+}
+
+/// Returns the register `name` as a memory base or index, or `None` if it
+/// is not `rip` or a general-purpose register of 16 bits or more.
+pub(crate) fn addr_reg(name: &str) -> Option<AddrReg> {
+    if name.eq_ignore_ascii_case("rip") {
+        return Some(AddrReg::Rip);
+    }
+    match Operand::register(name)? {
+        Operand::Reg(r) => Some(r.into()),
+        Operand::Reg32(r) => Some(r.into()),
+        Operand::Reg16(r) => Some(r.into()),
+        _ => None,
+    }
+}
+
+/// Returns the scale factor `value`, which must be 1, 2, 4 or 8.
+pub(crate) fn scale(value: i64) -> Option<Scale> {
+    match value {
+        1 => Some(Scale::S1),
+        2 => Some(Scale::S2),
+        4 => Some(Scale::S4),
+        8 => Some(Scale::S8),
+        _ => None,
+    }
+}
@@ -0,0 +1,512 @@
+//! The AT&T (GNU as) syntax front-end.
+//!
+//! Operands come source first, registers are written `%rax`, immediates
+//! `$1` and memory `segment:disp(base, index, scale)`, and a `b`, `w`, `l`
+//! or `q` suffix on the mnemonic gives the operand size. The statements are
+//! the same as the Intel front-end produces, so both syntaxes encode alike.
+//!
+//! A label may be a number, as in `1:`, and be defined any number of
+//! times; `1f` refers to the next definition and `1b` to the last one.
+//! Each definition becomes its own symbol, `1$1`, `1$2` and so on.
+
+use crate::{
+    ast::{
+        self, BinOp, Datum, Expr, ExprSyntax, Insn, Mem, Op, OpKind, Stmt, StmtKind, parse_expr,
+    },
+    lexer::{Cursor, Dialect, Tok, error, tokenize},
+};
+use rask_common::RaskResult;
+use rask_x86_64::{
+    instruction::Mnemonic,
+    mode::Mode,
+    operand::{AddrReg, MemSize, Operand, Scale},
+    registers::SegReg,
+};
+use std::{cell::RefCell, collections::HashMap};
+
+/// Parses a whole source file.
+pub(crate) fn parse(source: &str) -> RaskResult<Vec<Stmt>> {
+    let mut att = Att::default();
+    let mut stmts = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let tokens = tokenize(text, line, Dialect::Att)?;
+        let mut cursor = Cursor::new(&tokens, line, text.chars().count() + 1);
+        loop {
+            att.parse_stmt(&mut cursor, &mut stmts)?;
+            if !cursor.eat(';') {
+                break;
+            }
+        }
+        cursor.expect_end()?;
+    }
+    att.check_local_labels()?;
+    Ok(stmts)
+}
+
+#[derive(Default)]
+struct Att {
+    /// How many times each numeric label has been defined so far.
+    locals: HashMap<i64, usize>,
+    /// Every `1f` and `1b` read so far, checked against the definitions
+    /// once the whole source has been read.
+    references: RefCell<Vec<LocalRef>>,
+}
+
+/// A reference to the `instance`th definition of a numeric label.
+struct LocalRef {
+    number: i64,
+    forward: bool,
+    instance: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Returns the symbol for the `instance`th definition of the numeric label
+/// `number`.
+fn local_name(number: i64, instance: usize) -> String {
+    format!("{number}${instance}")
+}
+
+impl ExprSyntax for Att {
+    fn atom(&self, tok: &Tok, column: usize, cursor: &mut Cursor<'_>) -> Option<Expr> {
+        match tok {
+            Tok::Ident(name) if name == "." => Some(Expr::Here),
+            Tok::LocalLabel { number, forward } => {
+                let defined = self.locals.get(number).copied().unwrap_or(0);
+                let instance = if *forward { defined + 1 } else { defined };
+                self.references.borrow_mut().push(LocalRef {
+                    number: *number,
+                    forward: *forward,
+                    instance,
+                    line: cursor.line,
+                    column,
+                });
+                Some(Expr::Symbol {
+                    name: local_name(*number, instance),
+                    column,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn symbol(&self, name: &str) -> String {
+        name.to_string()
+    }
+}
+
+/// Returns true at the end of the line or of a `;`-separated statement.
+fn at_end(cursor: &Cursor<'_>) -> bool {
+    matches!(cursor.peek(), None | Some(Tok::Punct(';')))
+}
+
+fn expect_end(cursor: &Cursor<'_>) -> RaskResult<()> {
+    if at_end(cursor) {
+        Ok(())
+    } else {
+        cursor.expect_end()
+    }
+}
+
+/// Skips the rest of the statement.
+fn skip_rest(cursor: &mut Cursor<'_>) {
+    while !at_end(cursor) {
+        cursor.next();
+    }
+}
+
+/// Returns the instruction written as `word` and the operand size its
+/// suffix gives, if it has one, with its width in bits.
+fn mnemonic(word: &str) -> Option<(Mnemonic, Option<(MemSize, u32)>)> {
+    if let Ok(mnemonic) = word.parse() {
+        return Some((mnemonic, None));
+    }
+    let (stem, suffix) = word.split_at(word.len().checked_sub(1)?);
+    let size = match suffix.to_ascii_lowercase().as_str() {
+        "b" => (MemSize::Byte, 8),
+        "w" => (MemSize::Word, 16),
+        "l" => (MemSize::Dword, 32),
+        "q" => (MemSize::Qword, 64),
+        _ => return None,
+    };
+    Some((stem.parse().ok()?, Some(size)))
+}
+
+/// Returns the width of a general-purpose register operand in bits.
+fn gpr_bits(operand: &Operand) -> Option<u32> {
+    match operand {
+        Operand::Reg(_) => Some(64),
+        Operand::Reg32(_) => Some(32),
+        Operand::Reg16(_) => Some(16),
+        Operand::Reg8(_) => Some(8),
+        _ => None,
+    }
+}
+
+impl Att {
+    /// Parses one statement and the labels before it, stopping before a
+    /// `;` that separates it from the next.
+    fn parse_stmt(&mut self, cursor: &mut Cursor<'_>, stmts: &mut Vec<Stmt>) -> RaskResult<()> {
+        let line = cursor.line;
+        while cursor.peek_at(1) == Some(&Tok::Punct(':')) {
+            let name = match cursor.peek() {
+                Some(Tok::Ident(name)) => name.clone(),
+                Some(Tok::Int(number)) => {
+                    let instance = self.locals.entry(*number).or_default();
+                    *instance += 1;
+                    local_name(*number, *instance)
+                }
+                _ => break,
+            };
+            let column = cursor.column();
+            cursor.next();
+            cursor.next();
+            stmts.push(Stmt {
+                line,
+                column,
+                kind: StmtKind::Label(name),
+            });
+        }
+        if at_end(cursor) {
+            return Ok(());
+        }
+
+        let column = cursor.column();
+        let kind = match (cursor.peek(), cursor.peek_at(1)) {
+            (Some(Tok::Ident(name)), Some(Tok::Punct('='))) => {
+                cursor.next();
+                cursor.next();
+                StmtKind::Equ(name.clone(), parse_expr(cursor, self)?)
+            }
+            (Some(Tok::Ident(word)), _) if word.starts_with('.') && word.len() > 1 => {
+                cursor.next();
+                match self.parse_directive(cursor, word, column)? {
+                    Some(kind) => kind,
+                    None => return Ok(()),
+                }
+            }
+            _ => {
+                let word = cursor.expect_ident("an instruction or directive")?;
+                if word.eq_ignore_ascii_case("lock") {
+                    cursor.eat(';');
+                    let column = cursor.column();
+                    let word = cursor.expect_ident("an instruction")?;
+                    let mut insn = self.parse_insn(cursor, word, column)?;
+                    insn.lock = true;
+                    StmtKind::Insn(insn)
+                } else {
+                    StmtKind::Insn(self.parse_insn(cursor, word, column)?)
+                }
+            }
+        };
+        expect_end(cursor)?;
+        stmts.push(Stmt { line, column, kind });
+        Ok(())
+    }
+
+    /// Fails if a `1b` comes before any `1:` or a `1f` after the last one.
+    fn check_local_labels(&self) -> RaskResult<()> {
+        for r in self.references.borrow().iter() {
+            let defined = self.locals.get(&r.number).copied().unwrap_or(0);
+            if (1..=defined).contains(&r.instance) {
+                continue;
+            }
+            let (suffix, place) = if r.forward {
+                ('f', "after")
+            } else {
+                ('b', "before")
+            };
+            return Err(error(
+                r.line,
+                r.column,
+                format!("no `{}:` {place} `{}{suffix}`", r.number, r.number),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Parses the directive `word`, returning `None` for one that is
+    /// accepted but has no effect.
+    fn parse_directive(
+        &self,
+        cursor: &mut Cursor<'_>,
+        word: &str,
+        column: usize,
+    ) -> RaskResult<Option<StmtKind>> {
+        let word = word.to_ascii_lowercase();
+        Ok(Some(match word.as_str() {
+            ".text" | ".data" | ".bss" => StmtKind::Section(word),
+            ".section" => {
+                let name = match cursor.next() {
+                    Some(Tok::Ident(name)) => name.clone(),
+                    Some(Tok::Str(bytes)) => String::from_utf8_lossy(bytes).into_owned(),
+                    _ => return Err(error(cursor.line, column, "expected a section name")),
+                };
+                // Flags and section types mean nothing in a flat object.
+                if cursor.eat(',') {
+                    skip_rest(cursor);
+                }
+                StmtKind::Section(name)
+            }
+            ".globl" | ".global" => StmtKind::Global(self.parse_names(cursor)?),
+            ".extern" => StmtKind::Extern(self.parse_names(cursor)?),
+            ".code16" => StmtKind::Bits(Mode::Real16),
+            ".code32" => StmtKind::Bits(Mode::Protected32),
+            ".code64" => StmtKind::Bits(Mode::Long64),
+            ".set" | ".equ" => {
+                let name = cursor.expect_ident("a symbol name")?;
+                cursor.expect(',')?;
+                StmtKind::Equ(name.to_string(), parse_expr(cursor, self)?)
+            }
+            ".byte" => StmtKind::Data(1, self.parse_data(cursor)?),
+            ".word" | ".short" | ".value" => StmtKind::Data(2, self.parse_data(cursor)?),
+            ".long" | ".int" => StmtKind::Data(4, self.parse_data(cursor)?),
+            ".quad" => StmtKind::Data(8, self.parse_data(cursor)?),
+            ".ascii" => StmtKind::Data(1, self.parse_strings(cursor, false)?),
+            ".asciz" | ".string" => StmtKind::Data(1, self.parse_strings(cursor, true)?),
+            ".p2align" => {
+                let arg = cursor.column();
+                let shift = self.parse_align(cursor)?;
+                // `1 << 63` is negative, and larger shifts do not fit.
+                let value = match &shift {
+                    Expr::Int(value) => Some(*value),
+                    Expr::Neg(e) => match **e {
+                        Expr::Int(value) => Some(value.wrapping_neg()),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some(value) = value
+                    && !(0..63).contains(&value)
+                {
+                    return Err(error(
+                        cursor.line,
+                        arg,
+                        format!("`.p2align {value}` is outside 0..63"),
+                    ));
+                }
+                StmtKind::Align(Expr::Binary(
+                    BinOp::Shl,
+                    Box::new(Expr::Int(1)),
+                    Box::new(shift),
+                ))
+            }
+            ".balign" | ".align" => StmtKind::Align(self.parse_align(cursor)?),
+            ".zero" | ".skip" | ".space" => {
+                let count = parse_expr(cursor, self)?;
+                let fill = if cursor.eat(',') {
+                    parse_expr(cursor, self)?
+                } else {
+                    Expr::Int(0)
+                };
+                StmtKind::Times(count, Box::new(StmtKind::Data(1, vec![Datum::Expr(fill)])))
+            }
+            // Symbol types and sizes, and file and tool names, have no place
+            // in an `Object`.
+            ".type" | ".size" | ".file" | ".ident" => {
+                skip_rest(cursor);
+                return Ok(None);
+            }
+            _ => {
+                return Err(error(
+                    cursor.line,
+                    column,
+                    format!("unknown directive `{word}`"),
+                ));
+            }
+        }))
+    }
+
+    fn parse_names(&self, cursor: &mut Cursor<'_>) -> RaskResult<Vec<(String, usize)>> {
+        let mut names = Vec::new();
+        loop {
+            let column = cursor.column();
+            let name = cursor.expect_ident("a symbol name")?;
+            names.push((name.to_string(), column));
+            if !cursor.eat(',') {
+                return Ok(names);
+            }
+        }
+    }
+
+    fn parse_data(&self, cursor: &mut Cursor<'_>) -> RaskResult<Vec<Datum>> {
+        let mut items = Vec::new();
+        loop {
+            items.push(Datum::Expr(parse_expr(cursor, self)?));
+            if !cursor.eat(',') {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Parses the strings of `.ascii`, or with `terminate` those of
+    /// `.asciz`, each of which ends with a zero byte.
+    fn parse_strings(&self, cursor: &mut Cursor<'_>, terminate: bool) -> RaskResult<Vec<Datum>> {
+        let mut items = Vec::new();
+        loop {
+            let Some(Tok::Str(bytes)) = cursor.peek() else {
+                return Err(cursor.error("expected a string"));
+            };
+            cursor.next();
+            let mut bytes = bytes.clone();
+            if terminate {
+                bytes.push(0);
+            }
+            items.push(Datum::Bytes(bytes));
+            if !cursor.eat(',') {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Parses the alignment of `.p2align`, `.balign` or `.align`, which
+    /// may not give a fill value or a limit.
+    fn parse_align(&self, cursor: &mut Cursor<'_>) -> RaskResult<Expr> {
+        let align = parse_expr(cursor, self)?;
+        if cursor.peek() == Some(&Tok::Punct(',')) {
+            return Err(cursor.error("alignment fill values and limits are not supported"));
+        }
+        Ok(align)
+    }
+
+    fn parse_insn(&self, cursor: &mut Cursor<'_>, word: &str, column: usize) -> RaskResult<Insn> {
+        let (mnemonic, size) = mnemonic(word)
+            .ok_or_else(|| error(cursor.line, column, format!("unknown instruction `{word}`")))?;
+        let branch =
+            matches!(mnemonic, Mnemonic::Jmp | Mnemonic::Call) || mnemonic.condition().is_some();
+        let mut operands = Vec::new();
+        if !at_end(cursor) {
+            loop {
+                operands.push(self.parse_operand(cursor, branch)?);
+                if !cursor.eat(',') {
+                    break;
+                }
+            }
+        }
+        operands.reverse();
+
+        // The suffix sizes memory operands; without one, it must match a
+        // register, as `crc32b %cl, %eax` shows it need not match them all.
+        if let Some((size, width)) = size {
+            let bits: Vec<u32> = operands
+                .iter()
+                .filter_map(|op| match &op.kind {
+                    OpKind::Reg(reg) => gpr_bits(reg),
+                    _ => None,
+                })
+                .collect();
+            let memory = operands.iter().any(|op| matches!(op.kind, OpKind::Mem(_)));
+            if !memory && !bits.is_empty() && !bits.contains(&width) {
+                return Err(error(
+                    cursor.line,
+                    column,
+                    format!("the suffix of `{word}` does not match its register operands"),
+                ));
+            }
+            for op in &mut operands {
+                if let OpKind::Mem(mem) = &mut op.kind {
+                    mem.size.get_or_insert(size);
+                }
+            }
+        }
+        Ok(Insn {
+            lock: false,
+            mnemonic,
+            operands,
+        })
+    }
+
+    /// Parses an operand. The operand of a branch is its target unless it
+    /// is marked indirect with `*`.
+    fn parse_operand(&self, cursor: &mut Cursor<'_>, branch: bool) -> RaskResult<Op> {
+        let column = cursor.column();
+        let indirect = cursor.eat('*');
+        if indirect && !branch {
+            return Err(error(
+                cursor.line,
+                column,
+                "only branch targets can be marked indirect with `*`",
+            ));
+        }
+        let kind = match cursor.peek() {
+            Some(Tok::Punct('$')) if !indirect => {
+                cursor.next();
+                OpKind::Imm(parse_expr(cursor, self)?)
+            }
+            Some(Tok::Punct('%')) => {
+                cursor.next();
+                let column = cursor.column();
+                let name = cursor.expect_ident("a register")?;
+                if cursor.eat(':') {
+                    let segment = name
+                        .parse()
+                        .map_err(|_| error(cursor.line, column, "unknown segment register"))?;
+                    OpKind::Mem(self.parse_mem(cursor, Some(segment))?)
+                } else {
+                    OpKind::Reg(Operand::register(name).ok_or_else(|| {
+                        error(cursor.line, column, format!("unknown register `%{name}`"))
+                    })?)
+                }
+            }
+            _ if branch && !indirect => OpKind::Imm(parse_expr(cursor, self)?),
+            _ => OpKind::Mem(self.parse_mem(cursor, None)?),
+        };
+        Ok(Op { column, kind })
+    }
+
+    /// Parses `disp(base, index, scale)`, where every part is optional but
+    /// an address has at least a displacement or a base or index register.
+    fn parse_mem(&self, cursor: &mut Cursor<'_>, segment: Option<SegReg>) -> RaskResult<Mem> {
+        let column = cursor.column();
+        let mut mem = Mem {
+            size: None,
+            segment,
+            base: None,
+            index: None,
+            disp: None,
+            addressing: None,
+        };
+        let registers_only = cursor.peek() == Some(&Tok::Punct('('))
+            && matches!(cursor.peek_at(1), Some(Tok::Punct('%' | ',')));
+        if !registers_only {
+            mem.disp = Some(parse_expr(cursor, self)?);
+        }
+        if !cursor.eat('(') {
+            return Ok(mem);
+        }
+
+        if cursor.peek() == Some(&Tok::Punct('%')) {
+            mem.base = Some(self.parse_addr_reg(cursor)?);
+        }
+        if cursor.eat(',') {
+            let index = self.parse_addr_reg(cursor)?;
+            let mut scale = Scale::S1;
+            if cursor.eat(',') {
+                let column = cursor.column();
+                scale = match cursor.next() {
+                    Some(Tok::Int(n)) => ast::scale(*n),
+                    _ => None,
+                }
+                .ok_or_else(|| error(cursor.line, column, "scale must be 1, 2, 4 or 8"))?;
+            }
+            mem.index = Some((index, scale));
+        }
+        cursor.expect(')')?;
+        mem.check_widths(cursor.line, column)?;
+        Ok(mem)
+    }
+
+    fn parse_addr_reg(&self, cursor: &mut Cursor<'_>) -> RaskResult<AddrReg> {
+        cursor.expect('%')?;
+        let column = cursor.column();
+        let name = cursor.expect_ident("a register")?;
+        ast::addr_reg(name).ok_or_else(|| {
+            error(
+                cursor.line,
+                column,
+                format!("`%{name}` cannot address memory"),
+            )
+        })
+    }
+}
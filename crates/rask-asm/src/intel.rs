@@ -0,0 +1,355 @@
+//! The Intel (NASM-style) syntax front-end.
+//!
+//! Labels starting with a single `.` are local to the last label without
+//! one, as in NASM: `.loop` after `main:` is the symbol `main.loop`.
+
+use crate::{
+    ast::{
+        self, Addressing, Datum, Expr, ExprSyntax, Insn, Mem, Op, OpKind, Stmt, StmtKind,
+        parse_expr, parse_product,
+    },
+    lexer::{Cursor, Dialect, Tok, error, tokenize},
+};
+use rask_common::RaskResult;
+use rask_x86_64::{
+    instruction::Mnemonic,
+    mode::Mode,
+    operand::{Operand, Scale},
+    registers::SegReg,
+};
+
+/// Parses a whole source file.
+pub(crate) fn parse(source: &str) -> RaskResult<Vec<Stmt>> {
+    let mut parser = Intel {
+        scope: String::new(),
+    };
+    let mut stmts = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let tokens = tokenize(text, line, Dialect::Intel)?;
+        let mut cursor = Cursor::new(&tokens, line, text.chars().count() + 1);
+        parser.parse_line(&mut cursor, &mut stmts)?;
+    }
+    Ok(stmts)
+}
+
+struct Intel {
+    /// The last label without a leading `.`.
+    scope: String,
+}
+
+impl ExprSyntax for Intel {
+    fn atom(&self, tok: &Tok, _column: usize, cursor: &mut Cursor<'_>) -> Option<Expr> {
+        if *tok != Tok::Punct('$') {
+            return None;
+        }
+        if cursor.peek() == Some(&Tok::Punct('$')) && cursor.adjacent() {
+            cursor.next();
+            return Some(Expr::SectionStart);
+        }
+        Some(Expr::Here)
+    }
+
+    fn symbol(&self, name: &str) -> String {
+        if name.starts_with('.') && !name.starts_with("..") {
+            format!("{}{name}", self.scope)
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+impl Intel {
+    fn parse_line(&mut self, cursor: &mut Cursor<'_>, stmts: &mut Vec<Stmt>) -> RaskResult<()> {
+        let line = cursor.line;
+        if let (Some(Tok::Ident(name)), Some(Tok::Punct(':'))) = (cursor.peek(), cursor.peek_at(1))
+        {
+            let column = cursor.column();
+            cursor.next();
+            cursor.next();
+            let symbol = self.symbol(name);
+            if cursor.eat_word("equ") {
+                let kind = StmtKind::Equ(symbol, parse_expr(cursor, self)?);
+                stmts.push(Stmt { line, column, kind });
+                return cursor.expect_end();
+            }
+            if !name.starts_with('.') {
+                self.scope.clone_from(&symbol);
+            }
+            stmts.push(Stmt {
+                line,
+                column,
+                kind: StmtKind::Label(symbol),
+            });
+        }
+        if cursor.is_empty() {
+            return Ok(());
+        }
+
+        let column = cursor.column();
+        let kind = match (cursor.peek(), cursor.peek_at(1)) {
+            (Some(Tok::Ident(name)), Some(Tok::Ident(equ))) if equ.eq_ignore_ascii_case("equ") => {
+                cursor.next();
+                cursor.next();
+                StmtKind::Equ(self.symbol(name), parse_expr(cursor, self)?)
+            }
+            _ => self.parse_stmt(cursor)?,
+        };
+        cursor.expect_end()?;
+        stmts.push(Stmt { line, column, kind });
+        Ok(())
+    }
+
+    /// Parses a directive or an instruction.
+    fn parse_stmt(&mut self, cursor: &mut Cursor<'_>) -> RaskResult<StmtKind> {
+        let column = cursor.column();
+        let word = cursor.expect_ident("an instruction or directive")?;
+        Ok(match word.to_ascii_lowercase().as_str() {
+            "section" | "segment" => {
+                StmtKind::Section(cursor.expect_ident("a section name")?.to_string())
+            }
+            "global" => StmtKind::Global(self.parse_names(cursor)?),
+            "extern" => StmtKind::Extern(self.parse_names(cursor)?),
+            "bits" => {
+                let mode = match cursor.peek() {
+                    Some(Tok::Int(16)) => Mode::Real16,
+                    Some(Tok::Int(32)) => Mode::Protected32,
+                    Some(Tok::Int(64)) => Mode::Long64,
+                    _ => return Err(cursor.error("expected 16, 32 or 64")),
+                };
+                cursor.next();
+                StmtKind::Bits(mode)
+            }
+            "default" => StmtKind::Default(
+                self.parse_addressing(cursor)
+                    .ok_or_else(|| cursor.error("expected `rel` or `abs`"))?,
+            ),
+            "org" => StmtKind::Org(parse_expr(cursor, self)?),
+            "align" => StmtKind::Align(parse_expr(cursor, self)?),
+            "db" => StmtKind::Data(1, self.parse_data(cursor)?),
+            "dw" => StmtKind::Data(2, self.parse_data(cursor)?),
+            "dd" => StmtKind::Data(4, self.parse_data(cursor)?),
+            "dq" => StmtKind::Data(8, self.parse_data(cursor)?),
+            "times" => {
+                let count = parse_expr(cursor, self)?;
+                let repeated = cursor.column();
+                match self.parse_stmt(cursor)? {
+                    kind @ (StmtKind::Data(..) | StmtKind::Insn(_)) => {
+                        StmtKind::Times(count, Box::new(kind))
+                    }
+                    _ => {
+                        return Err(error(
+                            cursor.line,
+                            repeated,
+                            "`times` repeats only data and instructions",
+                        ));
+                    }
+                }
+            }
+            "lock" => {
+                let column = cursor.column();
+                let word = cursor.expect_ident("an instruction")?;
+                let mut insn = self.parse_insn(cursor, word, column)?;
+                insn.lock = true;
+                StmtKind::Insn(insn)
+            }
+            _ => StmtKind::Insn(self.parse_insn(cursor, word, column)?),
+        })
+    }
+
+    fn parse_names(&self, cursor: &mut Cursor<'_>) -> RaskResult<Vec<(String, usize)>> {
+        let mut names = Vec::new();
+        loop {
+            let column = cursor.column();
+            let name = cursor.expect_ident("a symbol name")?;
+            names.push((self.symbol(name), column));
+            if !cursor.eat(',') {
+                return Ok(names);
+            }
+        }
+    }
+
+    fn parse_data(&self, cursor: &mut Cursor<'_>) -> RaskResult<Vec<Datum>> {
+        let mut items = Vec::new();
+        loop {
+            match (cursor.peek(), cursor.peek_at(1)) {
+                (Some(Tok::Str(bytes)), None | Some(Tok::Punct(','))) => {
+                    cursor.next();
+                    items.push(Datum::Bytes(bytes.clone()));
+                }
+                _ => items.push(Datum::Expr(parse_expr(cursor, self)?)),
+            }
+            if !cursor.eat(',') {
+                return Ok(items);
+            }
+        }
+    }
+
+    fn parse_insn(&self, cursor: &mut Cursor<'_>, word: &str, column: usize) -> RaskResult<Insn> {
+        let mnemonic: Mnemonic = word
+            .parse()
+            .map_err(|_| error(cursor.line, column, format!("unknown instruction `{word}`")))?;
+        let mut operands = Vec::new();
+        if !cursor.is_empty() {
+            loop {
+                operands.push(self.parse_operand(cursor)?);
+                if !cursor.eat(',') {
+                    break;
+                }
+            }
+        }
+        Ok(Insn {
+            lock: false,
+            mnemonic,
+            operands,
+        })
+    }
+
+    fn parse_operand(&self, cursor: &mut Cursor<'_>) -> RaskResult<Op> {
+        let column = cursor.column();
+        let kind = match (cursor.peek(), cursor.peek_at(1)) {
+            (Some(Tok::Ident(word)), _) if ast::mem_size(word).is_some() => {
+                cursor.next();
+                cursor.eat_word("ptr");
+                let mut mem = self.parse_mem(cursor)?;
+                mem.size = ast::mem_size(word);
+                OpKind::Mem(mem)
+            }
+            (Some(Tok::Punct('[')), _) => OpKind::Mem(self.parse_mem(cursor)?),
+            (Some(Tok::Ident(word)), Some(Tok::Punct(':'))) if word.parse::<SegReg>().is_ok() => {
+                OpKind::Mem(self.parse_mem(cursor)?)
+            }
+            (Some(Tok::Ident(word)), _) if Operand::register(word).is_some() => {
+                cursor.next();
+                OpKind::Reg(Operand::register(word).unwrap())
+            }
+            _ => OpKind::Imm(parse_expr(cursor, self)?),
+        };
+        Ok(Op { column, kind })
+    }
+
+    /// Parses `[...]`, with the segment override either before the bracket
+    /// or inside it.
+    fn parse_mem(&self, cursor: &mut Cursor<'_>) -> RaskResult<Mem> {
+        let mut mem = Mem {
+            size: None,
+            segment: self.parse_segment(cursor)?,
+            base: None,
+            index: None,
+            disp: None,
+            addressing: None,
+        };
+        let column = cursor.column();
+        cursor.expect('[')?;
+        if let Some(segment) = self.parse_segment(cursor)? {
+            mem.segment = Some(segment);
+        }
+        mem.addressing = self.parse_addressing(cursor);
+
+        let mut negative = cursor.eat('-');
+        loop {
+            self.parse_term(cursor, &mut mem, negative)?;
+            if cursor.eat('+') {
+                negative = false;
+            } else if cursor.eat('-') {
+                negative = true;
+            } else {
+                break;
+            }
+        }
+        cursor.expect(']')?;
+        mem.check_widths(cursor.line, column)?;
+        Ok(mem)
+    }
+
+    /// Parses `rel` or `abs`, if it comes next.
+    fn parse_addressing(&self, cursor: &mut Cursor<'_>) -> Option<Addressing> {
+        if cursor.eat_word("rel") {
+            Some(Addressing::Rel)
+        } else if cursor.eat_word("abs") {
+            Some(Addressing::Abs)
+        } else {
+            None
+        }
+    }
+
+    fn parse_segment(&self, cursor: &mut Cursor<'_>) -> RaskResult<Option<SegReg>> {
+        if let (Some(Tok::Ident(name)), Some(Tok::Punct(':'))) = (cursor.peek(), cursor.peek_at(1))
+        {
+            let segment = name
+                .parse()
+                .map_err(|_| cursor.error("unknown segment register"))?;
+            cursor.next();
+            cursor.next();
+            return Ok(Some(segment));
+        }
+        Ok(None)
+    }
+
+    /// Parses one term of an address: `reg`, `reg*scale`, `scale*reg` or a
+    /// displacement.
+    fn parse_term(&self, cursor: &mut Cursor<'_>, mem: &mut Mem, negative: bool) -> RaskResult<()> {
+        let is_reg = |tok: Option<&Tok>| {
+            matches!(tok, Some(Tok::Ident(name))
+                if Operand::register(name).is_some() || ast::addr_reg(name).is_some())
+        };
+        let mut factor = None;
+        if let (Some(Tok::Int(n)), Some(Tok::Punct('*'))) = (cursor.peek(), cursor.peek_at(1))
+            && is_reg(cursor.peek_at(2))
+        {
+            factor = Some(*n);
+            cursor.next();
+            cursor.next();
+        } else if !is_reg(cursor.peek()) {
+            let term = parse_product(cursor, self)?;
+            let term = if negative {
+                Expr::Neg(Box::new(term))
+            } else {
+                term
+            };
+            mem.disp = Some(match mem.disp.take() {
+                Some(disp) => Expr::Binary(ast::BinOp::Add, Box::new(disp), Box::new(term)),
+                None => term,
+            });
+            return Ok(());
+        }
+
+        let column = cursor.column();
+        let name = cursor.expect_ident("a register")?;
+        let reg = ast::addr_reg(name).ok_or_else(|| {
+            error(
+                cursor.line,
+                column,
+                format!("`{name}` cannot address memory"),
+            )
+        })?;
+        if factor.is_none() && cursor.eat('*') {
+            match cursor.next() {
+                Some(Tok::Int(n)) => factor = Some(*n),
+                _ => return Err(error(cursor.line, column, "expected a scale after `*`")),
+            }
+        }
+        if negative {
+            return Err(error(cursor.line, column, "registers cannot be subtracted"));
+        }
+
+        let scale = match factor {
+            Some(factor) => ast::scale(factor)
+                .ok_or_else(|| error(cursor.line, column, "scale must be 1, 2, 4 or 8"))?,
+            None if mem.base.is_none() => {
+                mem.base = Some(reg);
+                return Ok(());
+            }
+            None => Scale::S1,
+        };
+        if mem.index.is_some() {
+            return Err(error(
+                cursor.line,
+                column,
+                "a memory operand has at most one index register",
+            ));
+        }
+        mem.index = Some((reg, scale));
+        Ok(())
+    }
+}
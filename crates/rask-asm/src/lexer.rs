@@ -0,0 +1,331 @@
+//! Splitting a source line into tokens.
+
+use rask_common::{RaskError, RaskResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Tok {
+    Ident(String),
+    Int(i64),
+    Str(Vec<u8>),
+    Punct(char),
+    Shl,
+    Shr,
+    /// A GNU as numeric local label reference: `1f` for the next `1:`, or
+    /// `1b` for the last one.
+    LocalLabel {
+        number: i64,
+        forward: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub tok: Tok,
+    /// 1-based column of the first character.
+    pub column: usize,
+}
+
+/// The lexical differences between the syntaxes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    /// NASM: `;` comments, escapes only in backquoted strings, and
+    /// hexadecimal numbers with an `h` suffix.
+    Intel,
+    /// GNU as: `#` comments, `;` and `=` as punctuation, escapes in
+    /// double-quoted strings, and octal numbers with a leading `0`.
+    Att,
+}
+
+impl Dialect {
+    fn comment(self) -> char {
+        match self {
+            Dialect::Intel => ';',
+            Dialect::Att => '#',
+        }
+    }
+
+    /// Returns the quote whose strings take escapes.
+    fn escaping_quote(self) -> char {
+        match self {
+            Dialect::Intel => '`',
+            Dialect::Att => '"',
+        }
+    }
+}
+
+/// Returns a [`RaskError::Diagnostic`] at `line` and `column`.
+pub(crate) fn error(line: usize, column: usize, message: impl Into<String>) -> RaskError {
+    RaskError::Diagnostic {
+        line,
+        column,
+        message: message.into(),
+    }
+}
+
+/// Splits one line into tokens, dropping any comment.
+pub(crate) fn tokenize(text: &str, line: usize, dialect: Dialect) -> RaskResult<Vec<Token>> {
+    let comment = dialect.comment();
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c == comment {
+            break;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let tok = if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) && chars[i] != comment {
+                i += 1;
+            }
+            Tok::Ident(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            match local_label(&text) {
+                Some(tok) if dialect == Dialect::Att => tok,
+                _ => Tok::Int(
+                    parse_int(&text, dialect)
+                        .ok_or_else(|| error(line, column, format!("invalid number `{text}`")))?,
+                ),
+            }
+        } else if matches!(c, '\'' | '"' | '`') {
+            let (bytes, end) = parse_string(&chars, i, line, dialect.escaping_quote())?;
+            i = end;
+            Tok::Str(bytes)
+        } else if (c == '<' || c == '>') && chars.get(i + 1) == Some(&c) {
+            i += 2;
+            if c == '<' { Tok::Shl } else { Tok::Shr }
+        } else if ",:[]+-*/%()~&|^$".contains(c) || (dialect == Dialect::Att && "=;".contains(c)) {
+            i += 1;
+            Tok::Punct(c)
+        } else {
+            return Err(error(line, column, format!("unexpected character `{c}`")));
+        };
+        tokens.push(Token { tok, column });
+    }
+    Ok(tokens)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '?' | '@')
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '?' | '@' | '$' | '#')
+}
+
+/// Returns the numeric local label reference `text`, which is decimal
+/// digits followed by `f` or `b`.
+fn local_label(text: &str) -> Option<Tok> {
+    let (digits, direction) = text.split_at(text.len().checked_sub(1)?);
+    let forward = match direction {
+        "f" => true,
+        "b" => false,
+        _ => return None,
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let number = digits.parse().ok()?;
+    Some(Tok::LocalLabel { number, forward })
+}
+
+/// Parses a decimal, `0x`, `0b` or `0o` number, or in NASM a hexadecimal
+/// one with an `h` suffix and in GNU as an octal one with a leading `0`.
+/// Values up to `u64::MAX` wrap into an `i64`.
+fn parse_int(text: &str, dialect: Dialect) -> Option<i64> {
+    let text = text.replace('_', "").to_ascii_lowercase();
+    let (digits, radix) = if let Some(hex) = text.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(bin) = text.strip_prefix("0b") {
+        (bin, 2)
+    } else if let Some(oct) = text.strip_prefix("0o") {
+        (oct, 8)
+    } else if dialect == Dialect::Intel
+        && let Some(hex) = text.strip_suffix('h')
+    {
+        (hex, 16)
+    } else if dialect == Dialect::Att && text.len() > 1 && text.starts_with('0') {
+        (&text[1..], 8)
+    } else {
+        (text.as_str(), 10)
+    };
+    u64::from_str_radix(digits, radix).ok().map(|v| v as i64)
+}
+
+/// Parses the string starting with the quote at `chars[start]`, returning
+/// its bytes and the index just past the closing quote. Strings quoted with
+/// `escaping` take C-style escapes; the others are taken literally.
+fn parse_string(
+    chars: &[char],
+    start: usize,
+    line: usize,
+    escaping: char,
+) -> RaskResult<(Vec<u8>, usize)> {
+    let quote = chars[start];
+    let mut bytes = Vec::new();
+    let mut i = start + 1;
+    let unterminated = || error(line, start + 1, "unterminated string");
+    loop {
+        let c = *chars.get(i).ok_or_else(unterminated)?;
+        i += 1;
+        if c == quote {
+            return Ok((bytes, i));
+        }
+        if c != '\\' || quote != escaping {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let escape = *chars.get(i).ok_or_else(unterminated)?;
+        i += 1;
+        bytes.push(match escape {
+            'n' => b'\n',
+            't' => b'\t',
+            'r' => b'\r',
+            '0'..='7' => {
+                let mut value = escape as u32 - '0' as u32;
+                for _ in 0..2 {
+                    match chars.get(i).and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            value = value * 8 + digit;
+                            i += 1;
+                        }
+                        None => break,
+                    }
+                }
+                u8::try_from(value)
+                    .map_err(|_| error(line, i - 3, format!("invalid escape `\\{value:o}`")))?
+            }
+            '\\' | '\'' | '"' | '`' => escape as u8,
+            'x' => {
+                let hex: String = chars.iter().skip(i).take(2).collect();
+                i += 2;
+                u8::from_str_radix(&hex, 16)
+                    .map_err(|_| error(line, i - 3, format!("invalid escape `\\x{hex}`")))?
+            }
+            _ => return Err(error(line, i - 1, format!("invalid escape `\\{escape}`"))),
+        });
+    }
+}
+
+/// A position in the tokens of one line.
+pub(crate) struct Cursor<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    pub line: usize,
+    /// Column just past the end of the line, for errors at its end.
+    end: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(tokens: &'a [Token], line: usize, end: usize) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            line,
+            end,
+        }
+    }
+
+    pub fn peek(&self) -> Option<&'a Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    pub fn peek_at(&self, ahead: usize) -> Option<&'a Tok> {
+        self.tokens.get(self.pos + ahead).map(|t| &t.tok)
+    }
+
+    pub fn next(&mut self) -> Option<&'a Tok> {
+        let tok = self.peek();
+        if tok.is_some() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    /// Returns the column of the next token, or of the end of the line.
+    pub fn column(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |t| t.column)
+    }
+
+    /// Returns true if the next token directly follows the previous one,
+    /// with no space between them.
+    pub fn adjacent(&self) -> bool {
+        match (self.pos.checked_sub(1), self.tokens.get(self.pos)) {
+            (Some(prev), Some(next)) => {
+                let prev = &self.tokens[prev];
+                next.column == prev.column + 1 && matches!(prev.tok, Tok::Punct(_))
+            }
+            _ => false,
+        }
+    }
+
+    /// Consumes the next token if it is the punctuation `c`.
+    pub fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Tok::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the next token if it is the identifier `word`, ignoring
+    /// case.
+    pub fn eat_word(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Tok::Ident(name)) if name.eq_ignore_ascii_case(word) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn expect(&mut self, c: char) -> RaskResult<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{c}`")))
+        }
+    }
+
+    pub fn expect_ident(&mut self, what: &str) -> RaskResult<&'a str> {
+        match self.peek() {
+            Some(Tok::Ident(name)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.error(format!("expected {what}"))),
+        }
+    }
+
+    /// Fails unless every token has been consumed.
+    pub fn expect_end(&self) -> RaskResult<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected text at the end of the line"))
+        }
+    }
+
+    /// Returns an error at the next token.
+    pub fn error(&self, message: impl Into<String>) -> RaskError {
+        error(self.line, self.column(), message)
+    }
+}
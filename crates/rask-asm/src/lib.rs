@@ -0,0 +1,221 @@
+//! Text assembler for Rask.
+//!
+//! Assembles Intel-syntax source in the style of NASM, or AT&T-syntax
+//! source in the style of GNU as, into machine code and a symbol table,
+//! using the `rask_x86_64` encoder for every instruction:
+//!
+//! ```
+//! let object = rask_asm::assemble(
+//!     "
+//!     global count
+//!     count:              ; rcx times around the loop
+//!         xor eax, eax
+//!     .loop:
+//!         add rax, [table + 8]
+//!         dec rcx
+//!         jnz .loop
+//!         ret
+//!
+//!     section .data
+//!     table: dq 1, 2, 3
+//!     ",
+//! )?;
+//! assert_eq!(object.symbol("count.loop").unwrap().value, 2);
+//! assert_eq!(object.symbol("table").unwrap().value, 16);
+//! assert_eq!(object.section(".data").unwrap().len(), 24);
+//! # Ok::<(), rask_asm::RaskError>(())
+//! ```
+//!
+//! ### Syntax
+//! ```text
+//! name:                     a label; `.name` is local to the last label
+//! name equ expr             a constant
+//! [lock] mnemonic op, ...   an instruction
+//! db/dw/dd/dq item, ...     data: numbers, expressions or strings
+//! times count stmt          repeats data or an instruction
+//! align n                   pads with NOPs in `.text*`, zeros elsewhere
+//! section name              switches section (`segment` also works)
+//! global name, ...          marks symbols as exported
+//! extern name, ...          declares symbols defined elsewhere
+//! bits 16|32|64             sets the processor mode
+//! default rel|abs           sets how `[label]` is addressed in 64-bit code
+//! org address               sets the address of the first byte
+//! ; comment
+//! ```
+//!
+//! Operands are registers, memory operands such as
+//! `qword ptr fs:[rbx + rsi*8 - 16]` (the `ptr` is optional), and
+//! expressions. Expressions take decimal, `0x`, `0b`, `0o` and `h`-suffixed
+//! numbers, character constants, symbols, `$` (the current address) and
+//! `$$` (the start of the section), with the operators `+ - * / % << >> &
+//! | ^ ~` and parentheses. Arithmetic that overflows 64 bits, and shifts
+//! by 64 or more, are errors.
+//!
+//! In 64-bit code a memory operand without registers whose displacement is
+//! an address, such as `[table + 8]`, is RIP-relative: it holds the distance
+//! from the end of the instruction, so the code runs wherever it is loaded.
+//! A plain number such as `[0x1000]` stays absolute. `[rel x]` and
+//! `[abs x]` choose explicitly, `default rel` and `default abs` change the
+//! choice for everything after them, and `fs:` and `gs:` operands are
+//! absolute unless marked `rel`. `[rip + x]` is relative to the end of the
+//! instruction when `x` is an address and takes `x` as the displacement
+//! when it is a number.
+//!
+//! Symbols may be used before they are defined. A branch target must be a
+//! label, `$`, or an external symbol; external symbols may also be `dd` or
+//! `dq` values, and each use is recorded as a [`Relocation`]. A program may
+//! assemble to at most [`Assembler::MAX_OUTPUT`] bytes, 256 MiB unless
+//! [`Assembler::set_max_output`] says otherwise, so a runaway `times`,
+//! `align` or `.skip` fails instead of exhausting memory.
+//!
+//! ### AT&T syntax
+//! [`Syntax::Att`] reads the syntax of GNU as instead, and produces exactly
+//! what the same program in Intel syntax would:
+//!
+//! ```
+//! use rask_asm::{Assembler, Syntax};
+//!
+//! let object = Assembler::with_syntax(Syntax::Att).assemble(
+//!     r#"
+//!     .text
+//!     .globl greet
+//!     greet:
+//!         movq 8(%rbx), %rax      # source first, then destination
+//!         movl $7, %fs:(%rdi,%rcx,4)
+//!         jmp *%rax
+//!     .data
+//!     msg: .asciz "hi\n"
+//!     "#,
+//! )?;
+//! assert_eq!(object.section(".data").unwrap(), b"hi\n\0");
+//! # Ok::<(), rask_asm::RaskError>(())
+//! ```
+//!
+//! A `b`, `w`, `l` or `q` suffix gives the size of a memory operand, `*`
+//! marks an indirect branch, and `;` separates statements on one line. The
+//! directives are `.text`, `.data`, `.bss`, `.section`, `.globl`,
+//! `.extern`, `.code16/32/64`, `.set`, `name = expr`, `.byte`, `.word`,
+//! `.long`, `.quad`, `.ascii`, `.asciz`, `.zero`, `.skip`, `.p2align`,
+//! `.balign` and `.align`; `.type`, `.size`, `.file` and `.ident` are
+//! accepted and ignored. `.` is the current address, and numbers with a
+//! leading `0` are octal. Numeric labels such as `1:` may be defined more
+//! than once, with `1f` naming the next and `1b` the last. `sym(%rip)` is
+//! RIP-relative like `[rip + sym]`; unlike GNU as, a bare `sym` memory
+//! operand in 64-bit code is RIP-relative too, as it is in Intel syntax.
+//!
+//! Errors are [`RaskError::Diagnostic`]s carrying the line and column of
+//! the offending text.
+
+mod assemble;
+mod ast;
+mod att;
+mod intel;
+mod lexer;
+mod object;
+
+pub use object::{Object, Relocation, RelocationKind, Section, Symbol, SymbolKind};
+pub use rask_common::{RaskError, RaskResult};
+
+use rask_x86_64::{features::CpuFeatures, mode::Mode};
+
+/// The syntax of the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// Intel syntax in the style of NASM.
+    #[default]
+    Intel,
+    /// AT&T syntax in the style of GNU as.
+    Att,
+}
+
+/// Assembles source text in the given syntax, with the given starting mode
+/// and CPU features.
+#[derive(Debug, Clone, Copy)]
+pub struct Assembler {
+    syntax: Syntax,
+    mode: Mode,
+    features: CpuFeatures,
+    max_output: u64,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self {
+            syntax: Syntax::default(),
+            mode: Mode::default(),
+            features: CpuFeatures::default(),
+            max_output: Self::MAX_OUTPUT,
+        }
+    }
+}
+
+impl Assembler {
+    /// The default limit on the size of a program's output: 256 MiB.
+    pub const MAX_OUTPUT: u64 = 256 << 20;
+
+    /// Creates an assembler for 64-bit code with no optional features.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an assembler for source in `syntax`.
+    pub fn with_syntax(syntax: Syntax) -> Self {
+        Self {
+            syntax,
+            ..Self::default()
+        }
+    }
+
+    /// Creates an assembler that starts in `mode`; `bits` (or `.code64` and
+    /// the like) can still change it.
+    pub fn with_mode(mode: Mode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Creates an assembler that accepts the instructions of `features`.
+    pub fn with_features(features: CpuFeatures) -> Self {
+        Self {
+            features,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn set_features(&mut self, features: CpuFeatures) {
+        self.features = features;
+    }
+
+    /// Sets the most bytes a program may assemble to, sections and padding
+    /// included. Going past it is a [`RaskError::Diagnostic`] at the
+    /// statement that would.
+    pub fn set_max_output(&mut self, bytes: u64) {
+        self.max_output = bytes;
+    }
+
+    /// Assembles `source`.
+    ///
+    /// Returns a [`RaskError::Diagnostic`] for the first error found.
+    pub fn assemble(&self, source: &str) -> RaskResult<Object> {
+        let stmts = match self.syntax {
+            Syntax::Intel => intel::parse(source)?,
+            Syntax::Att => att::parse(source)?,
+        };
+        assemble::assemble(&stmts, self.mode, self.features, self.max_output)
+    }
+}
+
+/// Assembles 64-bit Intel-syntax `source`; see the [crate] documentation
+/// and [`Assembler`] for other syntaxes and modes.
+pub fn assemble(source: &str) -> RaskResult<Object> {
+    Assembler::new().assemble(source)
+}
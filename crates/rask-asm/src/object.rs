@@ -0,0 +1,85 @@
+//! The output of the assembler.
+
+/// The code and data assembled from one source file, with its symbol
+/// table.
+///
+/// Sections are laid out one after another in [`Object::bytes`], in the
+/// order they first appear in the source, each starting on a 16-byte
+/// boundary. Addresses count from the `org` address, [`Object::origin`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub bytes: Vec<u8>,
+    /// The address of the first byte, set with `org`; 0 by default.
+    pub origin: i64,
+    pub sections: Vec<Section>,
+    /// Every label, constant and external symbol, in source order.
+    pub symbols: Vec<Symbol>,
+    /// Places that refer to external symbols, to be filled in by whoever
+    /// links or loads the code.
+    pub relocations: Vec<Relocation>,
+}
+
+impl Object {
+    /// Returns the symbol called `name`.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    /// Returns the bytes of the section called `name`.
+    pub fn section(&self, name: &str) -> Option<&[u8]> {
+        let section = self.sections.iter().find(|s| s.name == name)?;
+        Some(&self.bytes[section.offset..section.offset + section.size])
+    }
+}
+
+/// A section's place in [`Object::bytes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// The address of a label, the value of a constant, or 0 for an
+    /// external symbol.
+    pub value: i64,
+    pub kind: SymbolKind,
+    /// True if the symbol is named by a `global` directive.
+    pub global: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A label in the section with this index in [`Object::sections`].
+    Label { section: usize },
+    /// A constant defined with `equ`.
+    Constant,
+    /// A symbol declared with `extern`, defined elsewhere.
+    Extern,
+}
+
+/// A field in [`Object::bytes`] that needs the address of an external
+/// symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Offset of the field in [`Object::bytes`].
+    pub offset: usize,
+    pub symbol: String,
+    pub kind: RelocationKind,
+    pub addend: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// A 32-bit displacement from the field: symbol + addend - field
+    /// address. Branches use an addend of -4, as the displacement counts
+    /// from the end of the instruction.
+    Rel32,
+    /// The 32-bit address symbol + addend.
+    Abs32,
+    /// The 64-bit address symbol + addend.
+    Abs64,
+}
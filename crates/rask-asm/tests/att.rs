@@ -0,0 +1,298 @@
+use rask_asm::{Assembler, Object, RaskError, RaskResult, SymbolKind, Syntax};
+use rask_x86_64::mode::Mode;
+
+/// Helper to format mismatches clearly when comparing byte sequences.
+fn assert_bytes(actual: &[u8], expected: &[u8]) {
+    if actual != expected {
+        println!("Expected: {:02x?}", expected);
+        println!("Actual:   {:02x?}", actual);
+        panic!("Byte sequence mismatch");
+    }
+}
+
+fn assemble(source: &str) -> RaskResult<Object> {
+    Assembler::with_syntax(Syntax::Att).assemble(source)
+}
+
+/// Asserts that assembling `source` fails at `line` and `column` with a
+/// message containing `text`.
+fn assert_diagnostic(source: &str, line: usize, column: usize, text: &str) {
+    match assemble(source) {
+        Err(RaskError::Diagnostic {
+            line: l,
+            column: c,
+            message,
+        }) => {
+            assert_eq!((l, c), (line, column), "{message}");
+            assert!(message.contains(text), "{message:?} lacks {text:?}");
+        }
+        other => panic!("expected a diagnostic, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_instructions() -> RaskResult<()> {
+    let object = assemble(
+        "
+        movq 8(%rbx), %rax
+        movl $7, %fs:(%rdi)
+        lock addq %rax, (%rdi)
+        movl -16(%rbx,%rsi,8), %ecx
+        movb $0xff, -1(%rbp)
+        movq 0x1000, %rdx           # absolute address
+        crc32b 1(%rsi), %eax
+        cmpq $0, (,%rcx,4)
+        jmp *%rax
+        callq *16(%rbx)
+        pclmulqdq $0x11, %xmm1, %xmm0
+        XORL %EAX, %EAX
+        retq
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0x48, 0x8B, 0x43, 0x08,                         // movq 8(%rbx), %rax
+        0x64, 0xC7, 0x07, 0x07, 0x00, 0x00, 0x00,       // movl $7, %fs:(%rdi)
+        0xF0, 0x48, 0x01, 0x07,                         // lock addq %rax, (%rdi)
+        0x8B, 0x4C, 0xF3, 0xF0,                         // movl -16(%rbx,%rsi,8), %ecx
+        0xC6, 0x45, 0xFF, 0xFF,                         // movb $0xff, -1(%rbp)
+        0x48, 0x8B, 0x14, 0x25, 0x00, 0x10, 0x00, 0x00, // movq 0x1000, %rdx
+        0xF2, 0x0F, 0x38, 0xF0, 0x46, 0x01,             // crc32b 1(%rsi), %eax
+        0x48, 0x83, 0x3C, 0x8D, 0x00, 0x00, 0x00, 0x00, 0x00, // cmpq $0, (,%rcx,4)
+        0xFF, 0xE0,                                     // jmp *%rax
+        0xFF, 0x53, 0x10,                               // callq *16(%rbx)
+        0x66, 0x0F, 0x3A, 0x44, 0xC1, 0x11,             // pclmulqdq $0x11, %xmm1, %xmm0
+        0x31, 0xC0,                                     // xorl %eax, %eax
+        0xC3,                                           // retq
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_matches_intel_syntax() -> RaskResult<()> {
+    let att = assemble(
+        r#"
+            .globl main
+            .text
+        main:
+            movl $len, %ecx
+            movq table(,%rcx,8), %rax
+        again:
+            decq %rcx
+            jnz again
+            call helper
+            ret
+            .p2align 4
+        helper:
+            jmpq *(%rax)
+            .data
+        table: .quad 1, 2, 3
+        msg: .asciz "hi\n"
+        len = . - msg
+        "#,
+    )?;
+    let intel = rask_asm::assemble(
+        "
+            global main
+            section .text
+        main:
+            mov ecx, len
+            mov rax, [table + rcx*8]
+        again:
+            dec rcx
+            jnz again
+            call helper
+            ret
+            align 16
+        helper:
+            jmp qword [rax]
+            section .data
+        table: dq 1, 2, 3
+        msg: db 'hi', 10, 0
+        len equ $ - msg
+        ",
+    )?;
+
+    assert_eq!(att, intel);
+    assert_eq!(att.symbol("len").unwrap().value, 4);
+    assert!(att.symbol("main").unwrap().global);
+    Ok(())
+}
+
+#[test]
+fn test_data_directives() -> RaskResult<()> {
+    let object = assemble(
+        r#"
+        .section .rodata, "a", @progbits
+        .byte 1, -1, 'A', 010       # 010 is octal
+        .word 0x1234
+        .short 0b101
+        .long -2
+        .quad base + 1
+        .ascii "a\tb", "\101\x42"
+        .string "z"
+        .zero 2
+        .skip 2, 0xCC
+        .balign 4
+        .type base, @object
+        .size base, 8
+        .set base, 0x10
+        "#,
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0x01, 0xFF, 0x41, 0x08,                         // .byte
+        0x34, 0x12,                                     // .word
+        0x05, 0x00,                                     // .short
+        0xFE, 0xFF, 0xFF, 0xFF,                         // .long -2
+        0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // .quad base + 1
+        0x61, 0x09, 0x62, 0x41, 0x42,                   // .ascii
+        0x7A, 0x00,                                     // .string
+        0x00, 0x00,                                     // .zero 2
+        0xCC, 0xCC,                                     // .skip 2, 0xCC
+        0x00,                                           // .balign 4, with zeros
+    ]);
+    assert_eq!(object.sections[0].name, ".rodata");
+    assert_eq!(object.symbol("base").unwrap().kind, SymbolKind::Constant);
+    Ok(())
+}
+
+#[test]
+fn test_several_statements_per_line() -> RaskResult<()> {
+    let object = assemble("start: lock; incq (%rax); jmp start # again\nret; ret")?;
+    assert_bytes(
+        &object.bytes,
+        &[0xF0, 0x48, 0xFF, 0x00, 0xEB, 0xFA, 0xC3, 0xC3],
+    );
+    Ok(())
+}
+
+#[test]
+fn test_code16_and_code32() -> RaskResult<()> {
+    let object = assemble(
+        "
+        .code16
+        start:
+            xorw %ax, %ax
+            jmp .
+        .code32
+            incl %ecx
+        ",
+    )?;
+    assert_bytes(&object.bytes, &[0x31, 0xC0, 0xEB, 0xFE, 0x41]);
+
+    let mut assembler = Assembler::with_mode(Mode::Protected32);
+    assembler.set_syntax(Syntax::Att);
+    assert_bytes(&assembler.assemble("decl %eax")?.bytes, &[0x48]);
+    Ok(())
+}
+
+#[test]
+fn test_externs_become_relocations() -> RaskResult<()> {
+    let object = assemble(".extern printf\ncall printf\n.data\n.quad printf")?;
+    assert_bytes(
+        object.section(".text").unwrap(),
+        &[0xE8, 0x00, 0x00, 0x00, 0x00],
+    );
+    assert_eq!(object.relocations.len(), 2);
+    assert_eq!(object.relocations[1].offset, 16);
+    Ok(())
+}
+
+#[test]
+fn test_rip_relative_addressing() -> RaskResult<()> {
+    let object = assemble(
+        "
+        movq table(%rip), %rax
+        movq 8(%rip), %rax
+        movl %eax, table+4(%rip)
+        table: .quad 0
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0x48, 0x8B, 0x05, 0x0D, 0x00, 0x00, 0x00, // movq table(%rip), %rax
+        0x48, 0x8B, 0x05, 0x08, 0x00, 0x00, 0x00, // movq 8(%rip), %rax
+        0x89, 0x05, 0x04, 0x00, 0x00, 0x00,       // movl %eax, table+4(%rip)
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_numeric_local_labels() -> RaskResult<()> {
+    let object = assemble(
+        "
+        1:  decq %rcx
+            jnz 1b
+            jmp 1f
+            nop
+        1:  ret
+            jmp 1b
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0x48, 0xFF, 0xC9,             // 1: decq %rcx
+        0x75, 0xFB,                   // jnz 1b
+        0xE9, 0x01, 0x00, 0x00, 0x00, // jmp 1f
+        0x90,                         // nop
+        0xC3,                         // 1: ret
+        0xEB, 0xFD,                   // jmp 1b
+    ]);
+    assert_eq!(object.symbol("1$1").unwrap().value, 0);
+    assert_eq!(object.symbol("1$2").unwrap().value, 11);
+    Ok(())
+}
+
+#[test]
+fn test_output_limit() -> RaskResult<()> {
+    let mut assembler = Assembler::with_syntax(Syntax::Att);
+    assembler.set_max_output(16);
+    assert_eq!(assembler.assemble(".skip 16, 0x90")?.bytes.len(), 16);
+    for (source, line) in [(".skip 17", 1), ("nop\n.p2align 5", 2)] {
+        match assembler.assemble(source) {
+            Err(RaskError::Diagnostic {
+                line: l, message, ..
+            }) => {
+                assert_eq!(l, line, "{source}");
+                assert!(message.contains("exceed 16 bytes"), "{message}");
+            }
+            other => panic!("expected a diagnostic for {source:?}, got {other:?}"),
+        }
+    }
+    assert_diagnostic(".zero (1 << 28) + 1", 1, 1, "exceed 268435456 bytes");
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics() {
+    assert_diagnostic("movl %rax, %rbx", 1, 1, "suffix of `movl`");
+    assert_diagnostic("movq (%rbx,%ecx), %rax", 1, 6, "same width");
+    assert_diagnostic("movq (%rbx,%rcx,3), %rax", 1, 17, "scale");
+    assert_diagnostic("movq %foo, %rax", 1, 7, "unknown register `%foo`");
+    assert_diagnostic("movl %ax:4, %eax", 1, 7, "unknown segment register");
+    assert_diagnostic("movq (%xmm0), %rax", 1, 8, "cannot address memory");
+    assert_diagnostic("incq *%rax", 1, 6, "indirect");
+    assert_diagnostic("\n  .frobnicate 1", 2, 3, "unknown directive `.frobnicate`");
+    assert_diagnostic(".p2align 4,,10", 1, 11, "not supported");
+    assert_diagnostic(".p2align 70", 1, 10, "outside 0..63");
+    assert_diagnostic("nop\n  .p2align 63", 2, 12, "outside 0..63");
+    assert_diagnostic(".p2align -1", 1, 10, "outside 0..63");
+    assert_diagnostic("ret; movx %eax, %ebx", 1, 6, "unknown instruction `movx`");
+    assert_diagnostic("movl $1, %eax %ebx", 1, 15, "unexpected text");
+    assert_diagnostic(".ascii 5", 1, 8, "expected a string");
+    assert_diagnostic(".byte 08", 1, 7, "invalid number `08`");
+    assert_diagnostic("jmp nowhere", 1, 5, "undefined symbol `nowhere`");
+    assert_diagnostic(".byte 256", 1, 1, "does not fit in 8 bits");
+    assert_diagnostic(".zero -1", 1, 1, "negative");
+    assert_diagnostic("ret\n.skip 100000000000, 0x90", 2, 1, "exceed");
+    assert_diagnostic("nop\n.p2align 40", 2, 1, "exceed");
+    assert_diagnostic("1: jmp 1f", 1, 8, "no `1:` after `1f`");
+    assert_diagnostic("1: ret\n  jmp 2b", 2, 7, "no `2:` before `2b`");
+    assert_diagnostic("movq (,%rip), %rax", 1, 1, "RIP cannot be used as an index");
+}
@@ -0,0 +1,408 @@
+use rask_asm::{
+    Assembler, RaskError, RaskResult, Relocation, RelocationKind, Section, SymbolKind, assemble,
+};
+use rask_x86_64::{
+    features::{CpuFeature, CpuFeatures},
+    mode::Mode,
+};
+
+/// Helper to format mismatches clearly when comparing byte sequences.
+fn assert_bytes(actual: &[u8], expected: &[u8]) {
+    if actual != expected {
+        println!("Expected: {:02x?}", expected);
+        println!("Actual:   {:02x?}", actual);
+        panic!("Byte sequence mismatch");
+    }
+}
+
+/// Asserts that assembling `source` fails at `line` and `column` with a
+/// message containing `text`.
+fn assert_diagnostic(source: &str, line: usize, column: usize, text: &str) {
+    match assemble(source) {
+        Err(RaskError::Diagnostic {
+            line: l,
+            column: c,
+            message,
+        }) => {
+            assert_eq!((l, c), (line, column), "{message}");
+            assert!(message.contains(text), "{message:?} lacks {text:?}");
+        }
+        other => panic!("expected a diagnostic, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_instructions() -> RaskResult<()> {
+    let object = assemble(
+        "
+        xor eax, eax
+        mov rax, qword ptr [rbx + rsi*8 - 16]
+        mov dword ptr fs:[rdi], 7
+        MOV DWORD [FS:RDI], 7           ; NASM spelling
+        lock add qword ptr [rdi], rax
+        mov ecx, 0x1234
+        mov rdx, [0x1000]
+        crc32 eax, byte ptr [1 + rsi]
+        mov byte ptr [rbp - 1], 0ffh
+        ret
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0x31, 0xC0,                               // xor eax, eax
+        0x48, 0x8B, 0x44, 0xF3, 0xF0,             // mov rax, [rbx + rsi*8 - 16]
+        0x64, 0xC7, 0x07, 0x07, 0x00, 0x00, 0x00, // mov dword ptr fs:[rdi], 7
+        0x64, 0xC7, 0x07, 0x07, 0x00, 0x00, 0x00,
+        0xF0, 0x48, 0x01, 0x07,                   // lock add [rdi], rax
+        0xB9, 0x34, 0x12, 0x00, 0x00,             // mov ecx, 0x1234
+        0x48, 0x8B, 0x14, 0x25, 0x00, 0x10, 0x00, 0x00, // mov rdx, [0x1000]
+        0xF2, 0x0F, 0x38, 0xF0, 0x46, 0x01,       // crc32 eax, byte ptr [rsi + 1]
+        0xC6, 0x45, 0xFF, 0xFF,                   // mov byte ptr [rbp - 1], 0xff
+        0xC3,                                     // ret
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_labels_and_branches() -> RaskResult<()> {
+    let object = assemble(
+        "
+        main:
+            jmp .check
+        .top:
+            dec rcx
+        .check:
+            cmp rcx, 0
+            jne .top
+            call helper
+            ret
+        helper:
+            jmp $
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0xE9, 0x03, 0x00, 0x00, 0x00, // jmp .check
+        0x48, 0xFF, 0xC9,             // .top: dec rcx
+        0x48, 0x83, 0xF9, 0x00,       // .check: cmp rcx, 0
+        0x75, 0xF7,                   // jne .top
+        0xE8, 0x01, 0x00, 0x00, 0x00, // call helper
+        0xC3,                         // ret
+        0xEB, 0xFE,                   // helper: jmp $
+    ]);
+    let names: Vec<_> = object.symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["main", "main.top", "main.check", "helper"]);
+    assert_eq!(object.symbol("helper").unwrap().value, 20);
+    Ok(())
+}
+
+#[test]
+fn test_data_directives() -> RaskResult<()> {
+    let object = assemble(
+        r#"
+        section .data
+        db 1, -1, 'A', "hi", `\n\0`
+        dw 0x1234, 'abc'
+        dd -2
+        dq 'ok' + 1
+        times 3 db 0xCC
+        align 8
+        dq $ - $$
+        "#,
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0x01, 0xFF, 0x41, 0x68, 0x69, 0x0A, 0x00,  // db
+        0x34, 0x12, 0x61, 0x62, 0x63, 0x00,        // dw, with the string padded
+        0xFE, 0xFF, 0xFF, 0xFF,                    // dd -2
+        0x70, 0x6B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // dq 'ok' + 1
+        0xCC, 0xCC, 0xCC,                          // times 3 db 0xCC
+        0x00, 0x00, 0x00, 0x00,                    // align 8, with zeros
+        0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // dq $ - $$
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_align_in_code_uses_nops() -> RaskResult<()> {
+    let object = assemble("ret\nalign 4\nret")?;
+    assert_bytes(&object.bytes, &[0xC3, 0x0F, 0x1F, 0x00, 0xC3]);
+    Ok(())
+}
+
+#[test]
+fn test_boot_sector() -> RaskResult<()> {
+    let object = assemble(
+        "
+        bits 16
+        org 0x7C00
+        start:
+            xor ax, ax
+            jmp start
+            times 510 - ($ - $$) db 0
+            dw 0xAA55
+        ",
+    )?;
+
+    assert_eq!(object.bytes.len(), 512);
+    assert_eq!(object.origin, 0x7C00);
+    assert_bytes(&object.bytes[..5], &[0x31, 0xC0, 0xEB, 0xFC, 0x00]);
+    assert_bytes(&object.bytes[510..], &[0x55, 0xAA]);
+    assert_eq!(object.symbol("start").unwrap().value, 0x7C00);
+    Ok(())
+}
+
+#[test]
+fn test_constants_and_expressions() -> RaskResult<()> {
+    let object = assemble(
+        "
+        SIZE equ COUNT * 8          ; defined before COUNT
+        COUNT equ 1 + 2 * 3
+        MASK equ ~0 >> 60 | 1 << 8
+        mov ecx, SIZE
+        mov edx, MASK
+        mov eax, (SIZE - 6) / 5 % 7
+        msg: db 'hello'
+        len: equ $ - msg
+        ",
+    )?;
+
+    assert_eq!(object.symbol("COUNT").unwrap().value, 7);
+    assert_eq!(object.symbol("SIZE").unwrap().value, 56);
+    assert_eq!(object.symbol("MASK").unwrap().value, 0x10F);
+    assert_eq!(object.symbol("len").unwrap().value, 5);
+    assert_eq!(object.symbol("len").unwrap().kind, SymbolKind::Constant);
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes[..15], &[
+        0xB9, 0x38, 0x00, 0x00, 0x00, // mov ecx, 56
+        0xBA, 0x0F, 0x01, 0x00, 0x00, // mov edx, 0x10f
+        0xB8, 0x03, 0x00, 0x00, 0x00, // mov eax, 3
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_forward_references_settle() -> RaskResult<()> {
+    // `end - start` is unknown on the first pass, so the immediate starts
+    // out as an imm8 and grows once the distance is known.
+    let object = assemble(
+        "
+        start:
+            add rax, end - start
+            times 200 db 0x90
+        end:
+        ",
+    )?;
+
+    assert_eq!(object.bytes.len(), 206);
+    assert_bytes(&object.bytes[..6], &[0x48, 0x05, 0xCE, 0x00, 0x00, 0x00]);
+    Ok(())
+}
+
+#[test]
+fn test_sections_and_symbols() -> RaskResult<()> {
+    let object = assemble(
+        "
+        global _start, msg
+        section .text
+        _start:
+            mov esi, msg
+            ret
+        section .data
+        msg: db 'hi'
+        section .text
+            ret
+        ",
+    )?;
+
+    assert_eq!(
+        object.sections,
+        [
+            Section {
+                name: ".text".to_string(),
+                offset: 0,
+                size: 7,
+            },
+            Section {
+                name: ".data".to_string(),
+                offset: 16,
+                size: 2,
+            },
+        ]
+    );
+    assert_bytes(
+        object.section(".text").unwrap(),
+        &[0xBE, 0x10, 0x00, 0x00, 0x00, 0xC3, 0xC3],
+    );
+    assert_bytes(object.section(".data").unwrap(), b"hi");
+
+    let msg = object.symbol("msg").unwrap();
+    assert_eq!(
+        (msg.value, msg.kind, msg.global),
+        (16, SymbolKind::Label { section: 1 }, true)
+    );
+    assert!(object.symbol("_start").unwrap().global);
+    Ok(())
+}
+
+#[test]
+fn test_externs_become_relocations() -> RaskResult<()> {
+    let object = assemble(
+        "
+        extern printf, table
+        call printf
+        jz printf
+        section .data
+        dq table + 8
+        dd table
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(object.section(".text").unwrap(), &[
+        0xE8, 0x00, 0x00, 0x00, 0x00,
+        0x0F, 0x84, 0x00, 0x00, 0x00, 0x00,
+    ]);
+    let reloc = |offset, symbol: &str, kind, addend| Relocation {
+        offset,
+        symbol: symbol.to_string(),
+        kind,
+        addend,
+    };
+    assert_eq!(
+        object.relocations,
+        [
+            reloc(1, "printf", RelocationKind::Rel32, -4),
+            reloc(7, "printf", RelocationKind::Rel32, -4),
+            reloc(16, "table", RelocationKind::Abs64, 8),
+            reloc(24, "table", RelocationKind::Abs32, 0),
+        ]
+    );
+    assert_eq!(object.symbol("table").unwrap().kind, SymbolKind::Extern);
+    Ok(())
+}
+
+#[test]
+fn test_rip_relative_addressing() -> RaskResult<()> {
+    let object = assemble(
+        "
+        mov rax, [data]
+        mov rax, [rip + 8]
+        mov rax, [rel 0x1000]
+        add rcx, [rip + data]
+        mov rax, [abs data]
+        mov eax, fs:[data]
+        data: dq 0
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes[..44], &[
+        0x48, 0x8B, 0x05, 0x25, 0x00, 0x00, 0x00, // mov rax, [rip + 37]
+        0x48, 0x8B, 0x05, 0x08, 0x00, 0x00, 0x00, // mov rax, [rip + 8]
+        0x48, 0x8B, 0x05, 0xEB, 0x0F, 0x00, 0x00, // mov rax, [rip + 0xFEB]
+        0x48, 0x03, 0x0D, 0x10, 0x00, 0x00, 0x00, // add rcx, [rip + 16]
+        0x48, 0x8B, 0x04, 0x25, 0x2C, 0x00, 0x00, 0x00, // mov rax, [0x2C]
+        0x64, 0x8B, 0x04, 0x25, 0x2C, 0x00, 0x00, 0x00, // mov eax, fs:[0x2C]
+    ]);
+
+    let object = assemble(
+        "
+        default rel
+        mov rax, [0x1000]
+        default abs
+        l: mov rax, [l]
+        bits 32
+        mov eax, [l]
+        ",
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&object.bytes, &[
+        0x48, 0x8B, 0x05, 0xF9, 0x0F, 0x00, 0x00,       // mov rax, [rip + 0xFF9]
+        0x48, 0x8B, 0x04, 0x25, 0x07, 0x00, 0x00, 0x00, // l: mov rax, [0x7]
+        0x8B, 0x05, 0x07, 0x00, 0x00, 0x00,             // mov eax, [0x7]
+    ]);
+
+    assert_diagnostic("mov rax, [rel 0x100000000]", 1, 10, "out of reach");
+    assert_diagnostic("bits 32\nmov eax, [rip]", 2, 1, "64-bit mode");
+    assert_diagnostic("default far", 1, 9, "expected `rel` or `abs`");
+    Ok(())
+}
+
+#[test]
+fn test_assembler_options() -> RaskResult<()> {
+    let object = Assembler::with_mode(Mode::Protected32).assemble("inc ecx")?;
+    assert_bytes(&object.bytes, &[0x41]);
+
+    let source = "push2 r16, rbx";
+    assert!(assemble(source).is_err());
+    let apx = Assembler::with_features(CpuFeatures::new().with(CpuFeature::Apx));
+    assert_bytes(
+        &apx.assemble(source)?.bytes,
+        &[0x62, 0xF4, 0x7C, 0x10, 0xFF, 0xF3],
+    );
+    Ok(())
+}
+
+#[test]
+fn test_output_limit() -> RaskResult<()> {
+    let mut assembler = Assembler::new();
+    assembler.set_max_output(16);
+    assert_eq!(assembler.assemble("times 16 db 0")?.bytes.len(), 16);
+    assert_eq!(assembler.assemble("ret\nalign 16")?.bytes.len(), 16);
+    for (source, line) in [("times 17 db 0", 1), ("ret\nalign 32", 2)] {
+        match assembler.assemble(source) {
+            Err(RaskError::Diagnostic {
+                line: l, message, ..
+            }) => {
+                assert_eq!(l, line, "{source}");
+                assert!(message.contains("exceed 16 bytes"), "{message}");
+            }
+            other => panic!("expected a diagnostic for {source:?}, got {other:?}"),
+        }
+    }
+
+    assert_diagnostic("times (1 << 28) + 1 db 0", 1, 1, "exceed 268435456 bytes");
+    assert_diagnostic("ret\nalign 1 << 29", 2, 1, "exceed 268435456 bytes");
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics() {
+    assert_diagnostic("ret\n  movv rax, 1", 2, 3, "unknown instruction `movv`");
+    assert_diagnostic("mov rax, [rbx + ecx]", 1, 10, "same width");
+    assert_diagnostic("mov rax, [rbx + rcx*3]", 1, 17, "scale");
+    assert_diagnostic("mov rax, [rbx - rcx]", 1, 17, "subtracted");
+    assert_diagnostic("mov rax, [xmm0]", 1, 11, "cannot address memory");
+    assert_diagnostic("\n\njmp nowhere", 3, 5, "undefined symbol `nowhere`");
+    assert_diagnostic("x: ret\nx: ret", 2, 1, "defined more than once");
+    assert_diagnostic("mov 1, rax", 1, 1, "invalid operands for MOV");
+    assert_diagnostic("add al, 300", 1, 1, "does not fit in 8 bits");
+    assert_diagnostic("db 256", 1, 1, "does not fit in 8 bits");
+    assert_diagnostic("db 'abc", 1, 4, "unterminated string");
+    assert_diagnostic("mov rax, 1 2", 1, 12, "unexpected text");
+    assert_diagnostic("extern f\nmov rax, f", 2, 1, "external symbol `f`");
+    assert_diagnostic("N equ 5\njmp N", 2, 5, "not a label");
+    assert_diagnostic("jmp $ + 2", 1, 5, "branch target");
+    assert_diagnostic("mov eax, 1 / 0", 1, 1, "division by zero");
+    assert_diagnostic("global nope", 1, 8, "undefined symbol `nope`");
+    assert_diagnostic("x equ x\nmov rax, x", 1, 1, "`x` is defined");
+    assert_diagnostic("a equ b\nb equ a + 1\ndq a", 1, 1, "`a` is defined");
+    assert_diagnostic("c equ 1\n  d equ e\n  e equ d", 2, 3, "`d` is defined");
+    assert_diagnostic("mov rax, 1 << 70", 1, 1, "shift count");
+    assert_diagnostic("db -1 >> 64", 1, 1, "shift count");
+    assert_diagnostic("db 1 << -1", 1, 1, "shift count");
+    assert_diagnostic("dq 9223372036854775807 + 1", 1, 1, "overflow");
+    assert_diagnostic("dq -4611686018427387904 * 3", 1, 1, "overflow");
+    assert_diagnostic("org 1 << 64", 1, 1, "shift count");
+    assert_diagnostic("org start\nstart:", 1, 1, "constant address");
+    assert_diagnostic("times 100000000000 db 0", 1, 1, "exceed");
+    assert_diagnostic("times 1 << 31 dw 0", 1, 1, "exceed");
+    assert_diagnostic("ret\nalign 1 << 40", 2, 1, "exceed");
+    assert_diagnostic("bits 64\nmov rax, 1\nbits 32\nmov rax, 1", 4, 1, "requires");
+}
@@ -0,0 +1,38 @@
+#![cfg(all(unix, target_arch = "x86_64"))]
+
+use rask_asm::{Assembler, RaskResult, Syntax, assemble};
+use rask_jit::JitFn;
+
+/// Loads `bytes` wherever the system maps them and calls them.
+fn run(bytes: &[u8]) -> i64 {
+    let f: JitFn<extern "sysv64" fn() -> i64> = unsafe { JitFn::new(bytes) }.unwrap();
+    f()
+}
+
+#[test]
+fn test_code_reads_its_own_data() -> RaskResult<()> {
+    let object = assemble(
+        "
+        mov rax, [table]
+        add rax, [table + 8]
+        add rax, [rip + table + 16]
+        ret
+
+        section .data
+        table: dq 1, 20, 300
+        ",
+    )?;
+    assert_eq!(run(&object.bytes), 321);
+
+    let object = Assembler::with_syntax(Syntax::Att).assemble(
+        "
+        movq table(%rip), %rax
+        addq table+8(%rip), %rax
+        ret
+        .data
+        table: .quad 4000, 50000
+        ",
+    )?;
+    assert_eq!(run(&object.bytes), 54000);
+    Ok(())
+}
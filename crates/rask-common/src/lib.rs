@@ -93,6 +93,56 @@ pub fn is_power_of_two(value: usize) -> bool {
 pub enum RaskError {
     InvalidInstruction,
     UnsupportedAbi,
+    /// No form of `mnemonic` accepts `operands`; `reason` says why.
+    InvalidOperands {
+        mnemonic: String,
+        operands: String,
+        reason: String,
+    },
+    /// `value` does not fit in the `bits`-wide immediate field of `mnemonic`.
+    ImmediateOutOfRange {
+        mnemonic: String,
+        value: i64,
+        bits: u32,
+    },
+    /// `what` needs a CPU feature or processor mode that the encoder does
+    /// not target.
+    UnsupportedFeature {
+        what: String,
+        feature: String,
+    },
+    /// A reference to a label that was never bound to an address.
+    UnboundLabel {
+        label: String,
+    },
+    /// The output buffer has room for `available` more bytes, but `needed`
+    /// were written.
+    BufferOverflow {
+        needed: usize,
+        available: usize,
+    },
+    /// An error in assembly source text, at a 1-based `line` and `column`.
+    Diagnostic {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The bytes at `offset` do not form a valid instruction; `reason` says
+    /// why.
+    InvalidEncoding {
+        offset: usize,
+        reason: String,
+    },
+    /// The bytes at `offset` form an instruction the decoder does not
+    /// support.
+    UnsupportedEncoding {
+        offset: usize,
+        reason: String,
+    },
+    /// The instruction at `offset` continues past the end of the input.
+    TruncatedInstruction {
+        offset: usize,
+    },
     Io(std::io::Error),
     Other(String),
 }
@@ -110,6 +160,39 @@ impl std::fmt::Display for RaskError {
         match self {
             Self::InvalidInstruction => write!(f, "invalid instruction"),
             Self::UnsupportedAbi => write!(f, "unsupported ABI"),
+            Self::InvalidOperands {
+                mnemonic,
+                operands,
+                reason,
+            } => write!(f, "invalid operands for {mnemonic} {operands}: {reason}"),
+            Self::ImmediateOutOfRange {
+                mnemonic,
+                value,
+                bits,
+            } => write!(
+                f,
+                "{mnemonic} immediate {value} does not fit in {bits} bits"
+            ),
+            Self::UnsupportedFeature { what, feature } => write!(f, "{what} requires {feature}"),
+            Self::UnboundLabel { label } => write!(f, "label {label} is never bound"),
+            Self::BufferOverflow { needed, available } => write!(
+                f,
+                "buffer overflow: {needed} bytes needed, {available} available"
+            ),
+            Self::Diagnostic {
+                line,
+                column,
+                message,
+            } => write!(f, "{line}:{column}: {message}"),
+            Self::InvalidEncoding { offset, reason } => {
+                write!(f, "invalid instruction at offset {offset}: {reason}")
+            }
+            Self::UnsupportedEncoding { offset, reason } => {
+                write!(f, "unsupported instruction at offset {offset}: {reason}")
+            }
+            Self::TruncatedInstruction { offset } => {
+                write!(f, "instruction at offset {offset} is truncated")
+            }
             Self::Io(e) => write!(f, "I/O error: {e}"),
             Self::Other(msg) => write!(f, "{msg}"),
         }
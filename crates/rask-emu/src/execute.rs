@@ -0,0 +1,477 @@
+//! Instruction semantics.
+
+use crate::{Emulator, Exception, Flag, Stop};
+use rask_common::RaskError;
+use rask_x86_64::{
+    decoder::Decoded,
+    instruction::{Instruction, Mnemonic},
+    mode::Mode,
+    operand::{AddrReg, MemOperand, Operand},
+    registers::{Reg64, SegReg},
+};
+
+/// Why an instruction did not complete.
+pub(crate) enum Trap {
+    /// The instruction raised a CPU exception.
+    Exception(Exception),
+    /// The emulator cannot execute the instruction.
+    Error(RaskError),
+}
+
+impl From<Exception> for Trap {
+    fn from(value: Exception) -> Self {
+        Trap::Exception(value)
+    }
+}
+
+impl From<RaskError> for Trap {
+    fn from(value: RaskError) -> Self {
+        Trap::Error(value)
+    }
+}
+
+type Exec<T> = Result<T, Trap>;
+
+fn unsupported(insn: &Instruction) -> Trap {
+    Trap::Error(RaskError::Other(format!(
+        "the emulator does not implement {insn}"
+    )))
+}
+
+/// Returns the mask of the low `bits` bits.
+#[inline]
+fn mask(bits: u32) -> u64 {
+    u64::MAX >> (64 - bits)
+}
+
+/// Returns the sign bit of a `bits`-wide value.
+#[inline]
+fn sign(bits: u32) -> u64 {
+    1 << (bits - 1)
+}
+
+/// Returns the width of a register or sized memory operand.
+fn width(op: &Operand) -> Option<u32> {
+    match op {
+        Operand::Reg(_) => Some(64),
+        Operand::Reg32(_) => Some(32),
+        Operand::Reg16(_) => Some(16),
+        Operand::Reg8(_) => Some(8),
+        Operand::Mem(m) => m.size.map(|size| size.bits()),
+        _ => None,
+    }
+}
+
+/// Multiplies `a` and `b` without carries, as `pclmulqdq` does.
+fn clmul(a: u64, b: u64) -> u128 {
+    (0..64)
+        .filter(|i| b >> i & 1 != 0)
+        .fold(0, |acc, i| acc ^ u128::from(a) << i)
+}
+
+/// Adds the bytes of `value` to a CRC-32C (Castagnoli) checksum, as
+/// `crc32` does.
+fn crc32c(mut crc: u32, value: u64, bytes: usize) -> u32 {
+    for byte in &value.to_le_bytes()[..bytes] {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = crc >> 1 ^ 0x82F6_3B78 & (crc & 1).wrapping_neg();
+        }
+    }
+    crc
+}
+
+impl Emulator {
+    /// Executes `decoded`, which was fetched from RIP.
+    ///
+    /// On an error, the caller restores the registers; memory is written
+    /// only by the last access of an instruction, so it is unchanged.
+    pub(crate) fn execute(&mut self, decoded: &Decoded) -> Exec<Option<Stop>> {
+        use Mnemonic::*;
+
+        let insn = decoded.instruction();
+        let mnemonic = insn.mnemonic();
+        let ops = insn.operands();
+        self.registers.set_rip(self.mask_address(decoded.end()));
+        let stack = self.stack_width();
+
+        match (mnemonic, ops) {
+            (Add | Or | Adc | Sbb | And | Sub | Xor | Cmp, &[dst, src]) => {
+                let bits = self.width(&insn)?;
+                let a = self.read(&dst, bits)?;
+                let b = self.read(&src, bits)?;
+                let result = self.alu(mnemonic, a, b, bits);
+                if mnemonic != Cmp {
+                    self.write(&dst, bits, result)?;
+                }
+            }
+            (Add | Or | Adc | Sbb | And | Sub | Xor, &[dst, a, b]) => {
+                let bits = self.width(&insn)?;
+                let a = self.read(&a, bits)?;
+                let b = self.read(&b, bits)?;
+                let result = self.alu(mnemonic, a, b, bits);
+                self.write(&dst, bits, result)?;
+            }
+            (Mov | Movnti, &[dst, src]) => {
+                let bits = self.width(&insn)?;
+                let value = self.read(&src, bits)?;
+                self.write(&dst, bits, value)?;
+            }
+            (Inc | Dec, &[dst]) => {
+                let bits = self.width(&insn)?;
+                let value = self.read(&dst, bits)?;
+                let carry = self.registers.flag(Flag::Carry);
+                let result = self.arith(value, 1, false, mnemonic == Dec, bits);
+                self.registers.set_flag(Flag::Carry, carry);
+                self.write(&dst, bits, result)?;
+            }
+
+            (Jmp, &[target]) => {
+                let target = self.target(&target)?;
+                self.registers.set_rip(target);
+            }
+            (Call, &[target]) => {
+                let target = self.target(&target)?;
+                self.push_value(self.registers.rip(), stack)?;
+                self.registers.set_rip(target);
+            }
+            (Ret, []) => {
+                let target = self.pop_value(stack)?;
+                self.registers.set_rip(target);
+            }
+            (_, &[Operand::Imm(target)]) if mnemonic.condition().is_some() => {
+                if let Some(cond) = mnemonic.condition()
+                    && self.registers.condition(cond)
+                {
+                    self.registers.set_rip(self.mask_address(target as u64));
+                }
+            }
+
+            (Pusha | Pushad, []) => {
+                let bytes = if mnemonic == Pusha { 2 } else { 4 };
+                let sp = self.stack_pointer();
+                let mut frame = [0; 32];
+                // DI ends up at the lowest address, AX at the highest.
+                for (i, slot) in frame.chunks_mut(bytes).take(8).enumerate() {
+                    let value = match Reg64::ALL[7 - i] {
+                        Reg64::RSP => sp,
+                        reg => self.registers.get(reg),
+                    };
+                    slot.copy_from_slice(&value.to_le_bytes()[..bytes]);
+                }
+                let sp = self.mask_address(sp.wrapping_sub(8 * bytes as u64));
+                self.memory.write(sp, &frame[..8 * bytes])?;
+                self.set_stack_pointer(sp);
+            }
+            (Popa | Popad, []) => {
+                let bytes = if mnemonic == Popa { 2 } else { 4 };
+                let sp = self.stack_pointer();
+                let mut frame = [0; 32];
+                self.memory.read(sp, &mut frame[..8 * bytes])?;
+                for (i, slot) in frame.chunks(bytes).take(8).enumerate() {
+                    let reg = Reg64::ALL[7 - i];
+                    if reg == Reg64::RSP {
+                        continue;
+                    }
+                    let mut value = [0; 8];
+                    value[..bytes].copy_from_slice(slot);
+                    let value = u64::from_le_bytes(value);
+                    let old = self.registers.get(reg);
+                    let merged = if bytes == 4 {
+                        value
+                    } else {
+                        old & !0xFFFF | value
+                    };
+                    self.registers.set(reg, merged);
+                }
+                self.set_stack_pointer(self.mask_address(sp.wrapping_add(8 * bytes as u64)));
+            }
+            (Push2 | Push2p, &[Operand::Reg(a), Operand::Reg(b)]) => {
+                let sp = self.stack_pointer();
+                if !sp.is_multiple_of(16) {
+                    return Err(Exception::GeneralProtection.into());
+                }
+                let mut frame = [0; 16];
+                frame[..8].copy_from_slice(&self.registers.get(b).to_le_bytes());
+                frame[8..].copy_from_slice(&self.registers.get(a).to_le_bytes());
+                self.memory.write(sp.wrapping_sub(16), &frame)?;
+                self.set_stack_pointer(sp.wrapping_sub(16));
+            }
+            (Pop2 | Pop2p, &[Operand::Reg(a), Operand::Reg(b)]) => {
+                let sp = self.stack_pointer();
+                if !sp.is_multiple_of(16) {
+                    return Err(Exception::GeneralProtection.into());
+                }
+                let first = self.memory.read_u64(sp)?;
+                let second = self.memory.read_u64(sp.wrapping_add(8))?;
+                self.registers.set(a, first);
+                self.registers.set(b, second);
+                self.set_stack_pointer(sp.wrapping_add(16));
+            }
+
+            (Rdfsbase | Rdgsbase, &[dst]) => {
+                let bits = self.width(&insn)?;
+                let base = match mnemonic {
+                    Rdfsbase => self.registers.fs_base(),
+                    _ => self.registers.gs_base(),
+                };
+                self.write(&dst, bits, base & mask(bits))?;
+            }
+            (Wrfsbase | Wrgsbase, &[src]) => {
+                let bits = self.width(&insn)?;
+                let base = self.read(&src, bits)?;
+                match mnemonic {
+                    Wrfsbase => self.registers.set_fs_base(base),
+                    _ => self.registers.set_gs_base(base),
+                }
+            }
+
+            (Movntdq | Movntps | Movntpd, &[Operand::Mem(m), Operand::Xmm(src)]) => {
+                let address = self.aligned(&m)?;
+                let value = self.registers.xmm(src);
+                self.memory.write(address, &value.to_le_bytes())?;
+            }
+            (Movntdqa, &[Operand::Xmm(dst), src]) => {
+                let value = self.read128(&src)?;
+                self.registers.set_xmm(dst, value);
+            }
+            (Crc32, &[dst, src]) => {
+                let bits = width(&dst).unwrap_or(32);
+                let src_bits = width(&src).ok_or_else(|| unsupported(&insn))?;
+                let crc = self.read(&dst, 32)? as u32;
+                let value = self.read(&src, src_bits)?;
+                let crc = crc32c(crc, value, src_bits as usize / 8);
+                self.write(&dst, bits, u64::from(crc))?;
+            }
+            (Pclmulqdq, &[Operand::Xmm(dst), src, Operand::Imm(imm)]) => {
+                let a = self.registers.xmm(dst);
+                let b = self.read128(&src)?;
+                let a = (a >> (64 * (imm & 1))) as u64;
+                let b = (b >> (64 * (imm >> 4 & 1))) as u64;
+                self.registers.set_xmm(dst, clmul(a, b));
+            }
+
+            (Int3, []) => return Ok(Some(Stop::Int3(decoded.address()))),
+            (Clflush | Clflushopt | Clwb, &[Operand::Mem(m)]) => {
+                self.memory.read_u8(self.address(&m))?;
+            }
+            (
+                Nop | Prefetcht0 | Prefetcht1 | Prefetcht2 | Prefetchnta | Prefetchw | Sfence
+                | Lfence | Mfence,
+                _,
+            ) => {}
+            _ => return Err(unsupported(&insn)),
+        }
+        Ok(None)
+    }
+
+    /// Returns the operand size of `insn`: the width of its first register
+    /// or sized memory operand.
+    fn width(&self, insn: &Instruction) -> Exec<u32> {
+        insn.operands()
+            .iter()
+            .find_map(width)
+            .ok_or_else(|| unsupported(insn))
+    }
+
+    /// Reads a register, memory or immediate operand of `bits` width.
+    fn read(&self, op: &Operand, bits: u32) -> Exec<u64> {
+        let regs = &self.registers;
+        Ok(match *op {
+            Operand::Reg(r) => regs.get(r),
+            Operand::Reg32(r) => u64::from(regs.get32(r)),
+            Operand::Reg16(r) => u64::from(regs.get16(r)),
+            Operand::Reg8(r) => u64::from(regs.get8(r)),
+            Operand::Mem(m) => self.memory.read_le(self.address(&m), bits as usize / 8)?,
+            Operand::Imm(v) => v as u64 & mask(bits),
+            other => {
+                return Err(Trap::Error(RaskError::Other(format!(
+                    "the emulator cannot read {other:?}"
+                ))));
+            }
+        })
+    }
+
+    /// Writes `value` to a register or memory operand of `bits` width.
+    fn write(&mut self, op: &Operand, bits: u32, value: u64) -> Exec<()> {
+        let regs = &mut self.registers;
+        match *op {
+            Operand::Reg(r) => regs.set(r, value),
+            Operand::Reg32(r) => regs.set32(r, value as u32),
+            Operand::Reg16(r) => regs.set16(r, value as u16),
+            Operand::Reg8(r) => regs.set8(r, value as u8),
+            Operand::Mem(m) => {
+                let address = self.address(&m);
+                self.memory.write_le(address, bits as usize / 8, value)?;
+            }
+            other => {
+                return Err(Trap::Error(RaskError::Other(format!(
+                    "the emulator cannot write {other:?}"
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a 128-bit XMM register or 16-byte aligned memory operand.
+    fn read128(&self, op: &Operand) -> Exec<u128> {
+        match op {
+            Operand::Xmm(r) => Ok(self.registers.xmm(*r)),
+            Operand::Mem(m) => {
+                let mut bytes = [0; 16];
+                self.memory.read(self.aligned(m)?, &mut bytes)?;
+                Ok(u128::from_le_bytes(bytes))
+            }
+            other => Err(Trap::Error(RaskError::Other(format!(
+                "the emulator cannot read {other:?} as 128 bits"
+            )))),
+        }
+    }
+
+    /// Returns the address of a memory operand, which SSE requires to be
+    /// 16-byte aligned.
+    fn aligned(&self, m: &MemOperand) -> Exec<u64> {
+        let address = self.address(m);
+        if !address.is_multiple_of(16) {
+            return Err(Exception::GeneralProtection.into());
+        }
+        Ok(address)
+    }
+
+    /// Computes the linear address of a memory operand. RIP must already
+    /// point past the instruction.
+    fn address(&self, m: &MemOperand) -> u64 {
+        let regs = &self.registers;
+        let value = |reg: AddrReg| match reg {
+            AddrReg::R64(r) => regs.get(r),
+            AddrReg::R32(r) => u64::from(regs.get32(r)),
+            AddrReg::R16(r) => u64::from(regs.get16(r)),
+            AddrReg::Rip => regs.rip(),
+        };
+        let bits = m.address_bits().unwrap_or(self.mode.default_address_bits());
+        let base = m.base.map_or(0, value);
+        let index = m
+            .index
+            .map_or(0, |(reg, scale)| value(reg) * u64::from(scale.factor()));
+        let offset = base.wrapping_add(index).wrapping_add(m.disp as i64 as u64) & mask(bits);
+        let segment = match m.segment {
+            Some(SegReg::FS) => regs.fs_base(),
+            Some(SegReg::GS) => regs.gs_base(),
+            _ => 0,
+        };
+        offset.wrapping_add(segment)
+    }
+
+    /// Returns the target of a branch: an absolute address, or a register
+    /// or memory operand of the stack width holding one.
+    fn target(&self, op: &Operand) -> Exec<u64> {
+        let target = match op {
+            Operand::Imm(target) => *target as u64,
+            _ => self.read(op, self.stack_width() as u32 * 8)?,
+        };
+        Ok(self.mask_address(target))
+    }
+
+    /// Applies an ALU operation, sets the flags and returns the result.
+    fn alu(&mut self, mnemonic: Mnemonic, a: u64, b: u64, bits: u32) -> u64 {
+        let carry = self.registers.flag(Flag::Carry);
+        match mnemonic {
+            Mnemonic::Add => self.arith(a, b, false, false, bits),
+            Mnemonic::Adc => self.arith(a, b, carry, false, bits),
+            Mnemonic::Sub | Mnemonic::Cmp => self.arith(a, b, false, true, bits),
+            Mnemonic::Sbb => self.arith(a, b, carry, true, bits),
+            Mnemonic::And => self.logic(a & b, bits),
+            Mnemonic::Or => self.logic(a | b, bits),
+            _ => self.logic(a ^ b, bits),
+        }
+    }
+
+    /// Adds or subtracts `b` and a carry from `a` and sets all six status
+    /// flags.
+    fn arith(&mut self, a: u64, b: u64, carry: bool, subtract: bool, bits: u32) -> u64 {
+        let c = u64::from(carry);
+        let (result, cf) = if subtract {
+            let result = a.wrapping_sub(b).wrapping_sub(c) & mask(bits);
+            (result, u128::from(a) < u128::from(b) + u128::from(c))
+        } else {
+            let wide = u128::from(a) + u128::from(b) + u128::from(c);
+            (wide as u64 & mask(bits), wide > u128::from(mask(bits)))
+        };
+        let overflow = if subtract {
+            (a ^ b) & (a ^ result)
+        } else {
+            (a ^ result) & (b ^ result)
+        };
+        self.set_result_flags(result, bits);
+        let regs = &mut self.registers;
+        regs.set_flag(Flag::Carry, cf);
+        regs.set_flag(Flag::Overflow, overflow & sign(bits) != 0);
+        regs.set_flag(Flag::Adjust, (a ^ b ^ result) & 0x10 != 0);
+        result
+    }
+
+    /// Sets the flags for a logical result: `CF`, `OF` and `AF` cleared.
+    fn logic(&mut self, result: u64, bits: u32) -> u64 {
+        self.set_result_flags(result, bits);
+        let regs = &mut self.registers;
+        regs.set_flag(Flag::Carry, false);
+        regs.set_flag(Flag::Overflow, false);
+        regs.set_flag(Flag::Adjust, false);
+        result
+    }
+
+    /// Sets `ZF`, `SF` and `PF` from a result.
+    fn set_result_flags(&mut self, result: u64, bits: u32) {
+        let regs = &mut self.registers;
+        regs.set_flag(Flag::Zero, result & mask(bits) == 0);
+        regs.set_flag(Flag::Sign, result & sign(bits) != 0);
+        regs.set_flag(Flag::Parity, (result as u8).count_ones().is_multiple_of(2));
+    }
+
+    /// Returns the number of bytes `call`, `ret` and branch targets on the
+    /// stack take in the current mode.
+    pub(crate) fn stack_width(&self) -> usize {
+        match self.mode {
+            Mode::Real16 => 2,
+            Mode::Protected32 => 4,
+            Mode::Long64 => 8,
+        }
+    }
+
+    /// Returns SP, ESP or RSP, whichever the mode uses.
+    pub(crate) fn stack_pointer(&self) -> u64 {
+        self.registers.get(Reg64::RSP) & mask(self.stack_width() as u32 * 8)
+    }
+
+    /// Sets SP, ESP or RSP, whichever the mode uses.
+    pub(crate) fn set_stack_pointer(&mut self, sp: u64) {
+        let bits = self.stack_width() as u32 * 8;
+        let old = self.registers.get(Reg64::RSP);
+        let value = match bits {
+            16 => old & !0xFFFF | sp & 0xFFFF,
+            _ => sp & mask(bits),
+        };
+        self.registers.set(Reg64::RSP, value);
+    }
+
+    /// Pushes the low `bytes` bytes of `value`.
+    pub(crate) fn push_value(&mut self, value: u64, bytes: usize) -> Result<(), Exception> {
+        let sp = self.mask_address(self.stack_pointer().wrapping_sub(bytes as u64));
+        self.memory.write_le(sp, bytes, value)?;
+        self.set_stack_pointer(sp);
+        Ok(())
+    }
+
+    /// Pops a value of `bytes` bytes.
+    fn pop_value(&mut self, bytes: usize) -> Result<u64, Exception> {
+        let sp = self.stack_pointer();
+        let value = self.memory.read_le(sp, bytes)?;
+        self.set_stack_pointer(self.mask_address(sp.wrapping_add(bytes as u64)));
+        Ok(value)
+    }
+
+    /// Truncates an address or instruction pointer to the mode's width.
+    pub(crate) fn mask_address(&self, address: u64) -> u64 {
+        address & mask(self.mode.default_address_bits())
+    }
+}
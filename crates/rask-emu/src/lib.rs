@@ -0,0 +1,371 @@
+//! Software x86-64 emulator for Rask.
+//!
+//! Executes the instructions the `rask_x86_64` encoder emits on an
+//! emulated register file and a sparse memory, so tests can run generated
+//! code and check its results without mapping executable memory, on any
+//! host, and for any [`Mode`] or calling convention:
+//!
+//! ```
+//! use rask_emu::{Emulator, Stop};
+//! use rask_x86_64::registers::Reg64::RAX;
+//!
+//! // fn add3(a, b, c) -> a + b + c
+//! let code = rask_asm::assemble("
+//!     mov rax, rdi
+//!     add rax, rsi
+//!     add rax, rdx
+//!     ret
+//! ")?.bytes;
+//! let mut emu = Emulator::new();
+//! emu.load(0x1000, &code)?;
+//! assert_eq!(emu.call(0x1000, &[1, 2, 3], 1000)?, Stop::Returned);
+//! assert_eq!(emu.registers().get(RAX), 6);
+//! # Ok::<(), rask_emu::RaskError>(())
+//! ```
+//!
+//! ### What is emulated
+//! - The integer instructions: `mov`, the ALU family (including the APX
+//!   three-operand forms), `inc`/`dec`, with every flag they define
+//! - Control flow: `jmp`, `call`, `ret` and `jcc`, direct and indirect
+//! - The stack: `push2`/`pop2`, `pusha`/`popa` and their 32-bit forms
+//! - `rdfsbase`/`wrfsbase` and friends, and FS/GS segment overrides
+//! - `movnti` and the SSE non-temporal moves, `crc32` and `pclmulqdq`
+//! - `nop`, prefetches, fences and cache-line flushes, which only check
+//!   that their address is mapped where the hardware would
+//!
+//! Other segment registers are flat, with a zero base, in every mode.
+//! Instructions outside this list, such as the AES, SHA and AVX-512
+//! families and AMX, stop execution with an error.
+//!
+//! ### Stopping
+//! [`Emulator::run`] executes until something in [`Stop`] happens: a
+//! breakpoint, `int3`, a CPU [`Exception`] such as a page fault, a return
+//! to [`RETURN_ADDRESS`], or the step limit. Faulting instructions leave
+//! the registers and memory as they were, with RIP at the instruction.
+
+mod execute;
+mod memory;
+mod registers;
+
+pub use memory::{Memory, PAGE_SIZE};
+pub use rask_common::{Abi, RaskError, RaskResult};
+pub use registers::{Flag, RFLAGS_RESET, Registers};
+
+use rask_x86_64::{
+    decoder::{Decoded, Decoder},
+    instruction::MAX_INSTRUCTION_LEN,
+    mode::Mode,
+    registers::Reg64,
+};
+use std::{collections::BTreeSet, fmt};
+
+/// The return address [`Emulator::call`] pushes, truncated to the mode's
+/// address width. Execution reaching it stops with [`Stop::Returned`].
+pub const RETURN_ADDRESS: u64 = 0xFFFF_FFFF_FFFF_F000;
+
+/// A CPU exception raised by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exception {
+    /// `#PF`: an access to an unmapped `address`.
+    PageFault { address: u64, write: bool },
+    /// `#GP`: a misaligned `push2`/`pop2` stack or SSE memory operand.
+    GeneralProtection,
+    /// `#UD`: the bytes at RIP are not a valid instruction.
+    InvalidOpcode,
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PageFault {
+                address,
+                write: false,
+            } => write!(f, "page fault reading {address:#x}"),
+            Self::PageFault {
+                address,
+                write: true,
+            } => write!(f, "page fault writing {address:#x}"),
+            Self::GeneralProtection => write!(f, "general-protection fault"),
+            Self::InvalidOpcode => write!(f, "invalid opcode"),
+        }
+    }
+}
+
+impl std::error::Error for Exception {}
+
+impl From<Exception> for RaskError {
+    fn from(value: Exception) -> Self {
+        RaskError::Other(value.to_string())
+    }
+}
+
+/// Why [`Emulator::run`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stop {
+    /// Execution reached [`RETURN_ADDRESS`].
+    Returned,
+    /// Execution reached a breakpoint at the address, which has not run yet.
+    Breakpoint(u64),
+    /// An `int3` at the address ran; RIP is after it.
+    Int3(u64),
+    /// The instruction at RIP raised an exception and did not run.
+    Exception(Exception),
+    /// The step limit was reached.
+    StepLimit,
+}
+
+/// An emulated CPU with its memory.
+#[derive(Debug, Clone)]
+pub struct Emulator {
+    registers: Registers,
+    memory: Memory,
+    mode: Mode,
+    abi: Abi,
+    breakpoints: BTreeSet<u64>,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator {
+    /// Creates an emulator for 64-bit code; see [`Emulator::with_mode`].
+    pub fn new() -> Self {
+        Self::with_mode(Mode::Long64)
+    }
+
+    /// Creates an emulator executing code in `mode`, with a 64 KiB stack
+    /// (4 KiB in 16-bit mode) mapped below RSP and everything else
+    /// unmapped.
+    pub fn with_mode(mode: Mode) -> Self {
+        let (top, size) = match mode {
+            Mode::Real16 => (0x9000, 0x1000),
+            Mode::Protected32 | Mode::Long64 => (0x7FFF_0000, 0x1_0000),
+        };
+        let mut memory = Memory::new();
+        memory.map(top - size, size);
+        let mut registers = Registers::new();
+        registers.set(Reg64::RSP, top);
+        Self {
+            registers,
+            memory,
+            mode,
+            abi: Abi::SystemV,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the mode code executes in.
+    #[inline]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Sets the calling convention [`Emulator::call`] passes 64-bit
+    /// arguments with: System V (the default) or Windows x64.
+    #[inline]
+    pub fn set_abi(&mut self, abi: Abi) {
+        self.abi = abi;
+    }
+
+    /// Returns the registers.
+    #[inline]
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// Returns the registers for modification.
+    #[inline]
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
+    /// Returns the memory.
+    #[inline]
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Returns the memory for modification.
+    #[inline]
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Maps memory for `bytes` at `address` and copies them there; see
+    /// [`Memory::load`].
+    pub fn load(&mut self, address: u64, bytes: &[u8]) -> RaskResult<()> {
+        Ok(self.memory.load(address, bytes)?)
+    }
+
+    /// Adds a breakpoint at `address`. Returns `false` if there already was
+    /// one.
+    pub fn add_breakpoint(&mut self, address: u64) -> bool {
+        self.breakpoints.insert(address)
+    }
+
+    /// Removes the breakpoint at `address`. Returns `false` if there was
+    /// none.
+    pub fn remove_breakpoint(&mut self, address: u64) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    /// Decodes the instruction at RIP without executing it.
+    ///
+    /// Returns [`Exception::PageFault`] if it is not mapped and
+    /// [`Exception::InvalidOpcode`] if it does not decode. An instruction
+    /// the decoder does not support is an error.
+    pub fn fetch(&self) -> RaskResult<Result<Decoded, Exception>> {
+        let rip = self.registers.rip();
+        let mut bytes = [0; MAX_INSTRUCTION_LEN];
+        let mut len = 0;
+        while len < bytes.len() {
+            match self.memory.read_u8(rip.wrapping_add(len as u64)) {
+                Ok(byte) => bytes[len] = byte,
+                Err(_) => break,
+            }
+            len += 1;
+        }
+        let mut decoder = Decoder::with_mode(&bytes[..len], self.mode);
+        decoder.set_address(rip);
+        match decoder.decode() {
+            Ok(decoded) => Ok(Ok(decoded)),
+            Err(RaskError::TruncatedInstruction { .. }) if len < bytes.len() => {
+                Ok(Err(Exception::PageFault {
+                    address: rip.wrapping_add(len as u64),
+                    write: false,
+                }))
+            }
+            Err(RaskError::InvalidEncoding { .. } | RaskError::TruncatedInstruction { .. }) => {
+                Ok(Err(Exception::InvalidOpcode))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Executes one instruction, ignoring breakpoints.
+    ///
+    /// Returns the [`Stop`] the instruction caused, if any: `int3` or an
+    /// exception.
+    ///
+    /// Returns an error if the instruction is one the emulator does not
+    /// implement; nothing is executed then.
+    pub fn step(&mut self) -> RaskResult<Option<Stop>> {
+        let decoded = match self.fetch()? {
+            Ok(decoded) => decoded,
+            Err(exception) => return Ok(Some(Stop::Exception(exception))),
+        };
+        let saved = self.registers.clone();
+        match self.execute(&decoded) {
+            Ok(stop) => Ok(stop),
+            Err(err) => {
+                self.registers = saved;
+                match err {
+                    execute::Trap::Exception(exception) => Ok(Some(Stop::Exception(exception))),
+                    execute::Trap::Error(err) => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Executes instructions from RIP until one of the [`Stop`] reasons,
+    /// or until `max_steps` instructions have run.
+    ///
+    /// A breakpoint at RIP when `run` is called does not stop it, so a run
+    /// that stopped at a breakpoint resumes with another call.
+    ///
+    /// ```
+    /// use rask_emu::{Emulator, Stop};
+    /// use rask_x86_64::registers::Reg64::RCX;
+    ///
+    /// // Count RCX down from 3, stopping on each pass through `dec`.
+    /// let code = rask_asm::assemble("
+    ///     mov ecx, 3
+    /// top:
+    ///     dec rcx
+    ///     jnz top
+    ///     int3
+    /// ")?.bytes;
+    /// let mut emu = Emulator::new();
+    /// emu.load(0, &code)?;
+    /// emu.add_breakpoint(5);
+    /// for left in [3, 2, 1] {
+    ///     assert_eq!(emu.run(100)?, Stop::Breakpoint(5));
+    ///     assert_eq!(emu.registers().get(RCX), left);
+    /// }
+    /// assert_eq!(emu.run(100)?, Stop::Int3(10));
+    /// # Ok::<(), rask_emu::RaskError>(())
+    /// ```
+    ///
+    /// Returns an error if an instruction is one the emulator does not
+    /// implement; RIP is at that instruction then.
+    pub fn run(&mut self, max_steps: u64) -> RaskResult<Stop> {
+        let return_address = self.mask_address(RETURN_ADDRESS);
+        for step in 0..max_steps {
+            let rip = self.registers.rip();
+            if rip == return_address {
+                return Ok(Stop::Returned);
+            }
+            if step > 0 && self.breakpoints.contains(&rip) {
+                return Ok(Stop::Breakpoint(rip));
+            }
+            if let Some(stop) = self.step()? {
+                return Ok(stop);
+            }
+        }
+        Ok(Stop::StepLimit)
+    }
+
+    /// Calls the function at `entry` with integer `args` and runs it, as
+    /// [`Emulator::run`] does, until it returns or stops otherwise.
+    ///
+    /// In 64-bit mode the arguments are passed as the [`Abi`] set with
+    /// [`Emulator::set_abi`] specifies: in RDI, RSI, RDX, RCX, R8 and R9 for
+    /// System V, and in RCX, RDX, R8 and R9 above 32 bytes of shadow space
+    /// for Windows, with the rest on the stack. In 32- and 16-bit mode all
+    /// of them go on the stack, truncated to the mode's width, as `cdecl`
+    /// passes them. [`RETURN_ADDRESS`] is pushed as the return address; when
+    /// the function returns, RSP is back where it was before the call.
+    ///
+    /// The result is in RAX (EAX, AX), for the caller to read from
+    /// [`Emulator::registers`].
+    ///
+    /// Returns an error if an instruction is one the emulator does not
+    /// implement, or if the stack has no room for the arguments.
+    pub fn call(&mut self, entry: u64, args: &[u64], max_steps: u64) -> RaskResult<Stop> {
+        use Reg64::*;
+
+        let (in_registers, shadow): (&[Reg64], u64) = match (self.mode, self.abi) {
+            (Mode::Long64, Abi::SystemV) => (&[RDI, RSI, RDX, RCX, R8, R9], 0),
+            (Mode::Long64, Abi::Windows) => (&[RCX, RDX, R8, R9], 32),
+            _ => (&[], 0),
+        };
+        let (registers, stack) = args.split_at(args.len().min(in_registers.len()));
+        for (&reg, &value) in in_registers.iter().zip(registers) {
+            self.registers.set(reg, value);
+        }
+
+        let sp = self.stack_pointer();
+        for &value in stack.iter().rev() {
+            self.push(value)?;
+        }
+        if shadow > 0 {
+            self.set_stack_pointer(self.stack_pointer().wrapping_sub(shadow));
+        }
+        self.push(RETURN_ADDRESS)?;
+        self.registers.set_rip(entry);
+
+        let stop = self.run(max_steps)?;
+        if stop == Stop::Returned {
+            self.set_stack_pointer(sp);
+        }
+        Ok(stop)
+    }
+
+    /// Pushes a value of the mode's stack width for [`Emulator::call`].
+    fn push(&mut self, value: u64) -> RaskResult<()> {
+        Ok(self.push_value(value, self.stack_width())?)
+    }
+}
@@ -0,0 +1,193 @@
+//! A sparse, page-granular memory model.
+
+use crate::Exception;
+use std::collections::BTreeMap;
+
+/// The size of a page, the unit memory is mapped in.
+pub const PAGE_SIZE: u64 = 0x1000;
+
+/// Returns the start of the page holding `address`.
+#[inline]
+fn page_of(address: u64) -> u64 {
+    address & !(PAGE_SIZE - 1)
+}
+
+/// The emulated address space.
+///
+/// Only mapped pages can be read or written; any other access raises
+/// [`Exception::PageFault`], so code that strays outside the memory a test
+/// set up stops instead of reading zeros. Pages are allocated when mapped,
+/// so a few pages at opposite ends of the 64-bit address space cost no
+/// more than two adjacent ones. There are no access permissions: code,
+/// data and stack pages are all readable, writable and executable.
+///
+/// ```
+/// use rask_emu::{Exception, Memory};
+///
+/// let mut memory = Memory::new();
+/// memory.load(0x1000, &[1, 2, 3, 4])?;
+/// assert_eq!(memory.read_u32(0x1000)?, 0x0403_0201);
+/// assert_eq!(memory.read_u8(0x1FFF)?, 0); // the rest of the page is zero
+/// assert_eq!(
+///     memory.read_u8(0x2000),
+///     Err(Exception::PageFault { address: 0x2000, write: false })
+/// );
+/// # Ok::<(), Exception>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Memory {
+    pages: BTreeMap<u64, Box<[u8]>>,
+}
+
+impl Memory {
+    /// Returns an empty address space.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps zeroed pages covering `len` bytes from `address`. Pages that
+    /// are already mapped keep their contents.
+    pub fn map(&mut self, address: u64, len: u64) {
+        for page in pages(address, len) {
+            self.pages
+                .entry(page)
+                .or_insert_with(|| vec![0; PAGE_SIZE as usize].into_boxed_slice());
+        }
+    }
+
+    /// Unmaps the pages covering `len` bytes from `address`.
+    pub fn unmap(&mut self, address: u64, len: u64) {
+        for page in pages(address, len) {
+            self.pages.remove(&page);
+        }
+    }
+
+    /// Returns whether the byte at `address` is mapped.
+    #[inline]
+    pub fn is_mapped(&self, address: u64) -> bool {
+        self.pages.contains_key(&page_of(address))
+    }
+
+    /// Maps the pages covering `bytes` at `address` and copies them there.
+    ///
+    /// Returns an error only if the bytes run past the end of the address
+    /// space.
+    pub fn load(&mut self, address: u64, bytes: &[u8]) -> Result<(), Exception> {
+        self.map(address, bytes.len() as u64);
+        self.write(address, bytes)
+    }
+
+    /// Reads `buf.len()` bytes from `address`.
+    ///
+    /// Returns [`Exception::PageFault`] for the first unmapped byte, and
+    /// reads nothing then.
+    pub fn read(&self, address: u64, buf: &mut [u8]) -> Result<(), Exception> {
+        self.check(address, buf.len(), false)?;
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let at = address.wrapping_add(i as u64);
+            *byte = self.pages[&page_of(at)][(at % PAGE_SIZE) as usize];
+        }
+        Ok(())
+    }
+
+    /// Writes `bytes` to `address`.
+    ///
+    /// Returns [`Exception::PageFault`] for the first unmapped byte, and
+    /// writes nothing then.
+    pub fn write(&mut self, address: u64, bytes: &[u8]) -> Result<(), Exception> {
+        self.check(address, bytes.len(), true)?;
+        for (i, byte) in bytes.iter().enumerate() {
+            let at = address.wrapping_add(i as u64);
+            if let Some(page) = self.pages.get_mut(&page_of(at)) {
+                page[(at % PAGE_SIZE) as usize] = *byte;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a little-endian value of `len` bytes (at most 8).
+    pub(crate) fn read_le(&self, address: u64, len: usize) -> Result<u64, Exception> {
+        let mut bytes = [0; 8];
+        self.read(address, &mut bytes[..len])?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Writes the low `len` bytes (at most 8) of `value`, little-endian.
+    pub(crate) fn write_le(
+        &mut self,
+        address: u64,
+        len: usize,
+        value: u64,
+    ) -> Result<(), Exception> {
+        self.write(address, &value.to_le_bytes()[..len])
+    }
+
+    /// Reads a byte.
+    pub fn read_u8(&self, address: u64) -> Result<u8, Exception> {
+        Ok(self.read_le(address, 1)? as u8)
+    }
+
+    /// Reads a little-endian 16-bit value.
+    pub fn read_u16(&self, address: u64) -> Result<u16, Exception> {
+        Ok(self.read_le(address, 2)? as u16)
+    }
+
+    /// Reads a little-endian 32-bit value.
+    pub fn read_u32(&self, address: u64) -> Result<u32, Exception> {
+        Ok(self.read_le(address, 4)? as u32)
+    }
+
+    /// Reads a little-endian 64-bit value.
+    pub fn read_u64(&self, address: u64) -> Result<u64, Exception> {
+        self.read_le(address, 8)
+    }
+
+    /// Writes a byte.
+    pub fn write_u8(&mut self, address: u64, value: u8) -> Result<(), Exception> {
+        self.write(address, &[value])
+    }
+
+    /// Writes a little-endian 16-bit value.
+    pub fn write_u16(&mut self, address: u64, value: u16) -> Result<(), Exception> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Writes a little-endian 32-bit value.
+    pub fn write_u32(&mut self, address: u64, value: u32) -> Result<(), Exception> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Writes a little-endian 64-bit value.
+    pub fn write_u64(&mut self, address: u64, value: u64) -> Result<(), Exception> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Checks that `len` bytes from `address` are mapped.
+    fn check(&self, address: u64, len: usize, write: bool) -> Result<(), Exception> {
+        if len == 0 {
+            return Ok(());
+        }
+        for page in pages(address, len as u64) {
+            if !self.pages.contains_key(&page) {
+                return Err(Exception::PageFault {
+                    address: page.max(address),
+                    write,
+                });
+            }
+        }
+        if address.checked_add(len as u64 - 1).is_none() {
+            return Err(Exception::PageFault { address: 0, write });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the start of every page covering `len` bytes from `address`.
+fn pages(address: u64, len: u64) -> impl Iterator<Item = u64> {
+    let first = page_of(address);
+    let count = match len {
+        0 => 0,
+        _ => (page_of(address.saturating_add(len - 1)) - first) / PAGE_SIZE + 1,
+    };
+    (0..count).map(move |i| first + i * PAGE_SIZE)
+}
@@ -0,0 +1,247 @@
+//! The register file: general-purpose, XMM and segment-base registers,
+//! RIP and RFLAGS.
+
+use rask_x86_64::{
+    instruction::Condition,
+    registers::{Reg8, Reg16, Reg32, Reg64, XmmReg},
+};
+
+/// A status flag in RFLAGS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flag {
+    /// `CF`: an unsigned result carried or borrowed out of the top bit.
+    Carry,
+    /// `PF`: the low byte of the result has an even number of set bits.
+    Parity,
+    /// `AF`: a carry or borrow out of bit 3.
+    Adjust,
+    /// `ZF`: the result is zero.
+    Zero,
+    /// `SF`: the top bit of the result.
+    Sign,
+    /// `DF`: string instructions count down.
+    Direction,
+    /// `OF`: a signed result does not fit.
+    Overflow,
+}
+
+impl Flag {
+    /// The flags arithmetic instructions set.
+    pub const STATUS: [Flag; 6] = [
+        Flag::Carry,
+        Flag::Parity,
+        Flag::Adjust,
+        Flag::Zero,
+        Flag::Sign,
+        Flag::Overflow,
+    ];
+
+    /// Returns the flag's bit in RFLAGS.
+    #[inline]
+    pub fn mask(self) -> u64 {
+        1 << match self {
+            Flag::Carry => 0,
+            Flag::Parity => 2,
+            Flag::Adjust => 4,
+            Flag::Zero => 6,
+            Flag::Sign => 7,
+            Flag::Direction => 10,
+            Flag::Overflow => 11,
+        }
+    }
+}
+
+/// RFLAGS after reset: only the reserved bit 1 is set.
+pub const RFLAGS_RESET: u64 = 0x2;
+
+/// The registers of one emulated CPU.
+///
+/// General-purpose registers are read and written at every width the
+/// encoder has operands for. Writing a 32-bit register zeroes the upper
+/// half of the 64-bit register, as on hardware; 16- and 8-bit writes leave
+/// the other bits alone. All 32 APX registers and XMM0–XMM31 are present,
+/// whichever features the code was encoded with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registers {
+    gpr: [u64; 32],
+    xmm: [u128; 32],
+    rip: u64,
+    rflags: u64,
+    fs_base: u64,
+    gs_base: u64,
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self {
+            gpr: [0; 32],
+            xmm: [0; 32],
+            rip: 0,
+            rflags: RFLAGS_RESET,
+            fs_base: 0,
+            gs_base: 0,
+        }
+    }
+}
+
+impl Registers {
+    /// Returns registers that are all zero, with RFLAGS at
+    /// [`RFLAGS_RESET`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a 64-bit register.
+    #[inline]
+    pub fn get(&self, reg: Reg64) -> u64 {
+        self.gpr[usize::from(reg.id())]
+    }
+
+    /// Sets a 64-bit register.
+    #[inline]
+    pub fn set(&mut self, reg: Reg64, value: u64) {
+        self.gpr[usize::from(reg.id())] = value;
+    }
+
+    /// Returns a 32-bit register.
+    #[inline]
+    pub fn get32(&self, reg: Reg32) -> u32 {
+        self.gpr[usize::from(reg.id())] as u32
+    }
+
+    /// Sets a 32-bit register, zeroing the upper half of the 64-bit one.
+    #[inline]
+    pub fn set32(&mut self, reg: Reg32, value: u32) {
+        self.gpr[usize::from(reg.id())] = u64::from(value);
+    }
+
+    /// Returns a 16-bit register.
+    #[inline]
+    pub fn get16(&self, reg: Reg16) -> u16 {
+        self.gpr[usize::from(reg.id())] as u16
+    }
+
+    /// Sets a 16-bit register, keeping the other bits.
+    #[inline]
+    pub fn set16(&mut self, reg: Reg16, value: u16) {
+        let r = &mut self.gpr[usize::from(reg.id())];
+        *r = *r & !0xFFFF | u64::from(value);
+    }
+
+    /// Returns an 8-bit register; AH–BH are bits 8–15 of RAX–RBX.
+    #[inline]
+    pub fn get8(&self, reg: Reg8) -> u8 {
+        let (index, shift) = byte_slot(reg);
+        (self.gpr[index] >> shift) as u8
+    }
+
+    /// Sets an 8-bit register, keeping the other bits.
+    #[inline]
+    pub fn set8(&mut self, reg: Reg8, value: u8) {
+        let (index, shift) = byte_slot(reg);
+        let r = &mut self.gpr[index];
+        *r = *r & !(0xFF << shift) | u64::from(value) << shift;
+    }
+
+    /// Returns an XMM register.
+    #[inline]
+    pub fn xmm(&self, reg: XmmReg) -> u128 {
+        self.xmm[usize::from(reg.id())]
+    }
+
+    /// Sets an XMM register.
+    #[inline]
+    pub fn set_xmm(&mut self, reg: XmmReg, value: u128) {
+        self.xmm[usize::from(reg.id())] = value;
+    }
+
+    /// Returns the instruction pointer.
+    #[inline]
+    pub fn rip(&self) -> u64 {
+        self.rip
+    }
+
+    /// Sets the instruction pointer.
+    #[inline]
+    pub fn set_rip(&mut self, rip: u64) {
+        self.rip = rip;
+    }
+
+    /// Returns RFLAGS.
+    #[inline]
+    pub fn rflags(&self) -> u64 {
+        self.rflags
+    }
+
+    /// Sets RFLAGS. Bit 1 always reads as set.
+    #[inline]
+    pub fn set_rflags(&mut self, rflags: u64) {
+        self.rflags = rflags | RFLAGS_RESET;
+    }
+
+    /// Returns whether `flag` is set.
+    #[inline]
+    pub fn flag(&self, flag: Flag) -> bool {
+        self.rflags & flag.mask() != 0
+    }
+
+    /// Sets or clears `flag`.
+    #[inline]
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        if value {
+            self.rflags |= flag.mask();
+        } else {
+            self.rflags &= !flag.mask();
+        }
+    }
+
+    /// Evaluates a condition code on the flags, as `jcc` does.
+    pub fn condition(&self, cond: Condition) -> bool {
+        use Flag::*;
+        let holds = match cond.code() >> 1 {
+            0 => self.flag(Overflow),
+            1 => self.flag(Carry),
+            2 => self.flag(Zero),
+            3 => self.flag(Carry) || self.flag(Zero),
+            4 => self.flag(Sign),
+            5 => self.flag(Parity),
+            6 => self.flag(Sign) != self.flag(Overflow),
+            _ => self.flag(Zero) || self.flag(Sign) != self.flag(Overflow),
+        };
+        holds != (cond.code() & 1 != 0)
+    }
+
+    /// Returns the FS segment base, which `fs:` memory operands add.
+    #[inline]
+    pub fn fs_base(&self) -> u64 {
+        self.fs_base
+    }
+
+    /// Sets the FS segment base.
+    #[inline]
+    pub fn set_fs_base(&mut self, base: u64) {
+        self.fs_base = base;
+    }
+
+    /// Returns the GS segment base, which `gs:` memory operands add.
+    #[inline]
+    pub fn gs_base(&self) -> u64 {
+        self.gs_base
+    }
+
+    /// Sets the GS segment base.
+    #[inline]
+    pub fn set_gs_base(&mut self, base: u64) {
+        self.gs_base = base;
+    }
+}
+
+/// Returns the index of the 64-bit register holding `reg` and the bit
+/// offset of the byte within it.
+fn byte_slot(reg: Reg8) -> (usize, u32) {
+    if reg.is_high_byte() {
+        (usize::from(reg.id() - 4), 8)
+    } else {
+        (usize::from(reg.id()), 0)
+    }
+}
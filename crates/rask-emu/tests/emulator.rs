@@ -0,0 +1,409 @@
+use rask_asm::{Assembler, assemble};
+use rask_emu::{Abi, Emulator, Exception, Flag, RETURN_ADDRESS, Stop};
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, Operand};
+use rask_x86_64::registers::Reg8::{AH, AL};
+use rask_x86_64::registers::Reg16::AX;
+use rask_x86_64::registers::Reg32::EAX;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::XmmReg::{XMM1, XMM2};
+
+use Operand::{Mem, Reg};
+
+const CODE: u64 = 0x40_0000;
+
+/// Assembles `source` for `mode` and loads it at [`CODE`].
+fn emulator(mode: Mode, source: &str) -> Emulator {
+    let apx = CpuFeatures::new().with(CpuFeature::Apx);
+    let mut asm = Assembler::with_mode(mode);
+    asm.set_features(apx);
+    let object = match asm.assemble(&format!("org {CODE}\n{source}")) {
+        Ok(object) => object,
+        Err(err) => panic!("{err}"),
+    };
+    let mut emu = Emulator::with_mode(mode);
+    emu.load(CODE, &object.bytes).unwrap();
+    emu
+}
+
+/// Runs `source` as a 64-bit function with `args` and returns RAX.
+fn call(source: &str, args: &[u64]) -> u64 {
+    let mut emu = emulator(Mode::Long64, source);
+    assert_eq!(emu.call(CODE, args, 10_000).unwrap(), Stop::Returned);
+    emu.registers().get(RAX)
+}
+
+#[test]
+fn test_arithmetic_and_flags() {
+    let mut emu = emulator(
+        Mode::Long64,
+        "
+        mov eax, 0x7FFFFFFF
+        add eax, 1
+        int3
+        mov rax, -1
+        add rax, 1
+        int3
+        sub al, 1
+        int3
+        cmp eax, 0x100
+        int3
+        ",
+    );
+    emu.registers_mut().set_rip(CODE);
+    let flags = |emu: &Emulator| {
+        [Flag::Carry, Flag::Zero, Flag::Sign, Flag::Overflow].map(|flag| emu.registers().flag(flag))
+    };
+
+    emu.run(100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0x8000_0000);
+    assert_eq!(flags(&emu), [false, false, true, true]);
+    assert!(emu.registers().flag(Flag::Adjust));
+    assert!(emu.registers().flag(Flag::Parity));
+
+    emu.run(100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0);
+    assert_eq!(flags(&emu), [true, true, false, false]);
+
+    emu.run(100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0xFF);
+    assert_eq!(flags(&emu), [true, false, true, false]);
+
+    emu.run(100).unwrap();
+    assert_eq!(flags(&emu), [true, false, true, false]);
+    assert_eq!(emu.registers().rflags() & 0x2, 0x2);
+}
+
+#[test]
+fn test_wide_arithmetic() {
+    // A 128-bit add of (rdi:rsi) and (rdx:rcx), high half in rdx.
+    let source = "
+        mov rax, rsi
+        add rax, rcx
+        adc rdx, rdi
+        ret
+    ";
+    let mut emu = emulator(Mode::Long64, source);
+    emu.call(CODE, &[1, u64::MAX, 2, 1], 100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0);
+    assert_eq!(emu.registers().get(RDX), 4);
+
+    // ... and a 128-bit subtract with sbb.
+    let source = "
+        mov rax, rdi
+        sub rax, rdx
+        sbb rsi, rcx
+        mov rdx, rsi
+        ret
+    ";
+    let mut emu = emulator(Mode::Long64, source);
+    emu.call(CODE, &[0, 5, 1, 0], 100).unwrap();
+    assert_eq!(emu.registers().get(RAX), u64::MAX);
+    assert_eq!(emu.registers().get(RDX), 4);
+
+    // APX three-operand forms leave their sources alone.
+    assert_eq!(
+        call("add rax, rdi, rsi\nsub rax, rax, 1\nret", &[40, 3]),
+        42
+    );
+}
+
+#[test]
+fn test_register_widths() {
+    let mut emu = emulator(
+        Mode::Long64,
+        "
+        mov rax, -1
+        mov eax, 0x12345678
+        int3
+        mov ah, 0xAB
+        int3
+        mov ax, 0xBEEF
+        inc al
+        ",
+    );
+    emu.registers_mut().set_rip(CODE);
+    emu.run(100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0x1234_5678);
+    emu.run(100).unwrap();
+    assert_eq!(emu.registers().get8(AH), 0xAB);
+    assert_eq!(emu.registers().get8(AL), 0x78);
+    assert_eq!(emu.registers().get(RAX), 0x1234_AB78);
+    assert_eq!(emu.run(2).unwrap(), Stop::StepLimit);
+    assert_eq!(emu.registers().get16(AX), 0xBEF0);
+    assert_eq!(emu.registers().get32(EAX), 0x1234_BEF0);
+}
+
+#[test]
+fn test_conditions_and_loops() {
+    // max(a, b), signed.
+    let max = "
+        mov rax, rdi
+        cmp rdi, rsi
+        jge done
+        mov rax, rsi
+    done:
+        ret
+    ";
+    assert_eq!(call(max, &[3, 7]), 7);
+    assert_eq!(call(max, &[-3i64 as u64, -7i64 as u64]), -3i64 as u64);
+    // The same comparison unsigned.
+    let maxu = &max.replace("jge", "jae");
+    assert_eq!(call(maxu, &[-3i64 as u64, 7]), -3i64 as u64);
+
+    // fib(n) with a counted loop.
+    let fib = "
+        xor eax, eax
+        mov edx, 1
+        test_n:
+        cmp rdi, 0
+        je done
+        mov rcx, rax
+        add rcx, rdx
+        mov rax, rdx
+        mov rdx, rcx
+        dec rdi
+        jmp test_n
+    done:
+        ret
+    ";
+    assert_eq!(call(fib, &[10]), 55);
+    assert_eq!(call(fib, &[90]), 2_880_067_194_370_816_120);
+}
+
+#[test]
+fn test_memory_and_calls() {
+    // sum(ptr, len) over qwords, through a helper that loads one element.
+    let sum = "
+        xor eax, eax
+        xor ecx, ecx
+    next:
+        cmp rcx, rsi
+        je done
+        call load
+        add rax, rdx
+        inc rcx
+        jmp next
+    done:
+        ret
+    load:
+        mov rdx, [rdi + rcx*8]
+        ret
+    ";
+    let mut emu = emulator(Mode::Long64, sum);
+    let data: Vec<u8> = (1..=10u64).flat_map(|v| v.to_le_bytes()).collect();
+    emu.load(0x10_0000, &data).unwrap();
+    assert_eq!(
+        emu.call(CODE, &[0x10_0000, 10], 1000).unwrap(),
+        Stop::Returned
+    );
+    assert_eq!(emu.registers().get(RAX), 55);
+    assert_eq!(emu.registers().get(RSP), 0x7FFF_0000);
+
+    // An absolute load, an indirect call through it, and a store.
+    let mut emu = emulator(
+        Mode::Long64,
+        "
+        mov rax, [table]
+        call rax
+        mov [rdi], rax
+        ret
+    seven:
+        mov eax, 7
+        ret
+    table:
+        dq seven
+        ",
+    );
+    emu.load(0x20_0000, &[0; 8]).unwrap();
+    emu.call(CODE, &[0x20_0000], 100).unwrap();
+    assert_eq!(emu.memory().read_u64(0x20_0000).unwrap(), 7);
+
+    // FS-relative loads add the FS base.
+    let mut emu = emulator(Mode::Long64, "mov rax, fs:[8]\nret");
+    emu.registers_mut().set_fs_base(0x30_0000);
+    emu.load(0x30_0008, &0x1234u64.to_le_bytes()).unwrap();
+    emu.call(CODE, &[], 100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0x1234);
+}
+
+#[test]
+fn test_faults() {
+    // A store to unmapped memory stops before it runs.
+    let mut emu = emulator(Mode::Long64, "mov eax, 1\nmov [rdi], rax\nret");
+    let stop = emu.call(CODE, &[0xDEAD_0000], 100).unwrap();
+    assert_eq!(
+        stop,
+        Stop::Exception(Exception::PageFault {
+            address: 0xDEAD_0000,
+            write: true
+        })
+    );
+    assert_eq!(emu.registers().rip(), CODE + 5);
+    assert_eq!(emu.registers().get(RAX), 1);
+
+    // Running off the end of the code faults on the fetch.
+    let mut emu = emulator(Mode::Long64, "nop");
+    emu.registers_mut().set_rip(CODE + 0xFFF);
+    assert_eq!(
+        emu.run(10).unwrap(),
+        Stop::Exception(Exception::PageFault {
+            address: CODE + 0x1000,
+            write: false
+        })
+    );
+
+    // Bytes that are no instruction: LOCK on an instruction without memory.
+    let mut emu = Emulator::new();
+    emu.load(0, &[0xF0, 0x90]).unwrap();
+    assert_eq!(
+        emu.run(10).unwrap(),
+        Stop::Exception(Exception::InvalidOpcode)
+    );
+
+    // An instruction the emulator does not implement is an error.
+    let mut emu = emulator(Mode::Long64, "aesenc xmm1, xmm2");
+    emu.registers_mut().set_rip(CODE);
+    assert!(emu.step().is_err());
+    assert_eq!(emu.registers().rip(), CODE);
+}
+
+#[test]
+fn test_breakpoints_and_steps() {
+    let mut emu = emulator(
+        Mode::Long64,
+        "
+        mov ecx, 5
+    top:
+        add eax, ecx
+        dec ecx
+        jnz top
+        ret
+        ",
+    );
+    let top = CODE + 5;
+    assert!(emu.add_breakpoint(top));
+    assert!(!emu.add_breakpoint(top));
+    let mut passes = 0;
+    let mut stop = emu.call(CODE, &[], 100).unwrap();
+    while stop == Stop::Breakpoint(top) {
+        passes += 1;
+        stop = emu.run(100).unwrap();
+    }
+    assert_eq!((stop, passes), (Stop::Returned, 5));
+    assert_eq!(emu.registers().get(RAX), 15);
+
+    assert!(emu.remove_breakpoint(top));
+    emu.registers_mut().set_rip(CODE);
+    assert_eq!(emu.step().unwrap(), None);
+    assert_eq!(emu.registers().rip(), top);
+    assert_eq!(emu.fetch().unwrap().unwrap().len(), 2);
+}
+
+#[test]
+fn test_other_modes_and_abis() {
+    // cdecl in 32-bit mode: arguments on the stack.
+    let mut emu = emulator(
+        Mode::Protected32,
+        "
+        mov eax, [esp + 4]
+        sub eax, [esp + 8]
+        ret
+        ",
+    );
+    assert_eq!(emu.call(CODE, &[50, 8], 100).unwrap(), Stop::Returned);
+    assert_eq!(emu.registers().get32(EAX), 42);
+    assert_eq!(emu.registers().get(RSP), 0x7FFF_0000);
+
+    // pusha/popa in 16-bit mode.
+    let mut emu = Emulator::with_mode(Mode::Real16);
+    let code = Assembler::with_mode(Mode::Real16)
+        .assemble("mov ax, 1\npusha\nmov ax, 2\npopa\nret")
+        .unwrap();
+    emu.load(0x7C00, &code.bytes).unwrap();
+    assert_eq!(emu.call(0x7C00, &[], 100).unwrap(), Stop::Returned);
+    assert_eq!(emu.registers().get16(AX), 1);
+    let memory = emu.memory();
+    assert_eq!(memory.read_u16(0x9000 - 2).unwrap(), RETURN_ADDRESS as u16);
+    // The frame below it: AX first, the original SP fifth.
+    assert_eq!(memory.read_u16(0x9000 - 4).unwrap(), 1);
+    assert_eq!(memory.read_u16(0x9000 - 12).unwrap(), 0x9000 - 2);
+
+    // Windows x64: RCX, RDX, R8, R9, then the stack above the shadow space.
+    let mut emu = emulator(
+        Mode::Long64,
+        "
+        mov rax, rcx
+        add rax, r9
+        add rax, [rsp + 40]
+        ret
+        ",
+    );
+    emu.set_abi(Abi::Windows);
+    emu.call(CODE, &[1, 0, 0, 20, 300], 100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 321);
+}
+
+#[test]
+fn test_apx_and_extensions() {
+    // push2/pop2 swap two registers and need a 16-byte aligned stack.
+    let mut emu = emulator(Mode::Long64, "push2 rdi, rsi\npop2 rdi, rsi\nret");
+    emu.registers_mut().set(RSP, 0x7FFF_0000 - 8);
+    emu.call(CODE, &[1, 2], 100).unwrap();
+    assert_eq!(emu.registers().get(RDI), 2);
+    assert_eq!(emu.registers().get(RSI), 1);
+    emu.registers_mut().set(RSP, 0x7FFF_0000);
+    let stop = emu.call(CODE, &[1, 2], 100).unwrap();
+    assert_eq!(stop, Stop::Exception(Exception::GeneralProtection));
+
+    // CRC-32C of "123456789".
+    let mut emu = emulator(
+        Mode::Long64,
+        "
+        mov eax, -1
+        crc32 rax, qword ptr [rdi]
+        crc32 eax, byte ptr [rdi + 8]
+        xor eax, -1
+        ret
+        ",
+    );
+    emu.load(0x10_0000, b"123456789").unwrap();
+    emu.call(CODE, &[0x10_0000], 100).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0xE306_9283);
+
+    // Carry-less multiplication of the high quadwords.
+    let mut emu = emulator(
+        Mode::Long64,
+        "pclmulqdq xmm1, xmm2, 0x11\nwrfsbase rdi\nret",
+    );
+    emu.registers_mut().set_xmm(XMM1, 0b1011 << 64);
+    emu.registers_mut().set_xmm(XMM2, 0b11 << 64);
+    emu.call(CODE, &[0x1000], 100).unwrap();
+    assert_eq!(emu.registers().xmm(XMM1), 0b11101);
+    assert_eq!(emu.registers().fs_base(), 0x1000);
+}
+
+#[test]
+fn test_generated_code() {
+    // Code from the encoder, with a RIP-relative load of the constant
+    // after it.
+    let mut enc = Encoder::new();
+    let mut asm = enc.panicking();
+    asm.mov(Reg(RAX), Mem(MemOperand::rip(4)));
+    asm.add(RAX, RDI);
+    asm.ret();
+    asm.emit_all(&0x1122_3344_5566_7788u64.to_le_bytes());
+    let mut emu = Emulator::new();
+    emu.load(CODE, enc.bytes()).unwrap();
+    emu.call(CODE, &[1], 10).unwrap();
+    assert_eq!(emu.registers().get(RAX), 0x1122_3344_5566_7789);
+
+    // The same function assembled from text, at address 0.
+    let object = assemble("mov rax, [value]\nadd rax, rdi\nret\nvalue: dq 41").unwrap();
+    let mut emu = Emulator::new();
+    emu.load(0, &object.bytes).unwrap();
+    emu.call(0, &[1], 10).unwrap();
+    assert_eq!(emu.registers().get(RAX), 42);
+}
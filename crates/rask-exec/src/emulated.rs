@@ -0,0 +1,115 @@
+//! Emulated runs in [`rask_emu`].
+
+use crate::{ARGUMENT_REGISTERS, Call, Crash, Layout, Outcome, Returned};
+use rask_common::{RaskResult, align_to};
+use rask_emu::{Emulator, Exception, PAGE_SIZE, Stop};
+use rask_x86_64::registers::Reg64;
+
+/// Where [`emulate`] places the code.
+const CODE: u64 = 0x10_0000;
+
+/// Where [`emulate`] places the memory buffer.
+const MEMORY: u64 = 0x20_0000;
+
+/// RSP at the call in [`emulate`].
+const STACK: u64 = 0x7FFE_F000;
+
+/// How much stack is mapped below RSP at the call.
+const STACK_SIZE: u64 = 0x10000;
+
+/// The Linux signal numbers of the faults the emulator reports.
+const SIGILL: i32 = 4;
+const SIGTRAP: i32 = 5;
+const SIGSEGV: i32 = 11;
+
+/// Runs `code` in the emulator as [`run`](crate::run) runs it natively.
+///
+/// The code is placed at 0x100000, the memory buffer at 0x200000 and the
+/// stack just below 0x7FFF0000. Instructions after a number of steps set
+/// with [`Call::max_steps`] end the run with [`Outcome::Timeout`]. Faults
+/// become the signals Linux would send: page and general-protection
+/// faults `SIGSEGV`, invalid opcodes `SIGILL` and `int3` `SIGTRAP`.
+///
+/// ```
+/// use rask_exec::{Call, Outcome};
+///
+/// let code = rask_asm::assemble("mov rax, [rdi]\nret")?.bytes;
+/// let outcome = rask_exec::emulate(&code, &Call::new().arg(0))?;
+/// let Outcome::Crashed(crash) = outcome else { panic!() };
+/// assert_eq!(crash.signal_name(), "SIGSEGV");
+/// assert_eq!(crash.offset, Some(0));
+/// # Ok::<(), rask_exec::RaskError>(())
+/// ```
+///
+/// Returns an error if the call sets RSP or an APX register, or if the
+/// code runs an instruction the emulator does not implement.
+pub fn emulate(code: &[u8], call: &Call) -> RaskResult<Outcome> {
+    call.validate()?;
+    let memory = if call.memory.is_some() { MEMORY } else { 0 };
+    let layout = Layout {
+        code: CODE,
+        memory,
+        stack: STACK,
+    };
+    run(code, call, layout)
+}
+
+/// Runs `code` with the code, memory buffer and stack at `layout`.
+pub(crate) fn run(code: &[u8], call: &Call, layout: Layout) -> RaskResult<Outcome> {
+    let mut emu = Emulator::new();
+    emu.load(layout.code, code)?;
+    let memory = call.memory.as_deref().unwrap_or_default();
+    if call.memory.is_some() {
+        let len = align_to(memory.len().max(1), PAGE_SIZE as usize) as u64;
+        emu.memory_mut().map(layout.memory, len);
+        emu.memory_mut().write(layout.memory, memory)?;
+    }
+
+    let stack_args = call.stack_args(layout.memory);
+    let top = layout.stack + 8 * stack_args.len() as u64;
+    let bottom = (layout.stack & !(PAGE_SIZE - 1)) - STACK_SIZE;
+    emu.memory_mut().map(bottom, top + 8 - bottom);
+
+    let mut entry = call.registers_with(layout.memory);
+    entry[usize::from(Reg64::RSP.id())] = top;
+    for &reg in &Reg64::ALL[..16] {
+        emu.registers_mut().set(reg, entry[usize::from(reg.id())]);
+    }
+    let mut args: Vec<u64> = ARGUMENT_REGISTERS
+        .iter()
+        .map(|reg| entry[usize::from(reg.id())])
+        .collect();
+    args.extend(stack_args);
+
+    let stop = emu.call(layout.code, &args, call.max_steps)?;
+    let rip = emu.registers().rip();
+    let crash = |signal, rip: u64, address| {
+        let offset = rip
+            .checked_sub(layout.code)
+            .filter(|&offset| offset < code.len() as u64);
+        Outcome::Crashed(Crash {
+            signal,
+            rip: Some(rip),
+            offset: offset.map(|offset| offset as usize),
+            address: Some(address),
+        })
+    };
+    Ok(match stop {
+        Stop::Returned => {
+            let mut bytes = vec![0; memory.len()];
+            emu.memory().read(layout.memory, &mut bytes)?;
+            Outcome::Returned(Box::new(Returned {
+                registers: std::array::from_fn(|id| emu.registers().get(Reg64::ALL[id])),
+                entry,
+                rflags: emu.registers().rflags(),
+                memory: bytes,
+            }))
+        }
+        Stop::Breakpoint(address) => crash(SIGTRAP, address, 0),
+        Stop::Int3(_) => crash(SIGTRAP, rip, 0),
+        Stop::Exception(Exception::PageFault { address, .. }) => crash(SIGSEGV, rip, address),
+        Stop::Exception(Exception::GeneralProtection) => crash(SIGSEGV, rip, 0),
+        Stop::Exception(Exception::InvalidOpcode) => crash(SIGILL, rip, rip),
+        Stop::StepLimit => Outcome::Timeout,
+    })
+}
@@ -0,0 +1,478 @@
+//! Host execution harness for Rask.
+//!
+//! Runs generated x86-64 code on the machine itself, so tests can check
+//! what it computes rather than which bytes it is. Each [`run`] forks a
+//! child process, maps the code into executable memory there and calls it
+//! with the System V ABI; whatever the code does — return, fault, trap,
+//! exit or loop forever — the calling process survives and gets an
+//! [`Outcome`]:
+//!
+//! ```
+//! use rask_exec::{Call, Outcome};
+//! use rask_x86_64::registers::Reg64::RAX;
+//!
+//! // fn add(a, b) -> a + b
+//! let code = rask_asm::assemble("
+//!     mov rax, rdi
+//!     add rax, rsi
+//!     ret
+//! ")?.bytes;
+//! # if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+//! let Outcome::Returned(out) = rask_exec::run(&code, &Call::new().arg(40).arg(2))? else {
+//!     panic!("the call did not return");
+//! };
+//! assert_eq!(out.get(RAX), 42);
+//! assert!(out.clobbered().is_empty());
+//! # }
+//! # Ok::<(), rask_exec::RaskError>(())
+//! ```
+//!
+//! ### Inputs
+//! A [`Call`] gives the integer arguments, passed in RDI, RSI, RDX, RCX,
+//! R8 and R9 and then on the stack, values for any other general-purpose
+//! register, and a memory buffer whose address is passed as an argument.
+//! Registers the call leaves open hold a distinct value each (see
+//! [`Call::initial`]), so code that reads one by mistake does not see a
+//! convenient zero, and [`Returned::clobbered`] can tell which
+//! callee-saved registers the code failed to preserve.
+//!
+//! ### Outputs
+//! A call that returns gives every general-purpose register, RFLAGS and
+//! the memory buffer as they are after the return. A crash gives the
+//! signal, the instruction pointer and the faulting address.
+//!
+//! ### Against the emulator
+//! [`emulate`] runs the same call in [`rask_emu`], and [`compare`] runs it
+//! both ways — the emulator with the addresses the native run used — and
+//! fails if the outcomes differ.
+//!
+//! Native execution needs Linux on x86-64; elsewhere [`run`] and
+//! [`compare`] return [`RaskError::UnsupportedFeature`]. [`emulate`] works
+//! on every host.
+
+mod emulated;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod native;
+
+pub use emulated::emulate;
+pub use rask_common::{RaskError, RaskResult};
+
+use rask_x86_64::registers::Reg64;
+use std::{fmt, time::Duration};
+
+/// The registers System V passes the first integer arguments in.
+const ARGUMENT_REGISTERS: [Reg64; 6] = [
+    Reg64::RDI,
+    Reg64::RSI,
+    Reg64::RDX,
+    Reg64::RCX,
+    Reg64::R8,
+    Reg64::R9,
+];
+
+/// The registers System V requires a function to preserve, besides RSP.
+pub const CALLEE_SAVED: [Reg64; 6] = [
+    Reg64::RBX,
+    Reg64::RBP,
+    Reg64::R12,
+    Reg64::R13,
+    Reg64::R14,
+    Reg64::R15,
+];
+
+/// The mask of the status flags in RFLAGS: CF, PF, AF, ZF, SF and OF.
+pub const STATUS_FLAGS: u64 = 0x8D5;
+
+/// An integer argument: a value, or the address of the memory buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arg {
+    Value(u64),
+    Memory,
+}
+
+/// The inputs of a call: arguments, registers and memory.
+///
+/// ```
+/// use rask_exec::Call;
+/// use rask_x86_64::registers::Reg64::{R10, RDI, RSI};
+/// use std::time::Duration;
+///
+/// let call = Call::new()
+///     .arg(1)
+///     .memory(&[0; 16])
+///     .register(R10, 7)
+///     .timeout(Duration::from_millis(100));
+/// assert_eq!(call.registers()[RDI.id() as usize], 1);
+/// assert_eq!(call.registers()[R10.id() as usize], 7);
+/// // The buffer's address is only known when the call runs.
+/// assert_eq!(call.registers()[RSI.id() as usize], Call::initial(RSI));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    args: Vec<Arg>,
+    registers: Vec<(Reg64, u64)>,
+    memory: Option<Vec<u8>>,
+    timeout: Duration,
+    max_steps: u64,
+}
+
+impl Default for Call {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Call {
+    /// Returns a call without arguments, a 5 second timeout for native
+    /// runs and a limit of ten million instructions for emulated ones.
+    pub fn new() -> Self {
+        Self {
+            args: Vec::new(),
+            registers: Vec::new(),
+            memory: None,
+            timeout: Duration::from_secs(5),
+            max_steps: 10_000_000,
+        }
+    }
+
+    /// Adds an integer argument.
+    pub fn arg(mut self, value: u64) -> Self {
+        self.args.push(Arg::Value(value));
+        self
+    }
+
+    /// Adds integer arguments.
+    pub fn args(mut self, values: &[u64]) -> Self {
+        self.args.extend(values.iter().map(|&v| Arg::Value(v)));
+        self
+    }
+
+    /// Sets a memory buffer holding `bytes` and adds its address as the
+    /// next argument. The buffer is read back after a call that returns;
+    /// see [`Returned::memory`].
+    ///
+    /// Panics if the call already has a buffer.
+    pub fn memory(mut self, bytes: &[u8]) -> Self {
+        assert!(
+            self.memory.is_none(),
+            "a call has at most one memory buffer"
+        );
+        self.memory = Some(bytes.to_vec());
+        self.args.push(Arg::Memory);
+        self
+    }
+
+    /// Sets `reg` to `value` on entry, overriding an argument passed in it.
+    /// RSP cannot be set; [`run`] rejects a call that tries.
+    pub fn register(mut self, reg: Reg64, value: u64) -> Self {
+        self.registers.push((reg, value));
+        self
+    }
+
+    /// Sets how long a native run may take before the child is killed and
+    /// the outcome is [`Outcome::Timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many instructions an emulated run may execute before the
+    /// outcome is [`Outcome::Timeout`].
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Returns the value a register holds on entry when the call does not
+    /// set it: `0x5EED_0000_0000_0000` plus the register's ID.
+    pub fn initial(reg: Reg64) -> u64 {
+        0x5EED_0000_0000_0000 + u64::from(reg.id())
+    }
+
+    /// Returns the general-purpose registers (by ID, RAX to R15) on entry,
+    /// with the memory buffer's argument register left at its initial
+    /// value. RSP is not set by the call and holds its initial value too.
+    pub fn registers(&self) -> [u64; 16] {
+        self.registers_with(0)
+    }
+
+    /// Returns the registers on entry with the buffer at `memory`.
+    fn registers_with(&self, memory: u64) -> [u64; 16] {
+        let mut values: [u64; 16] = std::array::from_fn(|id| Self::initial(Reg64::ALL[id]));
+        for (&reg, &arg) in ARGUMENT_REGISTERS.iter().zip(&self.args) {
+            match arg {
+                Arg::Value(value) => values[usize::from(reg.id())] = value,
+                Arg::Memory if memory != 0 => values[usize::from(reg.id())] = memory,
+                Arg::Memory => {}
+            }
+        }
+        for &(reg, value) in &self.registers {
+            if reg.id() < 16 {
+                values[usize::from(reg.id())] = value;
+            }
+        }
+        values
+    }
+
+    /// Returns the arguments passed on the stack, with the buffer at
+    /// `memory`.
+    fn stack_args(&self, memory: u64) -> Vec<u64> {
+        let args = self.args.iter().skip(ARGUMENT_REGISTERS.len());
+        args.map(|&arg| match arg {
+            Arg::Value(value) => value,
+            Arg::Memory => memory,
+        })
+        .collect()
+    }
+
+    /// Checks the registers the call sets.
+    fn validate(&self) -> RaskResult<()> {
+        for &(reg, _) in &self.registers {
+            if reg == Reg64::RSP {
+                return Err(RaskError::Other(
+                    "the stack pointer of a call cannot be set".to_string(),
+                ));
+            }
+            if reg.id() >= 16 {
+                return Err(RaskError::UnsupportedFeature {
+                    what: format!("setting {reg}"),
+                    feature: "APX".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a run placed the code, the memory buffer and the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    /// The address of the first byte of the code.
+    code: u64,
+    /// The address of the memory buffer, 0 without one.
+    memory: u64,
+    /// RSP at the `call`, after the stack arguments were pushed.
+    stack: u64,
+}
+
+/// How a call ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The code returned.
+    Returned(Box<Returned>),
+    /// The code was stopped by a signal.
+    Crashed(Crash),
+    /// The code ended the process with the exit status, through the `exit`
+    /// system call.
+    Exited(i32),
+    /// The code ran past the timeout or step limit.
+    Timeout,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Returned(out) => write!(f, "returned {:#x}", out.get(Reg64::RAX)),
+            Self::Crashed(crash) => write!(f, "{crash}"),
+            Self::Exited(status) => write!(f, "exited with status {status}"),
+            Self::Timeout => write!(f, "timed out"),
+        }
+    }
+}
+
+/// The state after a call returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Returned {
+    registers: [u64; 16],
+    entry: [u64; 16],
+    rflags: u64,
+    memory: Vec<u8>,
+}
+
+impl Returned {
+    /// Returns the value of `reg` after the return. RSP is read after the
+    /// caller removed the stack arguments, so a balanced function leaves
+    /// it as it was before the call.
+    ///
+    /// Panics for the APX registers R16–R31.
+    pub fn get(&self, reg: Reg64) -> u64 {
+        self.registers[usize::from(reg.id())]
+    }
+
+    /// Returns RFLAGS after the return.
+    pub fn rflags(&self) -> u64 {
+        self.rflags
+    }
+
+    /// Returns the memory buffer after the return, empty if the call has
+    /// none.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Returns the callee-saved registers, RSP included, whose value the
+    /// code changed.
+    pub fn clobbered(&self) -> Vec<Reg64> {
+        let saved = CALLEE_SAVED.iter().chain(&[Reg64::RSP]);
+        saved
+            .filter(|reg| self.get(**reg) != self.entry[usize::from(reg.id())])
+            .copied()
+            .collect()
+    }
+
+    /// Describes how `other` differs from `self`: every register, the
+    /// status flags and the first differing byte of memory.
+    fn differences(&self, other: &Returned) -> Vec<String> {
+        let mut out = Vec::new();
+        for &reg in &Reg64::ALL[..16] {
+            let (a, b) = (self.get(reg), other.get(reg));
+            if a != b {
+                out.push(format!("{reg} is {a:#x}, not {b:#x}"));
+            }
+        }
+        let (a, b) = (self.rflags & STATUS_FLAGS, other.rflags & STATUS_FLAGS);
+        if a != b {
+            out.push(format!("the status flags are {a:#x}, not {b:#x}"));
+        }
+        if let Some(at) = self
+            .memory
+            .iter()
+            .zip(&other.memory)
+            .position(|(a, b)| a != b)
+        {
+            out.push(format!(
+                "memory byte {at} is {:#04x}, not {:#04x}",
+                self.memory[at], other.memory[at]
+            ));
+        }
+        out
+    }
+}
+
+/// A call stopped by a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crash {
+    /// The signal number, such as `SIGSEGV`.
+    pub signal: i32,
+    /// The instruction pointer when the signal arrived: the faulting
+    /// instruction for a fault, the one after `int3` for a trap. `None`
+    /// if the signal did not come from the code, such as a `SIGKILL`.
+    pub rip: Option<u64>,
+    /// The offset of `rip` in the code, if it points into it.
+    pub offset: Option<usize>,
+    /// The faulting address of a `SIGSEGV` or `SIGBUS`, the faulting
+    /// instruction of a `SIGILL`, 0 for faults without one.
+    pub address: Option<u64>,
+}
+
+impl Crash {
+    /// Returns the name of the signal, such as `"SIGSEGV"`.
+    pub fn signal_name(&self) -> String {
+        match self.signal {
+            4 => "SIGILL".to_string(),
+            5 => "SIGTRAP".to_string(),
+            6 => "SIGABRT".to_string(),
+            7 => "SIGBUS".to_string(),
+            8 => "SIGFPE".to_string(),
+            9 => "SIGKILL".to_string(),
+            11 => "SIGSEGV".to_string(),
+            31 => "SIGSYS".to_string(),
+            signal => format!("signal {signal}"),
+        }
+    }
+}
+
+impl fmt::Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.signal_name())?;
+        match (self.offset, self.rip) {
+            (Some(offset), _) => write!(f, " at offset {offset:#x}")?,
+            (None, Some(rip)) => write!(f, " at {rip:#x}")?,
+            (None, None) => {}
+        }
+        match self.address {
+            Some(address) if address != 0 && self.signal != 4 => {
+                write!(f, " accessing {address:#x}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Runs `code` natively in a child process, calling its first byte as
+/// `call` describes.
+///
+/// The code is copied into fresh pages, which are made executable and no
+/// longer writable before the call, so it must not modify itself. It may
+/// use any address relative to itself; absolute addresses into it are
+/// unknown until the run.
+///
+/// Returns an error if the call sets RSP or an APX register, if the
+/// child process cannot be started or memory cannot be mapped, or, on
+/// hosts other than Linux on x86-64, always.
+pub fn run(code: &[u8], call: &Call) -> RaskResult<Outcome> {
+    call.validate()?;
+    run_native(code, call).map(|(outcome, _)| outcome)
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn run_native(code: &[u8], call: &Call) -> RaskResult<(Outcome, Layout)> {
+    native::run(code, call)
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+fn run_native(_: &[u8], _: &Call) -> RaskResult<(Outcome, Layout)> {
+    Err(RaskError::UnsupportedFeature {
+        what: "native execution".to_string(),
+        feature: "Linux on x86-64".to_string(),
+    })
+}
+
+/// Runs `code` natively and in the emulator, and returns the native
+/// outcome if both agree.
+///
+/// The emulator places the code, the memory buffer and the stack at the
+/// addresses the native run used, so even address-dependent results
+/// compare equal. Calls that return must agree on every register, the
+/// status flags and the memory buffer; crashes on the signal and the
+/// instruction pointer.
+///
+/// ```
+/// use rask_exec::Call;
+///
+/// let code = rask_asm::assemble("
+///     mov rax, [rdi]
+///     add rax, rsi
+///     mov [rdi + 8], rax
+///     ret
+/// ")?.bytes;
+/// let call = Call::new().memory(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).arg(41);
+/// # if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+/// rask_exec::compare(&code, &call)?;
+/// # }
+/// # Ok::<(), rask_exec::RaskError>(())
+/// ```
+///
+/// Returns an error if the outcomes differ, with what differs, if the
+/// emulator does not implement an instruction the code runs, or as
+/// [`run`] does.
+pub fn compare(code: &[u8], call: &Call) -> RaskResult<Outcome> {
+    call.validate()?;
+    let (native, layout) = run_native(code, call)?;
+    let emulated = emulated::run(code, call, layout)?;
+    let differences = match (&native, &emulated) {
+        (Outcome::Returned(a), Outcome::Returned(b)) => a.differences(b),
+        (Outcome::Crashed(a), Outcome::Crashed(b)) if a.signal == b.signal && a.rip == b.rip => {
+            Vec::new()
+        }
+        (a, b) if a == b => Vec::new(),
+        (a, b) => vec![format!("the native run {a}, the emulated one {b}")],
+    };
+    if differences.is_empty() {
+        return Ok(native);
+    }
+    Err(RaskError::Other(format!(
+        "native and emulated runs differ: {}",
+        differences.join(", ")
+    )))
+}
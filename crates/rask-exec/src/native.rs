@@ -0,0 +1,354 @@
+//! Native runs in a forked child process.
+//!
+//! The parent maps a shared region holding a [`Block`] and the memory
+//! buffer, and builds an image: a trampoline followed by the code. The
+//! child maps the image executable and calls the trampoline, which sets
+//! the registers, calls the code and stores the registers it returns with
+//! in the block. A signal handler in the child records where a crash
+//! happened before letting the signal kill it.
+
+use crate::{Call, Crash, Layout, Outcome, Returned};
+use rask_common::{RaskResult, align_to};
+use rask_x86_64::{
+    encoder::Encoder,
+    operand::{MemOperand, Operand},
+    registers::Reg64::{self, *},
+};
+use std::{
+    mem::offset_of,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+const PAGE: usize = 0x1000;
+
+/// The signals a crash is recorded for.
+const SIGNALS: [i32; 5] = [
+    libc::SIGSEGV,
+    libc::SIGBUS,
+    libc::SIGILL,
+    libc::SIGFPE,
+    libc::SIGTRAP,
+];
+
+/// The size of the child's signal stack.
+const SIGNAL_STACK: usize = 0x10000;
+
+/// `Block::status` once the code returned.
+const RETURNED: u64 = 1;
+
+/// What the child reports to the parent, at the start of the shared region.
+#[repr(C)]
+struct Block {
+    status: u64,
+    /// The address the code was mapped at.
+    code: u64,
+    /// RSP at the call of the code.
+    stack: u64,
+    /// The trampoline caller's RBX, RBP, R12–R15 and RSP.
+    saved: [u64; 7],
+    /// The registers after the return, by ID.
+    registers: [u64; 16],
+    rflags: u64,
+    signal: u64,
+    fault_address: u64,
+    fault_rip: u64,
+}
+
+/// The block of the child's signal handler.
+static BLOCK: AtomicPtr<Block> = AtomicPtr::new(ptr::null_mut());
+
+/// A shared anonymous mapping, unmapped on drop.
+struct Shared {
+    base: *mut u8,
+    len: usize,
+}
+
+impl Shared {
+    fn new(len: usize) -> RaskResult<Self> {
+        // SAFETY: a fresh anonymous mapping aliases nothing.
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            base: base.cast(),
+            len,
+        })
+    }
+
+    fn block(&self) -> *mut Block {
+        self.base.cast()
+    }
+
+    /// Returns the memory buffer, which starts on the second page.
+    fn memory(&self) -> *mut u8 {
+        self.base.wrapping_add(PAGE)
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        // SAFETY: the mapping is ours and nothing borrows from it.
+        unsafe { libc::munmap(self.base.cast(), self.len) };
+    }
+}
+
+pub(crate) fn run(code: &[u8], call: &Call) -> RaskResult<(Outcome, Layout)> {
+    let memory = call.memory.as_deref().unwrap_or_default();
+    let shared = Shared::new(PAGE + align_to(memory.len().max(1), PAGE))?;
+    // SAFETY: the buffer has room for `memory` and no child exists yet.
+    unsafe { ptr::copy_nonoverlapping(memory.as_ptr(), shared.memory(), memory.len()) };
+    let memory_address = if call.memory.is_some() {
+        shared.memory() as u64
+    } else {
+        0
+    };
+
+    let stack_args = call.stack_args(memory_address);
+    let mut image = trampoline(
+        shared.block() as u64,
+        &call.registers_with(memory_address),
+        &stack_args,
+    )?;
+    let entry = image.len();
+    image.extend_from_slice(code);
+
+    // SAFETY: the child only makes async-signal-safe calls before `_exit`.
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    if pid == 0 {
+        // SAFETY: the block is mapped, and `image` starts with a trampoline
+        // that calls `code` and returns.
+        unsafe { child(shared.block(), &image, entry) }
+    }
+
+    let status = wait(pid, call.timeout)?;
+    // SAFETY: the child has exited, so nothing writes the block anymore.
+    let block = unsafe { ptr::read_volatile(shared.block()) };
+    let n = stack_args.len() as u64;
+    let layout = Layout {
+        code: block.code,
+        memory: memory_address,
+        stack: block.stack,
+    };
+
+    let outcome = match status {
+        None => Outcome::Timeout,
+        Some(status) if libc::WIFSIGNALED(status) => {
+            let signal = libc::WTERMSIG(status);
+            let recorded = block.signal == signal as u64;
+            let rip = recorded.then_some(block.fault_rip);
+            let offset = rip
+                .and_then(|rip| rip.checked_sub(block.code))
+                .filter(|&offset| offset < code.len() as u64)
+                .map(|offset| offset as usize);
+            Outcome::Crashed(Crash {
+                signal,
+                rip,
+                offset,
+                address: recorded.then_some(block.fault_address),
+            })
+        }
+        Some(status) if block.status != RETURNED => Outcome::Exited(libc::WEXITSTATUS(status)),
+        Some(_) => {
+            let mut registers = block.registers;
+            // The trampoline read RSP after pushing RAX and RFLAGS.
+            registers[usize::from(RSP.id())] += 16 + 8 * n;
+            let mut entry = call.registers_with(memory_address);
+            entry[usize::from(RSP.id())] = block.stack + 8 * n;
+            let mut bytes = vec![0; memory.len()];
+            // SAFETY: the buffer holds `memory.len()` bytes.
+            unsafe { ptr::copy_nonoverlapping(shared.memory(), bytes.as_mut_ptr(), bytes.len()) };
+            Outcome::Returned(Box::new(Returned {
+                registers,
+                entry,
+                rflags: block.rflags,
+                memory: bytes,
+            }))
+        }
+    };
+    Ok((outcome, layout))
+}
+
+/// Waits for the child, killing it after `timeout`. Returns its wait
+/// status, or `None` if it was killed.
+fn wait(pid: libc::pid_t, timeout: Duration) -> RaskResult<Option<i32>> {
+    let deadline = Instant::now() + timeout;
+    let mut status = 0;
+    loop {
+        // SAFETY: `status` is a valid pointer.
+        match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+            0 if Instant::now() >= deadline => break,
+            0 => thread::sleep(Duration::from_millis(1)),
+            -1 => return Err(std::io::Error::last_os_error().into()),
+            _ => return Ok(Some(status)),
+        }
+    }
+    // SAFETY: `pid` is our child, which has not been reaped.
+    unsafe {
+        libc::kill(pid, libc::SIGKILL);
+        libc::waitpid(pid, &mut status, 0);
+    }
+    Ok(None)
+}
+
+/// Returns the trampoline: it sets `registers`, calls the code that
+/// follows it with `stack_args`, stores the registers and RFLAGS the code
+/// returns with in the block at `block`, and returns to its caller with
+/// the callee-saved registers restored.
+fn trampoline(block: u64, registers: &[u64; 16], stack_args: &[u64]) -> RaskResult<Vec<u8>> {
+    let field = |offset: usize| Operand::Mem(MemOperand::new(RAX, offset as i32));
+    let saved = |i: usize| field(offset_of!(Block, saved) + 8 * i);
+    let register = |reg: Reg64| field(offset_of!(Block, registers) + 8 * usize::from(reg.id()));
+    let imm = |value: u64| Operand::Imm(value as i64);
+    let callee_saved = [RBX, RBP, R12, R13, R14, R15, RSP];
+
+    let mut enc = Encoder::new();
+    enc.mov(Operand::Reg(RAX), imm(block))?;
+    for (i, &reg) in callee_saved.iter().enumerate() {
+        enc.mov(saved(i), Operand::Reg(reg))?;
+    }
+
+    // The trampoline was called with RSP 8 modulo 16; the code must be
+    // called with it 0 modulo 16.
+    let n = stack_args.len();
+    let frame = 8 * n + if n.is_multiple_of(2) { 8 } else { 0 };
+    enc.emit_instruction("sub", &[Operand::Reg(RSP), imm(frame as u64)])?;
+    enc.mov(field(offset_of!(Block, stack)), Operand::Reg(RSP))?;
+    for (i, &value) in stack_args.iter().enumerate() {
+        enc.mov(Operand::Reg(RCX), imm(value))?;
+        enc.mov(
+            Operand::Mem(MemOperand::new(RSP, 8 * i as i32)),
+            Operand::Reg(RCX),
+        )?;
+    }
+    // push rcx; popfq: clear the status flags and DF, as the emulator does.
+    enc.mov(Operand::Reg(RCX), imm(rask_emu::RFLAGS_RESET))?;
+    enc.emit_all(&[0x51, 0x9D])?;
+    for &reg in Reg64::ALL[..16].iter().rev() {
+        if reg != RSP {
+            enc.mov(Operand::Reg(reg), imm(registers[usize::from(reg.id())]))?;
+        }
+    }
+
+    let code = enc.create_label();
+    enc.call(Operand::Label(code))?;
+    // push rax; pushfq
+    enc.emit_all(&[0x50, 0x9C])?;
+    enc.mov(Operand::Reg(RAX), imm(block))?;
+    for &reg in &Reg64::ALL[1..16] {
+        enc.mov(register(reg), Operand::Reg(reg))?;
+    }
+    let top = |offset: i32| Operand::Mem(MemOperand::new(RSP, offset));
+    enc.mov(Operand::Reg(RCX), top(0))?;
+    enc.mov(field(offset_of!(Block, rflags)), Operand::Reg(RCX))?;
+    enc.mov(Operand::Reg(RCX), top(8))?;
+    enc.mov(register(RAX), Operand::Reg(RCX))?;
+
+    // Restore RSP first, in case the code left it unbalanced.
+    for (i, &reg) in callee_saved.iter().enumerate().rev() {
+        enc.mov(Operand::Reg(reg), saved(i))?;
+    }
+    enc.ret()?;
+    enc.bind_label(code)?;
+    enc.finish()
+}
+
+/// Runs in the child: maps `image`, calls it at offset 0 and exits.
+///
+/// # Safety
+/// `block` must point to the shared block and `image` must start with a
+/// trampoline for it, followed by the code at `entry`.
+unsafe fn child(block: *mut Block, image: &[u8], entry: usize) -> ! {
+    // SAFETY: only async-signal-safe calls on memory this process owns.
+    unsafe {
+        let no_core = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        libc::setrlimit(libc::RLIMIT_CORE, &no_core);
+
+        BLOCK.store(block, Ordering::SeqCst);
+        let stack = map(SIGNAL_STACK, libc::PROT_READ | libc::PROT_WRITE);
+        let alt = libc::stack_t {
+            ss_sp: stack.cast(),
+            ss_flags: 0,
+            ss_size: SIGNAL_STACK,
+        };
+        libc::sigaltstack(&alt, ptr::null_mut());
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_signal as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+        libc::sigemptyset(&mut action.sa_mask);
+        for signal in SIGNALS {
+            libc::sigaction(signal, &action, ptr::null_mut());
+        }
+
+        let len = align_to(image.len(), PAGE);
+        let text = map(len, libc::PROT_READ | libc::PROT_WRITE);
+        ptr::copy_nonoverlapping(image.as_ptr(), text, image.len());
+        if libc::mprotect(text.cast(), len, libc::PROT_READ | libc::PROT_EXEC) != 0 {
+            libc::_exit(127);
+        }
+        ptr::write_volatile(&raw mut (*block).code, text as u64 + entry as u64);
+
+        let trampoline: extern "sysv64" fn() = std::mem::transmute(text);
+        trampoline();
+        ptr::write_volatile(&raw mut (*block).status, RETURNED);
+        libc::_exit(0)
+    }
+}
+
+/// Maps `len` bytes of private memory, exiting the child on failure.
+///
+/// # Safety
+/// Must only be called in the child.
+unsafe fn map(len: usize, prot: i32) -> *mut u8 {
+    // SAFETY: a fresh anonymous mapping aliases nothing.
+    unsafe {
+        let base = libc::mmap(
+            ptr::null_mut(),
+            len,
+            prot,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if base == libc::MAP_FAILED {
+            libc::_exit(127);
+        }
+        base.cast()
+    }
+}
+
+/// Records a crash in the block, then lets the signal kill the child.
+extern "C" fn on_signal(signal: i32, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    // SAFETY: the kernel passes a valid `siginfo_t` and `ucontext_t` to an
+    // `SA_SIGINFO` handler, and the block was set before it was installed.
+    unsafe {
+        let block = BLOCK.load(Ordering::SeqCst);
+        let context = context.cast::<libc::ucontext_t>();
+        let rip = (*context).uc_mcontext.gregs[libc::REG_RIP as usize] as u64;
+        ptr::write_volatile(&raw mut (*block).signal, signal as u64);
+        ptr::write_volatile(&raw mut (*block).fault_address, (*info).si_addr() as u64);
+        ptr::write_volatile(&raw mut (*block).fault_rip, rip);
+        // The signal is blocked until the handler returns, and is then
+        // delivered again with the default action.
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
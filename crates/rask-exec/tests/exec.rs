@@ -0,0 +1,170 @@
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use rask_asm::assemble;
+use rask_exec::{Call, Crash, Outcome, Returned, compare, emulate, run};
+use rask_x86_64::registers::Reg64::*;
+use std::time::Duration;
+
+fn code(source: &str) -> Vec<u8> {
+    match assemble(source) {
+        Ok(object) => object.bytes,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+fn returned(outcome: Outcome) -> Returned {
+    match outcome {
+        Outcome::Returned(out) => *out,
+        other => panic!("expected a return, the call {other}"),
+    }
+}
+
+fn crashed(outcome: Outcome) -> Crash {
+    match outcome {
+        Outcome::Crashed(crash) => crash,
+        other => panic!("expected a crash, the call {other}"),
+    }
+}
+
+#[test]
+fn test_returns_registers() {
+    let code = code("mov rax, rdi\nadd rax, rsi\nret");
+    let out = returned(run(&code, &Call::new().arg(40).arg(2)).unwrap());
+    assert_eq!(out.get(RAX), 42);
+    assert_eq!(out.get(RDI), 40);
+    assert_eq!(out.get(R11), Call::initial(R11));
+    assert!(out.clobbered().is_empty());
+    // add rax, rsi leaves a positive, nonzero result
+    assert_eq!(out.rflags() & rask_exec::STATUS_FLAGS, 0);
+}
+
+#[test]
+fn test_stack_arguments() {
+    // The seventh and eighth arguments are above the return address.
+    let code = code("mov rax, [rsp + 8]\nadd rax, [rsp + 16]\nadd rax, r9\nret");
+    let out = returned(run(&code, &Call::new().args(&[1, 2, 3, 4, 5, 6, 7, 8])).unwrap());
+    assert_eq!(out.get(RAX), 6 + 7 + 8);
+    assert!(out.clobbered().is_empty());
+}
+
+#[test]
+fn test_stack_is_aligned() {
+    // RSP + 8 is 16-byte aligned on entry.
+    let code = code("mov rax, rsp\nret");
+    for count in 0..10 {
+        let args: Vec<u64> = (0..count).collect();
+        let out = returned(run(&code, &Call::new().args(&args)).unwrap());
+        assert_eq!((out.get(RAX) + 8) % 16, 0, "{count} arguments");
+    }
+}
+
+#[test]
+fn test_memory_buffer() {
+    let code = code("mov rax, [rdi]\nadd rax, rsi\nmov [rdi + 8], rax\nret");
+    let mut bytes = [0; 16];
+    bytes[0] = 1;
+    let out = returned(run(&code, &Call::new().memory(&bytes).arg(41)).unwrap());
+    assert_eq!(out.get(RAX), 42);
+    assert_eq!(&out.memory()[..8], &bytes[..8]);
+    assert_eq!(out.memory()[8..], 42u64.to_le_bytes());
+}
+
+#[test]
+fn test_register_overrides() {
+    let code = code("mov rax, r10\nret");
+    let call = Call::new().register(R10, 7).arg(1).register(RDI, 2);
+    let out = returned(run(&code, &call).unwrap());
+    assert_eq!(out.get(RAX), 7);
+    assert_eq!(out.get(RDI), 2);
+    assert!(run(&code, &Call::new().register(RSP, 0)).is_err());
+}
+
+#[test]
+fn test_clobbered_registers() {
+    let code = code("mov rbx, 1\nmov r15, 2\nmov r11, 3\nret");
+    let out = returned(run(&code, &Call::new()).unwrap());
+    assert_eq!(out.clobbered(), [RBX, R15]);
+}
+
+#[test]
+fn test_crashes() {
+    let crash = crashed(run(&code("nop\nmov rax, [0x10]\nret"), &Call::new()).unwrap());
+    assert_eq!(crash.signal_name(), "SIGSEGV");
+    assert_eq!(crash.offset, Some(1));
+    assert_eq!(crash.address, Some(0x10));
+    assert!(crash.to_string().starts_with("SIGSEGV at offset 0x1"));
+
+    // int3 traps after the instruction.
+    let crash = crashed(run(&[0x90, 0xCC, 0xC3], &Call::new()).unwrap());
+    assert_eq!(crash.signal_name(), "SIGTRAP");
+    assert_eq!(crash.offset, Some(2));
+
+    // lock nop
+    let crash = crashed(run(&[0xF0, 0x90, 0xC3], &Call::new()).unwrap());
+    assert_eq!(crash.signal_name(), "SIGILL");
+    assert_eq!(crash.offset, Some(0));
+
+    // The code cannot write itself.
+    // mov byte [rip], 0; ret
+    let store = [0xC6, 0x05, 0, 0, 0, 0, 0, 0xC3];
+    let crash = crashed(run(&store, &Call::new()).unwrap());
+    assert_eq!(crash.signal_name(), "SIGSEGV");
+}
+
+#[test]
+fn test_exit_and_timeout() {
+    // exit(3), with the syscall instruction appended
+    let mut exit = code("mov eax, 60\nmov edi, 3");
+    exit.extend([0x0F, 0x05]);
+    assert_eq!(run(&exit, &Call::new()).unwrap(), Outcome::Exited(3));
+
+    let call = Call::new().timeout(Duration::from_millis(50));
+    let outcome = run(&code("top:\njmp top"), &call).unwrap();
+    assert_eq!(outcome, Outcome::Timeout);
+}
+
+#[test]
+fn test_emulate() {
+    let code = code("mov rax, [rdi]\nadd rax, [rsp + 8]\nret");
+    let call = Call::new()
+        .memory(&5u64.to_le_bytes())
+        .args(&[0, 0, 0, 0, 0, 37]);
+    assert_eq!(returned(emulate(&code, &call).unwrap()).get(RAX), 42);
+
+    let call = Call::new().max_steps(100);
+    assert_eq!(emulate(&[0xEB, 0xFE], &call).unwrap(), Outcome::Timeout);
+    let crash = crashed(emulate(&[0x90, 0xCC, 0xC3], &call).unwrap());
+    assert_eq!(
+        (crash.signal_name().as_str(), crash.offset),
+        ("SIGTRAP", Some(2))
+    );
+}
+
+#[test]
+fn test_compare() {
+    let sum = code(
+        "
+        mov rax, [rdi]
+        add rax, rsi
+        mov [rdi + 8], rax
+        sub rax, rdx
+        mov rbx, rsp
+        ret
+        ",
+    );
+    let call = Call::new().memory(&[0xFF; 16]).arg(1).arg(u64::MAX);
+    let out = returned(compare(&sum, &call).unwrap());
+    assert_eq!(out.clobbered(), [RBX]);
+
+    // The stack is at the same address in both runs.
+    compare(&code("mov rax, rsp\nret"), &Call::new().args(&[0; 9])).unwrap();
+    compare(&code("mov rax, [rdi]\nret"), &Call::new().arg(8)).unwrap();
+    compare(&[0xCC], &Call::new()).unwrap();
+
+    // The emulator's FS base is 0; the native one points at the thread.
+    let err = compare(&code("mov rax, fs:[0]\nret"), &Call::new()).unwrap_err();
+    assert!(
+        err.to_string().contains("native and emulated runs differ"),
+        "{err}"
+    );
+}
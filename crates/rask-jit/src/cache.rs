@@ -0,0 +1,527 @@
+//! A code cache for many functions.
+//!
+//! [`CodeCache`] packs functions into large shared regions instead of
+//! giving each its own pages, frees them and reuses their space, and finds
+//! the function an instruction address belongs to, as a VM needs for stack
+//! walks and profiling.
+//!
+//! Regions are strictly W^X: their pages are read-only and executable,
+//! and inserting or freeing a function makes the pages it spans writable,
+//! and not executable, only while the cache writes them. A thread that
+//! runs code in those pages meanwhile faults, so functions must not be
+//! inserted or freed while other threads may run code that shares a page
+//! with them.
+//!
+//! [`CodeCache::dual_mapped_non_wx`] trades that guarantee for
+//! concurrency: each region is mapped twice, code is written through a
+//! writable view and runs from an executable one, and neither view ever
+//! changes protection, so a compiler thread can insert functions while
+//! other threads run functions in the same region. No view is both
+//! writable and executable, but the same physical code stays writable
+//! through its alias for as long as the cache lives.
+//!
+//! Placing new code where freed code ran is cross-modifying code: see
+//! [`CodeCache::free`].
+
+use crate::memory::FnPtr;
+use crate::sys;
+use rask_common::{RaskError, RaskResult, align_to, is_power_of_two};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::RwLock,
+};
+
+/// The byte freed space is filled with: `int3`, so a call into freed code
+/// traps instead of running whatever is placed there next.
+const FILL: u8 = 0xCC;
+
+/// Identifies a function in a [`CodeCache`]. IDs are never reused, so the
+/// ID of a freed function stays invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CodeId(u64);
+
+impl fmt::Display for CodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A function in a [`CodeCache`]: its ID, address and length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeRef {
+    id: CodeId,
+    address: usize,
+    len: usize,
+}
+
+impl CodeRef {
+    /// Returns the function's ID.
+    pub fn id(&self) -> CodeId {
+        self.id
+    }
+
+    /// Returns the address of the function's first byte.
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    /// Returns the address of the function's first byte as a pointer.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.address as *const u8
+    }
+
+    /// Returns the length of the function's code.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the code is empty, which it never is.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if `address` is in the function's code.
+    pub fn contains(&self, address: usize) -> bool {
+        (self.address..self.address + self.len).contains(&address)
+    }
+
+    /// Returns a pointer to the function as `F`.
+    ///
+    /// # Safety
+    /// The code must be a function with the signature and calling
+    /// convention of `F`, and the pointer must not be called once the
+    /// function is freed or the cache is dropped.
+    pub unsafe fn function<F: FnPtr>(&self) -> F {
+        // SAFETY: the caller guarantees that the code is an `F`.
+        unsafe { F::from_address(self.as_ptr()) }
+    }
+}
+
+/// A region of executable pages.
+struct Region {
+    exec: *const u8,
+    /// The writable view of a dual-mapped region.
+    alias: Option<*mut u8>,
+    len: usize,
+    /// Free space, by offset, never adjacent to another free span.
+    free: BTreeMap<usize, usize>,
+}
+
+impl Region {
+    fn new(len: usize, dual_mapped: bool) -> RaskResult<Self> {
+        let (exec, alias) = if dual_mapped {
+            let (write, exec) = sys::map_dual(len)?;
+            (exec, Some(write))
+        } else {
+            let base = sys::map(len)?;
+            // SAFETY: the pages are freshly mapped and run no code; they are
+            // unmapped again if they cannot be made executable.
+            unsafe { sys::make_executable(base, len) }
+                .inspect_err(|_| unsafe { sys::unmap(base, len) })?;
+            (base.cast_const(), None)
+        };
+        Ok(Self {
+            exec,
+            alias,
+            len,
+            free: BTreeMap::from([(0, len)]),
+        })
+    }
+
+    /// Calls `write` with a writable pointer to the `len` bytes at
+    /// `offset`. Without an alias, the pages they span are writable and
+    /// not executable during the call.
+    ///
+    /// # Safety
+    /// No thread may run code in the bytes, nor, without an alias, in the
+    /// pages they span.
+    unsafe fn write(
+        &self,
+        offset: usize,
+        len: usize,
+        write: impl FnOnce(*mut u8),
+    ) -> RaskResult<()> {
+        if let Some(alias) = self.alias {
+            // SAFETY: the caller keeps code from running in the bytes.
+            write(unsafe { alias.add(offset) });
+            return Ok(());
+        }
+        let page = sys::page_size();
+        let start = offset / page * page;
+        let pages = align_to(offset + len, page) - start;
+        let base = self.exec.cast_mut();
+        // SAFETY: the pages are inside the region, and the caller keeps
+        // code from running in them.
+        unsafe {
+            sys::make_writable(base.add(start), pages)?;
+            write(base.add(offset));
+            sys::make_executable(base.add(start), pages)
+        }
+    }
+
+    /// Takes `size` bytes from the first free span that has them.
+    fn allocate(&mut self, size: usize) -> Option<usize> {
+        let (&offset, &len) = self.free.iter().find(|&(_, &len)| len >= size)?;
+        self.free.remove(&offset);
+        if len > size {
+            self.free.insert(offset + size, len - size);
+        }
+        Some(offset)
+    }
+
+    /// Returns `size` bytes at `offset` to the free space, merging them
+    /// with their free neighbours.
+    fn release(&mut self, mut offset: usize, mut size: usize) {
+        if let Some((&before, &len)) = self.free.range(..offset).next_back()
+            && before + len == offset
+        {
+            self.free.remove(&before);
+            offset = before;
+            size += len;
+        }
+        if let Some(len) = self.free.remove(&(offset + size)) {
+            size += len;
+        }
+        self.free.insert(offset, size);
+    }
+
+    fn contains(&self, address: usize) -> bool {
+        let base = self.exec as usize;
+        (base..base + self.len).contains(&address)
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        // SAFETY: the views are ours, and the cache that owned them is gone.
+        unsafe {
+            if let Some(alias) = self.alias {
+                sys::unmap(alias, self.len);
+            }
+            sys::unmap(self.exec.cast_mut(), self.len);
+        }
+    }
+}
+
+/// A live function.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    id: CodeId,
+    region: usize,
+    /// The space taken, the code length rounded up to the alignment.
+    size: usize,
+    len: usize,
+}
+
+#[derive(Default)]
+struct State {
+    regions: Vec<Region>,
+    /// Live functions, by address.
+    functions: BTreeMap<usize, Entry>,
+    addresses: HashMap<CodeId, usize>,
+    next_id: u64,
+}
+
+impl State {
+    fn code_ref(address: usize, entry: &Entry) -> CodeRef {
+        CodeRef {
+            id: entry.id,
+            address,
+            len: entry.len,
+        }
+    }
+}
+
+// SAFETY: the regions are only written while the lock is held for
+// writing.
+unsafe impl Send for State {}
+unsafe impl Sync for State {}
+
+/// A thread-safe cache of functions in shared executable regions.
+///
+/// ```
+/// use rask_jit::CodeCache;
+/// use rask_x86_64::{encoder::Encoder, operand::Operand, registers::Reg64::RAX};
+///
+/// let constant = |value| -> rask_jit::RaskResult<Vec<u8>> {
+///     let mut enc = Encoder::new();
+///     enc.mov(Operand::Reg(RAX), Operand::Imm(value))?;
+///     enc.ret()?;
+///     Ok(enc.bytes().to_vec())
+/// };
+///
+/// # if cfg!(all(unix, target_arch = "x86_64")) {
+/// let cache = CodeCache::new();
+/// let one = cache.insert(&constant(1)?)?;
+/// let two = cache.insert(&constant(2)?)?;
+/// assert_eq!(two.address() - one.address(), 16);
+/// assert_eq!(cache.lookup(two.address() + 3), Some(two));
+///
+/// // SAFETY: the code is a function without parameters.
+/// let f: extern "sysv64" fn() -> i64 = unsafe { two.function() };
+/// assert_eq!(f(), 2);
+///
+/// // The space of a freed function is reused.
+/// cache.free(one.id())?;
+/// assert_eq!(cache.insert(&constant(3)?)?.address(), one.address());
+/// # }
+/// # Ok::<(), rask_jit::RaskError>(())
+/// ```
+///
+/// Share it between threads in an `Arc`. Inserting and freeing take a
+/// write lock, getting and looking up functions a read lock; calling a
+/// function takes no lock at all. Freeing a function does not stop
+/// threads that are running it or hold a pointer to it: the caller must
+/// make sure none do, as a VM does before it discards compiled code.
+/// Reusing freed space has a further requirement, described at
+/// [`free`](Self::free).
+///
+/// The regions of a cache from [`new`](Self::new) or
+/// [`with_config`](Self::with_config) are never writable and executable
+/// at once, so no thread may run code in the pages a function being
+/// inserted or freed spans until the call returns. A cache from
+/// [`dual_mapped_non_wx`](Self::dual_mapped_non_wx) lifts that
+/// restriction by keeping every region writable through a second mapping.
+pub struct CodeCache {
+    state: RwLock<State>,
+    region_size: usize,
+    alignment: usize,
+    dual_mapped: bool,
+}
+
+impl Default for CodeCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CodeCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodeCache")
+            .field("region_size", &self.region_size)
+            .field("alignment", &self.alignment)
+            .field("dual_mapped", &self.dual_mapped)
+            .field("functions", &self.len())
+            .finish()
+    }
+}
+
+impl CodeCache {
+    /// The default size of a region: 1 MiB.
+    pub const REGION_SIZE: usize = 0x10_0000;
+
+    /// The default alignment of functions: 16 bytes.
+    pub const ALIGNMENT: usize = 16;
+
+    /// Returns an empty W^X cache with 1 MiB regions and functions aligned
+    /// to 16 bytes. Regions are mapped as functions need them.
+    pub fn new() -> Self {
+        Self {
+            state: RwLock::default(),
+            region_size: Self::REGION_SIZE,
+            alignment: Self::ALIGNMENT,
+            dual_mapped: false,
+        }
+    }
+
+    /// Returns an empty W^X cache with regions of at least `region_size`
+    /// bytes and functions aligned to `alignment` bytes. A function larger
+    /// than a region gets a region of its own.
+    ///
+    /// Returns [`RaskError::Other`] if `alignment` is not a power of two
+    /// or larger than a page.
+    pub fn with_config(region_size: usize, alignment: usize) -> RaskResult<Self> {
+        if !is_power_of_two(alignment) || alignment > sys::page_size() {
+            return Err(RaskError::Other(format!(
+                "code alignment {alignment} is not a power of two up to the page size"
+            )));
+        }
+        Ok(Self {
+            state: RwLock::default(),
+            region_size: align_to(region_size.max(1), sys::page_size()),
+            alignment,
+            dual_mapped: false,
+        })
+    }
+
+    /// Returns an empty cache like [`with_config`](Self::with_config)
+    /// whose regions are mapped twice, so that functions can be inserted
+    /// and freed while other threads run code in the same pages.
+    ///
+    /// The cache is not W^X: every region stays writable through its
+    /// second mapping for as long as the cache lives, so a stray write
+    /// through that mapping changes code that runs.
+    pub fn dual_mapped_non_wx(region_size: usize, alignment: usize) -> RaskResult<Self> {
+        Ok(Self {
+            dual_mapped: true,
+            ..Self::with_config(region_size, alignment)?
+        })
+    }
+
+    /// Returns `true` if the cache came from
+    /// [`dual_mapped_non_wx`](Self::dual_mapped_non_wx).
+    pub fn is_dual_mapped(&self) -> bool {
+        self.dual_mapped
+    }
+
+    /// Returns the alignment of functions.
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Copies `code` into the cache, mapping a new region if no free space
+    /// fits it, and returns where it is.
+    ///
+    /// Returns [`RaskError::Other`] if `code` is empty, [`RaskError::Io`]
+    /// if a region cannot be mapped or its protection changed, and
+    /// [`RaskError::UnsupportedFeature`] on hosts other than Unix, or for
+    /// a dual-mapped cache other than Linux.
+    pub fn insert(&self, code: &[u8]) -> RaskResult<CodeRef> {
+        if code.is_empty() {
+            return Err(RaskError::Other(
+                "cannot insert empty code into a code cache".to_string(),
+            ));
+        }
+        let size = align_to(code.len(), self.alignment);
+        let mut state = self.write();
+        let found = state
+            .regions
+            .iter_mut()
+            .enumerate()
+            .find_map(|(i, region)| Some((i, region.allocate(size)?)));
+        let (index, offset) = match found {
+            Some(found) => found,
+            None => {
+                let mut region = Region::new(size.max(self.region_size), self.dual_mapped)?;
+                let offset = region.allocate(size).expect("a new region fits the code");
+                state.regions.push(region);
+                (state.regions.len() - 1, offset)
+            }
+        };
+
+        let region = &mut state.regions[index];
+        // SAFETY: the span is allocated to this function, and no function
+        // runs from it yet; the caller keeps code in its pages from running
+        // in a W^X cache.
+        let written = unsafe {
+            region.write(offset, size, |dst| {
+                dst.copy_from_nonoverlapping(code.as_ptr(), code.len());
+                dst.add(code.len()).write_bytes(FILL, size - code.len());
+            })
+        };
+        if let Err(err) = written {
+            region.release(offset, size);
+            return Err(err);
+        }
+        let address = region.exec as usize + offset;
+        let id = CodeId(state.next_id);
+        state.next_id += 1;
+        let entry = Entry {
+            id,
+            region: index,
+            size,
+            len: code.len(),
+        };
+        state.functions.insert(address, entry);
+        state.addresses.insert(id, address);
+        Ok(State::code_ref(address, &entry))
+    }
+
+    /// Frees the function `id`, filling its space with `int3` for reuse.
+    ///
+    /// A later [`insert`](Self::insert) may place a function where this
+    /// one ran. A thread that ran code at that address may still hold the
+    /// old instructions in its pipeline, so before it runs the new function
+    /// it must execute a serializing instruction such as `cpuid`, or be
+    /// made to, for example with Linux's
+    /// `membarrier(MEMBARRIER_CMD_PRIVATE_EXPEDITED_SYNC_CORE)`. Code
+    /// inserted at addresses that never held code needs no such step.
+    ///
+    /// Returns [`RaskError::Other`] if there is no such live function, and
+    /// [`RaskError::Io`] if the protection of its pages cannot be changed,
+    /// which leaves it live.
+    pub fn free(&self, id: CodeId) -> RaskResult<()> {
+        let mut state = self.write();
+        let Some(&address) = state.addresses.get(&id) else {
+            return Err(RaskError::Other(format!(
+                "no live function {id} in the code cache"
+            )));
+        };
+        let entry = state.functions[&address];
+        let region = &mut state.regions[entry.region];
+        let offset = address - region.exec as usize;
+        // SAFETY: the span was allocated to the function, which the caller
+        // guarantees nothing runs anymore, nor in a W^X cache anything else
+        // in its pages.
+        unsafe { region.write(offset, entry.size, |dst| dst.write_bytes(FILL, entry.size))? };
+        region.release(offset, entry.size);
+        state.addresses.remove(&id);
+        state.functions.remove(&address);
+        Ok(())
+    }
+
+    /// Returns the live function `id`.
+    pub fn get(&self, id: CodeId) -> Option<CodeRef> {
+        let state = self.read();
+        let address = *state.addresses.get(&id)?;
+        Some(State::code_ref(address, &state.functions[&address]))
+    }
+
+    /// Returns the live function whose code contains `address`, such as a
+    /// return address found in a stack walk.
+    pub fn lookup(&self, address: usize) -> Option<CodeRef> {
+        let state = self.read();
+        let (&start, entry) = state.functions.range(..=address).next_back()?;
+        let function = State::code_ref(start, entry);
+        function.contains(address).then_some(function)
+    }
+
+    /// Returns `true` if `address` is in one of the cache's regions, live
+    /// code or not.
+    pub fn owns(&self, address: usize) -> bool {
+        self.read()
+            .regions
+            .iter()
+            .any(|region| region.contains(address))
+    }
+
+    /// Returns the live functions, by address.
+    pub fn functions(&self) -> Vec<CodeRef> {
+        let state = self.read();
+        let functions = state.functions.iter();
+        functions
+            .map(|(&address, entry)| State::code_ref(address, entry))
+            .collect()
+    }
+
+    /// Returns the number of live functions.
+    pub fn len(&self) -> usize {
+        self.read().functions.len()
+    }
+
+    /// Returns `true` if the cache has no live functions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the bytes mapped for regions.
+    pub fn capacity(&self) -> usize {
+        self.read().regions.iter().map(|region| region.len).sum()
+    }
+
+    /// Returns the bytes taken by live functions, with alignment padding.
+    pub fn used(&self) -> usize {
+        self.read().functions.values().map(|entry| entry.size).sum()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        // The state is consistent between statements, so a panic while the
+        // lock was held leaves nothing half-done.
+        self.state.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, State> {
+        self.state.write().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,43 @@
+//! Executable memory for Rask.
+//!
+//! Runs the bytes an `Encoder` produces in the current process: the
+//! [`memory`] module maps them into executable pages and hands out typed
+//! function pointers that keep those pages alive, and the [`cache`]
+//! module packs many functions into shared regions for a long-running VM.
+//!
+//! ```
+//! use rask_jit::JitFn;
+//! use rask_x86_64::{encoder::Encoder, operand::Operand, registers::Reg64::*};
+//!
+//! let mut enc = Encoder::new();
+//! enc.mov(Operand::Reg(RAX), Operand::Reg(RDI))?;
+//! enc.sub(RAX, RSI)?;
+//! enc.ret()?;
+//!
+//! # if cfg!(all(unix, target_arch = "x86_64")) {
+//! // SAFETY: the code is a System V function of two integers.
+//! let sub: JitFn<extern "sysv64" fn(i64, i64) -> i64> = unsafe { JitFn::new(enc.bytes())? };
+//! assert_eq!(sub(50, 8), 42);
+//! # }
+//! # Ok::<(), rask_common::RaskError>(())
+//! ```
+//!
+//! [`JitMemory`] pages are never writable and executable at the same
+//! time: code is copied in while they are writable, and they are made
+//! executable and read-only before any of it can run. [`CodeCache`]
+//! regions are W^X too, made writable only while a function in them is
+//! inserted or freed, so no thread may run code in those pages meanwhile.
+//! [`CodeCache::dual_mapped_non_wx`] lifts that restriction by keeping a
+//! writable alias of each region for as long as the cache lives, which is
+//! W^X per view only.
+//!
+//! Executable memory needs a Unix host, and the dual-mapped code cache
+//! Linux; elsewhere mapping returns [`RaskError::UnsupportedFeature`].
+
+pub mod cache;
+pub mod memory;
+mod sys;
+
+pub use cache::{CodeCache, CodeId, CodeRef};
+pub use memory::{FnPtr, JitFn, JitMemory};
+pub use rask_common::{RaskError, RaskResult};
@@ -0,0 +1,274 @@
+//! Executable memory and typed function handles.
+//!
+//! [`JitMemory`] maps fresh pages, copies code into them while they are
+//! writable, and then makes them executable and read-only before anything
+//! can run them, so no page is ever writable and executable at once (W^X).
+//! The pages are unmapped when the `JitMemory` is dropped.
+//!
+//! [`JitFn`] is a function pointer into a `JitMemory` that keeps the
+//! memory alive, and derefs to the pointer so it can be called directly.
+
+use crate::sys;
+use rask_common::{RaskError, RaskResult, align_to};
+use std::{fmt, ops::Deref, ptr::NonNull, slice, sync::Arc};
+
+/// Executable, read-only pages holding code.
+pub struct JitMemory {
+    base: NonNull<u8>,
+    len: usize,
+    mapped: usize,
+}
+
+// SAFETY: the pages are never written after `new` returns, so sharing them
+// between threads is sharing immutable bytes.
+unsafe impl Send for JitMemory {}
+unsafe impl Sync for JitMemory {}
+
+impl JitMemory {
+    /// Maps pages, copies `code` into them and makes them executable.
+    ///
+    /// ```
+    /// use rask_jit::JitMemory;
+    ///
+    /// # if cfg!(unix) {
+    /// let memory = JitMemory::new(&[0xB8, 42, 0, 0, 0, 0xC3])?;
+    /// assert_eq!(memory.bytes(), [0xB8, 42, 0, 0, 0, 0xC3]);
+    /// assert_eq!(memory.as_ptr() as usize % 4096, 0);
+    /// # }
+    /// # Ok::<(), rask_common::RaskError>(())
+    /// ```
+    ///
+    /// Returns [`RaskError::Io`] if the pages cannot be mapped or
+    /// protected, and [`RaskError::UnsupportedFeature`] on hosts other
+    /// than Unix.
+    pub fn new(code: &[u8]) -> RaskResult<Self> {
+        let mapped = align_to(code.len().max(1), sys::page_size());
+        let base = sys::map(mapped)?;
+        // SAFETY: the pages are ours, writable and at least `code.len()`
+        // bytes long; on failure they are unmapped before anything ran.
+        unsafe {
+            base.copy_from_nonoverlapping(code.as_ptr(), code.len());
+            if let Err(err) = sys::make_executable(base, mapped) {
+                sys::unmap(base, mapped);
+                return Err(err);
+            }
+        }
+        Ok(Self {
+            base: NonNull::new(base).expect("mmap does not return null"),
+            len: code.len(),
+            mapped,
+        })
+    }
+
+    /// Returns the address of the first byte of the code.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.base.as_ptr()
+    }
+
+    /// Returns the length of the code in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the code is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the code.
+    pub fn bytes(&self) -> &[u8] {
+        // SAFETY: the pages are readable, hold `len` bytes of code and
+        // are never written again.
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    /// Returns `true` if `address` is in the code.
+    pub fn contains(&self, address: usize) -> bool {
+        let base = self.as_ptr() as usize;
+        (base..base + self.len).contains(&address)
+    }
+}
+
+impl Drop for JitMemory {
+    fn drop(&mut self) {
+        // SAFETY: the pages are ours, and no `JitFn` points into them
+        // anymore, since each one keeps its memory alive.
+        unsafe { sys::unmap(self.base.as_ptr(), self.mapped) };
+    }
+}
+
+impl fmt::Debug for JitMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JitMemory")
+            .field("address", &self.base)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// A function pointer type a [`JitFn`] can hold.
+///
+/// Implemented for `extern "C"` function pointers, and on x86-64 for
+/// `extern "sysv64"` and `extern "win64"` ones, with up to six
+/// parameters, safe or `unsafe`.
+///
+/// # Safety
+/// Implementors must be function pointers.
+pub unsafe trait FnPtr: Copy {
+    /// Returns a pointer to the function at `address`.
+    ///
+    /// # Safety
+    /// `address` must be the entry of a function with this signature.
+    unsafe fn from_address(address: *const u8) -> Self;
+
+    /// Returns the address the pointer points to.
+    fn address(self) -> *const u8;
+}
+
+macro_rules! fn_ptr {
+    ($abi:literal: $($arg:ident)*) => {
+        // SAFETY: these are function pointers.
+        unsafe impl<R, $($arg),*> FnPtr for extern $abi fn($($arg),*) -> R {
+            unsafe fn from_address(address: *const u8) -> Self {
+                // SAFETY: the caller passes the entry of such a function.
+                unsafe { std::mem::transmute::<*const u8, Self>(address) }
+            }
+
+            fn address(self) -> *const u8 {
+                self as *const u8
+            }
+        }
+
+        // SAFETY: these are function pointers.
+        unsafe impl<R, $($arg),*> FnPtr for unsafe extern $abi fn($($arg),*) -> R {
+            unsafe fn from_address(address: *const u8) -> Self {
+                // SAFETY: the caller passes the entry of such a function.
+                unsafe { std::mem::transmute::<*const u8, Self>(address) }
+            }
+
+            fn address(self) -> *const u8 {
+                self as *const u8
+            }
+        }
+    };
+}
+
+macro_rules! fn_ptrs {
+    ($abi:literal) => {
+        fn_ptr!($abi:);
+        fn_ptr!($abi: A);
+        fn_ptr!($abi: A B);
+        fn_ptr!($abi: A B C);
+        fn_ptr!($abi: A B C D);
+        fn_ptr!($abi: A B C D E);
+        fn_ptr!($abi: A B C D E F);
+    };
+}
+
+fn_ptrs!("C");
+#[cfg(target_arch = "x86_64")]
+fn_ptrs!("sysv64");
+#[cfg(target_arch = "x86_64")]
+fn_ptrs!("win64");
+
+/// A typed function in executable memory, which it keeps mapped.
+///
+/// Derefs to the function pointer, so it is called like a function:
+///
+/// ```
+/// use rask_jit::JitFn;
+/// use rask_x86_64::{encoder::Encoder, operand::Operand, registers::Reg64::*};
+///
+/// // fn add(a, b) -> a + b
+/// let mut enc = Encoder::new();
+/// enc.mov(Operand::Reg(RAX), Operand::Reg(RDI))?;
+/// enc.add(RAX, RSI)?;
+/// enc.ret()?;
+///
+/// # if cfg!(all(unix, target_arch = "x86_64")) {
+/// // SAFETY: the code is a System V function of two integers.
+/// let add: JitFn<extern "sysv64" fn(i64, i64) -> i64> = unsafe { JitFn::new(enc.bytes())? };
+/// assert_eq!(add(40, 2), 42);
+/// # }
+/// # Ok::<(), rask_common::RaskError>(())
+/// ```
+///
+/// A copy of the pointer taken out of the `JitFn` must not be called
+/// after the `JitFn` and its clones are dropped.
+pub struct JitFn<F: FnPtr> {
+    function: F,
+    memory: Arc<JitMemory>,
+}
+
+impl<F: FnPtr> JitFn<F> {
+    /// Maps `code` as [`JitMemory::new`] does and returns the function at
+    /// its first byte.
+    ///
+    /// # Safety
+    /// `code` must be a function with the signature and calling
+    /// convention of `F`.
+    ///
+    /// Returns an error as [`JitMemory::new`] does.
+    pub unsafe fn new(code: &[u8]) -> RaskResult<Self> {
+        // SAFETY: the caller guarantees that the code at offset 0 is an `F`.
+        unsafe { Self::from_memory(Arc::new(JitMemory::new(code)?), 0) }
+    }
+
+    /// Returns the function at `offset` in `memory`, such as the position
+    /// of a label in the `Encoder` buffer the memory was made from. Several
+    /// functions can share one memory.
+    ///
+    /// # Safety
+    /// The code at `offset` must be a function with the signature and
+    /// calling convention of `F`.
+    ///
+    /// Returns [`RaskError::Other`] if `offset` is not in the code.
+    pub unsafe fn from_memory(memory: Arc<JitMemory>, offset: usize) -> RaskResult<Self> {
+        if offset >= memory.len() {
+            return Err(RaskError::Other(format!(
+                "function offset {offset:#x} is outside the {} bytes of code",
+                memory.len()
+            )));
+        }
+        // SAFETY: the offset is in the code, and the caller guarantees
+        // that it is the entry of an `F`.
+        let function = unsafe { F::from_address(memory.as_ptr().add(offset)) };
+        Ok(Self { function, memory })
+    }
+
+    /// Returns the memory the function is in.
+    pub fn memory(&self) -> &Arc<JitMemory> {
+        &self.memory
+    }
+
+    /// Returns the address of the function's entry.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.function.address()
+    }
+}
+
+impl<F: FnPtr> Deref for JitFn<F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.function
+    }
+}
+
+impl<F: FnPtr> Clone for JitFn<F> {
+    fn clone(&self) -> Self {
+        Self {
+            function: self.function,
+            memory: Arc::clone(&self.memory),
+        }
+    }
+}
+
+impl<F: FnPtr> fmt::Debug for JitFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JitFn")
+            .field("address", &self.as_ptr())
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
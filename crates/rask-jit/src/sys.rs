@@ -0,0 +1,146 @@
+//! Page mapping and protection on the host.
+
+#[cfg(unix)]
+mod imp {
+    use rask_common::RaskResult;
+    use std::ptr;
+
+    pub(crate) fn page_size() -> usize {
+        // SAFETY: sysconf has no preconditions.
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        usize::try_from(size).unwrap_or(0x1000)
+    }
+
+    /// Maps `len` bytes of zeroed, readable and writable memory.
+    pub(crate) fn map(len: usize) -> RaskResult<*mut u8> {
+        // SAFETY: a fresh anonymous mapping aliases nothing.
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(base.cast())
+    }
+
+    /// Makes pages readable and executable, and no longer writable.
+    ///
+    /// # Safety
+    /// `base` and `len` must cover pages mapped by [`map`].
+    pub(crate) unsafe fn make_executable(base: *mut u8, len: usize) -> RaskResult<()> {
+        // SAFETY: the caller passes pages we mapped.
+        let result = unsafe { libc::mprotect(base.cast(), len, libc::PROT_READ | libc::PROT_EXEC) };
+        if result != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Makes pages readable and writable, and no longer executable.
+    ///
+    /// # Safety
+    /// `base` and `len` must cover pages mapped by [`map`], none of which
+    /// any thread runs code in.
+    pub(crate) unsafe fn make_writable(base: *mut u8, len: usize) -> RaskResult<()> {
+        // SAFETY: the caller passes pages we mapped that run no code.
+        let result =
+            unsafe { libc::mprotect(base.cast(), len, libc::PROT_READ | libc::PROT_WRITE) };
+        if result != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// # Safety
+    /// `base` and `len` must cover pages mapped by [`map`] or
+    /// [`map_dual`](super::map_dual) that nothing uses anymore.
+    pub(crate) unsafe fn unmap(base: *mut u8, len: usize) {
+        // SAFETY: the caller passes pages we mapped and no longer use.
+        unsafe { libc::munmap(base.cast(), len) };
+    }
+}
+
+/// Maps `len` bytes of zeroed shared memory twice, and returns a
+/// writable view and an executable view of it. Neither view ever changes
+/// protection, so code written through the first can run from the second
+/// while other code in the same pages runs.
+#[cfg(target_os = "linux")]
+pub(crate) fn map_dual(len: usize) -> rask_common::RaskResult<(*mut u8, *const u8)> {
+    use std::ptr;
+
+    let map = |fd, prot| {
+        // SAFETY: a fresh shared mapping of our own file aliases only the
+        // other view of it.
+        let base = unsafe { libc::mmap(ptr::null_mut(), len, prot, libc::MAP_SHARED, fd, 0) };
+        if base == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(base.cast::<u8>())
+    };
+    // SAFETY: the name is NUL-terminated, and the descriptor is closed on
+    // every path once both views are mapped or have failed.
+    unsafe {
+        let fd = libc::memfd_create(c"rask-jit".as_ptr(), libc::MFD_CLOEXEC);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let views = if libc::ftruncate(fd, len as libc::off_t) != 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            map(fd, libc::PROT_READ | libc::PROT_WRITE).and_then(|write| {
+                map(fd, libc::PROT_READ | libc::PROT_EXEC)
+                    .map(|exec| (write, exec.cast_const()))
+                    .inspect_err(|_| unmap(write, len))
+            })
+        };
+        libc::close(fd);
+        Ok(views?)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn map_dual(_: usize) -> rask_common::RaskResult<(*mut u8, *const u8)> {
+    Err(rask_common::RaskError::UnsupportedFeature {
+        what: "a dual-mapped code cache".to_string(),
+        feature: "Linux".to_string(),
+    })
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use rask_common::{RaskError, RaskResult};
+
+    fn unsupported() -> RaskError {
+        RaskError::UnsupportedFeature {
+            what: "executable memory".to_string(),
+            feature: "a Unix host".to_string(),
+        }
+    }
+
+    pub(crate) fn page_size() -> usize {
+        0x1000
+    }
+
+    pub(crate) fn map(_: usize) -> RaskResult<*mut u8> {
+        Err(unsupported())
+    }
+
+    pub(crate) unsafe fn make_executable(_: *mut u8, _: usize) -> RaskResult<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) unsafe fn make_writable(_: *mut u8, _: usize) -> RaskResult<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) unsafe fn unmap(_: *mut u8, _: usize) {}
+}
+
+pub(crate) use imp::*;
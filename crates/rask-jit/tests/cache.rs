@@ -0,0 +1,164 @@
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use rask_jit::{CodeCache, CodeRef};
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::operand::Operand::{Imm, Reg};
+use rask_x86_64::registers::Reg64::*;
+use std::sync::{Arc, mpsc};
+use std::thread;
+
+/// Returns a function that returns `value`, padded with `nop`s to `len`
+/// bytes.
+fn constant(value: i64, len: usize) -> Vec<u8> {
+    let mut enc = Encoder::new();
+    enc.mov(Reg(RAX), Imm(value)).unwrap();
+    enc.ret().unwrap();
+    let mut code = enc.bytes().to_vec();
+    code.resize(len.max(code.len()), 0x90);
+    code
+}
+
+fn call(function: &CodeRef) -> i64 {
+    // SAFETY: `constant` functions take no parameters.
+    let f: extern "sysv64" fn() -> i64 = unsafe { function.function() };
+    f()
+}
+
+#[test]
+fn test_insert_and_lookup() {
+    let cache = CodeCache::new();
+    let functions: Vec<CodeRef> = (0..100)
+        .map(|i| cache.insert(&constant(i, 16 + i as usize)).unwrap())
+        .collect();
+    assert_eq!(cache.len(), 100);
+    assert_eq!(cache.capacity(), CodeCache::REGION_SIZE);
+    for (i, function) in functions.iter().enumerate() {
+        assert_eq!(function.address() % 16, 0);
+        assert_eq!(function.len(), 16 + i);
+        assert_eq!(call(function), i as i64);
+        assert_eq!(cache.get(function.id()), Some(*function));
+        assert_eq!(cache.lookup(function.address()), Some(*function));
+        assert_eq!(
+            cache.lookup(function.address() + function.len() - 1),
+            Some(*function)
+        );
+    }
+    assert_eq!(cache.functions(), functions);
+
+    // Alignment padding belongs to no function.
+    let padded = functions[1];
+    assert_eq!(cache.lookup(padded.address() + padded.len()), None);
+    assert!(cache.owns(padded.address() + padded.len()));
+    assert_eq!(cache.lookup(0x1000), None);
+    assert!(!cache.owns(0x1000));
+    assert!(cache.insert(&[]).is_err());
+}
+
+#[test]
+fn test_free_and_reuse() {
+    let cache = CodeCache::with_config(0x1000, 64).unwrap();
+    let a = cache.insert(&constant(1, 100)).unwrap();
+    let b = cache.insert(&constant(2, 10)).unwrap();
+    let c = cache.insert(&constant(3, 10)).unwrap();
+    assert_eq!(
+        (b.address() - a.address(), c.address() - b.address()),
+        (128, 64)
+    );
+    assert_eq!(cache.used(), 256);
+
+    cache.free(a.id()).unwrap();
+    assert!(cache.free(a.id()).is_err());
+    assert_eq!((cache.get(a.id()), cache.lookup(a.address())), (None, None));
+    assert_eq!(call(&b), 2);
+
+    // Freed space is reused, and merged with free neighbours.
+    let d = cache.insert(&constant(4, 60)).unwrap();
+    assert_eq!(d.address(), a.address());
+    assert_ne!(d.id(), a.id());
+    cache.free(d.id()).unwrap();
+    cache.free(b.id()).unwrap();
+    let e = cache.insert(&constant(5, 190)).unwrap();
+    assert_eq!(e.address(), a.address());
+    assert_eq!((call(&e), call(&c)), (5, 3));
+
+    // A full region gets company, and a large function a region of its own.
+    let f = cache.insert(&constant(6, 0x1000 - 256)).unwrap();
+    let g = cache.insert(&constant(7, 64)).unwrap();
+    let h = cache.insert(&constant(8, 0x3000)).unwrap();
+    assert_eq!(cache.capacity(), 0x1000 + 0x1000 + 0x3000);
+    assert_eq!((call(&f), call(&g), call(&h)), (6, 7, 8));
+    assert_eq!(cache.lookup(h.address() + 0x2FFF), Some(h));
+
+    assert!(CodeCache::with_config(0x1000, 24).is_err());
+    assert!(CodeCache::with_config(0x1000, 0x10000).is_err());
+}
+
+/// Returns the permissions, such as `r-xp`, of the mapping that holds
+/// `address`.
+fn protection(address: usize) -> String {
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    let mapping = maps.lines().find(|line| {
+        let (start, end) = line.split_once(' ').unwrap().0.split_once('-').unwrap();
+        let start = usize::from_str_radix(start, 16).unwrap();
+        let end = usize::from_str_radix(end, 16).unwrap();
+        (start..end).contains(&address)
+    });
+    mapping
+        .unwrap()
+        .split_whitespace()
+        .nth(1)
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_regions_are_never_writable() {
+    let cache = CodeCache::with_config(0x1000, 16).unwrap();
+    assert!(!cache.is_dual_mapped());
+    let a = cache.insert(&constant(1, 32)).unwrap();
+    let b = cache.insert(&constant(2, 32)).unwrap();
+    assert_eq!(protection(a.address()), "r-xp");
+    cache.free(a.id()).unwrap();
+    assert_eq!(protection(b.address()), "r-xp");
+    assert_eq!(call(&b), 2);
+
+    // A dual-mapped region runs from a shared mapping with a writable twin.
+    let dual = CodeCache::dual_mapped_non_wx(0x1000, 16).unwrap();
+    assert!(dual.is_dual_mapped());
+    let c = dual.insert(&constant(3, 32)).unwrap();
+    assert_eq!(protection(c.address()), "r-xs");
+    assert_eq!(call(&c), 3);
+}
+
+#[test]
+fn test_concurrent_insert_and_call() {
+    let cache = Arc::new(CodeCache::dual_mapped_non_wx(0x1000, 16).unwrap());
+    let (sender, receiver) = mpsc::channel::<(i64, CodeRef)>();
+    let compiler = {
+        let cache = Arc::clone(&cache);
+        thread::spawn(move || {
+            for i in 0..500 {
+                let function = cache.insert(&constant(i, 32)).unwrap();
+                sender.send((i, function)).unwrap();
+            }
+        })
+    };
+
+    // The runner calls each function while later ones are written into
+    // the same region.
+    let runner = thread::spawn(move || {
+        let mut published = Vec::new();
+        for (value, function) in receiver {
+            published.push((value, function));
+            for (value, function) in &published[published.len().saturating_sub(8)..] {
+                assert_eq!(call(function), *value);
+            }
+        }
+        published.len()
+    });
+    compiler.join().unwrap();
+    assert_eq!(runner.join().unwrap(), 500);
+    assert_eq!(cache.len(), 500);
+    // 500 functions of 32 bytes fill four regions.
+    assert_eq!(cache.capacity(), 0x4000);
+}
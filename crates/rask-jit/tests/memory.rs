@@ -0,0 +1,83 @@
+#![cfg(all(unix, target_arch = "x86_64"))]
+
+use rask_jit::{JitFn, JitMemory};
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::operand::Operand::{Imm, Label, Reg};
+use rask_x86_64::registers::Reg64::*;
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn test_call_function() {
+    let mut enc = Encoder::new();
+    enc.mov(Reg(RAX), Reg(RDI)).unwrap();
+    enc.add(RAX, RSI).unwrap();
+    enc.ret().unwrap();
+    // SAFETY: the code adds its two arguments.
+    let add: JitFn<extern "sysv64" fn(i64, i64) -> i64> =
+        unsafe { JitFn::new(enc.bytes()) }.unwrap();
+    assert_eq!(add(40, 2), 42);
+    assert_eq!(add(-1, -1), -2);
+    assert_eq!(add.as_ptr(), add.memory().as_ptr());
+    assert_eq!(add.memory().bytes(), enc.bytes());
+
+    // The memory outlives the first handle.
+    let copy = add.clone();
+    drop(add);
+    assert_eq!(copy(1, 2), 3);
+    assert_eq!(thread::spawn(move || copy(3, 4)).join().unwrap(), 7);
+}
+
+#[test]
+fn test_functions_share_memory() {
+    let mut enc = Encoder::new();
+    let one = enc.create_label();
+    enc.mov(Reg(RAX), Imm(1)).unwrap();
+    enc.ret().unwrap();
+    let two = enc.position();
+    enc.call(Label(one)).unwrap();
+    enc.add(RAX, RAX).unwrap();
+    enc.ret().unwrap();
+    enc.bind_label(one).unwrap();
+    enc.mov(Reg(RAX), Imm(-7)).unwrap();
+    enc.ret().unwrap();
+    let code = enc.finish().unwrap();
+
+    let memory = Arc::new(JitMemory::new(&code).unwrap());
+    // SAFETY: both offsets are functions without parameters.
+    let (first, second) = unsafe {
+        (
+            JitFn::<extern "C" fn() -> i64>::from_memory(Arc::clone(&memory), 0).unwrap(),
+            JitFn::<extern "C" fn() -> i64>::from_memory(Arc::clone(&memory), two).unwrap(),
+        )
+    };
+    assert_eq!(first(), 1);
+    assert_eq!(second(), -14);
+    assert!(memory.contains(second.as_ptr() as usize));
+    assert!(!memory.contains(memory.as_ptr() as usize + code.len()));
+
+    let outside = unsafe { JitFn::<extern "C" fn()>::from_memory(memory, code.len()) };
+    assert!(outside.is_err());
+}
+
+#[test]
+fn test_pages_are_not_writable() {
+    let memory = JitMemory::new(&[0xC3; 5000]).unwrap();
+    assert_eq!(memory.len(), 5000);
+    assert_eq!(memory.as_ptr() as usize % 4096, 0);
+    let start = memory.as_ptr() as usize;
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    let line = maps
+        .lines()
+        .find(|line| line.starts_with(&format!("{start:x}-")))
+        .expect("the memory is mapped");
+    let mut fields = line.split_whitespace();
+    let range = fields.next().unwrap();
+    assert_eq!(fields.next(), Some("r-xp"), "{line}");
+    let end = usize::from_str_radix(range.split_once('-').unwrap().1, 16).unwrap();
+    assert!(end - start >= 5000);
+
+    // SAFETY: a lone ret is a function without parameters.
+    let ret: JitFn<unsafe extern "sysv64" fn()> = unsafe { JitFn::new(&[0xC3]) }.unwrap();
+    unsafe { ret() };
+}
@@ -0,0 +1,301 @@
+//! Code generation for `rask_asm!`.
+//!
+//! Every instruction becomes an [`Encoder::encode`] call on an
+//! [`Instruction`] built from constant operands where possible. Before
+//! generating it, instructions whose operands are all known at compile time
+//! are encoded once here, so operand errors surface as compile errors.
+
+use crate::parse::{Error, Index, Insn, LabelRef, Mem, Op, Program, Result, Stmt};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::{format_ident, quote};
+use rask_x86_64::{
+    encoder::Encoder,
+    features::{CpuFeature, CpuFeatures},
+    instruction::{Instruction, Prefix},
+    mode::Mode,
+    operand::{AddrReg, MemOperand, Operand},
+};
+use std::{collections::HashMap, fmt::Debug};
+
+/// Expands a parsed program into a block evaluating to `RaskResult<()>`.
+pub fn expand(program: &Program) -> Result<TokenStream> {
+    let labels = declare_labels(program)?;
+    let enc = &program.encoder;
+
+    let mut lets = Vec::new();
+    for stmt in &program.stmts {
+        if let Stmt::Bind(LabelRef::Local(name)) = stmt {
+            let var = &labels[&name.to_string()];
+            lets.push(quote! { let #var = (#enc).create_label(); });
+        }
+    }
+
+    let mut body = Vec::new();
+    for stmt in &program.stmts {
+        let call = match stmt {
+            Stmt::Bind(LabelRef::Local(name)) => {
+                let var = &labels[&name.to_string()];
+                quote! { (#enc).bind_label(#var) }
+            }
+            Stmt::Bind(LabelRef::Expr(label)) => quote! { (#enc).bind_label(#label) },
+            Stmt::Insn(insn) => {
+                check(insn)?;
+                let insn = instruction(insn, &labels)?;
+                quote! { (#enc).encode(&#insn) }
+            }
+        };
+        body.push(quote! {
+            if let ::core::result::Result::Err(err) = #call {
+                break '__rask_asm ::core::result::Result::Err(err);
+            }
+        });
+    }
+
+    Ok(quote! {
+        {
+            #(#lets)*
+            #[allow(unused_labels)]
+            let result: ::rask_x86_64::RaskResult<()> = '__rask_asm: {
+                #(#body)*
+                ::core::result::Result::Ok(())
+            };
+            result
+        }
+    })
+}
+
+/// Collects the labels defined in the program, keyed by name, checking
+/// that each is defined once and that every label used is defined.
+fn declare_labels(program: &Program) -> Result<HashMap<String, Ident>> {
+    let mut labels = HashMap::new();
+    for stmt in &program.stmts {
+        if let Stmt::Bind(LabelRef::Local(name)) = stmt {
+            let var = format_ident!("__rask_label_{}", name, span = Span::mixed_site());
+            if labels.insert(name.to_string(), var).is_some() {
+                return Err(Error::new(
+                    name.span(),
+                    format!("label `{name}` is defined more than once"),
+                ));
+            }
+        }
+    }
+    for stmt in &program.stmts {
+        if let Stmt::Insn(insn) = stmt {
+            for op in &insn.operands {
+                if let Op::Label(name) = op
+                    && !labels.contains_key(&name.to_string())
+                {
+                    return Err(Error::new(
+                        name.span(),
+                        format!("unknown register or undefined label `{name}`"),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(labels)
+}
+
+/// Encodes `insn` if all its operands are known, and fails if no processor
+/// mode accepts it.
+fn check(insn: &Insn) -> Result<()> {
+    let mut probe = Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx));
+    let label = probe.create_label();
+    let mut operands = Vec::new();
+    for op in &insn.operands {
+        operands.push(match op {
+            Op::Static(operand) => *operand,
+            Op::Label(_) => Operand::Label(label),
+            Op::Mem(mem) => match mem_operand(mem) {
+                Some(mem) => Operand::Mem(mem),
+                None => return Ok(()),
+            },
+            Op::Expr(_) => return Ok(()),
+        });
+    }
+    let mut probe_insn = Instruction::from_operands(insn.mnemonic, &operands)
+        .map_err(|err| Error::new(insn.span, err.to_string()))?;
+    if insn.lock {
+        probe_insn = probe_insn.with_prefix(Prefix::Lock);
+    }
+
+    let mut first_error = None;
+    for mode in [Mode::Long64, Mode::Protected32, Mode::Real16] {
+        probe.set_mode(mode);
+        match probe.encode(&probe_insn) {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    match first_error {
+        Some(err) => Err(Error::new(insn.span, err.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Returns the memory operand with interpolated displacements taken as 0,
+/// or `None` if its index register is interpolated.
+fn mem_operand(mem: &Mem) -> Option<MemOperand> {
+    let index = match &mem.index {
+        Some((Index::Static(reg), scale)) => Some((*reg, *scale)),
+        Some((Index::Expr(_), _)) => return None,
+        None => None,
+    };
+    Some(MemOperand {
+        base: mem.base,
+        index,
+        disp: mem.disp,
+        size: mem.size,
+        segment: mem.segment,
+    })
+}
+
+/// Generates the `Instruction` expression for `insn`.
+fn instruction(insn: &Insn, labels: &HashMap<String, Ident>) -> Result<TokenStream> {
+    let variant = variant(insn.mnemonic);
+    let mnemonic = quote! { ::rask_x86_64::instruction::Mnemonic::#variant };
+    let operands: Vec<TokenStream> = insn.operands.iter().map(|op| operand(op, labels)).collect();
+
+    let mut tokens = match operands.as_slice() {
+        [] => quote! { ::rask_x86_64::instruction::Instruction::new(#mnemonic) },
+        [a] => quote! { ::rask_x86_64::instruction::Instruction::with1(#mnemonic, #a) },
+        [a, b] => quote! { ::rask_x86_64::instruction::Instruction::with2(#mnemonic, #a, #b) },
+        [a, b, c] => {
+            quote! { ::rask_x86_64::instruction::Instruction::with3(#mnemonic, #a, #b, #c) }
+        }
+        [a, b, c, d] => quote! {
+            ::rask_x86_64::instruction::Instruction::with4(#mnemonic, #a, #b, #c, #d)
+        },
+        _ => return Err(Error::new(insn.span, "too many operands")),
+    };
+    if insn.lock {
+        tokens = quote! { #tokens.with_prefix(::rask_x86_64::instruction::Prefix::Lock) };
+    }
+    Ok(tokens)
+}
+
+fn operand(op: &Op, labels: &HashMap<String, Ident>) -> TokenStream {
+    match op {
+        Op::Static(operand) => static_operand(operand),
+        Op::Expr(expr) => quote! { ::rask_x86_64::operand::Operand::from(#expr) },
+        Op::Label(name) => {
+            let var = &labels[&name.to_string()];
+            quote! { ::rask_x86_64::operand::Operand::Label(#var) }
+        }
+        Op::Mem(mem) => {
+            let mem = mem_tokens(mem);
+            quote! { ::rask_x86_64::operand::Operand::Mem(#mem) }
+        }
+    }
+}
+
+fn static_operand(operand: &Operand) -> TokenStream {
+    let (variant, ty, name) = match operand {
+        Operand::Reg(r) => ("Reg", "Reg64", format!("{r:?}")),
+        Operand::Reg32(r) => ("Reg32", "Reg32", format!("{r:?}")),
+        Operand::Reg16(r) => ("Reg16", "Reg16", format!("{r:?}")),
+        Operand::Reg8(r) => ("Reg8", "Reg8", format!("{r:?}")),
+        Operand::Xmm(r) => ("Xmm", "XmmReg", format!("{r:?}")),
+        Operand::Ymm(r) => ("Ymm", "YmmReg", format!("{r:?}")),
+        Operand::Zmm(r) => ("Zmm", "ZmmReg", format!("{r:?}")),
+        Operand::Tmm(r) => ("Tmm", "TmmReg", format!("{r:?}")),
+        Operand::Imm(value) => {
+            let value = Literal::i64_suffixed(*value);
+            return quote! { ::rask_x86_64::operand::Operand::Imm(#value) };
+        }
+        Operand::Mem(_) | Operand::Label(_) => unreachable!("not produced by the parser"),
+    };
+    let (variant, ty, name) = (
+        format_ident!("{variant}"),
+        format_ident!("{ty}"),
+        format_ident!("{name}"),
+    );
+    quote! { ::rask_x86_64::operand::Operand::#variant(::rask_x86_64::registers::#ty::#name) }
+}
+
+fn addr_reg(reg: AddrReg) -> TokenStream {
+    let (variant, ty, name) = match reg {
+        AddrReg::R64(r) => ("R64", "Reg64", format!("{r:?}")),
+        AddrReg::R32(r) => ("R32", "Reg32", format!("{r:?}")),
+        AddrReg::R16(r) => ("R16", "Reg16", format!("{r:?}")),
+        AddrReg::Rip => return quote! { ::rask_x86_64::operand::AddrReg::Rip },
+    };
+    let (variant, ty, name) = (
+        format_ident!("{variant}"),
+        format_ident!("{ty}"),
+        format_ident!("{name}"),
+    );
+    quote! { ::rask_x86_64::operand::AddrReg::#variant(::rask_x86_64::registers::#ty::#name) }
+}
+
+/// Generates a `MemOperand` literal, adding interpolated displacements to
+/// the constant one at run time.
+fn mem_tokens(mem: &Mem) -> TokenStream {
+    let base = match mem.base {
+        Some(reg) => {
+            let reg = addr_reg(reg);
+            quote! { ::core::option::Option::Some(#reg) }
+        }
+        None => quote! { ::core::option::Option::None },
+    };
+    let index = match &mem.index {
+        Some((index, scale)) => {
+            let index = match index {
+                Index::Static(reg) => addr_reg(*reg),
+                Index::Expr(expr) => quote! {
+                    ::core::convert::Into::<::rask_x86_64::operand::AddrReg>::into(#expr)
+                },
+            };
+            let scale = variant(scale);
+            quote! {
+                ::core::option::Option::Some((#index, ::rask_x86_64::operand::Scale::#scale))
+            }
+        }
+        None => quote! { ::core::option::Option::None },
+    };
+
+    let mut disp = {
+        let value = Literal::i32_suffixed(mem.disp);
+        quote! { #value }
+    };
+    for (negative, expr) in &mem.disp_exprs {
+        let term = quote! { ::core::convert::Into::<i32>::into(#expr) };
+        disp = if *negative {
+            quote! { #disp - #term }
+        } else {
+            quote! { #disp + #term }
+        };
+    }
+
+    let size = match mem.size {
+        Some(size) => {
+            let size = variant(size);
+            quote! { ::core::option::Option::Some(::rask_x86_64::operand::MemSize::#size) }
+        }
+        None => quote! { ::core::option::Option::None },
+    };
+    let segment = match mem.segment {
+        Some(seg) => {
+            let seg = variant(seg);
+            quote! { ::core::option::Option::Some(::rask_x86_64::registers::SegReg::#seg) }
+        }
+        None => quote! { ::core::option::Option::None },
+    };
+
+    quote! {
+        ::rask_x86_64::operand::MemOperand {
+            base: #base,
+            index: #index,
+            disp: #disp,
+            size: #size,
+            segment: #segment,
+        }
+    }
+}
+
+/// Returns the identifier of an enum variant, from its `Debug` name.
+fn variant(value: impl Debug) -> Ident {
+    Ident::new(&format!("{value:?}"), Span::call_site())
+}
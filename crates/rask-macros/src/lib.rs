@@ -0,0 +1,99 @@
+//! Compile-time x86_64 assembly for Rask.
+//!
+//! [`rask_asm!`] takes Intel-syntax instructions and expands them into calls
+//! on a [`rask_x86_64`] `Encoder`. The text is parsed while the crate
+//! compiles, so a misspelled mnemonic or register, or an operand combination
+//! the instruction does not have, is a compile error pointing at the
+//! offending token rather than an error at run time.
+
+use proc_macro::TokenStream;
+use quote::quote_spanned;
+
+mod expand;
+mod parse;
+
+/// Assembles Intel-syntax instructions into an `Encoder`.
+///
+/// The first argument is the encoder, followed by `;`-separated statements.
+/// The macro evaluates to `RaskResult<()>` and stops at the first
+/// instruction that fails to encode.
+///
+/// ```
+/// use rask_macros::rask_asm;
+/// use rask_x86_64::encoder::Encoder;
+/// use rask_x86_64::registers::Reg64::RSI;
+///
+/// let mut enc = Encoder::new();
+/// let offset = 16;
+/// let src = RSI;
+/// rask_asm!(enc;
+///     mov rax, [rdi + {offset}];
+///     top:
+///     add rax, {src};
+///     dec rcx;
+///     jnz top;
+///     ret
+/// )?;
+/// assert_eq!(enc.bytes(), &[
+///     0x48, 0x8B, 0x47, 0x10, // mov rax, [rdi + 16]
+///     0x48, 0x01, 0xF0,       // top: add rax, rsi
+///     0x48, 0xFF, 0xC9,       // dec rcx
+///     0x75, 0xF8,             // jnz top
+///     0xC3,                   // ret
+/// ]);
+/// # Ok::<(), rask_x86_64::RaskError>(())
+/// ```
+///
+/// ### Statements
+/// ```text
+/// mnemonic op, op, ...    an instruction; `lock` may precede it
+/// name:                   a label, local to this invocation
+/// {label}:                binds a `Label` created with `create_label`
+/// ```
+///
+/// ### Operands
+/// ```text
+/// rax, r8d, xmm3, ...     registers, in any case
+/// 42, -1, 0xFF, 0b1010    integer immediates
+/// name                    a label defined in the same invocation
+/// {expr}                  any Rust expression convertible to `Operand`:
+///                         a register, an integer, a `MemOperand`, a `Label`
+/// [base + index*scale + disp]
+/// qword ptr fs:[...]      an explicit size and a segment override
+/// ```
+///
+/// Inside brackets, a term may also be `{expr}`, a displacement of any type
+/// convertible to `i32`, or `{expr}*scale`, an index of any type
+/// convertible to `AddrReg`.
+///
+/// Instructions whose operands are all written out (interpolated
+/// displacements aside) are checked against the encoder while compiling.
+/// An instruction that no processor mode accepts is rejected; whether it
+/// suits the encoder's mode and features is still checked at run time.
+///
+/// ```compile_fail
+/// # use rask_macros::rask_asm;
+/// # let mut enc = rask_x86_64::encoder::Encoder::new();
+/// // error: invalid operands for MOV ...: no form of the instruction takes these operands
+/// rask_asm!(enc; mov 1, rax)?;
+/// # Ok::<(), rask_x86_64::RaskError>(())
+/// ```
+///
+/// ```compile_fail
+/// # use rask_macros::rask_asm;
+/// # let mut enc = rask_x86_64::encoder::Encoder::new();
+/// // error: base and index registers must have the same width
+/// rask_asm!(enc; mov rax, [rbx + ecx*2])?;
+/// # Ok::<(), rask_x86_64::RaskError>(())
+/// ```
+#[proc_macro]
+pub fn rask_asm(input: TokenStream) -> TokenStream {
+    let result = parse::parse(input.into()).and_then(|program| expand::expand(&program));
+    match result {
+        Ok(tokens) => tokens.into(),
+        Err(err) => {
+            let message = err.message;
+            quote_spanned!(err.span=> ::core::compile_error!(#message)).into()
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! Parsing of the `rask_asm!` input into statements.
+//!
+//! The input is a Rust token stream, so the parser works on token trees:
+//! a bracket group is a memory operand, a brace group is an interpolated
+//! Rust expression, and `;` separates statements.
+
+use proc_macro2::{Delimiter, Ident, Span, TokenStream, TokenTree};
+use rask_x86_64::{
+    instruction::{MAX_OPERANDS, Mnemonic},
+    operand::{AddrReg, MemSize, Operand, Scale},
+    registers::SegReg,
+};
+
+/// A compile error at `span`.
+pub struct Error {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The whole macro input: the encoder expression and the statements.
+pub struct Program {
+    pub encoder: TokenStream,
+    pub stmts: Vec<Stmt>,
+}
+
+pub enum Stmt {
+    /// `name:` or `{expr}:`, binding a label to the current position.
+    Bind(LabelRef),
+    Insn(Insn),
+}
+
+pub enum LabelRef {
+    /// A label declared by the macro itself.
+    Local(Ident),
+    /// A Rust expression of type `Label`.
+    Expr(TokenStream),
+}
+
+pub struct Insn {
+    pub lock: bool,
+    pub mnemonic: Mnemonic,
+    pub span: Span,
+    pub operands: Vec<Op>,
+}
+
+pub enum Op {
+    /// A register or immediate known at compile time.
+    Static(Operand),
+    /// A whole operand given as a Rust expression.
+    Expr(TokenStream),
+    /// A reference to a label declared by the macro.
+    Label(Ident),
+    Mem(Mem),
+}
+
+pub struct Mem {
+    pub size: Option<MemSize>,
+    pub segment: Option<SegReg>,
+    pub base: Option<AddrReg>,
+    pub index: Option<(Index, Scale)>,
+    /// The sum of the literal displacement terms.
+    pub disp: i32,
+    /// Interpolated displacement terms, with `true` for a subtracted one.
+    pub disp_exprs: Vec<(bool, TokenStream)>,
+}
+
+pub enum Index {
+    Static(AddrReg),
+    Expr(TokenStream),
+}
+
+/// Parses the macro input.
+pub fn parse(input: TokenStream) -> Result<Program> {
+    let mut segments = split(input.into_iter().collect(), ';').into_iter();
+    let encoder: Vec<TokenTree> = segments.next().unwrap_or_default();
+    if encoder.is_empty() {
+        return Err(Error::new(
+            Span::call_site(),
+            "expected an encoder expression, as in `rask_asm!(enc; ret)`",
+        ));
+    }
+
+    let mut stmts = Vec::new();
+    for segment in segments {
+        parse_segment(&segment, &mut stmts)?;
+    }
+    Ok(Program {
+        encoder: encoder.into_iter().collect(),
+        stmts,
+    })
+}
+
+/// Parses one `;`-separated segment: any number of label definitions,
+/// optionally followed by an instruction.
+fn parse_segment(mut tokens: &[TokenTree], stmts: &mut Vec<Stmt>) -> Result<()> {
+    while let [first, TokenTree::Punct(colon), rest @ ..] = tokens
+        && colon.as_char() == ':'
+    {
+        let label = match first {
+            TokenTree::Ident(name) => LabelRef::Local(name.clone()),
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => LabelRef::Expr(g.stream()),
+            _ => break,
+        };
+        stmts.push(Stmt::Bind(label));
+        tokens = rest;
+    }
+    if tokens.is_empty() {
+        return Ok(());
+    }
+
+    let mut lock = false;
+    let (mnemonic, rest) = loop {
+        match tokens {
+            [TokenTree::Ident(name), rest @ ..] if name == "lock" && !lock => {
+                lock = true;
+                tokens = rest;
+            }
+            [TokenTree::Ident(name), rest @ ..] => break (name, rest),
+            [other, ..] => return Err(Error::new(other.span(), "expected an instruction")),
+            [] => return Err(Error::new(Span::call_site(), "expected an instruction")),
+        }
+    };
+    let span = mnemonic.span();
+    let name = mnemonic.to_string();
+    let mnemonic: Mnemonic = name
+        .parse()
+        .map_err(|_| Error::new(span, format!("unknown instruction `{name}`")))?;
+
+    let mut operands = Vec::new();
+    if !rest.is_empty() {
+        for operand in split(rest.to_vec(), ',') {
+            operands.push(parse_operand(&operand, span)?);
+        }
+    }
+    if operands.len() > MAX_OPERANDS {
+        return Err(Error::new(
+            span,
+            format!("instructions take at most {MAX_OPERANDS} operands"),
+        ));
+    }
+    stmts.push(Stmt::Insn(Insn {
+        lock,
+        mnemonic,
+        span,
+        operands,
+    }));
+    Ok(())
+}
+
+fn parse_operand(tokens: &[TokenTree], span: Span) -> Result<Op> {
+    match tokens {
+        [] => Err(Error::new(span, "expected an operand")),
+        [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => Ok(Op::Expr(g.stream())),
+        [TokenTree::Ident(name)] => {
+            let text = name.to_string();
+            if let Some(reg) = Operand::register(&text) {
+                Ok(Op::Static(reg))
+            } else if mem_size(&text).is_some() || segment(&text).is_some() {
+                Err(Error::new(
+                    name.span(),
+                    "expected a memory operand after this",
+                ))
+            } else {
+                Ok(Op::Label(name.clone()))
+            }
+        }
+        [TokenTree::Ident(name), rest @ ..] if mem_size(&name.to_string()).is_some() => {
+            let size = mem_size(&name.to_string());
+            let rest = match rest {
+                [TokenTree::Ident(ptr), rest @ ..] if ptr == "ptr" => rest,
+                _ => rest,
+            };
+            let mut mem = parse_segmented_mem(rest, name.span())?;
+            mem.size = size;
+            Ok(Op::Mem(mem))
+        }
+        [TokenTree::Ident(_), ..] | [TokenTree::Group(_)] => {
+            Ok(Op::Mem(parse_segmented_mem(tokens, span)?))
+        }
+        _ => Ok(Op::Static(Operand::Imm(parse_imm(tokens)?))),
+    }
+}
+
+/// Parses `[...]` or `seg:[...]`.
+fn parse_segmented_mem(tokens: &[TokenTree], span: Span) -> Result<Mem> {
+    match tokens {
+        [TokenTree::Group(g)] if g.delimiter() == Delimiter::Bracket => parse_mem(g),
+        [
+            TokenTree::Ident(seg),
+            TokenTree::Punct(colon),
+            TokenTree::Group(g),
+        ] if colon.as_char() == ':' && g.delimiter() == Delimiter::Bracket => {
+            let segment = segment(&seg.to_string()).ok_or_else(|| {
+                Error::new(seg.span(), format!("unknown segment register `{seg}`"))
+            })?;
+            let mut mem = parse_mem(g)?;
+            mem.segment = Some(segment);
+            Ok(mem)
+        }
+        [first, ..] => Err(Error::new(
+            first.span(),
+            "expected a memory operand `[...]`",
+        )),
+        [] => Err(Error::new(span, "expected a memory operand `[...]`")),
+    }
+}
+
+/// Parses the inside of `[...]`: registers, `reg*scale`, literal
+/// displacements and `{expr}` displacements, joined by `+` and `-`.
+fn parse_mem(group: &proc_macro2::Group) -> Result<Mem> {
+    let mut mem = Mem {
+        size: None,
+        segment: None,
+        base: None,
+        index: None,
+        disp: 0,
+        disp_exprs: Vec::new(),
+    };
+    let mut disp: i64 = 0;
+    let tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+    if tokens.is_empty() {
+        return Err(Error::new(group.span(), "empty memory operand"));
+    }
+
+    let mut negative = is_punct(&tokens[0], '-');
+    let mut i = usize::from(negative);
+    while i < tokens.len() {
+        // Terms end at the next `+` or `-`.
+        let end = (i + 1..tokens.len())
+            .find(|&j| is_punct(&tokens[j], '+') || is_punct(&tokens[j], '-'))
+            .unwrap_or(tokens.len());
+        let term = &tokens[i..end];
+        let bad_sign = |span| Error::new(span, "registers cannot be subtracted");
+        match term {
+            [TokenTree::Ident(reg)] => {
+                if negative {
+                    return Err(bad_sign(reg.span()));
+                }
+                let reg = addr_reg(reg)?;
+                if mem.base.is_none() {
+                    mem.base = Some(reg);
+                } else {
+                    set_index(&mut mem, Index::Static(reg), Scale::S1, group.span())?;
+                }
+            }
+            [TokenTree::Ident(reg), star, factor] | [factor, star, TokenTree::Ident(reg)]
+                if is_punct(star, '*') =>
+            {
+                if negative {
+                    return Err(bad_sign(reg.span()));
+                }
+                let scale = scale(factor)?;
+                set_index(&mut mem, Index::Static(addr_reg(reg)?), scale, group.span())?;
+            }
+            [TokenTree::Group(g), star, factor]
+                if is_punct(star, '*') && g.delimiter() == Delimiter::Brace =>
+            {
+                if negative {
+                    return Err(bad_sign(g.span()));
+                }
+                set_index(
+                    &mut mem,
+                    Index::Expr(g.stream()),
+                    scale(factor)?,
+                    group.span(),
+                )?;
+            }
+            [TokenTree::Group(g)] if g.delimiter() == Delimiter::Brace => {
+                mem.disp_exprs.push((negative, g.stream()));
+            }
+            [TokenTree::Literal(_)] => {
+                let value = parse_imm(term)?;
+                disp = if negative { disp - value } else { disp + value };
+            }
+            [first, ..] => return Err(Error::new(first.span(), "invalid memory operand term")),
+            [] => {}
+        }
+        if end == tokens.len() {
+            break;
+        }
+        negative = is_punct(&tokens[end], '-');
+        i = end + 1;
+        if i == tokens.len() {
+            return Err(Error::new(tokens[end].span(), "expected a term after this"));
+        }
+    }
+
+    mem.disp = i32::try_from(disp).map_err(|_| {
+        Error::new(
+            group.span(),
+            format!("displacement {disp} does not fit in 32 bits"),
+        )
+    })?;
+    if let (Some(base), Some((Index::Static(index), _))) = (mem.base, &mem.index)
+        && base.bits() != index.bits()
+    {
+        return Err(Error::new(
+            group.span(),
+            "base and index registers must have the same width",
+        ));
+    }
+    Ok(mem)
+}
+
+fn set_index(mem: &mut Mem, index: Index, scale: Scale, span: Span) -> Result<()> {
+    if mem.index.is_some() {
+        return Err(Error::new(
+            span,
+            "a memory operand has at most one index register",
+        ));
+    }
+    mem.index = Some((index, scale));
+    Ok(())
+}
+
+fn addr_reg(name: &Ident) -> Result<AddrReg> {
+    match Operand::register(&name.to_string()) {
+        Some(Operand::Reg(r)) => Ok(r.into()),
+        Some(Operand::Reg32(r)) => Ok(r.into()),
+        Some(Operand::Reg16(r)) => Ok(r.into()),
+        Some(_) => Err(Error::new(
+            name.span(),
+            format!("`{name}` cannot address memory"),
+        )),
+        None => Err(Error::new(
+            name.span(),
+            format!("unknown register `{name}`"),
+        )),
+    }
+}
+
+fn scale(token: &TokenTree) -> Result<Scale> {
+    match parse_imm(std::slice::from_ref(token)) {
+        Ok(1) => Ok(Scale::S1),
+        Ok(2) => Ok(Scale::S2),
+        Ok(4) => Ok(Scale::S4),
+        Ok(8) => Ok(Scale::S8),
+        _ => Err(Error::new(token.span(), "scale must be 1, 2, 4 or 8")),
+    }
+}
+
+/// Parses an integer literal, optionally negated. Values up to `u64::MAX`
+/// are accepted and wrap into the `i64` immediate.
+fn parse_imm(tokens: &[TokenTree]) -> Result<i64> {
+    let (negative, literal) = match tokens {
+        [TokenTree::Punct(minus), TokenTree::Literal(lit)] if minus.as_char() == '-' => (true, lit),
+        [TokenTree::Literal(lit)] => (false, lit),
+        [first, ..] => return Err(Error::new(first.span(), "expected an operand")),
+        [] => return Err(Error::new(Span::call_site(), "expected an operand")),
+    };
+    let span = literal.span();
+    let text = literal.to_string().replace('_', "");
+    let (digits, radix) = match text.get(..2) {
+        Some("0x" | "0X") => (&text[2..], 16),
+        Some("0b" | "0B") => (&text[2..], 2),
+        Some("0o" | "0O") => (&text[2..], 8),
+        _ => (text.as_str(), 10),
+    };
+    // Drop an integer type suffix such as `u8` or `i64`.
+    let digits = match digits.find(['i', 'u']) {
+        Some(at) => &digits[..at],
+        None => digits,
+    };
+    let value = u64::from_str_radix(digits, radix)
+        .map_err(|_| Error::new(span, format!("expected an integer, found `{literal}`")))?;
+    if !negative {
+        return Ok(value as i64);
+    }
+    i64::try_from(-i128::from(value))
+        .map_err(|_| Error::new(span, format!("-{value} does not fit in 64 bits")))
+}
+
+fn mem_size(name: &str) -> Option<MemSize> {
+    match name.to_ascii_lowercase().as_str() {
+        "byte" => Some(MemSize::Byte),
+        "word" => Some(MemSize::Word),
+        "dword" => Some(MemSize::Dword),
+        "qword" => Some(MemSize::Qword),
+        _ => None,
+    }
+}
+
+fn segment(name: &str) -> Option<SegReg> {
+    name.parse().ok()
+}
+
+fn is_punct(token: &TokenTree, ch: char) -> bool {
+    matches!(token, TokenTree::Punct(p) if p.as_char() == ch)
+}
+
+/// Splits `tokens` at every top-level `sep`.
+fn split(tokens: Vec<TokenTree>, sep: char) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    for token in tokens {
+        if is_punct(&token, sep) {
+            parts.push(Vec::new());
+        } else if let Some(last) = parts.last_mut() {
+            last.push(token);
+        }
+    }
+    parts
+}
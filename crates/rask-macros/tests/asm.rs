@@ -0,0 +1,206 @@
+use rask_macros::rask_asm;
+use rask_x86_64::{
+    RaskError, RaskResult,
+    encoder::Encoder,
+    mode::Mode,
+    operand::{MemOperand, Operand},
+    registers::{Reg32::ECX, Reg64::*},
+    sink::SliceSink,
+};
+
+/// Helper to format mismatches clearly when comparing byte sequences.
+fn assert_bytes(actual: &[u8], expected: &[u8]) {
+    if actual != expected {
+        println!("Expected: {:02x?}", expected);
+        println!("Actual:   {:02x?}", actual);
+        panic!("Byte sequence mismatch");
+    }
+}
+
+#[test]
+fn test_registers_and_immediates() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    rask_asm!(enc;
+        xor eax, eax;
+        add rax, 1;
+        sub R9, 0x3;
+        cmp ecx, 1_00;
+        mov rax, 0x1122334455667788;
+        aesenc xmm1, xmm2;
+        ret
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(enc.bytes(), &[
+        0x31, 0xC0,                   // xor eax, eax
+        0x48, 0x83, 0xC0, 0x01,       // add rax, 1
+        0x49, 0x83, 0xE9, 0x03,       // sub r9, 3
+        0x83, 0xF9, 0x64,             // cmp ecx, 100
+        0x48, 0xB8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11,
+        0x66, 0x0F, 0x38, 0xDC, 0xCA, // aesenc xmm1, xmm2
+        0xC3,                         // ret
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_memory_operands() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    rask_asm!(enc;
+        mov rcx, [rbx + rcx*8 - 8];
+        mov eax, dword ptr fs:[rax];
+        mov rdx, [rsp];
+        mov dword ptr [rdi + 4*rsi + 12], -1;
+        mov byte ptr [rax - 0x80], 0x7f;
+        mov ax, word ptr [r12];
+        crc32 eax, byte ptr [rsi];
+        prefetcht0 byte ptr [rsi + 64]
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(enc.bytes(), &[
+        0x48, 0x8B, 0x4C, 0xCB, 0xF8,       // mov rcx, [rbx + rcx*8 - 8]
+        0x64, 0x8B, 0x00,                   // mov eax, dword ptr fs:[rax]
+        0x48, 0x8B, 0x14, 0x24,             // mov rdx, [rsp]
+        0xC7, 0x44, 0xB7, 0x0C, 0xFF, 0xFF, 0xFF, 0xFF,
+        0xC6, 0x40, 0x80, 0x7F,             // mov byte ptr [rax - 0x80], 0x7f
+        0x66, 0x41, 0x8B, 0x04, 0x24,       // mov ax, word ptr [r12]
+        0xF2, 0x0F, 0x38, 0xF0, 0x06,       // crc32 eax, byte ptr [rsi]
+        0x0F, 0x18, 0x4E, 0x40,             // prefetcht0 byte ptr [rsi + 64]
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_interpolated_operands() -> RaskResult<()> {
+    let dst = R10;
+    let field = 0x10;
+    let stride = 8i8;
+    let index = RAX;
+    let slot = MemOperand::new(RDI, 0x40);
+    let mut enc = Encoder::new();
+    rask_asm!(enc;
+        mov rax, [rbx + {field}];
+        mov {dst}, [rdi + {index}*8 + {field} + {stride}];
+        movnti {slot}, rax;
+        cmp {ECX}, {100};
+        add rax, {-1i64}
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(enc.bytes(), &[
+        0x48, 0x8B, 0x43, 0x10,       // mov rax, [rbx + 0x10]
+        0x4C, 0x8B, 0x54, 0xC7, 0x18, // mov r10, [rdi + rax*8 + 0x18]
+        0x48, 0x0F, 0xC3, 0x47, 0x40, // movnti [rdi + 0x40], rax
+        0x83, 0xF9, 0x64,             // cmp ecx, 100
+        0x48, 0x83, 0xC0, 0xFF,       // add rax, -1
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_matches_typed_methods() -> RaskResult<()> {
+    let mut expected = Encoder::new();
+    expected.mov(Operand::Reg(RAX), Operand::Mem(MemOperand::new(RDI, -24)))?;
+    expected.add(RAX, RSI)?;
+    expected.inc(Operand::Reg(RAX))?;
+
+    let mut enc = Encoder::new();
+    rask_asm!(enc; mov rax, [rdi - 24]; add rax, rsi; inc rax)?;
+    assert_bytes(enc.bytes(), expected.bytes());
+    Ok(())
+}
+
+#[test]
+fn test_lock_prefix() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    rask_asm!(enc; lock add qword ptr [rdi], rax)?;
+    assert_bytes(enc.bytes(), &[0xF0, 0x48, 0x01, 0x07]);
+    Ok(())
+}
+
+#[test]
+fn test_local_labels() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    rask_asm!(enc;
+        jmp check;
+        top: dec rcx;
+        check: cmp rcx, 0;
+        jne top;
+        call done;
+        done: ret
+    )?;
+
+    #[rustfmt::skip]
+    assert_bytes(&enc.finish()?, &[
+        0xE9, 0x03, 0x00, 0x00, 0x00, // jmp check
+        0x48, 0xFF, 0xC9,             // top: dec rcx
+        0x48, 0x83, 0xF9, 0x00,       // check: cmp rcx, 0
+        0x75, 0xF7,                   // jne top
+        0xE8, 0x00, 0x00, 0x00, 0x00, // call done
+        0xC3,                         // done: ret
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_labels_are_local_to_each_invocation() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    for _ in 0..2 {
+        rask_asm!(enc; top: dec rcx; jnz top)?;
+    }
+    #[rustfmt::skip]
+    assert_bytes(enc.bytes(), &[
+        0x48, 0xFF, 0xC9, 0x75, 0xFB,
+        0x48, 0xFF, 0xC9, 0x75, 0xFB,
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_external_labels() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    let exit = enc.create_label();
+    rask_asm!(enc; jz {exit}; inc rax)?;
+    rask_asm!(enc; {exit}: ret)?;
+
+    assert_bytes(
+        &enc.finish()?,
+        &[0x0F, 0x84, 0x03, 0x00, 0x00, 0x00, 0x48, 0xFF, 0xC0, 0xC3],
+    );
+    Ok(())
+}
+
+#[test]
+fn test_encoder_expression() -> RaskResult<()> {
+    fn emit(enc: &mut Encoder) -> RaskResult<()> {
+        rask_asm!(enc; xor eax, eax; ret)
+    }
+
+    let mut encoders = [Encoder::new(), Encoder::new()];
+    emit(&mut encoders[0])?;
+    rask_asm!(encoders[1]; ret)?;
+    assert_bytes(encoders[0].bytes(), &[0x31, 0xC0, 0xC3]);
+    assert_bytes(encoders[1].bytes(), &[0xC3]);
+    Ok(())
+}
+
+#[test]
+fn test_errors_stop_at_the_failing_instruction() {
+    // 64-bit registers only exist in 64-bit mode, which the encoder checks
+    // at run time.
+    let mut enc = Encoder::with_mode(Mode::Protected32);
+    let result = rask_asm!(enc; inc ecx; mov rax, 1; ret);
+    assert!(matches!(result, Err(RaskError::UnsupportedFeature { .. })));
+    assert_bytes(enc.bytes(), &[0x41]);
+
+    let mut memory = [0; 4];
+    let mut enc = Encoder::with_sink(SliceSink::new(&mut memory));
+    let result = rask_asm!(enc; xor eax, eax; add rax, 1; ret);
+    assert!(matches!(result, Err(RaskError::BufferOverflow { .. })));
+    assert_bytes(enc.sink().written(), &[0x31, 0xC0]);
+
+    let mut enc = Encoder::new();
+    let result = rask_asm!(enc; mov rax, {ECX});
+    assert!(matches!(result, Err(RaskError::InvalidOperands { .. })));
+}
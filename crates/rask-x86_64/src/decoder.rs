@@ -0,0 +1,717 @@
+//! x86-64 instruction decoder
+//!
+//! The [`Decoder`] turns machine code back into [`Instruction`] values:
+//! it reads the prefixes, opcode, ModR/M, SIB, displacement and immediate
+//! of one instruction at a time and reports each as a [`Decoded`], which
+//! also records the instruction's address, length and raw bytes.
+//!
+//! ```
+//! use rask_x86_64::decoder::Decoder;
+//! use rask_x86_64::instruction::{Instruction, Mnemonic};
+//! use rask_x86_64::operand::{MemOperand, MemSize, Operand};
+//! use rask_x86_64::registers::Reg64::{RAX, RBX};
+//!
+//! // mov rax, qword ptr [rbx + 8]; ret
+//! let code = [0x48, 0x8B, 0x43, 0x08, 0xC3];
+//! let mut decoder = Decoder::new(&code);
+//!
+//! let mov = decoder.decode()?;
+//! let src = MemOperand::new(RBX, 8).with_size(MemSize::Qword);
+//! assert_eq!(mov.instruction(), Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Mem(src)));
+//! assert_eq!(mov.len(), 4);
+//!
+//! let ret = decoder.decode()?;
+//! assert_eq!(ret.instruction().mnemonic(), Mnemonic::Ret);
+//! assert_eq!(ret.address(), 4);
+//! # Ok::<(), rask_x86_64::RaskError>(())
+//! ```
+//!
+//! Decoding covers everything [`Encoder`](crate::encoder::Encoder) emits,
+//! in all three [`Mode`]s. Legacy-encoded instructions (with REX or REX2)
+//! are looked up in the instruction table in [`crate::table`], in the
+//! opposite direction to [`Encoder::emit_instruction`](crate::encoder::Encoder::emit_instruction);
+//! branches and the VEX, EVEX and APX map-4 forms are decoded by hand, as
+//! they are encoded.
+//!
+//! Decoded instructions are normalised the way the encoder builds them:
+//!
+//! * An `r/m` memory operand carries its access width as a [`MemSize`];
+//!   memory operands whose width is implied by the instruction have none.
+//! * Immediates the processor sign-extends decode as signed values, all
+//!   others as unsigned ones (`mov eax, 0xFFFFFFFF`, `add rax, -1`).
+//! * A direct branch (`jmp`, `jcc`, `call`) has its absolute target address
+//!   as an [`Operand::Imm`], computed from [`Decoder::set_address`]; see
+//!   [`Decoded::branch_target`].
+//! * A `mod = 00`, `r/m = 101` operand in 64-bit mode is RIP-relative and
+//!   decodes to [`MemOperand::rip`].
+//!
+//! Bytes that are not a valid instruction are reported as
+//! [`RaskError::InvalidEncoding`], valid instructions rask does not know as
+//! [`RaskError::UnsupportedEncoding`], and input that ends in the middle of
+//! an instruction as [`RaskError::TruncatedInstruction`].
+
+mod legacy;
+mod vex;
+
+use crate::{
+    instruction::{Instruction, MAX_INSTRUCTION_LEN},
+    mode::Mode,
+    operand::{AddrReg, MemOperand, MemSize, Operand, Scale},
+    registers::{Reg8, Reg16, Reg32, Reg64, SegReg},
+    table::InstrDef,
+};
+use rask_common::{RaskError, RaskResult};
+
+/// How an instruction's opcode and register extensions are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Legacy prefixes and opcode, with an optional REX prefix.
+    Legacy,
+    /// The APX REX2 prefix (`D5`) in front of a legacy opcode.
+    Rex2,
+    /// A two- or three-byte VEX prefix (`C5`/`C4`).
+    Vex,
+    /// A four-byte EVEX prefix (`62`), including the APX map-4 forms.
+    Evex,
+}
+
+/// One decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoded {
+    instruction: Instruction,
+    address: u64,
+    bytes: [u8; MAX_INSTRUCTION_LEN],
+    len: u8,
+    prefix_len: u8,
+    rex: Option<u8>,
+    encoding: Encoding,
+    branch_target: Option<u64>,
+    layout: Layout,
+}
+
+/// Where the fields after the opcode sit in an instruction's bytes, and
+/// the table form it matched, for [`crate::explain`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Layout {
+    /// Offset of the ModR/M byte.
+    pub(crate) modrm: Option<u8>,
+    /// Offset of the SIB byte.
+    pub(crate) sib: Option<u8>,
+    /// Offset and length of the displacement.
+    pub(crate) disp: Option<(u8, u8)>,
+    /// Offset of the first immediate byte, or of a branch's relative
+    /// target; immediates run to the end of the instruction.
+    pub(crate) imm: Option<u8>,
+    /// The instruction-table form of a legacy or REX2 instruction.
+    pub(crate) form: Option<&'static InstrDef>,
+}
+
+impl Decoded {
+    /// Returns the instruction.
+    #[inline]
+    pub fn instruction(&self) -> Instruction {
+        self.instruction
+    }
+
+    /// Returns the address of the first byte, see [`Decoder::set_address`].
+    #[inline]
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Returns the address of the next instruction.
+    #[inline]
+    pub fn end(&self) -> u64 {
+        self.address.wrapping_add(self.len.into())
+    }
+
+    /// Returns the length of the encoding in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len.into()
+    }
+
+    /// Always false: an instruction is at least one byte long.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the encoded bytes.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len()]
+    }
+
+    /// Returns the legacy prefixes (`F0`, `F2`, `F3`, `66`, `67` and the
+    /// segment overrides) in the order they appear, without REX.
+    #[inline]
+    pub fn prefixes(&self) -> &[u8] {
+        &self.bytes[..self.prefix_len.into()]
+    }
+
+    /// Returns the REX prefix byte, if the instruction has one.
+    #[inline]
+    pub fn rex(&self) -> Option<u8> {
+        self.rex
+    }
+
+    /// Returns how the instruction is encoded.
+    #[inline]
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the target address of a direct `jmp`, `jcc` or `call`, or
+    /// `None` for any other instruction. It is also the branch's operand.
+    #[inline]
+    pub fn branch_target(&self) -> Option<u64> {
+        self.branch_target
+    }
+
+    #[inline]
+    pub(crate) fn layout(&self) -> &Layout {
+        &self.layout
+    }
+}
+
+/// Decodes instructions from a byte slice, one at a time or as an
+/// [`Iterator`].
+///
+/// The iterator yields every instruction up to the end of the input. After
+/// an invalid or unsupported encoding it resumes at the next byte, like a
+/// disassembler resynchronising, so collect into a `RaskResult<Vec<_>>` to
+/// stop at the first error instead:
+///
+/// ```
+/// use rask_x86_64::decoder::Decoder;
+/// use rask_x86_64::instruction::Mnemonic;
+///
+/// let code = [0x90, 0x48, 0x01, 0xD8, 0xC3];
+/// let mnemonics = Decoder::new(&code)
+///     .map(|d| d.map(|d| d.instruction().mnemonic()))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(mnemonics, [Mnemonic::Nop, Mnemonic::Add, Mnemonic::Ret]);
+/// # Ok::<(), rask_x86_64::RaskError>(())
+/// ```
+pub struct Decoder<'a> {
+    /// The code being decoded.
+    bytes: &'a [u8],
+    /// Offset of the next instruction in `bytes`.
+    pos: usize,
+    /// Address of `bytes[0]`.
+    address: u64,
+    /// Processor mode the code is decoded for.
+    mode: Mode,
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a decoder for 64-bit code at address 0.
+    #[inline]
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_mode(bytes, Mode::Long64)
+    }
+
+    /// Creates a decoder for code in the given processor mode, at address 0.
+    ///
+    /// ```
+    /// use rask_x86_64::decoder::Decoder;
+    /// use rask_x86_64::mode::Mode;
+    /// use rask_x86_64::instruction::Mnemonic;
+    ///
+    /// // 40 is `inc eax` in 32-bit mode, but a REX prefix in 64-bit mode.
+    /// let inc = Decoder::with_mode(&[0x40], Mode::Protected32).decode()?;
+    /// assert_eq!(inc.instruction().mnemonic(), Mnemonic::Inc);
+    /// assert!(Decoder::new(&[0x40]).decode().is_err());
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    #[inline]
+    pub fn with_mode(bytes: &'a [u8], mode: Mode) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            address: 0,
+            mode,
+        }
+    }
+
+    /// Returns the processor mode the code is decoded for.
+    #[inline]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Switches the processor mode for the instructions that follow.
+    #[inline]
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// Returns the address of the first byte of the input.
+    #[inline]
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Sets the address the first byte of the input is loaded at. Branch
+    /// targets are computed from it.
+    #[inline]
+    pub fn set_address(&mut self, address: u64) {
+        self.address = address;
+    }
+
+    /// Returns the offset in the input of the next instruction.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Continues decoding at offset `pos`, for example to skip data
+    /// embedded in the code.
+    #[inline]
+    pub fn set_position(&mut self, pos: usize) {
+        self.pos = pos.min(self.bytes.len());
+    }
+
+    /// Decodes the instruction at the current position and advances past
+    /// it.
+    ///
+    /// On error the position advances by one byte, or to the end of the
+    /// input if the instruction is truncated.
+    pub fn decode(&mut self) -> RaskResult<Decoded> {
+        let start = self.pos;
+        let address = self.address.wrapping_add(start as u64);
+        let result = Insn::new(&self.bytes[start..], start, self.mode).decode(address);
+        self.pos = match &result {
+            Ok(decoded) => start + decoded.len(),
+            Err(RaskError::TruncatedInstruction { .. }) => self.bytes.len(),
+            Err(_) => start + 1,
+        };
+        result
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = RaskResult<Decoded>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.bytes.len() {
+            return None;
+        }
+        Some(self.decode())
+    }
+}
+
+/// Register-ID extension bits from a REX, REX2, VEX or EVEX prefix,
+/// already shifted into place: `r`, `x` and `b` hold bits 3 and 4 of the
+/// `reg`, index and base (or `r/m`) IDs.
+#[derive(Debug, Clone, Copy, Default)]
+struct Ext {
+    w: bool,
+    r: u8,
+    x: u8,
+    b: u8,
+    /// A REX or REX2 prefix is present, so byte registers 4–7 are SPL–DIL.
+    rex: bool,
+}
+
+/// The fields of a ModR/M byte.
+#[derive(Debug, Clone, Copy)]
+struct ModRmByte {
+    md: u8,
+    reg: u8,
+    rm: u8,
+}
+
+impl ModRmByte {
+    #[inline]
+    fn new(byte: u8) -> Self {
+        Self {
+            md: byte >> 6,
+            reg: (byte >> 3) & 0x07,
+            rm: byte & 0x07,
+        }
+    }
+
+    /// Returns true for register-direct addressing (`mod = 11`).
+    #[inline]
+    fn is_reg(self) -> bool {
+        self.md == 0b11
+    }
+}
+
+/// A decoded `r/m` operand: a full register ID or a memory operand.
+#[derive(Debug, Clone, Copy)]
+enum Rm {
+    Reg(u8),
+    Mem(MemOperand),
+}
+
+/// The state of decoding one instruction.
+struct Insn<'a> {
+    /// The input from the first byte of the instruction on.
+    bytes: &'a [u8],
+    /// Number of bytes consumed.
+    pos: usize,
+    /// Offset of the instruction in the decoder's input, for errors.
+    offset: usize,
+    mode: Mode,
+    lock: bool,
+    /// The last `F2`/`F3` prefix.
+    rep: Option<u8>,
+    /// A `66` prefix is present.
+    opsize: bool,
+    /// A `67` prefix is present.
+    addrsize: bool,
+    segment: Option<SegReg>,
+    prefix_len: usize,
+    rex: Option<u8>,
+    ext: Ext,
+    layout: Layout,
+}
+
+impl<'a> Insn<'a> {
+    fn new(bytes: &'a [u8], offset: usize, mode: Mode) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            offset,
+            mode,
+            lock: false,
+            rep: None,
+            opsize: false,
+            addrsize: false,
+            segment: None,
+            prefix_len: 0,
+            rex: None,
+            ext: Ext::default(),
+            layout: Layout::default(),
+        }
+    }
+
+    /// Decodes the instruction, which starts at `address`.
+    fn decode(mut self, address: u64) -> RaskResult<Decoded> {
+        self.prefixes();
+        let next = self.peek_at(1);
+        let vex_escape = self.mode == Mode::Long64 || next.is_some_and(|b| b >= 0xC0);
+        let (instruction, encoding, branch_target) = match self.peek() {
+            Some(0xD5) if self.mode == Mode::Long64 => (self.rex2()?, Encoding::Rex2, None),
+            Some(0xC4 | 0xC5) if vex_escape => (self.vex()?, Encoding::Vex, None),
+            Some(0x62) if vex_escape => (self.evex()?, Encoding::Evex, None),
+            _ => {
+                let (insn, target) = self.legacy(address)?;
+                (insn, Encoding::Legacy, target)
+            }
+        };
+
+        // Opcode bytes are matched before they are consumed, so check the
+        // length once more.
+        if self.pos > MAX_INSTRUCTION_LEN {
+            return Err(self.too_long());
+        }
+        let mut bytes = [0; MAX_INSTRUCTION_LEN];
+        bytes[..self.pos].copy_from_slice(&self.bytes[..self.pos]);
+        Ok(Decoded {
+            instruction,
+            address,
+            bytes,
+            len: self.pos as u8,
+            prefix_len: self.prefix_len as u8,
+            rex: self.rex,
+            encoding,
+            branch_target,
+            layout: self.layout,
+        })
+    }
+
+    /// Reads the legacy prefixes and, in 64-bit mode, a REX prefix. A REX
+    /// prefix only counts when it comes last; the processor ignores one
+    /// followed by another prefix.
+    fn prefixes(&mut self) {
+        while self.pos < MAX_INSTRUCTION_LEN
+            && let Some(byte) = self.peek()
+        {
+            match byte {
+                0xF0 => self.lock = true,
+                0xF2 | 0xF3 => self.rep = Some(byte),
+                0x66 => self.opsize = true,
+                0x67 => self.addrsize = true,
+                0x40..=0x4F if self.mode == Mode::Long64 => {
+                    self.rex = Some(byte);
+                    self.pos += 1;
+                    continue;
+                }
+                _ => match SegReg::ALL.iter().find(|s| s.prefix() == byte) {
+                    Some(&seg) => self.segment = Some(seg),
+                    None => break,
+                },
+            }
+            self.rex = None;
+            self.pos += 1;
+            self.prefix_len = self.pos;
+        }
+        if let Some(rex) = self.rex {
+            self.ext = Ext {
+                w: rex & 0x08 != 0,
+                r: (rex & 0x04) << 1,
+                x: (rex & 0x02) << 2,
+                b: (rex & 0x01) << 3,
+                rex: true,
+            };
+        }
+    }
+
+    // -------------------------------------------------------------------------
+    // Reading bytes
+    // -------------------------------------------------------------------------
+
+    fn invalid(&self, reason: impl Into<String>) -> RaskError {
+        RaskError::InvalidEncoding {
+            offset: self.offset,
+            reason: reason.into(),
+        }
+    }
+
+    fn unsupported(&self, reason: impl Into<String>) -> RaskError {
+        RaskError::UnsupportedEncoding {
+            offset: self.offset,
+            reason: reason.into(),
+        }
+    }
+
+    fn too_long(&self) -> RaskError {
+        self.invalid(format!(
+            "instruction is longer than {MAX_INSTRUCTION_LEN} bytes"
+        ))
+    }
+
+    fn truncated(&self) -> RaskError {
+        RaskError::TruncatedInstruction {
+            offset: self.offset,
+        }
+    }
+
+    /// Returns the next byte without consuming it.
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.peek_at(0)
+    }
+
+    /// Returns the byte `ahead` bytes past the next one without consuming
+    /// anything.
+    #[inline]
+    fn peek_at(&self, ahead: usize) -> Option<u8> {
+        self.bytes.get(self.pos + ahead).copied()
+    }
+
+    /// Consumes the next byte.
+    ///
+    /// Fails at the end of the input, or when the instruction would grow
+    /// past [`MAX_INSTRUCTION_LEN`] bytes.
+    fn byte(&mut self) -> RaskResult<u8> {
+        if self.pos >= MAX_INSTRUCTION_LEN {
+            return Err(self.too_long());
+        }
+        let Some(&byte) = self.bytes.get(self.pos) else {
+            return Err(self.truncated());
+        };
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    /// Consumes a little-endian value of `len` bytes.
+    fn le(&mut self, len: usize) -> RaskResult<u64> {
+        let mut value = 0;
+        for i in 0..len {
+            value |= u64::from(self.byte()?) << (8 * i);
+        }
+        Ok(value)
+    }
+
+    /// Consumes a little-endian immediate of `len` bytes.
+    fn imm(&mut self, len: usize) -> RaskResult<u64> {
+        if self.layout.imm.is_none() {
+            self.layout.imm = Some(self.pos as u8);
+        }
+        self.le(len)
+    }
+
+    /// Consumes an `imm8` and returns it as an operand.
+    fn imm8(&mut self) -> RaskResult<Operand> {
+        Ok(Operand::Imm(self.imm(1)? as i64))
+    }
+
+    /// Fails if any prefix that VEX and EVEX replace (`66`, `F2`, `F3`,
+    /// `LOCK`, REX) precedes the instruction.
+    fn forbid_legacy_prefixes(&self, what: &str) -> RaskResult<()> {
+        if self.opsize || self.rep.is_some() || self.lock || self.rex.is_some() {
+            return Err(self.invalid(format!(
+                "{what} cannot follow a 66, F2, F3, LOCK or REX prefix"
+            )));
+        }
+        Ok(())
+    }
+
+    // -------------------------------------------------------------------------
+    // Operand sizes and registers
+    // -------------------------------------------------------------------------
+
+    /// Returns the operand size in bits selected by `REX.W` and the `66`
+    /// prefix, unless `66` is a mandatory prefix.
+    fn operand_bits(&self, mandatory_66: bool) -> u32 {
+        let default = self.mode.default_operand_bits();
+        match (self.ext.w, self.opsize && !mandatory_66) {
+            (true, _) => 64,
+            (false, true) => 48 - default,
+            (false, false) => default,
+        }
+    }
+
+    /// Returns the address size in bits selected by the `67` prefix.
+    fn address_bits(&self) -> u32 {
+        match (self.mode, self.addrsize) {
+            (Mode::Long64, false) => 64,
+            (Mode::Long64, true) | (Mode::Protected32, false) | (Mode::Real16, true) => 32,
+            (Mode::Protected32, true) | (Mode::Real16, false) => 16,
+        }
+    }
+
+    /// Returns the general-purpose register with `id` of `bits` width.
+    /// Byte registers 4–7 are AH–BH unless a REX or REX2 prefix is present.
+    fn gpr(&self, bits: u32, id: u8) -> Operand {
+        let id = usize::from(id);
+        match bits {
+            8 if (4..8).contains(&id) && !self.ext.rex => Operand::Reg8(Reg8::ALL[32 + id - 4]),
+            8 => Operand::Reg8(Reg8::ALL[id]),
+            16 => Operand::Reg16(Reg16::ALL[id]),
+            32 => Operand::Reg32(Reg32::ALL[id]),
+            _ => Operand::Reg(Reg64::ALL[id]),
+        }
+    }
+
+    // -------------------------------------------------------------------------
+    // ModR/M, SIB and displacement
+    // -------------------------------------------------------------------------
+
+    /// Consumes a ModR/M byte.
+    fn modrm(&mut self) -> RaskResult<ModRmByte> {
+        self.layout.modrm = Some(self.pos as u8);
+        Ok(ModRmByte::new(self.byte()?))
+    }
+
+    /// Decodes the `r/m` operand of `modrm`, consuming the SIB byte and
+    /// displacement. A register ID is extended with `ext.b`; memory
+    /// operands use `ext.b` and `ext.x` for the base and index, and get the
+    /// segment override. `disp_n` is the EVEX compressed-displacement
+    /// factor, 1 elsewhere.
+    fn rm(&mut self, modrm: ModRmByte, disp_n: i32) -> RaskResult<Rm> {
+        if modrm.is_reg() {
+            return Ok(Rm::Reg(modrm.rm | self.ext.b));
+        }
+        let mut mem = match self.address_bits() {
+            16 => self.mem16(modrm, disp_n)?,
+            bits => self.mem32(modrm, bits, disp_n)?,
+        };
+        mem.segment = self.segment;
+        Ok(Rm::Mem(mem))
+    }
+
+    /// Decodes a 32- or 64-bit memory operand.
+    fn mem32(&mut self, modrm: ModRmByte, bits: u32, disp_n: i32) -> RaskResult<MemOperand> {
+        let reg = |id: u8| match bits {
+            64 => AddrReg::R64(Reg64::ALL[usize::from(id)]),
+            _ => AddrReg::R32(Reg32::ALL[usize::from(id)]),
+        };
+
+        let (base, index) = if modrm.rm == 0b100 {
+            self.layout.sib = Some(self.pos as u8);
+            let sib = self.byte()?;
+            let index = ((sib >> 3) & 0x07) | self.ext.x;
+            let scale = [Scale::S1, Scale::S2, Scale::S4, Scale::S8][usize::from(sib >> 6)];
+            // Index 100 without an extension means "no index".
+            let index = (index != 0b100).then(|| (reg(index), scale));
+            let base = sib & 0x07;
+            if modrm.md == 0b00 && base == 0b101 {
+                (None, index)
+            } else {
+                (Some(reg(base | self.ext.b)), index)
+            }
+        } else if modrm.md == 0b00 && modrm.rm == 0b101 {
+            if self.mode != Mode::Long64 {
+                (None, None)
+            } else if bits == 64 {
+                (Some(AddrReg::Rip), None)
+            } else {
+                return Err(self.unsupported("EIP-relative addressing"));
+            }
+        } else {
+            (Some(reg(modrm.rm | self.ext.b)), None)
+        };
+
+        let start = self.pos;
+        let disp = match (modrm.md, base) {
+            (0b01, _) => i32::from(self.byte()? as i8) * disp_n,
+            (0b10, _) | (0b00, None | Some(AddrReg::Rip)) => self.le(4)? as u32 as i32,
+            _ => 0,
+        };
+        self.mark_disp(start);
+        Ok(MemOperand {
+            base,
+            index,
+            disp,
+            size: None,
+            segment: None,
+        })
+    }
+
+    /// Decodes a 16-bit memory operand: `[BX/BP + SI/DI + disp]`, or an
+    /// absolute disp16 for `mod = 00`, `r/m = 110`.
+    fn mem16(&mut self, modrm: ModRmByte, disp_n: i32) -> RaskResult<MemOperand> {
+        use Reg16::{BP, BX, DI, SI};
+
+        let start = self.pos;
+        if modrm.md == 0b00 && modrm.rm == 0b110 {
+            let disp = self.le(2)? as i32;
+            self.mark_disp(start);
+            return Ok(MemOperand::absolute(disp));
+        }
+        let (base, index) = [
+            (BX, Some(SI)),
+            (BX, Some(DI)),
+            (BP, Some(SI)),
+            (BP, Some(DI)),
+            (SI, None),
+            (DI, None),
+            (BP, None),
+            (BX, None),
+        ][usize::from(modrm.rm)];
+        let disp = match modrm.md {
+            0b01 => i32::from(self.byte()? as i8) * disp_n,
+            0b10 => i32::from(self.le(2)? as u16 as i16),
+            _ => 0,
+        };
+        self.mark_disp(start);
+        let mem = MemOperand::new(base, disp);
+        Ok(match index {
+            Some(index) => mem.with_index(index, Scale::S1),
+            None => mem,
+        })
+    }
+
+    /// Records the displacement read since `start`, if there is one.
+    fn mark_disp(&mut self, start: usize) {
+        if self.pos > start {
+            self.layout.disp = Some((start as u8, (self.pos - start) as u8));
+        }
+    }
+}
+
+/// Returns the [`MemSize`] of an access `bits` wide.
+fn mem_size(bits: u32) -> MemSize {
+    match bits {
+        8 => MemSize::Byte,
+        16 => MemSize::Word,
+        32 => MemSize::Dword,
+        _ => MemSize::Qword,
+    }
+}
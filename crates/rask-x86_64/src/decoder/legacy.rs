@@ -0,0 +1,342 @@
+//! Legacy-encoded instructions: direct branches, and everything in the
+//! instruction table, with an optional REX or REX2 prefix.
+//!
+//! A table form matches when its opcode bytes, mandatory prefix, operand
+//! size, ModR/M `/digit` and `mod` agree with the input. Forms with a
+//! mandatory prefix win over forms that merely tolerate a `66` prefix, so
+//! `66 0F AE /7` is `clflushopt` rather than `clflush`.
+
+use super::{Ext, Insn, ModRmByte, Rm, mem_size};
+use crate::{
+    instruction::{Condition, Instruction, LOCKABLE, Mnemonic, Prefix},
+    mode::Mode,
+    operand::Operand,
+    registers::{Reg8, Reg16, Reg32, Reg64, XmmReg},
+    table::{INSTRUCTIONS, InstrDef, ModRm, OpSize, OperandKind},
+};
+use rask_common::RaskResult;
+
+/// The longest opcode in the table, including escape bytes and the fixed
+/// ModR/M byte of the fences.
+const MAX_OPCODE_LEN: usize = 4;
+
+impl Insn<'_> {
+    /// Decodes a legacy-encoded instruction.
+    pub(super) fn legacy(&mut self, address: u64) -> RaskResult<(Instruction, Option<u64>)> {
+        if let Some((insn, target)) = self.branch(address)? {
+            return Ok((self.lock(insn)?, Some(target)));
+        }
+        let insn = self.table(false)?;
+        Ok((self.lock(insn)?, None))
+    }
+
+    /// Decodes an instruction with a REX2 prefix:
+    ///
+    /// ```text
+    /// D5 [M0 R4 X4 B4 W R3 X3 B3] opcode ...
+    /// ```
+    ///
+    /// `M0` selects the `0F` map without its escape byte.
+    pub(super) fn rex2(&mut self) -> RaskResult<Instruction> {
+        if self.rex.is_some() {
+            return Err(self.invalid("REX2 cannot follow a REX prefix"));
+        }
+        self.byte()?;
+        let payload = self.byte()?;
+        let bit = |n: u8, shift: u8| ((payload >> n) & 1) << shift;
+        self.ext = Ext {
+            w: payload & 0x08 != 0,
+            r: bit(6, 4) | bit(2, 3),
+            x: bit(5, 4) | bit(1, 3),
+            b: bit(4, 4) | bit(0, 3),
+            rex: true,
+        };
+        let insn = self.table(payload & 0x80 != 0)?;
+        self.lock(insn)
+    }
+
+    /// Decodes a direct `jmp`, `jcc` or `call`, returning the instruction
+    /// and its target, or `None` for any other opcode.
+    ///
+    /// The target is truncated to the operand size, as the processor
+    /// truncates the instruction pointer.
+    fn branch(&mut self, address: u64) -> RaskResult<Option<(Instruction, u64)>> {
+        let cc = |byte: u8| Mnemonic::jcc(Condition::ALL[usize::from(byte & 0x0F)]);
+        let (mnemonic, opcode_len, short) = match (self.peek(), self.peek_at(1)) {
+            (Some(0xEB), _) => (Mnemonic::Jmp, 1, true),
+            (Some(0xE9), _) => (Mnemonic::Jmp, 1, false),
+            (Some(0xE8), _) => (Mnemonic::Call, 1, false),
+            (Some(byte @ 0x70..=0x7F), _) => (cc(byte), 1, true),
+            (Some(0x0F), Some(byte @ 0x80..=0x8F)) => (cc(byte), 2, false),
+            _ => return Ok(None),
+        };
+        if self.rep.is_some() {
+            return Err(self.unsupported("F2/F3 prefix on a branch"));
+        }
+        let bits = self.operand_bits(false);
+        if self.mode == Mode::Long64 && bits == 16 {
+            return Err(self.unsupported("16-bit branches in 64-bit mode"));
+        }
+
+        self.pos += opcode_len;
+        let rel = match (short, bits) {
+            (true, _) => i64::from(self.imm(1)? as i8),
+            (false, 16) => i64::from(self.imm(2)? as u16 as i16),
+            (false, _) => i64::from(self.imm(4)? as u32 as i32),
+        };
+        let end = address.wrapping_add(self.pos as u64);
+        let target = end.wrapping_add(rel as u64)
+            & match (self.mode, bits) {
+                (_, 16) => 0xFFFF,
+                (Mode::Protected32 | Mode::Real16, _) => 0xFFFF_FFFF,
+                (Mode::Long64, _) => u64::MAX,
+            };
+        let insn = Instruction::with1(mnemonic, Operand::Imm(target as i64));
+        Ok(Some((insn, target)))
+    }
+
+    /// Applies a `LOCK` prefix to `insn`, which must be a read-modify-write
+    /// instruction with a memory destination.
+    fn lock(&self, insn: Instruction) -> RaskResult<Instruction> {
+        if !self.lock {
+            return Ok(insn);
+        }
+        let lockable = LOCKABLE.contains(&insn.mnemonic())
+            && insn.operand_count() <= 2
+            && matches!(insn.operand(0), Some(Operand::Mem(_)));
+        if !lockable {
+            return Err(self.invalid(format!(
+                "LOCK prefix on `{}`, which cannot be locked",
+                insn.mnemonic()
+            )));
+        }
+        Ok(insn.with_prefix(Prefix::Lock))
+    }
+
+    // -------------------------------------------------------------------------
+    // Table lookup
+    // -------------------------------------------------------------------------
+
+    /// Decodes an instruction from the table. `map0f` is set for a REX2
+    /// prefix with `M0 = 1`, whose `0F` escape is implied.
+    fn table(&mut self, map0f: bool) -> RaskResult<Instruction> {
+        let rex2 = self.ext.rex && self.rex.is_none();
+        let implied = usize::from(map0f);
+        let mut window = [0x0F; MAX_OPCODE_LEN];
+        let mut len = implied;
+        while len < MAX_OPCODE_LEN
+            && let Some(byte) = self.peek_at(len - implied)
+        {
+            window[len] = byte;
+            len += 1;
+        }
+        let window = &window[..len];
+
+        let in_map = |def: &InstrDef| match def.opcode {
+            [0x0F, 0x38 | 0x3A, ..] if rex2 => false,
+            [0x0F, ..] => !rex2 || map0f,
+            _ => !map0f,
+        };
+        let opcode_matches = |def: &InstrDef| {
+            let (last, head) = def.opcode.split_last().expect("opcode must not be empty");
+            in_map(def)
+                && window.len() >= def.opcode.len()
+                && window.starts_with(head)
+                && match def.modrm {
+                    ModRm::PlusR => window[head.len()] & !0x07 == *last,
+                    _ => window[head.len()] == *last,
+                }
+        };
+
+        let forms = INSTRUCTIONS.iter().flat_map(|forms| forms.iter());
+        let candidates = || {
+            forms
+                .clone()
+                .filter(|def| opcode_matches(def) && self.accepts(def, implied))
+        };
+        let Some(def) = candidates()
+            .find(|def| def.prefix.is_some())
+            .or_else(|| candidates().next())
+        else {
+            let partial = forms.clone().any(|def| {
+                in_map(def) && def.opcode.len() > window.len() && def.opcode.starts_with(window)
+            });
+            return Err(if partial || window.len() == implied {
+                self.truncated()
+            } else {
+                self.unsupported(format!("opcode {}", hex(window)))
+            });
+        };
+
+        self.pos += def.opcode.len() - implied;
+        self.layout.form = Some(def);
+        self.table_operands(def)
+    }
+
+    /// Returns true if `def`'s prefixes, operand size, `/digit` and `mod`
+    /// agree with the input. The opcode has already been matched; the
+    /// ModR/M byte, if `def` has one, follows it.
+    fn accepts(&self, def: &InstrDef, implied: usize) -> bool {
+        if !def.modes.allows(self.mode) {
+            return false;
+        }
+        let mandatory_66 = def.prefix == Some(0x66);
+        let prefix_ok = match def.prefix {
+            Some(0x66) => self.opsize && self.rep.is_none(),
+            Some(p) => self.rep == Some(p),
+            None => self.rep.is_none(),
+        };
+        let bits = self.operand_bits(mandatory_66);
+        let size_ok = match def.size {
+            OpSize::O64 => self.ext.w,
+            OpSize::O16 => !self.ext.w && bits == 16,
+            OpSize::O32 => !self.ext.w && bits == 32,
+            // `66` is harmless on byte and SSE forms, but would change the
+            // width of any other operand.
+            OpSize::None => {
+                !self.ext.w && (!self.opsize || mandatory_66 || !def.operands.iter().any(sized))
+            }
+        };
+        if !prefix_ok || !size_ok {
+            return false;
+        }
+
+        let modrm = self.peek_at(def.opcode.len() - implied).map(ModRmByte::new);
+        match (def.modrm, modrm) {
+            (ModRm::None, _) => self.ext.r | self.ext.x | self.ext.b == 0,
+            (ModRm::PlusR, _) => true,
+            // Without a ModR/M byte the form can only be confirmed once more
+            // input arrives; reading it reports the truncation.
+            (_, None) => true,
+            (ModRm::R, Some(m)) => def.operands.iter().all(|k| fits_mod(*k, m)),
+            (ModRm::Digit(digit), Some(m)) => {
+                let reg_in_rm = !def.operands.iter().any(|k| k.is_rm());
+                m.reg == digit
+                    && (!reg_in_rm || m.is_reg())
+                    && def.operands.iter().all(|k| fits_mod(*k, m))
+            }
+        }
+    }
+
+    /// Decodes the operands of `def`, whose opcode has been consumed.
+    fn table_operands(&mut self, def: &InstrDef) -> RaskResult<Instruction> {
+        let (reg, rm) = match def.modrm {
+            ModRm::R => {
+                let modrm = self.modrm()?;
+                (Some(modrm.reg | self.ext.r), Some(self.rm(modrm, 1)?))
+            }
+            ModRm::Digit(_) => {
+                let modrm = self.modrm()?;
+                (None, Some(self.rm(modrm, 1)?))
+            }
+            ModRm::PlusR => {
+                let last = self.bytes[self.pos - 1];
+                (Some((last & 0x07) | self.ext.b), None)
+            }
+            ModRm::None => (None, None),
+        };
+        // A `/digit` form's only register operand is the `r/m` one.
+        let reg = reg.or(match rm {
+            Some(Rm::Reg(id)) if !def.operands.iter().any(|k| k.is_rm()) => Some(id),
+            _ => None,
+        });
+
+        let mut operands = [Operand::Imm(0); 4];
+        for (slot, kind) in operands.iter_mut().zip(def.operands) {
+            *slot = self.table_operand(*kind, reg, rm)?;
+        }
+        let mnemonic = def
+            .mnemonic
+            .parse::<Mnemonic>()
+            .expect("table mnemonics are Mnemonic variants");
+        Instruction::from_operands(mnemonic, &operands[..def.operands.len()])
+    }
+
+    /// Builds the operand of `kind` from the decoded `reg` and `rm` fields,
+    /// reading immediates from the input.
+    fn table_operand(
+        &mut self,
+        kind: OperandKind,
+        reg: Option<u8>,
+        rm: Option<Rm>,
+    ) -> RaskResult<Operand> {
+        use OperandKind as K;
+
+        let reg = || reg.expect("register operand without a register field");
+        let rm = || rm.expect("r/m operand without a ModR/M byte");
+        Ok(match kind {
+            K::Reg8 => self.gpr(8, reg()),
+            K::Reg16 => self.gpr(16, reg()),
+            K::Reg32 => self.gpr(32, reg()),
+            K::Reg64 => self.gpr(64, reg()),
+            K::Xmm => self.xmm(reg())?,
+            K::Rm8 | K::Rm16 | K::Rm32 | K::Rm64 => {
+                let bits = kind_bits(kind);
+                match rm() {
+                    Rm::Reg(id) => self.gpr(bits, id),
+                    Rm::Mem(mem) => Operand::Mem(mem.with_size(mem_size(bits))),
+                }
+            }
+            K::Mem => match rm() {
+                Rm::Mem(mem) => Operand::Mem(mem),
+                Rm::Reg(_) => unreachable!("`mod = 11` is rejected for memory operands"),
+            },
+            K::XmmM128 => match rm() {
+                Rm::Reg(id) => self.xmm(id)?,
+                Rm::Mem(mem) => Operand::Mem(mem),
+            },
+            K::Al => Operand::Reg8(Reg8::AL),
+            K::Ax => Operand::Reg16(Reg16::AX),
+            K::Eax => Operand::Reg32(Reg32::EAX),
+            K::Rax => Operand::Reg(Reg64::RAX),
+            K::Imm8 => Operand::Imm(self.imm(1)? as i64),
+            K::Imm16 => Operand::Imm(self.imm(2)? as i64),
+            K::Imm32 => Operand::Imm(self.imm(4)? as i64),
+            K::Imm64 => Operand::Imm(self.imm(8)? as i64),
+            K::SImm8 => Operand::Imm((self.imm(1)? as u8 as i8).into()),
+            K::SImm32 => Operand::Imm((self.imm(4)? as u32 as i32).into()),
+        })
+    }
+
+    /// Returns XMM register `id`, which must be below 16.
+    pub(super) fn xmm(&self, id: u8) -> RaskResult<Operand> {
+        XmmReg::ALL
+            .get(usize::from(id))
+            .map(|&r| Operand::Xmm(r))
+            .ok_or_else(|| self.invalid(format!("XMM register {id} does not exist")))
+    }
+}
+
+/// Returns true for kinds whose width follows the operand size.
+fn sized(kind: &OperandKind) -> bool {
+    use OperandKind as K;
+    matches!(
+        kind,
+        K::Reg16 | K::Reg32 | K::Reg64 | K::Rm16 | K::Rm32 | K::Rm64 | K::Ax | K::Eax | K::Rax
+    )
+}
+
+/// Returns false if `kind` cannot be encoded with the `mod` field of
+/// `modrm`: memory-only kinds need `mod != 11`.
+fn fits_mod(kind: OperandKind, modrm: ModRmByte) -> bool {
+    kind != OperandKind::Mem || !modrm.is_reg()
+}
+
+/// Returns the width in bits of an `r/m` kind.
+fn kind_bits(kind: OperandKind) -> u32 {
+    match kind {
+        OperandKind::Rm8 => 8,
+        OperandKind::Rm16 => 16,
+        OperandKind::Rm32 => 32,
+        _ => 64,
+    }
+}
+
+/// Formats bytes as space-separated hex, the way the SDM writes opcodes.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
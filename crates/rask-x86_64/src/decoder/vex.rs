@@ -0,0 +1,406 @@
+//! VEX- and EVEX-encoded instructions: the AVX cryptography and
+//! non-temporal forms, AMX, the AVX-512 (ZMM) vector forms and the APX
+//! map-4 integer forms.
+//!
+//! The vector forms are listed in [`VEC_FORMS`], mirroring the `VecOp`
+//! descriptions the encoder uses; AMX and APX are decoded by hand, as they
+//! are encoded.
+
+use super::{Ext, Insn, ModRmByte, Rm, mem_size};
+use crate::{
+    instruction::{Instruction, Mnemonic},
+    mode::Mode,
+    operand::Operand,
+    registers::{Reg64, TmmReg, XmmReg, YmmReg, ZmmReg},
+};
+use rask_common::RaskResult;
+
+/// The operand layout of a vector form.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    /// `op vec, vec/mem`, without `vvvv`.
+    Rm,
+    /// `op vec, vec/mem, imm8`, without `vvvv`.
+    Rmi,
+    /// `op vec, vvvv, vec/mem`.
+    Rvm,
+    /// `op vec, vvvv, vec/mem, imm8`.
+    Rvmi,
+    /// `op mem, vec`: a store, without `vvvv`.
+    Store,
+    /// `op vec, mem`: a load, without `vvvv`.
+    Load,
+}
+
+/// A VEX or EVEX vector instruction form, as in the SDM opcode column
+/// (`VEX.256.66.0F38.WIG DC /r`).
+#[derive(Clone, Copy)]
+struct VecForm {
+    mnemonic: Mnemonic,
+    /// Implied legacy prefix: 0 = none, 1 = `66`, 2 = `F3`, 3 = `F2`.
+    pp: u8,
+    /// Opcode map: 1 = `0F`, 2 = `0F 38`, 3 = `0F 3A`.
+    map: u8,
+    opcode: u8,
+    /// Required `W` bit, or `None` for `WIG`.
+    w: Option<bool>,
+    /// Widest vector length: 0 = 128, 1 = 256, 2 = 512 bits (EVEX).
+    max_len: u8,
+    shape: Shape,
+}
+
+const fn form(mnemonic: Mnemonic, map: u8, opcode: u8, max_len: u8, shape: Shape) -> VecForm {
+    VecForm {
+        mnemonic,
+        pp: 1,
+        map,
+        opcode,
+        w: None,
+        max_len,
+        shape,
+    }
+}
+
+#[rustfmt::skip]
+static VEC_FORMS: &[VecForm] = &[
+    form(Mnemonic::Vmovntdq, 1, 0xE7, 1, Shape::Store),
+    VecForm { pp: 0, ..form(Mnemonic::Vmovntps, 1, 0x2B, 1, Shape::Store) },
+    form(Mnemonic::Vmovntpd, 1, 0x2B, 1, Shape::Store),
+    form(Mnemonic::Vmovntdqa, 2, 0x2A, 1, Shape::Load),
+    form(Mnemonic::Vaesenc, 2, 0xDC, 2, Shape::Rvm),
+    form(Mnemonic::Vaesenclast, 2, 0xDD, 2, Shape::Rvm),
+    form(Mnemonic::Vaesdec, 2, 0xDE, 2, Shape::Rvm),
+    form(Mnemonic::Vaesdeclast, 2, 0xDF, 2, Shape::Rvm),
+    form(Mnemonic::Vaesimc, 2, 0xDB, 0, Shape::Rm),
+    form(Mnemonic::Vaeskeygenassist, 3, 0xDF, 0, Shape::Rmi),
+    form(Mnemonic::Vpclmulqdq, 3, 0x44, 2, Shape::Rvmi),
+    VecForm { w: Some(true), ..form(Mnemonic::Vgf2p8affineinvqb, 3, 0xCF, 2, Shape::Rvmi) },
+    VecForm { w: Some(true), ..form(Mnemonic::Vgf2p8affineqb, 3, 0xCE, 2, Shape::Rvmi) },
+    VecForm { w: Some(false), ..form(Mnemonic::Vgf2p8mulb, 2, 0xCF, 2, Shape::Rvm) },
+];
+
+/// The APX NDD ALU operations by `/digit`; `CMP` (`/7`) has no NDD form.
+const NDD_ALU: [Mnemonic; 7] = [
+    Mnemonic::Add,
+    Mnemonic::Or,
+    Mnemonic::Adc,
+    Mnemonic::Sbb,
+    Mnemonic::And,
+    Mnemonic::Sub,
+    Mnemonic::Xor,
+];
+
+/// The fields of a VEX or EVEX prefix that select the instruction.
+#[derive(Clone, Copy)]
+struct VecPrefix {
+    map: u8,
+    pp: u8,
+    w: bool,
+    /// Vector length: 0 = 128, 1 = 256, 2 = 512 bits.
+    len: u8,
+    /// The extra register ID, already un-inverted.
+    vvvv: u8,
+    /// EVEX rather than VEX, so `disp8` is scaled by the vector size.
+    evex: bool,
+}
+
+impl Insn<'_> {
+    /// Decodes a VEX-encoded instruction:
+    ///
+    /// ```text
+    /// C5 [R vvvv L pp]              opcode ModR/M [SIB] [disp] [imm8]
+    /// C4 [R X B mmmmm] [W vvvv L pp] opcode ModR/M [SIB] [disp] [imm8]
+    /// ```
+    pub(super) fn vex(&mut self) -> RaskResult<Instruction> {
+        self.forbid_legacy_prefixes("VEX")?;
+        // The two-byte form implies X̄ = B̄ = 1, the 0F map and W0.
+        let (p0, p1) = match self.byte()? {
+            0xC5 => {
+                let p = self.byte()?;
+                ((p & 0x80) | 0x61, p & 0x7F)
+            }
+            _ => (self.byte()?, self.byte()?),
+        };
+        self.ext = Ext {
+            w: p1 & 0x80 != 0,
+            r: (!p0 >> 4) & 0x08,
+            x: (!p0 >> 3) & 0x08,
+            b: (!p0 >> 2) & 0x08,
+            rex: false,
+        };
+        let mut vex = VecPrefix {
+            map: p0 & 0x1F,
+            pp: p1 & 0x03,
+            w: self.ext.w,
+            len: (p1 >> 2) & 1,
+            vvvv: (!p1 >> 3) & 0x0F,
+            evex: false,
+        };
+        if self.mode != Mode::Long64 {
+            self.ext = Ext::default();
+            vex.vvvv &= 0x07;
+        }
+
+        let opcode = self.byte()?;
+        let modrm = self.modrm()?;
+        if vex.map == 2 && matches!(opcode, 0x49 | 0x4B | 0x5C | 0x5E) {
+            return self.amx(vex, opcode, modrm);
+        }
+        self.vector(vex, opcode, modrm)
+    }
+
+    /// Decodes an EVEX-encoded instruction:
+    ///
+    /// ```text
+    /// 62 [R X B R' B4 m m m] [W vvvv X4 pp] [z L'L b V' aaa] opcode ModR/M ...
+    /// ```
+    ///
+    /// Map 4 holds the APX integer forms; the other maps the vector forms,
+    /// of which only the unmasked, non-broadcast 512-bit ones are supported.
+    pub(super) fn evex(&mut self) -> RaskResult<Instruction> {
+        self.forbid_legacy_prefixes("EVEX")?;
+        self.byte()?;
+        let (p0, p1, p2) = (self.byte()?, self.byte()?, self.byte()?);
+        let map = p0 & 0x07;
+        let vvvv = ((!p1 >> 3) & 0x0F) | ((!p2 << 1) & 0x10);
+        let x4 = (!p1 << 2) & 0x10;
+        let b4 = (p0 << 1) & 0x10;
+        let r = ((!p0 >> 4) & 0x08) | (!p0 & 0x10);
+        let (x3, b3) = ((!p0 >> 3) & 0x08, (!p0 >> 2) & 0x08);
+
+        if map == 4 {
+            if self.mode != Mode::Long64 {
+                return Err(self.invalid("APX instructions require 64-bit mode"));
+            }
+            if p1 & 0x03 != 0 || p2 & !0x18 != 0 {
+                return Err(self.unsupported("APX map-4 forms with NF or an implied prefix"));
+            }
+            self.ext = Ext {
+                w: p1 & 0x80 != 0,
+                r,
+                x: x4 | x3,
+                b: b4 | b3,
+                rex: true,
+            };
+            return self.apx(vvvv, p2 & 0x10 != 0);
+        }
+
+        if p2 & 0x97 != 0 {
+            return Err(self.unsupported("EVEX masking, zeroing and embedded broadcast"));
+        }
+        let mut evex = VecPrefix {
+            map,
+            pp: p1 & 0x03,
+            w: p1 & 0x80 != 0,
+            len: (p2 >> 5) & 0x03,
+            vvvv,
+            evex: true,
+        };
+        if evex.len != 2 {
+            return Err(self.unsupported("EVEX-encoded 128- and 256-bit vectors"));
+        }
+
+        let opcode = self.byte()?;
+        let modrm = self.modrm()?;
+        // A register `r/m` takes its fifth ID bit from X rather than B4.
+        self.ext = Ext {
+            w: evex.w,
+            r,
+            x: x4 | x3,
+            b: if modrm.is_reg() {
+                (x3 << 1) | b3
+            } else {
+                b4 | b3
+            },
+            rex: false,
+        };
+        if self.mode != Mode::Long64 {
+            self.ext = Ext::default();
+            evex.vvvv &= 0x07;
+        }
+        self.vector(evex, opcode, modrm)
+    }
+
+    /// Decodes a vector form from [`VEC_FORMS`].
+    fn vector(&mut self, vex: VecPrefix, opcode: u8, modrm: ModRmByte) -> RaskResult<Instruction> {
+        let Some(form) = VEC_FORMS.iter().find(|f| {
+            f.map == vex.map
+                && f.opcode == opcode
+                && f.pp == vex.pp
+                && f.w.is_none_or(|w| w == vex.w)
+                && vex.len <= f.max_len
+        }) else {
+            return Err(self.unsupported(format!(
+                "{} map {} opcode {opcode:02X}",
+                if vex.evex { "EVEX" } else { "VEX" },
+                vex.map
+            )));
+        };
+        let uses_vvvv = matches!(form.shape, Shape::Rvm | Shape::Rvmi);
+        if !uses_vvvv && vex.vvvv != 0 {
+            return Err(self.invalid("vvvv must be 1111 when it encodes no register"));
+        }
+
+        let reg = self.vec(vex.len, modrm.reg | self.ext.r)?;
+        let disp_n = if vex.evex { 16 << vex.len } else { 1 };
+        let rm = match self.rm(modrm, disp_n)? {
+            Rm::Reg(_) if matches!(form.shape, Shape::Store | Shape::Load) => {
+                return Err(self.invalid(format!("`{}` needs a memory operand", form.mnemonic)));
+            }
+            Rm::Reg(id) => self.vec(vex.len, id)?,
+            Rm::Mem(mem) => Operand::Mem(mem),
+        };
+        let src1 = self.vec(vex.len, vex.vvvv)?;
+
+        let m = form.mnemonic;
+        Ok(match form.shape {
+            Shape::Rm | Shape::Load => Instruction::with2(m, reg, rm),
+            Shape::Rmi => Instruction::with3(m, reg, rm, self.imm8()?),
+            Shape::Rvm => Instruction::with3(m, reg, src1, rm),
+            Shape::Rvmi => Instruction::with4(m, reg, src1, rm, self.imm8()?),
+            Shape::Store => Instruction::with2(m, rm, reg),
+        })
+    }
+
+    /// Decodes an AMX tile instruction (`VEX.128.<pp>.0F38.W0`).
+    fn amx(&mut self, vex: VecPrefix, opcode: u8, modrm: ModRmByte) -> RaskResult<Instruction> {
+        use Mnemonic::*;
+
+        if vex.w || vex.len != 0 {
+            return Err(self.invalid("AMX instructions are VEX.128.W0"));
+        }
+        let uses_vvvv = matches!(opcode, 0x5C | 0x5E);
+        if !uses_vvvv && vex.vvvv != 0 {
+            return Err(self.invalid("vvvv must be 1111 when it encodes no register"));
+        }
+        let mnemonic = match (opcode, vex.pp, modrm.is_reg()) {
+            (0x49, 0, false) => Ldtilecfg,
+            (0x49, 1, false) => Sttilecfg,
+            (0x49, 0, true) if modrm.reg == 0 && modrm.rm == 0 => Tilerelease,
+            (0x49, 3, true) if modrm.rm == 0 => Tilezero,
+            (0x4B, 3, false) => Tileloadd,
+            (0x4B, 1, false) => Tileloaddt1,
+            (0x4B, 2, false) => Tilestored,
+            (0x5E, 3, true) => Tdpbssd,
+            (0x5E, 2, true) => Tdpbsud,
+            (0x5E, 1, true) => Tdpbusd,
+            (0x5E, 0, true) => Tdpbuud,
+            (0x5C, 2, true) => Tdpbf16ps,
+            _ => {
+                return Err(self.unsupported(format!(
+                    "AMX opcode {opcode:02X} with pp = {} and ModR/M {:02X}",
+                    vex.pp,
+                    (modrm.md << 6) | (modrm.reg << 3) | modrm.rm
+                )));
+            }
+        };
+        if opcode == 0x4B && modrm.rm != 0b100 {
+            return Err(self.invalid("tile loads and stores need a SIB byte"));
+        }
+
+        let reg = modrm.reg | self.ext.r;
+        let rm = self.rm(modrm, 1)?;
+        let m = mnemonic;
+        Ok(match (mnemonic, rm) {
+            (Tilerelease, _) => Instruction::new(m),
+            (Ldtilecfg | Sttilecfg, Rm::Mem(mem)) => Instruction::with1(m, Operand::Mem(mem)),
+            (Tilezero, _) => Instruction::with1(m, self.tmm(reg)?),
+            (Tileloadd | Tileloaddt1, Rm::Mem(mem)) => {
+                Instruction::with2(m, self.tmm(reg)?, Operand::Mem(mem))
+            }
+            (Tilestored, Rm::Mem(mem)) => Instruction::with2(m, Operand::Mem(mem), self.tmm(reg)?),
+            (_, Rm::Reg(id)) => {
+                Instruction::with3(m, self.tmm(reg)?, self.tmm(id)?, self.tmm(vex.vvvv)?)
+            }
+            _ => unreachable!("`mod` was checked with the mnemonic"),
+        })
+    }
+
+    /// Decodes an APX instruction in EVEX map 4: `PUSH2`/`POP2` and the
+    /// new-data-destination ALU forms. `vvvv` is the five-bit extra
+    /// register ID and `nd` the ND bit.
+    fn apx(&mut self, vvvv: u8, nd: bool) -> RaskResult<Instruction> {
+        let opcode = self.byte()?;
+        let modrm = self.modrm()?;
+        let w = self.ext.w;
+        if !nd {
+            return Err(self.unsupported("APX map-4 forms without a new data destination"));
+        }
+
+        if matches!((opcode, modrm.reg), (0xFF, 6) | (0x8F, 0)) {
+            if !modrm.is_reg() {
+                return Err(self.invalid("PUSH2 and POP2 take register operands"));
+            }
+            let first = Reg64::ALL[usize::from(vvvv)];
+            let second = Reg64::ALL[usize::from(modrm.rm | self.ext.b)];
+            if first == Reg64::RSP || second == Reg64::RSP {
+                return Err(self.invalid("RSP cannot be an operand of PUSH2 or POP2"));
+            }
+            let mnemonic = match (opcode, w) {
+                (0xFF, false) => Mnemonic::Push2,
+                (0xFF, true) => Mnemonic::Push2p,
+                (_, false) => Mnemonic::Pop2,
+                (_, true) => Mnemonic::Pop2p,
+            };
+            if opcode == 0x8F && first == second {
+                return Err(self.invalid("POP2 destinations must be different registers"));
+            }
+            return Ok(Instruction::with2(
+                mnemonic,
+                Operand::Reg(first),
+                Operand::Reg(second),
+            ));
+        }
+
+        let digit = match opcode {
+            0x81 | 0x83 => modrm.reg,
+            _ if opcode < 0x40 && opcode & 0x05 == 0x01 => opcode >> 3,
+            _ => return Err(self.unsupported(format!("APX map-4 opcode {opcode:02X}"))),
+        };
+        let Some(&mnemonic) = NDD_ALU.get(usize::from(digit)) else {
+            return Err(self.unsupported("CMP has no new-data-destination form"));
+        };
+        if !w {
+            return Err(self.unsupported("32-, 16- and 8-bit new-data-destination forms"));
+        }
+
+        let dst = Operand::Reg(Reg64::ALL[usize::from(vvvv)]);
+        let reg = Operand::Reg(Reg64::ALL[usize::from(modrm.reg | self.ext.r)]);
+        let rm = match self.rm(modrm, 1)? {
+            Rm::Reg(id) => Operand::Reg(Reg64::ALL[usize::from(id)]),
+            Rm::Mem(mem) => Operand::Mem(mem.with_size(mem_size(64))),
+        };
+        let insn = match opcode {
+            0x81 => {
+                let imm = Operand::Imm((self.imm(4)? as u32 as i32).into());
+                Instruction::with3(mnemonic, dst, rm, imm)
+            }
+            0x83 => {
+                let imm = Operand::Imm((self.imm(1)? as u8 as i8).into());
+                Instruction::with3(mnemonic, dst, rm, imm)
+            }
+            _ if opcode & 0x02 == 0 => Instruction::with3(mnemonic, dst, rm, reg),
+            _ => Instruction::with3(mnemonic, dst, reg, rm),
+        };
+        Ok(insn)
+    }
+
+    /// Returns vector register `id` of length `len` (0 = XMM, 1 = YMM,
+    /// 2 = ZMM).
+    fn vec(&self, len: u8, id: u8) -> RaskResult<Operand> {
+        let reg = match len {
+            0 => XmmReg::ALL.get(usize::from(id)).map(|&r| Operand::Xmm(r)),
+            1 => YmmReg::ALL.get(usize::from(id)).map(|&r| Operand::Ymm(r)),
+            _ => ZmmReg::ALL.get(usize::from(id)).map(|&r| Operand::Zmm(r)),
+        };
+        reg.ok_or_else(|| self.invalid(format!("vector register {id} does not exist")))
+    }
+
+    /// Returns tile register `id`.
+    fn tmm(&self, id: u8) -> RaskResult<Operand> {
+        TmmReg::ALL
+            .get(usize::from(id))
+            .map(|&r| Operand::Tmm(r))
+            .ok_or_else(|| self.invalid(format!("tile register {id} does not exist")))
+    }
+}
@@ -1,216 +1,941 @@
 //! x86-64 instruction encoder
 //!
-//! This module provides low-level helpers for writing machine-code bytes
-//! directly into a `Vec<u8>`.  It currently supports a minimal subset of
-//! instructions, starting with `mov r64, imm64` and `ret`.
+//! [`Encoder`] writes machine code into a [`CodeSink`], by default a
+//! `Vec<u8>`. It is table-driven: legacy-encoded instructions are described
+//! as data in [`crate::table`] and emitted by [`Encoder::emit_instruction`],
+//! and [`Encoder::encode`] takes any
+//! [`Instruction`](crate::instruction::Instruction).
+//!
+//! Submodules add further `impl Encoder` blocks: the VEX, EVEX and APX
+//! forms that are still encoded by hand (crypto, AMX and APX), labels and
+//! branches, cache control, segment and legacy-mode instructions, padding,
+//! and the enumeration of alternative encodings.
+//!
+//! Every instruction method returns a [`RaskResult`]. Invalid operand
+//! combinations, immediates that do not fit, and features or modes the
+//! encoder does not target are reported as a [`RaskError`], and the failed
+//! instruction leaves the buffer unchanged. Callers that would rather panic
+//! can use [`Encoder::panicking`].
+//!
+//! Each instruction is staged in a small internal buffer and handed to the
+//! sink only once it has been encoded completely, so a sink never sees a
+//! partial instruction.
+
+mod alternatives;
+mod amx;
+mod apx;
+mod branch;
+mod cache;
+mod crypto;
+mod encode;
+mod generic;
+mod legacy;
+mod pad;
+mod panicking;
+mod segment;
+
+pub use alternatives::{Alternative, Variation};
+pub use panicking::Panicking;
+
+use crate::{
+    features::{CpuFeature, CpuFeatures},
+    mode::Mode,
+    operand::{AddrReg, MemOperand, Operand, Scale},
+    registers::{Reg8, Reg16, Reg64},
+    sink::CodeSink,
+};
+use rask_common::{RaskError, RaskResult};
+
+/// Returns a [`RaskError::InvalidOperands`] for `reason`. Shared helpers do
+/// not know which instruction they encode; [`Encoder::instruction`] fills
+/// in the mnemonic and operands.
+fn invalid(reason: impl Into<String>) -> RaskError {
+    RaskError::InvalidOperands {
+        mnemonic: String::new(),
+        operands: String::new(),
+        reason: reason.into(),
+    }
+}
+
+/// The `r/m` operand of a ModR/M-encoded instruction.
+#[derive(Clone, Copy)]
+enum Rm<'a> {
+    /// Register-direct addressing (`mod = 11`), holding the register ID.
+    Reg(u8),
+    /// Register-direct addressing of a byte register, which needs the REX
+    /// special-casing described on [`Reg8`].
+    Reg8(Reg8),
+    /// Memory addressing through a [`MemOperand`].
+    Mem(&'a MemOperand),
+    /// Memory addressing that must be expressed with a SIB byte even without
+    /// an index register (the SDM's `sibmem`, used by AMX tile loads/stores).
+    SibMem(&'a MemOperand),
+}
+
+impl<'a> Rm<'a> {
+    /// Full ID of the base (or r/m register), or 0 when there is none.
+    #[inline]
+    fn base_id(self) -> u8 {
+        match self {
+            Rm::Reg(id) => id,
+            Rm::Reg8(r) => r.id(),
+            Rm::Mem(m) | Rm::SibMem(m) => m.base.map_or(0, |b| b.id()),
+        }
+    }
+
+    /// The memory operand, if `r/m` addresses memory.
+    #[inline]
+    fn mem(self) -> Option<&'a MemOperand> {
+        match self {
+            Rm::Reg(_) | Rm::Reg8(_) => None,
+            Rm::Mem(m) | Rm::SibMem(m) => Some(m),
+        }
+    }
+
+    /// Full ID of the index register, or 0 when there is none.
+    #[inline]
+    fn index_id(self) -> u8 {
+        match self {
+            Rm::Reg(_) | Rm::Reg8(_) => 0,
+            Rm::Mem(m) | Rm::SibMem(m) => m.index.map_or(0, |(idx, _)| idx.id()),
+        }
+    }
+
+    /// Bit 3 of the base (or r/m register) ID, carried in REX.B / VEX.B.
+    #[inline]
+    fn ext_b(self) -> bool {
+        self.base_id() & 0x08 != 0
+    }
+
+    /// Bit 3 of the index register ID, carried in REX.X / VEX.X.
+    #[inline]
+    fn ext_x(self) -> bool {
+        self.index_id() & 0x08 != 0
+    }
+
+    /// Returns true if the base or index is one of the APX registers R16–R31.
+    ///
+    /// Only meaningful for general-purpose operands: a vector `r/m` register
+    /// carries its own fifth ID bit.
+    #[inline]
+    fn uses_egpr(self) -> bool {
+        self.base_id() >= 16 || self.index_id() >= 16
+    }
+}
 
-use crate::{operand::Operand, registers::Reg64};
+/// Fixed fields of a VEX prefix, as written in the Intel SDM opcode column
+/// (e.g. `VEX.256.66.0F.WIG`).
+#[derive(Clone, Copy)]
+struct Vex {
+    /// Vector length: `false` = 128-bit, `true` = 256-bit.
+    l: bool,
+    /// Implied legacy prefix: 0 = none, 1 = `66`, 2 = `F3`, 3 = `F2`.
+    pp: u8,
+    /// Opcode map: 1 = `0F`, 2 = `0F 38`, 3 = `0F 3A`.
+    map: u8,
+    /// VEX.W bit.
+    w: bool,
+}
+
+/// Fixed fields of an EVEX prefix, as written in the Intel SDM opcode column
+/// (e.g. `EVEX.512.66.0F38.WIG`).
+#[derive(Clone, Copy)]
+struct Evex {
+    /// Vector length `L'L`: 0 = 128-bit, 1 = 256-bit, 2 = 512-bit.
+    ll: u8,
+    /// Implied legacy prefix: 0 = none, 1 = `66`, 2 = `F3`, 3 = `F2`.
+    pp: u8,
+    /// Opcode map: 1 = `0F`, 2 = `0F 38`, 3 = `0F 3A`.
+    map: u8,
+    /// EVEX.W bit.
+    w: bool,
+}
 
 /// The main byte emitter for x86-64 machine code.
 ///
-/// `Encoder` is intentionally dumb: it simply pushes bytes into an internal
-/// buffer.  Higher-level code (assemblers or backends) are responsible for
-/// instruction selection and validation.
-pub struct Encoder {
-    /// Output buffer holding raw machine-code bytes.
-    pub buffer: Vec<u8>,
+/// `Encoder` is intentionally dumb: it simply writes bytes to its
+/// [`CodeSink`].  Higher-level code (assemblers or backends) are responsible
+/// for instruction selection and validation.
+///
+/// `Encoder::new()` collects the code in a `Vec<u8>`; use
+/// [`Encoder::with_sink`] to write it elsewhere.
+pub struct Encoder<S = Vec<u8>> {
+    /// Destination of the encoded bytes.
+    sink: S,
+    /// Bytes of the instruction being encoded, not yet handed to the sink.
+    pending: Vec<u8>,
+    /// Nesting depth of [`Encoder::instruction`] calls.
+    depth: u32,
+    /// Offset of each label created so far, once it is bound.
+    labels: Vec<Option<usize>>,
+    /// Branches to labels that are not bound yet.
+    fixups: Vec<branch::Fixup>,
+    /// Offset of the sink's first byte in the code. Only the probes of
+    /// [`Encoder::encoded_len`] start anywhere but 0.
+    origin: usize,
+    /// Opt-in instruction-set extensions the encoder accepts.
+    features: CpuFeatures,
+    /// Processor mode the code is encoded for.
+    mode: Mode,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Encoder {
     /// Constructs an empty encoder.
     #[inline]
     pub fn new() -> Self {
-        Self { buffer: Vec::new() }
+        Self::with_features(CpuFeatures::new())
+    }
+
+    /// Constructs an empty encoder that accepts the given opt-in features.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::features::{CpuFeature, CpuFeatures};
+    ///
+    /// let enc = Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx));
+    /// assert!(enc.features().contains(CpuFeature::Apx));
+    /// ```
+    #[inline]
+    pub fn with_features(features: CpuFeatures) -> Self {
+        let mut enc = Self::with_sink(Vec::new());
+        enc.features = features;
+        enc
+    }
+
+    /// Constructs an empty encoder for the given processor mode.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::mode::Mode;
+    /// use rask_x86_64::operand::Operand;
+    /// use rask_x86_64::registers::Reg16::AX;
+    ///
+    /// let mut enc = Encoder::with_mode(Mode::Real16);
+    /// enc.mov(Operand::Reg16(AX), Operand::Imm(0x7C00));
+    /// assert_eq!(enc.bytes(), &[0xB8, 0x00, 0x7C]);
+    /// ```
+    #[inline]
+    pub fn with_mode(mode: Mode) -> Self {
+        let mut enc = Self::new();
+        enc.mode = mode;
+        enc
     }
 
     /// Returns a read-only view of the encoded bytes.
     #[inline]
     pub fn bytes(&self) -> &[u8] {
-        &self.buffer
+        &self.sink
     }
+}
 
-    /// Appends a single byte to the buffer.
+impl<S: CodeSink> Encoder<S> {
+    /// Constructs an encoder that writes to `sink`, with no opt-in features
+    /// and in 64-bit mode.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::sink::SliceSink;
+    ///
+    /// let mut page = [0xCC; 4];
+    /// let mut enc = Encoder::with_sink(SliceSink::new(&mut page));
+    /// enc.ret()?;
+    /// assert_eq!(enc.position(), 1);
+    /// assert_eq!(page, [0xC3, 0xCC, 0xCC, 0xCC]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    #[inline]
+    pub fn with_sink(sink: S) -> Self {
+        Self {
+            sink,
+            pending: Vec::new(),
+            depth: 0,
+            labels: Vec::new(),
+            fixups: Vec::new(),
+            origin: 0,
+            features: CpuFeatures::new(),
+            mode: Mode::Long64,
+        }
+    }
+
+    /// Returns the sink.
+    #[inline]
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Consumes the encoder and returns the sink.
+    #[inline]
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+
+    /// Returns the number of bytes written so far, which is the offset of
+    /// the next instruction.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.sink.position()
+    }
+
+    /// Returns the opt-in features this encoder accepts.
+    #[inline]
+    pub fn features(&self) -> CpuFeatures {
+        self.features
+    }
+
+    /// Replaces the opt-in features for the instructions that follow.
+    #[inline]
+    pub fn set_features(&mut self, features: CpuFeatures) {
+        self.features = features;
+    }
+
+    /// Returns the processor mode the encoder currently targets.
+    #[inline]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Switches the processor mode for the instructions that follow, like an
+    /// assembler's `.code16`/`.code32`/`.code64` directives. Boot code uses
+    /// this to continue in the new mode after the far jump that enters it.
+    #[inline]
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// Returns the offset in the code of the next byte to be emitted.
+    #[inline]
+    fn offset(&self) -> usize {
+        self.origin + self.sink.position() + self.pending.len()
+    }
+
+    /// Appends a single byte to the instruction being encoded.
     #[inline]
     fn emit(&mut self, byte: u8) {
-        self.buffer.push(byte);
+        self.pending.push(byte);
     }
 
-    /// Appends a full slice of bytes to the buffer.
+    /// Appends a full slice of bytes to the instruction being encoded.
     #[inline]
-    pub fn emit_all(&mut self, bytes: &[u8]) {
-        self.buffer.extend_from_slice(bytes);
+    fn emit_bytes(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+    }
+
+    /// Writes raw bytes, such as data embedded in the code, to the sink.
+    ///
+    /// Returns an error if the sink cannot take them.
+    #[inline]
+    pub fn emit_all(&mut self, bytes: &[u8]) -> RaskResult<()> {
+        self.sink.put(bytes)
     }
 
     // -------------------------------------------------------------------------
-    // Instruction encoders
+    // Encoding helpers
     // -------------------------------------------------------------------------
 
-    /// Encodes a `MOV r64, imm64` instruction.
-    ///
-    /// ### Encoding form
+    /// Encodes one instruction with `f`.
     ///
-    /// ```text
-    /// REX.W + B8+rd  imm64
-    /// ```
+    /// When `f` succeeds and this is the outermost call, the bytes it
+    /// emitted are written to the sink. If `f` fails, they are removed
+    /// again, and an [`RaskError::InvalidOperands`] or
+    /// [`RaskError::ImmediateOutOfRange`] raised by a shared helper is tagged
+    /// with `mnemonic` and `operands`.
+    fn instruction(
+        &mut self,
+        mnemonic: &str,
+        operands: &[Operand],
+        f: impl FnOnce(&mut Self) -> RaskResult<()>,
+    ) -> RaskResult<()> {
+        let start = self.pending.len();
+        let fixups = self.fixups.len();
+        self.depth += 1;
+        let mut result = f(self);
+        self.depth -= 1;
+        if result.is_ok() && self.depth == 0 {
+            result = self.sink.put(&self.pending);
+            self.pending.clear();
+        }
+        let Err(err) = result else {
+            return Ok(());
+        };
+        self.pending.truncate(start);
+        self.fixups.truncate(fixups);
+        Err(match err {
+            RaskError::InvalidOperands {
+                mnemonic: m,
+                reason,
+                ..
+            } if m.is_empty() => RaskError::InvalidOperands {
+                mnemonic: mnemonic.to_ascii_uppercase(),
+                operands: format!("{operands:?}"),
+                reason,
+            },
+            RaskError::ImmediateOutOfRange {
+                mnemonic: m,
+                value,
+                bits,
+            } if m.is_empty() => RaskError::ImmediateOutOfRange {
+                mnemonic: mnemonic.to_ascii_uppercase(),
+                value,
+                bits,
+            },
+            other => other,
+        })
+    }
+
+    /// Fails unless `feature` is enabled; `what` names the construct that
+    /// needs it.
+    fn require(&self, feature: CpuFeature, what: &str) -> RaskResult<()> {
+        if self.features.contains(feature) {
+            return Ok(());
+        }
+        Err(RaskError::UnsupportedFeature {
+            what: what.to_string(),
+            feature: format!("CpuFeature::{feature:?}"),
+        })
+    }
+
+    /// Fails unless the encoder is in 64-bit mode; `what` names the
+    /// construct that needs it.
+    fn require_long_mode(&self, what: &str) -> RaskResult<()> {
+        if self.mode == Mode::Long64 {
+            return Ok(());
+        }
+        Err(RaskError::UnsupportedFeature {
+            what: what.to_string(),
+            feature: "64-bit mode".to_string(),
+        })
+    }
+
+    /// Fails if the encoder is in 64-bit mode; `what` names the construct
+    /// that is invalid there.
+    fn forbid_long_mode(&self, what: &str) -> RaskResult<()> {
+        if self.mode != Mode::Long64 {
+            return Ok(());
+        }
+        Err(RaskError::UnsupportedFeature {
+            what: what.to_string(),
+            feature: "16- or 32-bit mode".to_string(),
+        })
+    }
+
+    /// Returns the operand-size prefix and `REX.W` bit selecting a
+    /// `bits`-wide operation in the current mode. The `66` prefix toggles
+    /// between the 16- and 32-bit sizes relative to the mode's default.
     ///
-    /// * **REX prefix** — 1 byte, of the form `0100WRXB`:
-    ///   - **W = 1** → 64-bit operand size
-    ///   - **R = 0** → no ModR/M reg-field extension in this form
-    ///   - **X = 0** → no SIB index extension
-    ///   - **B = (reg_id >> 3)** → extends the low 3-bit reg number to access R8–R15
+    /// Fails for 64-bit operations outside 64-bit mode.
+    fn operand_size(&self, bits: u32) -> RaskResult<(Option<u8>, bool)> {
+        Ok(match bits {
+            8 => (None, false),
+            64 => {
+                self.require_long_mode("64-bit operand size")?;
+                (None, true)
+            }
+            b if b == self.mode.default_operand_bits() => (None, false),
+            16 | 32 => (Some(0x66), false),
+            _ => unreachable!("invalid operand size {bits}"),
+        })
+    }
+
+    /// Emits the ModR/M byte, optional SIB byte and displacement addressing `mem`,
+    /// with `reg` (low 3 bits used) in the ModR/M `reg` field.
     ///
-    /// * **Opcode** — `0xB8 + (reg_id & 0b111)`
+    /// * `mod = 00` when there is no displacement, except for RBP/R13 bases whose
+    ///   `mod = 00` slot means RIP-relative (or "no base" in the SIB), so they
+    ///   fall back to a zero disp8.
+    /// * `mod = 01` / `mod = 10` for 8-bit / 32-bit displacements.
+    /// * `r/m = 100` selects a SIB byte. It is required when an index is present
+    ///   and when the base is RSP/R12, whose `r/m` encoding is taken by the SIB escape.
     ///
-    /// * **Immediate** — 8-byte little-endian constant
+    /// `disp_n` is the EVEX compressed-displacement factor (`disp8*N`): a disp8
+    /// is only used when the displacement is a multiple of `disp_n`, and the
+    /// byte stores the quotient. Non-EVEX encodings pass 1. `force_sib` emits
+    /// a SIB byte even when the addressing mode would not need one.
     ///
-    /// Example encodings:
+    /// Without a base register the address is `[index*scale + disp32]`. In
+    /// 64-bit mode it is expressed as `mod = 00` with SIB base `101`, since
+    /// the shorter ModR/M-only form (`mod = 00`, `r/m = 101`) means
+    /// RIP-relative there; other modes use the short form when there is no
+    /// index. 16-bit addresses use their own table; see [`Encoder::emit_mem16`].
     ///
-    /// | Instruction      | Bytes (hex)                                |
-    /// |------------------|--------------------------------------------|
-    /// | `mov rax, 1337`  | 48 B8 39 05 00 00 00 00 00 00             |
-    /// | `mov r10, 42`    | 49 BA 2A 00 00 00 00 00 00 00             |
+    /// A RIP base takes that short form in 64-bit mode: `mod = 00`,
+    /// `r/m = 101` and a disp32 relative to the end of the instruction.
     ///
-    /// Reference: Intel SDM Vol. 2A, “MOV—Move” (Opcode B8+rd).
-    fn mov_reg_imm64(&mut self, dst: Reg64, value: u64) {
-        // Base REX prefix with W=1 (01001000b).
-        let mut rex: u8 = 0x48;
+    /// Fails if RSP or RIP is used as an index register, which the SIB byte
+    /// cannot express, if a RIP-relative or 16-bit address needs a SIB byte,
+    /// or if the address size is not available in the current mode.
+    fn emit_mem(
+        &mut self,
+        reg: u8,
+        mem: &MemOperand,
+        disp_n: i32,
+        force_sib: bool,
+    ) -> RaskResult<()> {
+        match self.address_bits(mem) {
+            64 => self.require_long_mode("64-bit addressing")?,
+            16 => {
+                self.forbid_long_mode("16-bit addressing")?;
+                if force_sib {
+                    return Err(invalid("16-bit addresses cannot have a SIB byte"));
+                }
+                return self.emit_mem16(reg, mem, disp_n);
+            }
+            _ => {}
+        }
+
+        if mem.base == Some(AddrReg::Rip) {
+            if mem.index.is_some() || force_sib {
+                return Err(invalid("RIP-relative addresses cannot have a SIB byte"));
+            }
+            self.emit(((reg & 0x07) << 3) | 0b101);
+            self.emit_bytes(&mem.disp.to_le_bytes());
+            return Ok(());
+        }
+
+        let (index, scale) = match mem.index {
+            Some((AddrReg::Rip, _)) => {
+                return Err(invalid("RIP cannot be used as an index register"));
+            }
+            // ID 4 is RSP/ESP; R12 (ID 12) is a valid index.
+            Some((idx, _)) if idx.id() == 4 => {
+                return Err(invalid("RSP cannot be used as an index register"));
+            }
+            Some((idx, scale)) => (idx.id() & 0x07, scale.bits()),
+            // index = 100 without REX.X means "no index".
+            None => (0b100, 0),
+        };
+
+        let Some(base) = mem.base.map(|b| b.id() & 0x07) else {
+            if mem.index.is_none() && self.mode != Mode::Long64 && !force_sib {
+                self.emit(((reg & 0x07) << 3) | 0b101);
+                self.emit_bytes(&mem.disp.to_le_bytes());
+                return Ok(());
+            }
+            self.emit(((reg & 0x07) << 3) | 0b100);
+            self.emit((scale << 6) | (index << 3) | 0b101);
+            self.emit_bytes(&mem.disp.to_le_bytes());
+            return Ok(());
+        };
+
+        let disp8 = if mem.disp % disp_n == 0 {
+            i8::try_from(mem.disp / disp_n).ok()
+        } else {
+            None
+        };
+        let (mod_bits, disp_bytes, disp_len) = match disp8 {
+            Some(0) if base != 0b101 => (0b00, [0; 4], 0),
+            Some(d) => (0b01, [d as u8, 0, 0, 0], 1),
+            None => (0b10, mem.disp.to_le_bytes(), 4),
+        };
 
-        // Set REX.B if the destination register is R8–R15.
-        if dst.needs_rex() {
-            rex |= 0x01;
+        if force_sib || mem.index.is_some() || base == 0b100 {
+            self.emit((mod_bits << 6) | ((reg & 0x07) << 3) | 0b100);
+            self.emit((scale << 6) | (index << 3) | base);
+        } else {
+            self.emit((mod_bits << 6) | ((reg & 0x07) << 3) | base);
         }
 
-        // Emit prefix + opcode + immediate.
-        self.emit(rex);
-        self.emit(0xB8 + (dst.id() & 0x07));
-        self.emit_all(&value.to_le_bytes());
+        self.emit_bytes(&disp_bytes[..disp_len]);
+        Ok(())
     }
 
-    /// Encodes a `MOV r64, r64` instruction.
+    /// Emits the ModR/M byte and displacement for a 16-bit address.
+    ///
+    /// 16-bit addressing has no SIB byte. The `r/m` field selects one of
+    /// eight fixed register combinations:
+    ///
+    /// ```text
+    /// 000 [BX+SI]  001 [BX+DI]  010 [BP+SI]  011 [BP+DI]
+    /// 100 [SI]     101 [DI]     110 [BP]     111 [BX]
+    /// ```
+    ///
+    /// `mod = 00` with `r/m = 110` is an absolute disp16 instead of `[BP]`,
+    /// so a BP base without displacement gets a zero disp8.
     ///
-    /// ModR/M Encoding form
-    /// Bits 0-2: r/m (destination register or memory operand)
-    /// Bits 3-5: reg (source register)
-    /// Bits 6-7: mod (addressing mode register-direct or memory)
+    /// Fails for any other register combination, a scaled index, or a
+    /// displacement that does not fit in 16 bits.
+    fn emit_mem16(&mut self, reg: u8, mem: &MemOperand, disp_n: i32) -> RaskResult<()> {
+        // One bit per usable register, so each valid combination has a
+        // distinct mask.
+        let bit = |r: AddrReg| match r {
+            AddrReg::R16(Reg16::BX) => Ok(1),
+            AddrReg::R16(Reg16::BP) => Ok(2),
+            AddrReg::R16(Reg16::SI) => Ok(4),
+            AddrReg::R16(Reg16::DI) => Ok(8),
+            other => Err(invalid(format!(
+                "{other:?} cannot be used in a 16-bit address"
+            ))),
+        };
+        let index = match mem.index {
+            Some((_, scale)) if scale != Scale::S1 => {
+                return Err(invalid("16-bit addresses cannot scale the index register"));
+            }
+            Some((idx, _)) => bit(idx)?,
+            None => 0,
+        };
+        let base = mem.base.map_or(Ok(0), bit)?;
+        let rm = match (base | index, base & index) {
+            (0b0101, 0) => 0b000,
+            (0b1001, 0) => 0b001,
+            (0b0110, 0) => 0b010,
+            (0b1010, 0) => 0b011,
+            (0b0100, 0) => 0b100,
+            (0b1000, 0) => 0b101,
+            (0b0010, 0) => 0b110,
+            (0b0001, 0) => 0b111,
+            // Absolute addresses beyond 16 bits take 32-bit addressing; see
+            // `Encoder::address_bits`.
+            (0, 0) => {
+                self.emit(((reg & 0x07) << 3) | 0b110);
+                self.emit_bytes(&(mem.disp as u16).to_le_bytes());
+                return Ok(());
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "{:?} + {:?} is not a valid 16-bit address",
+                    mem.base, mem.index
+                )));
+            }
+        };
+
+        let disp8 = if mem.disp % disp_n == 0 {
+            i8::try_from(mem.disp / disp_n).ok()
+        } else {
+            None
+        };
+        let (mod_bits, disp_bytes, disp_len) = match disp8 {
+            Some(0) if rm != 0b110 => (0b00, [0; 2], 0),
+            Some(d) => (0b01, [d as u8, 0], 1),
+            None => match i16::try_from(mem.disp) {
+                Ok(d) => (0b10, d.to_le_bytes(), 2),
+                Err(_) => {
+                    return Err(invalid(format!(
+                        "16-bit displacement {} does not fit in 16 bits",
+                        mem.disp
+                    )));
+                }
+            },
+        };
+        self.emit((mod_bits << 6) | ((reg & 0x07) << 3) | rm);
+        self.emit_bytes(&disp_bytes[..disp_len]);
+        Ok(())
+    }
+
+    /// Emits the prefixes a memory `rm` operand needs on its own: the
+    /// segment override and, when the address registers differ in width from
+    /// the mode's default, the `67` address-size override. They go first,
+    /// ahead of mandatory, REX, REX2, VEX and EVEX prefixes.
     ///
-    fn mov_reg_reg(&mut self, _dst: Reg64, _src: Reg64) {
-        let mut rex: u8 = 0x48; // Base REX prefix with W=1 (01001000b).
+    /// Fails if the base and index registers differ in width.
+    #[inline]
+    fn emit_mem_prefixes(&mut self, rm: Rm) -> RaskResult<()> {
+        let Some(mem) = rm.mem() else {
+            return Ok(());
+        };
+        if let (Some(base), Some((index, _))) = (mem.base, mem.index)
+            && base.bits() != index.bits()
+        {
+            return Err(invalid(format!(
+                "base {base:?} and index {index:?} must have the same width"
+            )));
+        }
+        if let Some(seg) = mem.segment {
+            self.emit(seg.prefix());
+        }
+        if self.address_bits(mem) != self.mode.default_address_bits() {
+            self.emit(0x67);
+        }
+        Ok(())
+    }
 
-        if _src.id() >= 8 {
-            rex |= 0x04;
-        } // Set REX.R if the source register is R8–R15.
-        if _dst.id() >= 8 {
-            rex |= 0x01;
-        } // Set REX.B if the destination register is R8–R15.
+    /// Returns the address size `mem` is encoded with: the width of its
+    /// base and index registers, or the mode's default for an absolute
+    /// address. In 16-bit mode an absolute address beyond 16 bits takes
+    /// 32-bit addressing instead, as the `67` prefix and a disp32 reach it.
+    fn address_bits(&self, mem: &MemOperand) -> u32 {
+        mem.address_bits().unwrap_or_else(|| {
+            let bits = self.mode.default_address_bits();
+            let fits = i16::try_from(mem.disp).is_ok() || u16::try_from(mem.disp).is_ok();
+            if bits == 16 && !fits { 32 } else { bits }
+        })
+    }
 
-        self.emit(rex);
-        self.emit(0x89); // Opcode for MOV r/m64, r64
-        let modrm: u8 = (0b11 << 6) | ((_src.id() & 0x07) << 3) | (_dst.id() & 0x07); // ModR/M byte for register to register
-        self.emit(modrm);
+    /// Emits the ModR/M byte for `reg` and `rm`, followed by the SIB byte and
+    /// displacement when `rm` is a memory operand.
+    fn emit_modrm(&mut self, reg: u8, rm: Rm, disp_n: i32) -> RaskResult<()> {
+        match rm {
+            Rm::Reg(id) => self.emit(0xC0 | ((reg & 0x07) << 3) | (id & 0x07)),
+            Rm::Reg8(r) => self.emit(0xC0 | ((reg & 0x07) << 3) | (r.id() & 0x07)),
+            Rm::Mem(mem) => return self.emit_mem(reg, mem, disp_n, false),
+            Rm::SibMem(mem) => return self.emit_mem(reg, mem, disp_n, true),
+        }
+        Ok(())
     }
 
-    /// Encodes a `MOV r64, [mem]` instruction (load from memory).
+    /// Emits a legacy-encoded instruction with a ModR/M operand:
     ///
-    /// ### Encoding form
     /// ```text
-    /// REX.W + 8B /r
+    /// [segment] [67] [prefixes] [REX] opcode... ModR/M [SIB] [disp]
     /// ```
     ///
-    /// * **REX prefix** — 1 byte:
-    ///   - **W = 1** → 64-bit operand size
-    ///   - **R = (dst_id >> 3)** → extends destination register field
-    ///   - **B = (base_id >> 3)** → extends base register field
-    ///
-    /// * **Opcode** — `0x8B` (MOV r64, r/m64)
-    /// * **ModR/M** — depends on addressing mode
-    /// * **Displacement** — 0, 1, or 4 bytes depending on addressing mode
-    fn mov_reg_mem(&mut self, dst: Reg64, src: &crate::operand::MemOperand) {
-        let mut rex: u8 = 0x48; // Base REX.W
-
-        // Set REX.R if destination register needs extension
-        if dst.needs_rex() {
-            rex |= 0x04;
+    /// `prefixes` are legacy prefixes (operand-size `66`, mandatory `F2`/`F3`)
+    /// that must precede REX, in the order given.
+    /// The REX prefix is only emitted when `rex_w` is set, one of the
+    /// register IDs needs an extension bit, or `rm` is one of SPL–DIL.
+    ///
+    /// When an APX register (R16–R31) is involved, the two-byte REX2 prefix
+    /// replaces REX; see [`Encoder::emit_rex2_rm`].
+    ///
+    /// Fails if `rm` is AH–BH and a REX prefix is needed, or if a REX prefix
+    /// is needed outside 64-bit mode, where `40`–`4F` are `INC`/`DEC`.
+    fn emit_rm(
+        &mut self,
+        prefixes: &[u8],
+        rex_w: bool,
+        opcode: &[u8],
+        reg: u8,
+        rm: Rm,
+    ) -> RaskResult<()> {
+        self.emit_rm_reg8(prefixes, rex_w, opcode, reg, None, rm)
+    }
+
+    /// Like [`Encoder::emit_rm`], where `reg8` is the byte register in the
+    /// ModR/M `reg` field, if any, so that it gets the same REX treatment as
+    /// a byte register in `r/m`.
+    fn emit_rm_reg8(
+        &mut self,
+        prefixes: &[u8],
+        rex_w: bool,
+        opcode: &[u8],
+        reg: u8,
+        reg8: Option<Reg8>,
+        rm: Rm,
+    ) -> RaskResult<()> {
+        self.emit_mem_prefixes(rm)?;
+        self.emit_bytes(prefixes);
+
+        let rm8 = match rm {
+            Rm::Reg8(r) => Some(r),
+            _ => None,
+        };
+        let bytes = [reg8, rm8].into_iter().flatten();
+
+        if reg >= 16 || rm.uses_egpr() {
+            if let Some(r) = bytes.clone().find(|r| r.is_high_byte()) {
+                return Err(invalid(format!(
+                    "{r:?} cannot be encoded in an instruction that needs a REX2 prefix"
+                )));
+            }
+            return self.emit_rex2_rm(rex_w, opcode, reg, rm);
         }
 
-        // Set REX.B if base register needs extension
-        if src.base.needs_rex() {
-            rex |= 0x01;
+        let rex = 0x40
+            | ((rex_w as u8) << 3)
+            | (((reg >> 3) & 1) << 2)
+            | ((rm.ext_x() as u8) << 1)
+            | (rm.ext_b() as u8);
+        let needs_rex = rex != 0x40 || bytes.clone().any(|r| r.requires_rex());
+        if needs_rex {
+            if let Some(r) = bytes.clone().find(|r| r.is_high_byte()) {
+                return Err(invalid(format!(
+                    "{r:?} cannot be encoded in an instruction that needs a REX prefix"
+                )));
+            }
+            self.require_long_mode("the REX prefix (64-bit operands, R8–R15, SPL–DIL)")?;
+            self.emit(rex);
         }
 
-        self.emit(rex);
-        self.emit(0x8B); // MOV r64, r/m64
+        self.emit_bytes(opcode);
+        self.emit_modrm(reg, rm, 1)
+    }
 
-        // Determine addressing mode based on displacement
-        let (mod_bits, disp_bytes) = if src.disp == 0 && src.base != crate::registers::Reg64::RBP {
-            // [reg] - no displacement (except RBP which requires disp8)
-            (0b00, Vec::new())
-        } else if src.disp >= -128 && src.disp <= 127 {
-            // [reg + disp8] - 8-bit displacement
-            (0b01, vec![src.disp as i8 as u8])
+    /// Emits a legacy instruction whose register operand is added to the
+    /// last opcode byte (the SDM's `+rb`/`+rw`/`+rd` forms):
+    ///
+    /// ```text
+    /// [prefixes] [REX | REX2] opcode+r
+    /// ```
+    ///
+    /// Bit 3 of the register ID travels in REX.B (and bit 4 in REX2.B4).
+    /// Like [`Encoder::emit_rm`], SPL–DIL force a REX prefix and AH–BH
+    /// cannot take one.
+    fn emit_plus_r(
+        &mut self,
+        prefixes: &[u8],
+        w: bool,
+        opcode: &[u8],
+        id: u8,
+        reg8: Option<Reg8>,
+    ) -> RaskResult<()> {
+        self.emit_bytes(prefixes);
+        let (last, escape) = opcode.split_last().expect("opcode must not be empty");
+        if id >= 16 {
+            self.require(CpuFeature::Apx, "R16–R31")?;
+            self.require_long_mode("R16–R31")?;
+            let (m0, escape) = match escape {
+                [0x0F, rest @ ..] => (1, rest),
+                _ => (0, escape),
+            };
+            let payload = (m0 << 7) | (((id >> 4) & 1) << 4) | ((w as u8) << 3) | ((id >> 3) & 1);
+            self.emit_bytes(&[0xD5, payload]);
+            self.emit_bytes(escape);
         } else {
-            // [reg + disp32] - 32-bit displacement
-            (0b10, src.disp.to_le_bytes().to_vec())
-        };
+            let rex = 0x40 | ((w as u8) << 3) | ((id >> 3) & 1);
+            if rex != 0x40 || reg8.is_some_and(|r| r.requires_rex()) {
+                if let Some(r) = reg8.filter(|r| r.is_high_byte()) {
+                    return Err(invalid(format!(
+                        "{r:?} cannot be encoded in an instruction that needs a REX prefix"
+                    )));
+                }
+                self.require_long_mode("the REX prefix (64-bit operands, R8–R15, SPL–DIL)")?;
+                self.emit(rex);
+            }
+            self.emit_bytes(escape);
+        }
+        self.emit(last + (id & 0x07));
+        Ok(())
+    }
 
-        // Build ModR/M byte: mod(2) + reg(3) + r/m(3)
-        let modrm = (mod_bits << 6) | ((dst.id() & 0x07) << 3) | (src.base.id() & 0x07);
-        self.emit(modrm);
+    /// Emits the REX2 form of a legacy instruction, after any mandatory prefix:
+    ///
+    /// ```text
+    /// D5 [M0 R4 X4 B4 W R3 X3 B3] opcode ModR/M [SIB] [disp]
+    /// ```
+    ///
+    /// REX2 carries bits 3 and 4 of every register ID, none of them inverted.
+    /// `M0` selects the opcode map: set for `0F`, whose escape byte is then
+    /// dropped. Like REX, REX2 makes SPL–DIL addressable and AH–BH not.
+    ///
+    /// Fails unless [`CpuFeature::Apx`] is enabled, and for the `0F 38` /
+    /// `0F 3A` maps, which REX2 cannot express. AH–BH are rejected by
+    /// [`Encoder::emit_rm_reg8`].
+    fn emit_rex2_rm(&mut self, w: bool, opcode: &[u8], reg: u8, rm: Rm) -> RaskResult<()> {
+        self.require(CpuFeature::Apx, "R16–R31")?;
+        self.require_long_mode("R16–R31")?;
+        let (m0, opcode) = match opcode {
+            [0x0F, 0x38 | 0x3A, ..] => {
+                return Err(invalid(
+                    "REX2 cannot encode instructions in the 0F 38 and 0F 3A opcode maps",
+                ));
+            }
+            [0x0F, rest @ ..] => (1, rest),
+            _ => (0, opcode),
+        };
 
-        // Emit displacement bytes
-        self.emit_all(&disp_bytes);
+        let (b, x) = (rm.base_id(), rm.index_id());
+        let payload = (m0 << 7)
+            | (((reg >> 4) & 1) << 6)
+            | (((x >> 4) & 1) << 5)
+            | (((b >> 4) & 1) << 4)
+            | ((w as u8) << 3)
+            | (((reg >> 3) & 1) << 2)
+            | (((x >> 3) & 1) << 1)
+            | ((b >> 3) & 1);
+        self.emit_bytes(&[0xD5, payload]);
+        self.emit_bytes(opcode);
+        self.emit_modrm(reg, rm, 1)
     }
 
-    /// Encodes a `MOV [mem], r64` instruction (store to memory).
+    /// Emits a VEX-encoded instruction with a ModR/M operand:
     ///
-    /// ### Encoding form
     /// ```text
-    /// REX.W + 89 /r
+    /// C5 [R vvvv L pp]              opcode ModR/M [SIB] [disp]
+    /// C4 [R X B mmmmm] [W vvvv L pp] opcode ModR/M [SIB] [disp]
     /// ```
     ///
-    /// * **REX prefix** — 1 byte:
-    ///   - **W = 1** → 64-bit operand size
-    ///   - **R = (src_id >> 3)** → extends source register field
-    ///   - **B = (base_id >> 3)** → extends base register field
-    ///
-    /// * **Opcode** — `0x89` (MOV r/m64, r64)
-    /// * **ModR/M** — depends on addressing mode
-    /// * **Displacement** — 0, 1, or 4 bytes depending on addressing mode
-    fn mov_mem_reg(&mut self, dst: &crate::operand::MemOperand, src: Reg64) {
-        let mut rex: u8 = 0x48; // Base REX.W
-
-        // Set REX.R if source register needs extension
-        if src.needs_rex() {
-            rex |= 0x04;
+    /// The R, X, B and vvvv fields are stored inverted. The compact two-byte
+    /// form is used whenever the instruction lives in the `0F` map and needs
+    /// neither VEX.W nor the X/B extensions. `vvvv` is the extra source
+    /// register ID, or 0 when the instruction does not use it.
+    ///
+    /// Fails if a memory operand uses R16–R31, which VEX cannot address,
+    /// or if a register ID needs an extension bit outside 64-bit mode.
+    fn emit_vex_rm(&mut self, vex: Vex, opcode: u8, reg: u8, vvvv: u8, rm: Rm) -> RaskResult<()> {
+        if rm.uses_egpr() {
+            return Err(invalid("VEX-encoded instructions cannot address R16–R31"));
+        }
+        if (reg | vvvv | rm.base_id() | rm.index_id()) >= 8 {
+            self.require_long_mode("registers 8–15")?;
         }
+        self.emit_mem_prefixes(rm)?;
+        let r = (reg >> 3) & 1 == 0;
+        let x = !rm.ext_x();
+        let b = !rm.ext_b();
+        let tail = ((!vvvv & 0x0F) << 3) | ((vex.l as u8) << 2) | vex.pp;
 
-        // Set REX.B if base register needs extension
-        if dst.base.needs_rex() {
-            rex |= 0x01;
+        if vex.map == 1 && !vex.w && x && b {
+            self.emit(0xC5);
+            self.emit(((r as u8) << 7) | tail);
+        } else {
+            self.emit(0xC4);
+            self.emit(((r as u8) << 7) | ((x as u8) << 6) | ((b as u8) << 5) | vex.map);
+            self.emit(((vex.w as u8) << 7) | tail);
         }
 
-        self.emit(rex);
-        self.emit(0x89); // MOV r/m64, r64
+        self.emit(opcode);
+        self.emit_modrm(reg, rm, 1)
+    }
 
-        // Determine addressing mode based on displacement
-        let (mod_bits, disp_bytes) = if dst.disp == 0 && dst.base != crate::registers::Reg64::RBP {
-            // [reg] - no displacement (except RBP which requires disp8)
-            (0b00, Vec::new())
-        } else if dst.disp >= -128 && dst.disp <= 127 {
-            // [reg + disp8] - 8-bit displacement
-            (0b01, vec![dst.disp as i8 as u8])
-        } else {
-            // [reg + disp32] - 32-bit displacement
-            (0b10, dst.disp.to_le_bytes().to_vec())
+    /// Emits an EVEX-encoded instruction with a ModR/M operand:
+    ///
+    /// ```text
+    /// 62 [R X B R' 0 m m m] [W vvvv 1 pp] [z L'L b V' aaa] opcode ModR/M [SIB] [disp]
+    /// ```
+    ///
+    /// EVEX widens register IDs to 5 bits: R' extends `reg`, V' extends
+    /// `vvvv`, and for a register `r/m` the X bit supplies its fifth bit.
+    /// For a memory `r/m`, APX repurposes bit 3 of P0 as B4 (not inverted)
+    /// and bit 2 of P1 as the inverted X4, extending the base and index to
+    /// R16–R31. All other extension bits and `vvvv` are stored inverted.
+    /// Masking (`aaa`, `z`) and embedded broadcast (`b`) are not used, so
+    /// memory operands are full vectors and `disp8` is scaled by the vector
+    /// length in bytes.
+    ///
+    /// Fails if a register ID needs an extension bit outside 64-bit mode.
+    fn emit_evex_rm(
+        &mut self,
+        evex: Evex,
+        opcode: u8,
+        reg: u8,
+        vvvv: u8,
+        rm: Rm,
+    ) -> RaskResult<()> {
+        if (reg | vvvv | rm.base_id() | rm.index_id()) >= 8 {
+            self.require_long_mode("registers 8–31")?;
+        }
+        let (x, b, x4, b4) = match rm {
+            Rm::Reg(id) => (id & 0x10 != 0, id & 0x08 != 0, false, false),
+            _ => {
+                if rm.uses_egpr() {
+                    self.require(CpuFeature::Apx, "R16–R31")?;
+                }
+                let (base, index) = (rm.base_id(), rm.index_id());
+                (rm.ext_x(), rm.ext_b(), index & 0x10 != 0, base & 0x10 != 0)
+            }
         };
+        self.emit_mem_prefixes(rm)?;
+        let p0 = ((((reg >> 3) & 1) ^ 1) << 7)
+            | (((x as u8) ^ 1) << 6)
+            | (((b as u8) ^ 1) << 5)
+            | ((((reg >> 4) & 1) ^ 1) << 4)
+            | ((b4 as u8) << 3)
+            | evex.map;
+        let p1 = ((evex.w as u8) << 7) | ((!vvvv & 0x0F) << 3) | (((x4 as u8) ^ 1) << 2) | evex.pp;
+        let p2 = (evex.ll << 5) | ((((vvvv >> 4) & 1) ^ 1) << 3);
 
-        // Build ModR/M byte: mod(2) + reg(3) + r/m(3)
-        let modrm = (mod_bits << 6) | ((src.id() & 0x07) << 3) | (dst.base.id() & 0x07);
-        self.emit(modrm);
-
-        // Emit displacement bytes
-        self.emit_all(&disp_bytes);
+        self.emit_bytes(&[0x62, p0, p1, p2, opcode]);
+        self.emit_modrm(reg, rm, 16 << evex.ll)
     }
 
+    // -------------------------------------------------------------------------
+    // Instruction encoders
+    // -------------------------------------------------------------------------
+
     /// Encodes an `ADD r64, r64` instruction.
     ///
     /// ### Encoding form
@@ -219,73 +944,103 @@ impl Encoder {
     /// ```
     /// * **REX prefix** — 1 byte, of the form `0100WRXB`:
     ///   - **W = 1** → 64-bit operand size
-    ///  - **R = (src_id >> 3)** → extends the low 3-bit reg number of the source register to access R8–R15
-    ///  - **X = 0** → no SIB index extension
-    /// - **B = (dst_id >> 3)** → extends the low 3-bit reg number of the destination register to access R8–R15
+    ///   - **R = (src_id >> 3)** → extends the low 3-bit reg number of the source register to access R8–R15
+    ///   - **X = 0** → no SIB index extension
+    ///   - **B = (dst_id >> 3)** → extends the low 3-bit reg number of the destination register to access R8–R15
     /// * **Opcode** — `0x01`
     /// * **ModR/M** — ModR/M byte specifying the registers:
     ///   - Bits 0-2: r/m (destination register)
-    ///  - Bits 3-5: reg (source register)
-    ///  - Bits 6-7: mod (addressing mode, `11` for register-direct)
+    ///   - Bits 3-5: reg (source register)
+    ///   - Bits 6-7: mod (addressing mode, `11` for register-direct)
+    ///
     /// Example encoding:
+    ///
     /// | Instruction      | Bytes (hex)                                |
     /// |------------------|--------------------------------------------|
     /// | `add rax, rbx`   | 48 01 D8                                   |
     /// | `add r10, r9`    | 49 01 D1                                   |
+    ///
     /// Reference: Intel SDM Vol. 2A, "ADD—Add" (Opcode 01 /r).
-    /// Note: This implementation currently only supports register-to-register addition.
-    /// Memory operands and immediate values are not yet implemented.
-    /// TODO: Extend support for other operand types in the future.
-    /// Panics if either operand is not a register.
-    /// Currently, only `Reg` operands are supported.
-    pub fn add(&mut self, _dst: Reg64, _src: Reg64) {
-        let mut rex: u8 = 0x48; // Base REX prefix with W=1 (01001000b).
-
-        if _src.id() >= 8 {
-            rex |= 0x04;
-        } // Set REX.R if the source register is R8–R15.
-        if _dst.id() >= 8 {
-            rex |= 0x01;
-        } // Set REX.B if the destination register is R8–R15.
-
-        self.emit(rex);
-        self.emit(0x01); // Opcode for ADD r/m64, r64
-        let modrm: u8 = (0b11 << 6) | ((_src.id() & 0x07) << 3) | (_dst.id() & 0x07); // ModR/M byte for register to register
-        self.emit(modrm);
-    }
-
-    pub fn sub(&mut self, _dst: Reg64, _src: Reg64) {
-        let mut rex: u8 = 0x48; // Base REX prefix with W=1 (01001000b).
-
-        if _src.id() >= 8 {
-            rex |= 0x04;
-        } // Set REX.R if the source register is R8–R15.
-        if _dst.id() >= 8 {
-            rex |= 0x01;
-        } // Set REX.B if the destination register is R8–R15.
-
-        self.emit(rex);
-        self.emit(0x29); // Opcode for SUB r/m64, r64
-        let modrm: u8 = (0b11 << 6) | ((_src.id() & 0x07) << 3) | (_dst.id() & 0x07); // ModR/M byte for register to register
-        self.emit(modrm);
-    }
-
-    pub fn mov(&mut self, dst: Operand, src: Operand) {
-        match (dst, src) {
-            (Operand::Reg(d), Operand::Reg(s)) => self.mov_reg_reg(d, s),
-            (Operand::Reg(d), Operand::Imm(imm)) => self.mov_reg_imm64(d, imm as u64),
-            (Operand::Mem(ref m), Operand::Reg(r)) => self.mov_mem_reg(m, r),
-            (Operand::Reg(r), Operand::Mem(ref m)) => self.mov_reg_mem(r, m),
-            (Operand::Mem(_), Operand::Mem(_)) => {
-                panic!("MOV from memory to memory is invalid on x86-64");
-            }
-            (Operand::Imm(_), _) => {
-                panic!("Cannot move to an immediate value");
-            }
-            (Operand::Mem(_), Operand::Imm(_)) => {
-                todo!("mov [mem], imm not yet implemented");
-            }
-        }
+    ///
+    /// Note: This method only takes 64-bit registers. The memory, immediate
+    /// and narrower forms are available through [`Encoder::emit_instruction`].
+    pub fn add(&mut self, dst: Reg64, src: Reg64) -> RaskResult<()> {
+        self.emit_instruction("add", &[Operand::Reg(dst), Operand::Reg(src)])
+    }
+
+    /// Encodes a `SUB r64, r64` instruction (`REX.W + 29 /r`). See
+    /// [`Encoder::add`].
+    ///
+    /// Reference: Intel SDM Vol. 2B, "SUB—Subtract".
+    pub fn sub(&mut self, dst: Reg64, src: Reg64) -> RaskResult<()> {
+        self.emit_instruction("sub", &[Operand::Reg(dst), Operand::Reg(src)])
+    }
+
+    /// Encodes an `INC r/m` instruction.
+    ///
+    /// ### Encoding forms
+    /// ```text
+    /// FE /0          INC r/m8
+    /// [66] FF /0     INC r/m16, r/m32
+    /// REX.W FF /0    INC r/m64
+    /// [66] 40+rd     INC r16, r32   (not in 64-bit mode)
+    /// ```
+    ///
+    /// Outside 64-bit mode 16- and 32-bit registers use the one-byte
+    /// `40+rd` form; in 64-bit mode those bytes are REX prefixes. A memory
+    /// operand needs an explicit [`MemSize`](crate::operand::MemSize).
+    ///
+    /// | Instruction          | Bytes (hex) |
+    /// |----------------------|-------------|
+    /// | `inc eax`            | FF C0       |
+    /// | `inc eax` (32-bit)   | 40          |
+    /// | `inc qword ptr [rdi]`| 48 FF 07    |
+    ///
+    /// Reference: Intel SDM Vol. 2A, "INC—Increment by 1".
+    pub fn inc(&mut self, dst: Operand) -> RaskResult<()> {
+        self.emit_instruction("inc", &[dst])
+    }
+
+    /// Encodes a `DEC r/m` instruction (`FE /1`, `FF /1`, or `48+rd` outside
+    /// 64-bit mode). See [`Encoder::inc`].
+    ///
+    /// Reference: Intel SDM Vol. 2A, "DEC—Decrement by 1".
+    pub fn dec(&mut self, dst: Operand) -> RaskResult<()> {
+        self.emit_instruction("dec", &[dst])
+    }
+
+    /// Encodes a `MOV` instruction.
+    ///
+    /// ### Encoding forms
+    /// ```text
+    /// [66] [REX.W] 88/89 /r       MOV r/m, reg
+    /// [66] [REX.W] 8A/8B /r       MOV reg, r/m
+    /// [66] B0+rb/B8+rd ib/iw/id   MOV r8/r16/r32, imm
+    /// REX.W B8+rd io              MOV r64, imm64
+    /// [66] [REX.W] C6/C7 /0 imm   MOV r/m, imm  (imm32 sign-extended for r/m64)
+    /// ```
+    ///
+    /// A 64-bit register destination always takes the full `imm64` form.
+    /// Writing a 32-bit register in 64-bit mode zero-extends into the full
+    /// 64-bit one. An immediate may be given signed or unsigned and is stored
+    /// in the operand width; a memory destination for an immediate needs an
+    /// explicit [`MemSize`](crate::operand::MemSize).
+    ///
+    /// | Instruction             | Bytes (hex)                   |
+    /// |-------------------------|-------------------------------|
+    /// | `mov rax, 1337`         | 48 B8 39 05 00 00 00 00 00 00 |
+    /// | `mov r10d, -1`          | 41 BA FF FF FF FF             |
+    /// | `mov rcx, [rbx + 8]`    | 48 8B 4B 08                   |
+    /// | `mov dword ptr [rdi], 1`| C7 07 01 00 00 00             |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOV—Move".
+    ///
+    /// Returns [`RaskError::InvalidOperands`] for memory-to-memory moves, an
+    /// immediate destination or operands of different widths, and
+    /// [`RaskError::ImmediateOutOfRange`] for an immediate that does not fit
+    /// the operand width.
+    pub fn mov(&mut self, dst: Operand, src: Operand) -> RaskResult<()> {
+        self.emit_instruction("mov", &[dst, src])
     }
 
     /// Encodes a `RET` (near return) instruction.
@@ -296,7 +1051,7 @@ impl Encoder {
     /// ```
     ///
     /// Pops the return address from the stack and jumps to it.
-    pub fn ret(&mut self) {
-        self.emit(0xC3);
+    pub fn ret(&mut self) -> RaskResult<()> {
+        self.emit_instruction("ret", &[])
     }
 }
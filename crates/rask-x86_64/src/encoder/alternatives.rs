@@ -0,0 +1,383 @@
+//! Alternative encodings of an instruction.
+//!
+//! Most x86 instructions can be encoded in more than one way: `add rax, rbx`
+//! is `REX.W + 01 /r` or `REX.W + 03 /r` with the operands swapped, a
+//! memory operand without a displacement can carry a zero `disp8` or
+//! `disp32`, a base register can be addressed through a SIB byte without an
+//! index, and a REX prefix with no bits set changes nothing for most
+//! instructions. [`Encoder::alternatives`] lists every encoding it can
+//! derive this way, each decoded again to check that it means the same
+//! instruction, and [`Encoder::encode_as`] emits the one picked.
+
+use super::{Encoder, invalid};
+use crate::{
+    decoder::{Decoder, Encoding},
+    explain::{Explanation, FieldKind},
+    features::CpuFeature,
+    instruction::{Instruction, MAX_INSTRUCTION_LEN},
+    mode::Mode,
+    operand::Operand,
+    sink::{CodeSink, SliceSink},
+    table,
+};
+use rask_common::RaskResult;
+
+/// How an [`Alternative`] differs from the default encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variation {
+    /// Another form from the instruction table, such as `03 /r` for
+    /// `01 /r` or `81 /0 id` for `83 /0 ib`.
+    Form,
+    /// A zero `disp8` on a memory operand that needs no displacement.
+    Disp8,
+    /// A `disp32` (`disp16` with 16-bit addressing) where a shorter
+    /// displacement, or none, would do.
+    Disp32,
+    /// A SIB byte with no index for a memory operand that only has a base.
+    Sib,
+    /// A REX prefix with no bits set (`40`).
+    RedundantRex,
+    /// An APX REX2 prefix in place of REX, or of no prefix.
+    Rex2,
+    /// A `rel32` branch to a target a `rel8` branch reaches.
+    NearBranch,
+}
+
+/// One encoding of an instruction, as returned by [`Encoder::alternatives`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternative {
+    explanation: Explanation,
+    variations: Vec<Variation>,
+}
+
+impl Alternative {
+    /// Returns the encoded bytes.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        self.explanation.bytes()
+    }
+
+    /// Returns the SDM opcode form; see [`Explanation::form`].
+    #[inline]
+    pub fn form(&self) -> &str {
+        self.explanation.form()
+    }
+
+    /// Returns how this encoding differs from the default one, in the
+    /// order the changes were applied; empty for the default encoding.
+    #[inline]
+    pub fn variations(&self) -> &[Variation] {
+        &self.variations
+    }
+
+    /// Returns the field-by-field explanation of the encoding.
+    #[inline]
+    pub fn explanation(&self) -> &Explanation {
+        &self.explanation
+    }
+}
+
+impl<S: CodeSink> Encoder<S> {
+    /// Returns every encoding of `insn` this encoder can produce at the
+    /// current position, starting with the one [`Encoder::encode`] emits.
+    ///
+    /// The others use another table form or one or more [`Variation`]s of
+    /// the prefixes, ModR/M, SIB and displacement. Each is decoded in the
+    /// current mode and kept only if it decodes to the same instruction as
+    /// the default encoding, so the list holds no invalid or ambiguous
+    /// encodings. Alternatives that need a feature, such as
+    /// [`Variation::Rex2`] with APX, are listed only when the encoder has
+    /// it. A branch to a label that is not bound yet has no alternatives.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::{Encoder, Variation};
+    /// use rask_x86_64::instruction::{Instruction, Mnemonic};
+    /// use rask_x86_64::operand::Operand;
+    /// use rask_x86_64::registers::Reg64::{RAX, RBX};
+    ///
+    /// let enc = Encoder::new();
+    /// let add = Instruction::with2(Mnemonic::Add, Operand::Reg(RAX), Operand::Reg(RBX));
+    /// let alternatives = enc.alternatives(&add)?;
+    /// assert_eq!(alternatives[0].bytes(), &[0x48, 0x01, 0xD8]);
+    /// assert_eq!(alternatives[1].bytes(), &[0x48, 0x03, 0xC3]);
+    /// assert_eq!(alternatives[1].form(), "REX.W + 03 /r");
+    /// assert_eq!(alternatives[1].variations(), &[Variation::Form]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// Returns the error [`Encoder::encode`] would return.
+    pub fn alternatives(&self, insn: &Instruction) -> RaskResult<Vec<Alternative>> {
+        let default = self.explain(insn)?;
+        let reference = default.instruction();
+        let mut found = vec![Alternative {
+            explanation: default,
+            variations: Vec::new(),
+        }];
+
+        let labels: Vec<_> = insn
+            .operands()
+            .iter()
+            .filter_map(|op| match op {
+                Operand::Label(label) => Some(*label),
+                _ => None,
+            })
+            .collect();
+        if labels.iter().any(|&label| self.label_offset(label).is_none()) {
+            return Ok(found);
+        }
+
+        // Branches to labels are not in the table.
+        if labels.is_empty() {
+            for bytes in self.table_forms(insn) {
+                self.keep(&mut found, &bytes, &reference, &[], Variation::Form);
+            }
+        }
+
+        // Vary every encoding found so far, including the variations
+        // themselves, until nothing new turns up. Each variation only
+        // applies to encodings without it, so this ends.
+        let mut next = 0;
+        while next < found.len() {
+            let variations = found[next].variations.clone();
+            for (bytes, variation) in self.variants(&found[next].explanation) {
+                self.keep(&mut found, &bytes, &reference, &variations, variation);
+            }
+            next += 1;
+        }
+        Ok(found)
+    }
+
+    /// Encodes `insn` as `alternative`, one of the encodings
+    /// [`Encoder::alternatives`] returns for it at the current position.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::{Encoder, Variation};
+    /// use rask_x86_64::instruction::{Instruction, Mnemonic};
+    /// use rask_x86_64::operand::{MemOperand, Operand};
+    /// use rask_x86_64::registers::Reg64::{RAX, RDI};
+    ///
+    /// // Pad `mov rax, [rdi]` to seven bytes with a zero disp32.
+    /// let mut enc = Encoder::new();
+    /// let mov = Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Mem(MemOperand::new(RDI, 0)));
+    /// let wide = enc
+    ///     .alternatives(&mov)?
+    ///     .into_iter()
+    ///     .find(|alt| alt.variations() == [Variation::Disp32])
+    ///     .unwrap();
+    /// enc.encode_as(&mov, &wide)?;
+    /// assert_eq!(enc.bytes(), &[0x48, 0x8B, 0x87, 0x00, 0x00, 0x00, 0x00]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// Returns the error [`Encoder::encode`] would return, or an error if
+    /// `alternative` is not an encoding of `insn` here, such as one listed
+    /// for another instruction or for a branch at another position.
+    pub fn encode_as(&mut self, insn: &Instruction, alternative: &Alternative) -> RaskResult<()> {
+        let alternatives = self.alternatives(insn)?;
+        match alternatives
+            .iter()
+            .position(|alt| alt.bytes() == alternative.bytes())
+        {
+            // The default encoding may branch to an unbound label, which
+            // needs a fixup.
+            Some(0) => self.encode(insn),
+            Some(_) => {
+                let mnemonic = insn.mnemonic();
+                self.instruction(mnemonic.as_str(), insn.operands(), |enc| {
+                    enc.emit_bytes(alternative.bytes());
+                    Ok(())
+                })
+            }
+            None => {
+                let mnemonic = insn.mnemonic();
+                self.instruction(mnemonic.as_str(), insn.operands(), |_| {
+                    Err(invalid(format!(
+                        "{:02x?} is not an encoding of the instruction here",
+                        alternative.bytes()
+                    )))
+                })
+            }
+        }
+    }
+
+    /// Encodes `insn` with each table form that matches its operands and is
+    /// usable in the current mode.
+    fn table_forms(&self, insn: &Instruction) -> Vec<Vec<u8>> {
+        let mnemonic = insn.mnemonic();
+        let ops = insn.operands();
+        let Some(forms) = table::forms(mnemonic.as_str()) else {
+            return Vec::new();
+        };
+        let usable = forms
+            .iter()
+            .filter(|def| def.matches(ops) && def.modes.allows(self.mode));
+
+        let mut encodings = Vec::new();
+        for def in usable {
+            let mut bytes = [0; MAX_INSTRUCTION_LEN];
+            let mut probe = self.probe(SliceSink::new(&mut bytes));
+            let encoded = probe.instruction(mnemonic.as_str(), ops, |enc| {
+                if let Some(prefix) = insn.prefix() {
+                    enc.emit(prefix.byte());
+                }
+                enc.emit_def(def, ops)
+            });
+            if encoded.is_ok() {
+                let len = probe.position();
+                encodings.push(bytes[..len].to_vec());
+            }
+        }
+        encodings
+    }
+
+    /// Returns the encodings one [`Variation`] away from `explanation`.
+    /// They are not checked yet and may be invalid.
+    fn variants(&self, explanation: &Explanation) -> Vec<(Vec<u8>, Variation)> {
+        let bytes = explanation.bytes();
+        let decoded = explanation.decoded();
+        let field = |pred: fn(&FieldKind) -> bool| {
+            explanation
+                .fields()
+                .iter()
+                .find(|field| pred(&field.kind))
+                .copied()
+        };
+        let splice = |at: usize, remove: usize, insert: &[u8]| {
+            let mut bytes = bytes.to_vec();
+            bytes.splice(at..at + remove, insert.iter().copied());
+            bytes
+        };
+        let mut variants = Vec::new();
+
+        // The memory operand: displacement widths and a SIB byte.
+        let mem = decoded
+            .instruction()
+            .operands()
+            .iter()
+            .find_map(|op| match op {
+                Operand::Mem(m) => Some(*m),
+                _ => None,
+            });
+        let modrm = field(|kind| matches!(kind, FieldKind::ModRm(_)));
+        if let (Some(mem), Some(modrm_field)) = (mem, modrm)
+            && let FieldKind::ModRm(fields) = modrm_field.kind
+            && fields.md != 0b11
+        {
+            let at = modrm_field.offset;
+            let byte = bytes[at];
+            let sib = field(|kind| matches!(kind, FieldKind::Sib(_)));
+            let addr16 = mem.address_bits() == Some(16);
+            let base_reg = match sib.map(|f| f.kind) {
+                Some(FieldKind::Sib(sib)) => sib.base,
+                _ => fields.rm,
+            };
+            let no_base = fields.md == 0b00
+                && if addr16 {
+                    fields.rm == 0b110
+                } else {
+                    base_reg == 0b101
+                };
+            let disp_at = at + 1 + usize::from(sib.is_some());
+            let wide = if addr16 { 2 } else { 4 };
+
+            if !no_base {
+                match fields.md {
+                    0b00 => {
+                        let mut with_disp8 = splice(disp_at, 0, &[0]);
+                        with_disp8[at] = byte | 0b01 << 6;
+                        variants.push((with_disp8, Variation::Disp8));
+                        let mut with_disp32 = splice(disp_at, 0, &[0; 4][..wide]);
+                        with_disp32[at] = byte | 0b10 << 6;
+                        variants.push((with_disp32, Variation::Disp32));
+                    }
+                    0b01 => {
+                        let mut with_disp32 = splice(disp_at, 1, &mem.disp.to_le_bytes()[..wide]);
+                        with_disp32[at] = byte & 0x3F | 0b10 << 6;
+                        variants.push((with_disp32, Variation::Disp32));
+                    }
+                    _ => {}
+                }
+            }
+            if sib.is_none() && !addr16 && fields.rm != 0b100 {
+                let mut with_sib = splice(at + 1, 0, &[0b00_100_000 | fields.rm]);
+                with_sib[at] = byte & !0b111 | 0b100;
+                variants.push((with_sib, Variation::Sib));
+            }
+        }
+
+        // The prefixes: REX and REX2.
+        let opcode = field(|kind| matches!(kind, FieldKind::Opcode));
+        if decoded.encoding() == Encoding::Legacy
+            && self.mode == Mode::Long64
+            && let Some(opcode) = opcode
+        {
+            let rex = field(|kind| matches!(kind, FieldKind::Rex(_)));
+            if rex.is_none() {
+                variants.push((splice(opcode.offset, 0, &[0x40]), Variation::RedundantRex));
+            }
+
+            let code = &bytes[opcode.offset..opcode.offset + opcode.len];
+            let escape = code[0] == 0x0F;
+            if self.features.contains(CpuFeature::Apx)
+                && !(escape && matches!(code.get(1), Some(0x38 | 0x3A)))
+            {
+                let at = rex.map_or(opcode.offset, |rex| rex.offset);
+                let bits = decoded.rex().unwrap_or(0x40) & 0x0F;
+                let payload = u8::from(escape) << 7 | bits;
+                let remove = opcode.offset - at + usize::from(escape);
+                variants.push((splice(at, remove, &[0xD5, payload]), Variation::Rex2));
+            }
+        }
+
+        // A short branch: the rel32 form to the same target.
+        if let (Some(target), Some(opcode), Some((_, 1))) =
+            (decoded.branch_target(), opcode, explanation.immediate())
+            && decoded.layout().form.is_none()
+        {
+            let near: &[u8] = match bytes[opcode.offset] {
+                0xEB => &[0xE9],
+                cc @ 0x70..=0x7F => &[0x0F, cc + 0x10],
+                _ => &[],
+            };
+            if !near.is_empty() {
+                let width = if self.mode == Mode::Real16 { 2 } else { 4 };
+                let end = opcode.offset + near.len() + width;
+                let rel = target.wrapping_sub(decoded.address() + end as u64) as u32;
+                let mut branch = bytes[..opcode.offset].to_vec();
+                branch.extend_from_slice(near);
+                branch.extend_from_slice(&rel.to_le_bytes()[..width]);
+                variants.push((branch, Variation::NearBranch));
+            }
+        }
+        variants
+    }
+
+    /// Appends `bytes`, reached from an encoding with `variations` by
+    /// `variation`, to `found` if they are new and decode to `reference`.
+    fn keep(
+        &self,
+        found: &mut Vec<Alternative>,
+        bytes: &[u8],
+        reference: &Instruction,
+        variations: &[Variation],
+        variation: Variation,
+    ) {
+        if bytes.len() > MAX_INSTRUCTION_LEN || found.iter().any(|alt| alt.bytes() == bytes) {
+            return;
+        }
+        let mut decoder = Decoder::with_mode(bytes, self.mode);
+        decoder.set_address(self.offset() as u64);
+        let Ok(decoded) = decoder.decode() else {
+            return;
+        };
+        if decoded.len() != bytes.len() || decoded.instruction() != *reference {
+            return;
+        }
+        let mut variations = variations.to_vec();
+        variations.push(variation);
+        found.push(Alternative {
+            explanation: Explanation::new(decoded),
+            variations,
+        });
+    }
+}
@@ -0,0 +1,213 @@
+//! Intel AMX (Advanced Matrix Extensions) tile instructions.
+//!
+//! AMX adds eight tile registers ([`TmmReg`]) and a tile matrix-multiply
+//! unit. All instructions are VEX-encoded in the `0F 38` map with
+//! `VEX.128` and `W0`; the mandatory prefix (`pp`) distinguishes the
+//! variants sharing an opcode byte.
+//!
+//! AMX exists only in 64-bit mode; in other modes every tile instruction
+//! fails with [`RaskError::UnsupportedFeature`](rask_common::RaskError::UnsupportedFeature).
+//!
+//! Tile loads and stores use the SDM's `sibmem` operand: the address is
+//! always expressed with a SIB byte, and the index register (times the
+//! scale) is the stride between consecutive tile rows. Without an index the
+//! stride is zero.
+
+use super::{Encoder, Rm, Vex};
+use crate::{
+    operand::{MemOperand, Operand},
+    registers::TmmReg,
+    sink::CodeSink,
+};
+use rask_common::RaskResult;
+
+/// `VEX.128.<pp>.0F38.W0` for the given implied prefix.
+const fn amx(pp: u8) -> Vex {
+    Vex {
+        l: false,
+        pp,
+        map: 2,
+        w: false,
+    }
+}
+
+/// Implied-prefix values for the VEX `pp` field.
+const NP: u8 = 0;
+const P66: u8 = 1;
+const PF3: u8 = 2;
+const PF2: u8 = 3;
+
+impl<S: CodeSink> Encoder<S> {
+    /// Encodes an `LDTILECFG m512` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// VEX.128.NP.0F38.W0 49 !(11):000:bbb
+    /// ```
+    ///
+    /// Loads the 64-byte tile configuration (palette, rows and bytes per row
+    /// of each tile) from memory. `!(11)` means the ModR/M must address
+    /// memory; the `reg` field is 0.
+    ///
+    /// | Instruction          | Bytes (hex)       |
+    /// |----------------------|-------------------|
+    /// | `ldtilecfg [rax]`    | C4 E2 78 49 00    |
+    /// | `ldtilecfg [r8+64]`  | C4 C2 78 49 40 40 |
+    ///
+    /// Reference: Intel SDM Vol. 2A, "LDTILECFG—Load Tile Configuration".
+    pub fn ldtilecfg(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.instruction("ldtilecfg", &[Operand::Mem(mem)], |enc| {
+            enc.emit_amx(NP, 0x49, 0, 0, Rm::Mem(&mem))
+        })
+    }
+
+    /// Encodes an `STTILECFG m512` instruction (`VEX.128.66.0F38.W0 49 !(11):000:bbb`).
+    ///
+    /// Reference: Intel SDM Vol. 2B, "STTILECFG—Store Tile Configuration".
+    pub fn sttilecfg(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.instruction("sttilecfg", &[Operand::Mem(mem)], |enc| {
+            enc.emit_amx(P66, 0x49, 0, 0, Rm::Mem(&mem))
+        })
+    }
+
+    /// Encodes a `TILELOADD tmm, sibmem` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// VEX.128.F2.0F38.W0 4B !(11):rrr:100
+    /// ```
+    ///
+    /// `r/m = 100` forces the SIB byte: the base and displacement give the
+    /// address of the first row, the index register the row stride.
+    ///
+    /// | Instruction                     | Bytes (hex)          |
+    /// |---------------------------------|----------------------|
+    /// | `tileloadd tmm1, [rax + rcx*4]` | C4 E2 7B 4B 0C 88    |
+    /// | `tileloadd tmm0, [rsi]`         | C4 E2 7B 4B 04 26    |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "TILELOADD/TILELOADDT1—Load Tile".
+    pub fn tileloadd(&mut self, dst: TmmReg, src: MemOperand) -> RaskResult<()> {
+        let operands = [Operand::Tmm(dst), Operand::Mem(src)];
+        self.instruction("tileloadd", &operands, |enc| {
+            enc.emit_amx(PF2, 0x4B, dst.id(), 0, Rm::SibMem(&src))
+        })
+    }
+
+    /// Encodes a `TILELOADDT1 tmm, sibmem` instruction
+    /// (`VEX.128.66.0F38.W0 4B !(11):rrr:100`), a tile load with a hint that
+    /// the data will not be reused soon.
+    ///
+    /// Reference: Intel SDM Vol. 2B, "TILELOADD/TILELOADDT1—Load Tile".
+    pub fn tileloaddt1(&mut self, dst: TmmReg, src: MemOperand) -> RaskResult<()> {
+        let operands = [Operand::Tmm(dst), Operand::Mem(src)];
+        self.instruction("tileloaddt1", &operands, |enc| {
+            enc.emit_amx(P66, 0x4B, dst.id(), 0, Rm::SibMem(&src))
+        })
+    }
+
+    /// Encodes a `TILESTORED sibmem, tmm` instruction
+    /// (`VEX.128.F3.0F38.W0 4B !(11):rrr:100`).
+    ///
+    /// Reference: Intel SDM Vol. 2B, "TILESTORED—Store Tile".
+    pub fn tilestored(&mut self, dst: MemOperand, src: TmmReg) -> RaskResult<()> {
+        let operands = [Operand::Mem(dst), Operand::Tmm(src)];
+        self.instruction("tilestored", &operands, |enc| {
+            enc.emit_amx(PF3, 0x4B, src.id(), 0, Rm::SibMem(&dst))
+        })
+    }
+
+    /// Encodes a `TILEZERO tmm` instruction (`VEX.128.F2.0F38.W0 49 11:rrr:000`).
+    ///
+    /// Reference: Intel SDM Vol. 2B, "TILEZERO—Zero Tile".
+    pub fn tilezero(&mut self, dst: TmmReg) -> RaskResult<()> {
+        self.instruction("tilezero", &[Operand::Tmm(dst)], |enc| {
+            enc.emit_amx(PF2, 0x49, dst.id(), 0, Rm::Reg(0))
+        })
+    }
+
+    /// Encodes a `TILERELEASE` instruction (`VEX.128.NP.0F38.W0 49 C0`).
+    ///
+    /// Returns the tile unit to its initial (unconfigured) state.
+    ///
+    /// Reference: Intel SDM Vol. 2B, "TILERELEASE—Release Tile".
+    pub fn tilerelease(&mut self) -> RaskResult<()> {
+        self.instruction("tilerelease", &[], |enc| {
+            enc.emit_amx(NP, 0x49, 0, 0, Rm::Reg(0))
+        })
+    }
+
+    /// Encodes a `TDPBSSD tmm1, tmm2, tmm3` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// VEX.128.F2.0F38.W0 5E 11:rrr:bbb
+    /// ```
+    ///
+    /// Computes dot products of signed bytes in `tmm2` and signed bytes in
+    /// `tmm3`, accumulating dword results into `tmm1`. Note the operand
+    /// placement: `tmm1` is ModR/M `reg`, `tmm2` is ModR/M `r/m` and `tmm3`
+    /// travels in `VEX.vvvv`.
+    ///
+    /// | Instruction                | Bytes (hex)       |
+    /// |----------------------------|-------------------|
+    /// | `tdpbssd tmm1, tmm2, tmm3` | C4 E2 63 5E CA    |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "TDPBSSD/TDPBSUD/TDPBUSD/TDPBUUD—Dot
+    /// Product of Signed/Unsigned Bytes with Dword Accumulation".
+    pub fn tdpbssd(&mut self, dst: TmmReg, src1: TmmReg, src2: TmmReg) -> RaskResult<()> {
+        self.emit_tile_dp("tdpbssd", PF2, 0x5E, dst, src1, src2)
+    }
+
+    /// Encodes a `TDPBSUD tmm1, tmm2, tmm3` instruction (signed × unsigned,
+    /// `VEX.128.F3.0F38.W0 5E 11:rrr:bbb`). See [`Encoder::tdpbssd`].
+    pub fn tdpbsud(&mut self, dst: TmmReg, src1: TmmReg, src2: TmmReg) -> RaskResult<()> {
+        self.emit_tile_dp("tdpbsud", PF3, 0x5E, dst, src1, src2)
+    }
+
+    /// Encodes a `TDPBUSD tmm1, tmm2, tmm3` instruction (unsigned × signed,
+    /// `VEX.128.66.0F38.W0 5E 11:rrr:bbb`). See [`Encoder::tdpbssd`].
+    pub fn tdpbusd(&mut self, dst: TmmReg, src1: TmmReg, src2: TmmReg) -> RaskResult<()> {
+        self.emit_tile_dp("tdpbusd", P66, 0x5E, dst, src1, src2)
+    }
+
+    /// Encodes a `TDPBUUD tmm1, tmm2, tmm3` instruction (unsigned × unsigned,
+    /// `VEX.128.NP.0F38.W0 5E 11:rrr:bbb`). See [`Encoder::tdpbssd`].
+    pub fn tdpbuud(&mut self, dst: TmmReg, src1: TmmReg, src2: TmmReg) -> RaskResult<()> {
+        self.emit_tile_dp("tdpbuud", NP, 0x5E, dst, src1, src2)
+    }
+
+    /// Encodes a `TDPBF16PS tmm1, tmm2, tmm3` instruction
+    /// (`VEX.128.F3.0F38.W0 5C 11:rrr:bbb`): dot products of BF16 pairs
+    /// accumulated into single-precision results. See [`Encoder::tdpbssd`]
+    /// for the operand placement.
+    ///
+    /// Reference: Intel SDM Vol. 2B, "TDPBF16PS—Dot Product of BF16 Tiles
+    /// Accumulated into Packed Single Precision Tile".
+    pub fn tdpbf16ps(&mut self, dst: TmmReg, src1: TmmReg, src2: TmmReg) -> RaskResult<()> {
+        self.emit_tile_dp("tdpbf16ps", PF3, 0x5C, dst, src1, src2)
+    }
+
+    /// Emits a `VEX.128.<pp>.0F38.W0` tile instruction after checking that
+    /// the encoder is in 64-bit mode.
+    fn emit_amx(&mut self, pp: u8, opcode: u8, reg: u8, vvvv: u8, rm: Rm) -> RaskResult<()> {
+        self.require_long_mode("AMX")?;
+        self.emit_vex_rm(amx(pp), opcode, reg, vvvv, rm)
+    }
+
+    /// Shared body of the tile dot-product instructions: `reg = dst`,
+    /// `r/m = src1`, `vvvv = src2`.
+    fn emit_tile_dp(
+        &mut self,
+        mnemonic: &str,
+        pp: u8,
+        opcode: u8,
+        dst: TmmReg,
+        src1: TmmReg,
+        src2: TmmReg,
+    ) -> RaskResult<()> {
+        let operands = [dst, src1, src2].map(Operand::Tmm);
+        self.instruction(mnemonic, &operands, |enc| {
+            enc.emit_amx(pp, opcode, dst.id(), src2.id(), Rm::Reg(src1.id()))
+        })
+    }
+}
@@ -0,0 +1,289 @@
+//! Intel APX (Advanced Performance Extensions) instructions.
+//!
+//! APX adds the GPRs R16–R31 and a set of new instruction forms. Legacy
+//! instructions reach the new registers through the REX2 prefix, handled by
+//! [`Encoder::emit_rm`]; the forms defined here are EVEX-encoded in the new
+//! opcode map 4, where the EVEX fields that are meaningless for integer
+//! instructions are repurposed:
+//!
+//! ```text
+//! 62 [~R3 ~X3 ~B3 ~R4 B4 1 0 0] [W ~vvvv ~X4 pp] [0 0 0 ND ~V4 NF 0 0] opcode ModR/M ...
+//! ```
+//!
+//! `ND` (new data destination) turns `vvvv` into a destination register,
+//! giving non-destructive three-operand ALU ops. `NF` suppresses the flags
+//! update and is not used here.
+//!
+//! Every instruction in this module requires [`CpuFeature::Apx`].
+
+use super::{Encoder, Rm, invalid};
+use crate::{
+    features::CpuFeature,
+    operand::{MemSize, Operand},
+    registers::Reg64,
+    sink::CodeSink,
+};
+use rask_common::{RaskError, RaskResult};
+
+/// An ALU operation in the classic `00`–`3F` opcode block, identified by its
+/// `/digit` in the `80`–`83` immediate group. The `r/m, reg` form is at
+/// `digit * 8 + 1` and the `reg, r/m` form at `digit * 8 + 3`.
+#[derive(Clone, Copy)]
+struct AluOp {
+    mnemonic: &'static str,
+    digit: u8,
+}
+
+const fn alu(mnemonic: &'static str, digit: u8) -> AluOp {
+    AluOp { mnemonic, digit }
+}
+
+const ADD: AluOp = alu("ADD", 0);
+const OR: AluOp = alu("OR", 1);
+const ADC: AluOp = alu("ADC", 2);
+const SBB: AluOp = alu("SBB", 3);
+const AND: AluOp = alu("AND", 4);
+const SUB: AluOp = alu("SUB", 5);
+const XOR: AluOp = alu("XOR", 6);
+
+impl<S: CodeSink> Encoder<S> {
+    /// Encodes a `PUSH2 r64, r64` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// EVEX.128.NP.MAP4.W0 FF /6   (ND = 1, mod = 11)
+    /// ```
+    ///
+    /// Pushes `first` and then `second` with a single 16-byte stack
+    /// adjustment; RSP must be 16-byte aligned. `first` travels in
+    /// `EVEX.vvvv` and `second` in ModR/M `r/m`.
+    ///
+    /// | Instruction      | Bytes (hex)       |
+    /// |------------------|-------------------|
+    /// | `push2 rax, rcx` | 62 F4 7C 18 FF F1 |
+    ///
+    /// Reference: Intel APX Architecture Specification, "PUSH2—Push Two
+    /// 64-bit Operands".
+    ///
+    /// Returns an error if either register is RSP or APX is not enabled.
+    pub fn push2(&mut self, first: Reg64, second: Reg64) -> RaskResult<()> {
+        self.emit_push2_pop2("PUSH2", false, 0xFF, 6, first, second)
+    }
+
+    /// Encodes a `PUSH2P r64, r64` instruction: [`Encoder::push2`] with
+    /// `EVEX.W1`, hinting that a matching `POP2P` will restore the pair.
+    pub fn push2p(&mut self, first: Reg64, second: Reg64) -> RaskResult<()> {
+        self.emit_push2_pop2("PUSH2P", true, 0xFF, 6, first, second)
+    }
+
+    /// Encodes a `POP2 r64, r64` instruction (`EVEX.128.NP.MAP4.W0 8F /0`,
+    /// ND = 1).
+    ///
+    /// Pops into `first` and then `second`, so `pop2 rcx, rax` undoes
+    /// `push2 rax, rcx`. The operand placement matches [`Encoder::push2`].
+    ///
+    /// | Instruction     | Bytes (hex)       |
+    /// |-----------------|-------------------|
+    /// | `pop2 rcx, rax` | 62 F4 74 18 8F C0 |
+    ///
+    /// Reference: Intel APX Architecture Specification, "POP2—Pop Two
+    /// 64-bit Operands".
+    ///
+    /// Returns an error if either register is RSP, both are the same
+    /// register, or APX is not enabled.
+    pub fn pop2(&mut self, first: Reg64, second: Reg64) -> RaskResult<()> {
+        self.emit_push2_pop2("POP2", false, 0x8F, 0, first, second)
+    }
+
+    /// Encodes a `POP2P r64, r64` instruction: [`Encoder::pop2`] with
+    /// `EVEX.W1`, the counterpart of [`Encoder::push2p`].
+    pub fn pop2p(&mut self, first: Reg64, second: Reg64) -> RaskResult<()> {
+        self.emit_push2_pop2("POP2P", true, 0x8F, 0, first, second)
+    }
+
+    /// Encodes an `ADD r64, r/m64, r64|imm` new-data-destination instruction:
+    /// `dst = src1 + src2` without modifying either source.
+    ///
+    /// ### Encoding forms
+    /// ```text
+    /// EVEX.128.NP.MAP4.W1 01 /r     ADD r64 (vvvv), r/m64, r64
+    /// EVEX.128.NP.MAP4.W1 03 /r     ADD r64 (vvvv), r64, r/m64
+    /// EVEX.128.NP.MAP4.W1 83 /0 ib  ADD r64 (vvvv), r/m64, imm8
+    /// EVEX.128.NP.MAP4.W1 81 /0 id  ADD r64 (vvvv), r/m64, imm32
+    /// ```
+    ///
+    /// All forms set `ND = 1`; the destination travels in `EVEX.vvvv`. The
+    /// shortest immediate form is chosen automatically.
+    ///
+    /// | Instruction         | Bytes (hex)          |
+    /// |---------------------|----------------------|
+    /// | `add r8, rbx, rcx`  | 62 F4 BC 18 01 CB    |
+    /// | `add r20, r21, 1`   | 62 FC DC 10 83 C5 01 |
+    ///
+    /// Reference: Intel APX Architecture Specification, "ADD—Add".
+    ///
+    /// Returns an error if APX is not enabled, on an invalid operand
+    /// combination, or if the immediate does not fit in a sign-extended
+    /// 32-bit value.
+    pub fn add_ndd(&mut self, dst: Reg64, src1: Operand, src2: Operand) -> RaskResult<()> {
+        self.emit_alu_ndd(ADD, dst, src1, src2)
+    }
+
+    /// Encodes an `OR r64, r/m64, r64|imm` NDD instruction. See
+    /// [`Encoder::add_ndd`] for the forms (opcodes `09`, `0B`, `83 /1`, `81 /1`).
+    pub fn or_ndd(&mut self, dst: Reg64, src1: Operand, src2: Operand) -> RaskResult<()> {
+        self.emit_alu_ndd(OR, dst, src1, src2)
+    }
+
+    /// Encodes an `ADC r64, r/m64, r64|imm` NDD instruction. See
+    /// [`Encoder::add_ndd`] for the forms (opcodes `11`, `13`, `83 /2`, `81 /2`).
+    pub fn adc_ndd(&mut self, dst: Reg64, src1: Operand, src2: Operand) -> RaskResult<()> {
+        self.emit_alu_ndd(ADC, dst, src1, src2)
+    }
+
+    /// Encodes an `SBB r64, r/m64, r64|imm` NDD instruction. See
+    /// [`Encoder::add_ndd`] for the forms (opcodes `19`, `1B`, `83 /3`, `81 /3`).
+    pub fn sbb_ndd(&mut self, dst: Reg64, src1: Operand, src2: Operand) -> RaskResult<()> {
+        self.emit_alu_ndd(SBB, dst, src1, src2)
+    }
+
+    /// Encodes an `AND r64, r/m64, r64|imm` NDD instruction. See
+    /// [`Encoder::add_ndd`] for the forms (opcodes `21`, `23`, `83 /4`, `81 /4`).
+    pub fn and_ndd(&mut self, dst: Reg64, src1: Operand, src2: Operand) -> RaskResult<()> {
+        self.emit_alu_ndd(AND, dst, src1, src2)
+    }
+
+    /// Encodes a `SUB r64, r/m64, r64|imm` NDD instruction: `dst = src1 - src2`.
+    /// See [`Encoder::add_ndd`] for the forms (opcodes `29`, `2B`, `83 /5`,
+    /// `81 /5`).
+    pub fn sub_ndd(&mut self, dst: Reg64, src1: Operand, src2: Operand) -> RaskResult<()> {
+        self.emit_alu_ndd(SUB, dst, src1, src2)
+    }
+
+    /// Encodes an `XOR r64, r/m64, r64|imm` NDD instruction. See
+    /// [`Encoder::add_ndd`] for the forms (opcodes `31`, `33`, `83 /6`, `81 /6`).
+    pub fn xor_ndd(&mut self, dst: Reg64, src1: Operand, src2: Operand) -> RaskResult<()> {
+        self.emit_alu_ndd(XOR, dst, src1, src2)
+    }
+
+    /// Shared body of `PUSH2`/`POP2`: `vvvv = first`, `r/m = second`.
+    fn emit_push2_pop2(
+        &mut self,
+        mnemonic: &str,
+        w: bool,
+        opcode: u8,
+        digit: u8,
+        first: Reg64,
+        second: Reg64,
+    ) -> RaskResult<()> {
+        let operands = [Operand::Reg(first), Operand::Reg(second)];
+        self.instruction(mnemonic, &operands, |enc| {
+            enc.require(CpuFeature::Apx, mnemonic)?;
+            if first == Reg64::RSP || second == Reg64::RSP {
+                return Err(invalid("RSP cannot be an operand"));
+            }
+            if opcode == 0x8F && first == second {
+                return Err(invalid("destinations must be different registers"));
+            }
+            enc.emit_apx_evex(w, true, opcode, digit, first.id(), Rm::Reg(second.id()))
+        })
+    }
+
+    /// Selects the form of a 64-bit NDD ALU instruction and emits it.
+    fn emit_alu_ndd(
+        &mut self,
+        op: AluOp,
+        dst: Reg64,
+        src1: Operand,
+        src2: Operand,
+    ) -> RaskResult<()> {
+        let operands = [Operand::Reg(dst), src1, src2];
+        self.instruction(op.mnemonic, &operands, |enc| {
+            enc.require(CpuFeature::Apx, op.mnemonic)?;
+            enc.emit_alu_ndd_form(op, dst, src1, src2)
+        })
+    }
+
+    /// Body of [`Encoder::emit_alu_ndd`].
+    fn emit_alu_ndd_form(
+        &mut self,
+        op: AluOp,
+        dst: Reg64,
+        src1: Operand,
+        src2: Operand,
+    ) -> RaskResult<()> {
+        let base = op.digit * 8;
+        let ndd = dst.id();
+        for src in [src1, src2] {
+            if let Operand::Mem(m) = src
+                && m.size.is_some_and(|size| size != MemSize::Qword)
+            {
+                return Err(invalid(
+                    "the memory operand of a 64-bit NDD form must be a qword",
+                ));
+            }
+        }
+
+        match (src1, src2) {
+            (Operand::Reg(a), Operand::Reg(b)) => {
+                self.emit_apx_evex(true, true, base + 1, b.id(), ndd, Rm::Reg(a.id()))
+            }
+            (Operand::Mem(ref m), Operand::Reg(b)) => {
+                self.emit_apx_evex(true, true, base + 1, b.id(), ndd, Rm::Mem(m))
+            }
+            (Operand::Reg(a), Operand::Mem(ref m)) => {
+                self.emit_apx_evex(true, true, base + 3, a.id(), ndd, Rm::Mem(m))
+            }
+            (Operand::Reg(_) | Operand::Mem(_), Operand::Imm(imm)) => {
+                let rm = match src1 {
+                    Operand::Reg(a) => Rm::Reg(a.id()),
+                    Operand::Mem(ref m) => Rm::Mem(m),
+                    _ => unreachable!(),
+                };
+                if let Ok(imm8) = i8::try_from(imm) {
+                    self.emit_apx_evex(true, true, 0x83, op.digit, ndd, rm)?;
+                    self.emit(imm8 as u8);
+                } else if let Ok(imm32) = i32::try_from(imm) {
+                    self.emit_apx_evex(true, true, 0x81, op.digit, ndd, rm)?;
+                    self.emit_bytes(&imm32.to_le_bytes());
+                } else {
+                    return Err(RaskError::ImmediateOutOfRange {
+                        mnemonic: String::new(),
+                        value: imm,
+                        bits: 32,
+                    });
+                }
+                Ok(())
+            }
+            _ => Err(invalid("no NDD form takes these operands")),
+        }
+    }
+
+    /// Emits a 64-bit-wide integer instruction in EVEX map 4 with no implied
+    /// prefix. `rm` is a general-purpose register or memory operand, so bit 4
+    /// of its ID travels in B4 rather than in EVEX.X as for vector registers.
+    fn emit_apx_evex(
+        &mut self,
+        w: bool,
+        nd: bool,
+        opcode: u8,
+        reg: u8,
+        vvvv: u8,
+        rm: Rm,
+    ) -> RaskResult<()> {
+        self.require_long_mode("APX")?;
+        self.emit_mem_prefixes(rm)?;
+        let (b, x) = (rm.base_id(), rm.index_id());
+        let p0 = ((((reg >> 3) & 1) ^ 1) << 7)
+            | ((((x >> 3) & 1) ^ 1) << 6)
+            | ((((b >> 3) & 1) ^ 1) << 5)
+            | ((((reg >> 4) & 1) ^ 1) << 4)
+            | (((b >> 4) & 1) << 3)
+            | 0b100;
+        let p1 = ((w as u8) << 7) | ((!vvvv & 0x0F) << 3) | ((((x >> 4) & 1) ^ 1) << 2);
+        let p2 = ((nd as u8) << 4) | ((((vvvv >> 4) & 1) ^ 1) << 3);
+
+        self.emit_bytes(&[0x62, p0, p1, p2, opcode]);
+        self.emit_modrm(reg, rm, 1)
+    }
+}
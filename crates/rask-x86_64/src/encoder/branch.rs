@@ -0,0 +1,231 @@
+//! Labels and near branches.
+//!
+//! A branch to a label that is already bound takes the shortest encoding
+//! that reaches it. A branch to a label that is not bound yet always takes
+//! the full-width displacement, which [`Encoder::bind_label`] patches in the
+//! sink once the target is known.
+
+use super::Encoder;
+use crate::{
+    instruction::{Condition, Mnemonic},
+    mode::Mode,
+    operand::{Label, Operand},
+    sink::CodeSink,
+};
+use rask_common::{RaskError, RaskResult};
+
+/// A branch displacement waiting for its label to be bound.
+pub(super) struct Fixup {
+    label: Label,
+    /// Code offset of the displacement, which is the last field of the
+    /// branch, so the branch ends at `at + width`.
+    at: usize,
+    /// Width of the displacement in bytes: 2 in 16-bit mode, 4 otherwise.
+    width: usize,
+}
+
+impl<S: CodeSink> Encoder<S> {
+    /// Creates a new, unbound label.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::instruction::Condition;
+    /// use rask_x86_64::operand::Operand;
+    /// use rask_x86_64::registers::Reg64::RCX;
+    ///
+    /// // Count RCX down to zero.
+    /// let mut enc = Encoder::new();
+    /// let top = enc.create_label();
+    /// enc.bind_label(top)?;
+    /// enc.dec(Operand::Reg(RCX))?;
+    /// enc.jcc(Condition::Ne, top)?;
+    /// enc.ret()?;
+    /// assert_eq!(enc.bytes(), &[0x48, 0xFF, 0xC9, 0x75, 0xFB, 0xC3]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    pub fn create_label(&mut self) -> Label {
+        let label = Label(self.labels.len() as u32);
+        self.labels.push(None);
+        label
+    }
+
+    /// Binds `label` to the current position and fills in the branches
+    /// already emitted to it.
+    ///
+    /// Returns an error if the label is already bound or belongs to another
+    /// encoder, or if the sink cannot patch the earlier branches.
+    pub fn bind_label(&mut self, label: Label) -> RaskResult<()> {
+        let offset = self.offset();
+        match self.labels.get_mut(label.0 as usize) {
+            Some(slot @ None) => *slot = Some(offset),
+            Some(Some(_)) => {
+                return Err(RaskError::Other(format!("label {label} is bound twice")));
+            }
+            None => return Err(foreign(label)),
+        }
+
+        let (ready, waiting) = std::mem::take(&mut self.fixups)
+            .into_iter()
+            .partition(|f| f.label == label);
+        self.fixups = waiting;
+        for fixup in ready {
+            let bytes = displacement(offset, fixup.at + fixup.width, fixup.width)?;
+            self.sink
+                .patch(fixup.at - self.origin, &bytes[..fixup.width])?;
+        }
+        Ok(())
+    }
+
+    /// Returns the offset `label` is bound to, or `None` if it is not bound
+    /// yet.
+    pub fn label_offset(&self, label: Label) -> Option<usize> {
+        self.labels.get(label.0 as usize).copied().flatten()
+    }
+
+    /// Checks that every label a branch refers to has been bound and returns
+    /// the sink.
+    ///
+    /// Returns [`RaskError::UnboundLabel`] naming the first label that is
+    /// still unbound.
+    pub fn finish(self) -> RaskResult<S> {
+        if let Some(fixup) = self.fixups.first() {
+            return Err(RaskError::UnboundLabel {
+                label: fixup.label.to_string(),
+            });
+        }
+        Ok(self.sink)
+    }
+
+    /// Encodes a near `JMP`.
+    ///
+    /// ### Encoding forms
+    /// ```text
+    /// EB cb        JMP rel8
+    /// E9 cd        JMP rel32   (E9 cw in 16-bit mode)
+    /// FF /4        JMP r/m64   (r/m32, r/m16 outside 64-bit mode)
+    /// ```
+    ///
+    /// A [`Label`] target is reached with `rel8` when it is already bound
+    /// and close enough, and with `rel32` otherwise. A register or memory
+    /// target is an indirect jump.
+    ///
+    /// | Instruction             | Bytes (hex)    |
+    /// |-------------------------|----------------|
+    /// | `jmp` to itself         | EB FE          |
+    /// | `jmp` to a later label  | E9 xx xx xx xx |
+    /// | `jmp rax`               | FF E0          |
+    /// | `jmp qword ptr [rdi+8]` | FF 67 08       |
+    ///
+    /// Reference: Intel SDM Vol. 2A, "JMP—Jump".
+    pub fn jmp(&mut self, target: Operand) -> RaskResult<()> {
+        match target {
+            Operand::Label(label) => self.instruction("jmp", &[target], |enc| {
+                enc.emit_branch(Some(&[0xEB]), &[0xE9], label)
+            }),
+            _ => self.emit_instruction("jmp", &[target]),
+        }
+    }
+
+    /// Encodes a conditional near jump, `Jcc`.
+    ///
+    /// ### Encoding forms
+    /// ```text
+    /// 70+cc cb       Jcc rel8
+    /// 0F 80+cc cd    Jcc rel32   (0F 80+cc cw in 16-bit mode)
+    /// ```
+    ///
+    /// The displacement is chosen as for [`Encoder::jmp`].
+    ///
+    /// | Instruction               | Bytes (hex)       |
+    /// |---------------------------|-------------------|
+    /// | `jne` 5 bytes back        | 75 F9             |
+    /// | `je` to a later label     | 0F 84 xx xx xx xx |
+    ///
+    /// Reference: Intel SDM Vol. 2A, "Jcc—Jump if Condition Is Met".
+    pub fn jcc(&mut self, cond: Condition, target: Label) -> RaskResult<()> {
+        let cc = cond.code();
+        let mnemonic = Mnemonic::jcc(cond);
+        self.instruction(mnemonic.as_str(), &[Operand::Label(target)], |enc| {
+            enc.emit_branch(Some(&[0x70 | cc]), &[0x0F, 0x80 | cc], target)
+        })
+    }
+
+    /// Encodes a near `CALL`.
+    ///
+    /// ### Encoding forms
+    /// ```text
+    /// E8 cd        CALL rel32   (E8 cw in 16-bit mode)
+    /// FF /2        CALL r/m64   (r/m32, r/m16 outside 64-bit mode)
+    /// ```
+    ///
+    /// | Instruction              | Bytes (hex)    |
+    /// |--------------------------|----------------|
+    /// | `call` to a label        | E8 xx xx xx xx |
+    /// | `call r11`               | 41 FF D3       |
+    /// | `call qword ptr [rax]`   | FF 10          |
+    ///
+    /// Reference: Intel SDM Vol. 2A, "CALL—Call Procedure".
+    pub fn call(&mut self, target: Operand) -> RaskResult<()> {
+        match target {
+            Operand::Label(label) => self.instruction("call", &[target], |enc| {
+                enc.emit_branch(None, &[0xE8], label)
+            }),
+            _ => self.emit_instruction("call", &[target]),
+        }
+    }
+
+    /// Emits a branch to `label`: `short` with a `rel8` if the label is bound
+    /// and in reach, `near` with a full-width displacement otherwise.
+    fn emit_branch(&mut self, short: Option<&[u8]>, near: &[u8], label: Label) -> RaskResult<()> {
+        let Some(&target) = self.labels.get(label.0 as usize) else {
+            return Err(foreign(label));
+        };
+        let width = if self.mode == Mode::Real16 { 2 } else { 4 };
+        let start = self.offset();
+
+        if let Some(target) = target {
+            if let Some(short) = short {
+                let end = start + short.len() + 1;
+                if let Ok(rel) = i8::try_from(target as i64 - end as i64) {
+                    self.emit_bytes(short);
+                    self.emit(rel as u8);
+                    return Ok(());
+                }
+            }
+            let bytes = displacement(target, start + near.len() + width, width)?;
+            self.emit_bytes(near);
+            self.emit_bytes(&bytes[..width]);
+            return Ok(());
+        }
+
+        self.emit_bytes(near);
+        self.fixups.push(Fixup {
+            label,
+            at: self.offset(),
+            width,
+        });
+        self.emit_bytes(&[0; 4][..width]);
+        Ok(())
+    }
+}
+
+/// Returns the little-endian displacement from `end` to `target` in its
+/// first `width` bytes.
+fn displacement(target: usize, end: usize, width: usize) -> RaskResult<[u8; 4]> {
+    let rel = target as i64 - end as i64;
+    let fits = match width {
+        2 => i16::try_from(rel).is_ok(),
+        _ => i32::try_from(rel).is_ok(),
+    };
+    if !fits {
+        return Err(RaskError::Other(format!(
+            "branch displacement {rel} does not fit in {} bits",
+            width * 8
+        )));
+    }
+    Ok((rel as i32).to_le_bytes())
+}
+
+fn foreign(label: Label) -> RaskError {
+    RaskError::Other(format!("label {label} does not belong to this encoder"))
+}
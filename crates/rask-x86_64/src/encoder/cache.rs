@@ -0,0 +1,306 @@
+//! Cache-control, prefetch and non-temporal store instructions.
+//!
+//! These are the building blocks of bulk-copy loops that stream through
+//! buffers larger than the cache, and of persistent-memory writes that must
+//! be flushed out of the cache hierarchy explicitly.
+
+use super::{Encoder, Rm, Vex, invalid};
+use crate::{
+    operand::{MemOperand, Operand},
+    registers::XmmReg,
+    sink::CodeSink,
+};
+use rask_common::RaskResult;
+
+impl<S: CodeSink> Encoder<S> {
+    /// Encodes a `PREFETCHT0 m8` instruction (prefetch into all cache levels).
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// 0F 18 /1
+    /// ```
+    ///
+    /// The `/1` means the ModR/M `reg` field holds the constant 1 instead of a
+    /// register; it selects the hint. `PREFETCHNTA`, `PREFETCHT1` and
+    /// `PREFETCHT2` share the opcode with `/0`, `/2` and `/3`.
+    ///
+    /// | Instruction            | Bytes (hex) |
+    /// |------------------------|-------------|
+    /// | `prefetcht0 [rax]`     | 0F 18 08    |
+    /// | `prefetcht2 [r12]`     | 41 0F 18 1C 24 |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "PREFETCHh—Prefetch Data Into Caches".
+    pub fn prefetcht0(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("prefetcht0", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes a `PREFETCHT1 m8` instruction (prefetch into L2 and higher).
+    ///
+    /// Encoding: `0F 18 /2`. See [`Encoder::prefetcht0`].
+    pub fn prefetcht1(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("prefetcht1", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes a `PREFETCHT2 m8` instruction (prefetch into L3 and higher).
+    ///
+    /// Encoding: `0F 18 /3`. See [`Encoder::prefetcht0`].
+    pub fn prefetcht2(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("prefetcht2", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes a `PREFETCHNTA m8` instruction (prefetch with a non-temporal
+    /// hint, minimizing cache pollution).
+    ///
+    /// Encoding: `0F 18 /0`. See [`Encoder::prefetcht0`].
+    pub fn prefetchnta(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("prefetchnta", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes a `PREFETCHW m8` instruction (prefetch in anticipation of a write).
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// 0F 0D /1
+    /// ```
+    ///
+    /// Reference: Intel SDM Vol. 2B, "PREFETCHW—Prefetch Data Into Caches in
+    /// Anticipation of a Write".
+    pub fn prefetchw(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("prefetchw", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes a `CLFLUSH m8` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// 0F AE /7
+    /// ```
+    ///
+    /// Writes back and invalidates the cache line containing `mem`. `CLFLUSH`
+    /// is ordered with respect to other stores; prefer [`Encoder::clflushopt`]
+    /// or [`Encoder::clwb`] followed by [`Encoder::sfence`] for throughput.
+    ///
+    /// Reference: Intel SDM Vol. 2A, "CLFLUSH—Flush Cache Line".
+    pub fn clflush(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("clflush", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes a `CLFLUSHOPT m8` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// 66 0F AE /7
+    /// ```
+    ///
+    /// Same as `CLFLUSH` but weakly ordered; the `66` prefix is what tells the
+    /// two apart.
+    ///
+    /// Reference: Intel SDM Vol. 2A, "CLFLUSHOPT—Flush Cache Line Optimized".
+    pub fn clflushopt(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("clflushopt", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes a `CLWB m8` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// 66 0F AE /6
+    /// ```
+    ///
+    /// Writes back the cache line containing `mem` without necessarily
+    /// invalidating it — the usual way to persist stores to persistent memory.
+    ///
+    /// Reference: Intel SDM Vol. 2A, "CLWB—Cache Line Write Back".
+    pub fn clwb(&mut self, mem: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("clwb", &[Operand::Mem(mem)])
+    }
+
+    /// Encodes an `SFENCE` instruction (`0F AE F8`).
+    ///
+    /// Orders all preceding stores, including non-temporal stores and
+    /// `CLFLUSHOPT`/`CLWB`, before any following store.
+    ///
+    /// Reference: Intel SDM Vol. 2B, "SFENCE—Store Fence".
+    pub fn sfence(&mut self) -> RaskResult<()> {
+        self.emit_instruction("sfence", &[])
+    }
+
+    /// Encodes an `LFENCE` instruction (`0F AE E8`).
+    ///
+    /// Reference: Intel SDM Vol. 2A, "LFENCE—Load Fence".
+    pub fn lfence(&mut self) -> RaskResult<()> {
+        self.emit_instruction("lfence", &[])
+    }
+
+    /// Encodes an `MFENCE` instruction (`0F AE F0`).
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MFENCE—Memory Fence".
+    pub fn mfence(&mut self) -> RaskResult<()> {
+        self.emit_instruction("mfence", &[])
+    }
+
+    /// Encodes a `MOVNTI m32, r32` or `MOVNTI m64, r64` non-temporal store.
+    ///
+    /// ### Encoding form
+    /// ```text
+    ///         0F C3 /r    (Operand::Reg32 source)
+    /// REX.W + 0F C3 /r    (Operand::Reg source)
+    /// ```
+    ///
+    /// | Instruction                   | Bytes (hex)       |
+    /// |-------------------------------|-------------------|
+    /// | `movnti [rdi], eax`           | 0F C3 07          |
+    /// | `movnti [rdi + rcx*8], r10`   | 4C 0F C3 14 CF    |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOVNTI—Store Doubleword Using Non-Temporal Hint".
+    ///
+    /// Returns an error if `src` is not a 32- or 64-bit general-purpose register.
+    pub fn movnti(&mut self, dst: MemOperand, src: Operand) -> RaskResult<()> {
+        self.emit_instruction("movnti", &[Operand::Mem(dst), src])
+    }
+
+    /// Encodes a `MOVNTDQ m128, xmm` non-temporal store.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// 66 0F E7 /r
+    /// ```
+    ///
+    /// The `66` here is a *mandatory prefix* selecting the instruction, not an
+    /// operand-size override, so it is emitted before any REX prefix.
+    ///
+    /// | Instruction                        | Bytes (hex)          |
+    /// |------------------------------------|----------------------|
+    /// | `movntdq [rdi], xmm0`              | 66 0F E7 07          |
+    /// | `movntdq [r8 + rdx*2 + 16], xmm12` | 66 45 0F E7 64 50 10 |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOVNTDQ—Store Packed Integers Using Non-Temporal Hint".
+    pub fn movntdq(&mut self, dst: MemOperand, src: XmmReg) -> RaskResult<()> {
+        self.emit_instruction("movntdq", &[Operand::Mem(dst), Operand::Xmm(src)])
+    }
+
+    /// Encodes a `MOVNTPS m128, xmm` non-temporal store (`0F 2B /r`).
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOVNTPS—Store Packed Single Precision
+    /// Floating-Point Values Using Non-Temporal Hint".
+    pub fn movntps(&mut self, dst: MemOperand, src: XmmReg) -> RaskResult<()> {
+        self.emit_instruction("movntps", &[Operand::Mem(dst), Operand::Xmm(src)])
+    }
+
+    /// Encodes a `MOVNTPD m128, xmm` non-temporal store (`66 0F 2B /r`).
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOVNTPD—Store Packed Double Precision
+    /// Floating-Point Values Using Non-Temporal Hint".
+    pub fn movntpd(&mut self, dst: MemOperand, src: XmmReg) -> RaskResult<()> {
+        self.emit_instruction("movntpd", &[Operand::Mem(dst), Operand::Xmm(src)])
+    }
+
+    /// Encodes a `MOVNTDQA xmm, m128` non-temporal load (SSE4.1).
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// 66 0F 38 2A /r
+    /// ```
+    ///
+    /// Only has a non-temporal effect on write-combining memory; on ordinary
+    /// write-back memory it behaves like an aligned load.
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOVNTDQA—Load Double Quadword Non-Temporal
+    /// Aligned Hint".
+    pub fn movntdqa(&mut self, dst: XmmReg, src: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("movntdqa", &[Operand::Xmm(dst), Operand::Mem(src)])
+    }
+
+    /// Encodes a `VMOVNTDQ m128, xmm` or `VMOVNTDQ m256, ymm` non-temporal store.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// VEX.128.66.0F.WIG E7 /r    (Operand::Xmm source)
+    /// VEX.256.66.0F.WIG E7 /r    (Operand::Ymm source)
+    /// ```
+    ///
+    /// The VEX prefix folds the `66` mandatory prefix (`pp`), the `0F` escape
+    /// (`mmmmm`) and the REX bits into two or three bytes, and its `L` bit
+    /// selects the vector length.
+    ///
+    /// | Instruction                  | Bytes (hex)        |
+    /// |------------------------------|--------------------|
+    /// | `vmovntdq [rdi], xmm1`       | C5 F9 E7 0F        |
+    /// | `vmovntdq [rdi + 32], ymm15` | C5 7D E7 7F 20     |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOVNTDQ—Store Packed Integers Using Non-Temporal Hint".
+    ///
+    /// Returns an error if `src` is not an XMM or YMM register.
+    pub fn vmovntdq(&mut self, dst: MemOperand, src: Operand) -> RaskResult<()> {
+        self.vex_store("VMOVNTDQ", 1, 0xE7, dst, src)
+    }
+
+    /// Encodes a `VMOVNTPS m128/m256, xmm/ymm` non-temporal store
+    /// (`VEX.128/256.0F.WIG 2B /r`). See [`Encoder::vmovntdq`].
+    pub fn vmovntps(&mut self, dst: MemOperand, src: Operand) -> RaskResult<()> {
+        self.vex_store("VMOVNTPS", 0, 0x2B, dst, src)
+    }
+
+    /// Encodes a `VMOVNTPD m128/m256, xmm/ymm` non-temporal store
+    /// (`VEX.128/256.66.0F.WIG 2B /r`). See [`Encoder::vmovntdq`].
+    pub fn vmovntpd(&mut self, dst: MemOperand, src: Operand) -> RaskResult<()> {
+        self.vex_store("VMOVNTPD", 1, 0x2B, dst, src)
+    }
+
+    /// Encodes a `VMOVNTDQA xmm, m128` or `VMOVNTDQA ymm, m256` non-temporal load.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// VEX.128.66.0F38.WIG 2A /r    (Operand::Xmm destination)
+    /// VEX.256.66.0F38.WIG 2A /r    (Operand::Ymm destination, AVX2)
+    /// ```
+    ///
+    /// The `0F 38` map cannot be expressed by the two-byte VEX form, so this
+    /// always uses the three-byte `C4` prefix.
+    ///
+    /// Reference: Intel SDM Vol. 2B, "MOVNTDQA—Load Double Quadword Non-Temporal
+    /// Aligned Hint".
+    ///
+    /// Returns an error if `dst` is not an XMM or YMM register.
+    pub fn vmovntdqa(&mut self, dst: Operand, src: MemOperand) -> RaskResult<()> {
+        self.instruction("vmovntdqa", &[dst, Operand::Mem(src)], |enc| {
+            let (l, reg) = match dst {
+                Operand::Xmm(r) => (false, r.id()),
+                Operand::Ymm(r) => (true, r.id()),
+                _ => return Err(invalid("destination must be an XMM or YMM register")),
+            };
+            let vex = Vex {
+                l,
+                pp: 1,
+                map: 2,
+                w: false,
+            };
+            enc.emit_vex_rm(vex, 0x2A, reg, 0, Rm::Mem(&src))
+        })
+    }
+
+    /// Shared body of the `VMOVNT*` stores: picks VEX.L from the register width.
+    fn vex_store(
+        &mut self,
+        mnemonic: &str,
+        pp: u8,
+        opcode: u8,
+        dst: MemOperand,
+        src: Operand,
+    ) -> RaskResult<()> {
+        self.instruction(mnemonic, &[Operand::Mem(dst), src], |enc| {
+            let (l, reg) = match src {
+                Operand::Xmm(r) => (false, r.id()),
+                Operand::Ymm(r) => (true, r.id()),
+                _ => return Err(invalid("source must be an XMM or YMM register")),
+            };
+            let vex = Vex {
+                l,
+                pp,
+                map: 1,
+                w: false,
+            };
+            enc.emit_vex_rm(vex, opcode, reg, 0, Rm::Mem(&dst))
+        })
+    }
+}
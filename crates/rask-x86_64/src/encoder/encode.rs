@@ -0,0 +1,271 @@
+//! Encoding of [`Instruction`] values.
+//!
+//! Mnemonics defined in the instruction table go straight to
+//! [`Encoder::emit_instruction`]; the VEX, EVEX and APX families are
+//! dispatched to their typed methods after checking the operand kinds.
+//!
+//! [`Encoder::encoded_len`] and [`Encoder::encode_to_array`] preview an
+//! encoding without writing to the encoder's sink, and [`Encoder::explain`]
+//! breaks it down field by field.
+
+use super::{Encoder, invalid};
+use crate::{
+    decoder::Decoder,
+    explain::Explanation,
+    instruction::{Instruction, LOCKABLE, MAX_INSTRUCTION_LEN, Mnemonic, Prefix},
+    operand::{MemOperand, Operand},
+    registers::{Reg64, TmmReg, XmmReg},
+    sink::{CodeSink, CountingSink, SliceSink},
+    table,
+};
+use rask_common::{RaskError, RaskResult};
+
+fn mismatch() -> RaskError {
+    invalid("no form of the instruction takes these operands")
+}
+
+fn reg64(op: Operand) -> RaskResult<Reg64> {
+    match op {
+        Operand::Reg(r) => Ok(r),
+        _ => Err(mismatch()),
+    }
+}
+
+fn xmm(op: Operand) -> RaskResult<XmmReg> {
+    match op {
+        Operand::Xmm(r) => Ok(r),
+        _ => Err(mismatch()),
+    }
+}
+
+fn tmm(op: Operand) -> RaskResult<TmmReg> {
+    match op {
+        Operand::Tmm(r) => Ok(r),
+        _ => Err(mismatch()),
+    }
+}
+
+fn mem(op: Operand) -> RaskResult<MemOperand> {
+    match op {
+        Operand::Mem(m) => Ok(m),
+        _ => Err(mismatch()),
+    }
+}
+
+/// Returns an `imm8` operand as its byte, accepting signed and unsigned
+/// values like the table's `imm8` kind.
+fn imm8(op: Operand) -> RaskResult<u8> {
+    match op {
+        Operand::Imm(v) if (-128..=255).contains(&v) => Ok(v as u8),
+        Operand::Imm(v) => Err(RaskError::ImmediateOutOfRange {
+            mnemonic: String::new(),
+            value: v,
+            bits: 8,
+        }),
+        _ => Err(mismatch()),
+    }
+}
+
+impl<S: CodeSink> Encoder<S> {
+    /// Encodes `insn`.
+    ///
+    /// This reaches every instruction the encoder supports, with the same
+    /// encodings as the typed methods: `Instruction::with2(Mnemonic::Mov,
+    /// dst, src)` emits exactly what [`Encoder::mov`] does. A three-operand
+    /// ALU instruction is the APX new-data-destination form
+    /// ([`Encoder::add_ndd`] and friends).
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::instruction::{Instruction, Mnemonic, Prefix};
+    /// use rask_x86_64::operand::{MemOperand, MemSize, Operand};
+    /// use rask_x86_64::registers::Reg64::RDI;
+    ///
+    /// // lock inc qword ptr [rdi]
+    /// let counter = MemOperand::new(RDI, 0).with_size(MemSize::Qword);
+    /// let insn = Instruction::with1(Mnemonic::Inc, Operand::Mem(counter)).with_prefix(Prefix::Lock);
+    ///
+    /// let mut enc = Encoder::new();
+    /// enc.encode(&insn)?;
+    /// assert_eq!(enc.bytes(), &[0xF0, 0x48, 0xFF, 0x07]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// Returns an error if the operands do not fit the mnemonic, or if the
+    /// `LOCK` prefix is used on anything but a memory destination of an
+    /// instruction that allows it. Nothing is emitted then.
+    pub fn encode(&mut self, insn: &Instruction) -> RaskResult<()> {
+        let mnemonic = insn.mnemonic();
+        let ops = insn.operands();
+        self.instruction(mnemonic.as_str(), ops, |enc| {
+            if let Some(prefix) = insn.prefix() {
+                match prefix {
+                    Prefix::Lock => {
+                        let lockable = LOCKABLE.contains(&mnemonic) && ops.len() <= 2;
+                        if !lockable || !matches!(ops.first(), Some(Operand::Mem(_))) {
+                            return Err(invalid(
+                                "LOCK requires a read-modify-write memory destination",
+                            ));
+                        }
+                    }
+                }
+                enc.emit(prefix.byte());
+            }
+            enc.encode_operands(mnemonic, ops)
+        })
+    }
+
+    /// Returns the number of bytes [`Encoder::encode`] would emit for
+    /// `insn` in the current mode and with the current features, without
+    /// emitting anything.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::instruction::{Instruction, Mnemonic};
+    /// use rask_x86_64::operand::Operand;
+    /// use rask_x86_64::registers::Reg64::RAX;
+    ///
+    /// let enc = Encoder::new();
+    /// let insn = Instruction::with2(Mnemonic::Add, Operand::Reg(RAX), Operand::Imm(1));
+    /// assert_eq!(enc.encoded_len(&insn)?, 4);
+    /// assert!(enc.bytes().is_empty());
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// Returns the error [`Encoder::encode`] would return.
+    pub fn encoded_len(&self, insn: &Instruction) -> RaskResult<usize> {
+        let mut probe = self.probe(CountingSink::new());
+        probe.encode(insn)?;
+        Ok(probe.position())
+    }
+
+    /// Encodes `insn` into a stack array and returns it with the number of
+    /// bytes used, leaving the encoder's sink untouched. This suits patching
+    /// an instruction into code that has already been emitted. A branch to
+    /// a label that is not bound yet gets a zero displacement.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::instruction::{Instruction, Mnemonic};
+    /// use rask_x86_64::operand::Operand;
+    /// use rask_x86_64::registers::Reg64::{RAX, RBX};
+    ///
+    /// let enc = Encoder::new();
+    /// let insn = Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Reg(RBX));
+    /// let (bytes, len) = enc.encode_to_array(&insn)?;
+    /// assert_eq!(&bytes[..len], &[0x48, 0x89, 0xD8]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// Returns the error [`Encoder::encode`] would return.
+    pub fn encode_to_array(
+        &self,
+        insn: &Instruction,
+    ) -> RaskResult<([u8; MAX_INSTRUCTION_LEN], usize)> {
+        let mut bytes = [0; MAX_INSTRUCTION_LEN];
+        let mut probe = self.probe(SliceSink::new(&mut bytes));
+        probe.encode(insn)?;
+        let len = probe.position();
+        Ok((bytes, len))
+    }
+
+    /// Encodes `insn` as [`Encoder::encode_to_array`] does and explains the
+    /// encoding: its prefixes, REX bits, opcode, ModR/M, SIB, displacement
+    /// and immediate, and its SDM opcode form. See [`crate::explain`].
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::instruction::{Instruction, Mnemonic};
+    /// use rask_x86_64::operand::Operand;
+    /// use rask_x86_64::registers::Reg64::{RAX, R9};
+    ///
+    /// let enc = Encoder::new();
+    /// let add = Instruction::with2(Mnemonic::Add, Operand::Reg(R9), Operand::Reg(RAX));
+    /// let explanation = enc.explain(&add)?;
+    /// assert_eq!(explanation.form(), "REX.W + 01 /r");
+    /// assert!(explanation.rex().unwrap().b);
+    /// assert_eq!(explanation.opcode(), &[0x01]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// Returns the error [`Encoder::encode`] would return.
+    pub fn explain(&self, insn: &Instruction) -> RaskResult<Explanation> {
+        let (bytes, len) = self.encode_to_array(insn)?;
+        let mut decoder = Decoder::with_mode(&bytes[..len], self.mode);
+        decoder.set_address(self.offset() as u64);
+        Ok(Explanation::new(decoder.decode()?))
+    }
+
+    /// Returns an encoder for `sink` with this encoder's mode, features and
+    /// labels, positioned where the next instruction would go, so that
+    /// branches get the same displacements.
+    pub(super) fn probe<T: CodeSink>(&self, sink: T) -> Encoder<T> {
+        let mut probe = Encoder::with_sink(sink);
+        probe.set_features(self.features);
+        probe.set_mode(self.mode);
+        probe.labels = self.labels.clone();
+        probe.origin = self.offset();
+        probe
+    }
+
+    /// Dispatches `mnemonic` with `ops` to the encoder method for it.
+    fn encode_operands(&mut self, mnemonic: Mnemonic, ops: &[Operand]) -> RaskResult<()> {
+        use Mnemonic::*;
+
+        if let (Some(cond), &[Operand::Label(target)]) = (mnemonic.condition(), ops) {
+            return self.jcc(cond, target);
+        }
+        match (mnemonic, ops) {
+            (Add, &[dst, a, b]) => self.add_ndd(reg64(dst)?, a, b),
+            (Or, &[dst, a, b]) => self.or_ndd(reg64(dst)?, a, b),
+            (Adc, &[dst, a, b]) => self.adc_ndd(reg64(dst)?, a, b),
+            (Sbb, &[dst, a, b]) => self.sbb_ndd(reg64(dst)?, a, b),
+            (And, &[dst, a, b]) => self.and_ndd(reg64(dst)?, a, b),
+            (Sub, &[dst, a, b]) => self.sub_ndd(reg64(dst)?, a, b),
+            (Xor, &[dst, a, b]) => self.xor_ndd(reg64(dst)?, a, b),
+            (Jmp, &[target]) => self.jmp(target),
+            (Call, &[target]) => self.call(target),
+            _ if table::forms(mnemonic.as_str()).is_some() => {
+                self.emit_instruction(mnemonic.as_str(), ops)
+            }
+
+            (Push2, &[a, b]) => self.push2(reg64(a)?, reg64(b)?),
+            (Push2p, &[a, b]) => self.push2p(reg64(a)?, reg64(b)?),
+            (Pop2, &[a, b]) => self.pop2(reg64(a)?, reg64(b)?),
+            (Pop2p, &[a, b]) => self.pop2p(reg64(a)?, reg64(b)?),
+
+            (Vmovntdq, &[dst, src]) => self.vmovntdq(mem(dst)?, src),
+            (Vmovntps, &[dst, src]) => self.vmovntps(mem(dst)?, src),
+            (Vmovntpd, &[dst, src]) => self.vmovntpd(mem(dst)?, src),
+            (Vmovntdqa, &[dst, src]) => self.vmovntdqa(dst, mem(src)?),
+
+            (Vaesenc, &[dst, a, b]) => self.vaesenc(dst, a, b),
+            (Vaesenclast, &[dst, a, b]) => self.vaesenclast(dst, a, b),
+            (Vaesdec, &[dst, a, b]) => self.vaesdec(dst, a, b),
+            (Vaesdeclast, &[dst, a, b]) => self.vaesdeclast(dst, a, b),
+            (Vaesimc, &[dst, src]) => self.vaesimc(xmm(dst)?, src),
+            (Vaeskeygenassist, &[dst, src, imm]) => {
+                self.vaeskeygenassist(xmm(dst)?, src, imm8(imm)?)
+            }
+            (Vpclmulqdq, &[dst, a, b, imm]) => self.vpclmulqdq(dst, a, b, imm8(imm)?),
+            (Vgf2p8affineinvqb, &[dst, a, b, imm]) => self.vgf2p8affineinvqb(dst, a, b, imm8(imm)?),
+            (Vgf2p8affineqb, &[dst, a, b, imm]) => self.vgf2p8affineqb(dst, a, b, imm8(imm)?),
+            (Vgf2p8mulb, &[dst, a, b]) => self.vgf2p8mulb(dst, a, b),
+
+            (Ldtilecfg, &[m]) => self.ldtilecfg(mem(m)?),
+            (Sttilecfg, &[m]) => self.sttilecfg(mem(m)?),
+            (Tileloadd, &[dst, src]) => self.tileloadd(tmm(dst)?, mem(src)?),
+            (Tileloaddt1, &[dst, src]) => self.tileloaddt1(tmm(dst)?, mem(src)?),
+            (Tilestored, &[dst, src]) => self.tilestored(mem(dst)?, tmm(src)?),
+            (Tilezero, &[dst]) => self.tilezero(tmm(dst)?),
+            (Tilerelease, &[]) => self.tilerelease(),
+            (Tdpbssd, &[dst, a, b]) => self.tdpbssd(tmm(dst)?, tmm(a)?, tmm(b)?),
+            (Tdpbsud, &[dst, a, b]) => self.tdpbsud(tmm(dst)?, tmm(a)?, tmm(b)?),
+            (Tdpbusd, &[dst, a, b]) => self.tdpbusd(tmm(dst)?, tmm(a)?, tmm(b)?),
+            (Tdpbuud, &[dst, a, b]) => self.tdpbuud(tmm(dst)?, tmm(a)?, tmm(b)?),
+            (Tdpbf16ps, &[dst, a, b]) => self.tdpbf16ps(tmm(dst)?, tmm(a)?, tmm(b)?),
+
+            _ => Err(mismatch()),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! The generic encoder driven by the instruction table in [`crate::table`].
+//!
+//! Form selection, operand-size prefixes, REX/REX2, ModR/M and immediates
+//! are all derived from an [`InstrDef`]; the typed methods on [`Encoder`]
+//! for table-defined instructions are thin wrappers around
+//! [`Encoder::emit_instruction`].
+
+use super::{Encoder, Rm, invalid};
+use crate::{
+    operand::Operand,
+    registers::Reg8,
+    sink::CodeSink,
+    table::{self, InstrDef, ModRm, Modes},
+};
+use rask_common::{RaskError, RaskResult};
+
+/// Returns the `r/m` encoding of a register or memory operand.
+fn rm_of(op: &Operand) -> Rm<'_> {
+    match op {
+        Operand::Reg8(r) => Rm::Reg8(*r),
+        Operand::Mem(m) => Rm::Mem(m),
+        other => Rm::Reg(reg_of(other).0),
+    }
+}
+
+/// Returns the ID of a register operand, and the register itself if it is
+/// a byte register.
+fn reg_of(op: &Operand) -> (u8, Option<Reg8>) {
+    match op {
+        Operand::Reg8(r) => (r.id(), Some(*r)),
+        Operand::Reg16(r) => (r.id(), None),
+        Operand::Reg32(r) => (r.id(), None),
+        Operand::Reg(r) => (r.id(), None),
+        Operand::Xmm(r) => (r.id(), None),
+        other => unreachable!("{other:?} is not a register operand"),
+    }
+}
+
+impl<S: CodeSink> Encoder<S> {
+    /// Encodes `mnemonic` with `operands` using the first matching form in
+    /// the instruction table.
+    ///
+    /// This reaches every form the table defines, including those without
+    /// a dedicated method, such as the ALU instructions on memory and
+    /// immediates:
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    /// use rask_x86_64::operand::{MemOperand, MemSize, Operand};
+    /// use rask_x86_64::registers::Reg64::{RAX, RDI};
+    ///
+    /// let mut enc = Encoder::new();
+    /// enc.emit_instruction("add", &[Operand::Reg(RAX), Operand::Imm(1)])?;
+    /// let counter = MemOperand::new(RDI, 8).with_size(MemSize::Dword);
+    /// enc.emit_instruction("cmp", &[Operand::Mem(counter), Operand::Imm(100)])?;
+    /// assert_eq!(enc.bytes(), &[0x48, 0x83, 0xC0, 0x01, 0x83, 0x7F, 0x08, 0x64]);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// A memory operand needs an explicit size only when the other operands
+    /// leave its width open (`add [rdi], 1`, but not `add [rdi], eax`).
+    ///
+    /// Returns an error if the mnemonic is unknown, no form matches the
+    /// operands, an immediate does not fit, or the matching form is not
+    /// available in the current mode. Nothing is emitted then.
+    pub fn emit_instruction(&mut self, mnemonic: &str, operands: &[Operand]) -> RaskResult<()> {
+        self.instruction(mnemonic, operands, |enc| {
+            let def = enc.select_form(mnemonic, operands)?;
+            enc.emit_def(def, operands)
+        })
+    }
+
+    /// Picks the form of `mnemonic` to encode `operands` with.
+    fn select_form(&self, mnemonic: &str, operands: &[Operand]) -> RaskResult<&'static InstrDef> {
+        let Some(forms) = table::forms(mnemonic) else {
+            return Err(RaskError::Other(format!(
+                "unknown instruction {mnemonic:?}"
+            )));
+        };
+        let name = mnemonic.to_ascii_uppercase();
+        let matching = || forms.iter().filter(|f| f.matches(operands));
+        let mut usable = matching().filter(|f| f.modes.allows(self.mode));
+
+        let Some(def) = usable.next() else {
+            return Err(match matching().next().map(|f| f.modes) {
+                Some(Modes::Legacy) => self.forbid_long_mode(&name).unwrap_err(),
+                Some(_) => self.require_long_mode(&name).unwrap_err(),
+                None => reject(forms, operands),
+            });
+        };
+
+        // An unsized memory operand is ambiguous when another usable form
+        // would read it with a different width.
+        for (i, op) in operands.iter().enumerate() {
+            if let Operand::Mem(m) = op
+                && m.size.is_none()
+                && usable.clone().any(|f| f.operands[i] != def.operands[i])
+            {
+                return Err(invalid(
+                    "memory operand needs an explicit size (MemOperand::with_size)",
+                ));
+            }
+        }
+        Ok(def)
+    }
+
+    /// Emits `def` with `operands`, which must match it.
+    pub(super) fn emit_def(&mut self, def: &InstrDef, operands: &[Operand]) -> RaskResult<()> {
+        let (osize, w) = match def.size.bits() {
+            Some(bits) => self.operand_size(bits)?,
+            None => (None, false),
+        };
+        let mut prefixes = [0; 2];
+        let mut len = 0;
+        for p in [osize, def.prefix].into_iter().flatten() {
+            prefixes[len] = p;
+            len += 1;
+        }
+        let prefixes = &prefixes[..len];
+
+        let find = |pred: fn(table::OperandKind) -> bool| {
+            def.operands
+                .iter()
+                .zip(operands)
+                .find(|(kind, _)| pred(**kind))
+                .map(|(kind, op)| (*kind, op))
+        };
+        let reg = find(table::OperandKind::is_reg);
+        let rm = find(table::OperandKind::is_rm);
+        let imm = find(|kind| kind.imm_bits().is_some());
+
+        match (def.modrm, reg, rm) {
+            (ModRm::None, ..) => {
+                self.emit_bytes(prefixes);
+                if w {
+                    self.emit(0x48);
+                }
+                self.emit_bytes(def.opcode);
+            }
+            (ModRm::PlusR, Some((_, op)), _) => {
+                let (id, reg8) = reg_of(op);
+                self.emit_plus_r(prefixes, w, def.opcode, id, reg8)?;
+            }
+            (ModRm::R, Some((_, reg)), Some((_, rm))) => {
+                let (id, reg8) = reg_of(reg);
+                self.emit_rm_reg8(prefixes, w, def.opcode, id, reg8, rm_of(rm))?;
+            }
+            // A `/digit` form's only register operand is the `r/m` one.
+            (ModRm::Digit(digit), _, Some((_, op)))
+            | (ModRm::Digit(digit), Some((_, op)), None) => {
+                self.emit_rm(prefixes, w, def.opcode, digit, rm_of(op))?;
+            }
+            _ => unreachable!("malformed instruction definition {def:?}"),
+        }
+
+        if let Some((kind, &Operand::Imm(value))) = imm {
+            let bytes = kind.imm_bits().unwrap_or_default() as usize / 8;
+            self.emit_bytes(&value.to_le_bytes()[..bytes]);
+        }
+        Ok(())
+    }
+}
+
+/// Explains why no form of an instruction matches `operands`: an immediate
+/// that is out of range for every form that would otherwise fit, or an
+/// invalid operand combination. [`Encoder::instruction`] adds the mnemonic.
+fn reject(forms: &[InstrDef], operands: &[Operand]) -> RaskError {
+    let imm_bits = forms
+        .iter()
+        .filter(|f| {
+            f.operands.len() == operands.len()
+                && f.operands.iter().zip(operands).all(|(kind, op)| {
+                    kind.matches(op) || (kind.imm_bits().is_some() && matches!(op, Operand::Imm(_)))
+                })
+        })
+        .filter_map(|f| f.operands.iter().filter_map(|kind| kind.imm_bits()).max())
+        .max();
+    let imm = operands.iter().find_map(|op| match op {
+        Operand::Imm(v) => Some(*v),
+        _ => None,
+    });
+    if let (Some(bits), Some(value)) = (imm_bits, imm) {
+        return RaskError::ImmediateOutOfRange {
+            mnemonic: String::new(),
+            value,
+            bits,
+        };
+    }
+    invalid("no form of the instruction takes these operands")
+}
@@ -0,0 +1,85 @@
+//! Instructions that only exist outside 64-bit mode.
+//!
+//! 64-bit mode reassigned several one-byte opcodes: `60`/`61` (PUSHA/POPA)
+//! are invalid, and `C4`/`C5` (LES/LDS) became the VEX escapes. These
+//! instructions are still needed by 16-bit boot code and 32-bit targets;
+//! see [`Mode`](crate::mode::Mode).
+
+use super::Encoder;
+use crate::{
+    operand::{MemOperand, Operand},
+    sink::CodeSink,
+};
+use rask_common::RaskResult;
+
+impl<S: CodeSink> Encoder<S> {
+    /// Encodes a `PUSHA` instruction, pushing the eight 16-bit GPRs.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// [66] 60
+    /// ```
+    ///
+    /// The `66` prefix is emitted in 32-bit mode, where the bare opcode is
+    /// `PUSHAD`.
+    ///
+    /// | Instruction         | Bytes (hex) |
+    /// |---------------------|-------------|
+    /// | `pusha` (16-bit)    | 60          |
+    /// | `pusha` (32-bit)    | 66 60       |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "PUSHA/PUSHAD—Push All General-Purpose Registers".
+    ///
+    /// Returns an error in 64-bit mode.
+    pub fn pusha(&mut self) -> RaskResult<()> {
+        self.emit_instruction("pusha", &[])
+    }
+
+    /// Encodes a `PUSHAD` instruction (`[66] 60`), pushing the eight 32-bit
+    /// GPRs. See [`Encoder::pusha`].
+    pub fn pushad(&mut self) -> RaskResult<()> {
+        self.emit_instruction("pushad", &[])
+    }
+
+    /// Encodes a `POPA` instruction (`[66] 61`), the inverse of
+    /// [`Encoder::pusha`].
+    ///
+    /// Reference: Intel SDM Vol. 2B, "POPA/POPAD—Pop All General-Purpose Registers".
+    pub fn popa(&mut self) -> RaskResult<()> {
+        self.emit_instruction("popa", &[])
+    }
+
+    /// Encodes a `POPAD` instruction (`[66] 61`), the inverse of
+    /// [`Encoder::pushad`].
+    pub fn popad(&mut self) -> RaskResult<()> {
+        self.emit_instruction("popad", &[])
+    }
+
+    /// Encodes an `LES r16/r32, m16:16/m16:32` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// [66] C4 /r   (mod != 11)
+    /// ```
+    ///
+    /// Loads a far pointer: the offset into `dst` and the following word into
+    /// ES. In 64-bit mode `C4` is the three-byte VEX escape.
+    ///
+    /// | Instruction                | Bytes (hex) |
+    /// |----------------------------|-------------|
+    /// | `les di, [bx]` (16-bit)    | C4 3F       |
+    /// | `les eax, [ecx]` (32-bit)  | C4 01       |
+    ///
+    /// Reference: Intel SDM Vol. 2A, "LDS/LES/LFS/LGS/LSS—Load Far Pointer".
+    ///
+    /// Returns an error in 64-bit mode or if `dst` is not a 16- or 32-bit register.
+    pub fn les(&mut self, dst: Operand, src: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("les", &[dst, Operand::Mem(src)])
+    }
+
+    /// Encodes an `LDS r16/r32, m16:16/m16:32` instruction (`[66] C5 /r`),
+    /// loading the segment part into DS. See [`Encoder::les`].
+    pub fn lds(&mut self, dst: Operand, src: MemOperand) -> RaskResult<()> {
+        self.emit_instruction("lds", &[dst, Operand::Mem(src)])
+    }
+}
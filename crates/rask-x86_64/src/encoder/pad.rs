@@ -0,0 +1,126 @@
+//! Padding: multi-byte NOPs and alignment.
+//!
+//! Branch targets such as loop heads and function entries decode fastest
+//! when they start on a 16-, 32- or 64-byte boundary. The gap in front of
+//! them is filled either with NOPs, when execution may fall through it, or
+//! with a fill byte such as `int3` (`CC`) when it is never executed.
+
+use super::Encoder;
+use crate::{instruction::MAX_INSTRUCTION_LEN, mode::Mode, sink::CodeSink};
+use rask_common::{RaskError, RaskResult, align_to, is_power_of_two};
+
+/// The longest single NOP.
+const MAX_NOP_LEN: usize = MAX_INSTRUCTION_LEN;
+
+/// The recommended NOP of each length from 1 to 9 bytes (index 0 is unused).
+///
+/// Reference: Intel SDM Vol. 2B, "NOP—No Operation", Table 4-12.
+const NOPS: [&[u8]; 10] = [
+    &[],
+    &[0x90],
+    &[0x66, 0x90],
+    &[0x0F, 0x1F, 0x00],
+    &[0x0F, 0x1F, 0x40, 0x00],
+    &[0x0F, 0x1F, 0x44, 0x00, 0x00],
+    &[0x66, 0x0F, 0x1F, 0x44, 0x00, 0x00],
+    &[0x0F, 0x1F, 0x80, 0x00, 0x00, 0x00, 0x00],
+    &[0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+    &[0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+];
+
+impl<S: CodeSink> Encoder<S> {
+    /// Emits exactly `len` bytes of NOPs, using as few instructions as
+    /// possible.
+    ///
+    /// ### Encoding forms
+    /// ```text
+    /// 90                          1 byte
+    /// 66 90                       2 bytes
+    /// 0F 1F /0                    3–8 bytes (NOP r/m32 with growing ModR/M, SIB, disp)
+    /// 66 ... 66 0F 1F 84 00 ...   9–15 bytes (8-byte form plus 1–7 `66` prefixes)
+    /// ```
+    ///
+    /// Runs longer than 15 bytes are split into 15-byte NOPs followed by one
+    /// shorter NOP. `nop_n(0)` emits nothing. In [`Mode::Real16`] the forms
+    /// of 4 bytes and more are one byte shorter and get a `67` prefix.
+    ///
+    /// | Call        | Bytes (hex)                      |
+    /// |-------------|----------------------------------|
+    /// | `nop_n(1)`  | 90                               |
+    /// | `nop_n(5)`  | 0F 1F 44 00 00                   |
+    /// | `nop_n(11)` | 66 66 66 0F 1F 84 00 00 00 00 00 |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "NOP—No Operation".
+    pub fn nop_n(&mut self, len: usize) -> RaskResult<()> {
+        self.instruction("nop", &[], |enc| {
+            let mut left = len;
+            while left > 0 {
+                let n = left.min(MAX_NOP_LEN);
+                left -= n;
+                let mut body = n;
+                if enc.mode == Mode::Real16 && n >= 4 {
+                    // The table's ModR/M bytes assume 32-bit addressing (SIB,
+                    // disp8/disp32); with 16-bit addressing they would decode
+                    // to a shorter instruction.
+                    enc.emit(0x67);
+                    body -= 1;
+                }
+                if body < NOPS.len() {
+                    enc.emit_bytes(NOPS[body]);
+                } else {
+                    // Extra operand-size prefixes on the 8-byte form.
+                    for _ in 8..body {
+                        enc.emit(0x66);
+                    }
+                    enc.emit_bytes(NOPS[8]);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Pads the code with NOPs until its length is a multiple of `align`.
+    ///
+    /// Alignment is relative to the start of the sink, so the code must be
+    /// placed at an address that is itself `align`-aligned.
+    ///
+    /// ```
+    /// use rask_x86_64::encoder::Encoder;
+    ///
+    /// let mut enc = Encoder::new();
+    /// enc.ret()?;
+    /// enc.align(16)?;
+    /// assert_eq!(enc.bytes().len(), 16);
+    /// # Ok::<(), rask_x86_64::RaskError>(())
+    /// ```
+    ///
+    /// Returns an error if `align` is not a power of two.
+    pub fn align(&mut self, align: usize) -> RaskResult<()> {
+        let pad = self.padding_to(align)?;
+        self.nop_n(pad)
+    }
+
+    /// Pads the code with copies of `fill` until its length is a multiple
+    /// of `align`. Use `0xCC` (`int3`) for gaps that are never executed, so
+    /// that a stray jump into them traps.
+    ///
+    /// Returns an error if `align` is not a power of two.
+    pub fn align_with(&mut self, align: usize, fill: u8) -> RaskResult<()> {
+        let pad = self.padding_to(align)?;
+        self.instruction("align", &[], |enc| {
+            enc.pending.resize(pad, fill);
+            Ok(())
+        })
+    }
+
+    /// Number of bytes needed to bring the code length up to `align`.
+    fn padding_to(&self, align: usize) -> RaskResult<usize> {
+        if !is_power_of_two(align) {
+            return Err(RaskError::Other(format!(
+                "alignment must be a power of two, got {align}"
+            )));
+        }
+        let len = self.position();
+        Ok(align_to(len, align) - len)
+    }
+}
@@ -0,0 +1,170 @@
+//! A panicking adapter over [`Encoder`], for call sites that build code
+//! from operands they know to be valid.
+
+use super::Encoder;
+use crate::{
+    instruction::{Condition, Instruction},
+    operand::{Label, MemOperand, Operand},
+    registers::{Reg64, TmmReg, XmmReg},
+    sink::CodeSink,
+};
+
+/// Forwards to the [`Encoder`] methods of the same name and panics with the
+/// [`RaskError`](rask_common::RaskError) message where they would return one.
+///
+/// Generated code is usually fixed at compile time, so an encoding error is
+/// a bug in the generator rather than something to recover from. Obtain one
+/// with [`Encoder::panicking`]:
+///
+/// ```
+/// use rask_x86_64::encoder::Encoder;
+/// use rask_x86_64::registers::Reg64::{RAX, RBX};
+///
+/// let mut enc = Encoder::new();
+/// let mut asm = enc.panicking();
+/// asm.add(RAX, RBX);
+/// asm.ret();
+/// assert_eq!(enc.bytes(), &[0x48, 0x01, 0xD8, 0xC3]);
+/// ```
+pub struct Panicking<'a, S: CodeSink = Vec<u8>> {
+    encoder: &'a mut Encoder<S>,
+}
+
+impl<S: CodeSink> Encoder<S> {
+    /// Returns a [`Panicking`] adapter that panics on encoding errors instead
+    /// of returning them.
+    pub fn panicking(&mut self) -> Panicking<'_, S> {
+        Panicking { encoder: self }
+    }
+}
+
+impl<S: CodeSink> Panicking<'_, S> {
+    /// Calls [`Encoder::create_label`].
+    pub fn create_label(&mut self) -> Label {
+        self.encoder.create_label()
+    }
+}
+
+impl Panicking<'_> {
+    /// Returns the bytes emitted so far.
+    pub fn bytes(&self) -> &[u8] {
+        self.encoder.bytes()
+    }
+}
+
+macro_rules! panicking {
+    ($(fn $name:ident($($arg:ident: $ty:ty),*);)*) => {
+        impl<S: CodeSink> Panicking<'_, S> {
+            $(
+                #[doc = concat!("Calls [`Encoder::", stringify!($name), "`], panicking on error.")]
+                #[track_caller]
+                pub fn $name(&mut self, $($arg: $ty),*) {
+                    if let Err(err) = self.encoder.$name($($arg),*) {
+                        panic!("{err}");
+                    }
+                }
+            )*
+        }
+    };
+}
+
+#[rustfmt::skip]
+panicking! {
+        fn add(dst: Reg64, src: Reg64);
+        fn sub(dst: Reg64, src: Reg64);
+        fn inc(dst: Operand);
+        fn dec(dst: Operand);
+        fn mov(dst: Operand, src: Operand);
+        fn ret();
+        fn bind_label(label: Label);
+        fn jmp(target: Operand);
+        fn jcc(cond: Condition, target: Label);
+        fn call(target: Operand);
+        fn emit_all(bytes: &[u8]);
+        fn ldtilecfg(mem: MemOperand);
+        fn sttilecfg(mem: MemOperand);
+        fn tileloadd(dst: TmmReg, src: MemOperand);
+        fn tileloaddt1(dst: TmmReg, src: MemOperand);
+        fn tilestored(dst: MemOperand, src: TmmReg);
+        fn tilezero(dst: TmmReg);
+        fn tilerelease();
+        fn tdpbssd(dst: TmmReg, src1: TmmReg, src2: TmmReg);
+        fn tdpbsud(dst: TmmReg, src1: TmmReg, src2: TmmReg);
+        fn tdpbusd(dst: TmmReg, src1: TmmReg, src2: TmmReg);
+        fn tdpbuud(dst: TmmReg, src1: TmmReg, src2: TmmReg);
+        fn tdpbf16ps(dst: TmmReg, src1: TmmReg, src2: TmmReg);
+        fn push2(first: Reg64, second: Reg64);
+        fn push2p(first: Reg64, second: Reg64);
+        fn pop2(first: Reg64, second: Reg64);
+        fn pop2p(first: Reg64, second: Reg64);
+        fn add_ndd(dst: Reg64, src1: Operand, src2: Operand);
+        fn or_ndd(dst: Reg64, src1: Operand, src2: Operand);
+        fn adc_ndd(dst: Reg64, src1: Operand, src2: Operand);
+        fn sbb_ndd(dst: Reg64, src1: Operand, src2: Operand);
+        fn and_ndd(dst: Reg64, src1: Operand, src2: Operand);
+        fn sub_ndd(dst: Reg64, src1: Operand, src2: Operand);
+        fn xor_ndd(dst: Reg64, src1: Operand, src2: Operand);
+        fn prefetcht0(mem: MemOperand);
+        fn prefetcht1(mem: MemOperand);
+        fn prefetcht2(mem: MemOperand);
+        fn prefetchnta(mem: MemOperand);
+        fn prefetchw(mem: MemOperand);
+        fn clflush(mem: MemOperand);
+        fn clflushopt(mem: MemOperand);
+        fn clwb(mem: MemOperand);
+        fn sfence();
+        fn lfence();
+        fn mfence();
+        fn movnti(dst: MemOperand, src: Operand);
+        fn movntdq(dst: MemOperand, src: XmmReg);
+        fn movntps(dst: MemOperand, src: XmmReg);
+        fn movntpd(dst: MemOperand, src: XmmReg);
+        fn movntdqa(dst: XmmReg, src: MemOperand);
+        fn vmovntdq(dst: MemOperand, src: Operand);
+        fn vmovntps(dst: MemOperand, src: Operand);
+        fn vmovntpd(dst: MemOperand, src: Operand);
+        fn vmovntdqa(dst: Operand, src: MemOperand);
+        fn aesenc(dst: XmmReg, src: Operand);
+        fn aesenclast(dst: XmmReg, src: Operand);
+        fn aesdec(dst: XmmReg, src: Operand);
+        fn aesdeclast(dst: XmmReg, src: Operand);
+        fn aesimc(dst: XmmReg, src: Operand);
+        fn aeskeygenassist(dst: XmmReg, src: Operand, imm: u8);
+        fn vaesenc(dst: Operand, src1: Operand, src2: Operand);
+        fn vaesenclast(dst: Operand, src1: Operand, src2: Operand);
+        fn vaesdec(dst: Operand, src1: Operand, src2: Operand);
+        fn vaesdeclast(dst: Operand, src1: Operand, src2: Operand);
+        fn vaesimc(dst: XmmReg, src: Operand);
+        fn vaeskeygenassist(dst: XmmReg, src: Operand, imm: u8);
+        fn sha1rnds4(dst: XmmReg, src: Operand, imm: u8);
+        fn sha1nexte(dst: XmmReg, src: Operand);
+        fn sha1msg1(dst: XmmReg, src: Operand);
+        fn sha1msg2(dst: XmmReg, src: Operand);
+        fn sha256rnds2(dst: XmmReg, src: Operand);
+        fn sha256msg1(dst: XmmReg, src: Operand);
+        fn sha256msg2(dst: XmmReg, src: Operand);
+        fn pclmulqdq(dst: XmmReg, src: Operand, imm: u8);
+        fn vpclmulqdq(dst: Operand, src1: Operand, src2: Operand, imm: u8);
+        fn crc32(dst: Operand, src: Operand);
+        fn gf2p8affineinvqb(dst: XmmReg, src: Operand, imm: u8);
+        fn gf2p8affineqb(dst: XmmReg, src: Operand, imm: u8);
+        fn gf2p8mulb(dst: XmmReg, src: Operand);
+        fn vgf2p8affineinvqb(dst: Operand, src1: Operand, src2: Operand, imm: u8);
+        fn vgf2p8affineqb(dst: Operand, src1: Operand, src2: Operand, imm: u8);
+        fn vgf2p8mulb(dst: Operand, src1: Operand, src2: Operand);
+        fn encode(insn: &Instruction);
+        fn emit_instruction(mnemonic: &str, operands: &[Operand]);
+        fn pusha();
+        fn pushad();
+        fn popa();
+        fn popad();
+        fn les(dst: Operand, src: MemOperand);
+        fn lds(dst: Operand, src: MemOperand);
+        fn nop_n(len: usize);
+        fn align(align: usize);
+        fn align_with(align: usize, fill: u8);
+        fn rdfsbase(dst: Operand);
+        fn rdgsbase(dst: Operand);
+        fn wrfsbase(src: Operand);
+        fn wrgsbase(src: Operand);
+}
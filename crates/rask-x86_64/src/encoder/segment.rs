@@ -0,0 +1,58 @@
+//! FS/GS segment-base instructions (FSGSBASE).
+//!
+//! In 64-bit mode the FS and GS bases are the only segment state that still
+//! affects addressing. Runtimes point them at thread-local data and read it
+//! back through `fs:[..]`/`gs:[..]` operands (see
+//! [`MemOperand::with_segment`](crate::operand::MemOperand::with_segment)).
+//! The instructions here read and write the bases directly, without a
+//! system call; the OS must have enabled them (`CR4.FSGSBASE`).
+
+use super::Encoder;
+use crate::{operand::Operand, sink::CodeSink};
+use rask_common::RaskResult;
+
+impl<S: CodeSink> Encoder<S> {
+    /// Encodes a `RDFSBASE r32/r64` instruction.
+    ///
+    /// ### Encoding form
+    /// ```text
+    /// F3 [REX.W] 0F AE /0   (mod = 11)
+    /// ```
+    ///
+    /// The four FSGSBASE instructions share `F3 0F AE` and are told apart by
+    /// the ModR/M `reg` digit: `/0` RDFSBASE, `/1` RDGSBASE, `/2` WRFSBASE and
+    /// `/3` WRGSBASE. The 32-bit forms read or write the low half of the base.
+    ///
+    /// | Instruction     | Bytes (hex)       |
+    /// |-----------------|-------------------|
+    /// | `rdfsbase rax`  | F3 48 0F AE C0    |
+    /// | `rdfsbase eax`  | F3 0F AE C0       |
+    ///
+    /// Reference: Intel SDM Vol. 2B, "RDFSBASE/RDGSBASE—Read FS/GS Segment Base".
+    ///
+    /// Returns an error outside 64-bit mode or if `dst` is not a 32- or 64-bit
+    /// general-purpose register.
+    pub fn rdfsbase(&mut self, dst: Operand) -> RaskResult<()> {
+        self.emit_instruction("rdfsbase", &[dst])
+    }
+
+    /// Encodes a `RDGSBASE r32/r64` instruction (`F3 [REX.W] 0F AE /1`).
+    /// See [`Encoder::rdfsbase`].
+    pub fn rdgsbase(&mut self, dst: Operand) -> RaskResult<()> {
+        self.emit_instruction("rdgsbase", &[dst])
+    }
+
+    /// Encodes a `WRFSBASE r32/r64` instruction (`F3 [REX.W] 0F AE /2`).
+    /// See [`Encoder::rdfsbase`].
+    ///
+    /// Reference: Intel SDM Vol. 2D, "WRFSBASE/WRGSBASE—Write FS/GS Segment Base".
+    pub fn wrfsbase(&mut self, src: Operand) -> RaskResult<()> {
+        self.emit_instruction("wrfsbase", &[src])
+    }
+
+    /// Encodes a `WRGSBASE r32/r64` instruction (`F3 [REX.W] 0F AE /3`).
+    /// See [`Encoder::rdfsbase`].
+    pub fn wrgsbase(&mut self, src: Operand) -> RaskResult<()> {
+        self.emit_instruction("wrgsbase", &[src])
+    }
+}
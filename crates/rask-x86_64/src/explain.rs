@@ -0,0 +1,683 @@
+//! Field-by-field explanations of instruction encodings.
+//!
+//! An [`Explanation`] splits the bytes of one instruction into the fields
+//! the Intel SDM describes — legacy prefixes, REX (or REX2, VEX, EVEX),
+//! opcode, ModR/M, SIB, displacement and immediate — decodes each field's
+//! bits, and names the SDM opcode form the instruction was encoded with.
+//! Its [`fmt::Display`] impl prints a listing with every field's bytes
+//! lined up under the full encoding:
+//!
+//! ```
+//! use rask_x86_64::encoder::Encoder;
+//! use rask_x86_64::instruction::{Instruction, Mnemonic};
+//! use rask_x86_64::operand::{MemOperand, Operand};
+//! use rask_x86_64::registers::Reg64::{RAX, RSP};
+//!
+//! let enc = Encoder::new();
+//! let mov = Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Mem(MemOperand::new(RSP, 8)));
+//! let explanation = enc.explain(&mov)?;
+//!
+//! assert_eq!(explanation.form(), "REX.W + 8B /r");
+//! assert_eq!(explanation.modrm().unwrap().md, 0b01);
+//! assert_eq!(explanation.to_string(), "\
+//! 48 8b 44 24 08  mov rax, qword ptr [rsp+0x8]  ; REX.W + 8B /r
+//! 48              REX      0100WRXB  W=1 R=0 X=0 B=0
+//!    8b           opcode   8B
+//!       44        ModR/M   mod=01 reg=000 rm=100
+//!          24     SIB      scale=00 index=100 base=100
+//!             08  disp8    0x8");
+//! # Ok::<(), rask_x86_64::RaskError>(())
+//! ```
+//!
+//! [`Encoder::explain`](crate::encoder::Encoder::explain) explains the
+//! instruction the encoder would emit next; [`Explanation::new`] explains
+//! any [`Decoded`] instruction, so a whole buffer can be listed with a
+//! [`Decoder`](crate::decoder::Decoder):
+//!
+//! ```
+//! use rask_x86_64::decoder::Decoder;
+//! use rask_x86_64::explain::Explanation;
+//!
+//! let code = [0x48, 0x83, 0xC0, 0x01, 0xC3]; // add rax, 1; ret
+//! for decoded in Decoder::new(&code) {
+//!     println!("{}\n", Explanation::new(decoded?));
+//! }
+//! # Ok::<(), rask_x86_64::RaskError>(())
+//! ```
+
+use crate::{
+    decoder::{Decoded, Encoding},
+    formatter::Formatter,
+    instruction::{Instruction, Mnemonic},
+    registers::SegReg,
+    table::{InstrDef, ModRm, OpSize, OperandKind},
+};
+use std::fmt::{self, Write};
+
+/// The `W`, `R`, `X` and `B` bits of a REX prefix, or the same bits of the
+/// REX2, VEX or EVEX prefix that replaces it. `R`, `X` and `B` extend the
+/// ModR/M `reg`, the SIB index and the base or `r/m` register to 16
+/// registers; they are given here as set, even where VEX and EVEX store
+/// them inverted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RexBits {
+    pub w: bool,
+    pub r: bool,
+    pub x: bool,
+    pub b: bool,
+}
+
+/// The fields of a VEX or EVEX prefix, beyond its [`RexBits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VexFields {
+    pub rex: RexBits,
+    /// Opcode map: 1 = `0F`, 2 = `0F 38`, 3 = `0F 3A`, 4 = APX map 4.
+    pub map: u8,
+    /// Implied legacy prefix: 0 = none, 1 = `66`, 2 = `F3`, 3 = `F2`.
+    pub pp: u8,
+    /// Vector length: 0 = 128, 1 = 256, 2 = 512 bits.
+    pub len: u8,
+    /// The extra register operand, un-inverted.
+    pub vvvv: u8,
+}
+
+/// The fields of a ModR/M byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModRmFields {
+    /// Addressing mode: `11` for a register, otherwise the displacement size.
+    pub md: u8,
+    /// A register, or an opcode extension for `/digit` forms.
+    pub reg: u8,
+    /// A register or the base of a memory operand; `100` selects a SIB byte.
+    pub rm: u8,
+}
+
+/// The fields of a SIB byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SibFields {
+    /// The scale as its 2-bit encoding (0 = ×1 ... 3 = ×8).
+    pub scale: u8,
+    /// The index register; `100` without `REX.X` means no index.
+    pub index: u8,
+    /// The base register; `101` with `mod = 00` means no base.
+    pub base: u8,
+}
+
+/// What one [`Field`] of an encoding holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldKind {
+    /// A legacy prefix byte: `LOCK`, `REP`/`REPNE` or a mandatory prefix,
+    /// an operand- or address-size override, or a segment override.
+    Prefix(u8),
+    /// A REX prefix. A REX prefix followed by a legacy prefix is ignored
+    /// and listed as a [`FieldKind::Prefix`].
+    Rex(RexBits),
+    /// An APX REX2 prefix, with the fourth bits of the register IDs and
+    /// `m0`, which selects the `0F` opcode map.
+    Rex2 {
+        rex: RexBits,
+        r4: bool,
+        x4: bool,
+        b4: bool,
+        m0: bool,
+    },
+    /// A VEX prefix (`C5` or `C4`).
+    Vex(VexFields),
+    /// An EVEX prefix (`62`).
+    Evex(VexFields),
+    /// The opcode bytes, including any `0F` escapes.
+    Opcode,
+    /// The ModR/M byte.
+    ModRm(ModRmFields),
+    /// The SIB byte.
+    Sib(SibFields),
+    /// The displacement of a memory operand, as stored (EVEX `disp8` is
+    /// scaled by the operand size when used).
+    Displacement(i32),
+    /// An immediate, or the relative target of a direct branch, as its
+    /// little-endian unsigned value.
+    Immediate(u64),
+}
+
+/// A run of bytes in an encoding and what they hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Field {
+    /// Offset of the first byte in the instruction.
+    pub offset: usize,
+    /// Number of bytes.
+    pub len: usize,
+    pub kind: FieldKind,
+}
+
+/// One instruction's encoding, split into its fields; see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    decoded: Decoded,
+    fields: Vec<Field>,
+    form: String,
+}
+
+impl Explanation {
+    /// Explains a decoded instruction.
+    pub fn new(decoded: Decoded) -> Self {
+        let fields = fields(&decoded);
+        let form = form(&decoded, &fields);
+        Self {
+            decoded,
+            fields,
+            form,
+        }
+    }
+
+    /// Returns the decoded instruction, with its address and bytes.
+    #[inline]
+    pub fn decoded(&self) -> &Decoded {
+        &self.decoded
+    }
+
+    /// Returns the instruction.
+    #[inline]
+    pub fn instruction(&self) -> Instruction {
+        self.decoded.instruction()
+    }
+
+    /// Returns the encoded bytes.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        self.decoded.bytes()
+    }
+
+    /// Returns every field in the order of the bytes.
+    #[inline]
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the opcode form as the Intel SDM writes it, such as
+    /// `REX.W + 8B /r`, `B8+rd id` or `VEX.128.66.0F38.W0 DC /r`.
+    #[inline]
+    pub fn form(&self) -> &str {
+        &self.form
+    }
+
+    /// Returns the legacy prefixes; see [`Decoded::prefixes`].
+    #[inline]
+    pub fn prefixes(&self) -> &[u8] {
+        self.decoded.prefixes()
+    }
+
+    /// Returns the REX bits of the REX, REX2, VEX or EVEX prefix, if the
+    /// instruction has one.
+    pub fn rex(&self) -> Option<RexBits> {
+        self.fields.iter().find_map(|field| match field.kind {
+            FieldKind::Rex(rex) | FieldKind::Rex2 { rex, .. } => Some(rex),
+            FieldKind::Vex(vex) | FieldKind::Evex(vex) => Some(vex.rex),
+            _ => None,
+        })
+    }
+
+    /// Returns the opcode bytes.
+    pub fn opcode(&self) -> &[u8] {
+        self.field(|kind| matches!(kind, FieldKind::Opcode))
+            .map_or(&[], |field| self.slice(field))
+    }
+
+    /// Returns the ModR/M fields, if the instruction has a ModR/M byte.
+    pub fn modrm(&self) -> Option<ModRmFields> {
+        self.fields.iter().find_map(|field| match field.kind {
+            FieldKind::ModRm(modrm) => Some(modrm),
+            _ => None,
+        })
+    }
+
+    /// Returns the SIB fields, if the instruction has a SIB byte.
+    pub fn sib(&self) -> Option<SibFields> {
+        self.fields.iter().find_map(|field| match field.kind {
+            FieldKind::Sib(sib) => Some(sib),
+            _ => None,
+        })
+    }
+
+    /// Returns the displacement and its size in bytes.
+    pub fn displacement(&self) -> Option<(i32, usize)> {
+        self.fields.iter().find_map(|field| match field.kind {
+            FieldKind::Displacement(disp) => Some((disp, field.len)),
+            _ => None,
+        })
+    }
+
+    /// Returns the immediate (or branch displacement) and its size in bytes.
+    pub fn immediate(&self) -> Option<(u64, usize)> {
+        self.fields.iter().find_map(|field| match field.kind {
+            FieldKind::Immediate(imm) => Some((imm, field.len)),
+            _ => None,
+        })
+    }
+
+    fn field(&self, pred: impl Fn(&FieldKind) -> bool) -> Option<&Field> {
+        self.fields.iter().find(|field| pred(&field.kind))
+    }
+
+    fn slice(&self, field: &Field) -> &[u8] {
+        &self.bytes()[field.offset..field.offset + field.len]
+    }
+
+    /// Returns the name of `field` for the listing.
+    fn name(&self, field: &Field) -> String {
+        let bits = field.len * 8;
+        match field.kind {
+            FieldKind::Prefix(_) => "prefix".into(),
+            FieldKind::Rex(_) => "REX".into(),
+            FieldKind::Rex2 { .. } => "REX2".into(),
+            FieldKind::Vex(_) => "VEX".into(),
+            FieldKind::Evex(_) => "EVEX".into(),
+            FieldKind::Opcode => "opcode".into(),
+            FieldKind::ModRm(_) => "ModR/M".into(),
+            FieldKind::Sib(_) => "SIB".into(),
+            FieldKind::Displacement(_) => format!("disp{bits}"),
+            FieldKind::Immediate(_) if self.decoded.branch_target().is_some() => {
+                format!("rel{bits}")
+            }
+            FieldKind::Immediate(_) => format!("imm{bits}"),
+        }
+    }
+
+    /// Writes what `field` holds, for the listing.
+    fn detail(&self, field: &Field, f: &mut String) -> fmt::Result {
+        let bit = |set: bool| u8::from(set);
+        match field.kind {
+            FieldKind::Prefix(byte) => f.write_str(&prefix_meaning(byte, self.table_form())),
+            FieldKind::Rex(rex) => write!(
+                f,
+                "0100WRXB  W={} R={} X={} B={}",
+                bit(rex.w),
+                bit(rex.r),
+                bit(rex.x),
+                bit(rex.b)
+            ),
+            FieldKind::Rex2 {
+                rex,
+                r4,
+                x4,
+                b4,
+                m0,
+            } => write!(
+                f,
+                "M0={} R4={} X4={} B4={} W={} R3={} X3={} B3={}",
+                bit(m0),
+                bit(r4),
+                bit(x4),
+                bit(b4),
+                bit(rex.w),
+                bit(rex.r),
+                bit(rex.x),
+                bit(rex.b)
+            ),
+            FieldKind::Vex(vex) | FieldKind::Evex(vex) => write!(
+                f,
+                "R={} X={} B={} map={} W={} vvvv={:04b} L={} pp={}",
+                bit(vex.rex.r),
+                bit(vex.rex.x),
+                bit(vex.rex.b),
+                map_name(vex.map),
+                bit(vex.rex.w),
+                vex.vvvv & 0x0F,
+                vex.len,
+                pp_name(vex.pp)
+            ),
+            FieldKind::Opcode => {
+                let opcode = self.slice(field);
+                write!(f, "{}", hex(opcode))?;
+                if let Some(def) = self.table_form()
+                    && def.modrm == ModRm::PlusR
+                {
+                    write!(f, "  register {:03b}", opcode[opcode.len() - 1] & 0x07)?;
+                }
+                Ok(())
+            }
+            FieldKind::ModRm(m) => write!(f, "mod={:02b} reg={:03b} rm={:03b}", m.md, m.reg, m.rm),
+            FieldKind::Sib(s) => write!(
+                f,
+                "scale={:02b} index={:03b} base={:03b}",
+                s.scale, s.index, s.base
+            ),
+            FieldKind::Displacement(disp) => {
+                let sign = if disp < 0 { "-" } else { "" };
+                write!(f, "{sign}{:#x}", disp.unsigned_abs())?;
+                if field.len == 1 && self.decoded.encoding() == Encoding::Evex {
+                    f.write_str(" (disp8*N)")?;
+                }
+                Ok(())
+            }
+            FieldKind::Immediate(imm) => write!(f, "{imm:#x}"),
+        }
+    }
+
+    fn table_form(&self) -> Option<&'static InstrDef> {
+        self.decoded.layout().form
+    }
+}
+
+impl fmt::Display for Explanation {
+    /// Writes the listing: the bytes, the instruction and its SDM form,
+    /// then one line per field with its bytes under their position.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.bytes().len() * 3 - 1;
+        let text = Formatter::default()
+            .with_explicit_sizes(true)
+            .format(&self.instruction());
+        write!(
+            f,
+            "{:width$}  {text}  ; {}",
+            hex_lower(self.bytes()),
+            self.form
+        )?;
+        for field in &self.fields {
+            let mut line = " ".repeat(field.offset * 3);
+            line.push_str(&hex_lower(self.slice(field)));
+            let pad = width - line.len();
+            write!(line, "{:pad$}  {:<8} ", "", self.name(field))?;
+            self.detail(field, &mut line)?;
+            write!(f, "\n{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits `decoded` into its fields.
+fn fields(decoded: &Decoded) -> Vec<Field> {
+    let bytes = decoded.bytes();
+    let layout = decoded.layout();
+    let mut fields = Vec::new();
+    let mut push = |offset: usize, len: usize, kind| {
+        if len > 0 {
+            fields.push(Field { offset, len, kind });
+        }
+    };
+
+    for (i, &byte) in decoded.prefixes().iter().enumerate() {
+        push(i, 1, FieldKind::Prefix(byte));
+    }
+    let at = decoded.prefixes().len();
+    let prefix = bytes.get(at + 1..).unwrap_or(&[]);
+    let opcode = match decoded.encoding() {
+        Encoding::Legacy => match decoded.rex() {
+            Some(rex) => {
+                push(at, 1, FieldKind::Rex(rex_bits(rex)));
+                at + 1
+            }
+            None => at,
+        },
+        Encoding::Rex2 => {
+            let p = prefix[0];
+            let set = |n: u8| p & (1 << n) != 0;
+            let rex = rex_bits(p);
+            push(
+                at,
+                2,
+                FieldKind::Rex2 {
+                    rex,
+                    r4: set(6),
+                    x4: set(5),
+                    b4: set(4),
+                    m0: set(7),
+                },
+            );
+            at + 2
+        }
+        Encoding::Vex => {
+            let (len, vex) = vex_fields(bytes[at], prefix);
+            push(at, len, FieldKind::Vex(vex));
+            at + len
+        }
+        Encoding::Evex => {
+            let (p0, p1, p2) = (prefix[0], prefix[1], prefix[2]);
+            let vex = VexFields {
+                rex: RexBits {
+                    w: p1 & 0x80 != 0,
+                    r: p0 & 0x80 == 0,
+                    x: p0 & 0x40 == 0,
+                    b: p0 & 0x20 == 0,
+                },
+                map: p0 & 0x07,
+                pp: p1 & 0x03,
+                len: (p2 >> 5) & 0x03,
+                vvvv: ((!p1 >> 3) & 0x0F) | ((!p2 & 0x08) << 1),
+            };
+            push(at, 4, FieldKind::Evex(vex));
+            at + 4
+        }
+    };
+
+    let modrm = layout.modrm.map(usize::from);
+    let imm = layout.imm.map(usize::from);
+    let opcode_end = modrm.or(imm).unwrap_or(bytes.len());
+    push(opcode, opcode_end - opcode, FieldKind::Opcode);
+    if let Some(at) = modrm {
+        let byte = bytes[at];
+        let fields = ModRmFields {
+            md: byte >> 6,
+            reg: (byte >> 3) & 0x07,
+            rm: byte & 0x07,
+        };
+        push(at, 1, FieldKind::ModRm(fields));
+    }
+    if let Some(at) = layout.sib.map(usize::from) {
+        let byte = bytes[at];
+        let fields = SibFields {
+            scale: byte >> 6,
+            index: (byte >> 3) & 0x07,
+            base: byte & 0x07,
+        };
+        push(at, 1, FieldKind::Sib(fields));
+    }
+    if let Some((at, len)) = layout.disp {
+        let (at, len) = (usize::from(at), usize::from(len));
+        let raw = le(&bytes[at..at + len]);
+        // Sign-extend from the field's width.
+        let shift = 64 - 8 * len;
+        push(
+            at,
+            len,
+            FieldKind::Displacement(((raw << shift) as i64 >> shift) as i32),
+        );
+    }
+    if let Some(at) = imm {
+        push(at, bytes.len() - at, FieldKind::Immediate(le(&bytes[at..])));
+    }
+    fields
+}
+
+/// Returns the length and fields of the VEX prefix starting with `escape`
+/// (`C5` or `C4`), followed by `payload`.
+fn vex_fields(escape: u8, payload: &[u8]) -> (usize, VexFields) {
+    // The two-byte form implies X̄ = B̄ = 1, the 0F map and W0.
+    let (len, p0, p1) = match escape {
+        0xC5 => (2, (payload[0] & 0x80) | 0x61, payload[0] & 0x7F),
+        _ => (3, payload[0], payload[1]),
+    };
+    let vex = VexFields {
+        rex: RexBits {
+            w: p1 & 0x80 != 0,
+            r: p0 & 0x80 == 0,
+            x: p0 & 0x40 == 0,
+            b: p0 & 0x20 == 0,
+        },
+        map: p0 & 0x1F,
+        pp: p1 & 0x03,
+        len: (p1 >> 2) & 1,
+        vvvv: (!p1 >> 3) & 0x0F,
+    };
+    (len, vex)
+}
+
+/// Returns the `W`, `R`, `X` and `B` bits of a REX prefix or REX2 payload.
+fn rex_bits(byte: u8) -> RexBits {
+    RexBits {
+        w: byte & 0x08 != 0,
+        r: byte & 0x04 != 0,
+        x: byte & 0x02 != 0,
+        b: byte & 0x01 != 0,
+    }
+}
+
+/// Builds the SDM opcode form of `decoded`.
+fn form(decoded: &Decoded, fields: &[Field]) -> String {
+    let imm_len = fields.iter().find_map(|field| match field.kind {
+        FieldKind::Immediate(_) => Some(field.len),
+        _ => None,
+    });
+    let opcode = fields
+        .iter()
+        .find(|field| field.kind == FieldKind::Opcode)
+        .map_or(&[][..], |field| {
+            &decoded.bytes()[field.offset..field.offset + field.len]
+        });
+
+    if let Some(def) = decoded.layout().form {
+        return table_form(def, decoded.encoding() == Encoding::Rex2);
+    }
+    let vex = fields.iter().find_map(|field| match field.kind {
+        FieldKind::Vex(vex) => Some(("VEX", vex)),
+        FieldKind::Evex(vex) => Some(("EVEX", vex)),
+        _ => None,
+    });
+    let Some((name, vex)) = vex else {
+        // A direct branch: the opcode and the size of its code offset.
+        let size = match imm_len {
+            Some(1) => "cb",
+            Some(2) => "cw",
+            _ => "cd",
+        };
+        return format!("{} {size}", hex(opcode));
+    };
+
+    let bits = 128 << vex.len;
+    let mut form = format!(
+        "{name}.{bits}.{}.{}.W{} {}",
+        pp_name(vex.pp),
+        map_name(vex.map),
+        u8::from(vex.rex.w),
+        hex(opcode)
+    );
+    let mnemonic = decoded.instruction().mnemonic();
+    let modrm = fields.iter().find_map(|field| match field.kind {
+        FieldKind::ModRm(modrm) => Some(modrm),
+        _ => None,
+    });
+    if let Some(modrm) = modrm {
+        let digit = matches!(mnemonic, Mnemonic::Ldtilecfg | Mnemonic::Sttilecfg)
+            || (vex.map == 4 && matches!(opcode, [0x80..=0x83 | 0xFE | 0xFF]));
+        match mnemonic {
+            Mnemonic::Tilerelease => form.push_str(" C0"),
+            _ if digit => write!(form, " /{}", modrm.reg).unwrap(),
+            _ => form.push_str(" /r"),
+        }
+    }
+    if let Some(len) = imm_len {
+        form.push_str(imm_code(len * 8));
+    }
+    form
+}
+
+/// Builds the SDM opcode form of an instruction-table form.
+fn table_form(def: &InstrDef, rex2: bool) -> String {
+    let mut parts = Vec::new();
+    if let Some(prefix) = def.prefix {
+        parts.push(format!("{prefix:02X}"));
+    }
+    match (def.size, rex2) {
+        (OpSize::O64, false) if def.prefix.is_some() => parts.push("REX.W".into()),
+        (OpSize::O64, false) => parts.push("REX.W +".into()),
+        (OpSize::O64, true) => parts.push("REX2.W +".into()),
+        (_, true) => parts.push("REX2 +".into()),
+        _ => {}
+    }
+    let (last, head) = def.opcode.split_last().expect("opcode must not be empty");
+    parts.extend(head.iter().map(|b| format!("{b:02X}")));
+    match def.modrm {
+        ModRm::PlusR => {
+            let size = match def.operands.first() {
+                Some(OperandKind::Reg8) => "rb",
+                Some(OperandKind::Reg16) => "rw",
+                _ => "rd",
+            };
+            parts.push(format!("{last:02X}+{size}"));
+        }
+        _ => parts.push(format!("{last:02X}")),
+    }
+    match def.modrm {
+        ModRm::R => parts.push("/r".into()),
+        ModRm::Digit(digit) => parts.push(format!("/{digit}")),
+        ModRm::None | ModRm::PlusR => {}
+    }
+    let mut form = parts.join(" ");
+    for bits in def.operands.iter().filter_map(|kind| kind.imm_bits()) {
+        form.push_str(imm_code(bits as usize));
+    }
+    form
+}
+
+/// Returns the SDM suffix for an immediate of `bits` bits.
+fn imm_code(bits: usize) -> &'static str {
+    match bits {
+        8 => " ib",
+        16 => " iw",
+        32 => " id",
+        _ => " io",
+    }
+}
+
+/// Says what a legacy prefix byte does; `def` tells a mandatory prefix
+/// from a modifier.
+fn prefix_meaning(byte: u8, def: Option<&InstrDef>) -> String {
+    let mandatory = def.is_some_and(|def| def.prefix == Some(byte));
+    let meaning = match byte {
+        _ if mandatory => "mandatory prefix".to_string(),
+        0xF0 => "LOCK".into(),
+        0xF2 => "REPNE".into(),
+        0xF3 => "REP".into(),
+        0x66 => "operand-size override".into(),
+        0x67 => "address-size override".into(),
+        0x40..=0x4F => "REX, ignored: a legacy prefix follows".into(),
+        _ => match SegReg::ALL.iter().find(|seg| seg.prefix() == byte) {
+            Some(seg) => format!("segment override ({seg})"),
+            None => String::new(),
+        },
+    };
+    format!("{byte:02X} {meaning}")
+}
+
+fn map_name(map: u8) -> String {
+    match map {
+        1 => "0F".into(),
+        2 => "0F38".into(),
+        3 => "0F3A".into(),
+        _ => format!("MAP{map}"),
+    }
+}
+
+fn pp_name(pp: u8) -> &'static str {
+    ["NP", "66", "F3", "F2"][usize::from(pp & 0x03)]
+}
+
+/// Returns the little-endian value of `bytes`.
+fn le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+/// Formats `bytes` as uppercase hex pairs, as the SDM writes opcodes.
+fn hex(bytes: &[u8]) -> String {
+    let pairs: Vec<String> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+    pairs.join(" ")
+}
+
+/// Formats `bytes` as lowercase hex pairs, as a listing shows them.
+fn hex_lower(bytes: &[u8]) -> String {
+    let pairs: Vec<String> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    pairs.join(" ")
+}
@@ -0,0 +1,52 @@
+//! Optional instruction-set extensions the encoder can target.
+//!
+//! Most instructions are always available: whether the CPU running the code
+//! supports them is the caller's concern. Features listed here are different
+//! in that enabling them changes what the encoder itself accepts, so they
+//! are opt-in per [`Encoder`](crate::encoder::Encoder).
+
+/// An opt-in x86-64 instruction-set extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CpuFeature {
+    /// Intel Advanced Performance Extensions: the GPRs R16–R31, the REX2
+    /// prefix, new-data-destination (NDD) forms and `PUSH2`/`POP2`.
+    Apx,
+}
+
+impl CpuFeature {
+    #[inline(always)]
+    fn bit(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+/// A set of enabled [`CpuFeature`]s. The default set is empty.
+///
+/// ```
+/// use rask_x86_64::features::{CpuFeature, CpuFeatures};
+///
+/// let features = CpuFeatures::new().with(CpuFeature::Apx);
+/// assert!(features.contains(CpuFeature::Apx));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CpuFeatures(u32);
+
+impl CpuFeatures {
+    /// Creates an empty feature set.
+    #[inline]
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Returns this set with `feature` enabled.
+    #[inline]
+    pub fn with(self, feature: CpuFeature) -> Self {
+        Self(self.0 | feature.bit())
+    }
+
+    /// Returns true if `feature` is enabled.
+    #[inline]
+    pub fn contains(self, feature: CpuFeature) -> bool {
+        self.0 & feature.bit() != 0
+    }
+}
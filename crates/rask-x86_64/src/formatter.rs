@@ -0,0 +1,492 @@
+//! Assembly-language text for instructions and operands.
+//!
+//! A [`Formatter`] writes [`Instruction`]s, [`Operand`]s, [`MemOperand`]s
+//! and registers in one of three [`Syntax`] flavors. [`Formatter::display`]
+//! wraps a value in something that implements [`fmt::Display`], so it can
+//! go straight into `format!`, `println!` or a log line:
+//!
+//! ```
+//! use rask_x86_64::formatter::{Formatter, Syntax};
+//! use rask_x86_64::instruction::{Instruction, Mnemonic};
+//! use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+//! use rask_x86_64::registers::Reg64::{RAX, RBX, RSI};
+//!
+//! let mem = MemOperand::new(RBX, 8).with_index(RSI, Scale::S2).with_size(MemSize::Qword);
+//! let mov = Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Mem(mem));
+//!
+//! assert_eq!(mov.to_string(), "mov rax, [rbx+rsi*2+0x8]");
+//! let intel = Formatter::new(Syntax::Intel).with_explicit_sizes(true);
+//! assert_eq!(intel.format(&mov), "mov rax, qword ptr [rbx+rsi*2+0x8]");
+//! let nasm = Formatter::new(Syntax::Nasm).with_explicit_sizes(true);
+//! assert_eq!(nasm.format(&mov), "mov rax, qword [rbx+rsi*2+0x8]");
+//! let att = Formatter::new(Syntax::Att);
+//! assert_eq!(att.format(&mov), "mov 0x8(%rbx,%rsi,2), %rax");
+//! assert_eq!(format!("{}", att.display(&RAX)), "%rax");
+//! ```
+//!
+//! The `Display` impls of [`Instruction`], [`Operand`] and [`MemOperand`]
+//! use a default [`Formatter`], which writes Intel syntax.
+//!
+//! Sizes are written only where the other operands leave them open
+//! (`inc qword ptr [rax]`, `crc32 eax, byte ptr [rsi]`), unless
+//! [`Formatter::with_explicit_sizes`] asks for them on every memory operand
+//! that has a [`MemSize`]. In AT&T syntax the size is the `b`, `w`, `l` or
+//! `q` suffix of the mnemonic.
+//!
+//! Direct branch targets (the [`Operand::Imm`] of a `jmp`, `jcc` or
+//! `call`, as the [`Decoder`](crate::decoder::Decoder) produces them) are
+//! absolute addresses. [`Formatter::with_symbol_resolver`] can name them:
+//!
+//! ```
+//! use rask_x86_64::decoder::Decoder;
+//! use rask_x86_64::formatter::Formatter;
+//!
+//! // call 0x1000; ret
+//! let code = [0xE8, 0xFB, 0x0F, 0x00, 0x00, 0xC3];
+//! let fmt = Formatter::default()
+//!     .with_symbol_resolver(|addr| (addr == 0x1000).then(|| "memcpy".to_string()));
+//! let text: Vec<String> = Decoder::new(&code)
+//!     .map(|insn| fmt.format(&insn.unwrap().instruction()))
+//!     .collect();
+//! assert_eq!(text, ["call memcpy", "ret"]);
+//! ```
+
+use crate::{
+    instruction::{Instruction, Mnemonic},
+    operand::{AddrReg, MemOperand, MemSize, Operand},
+    registers::{Reg8, Reg16, Reg32, Reg64, SegReg, TmmReg, XmmReg, YmmReg, ZmmReg},
+};
+use std::fmt::{self, Write};
+
+/// An assembly-language flavor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Syntax {
+    /// Intel syntax as GNU tools write it: `mov rax, qword ptr fs:[rbx+0x8]`.
+    #[default]
+    Intel,
+    /// AT&T syntax, as GNU as reads it: `movq %fs:0x8(%rbx), %rax`.
+    Att,
+    /// NASM syntax: `mov rax, qword [fs:rbx+0x8]`.
+    Nasm,
+}
+
+/// How hexadecimal numbers are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HexStyle {
+    /// `0x1f`, as C and GNU as write it.
+    #[default]
+    Prefix,
+    /// `1fh`, as MASM and NASM accept it. A number that would start with a
+    /// letter gets a leading `0` (`0ffh`).
+    Suffix,
+}
+
+/// Writes instructions and operands as assembly-language text.
+///
+/// Build one with [`Formatter::new`] and the `with_*` options, then use
+/// [`Formatter::display`] or [`Formatter::format`]. The defaults are Intel
+/// syntax, `0x`-prefixed lowercase hex, lowercase mnemonics and sizes only
+/// where they are needed.
+pub struct Formatter<'a> {
+    syntax: Syntax,
+    hex_style: HexStyle,
+    uppercase_hex: bool,
+    uppercase_mnemonics: bool,
+    explicit_sizes: bool,
+    resolver: Option<Box<dyn Fn(u64) -> Option<String> + 'a>>,
+}
+
+impl Default for Formatter<'_> {
+    fn default() -> Self {
+        Self::new(Syntax::Intel)
+    }
+}
+
+impl fmt::Debug for Formatter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Formatter")
+            .field("syntax", &self.syntax)
+            .field("hex_style", &self.hex_style)
+            .field("uppercase_hex", &self.uppercase_hex)
+            .field("uppercase_mnemonics", &self.uppercase_mnemonics)
+            .field("explicit_sizes", &self.explicit_sizes)
+            .field("resolver", &self.resolver.is_some())
+            .finish()
+    }
+}
+
+impl<'a> Formatter<'a> {
+    /// Creates a formatter for `syntax` with the default options.
+    pub fn new(syntax: Syntax) -> Self {
+        Self {
+            syntax,
+            hex_style: HexStyle::Prefix,
+            uppercase_hex: false,
+            uppercase_mnemonics: false,
+            explicit_sizes: false,
+            resolver: None,
+        }
+    }
+
+    /// Sets how hexadecimal numbers are written.
+    pub fn with_hex_style(mut self, style: HexStyle) -> Self {
+        self.hex_style = style;
+        self
+    }
+
+    /// Writes the digits `a`-`f` of hexadecimal numbers in uppercase.
+    pub fn with_uppercase_hex(mut self, uppercase: bool) -> Self {
+        self.uppercase_hex = uppercase;
+        self
+    }
+
+    /// Writes mnemonics and the `lock` prefix in uppercase.
+    pub fn with_uppercase_mnemonics(mut self, uppercase: bool) -> Self {
+        self.uppercase_mnemonics = uppercase;
+        self
+    }
+
+    /// Writes the size of every memory operand that has one (`qword ptr`,
+    /// `qword`, or the `q` suffix in AT&T syntax), not only where the other
+    /// operands leave it open.
+    pub fn with_explicit_sizes(mut self, explicit: bool) -> Self {
+        self.explicit_sizes = explicit;
+        self
+    }
+
+    /// Names direct branch targets: `resolve` gets each target address and
+    /// returns the symbol to write in its place, or `None` to write the
+    /// address.
+    pub fn with_symbol_resolver(mut self, resolve: impl Fn(u64) -> Option<String> + 'a) -> Self {
+        self.resolver = Some(Box::new(resolve));
+        self
+    }
+
+    /// Returns the syntax this formatter writes.
+    #[inline]
+    pub fn syntax(&self) -> Syntax {
+        self.syntax
+    }
+
+    /// Wraps `value` so that its [`fmt::Display`] impl writes it with this
+    /// formatter.
+    #[inline]
+    pub fn display<'f, T: Format + ?Sized>(&'f self, value: &'f T) -> Formatted<'f, T> {
+        Formatted {
+            formatter: self,
+            value,
+        }
+    }
+
+    /// Formats `value` into a new string.
+    pub fn format<T: Format + ?Sized>(&self, value: &T) -> String {
+        self.display(value).to_string()
+    }
+
+    /// Writes `insn`: the prefix, the mnemonic and the operands.
+    fn instruction(&self, insn: &Instruction, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if insn.prefix().is_some() {
+            self.mnemonic("lock", f)?;
+            f.write_char(' ')?;
+        }
+        let sized = self.explicit_sizes || needs_size(insn);
+        let branch = is_branch(insn.mnemonic());
+        self.mnemonic(insn.mnemonic().as_str(), f)?;
+        if self.syntax == Syntax::Att
+            && sized
+            && let Some(size) = insn.operands().iter().find_map(|op| match op {
+                Operand::Mem(m) => m.size,
+                _ => None,
+            })
+        {
+            let suffix = match size {
+                MemSize::Byte => "b",
+                MemSize::Word => "w",
+                MemSize::Dword => "l",
+                MemSize::Qword => "q",
+            };
+            self.mnemonic(suffix, f)?;
+        }
+
+        let mut operands = insn.operands().to_vec();
+        if self.syntax == Syntax::Att {
+            operands.reverse();
+        }
+        for (i, op) in operands.iter().enumerate() {
+            f.write_str(if i == 0 { " " } else { ", " })?;
+            self.operand(op, sized, branch, f)?;
+        }
+        Ok(())
+    }
+
+    fn mnemonic(&self, name: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.uppercase_mnemonics {
+            name.chars()
+                .try_for_each(|c| f.write_char(c.to_ascii_uppercase()))
+        } else {
+            f.write_str(name)
+        }
+    }
+
+    /// Writes `op`. `sized` says whether a memory operand gets its size
+    /// keyword, `branch` whether the operand is a branch target.
+    fn operand(
+        &self,
+        op: &Operand,
+        sized: bool,
+        branch: bool,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let att = self.syntax == Syntax::Att;
+        if att && branch && !matches!(op, Operand::Imm(_) | Operand::Label(_)) {
+            f.write_char('*')?;
+        }
+        match *op {
+            Operand::Reg(r) => self.register(r.name(), f),
+            Operand::Reg32(r) => self.register(r.name(), f),
+            Operand::Reg16(r) => self.register(r.name(), f),
+            Operand::Reg8(r) => self.register(r.name(), f),
+            Operand::Xmm(r) => self.register(r.name(), f),
+            Operand::Ymm(r) => self.register(r.name(), f),
+            Operand::Zmm(r) => self.register(r.name(), f),
+            Operand::Tmm(r) => self.register(r.name(), f),
+            Operand::Mem(ref m) => self.mem(m, sized, f),
+            Operand::Imm(target) if branch => {
+                let target = target as u64;
+                match self.resolver.as_ref().and_then(|resolve| resolve(target)) {
+                    Some(symbol) => f.write_str(&symbol),
+                    None => self.hex(target, f),
+                }
+            }
+            Operand::Imm(value) => {
+                if att {
+                    f.write_char('$')?;
+                }
+                self.signed(value, f)
+            }
+            Operand::Label(label) => write!(f, "{label}"),
+        }
+    }
+
+    fn register(&self, name: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.syntax == Syntax::Att {
+            f.write_char('%')?;
+        }
+        f.write_str(name)
+    }
+
+    /// Writes a memory operand, with its size keyword if `sized` (Intel
+    /// and NASM only; AT&T puts the size on the mnemonic).
+    fn mem(&self, m: &MemOperand, sized: bool, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.syntax != Syntax::Att
+            && sized
+            && let Some(size) = m.size
+        {
+            f.write_str(match size {
+                MemSize::Byte => "byte",
+                MemSize::Word => "word",
+                MemSize::Dword => "dword",
+                MemSize::Qword => "qword",
+            })?;
+            f.write_str(if self.syntax == Syntax::Intel {
+                " ptr "
+            } else {
+                " "
+            })?;
+        }
+        match self.syntax {
+            Syntax::Intel => {
+                if let Some(seg) = m.segment {
+                    write!(f, "{seg}:")?;
+                }
+                f.write_char('[')?;
+                self.address(m, f)?;
+                f.write_char(']')
+            }
+            Syntax::Nasm => {
+                f.write_char('[')?;
+                if let Some(seg) = m.segment {
+                    write!(f, "{seg}:")?;
+                }
+                self.address(m, f)?;
+                f.write_char(']')
+            }
+            Syntax::Att => {
+                if let Some(seg) = m.segment {
+                    write!(f, "%{seg}:")?;
+                }
+                if m.disp != 0 || (m.base.is_none() && m.index.is_none()) {
+                    self.signed(i64::from(m.disp), f)?;
+                }
+                if m.base.is_none() && m.index.is_none() {
+                    return Ok(());
+                }
+                f.write_char('(')?;
+                if let Some(base) = m.base {
+                    self.register(addr_name(base), f)?;
+                }
+                if let Some((index, scale)) = m.index {
+                    f.write_char(',')?;
+                    self.register(addr_name(index), f)?;
+                    write!(f, ",{}", scale.factor())?;
+                }
+                f.write_char(')')
+            }
+        }
+    }
+
+    /// Writes the inside of an Intel or NASM memory operand,
+    /// `base+index*scale+disp`.
+    fn address(&self, m: &MemOperand, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(base) = m.base {
+            f.write_str(addr_name(base))?;
+        }
+        if let Some((index, scale)) = m.index {
+            if m.base.is_some() {
+                f.write_char('+')?;
+            }
+            write!(f, "{}*{}", addr_name(index), scale.factor())?;
+        }
+        if m.base.is_none() && m.index.is_none() {
+            self.signed(i64::from(m.disp), f)
+        } else if m.disp < 0 {
+            f.write_char('-')?;
+            self.hex(u64::from(m.disp.unsigned_abs()), f)
+        } else if m.disp > 0 {
+            f.write_char('+')?;
+            self.hex(m.disp as u64, f)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn signed(&self, value: i64, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if value < 0 {
+            f.write_char('-')?;
+        }
+        self.hex(value.unsigned_abs(), f)
+    }
+
+    fn hex(&self, value: u64, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = if self.uppercase_hex {
+            format!("{value:X}")
+        } else {
+            format!("{value:x}")
+        };
+        match self.hex_style {
+            HexStyle::Prefix => write!(f, "0x{digits}"),
+            HexStyle::Suffix => {
+                let zero = if digits.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    "0"
+                } else {
+                    ""
+                };
+                let h = if self.uppercase_hex { 'H' } else { 'h' };
+                write!(f, "{zero}{digits}{h}")
+            }
+        }
+    }
+}
+
+/// Whether the size of a memory operand of `insn` has to be written: when
+/// no register operand gives it, and for `crc32`, whose source may be
+/// narrower than its destination.
+fn needs_size(insn: &Instruction) -> bool {
+    insn.mnemonic() == Mnemonic::Crc32
+        || !insn.operands().iter().any(|op| {
+            matches!(
+                op,
+                Operand::Reg(_) | Operand::Reg32(_) | Operand::Reg16(_) | Operand::Reg8(_)
+            )
+        })
+}
+
+fn is_branch(mnemonic: Mnemonic) -> bool {
+    matches!(mnemonic, Mnemonic::Jmp | Mnemonic::Call) || mnemonic.condition().is_some()
+}
+
+fn addr_name(reg: AddrReg) -> &'static str {
+    match reg {
+        AddrReg::R64(r) => r.name(),
+        AddrReg::R32(r) => r.name(),
+        AddrReg::R16(r) => r.name(),
+        AddrReg::Rip => "rip",
+    }
+}
+
+/// A value a [`Formatter`] can write.
+pub trait Format {
+    /// Writes `self` to `f` as `formatter` directs.
+    fn fmt_with(&self, formatter: &Formatter<'_>, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl Format for Instruction {
+    fn fmt_with(&self, formatter: &Formatter<'_>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.instruction(self, f)
+    }
+}
+
+impl Format for Operand {
+    /// Writes the operand on its own: a memory operand with its size, if
+    /// it has one, and an immediate as a value rather than a branch target.
+    fn fmt_with(&self, formatter: &Formatter<'_>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.operand(self, true, false, f)
+    }
+}
+
+impl Format for MemOperand {
+    /// Writes the operand with its size, if it has one.
+    fn fmt_with(&self, formatter: &Formatter<'_>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.mem(self, true, f)
+    }
+}
+
+macro_rules! format_registers {
+    ($($ty:ty),*) => {
+        $(
+            impl Format for $ty {
+                fn fmt_with(&self, formatter: &Formatter<'_>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    formatter.register(self.name(), f)
+                }
+            }
+        )*
+    };
+}
+
+format_registers!(
+    Reg64, Reg32, Reg16, Reg8, XmmReg, YmmReg, ZmmReg, TmmReg, SegReg
+);
+
+/// A value paired with the [`Formatter`] that displays it; see
+/// [`Formatter::display`].
+pub struct Formatted<'f, T: ?Sized> {
+    formatter: &'f Formatter<'f>,
+    value: &'f T,
+}
+
+impl<T: Format + ?Sized> fmt::Display for Formatted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt_with(self.formatter, f)
+    }
+}
+
+impl fmt::Display for Instruction {
+    /// Writes the instruction in Intel syntax; see [`Formatter`] for others.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Formatter::default().instruction(self, f)
+    }
+}
+
+impl fmt::Display for Operand {
+    /// Writes the operand in Intel syntax; see [`Formatter`] for others.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with(&Formatter::default(), f)
+    }
+}
+
+impl fmt::Display for MemOperand {
+    /// Writes the operand in Intel syntax; see [`Formatter`] for others.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with(&Formatter::default(), f)
+    }
+}
@@ -0,0 +1,401 @@
+//! Instructions as values.
+//!
+//! An [`Instruction`] describes one instruction — its [`Mnemonic`], up to
+//! [`MAX_OPERANDS`] [`Operand`]s and an optional [`Prefix`] — without
+//! encoding it. A code generator can collect instructions in a list,
+//! rewrite them (peephole optimisation, scheduling, register allocation)
+//! and emit them afterwards with
+//! [`Encoder::encode`](crate::encoder::Encoder::encode):
+//!
+//! ```
+//! use rask_x86_64::encoder::Encoder;
+//! use rask_x86_64::instruction::{Instruction, Mnemonic};
+//! use rask_x86_64::operand::Operand;
+//! use rask_x86_64::registers::Reg64::{RAX, RBX};
+//!
+//! let mut code = vec![
+//!     Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Reg(RBX)),
+//!     Instruction::with2(Mnemonic::Add, Operand::Reg(RAX), Operand::Imm(0)),
+//!     Instruction::new(Mnemonic::Ret),
+//! ];
+//!
+//! // Adding zero is a no-op (apart from the flags).
+//! code.retain(|insn| insn.mnemonic() != Mnemonic::Add || insn.operand(1) != Some(Operand::Imm(0)));
+//!
+//! let mut enc = Encoder::new();
+//! for insn in &code {
+//!     enc.encode(insn)?;
+//! }
+//! assert_eq!(enc.bytes(), &[0x48, 0x89, 0xD8, 0xC3]);
+//! # Ok::<(), rask_x86_64::RaskError>(())
+//! ```
+
+use crate::operand::Operand;
+use rask_common::{RaskError, RaskResult};
+use std::{fmt, str::FromStr};
+
+/// The most operands any instruction takes (`vpclmulqdq ymm1, ymm2, ymm3, imm8`).
+pub const MAX_OPERANDS: usize = 4;
+
+/// The longest encoding an x86 instruction may have; the processor rejects
+/// longer ones.
+pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+macro_rules! mnemonics {
+    ($($variant:ident => $name:literal,)*) => {
+        /// Identifies an instruction the encoder supports.
+        ///
+        /// The same mnemonic may have several encodings; the operands of an
+        /// [`Instruction`] select one. Three-operand `add`, `or`, `adc`,
+        /// `sbb`, `and`, `sub` and `xor` are the APX new-data-destination
+        /// forms.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Mnemonic {
+            $(
+                #[doc = concat!("`", $name, "`")]
+                $variant,
+            )*
+        }
+
+        impl Mnemonic {
+            /// Every mnemonic, in declaration order.
+            pub const ALL: &'static [Mnemonic] = &[$(Mnemonic::$variant),*];
+
+            /// Returns the lowercase assembler name, as used by the
+            /// instruction table.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Mnemonic::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+mnemonics! {
+    Add => "add", Or => "or", Adc => "adc", Sbb => "sbb",
+    And => "and", Sub => "sub", Xor => "xor", Cmp => "cmp",
+    Mov => "mov", Inc => "inc", Dec => "dec", Ret => "ret",
+    Nop => "nop", Int3 => "int3",
+    Jmp => "jmp", Call => "call",
+    Jo => "jo", Jno => "jno", Jb => "jb", Jae => "jae", Je => "je", Jne => "jne", Jbe => "jbe", Ja => "ja",
+    Js => "js", Jns => "jns", Jp => "jp", Jnp => "jnp", Jl => "jl", Jge => "jge", Jle => "jle", Jg => "jg",
+    Pusha => "pusha", Pushad => "pushad", Popa => "popa", Popad => "popad",
+    Les => "les", Lds => "lds",
+    Push2 => "push2", Push2p => "push2p", Pop2 => "pop2", Pop2p => "pop2p",
+    Rdfsbase => "rdfsbase", Rdgsbase => "rdgsbase", Wrfsbase => "wrfsbase", Wrgsbase => "wrgsbase",
+    Prefetcht0 => "prefetcht0", Prefetcht1 => "prefetcht1", Prefetcht2 => "prefetcht2",
+    Prefetchnta => "prefetchnta", Prefetchw => "prefetchw",
+    Clflush => "clflush", Clflushopt => "clflushopt", Clwb => "clwb",
+    Sfence => "sfence", Lfence => "lfence", Mfence => "mfence",
+    Movnti => "movnti", Movntdq => "movntdq", Movntps => "movntps", Movntpd => "movntpd",
+    Movntdqa => "movntdqa",
+    Vmovntdq => "vmovntdq", Vmovntps => "vmovntps", Vmovntpd => "vmovntpd", Vmovntdqa => "vmovntdqa",
+    Aesenc => "aesenc", Aesenclast => "aesenclast", Aesdec => "aesdec", Aesdeclast => "aesdeclast",
+    Aesimc => "aesimc", Aeskeygenassist => "aeskeygenassist",
+    Vaesenc => "vaesenc", Vaesenclast => "vaesenclast", Vaesdec => "vaesdec", Vaesdeclast => "vaesdeclast",
+    Vaesimc => "vaesimc", Vaeskeygenassist => "vaeskeygenassist",
+    Sha1rnds4 => "sha1rnds4", Sha1nexte => "sha1nexte", Sha1msg1 => "sha1msg1", Sha1msg2 => "sha1msg2",
+    Sha256rnds2 => "sha256rnds2", Sha256msg1 => "sha256msg1", Sha256msg2 => "sha256msg2",
+    Pclmulqdq => "pclmulqdq", Vpclmulqdq => "vpclmulqdq", Crc32 => "crc32",
+    Gf2p8affineinvqb => "gf2p8affineinvqb", Gf2p8affineqb => "gf2p8affineqb", Gf2p8mulb => "gf2p8mulb",
+    Vgf2p8affineinvqb => "vgf2p8affineinvqb", Vgf2p8affineqb => "vgf2p8affineqb",
+    Vgf2p8mulb => "vgf2p8mulb",
+    Ldtilecfg => "ldtilecfg", Sttilecfg => "sttilecfg",
+    Tileloadd => "tileloadd", Tileloaddt1 => "tileloaddt1", Tilestored => "tilestored",
+    Tilezero => "tilezero", Tilerelease => "tilerelease",
+    Tdpbssd => "tdpbssd", Tdpbsud => "tdpbsud", Tdpbusd => "tdpbusd", Tdpbuud => "tdpbuud",
+    Tdpbf16ps => "tdpbf16ps",
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Alternative names of the conditional jumps, as accepted by assemblers.
+#[rustfmt::skip]
+const ALIASES: &[(&str, Mnemonic)] = &[
+    ("jc", Mnemonic::Jb), ("jnae", Mnemonic::Jb), ("jnb", Mnemonic::Jae), ("jnc", Mnemonic::Jae),
+    ("jz", Mnemonic::Je), ("jnz", Mnemonic::Jne), ("jna", Mnemonic::Jbe), ("jnbe", Mnemonic::Ja),
+    ("jpe", Mnemonic::Jp), ("jpo", Mnemonic::Jnp), ("jnge", Mnemonic::Jl), ("jnl", Mnemonic::Jge),
+    ("jng", Mnemonic::Jle), ("jnle", Mnemonic::Jg),
+];
+
+/// The conditional jumps, indexed by condition code.
+#[rustfmt::skip]
+const JCC: [Mnemonic; 16] = [
+    Mnemonic::Jo, Mnemonic::Jno, Mnemonic::Jb, Mnemonic::Jae,
+    Mnemonic::Je, Mnemonic::Jne, Mnemonic::Jbe, Mnemonic::Ja,
+    Mnemonic::Js, Mnemonic::Jns, Mnemonic::Jp, Mnemonic::Jnp,
+    Mnemonic::Jl, Mnemonic::Jge, Mnemonic::Jle, Mnemonic::Jg,
+];
+
+impl Mnemonic {
+    /// Returns the conditional jump taken on `cond`.
+    #[inline]
+    pub fn jcc(cond: Condition) -> Mnemonic {
+        JCC[usize::from(cond.code())]
+    }
+
+    /// Returns the condition of a conditional jump, or `None` for any other
+    /// instruction.
+    pub fn condition(self) -> Option<Condition> {
+        let index = JCC.iter().position(|&m| m == self)?;
+        Some(Condition::ALL[index])
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = RaskError;
+
+    /// Parses an assembler name, ignoring case. The conditional jumps also
+    /// accept their aliases, such as `jz` for `je`.
+    fn from_str(s: &str) -> RaskResult<Self> {
+        Mnemonic::ALL
+            .iter()
+            .map(|&m| (m.as_str(), m))
+            .chain(ALIASES.iter().copied())
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, m)| m)
+            .ok_or_else(|| RaskError::Other(format!("unknown instruction {s:?}")))
+    }
+}
+
+/// A condition code: the `cc` of `Jcc`, evaluated on the flags.
+///
+/// The discriminant is the 4-bit code the opcode carries (`70+cc`,
+/// `0F 80+cc`). Variants are named after the Intel mnemonics; the unsigned
+/// comparisons are `B`/`A` (below/above), the signed ones `L`/`G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Condition {
+    /// Overflow (`OF = 1`).
+    O,
+    /// No overflow (`OF = 0`).
+    No,
+    /// Below, or carry (`CF = 1`).
+    B,
+    /// Above or equal, or no carry (`CF = 0`).
+    Ae,
+    /// Equal, or zero (`ZF = 1`).
+    E,
+    /// Not equal, or not zero (`ZF = 0`).
+    Ne,
+    /// Below or equal (`CF = 1 or ZF = 1`).
+    Be,
+    /// Above (`CF = 0 and ZF = 0`).
+    A,
+    /// Sign (`SF = 1`).
+    S,
+    /// No sign (`SF = 0`).
+    Ns,
+    /// Parity even (`PF = 1`).
+    P,
+    /// Parity odd (`PF = 0`).
+    Np,
+    /// Less (`SF != OF`).
+    L,
+    /// Greater or equal (`SF = OF`).
+    Ge,
+    /// Less or equal (`ZF = 1 or SF != OF`).
+    Le,
+    /// Greater (`ZF = 0 and SF = OF`).
+    G,
+}
+
+impl Condition {
+    /// Every condition, in code order.
+    #[rustfmt::skip]
+    pub const ALL: [Condition; 16] = [
+        Condition::O, Condition::No, Condition::B, Condition::Ae,
+        Condition::E, Condition::Ne, Condition::Be, Condition::A,
+        Condition::S, Condition::Ns, Condition::P, Condition::Np,
+        Condition::L, Condition::Ge, Condition::Le, Condition::G,
+    ];
+
+    /// Returns the 4-bit condition code.
+    #[inline]
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the opposite condition, which differs in the lowest bit.
+    #[inline]
+    pub fn negate(self) -> Self {
+        Condition::ALL[usize::from(self.code() ^ 1)]
+    }
+}
+
+/// A legacy prefix that changes how an instruction executes. Segment
+/// overrides belong to the memory operand; see
+/// [`MemOperand::with_segment`](crate::operand::MemOperand::with_segment).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Prefix {
+    /// `LOCK` (`F0`): makes a read-modify-write of a memory destination
+    /// atomic. Valid on `add`, `or`, `adc`, `sbb`, `and`, `sub`, `xor`,
+    /// `inc` and `dec` with a memory destination.
+    Lock,
+}
+
+/// Mnemonics that accept a `LOCK` prefix on a memory destination.
+pub(crate) const LOCKABLE: &[Mnemonic] = &[
+    Mnemonic::Add,
+    Mnemonic::Or,
+    Mnemonic::Adc,
+    Mnemonic::Sbb,
+    Mnemonic::And,
+    Mnemonic::Sub,
+    Mnemonic::Xor,
+    Mnemonic::Inc,
+    Mnemonic::Dec,
+];
+
+impl Prefix {
+    /// Returns the prefix byte.
+    #[inline]
+    pub fn byte(self) -> u8 {
+        match self {
+            Prefix::Lock => 0xF0,
+        }
+    }
+}
+
+/// One instruction: a mnemonic, its operands in Intel order (destination
+/// first) and an optional prefix.
+///
+/// Instructions are plain `Copy` values. Building one does not check the
+/// operands; [`Encoder::encode`](crate::encoder::Encoder::encode) reports
+/// an invalid combination when the instruction is emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    mnemonic: Mnemonic,
+    prefix: Option<Prefix>,
+    len: u8,
+    /// Unused slots hold `Operand::Imm(0)`, so that equality only depends
+    /// on the used ones.
+    operands: [Operand; MAX_OPERANDS],
+}
+
+impl Instruction {
+    /// Creates an instruction without operands, such as `ret`.
+    #[inline]
+    pub fn new(mnemonic: Mnemonic) -> Self {
+        Self {
+            mnemonic,
+            prefix: None,
+            len: 0,
+            operands: [Operand::Imm(0); MAX_OPERANDS],
+        }
+    }
+
+    /// Creates an instruction with one operand.
+    #[inline]
+    pub fn with1(mnemonic: Mnemonic, op0: Operand) -> Self {
+        Self::new(mnemonic).with_operands([op0])
+    }
+
+    /// Creates an instruction with two operands.
+    #[inline]
+    pub fn with2(mnemonic: Mnemonic, op0: Operand, op1: Operand) -> Self {
+        Self::new(mnemonic).with_operands([op0, op1])
+    }
+
+    /// Creates an instruction with three operands.
+    #[inline]
+    pub fn with3(mnemonic: Mnemonic, op0: Operand, op1: Operand, op2: Operand) -> Self {
+        Self::new(mnemonic).with_operands([op0, op1, op2])
+    }
+
+    /// Creates an instruction with four operands.
+    #[inline]
+    pub fn with4(
+        mnemonic: Mnemonic,
+        op0: Operand,
+        op1: Operand,
+        op2: Operand,
+        op3: Operand,
+    ) -> Self {
+        Self::new(mnemonic).with_operands([op0, op1, op2, op3])
+    }
+
+    /// Creates an instruction from an operand slice.
+    ///
+    /// Returns an error if there are more than [`MAX_OPERANDS`] operands.
+    pub fn from_operands(mnemonic: Mnemonic, operands: &[Operand]) -> RaskResult<Self> {
+        if operands.len() > MAX_OPERANDS {
+            return Err(RaskError::InvalidOperands {
+                mnemonic: mnemonic.as_str().to_ascii_uppercase(),
+                operands: format!("{operands:?}"),
+                reason: format!("at most {MAX_OPERANDS} operands are supported"),
+            });
+        }
+        let mut insn = Self::new(mnemonic);
+        insn.operands[..operands.len()].copy_from_slice(operands);
+        insn.len = operands.len() as u8;
+        Ok(insn)
+    }
+
+    fn with_operands<const N: usize>(mut self, operands: [Operand; N]) -> Self {
+        self.operands[..N].copy_from_slice(&operands);
+        self.len = N as u8;
+        self
+    }
+
+    /// Adds `prefix` to the instruction.
+    #[inline]
+    pub fn with_prefix(mut self, prefix: Prefix) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Returns the mnemonic.
+    #[inline]
+    pub fn mnemonic(&self) -> Mnemonic {
+        self.mnemonic
+    }
+
+    /// Replaces the mnemonic, keeping the operands.
+    #[inline]
+    pub fn set_mnemonic(&mut self, mnemonic: Mnemonic) {
+        self.mnemonic = mnemonic;
+    }
+
+    /// Returns the prefix, if any.
+    #[inline]
+    pub fn prefix(&self) -> Option<Prefix> {
+        self.prefix
+    }
+
+    /// Replaces the prefix.
+    #[inline]
+    pub fn set_prefix(&mut self, prefix: Option<Prefix>) {
+        self.prefix = prefix;
+    }
+
+    /// Returns the operands in Intel order.
+    #[inline]
+    pub fn operands(&self) -> &[Operand] {
+        &self.operands[..self.len as usize]
+    }
+
+    /// Returns the operands for in-place rewriting, such as register
+    /// renaming.
+    #[inline]
+    pub fn operands_mut(&mut self) -> &mut [Operand] {
+        &mut self.operands[..self.len as usize]
+    }
+
+    /// Returns operand `index`, or `None` past the last operand.
+    #[inline]
+    pub fn operand(&self, index: usize) -> Option<Operand> {
+        self.operands().get(index).copied()
+    }
+
+    /// Returns the number of operands.
+    #[inline]
+    pub fn operand_count(&self) -> usize {
+        self.len as usize
+    }
+}
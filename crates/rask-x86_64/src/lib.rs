@@ -1,3 +1,13 @@
 pub mod registers;
+pub mod decoder;
 pub mod encoder;
+pub mod explain;
+pub mod formatter;
 pub mod operand;
+pub mod features;
+pub mod mode;
+pub mod table;
+pub mod instruction;
+pub use rask_common::{RaskError, RaskResult};
+pub mod sink;
+pub mod roundtrip;
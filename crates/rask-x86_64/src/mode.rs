@@ -0,0 +1,45 @@
+//! Processor operating modes.
+//!
+//! The same opcode bytes mean different things depending on the mode the
+//! processor executes them in: the default operand and address sizes
+//! change, and so does the meaning of the `66`/`67` size-override prefixes
+//! and of the `40`–`4F` byte range (REX in 64-bit mode, `INC`/`DEC`
+//! elsewhere). An [`Encoder`](crate::encoder::Encoder) encodes for one mode
+//! at a time.
+
+/// The processor mode code is encoded for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Mode {
+    /// 16-bit real mode (and 16-bit protected-mode code segments), as used
+    /// by boot sectors.
+    Real16,
+    /// 32-bit protected mode (and compatibility mode under a 64-bit OS).
+    Protected32,
+    /// 64-bit long mode.
+    #[default]
+    Long64,
+}
+
+impl Mode {
+    /// Returns the operand size, in bits, of instructions without a `66`
+    /// prefix. 64-bit mode keeps a 32-bit default and selects 64-bit
+    /// operands with `REX.W`.
+    #[inline]
+    pub fn default_operand_bits(self) -> u32 {
+        match self {
+            Mode::Real16 => 16,
+            Mode::Protected32 | Mode::Long64 => 32,
+        }
+    }
+
+    /// Returns the address size, in bits, of memory operands without a `67`
+    /// prefix.
+    #[inline]
+    pub fn default_address_bits(self) -> u32 {
+        match self {
+            Mode::Real16 => 16,
+            Mode::Protected32 => 32,
+            Mode::Long64 => 64,
+        }
+    }
+}
@@ -1,29 +1,368 @@
-use crate::registers::Reg64;
-
+use crate::registers::{Reg8, Reg16, Reg32, Reg64, SegReg, TmmReg, XmmReg, YmmReg, ZmmReg};
+use std::fmt;
 
 /// Represents any operand that can appear in an instruction.
-/// 
-/// For now, only `Reg` is implemented.
-/// `Mem` will be added later for memory addressing modes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Operand {
     /// A 64-bit general-purpose register.
     Reg(Reg64),
 
-    /// A memory operand (base register + displacement).
-    /// TODO: implement encoding logic for this variant.
+    /// A 32-bit general-purpose register.
+    Reg32(Reg32),
+
+    /// A 16-bit general-purpose register.
+    Reg16(Reg16),
+
+    /// An 8-bit general-purpose register.
+    Reg8(Reg8),
+
+    /// A 128-bit SSE register.
+    Xmm(XmmReg),
+
+    /// A 256-bit AVX register.
+    Ymm(YmmReg),
+
+    /// A 512-bit AVX-512 register.
+    Zmm(ZmmReg),
+
+    /// An AMX tile register.
+    Tmm(TmmReg),
+
+    /// A memory operand (base register + optional scaled index + displacement).
     Mem(MemOperand),
 
     /// A 64-bit immediate constant.
     Imm(i64),
+
+    /// A branch target; see [`Label`].
+    Label(Label),
+}
+
+impl Operand {
+    /// Looks up a register operand by its Intel-syntax name, ignoring case.
+    ///
+    /// ```
+    /// use rask_x86_64::operand::Operand;
+    /// use rask_x86_64::registers::{Reg32::R8D, XmmReg::XMM3};
+    ///
+    /// assert_eq!(Operand::register("r8d"), Some(Operand::Reg32(R8D)));
+    /// assert_eq!(Operand::register("XMM3"), Some(Operand::Xmm(XMM3)));
+    /// assert_eq!(Operand::register("rip"), None);
+    /// ```
+    pub fn register(name: &str) -> Option<Operand> {
+        name.parse()
+            .map(Operand::Reg)
+            .or_else(|_| name.parse().map(Operand::Reg32))
+            .or_else(|_| name.parse().map(Operand::Reg16))
+            .or_else(|_| name.parse().map(Operand::Reg8))
+            .or_else(|_| name.parse().map(Operand::Xmm))
+            .or_else(|_| name.parse().map(Operand::Ymm))
+            .or_else(|_| name.parse().map(Operand::Zmm))
+            .or_else(|_| name.parse().map(Operand::Tmm))
+            .ok()
+    }
+}
+
+macro_rules! operand_from {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for Operand {
+                #[inline]
+                fn from(value: $ty) -> Self {
+                    Operand::$variant(value)
+                }
+            }
+        )*
+    };
 }
 
+operand_from! {
+    Reg64 => Reg,
+    Reg32 => Reg32,
+    Reg16 => Reg16,
+    Reg8 => Reg8,
+    XmmReg => Xmm,
+    YmmReg => Ymm,
+    ZmmReg => Zmm,
+    TmmReg => Tmm,
+    MemOperand => Mem,
+    i64 => Imm,
+    Label => Label,
+}
+
+macro_rules! operand_from_int {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Operand {
+                #[inline]
+                fn from(value: $ty) -> Self {
+                    Operand::Imm(value.into())
+                }
+            }
+        )*
+    };
+}
+
+operand_from_int!(i8, i16, i32, u8, u16, u32);
+
+/// A position in the code that branches can target before it is known.
+///
+/// Create one with [`Encoder::create_label`](crate::encoder::Encoder::create_label),
+/// branch to it with `jmp`, `jcc` or `call`, and bind it to the current
+/// position with [`Encoder::bind_label`](crate::encoder::Encoder::bind_label).
+/// A label belongs to the encoder that created it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(pub(crate) u32);
+
+impl Label {
+    /// Returns the label's number, unique within its encoder.
+    #[inline]
+    pub fn id(self) -> u32 {
+        self.0
+    }
+}
 
-/// Describes a memory operand.
-/// 
-/// For now, only the structure is defined — no encoding logic yet.
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ".L{}", self.0)
+    }
+}
+
+/// Scale factor applied to the index register of a memory operand.
+///
+/// Stored in the two `scale` bits of the SIB byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scale {
+    S1,
+    S2,
+    S4,
+    S8,
+}
+
+impl Scale {
+    /// Returns the 2-bit SIB encoding of this scale factor.
+    #[inline(always)]
+    pub fn bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the multiplier this scale represents (1, 2, 4 or 8).
+    #[inline(always)]
+    pub fn factor(self) -> u8 {
+        1 << self.bits()
+    }
+}
+
+/// Width of a memory access, written `byte ptr`, `word ptr`, ... in Intel syntax.
+///
+/// Most instructions infer the width from their register operand. The size
+/// only has to be given explicitly where the instruction has several forms
+/// that differ in nothing but the memory width, such as `crc32 eax, byte ptr [rsi]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemSize {
+    Byte,
+    Word,
+    Dword,
+    Qword,
+}
+
+impl MemSize {
+    /// Returns the access width in bits.
+    #[inline(always)]
+    pub fn bits(self) -> u32 {
+        match self {
+            MemSize::Byte => 8,
+            MemSize::Word => 16,
+            MemSize::Dword => 32,
+            MemSize::Qword => 64,
+        }
+    }
+}
+
+/// A register usable as the base or index of a [`MemOperand`].
+///
+/// The register width selects the address size. 64-bit registers give
+/// native long-mode addressing. 32-bit registers select 32-bit address
+/// arithmetic: the effective address wraps at 4 GiB and is zero-extended.
+/// 16-bit registers select the 16-bit `[BX/BP + SI/DI]` forms, which are
+/// not available in 64-bit mode. When the width differs from the mode's
+/// default (see [`Mode`](crate::mode::Mode)), the instruction carries the
+/// `67` address-size override prefix. Base and index must have the same width.
+///
+/// [`AddrReg::Rip`] is the instruction pointer, which 64-bit mode can use as
+/// a base without an index; see [`MemOperand::rip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddrReg {
+    R64(Reg64),
+    R32(Reg32),
+    R16(Reg16),
+    Rip,
+}
+
+impl AddrReg {
+    /// Returns the register encoding ID used in ModR/M and SIB bytes.
+    ///
+    /// RIP has no register ID; this returns `101`, the `r/m` value that
+    /// selects RIP-relative addressing.
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        match self {
+            AddrReg::R64(r) => r.id(),
+            AddrReg::R32(r) => r.id(),
+            AddrReg::R16(r) => r.id(),
+            AddrReg::Rip => 0b101,
+        }
+    }
+
+    /// Returns the address width this register selects, in bits.
+    #[inline(always)]
+    pub fn bits(self) -> u32 {
+        match self {
+            AddrReg::R64(_) | AddrReg::Rip => 64,
+            AddrReg::R32(_) => 32,
+            AddrReg::R16(_) => 16,
+        }
+    }
+}
+
+impl From<Reg64> for AddrReg {
+    #[inline]
+    fn from(r: Reg64) -> Self {
+        AddrReg::R64(r)
+    }
+}
+
+impl From<Reg32> for AddrReg {
+    #[inline]
+    fn from(r: Reg32) -> Self {
+        AddrReg::R32(r)
+    }
+}
+
+impl From<Reg16> for AddrReg {
+    #[inline]
+    fn from(r: Reg16) -> Self {
+        AddrReg::R16(r)
+    }
+}
+
+/// Describes a memory operand of the form `seg:[base + index*scale + disp]`.
+///
+/// Build one with [`MemOperand::new`] (or [`MemOperand::absolute`] for an
+/// address without a base register), add an index with
+/// [`MemOperand::with_index`] and a segment override with
+/// [`MemOperand::with_segment`]:
+///
+/// Base and index registers may be 64-bit or 32-bit; see [`AddrReg`].
+///
+/// ```
+/// use rask_x86_64::operand::{MemOperand, Scale};
+/// use rask_x86_64::registers::{Reg32::*, Reg64::*, SegReg};
+///
+/// // [rdi + rcx*8 + 16]
+/// let mem = MemOperand::new(RDI, 16).with_index(RCX, Scale::S8);
+/// assert_eq!(mem.index, Some((RCX.into(), Scale::S8)));
+///
+/// // [ecx + edx*4], 32-bit addressing
+/// let mem = MemOperand::new(ECX, 0).with_index(EDX, Scale::S4);
+/// assert_eq!(mem.address_bits(), Some(32));
+///
+/// // fs:[0], the thread pointer on x86-64 Linux
+/// let tls = MemOperand::absolute(0).with_segment(SegReg::FS);
+/// assert_eq!(tls.base, None);
+/// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MemOperand {
-    pub base: Reg64,
+    /// Base register, or `None` for an absolute `[index*scale + disp32]` address.
+    pub base: Option<AddrReg>,
+    /// Optional index register and its scale factor. RSP/ESP cannot be an index.
+    pub index: Option<(AddrReg, Scale)>,
     pub disp: i32,
-}
\ No newline at end of file
+    /// Optional explicit access width; see [`MemSize`].
+    pub size: Option<MemSize>,
+    /// Optional segment override, emitted as a prefix byte.
+    pub segment: Option<SegReg>,
+}
+
+impl MemOperand {
+    /// Creates a `[base + disp]` memory operand.
+    #[inline]
+    pub fn new(base: impl Into<AddrReg>, disp: i32) -> Self {
+        Self {
+            base: Some(base.into()),
+            index: None,
+            disp,
+            size: None,
+            segment: None,
+        }
+    }
+
+    /// Creates an absolute `[disp]` memory operand with no base register.
+    ///
+    /// In 64-bit mode the displacement is sign-extended to 64 bits, so this
+    /// reaches the lowest and highest 2 GiB of the address space, or any
+    /// offset from an FS/GS segment base. In 16-bit mode an address beyond
+    /// 16 bits is encoded with 32-bit addressing (the `67` prefix).
+    #[inline]
+    pub fn absolute(disp: i32) -> Self {
+        Self {
+            base: None,
+            index: None,
+            disp,
+            size: None,
+            segment: None,
+        }
+    }
+
+    /// Creates a RIP-relative `[rip + disp]` memory operand, available in
+    /// 64-bit mode only.
+    ///
+    /// The displacement is relative to the end of the instruction, so the
+    /// same bytes reach the same data wherever the code is loaded.
+    ///
+    /// ```
+    /// use rask_x86_64::operand::{AddrReg, MemOperand};
+    ///
+    /// let constant = MemOperand::rip(0x100);
+    /// assert_eq!(constant.base, Some(AddrReg::Rip));
+    /// ```
+    #[inline]
+    pub fn rip(disp: i32) -> Self {
+        Self::new(AddrReg::Rip, disp)
+    }
+
+    /// Adds a scaled index register, producing `[base + index*scale + disp]`.
+    ///
+    /// The index must have the width of the base register; the encoder
+    /// rejects an operand where they differ.
+    #[inline]
+    pub fn with_index(mut self, index: impl Into<AddrReg>, scale: Scale) -> Self {
+        self.index = Some((index.into(), scale));
+        self
+    }
+
+    /// Sets an explicit access width (`byte ptr [..]` and friends).
+    #[inline]
+    pub fn with_size(mut self, size: MemSize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Returns the address width in bits selected by the base register, or
+    /// by the index without a base, or `None` for an absolute address, which
+    /// uses the mode's default address size.
+    #[inline]
+    pub fn address_bits(&self) -> Option<u32> {
+        match (self.base, self.index) {
+            (Some(r), _) | (None, Some((r, _))) => Some(r.bits()),
+            (None, None) => None,
+        }
+    }
+
+    /// Sets a segment override, producing `seg:[..]`.
+    #[inline]
+    pub fn with_segment(mut self, segment: SegReg) -> Self {
+        self.segment = Some(segment);
+        self
+    }
+}
@@ -1,8 +1,13 @@
-use rask_common::RegClass;
+use rask_common::{RaskError, RaskResult, RegClass};
+use std::{fmt, str::FromStr};
 
-/// Represents the 16 general-purpose 64-bit registers available in x86_64 mode.
+/// Represents the general-purpose 64-bit registers available in x86_64 mode.
 ///
 /// The numeric `id()` corresponds to the ModR/M and REX register encoding IDs.
+///
+/// R16–R31 are the extended GPRs added by Intel APX. They can only be encoded
+/// with the REX2 or extended EVEX prefixes, and the encoder rejects them
+/// unless [`CpuFeature::Apx`](crate::features::CpuFeature::Apx) is enabled.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Reg64 {
     RAX,
@@ -21,10 +26,29 @@ pub enum Reg64 {
     R13,
     R14,
     R15,
+    R16,
+    R17,
+    R18,
+    R19,
+    R20,
+    R21,
+    R22,
+    R23,
+    R24,
+    R25,
+    R26,
+    R27,
+    R28,
+    R29,
+    R30,
+    R31,
 }
 
 impl Reg64 {
-    /// Returns the 3- or 4-bit register encoding ID used in ModR/M and REX prefixes.
+    /// Returns the register encoding ID used in ModR/M and REX prefixes.
+    ///
+    /// IDs are 4 bits wide (0–15) for the legacy registers and 5 bits wide
+    /// (16–31) for the APX registers.
     #[inline(always)]
     pub fn id(self) -> u8 {
         use Reg64::*;
@@ -45,6 +69,22 @@ impl Reg64 {
             R13 => 13,
             R14 => 14,
             R15 => 15,
+            R16 => 16,
+            R17 => 17,
+            R18 => 18,
+            R19 => 19,
+            R20 => 20,
+            R21 => 21,
+            R22 => 22,
+            R23 => 23,
+            R24 => 24,
+            R25 => 25,
+            R26 => 26,
+            R27 => 27,
+            R28 => 28,
+            R29 => 29,
+            R30 => 30,
+            R31 => 31,
         }
     }
 
@@ -56,7 +96,582 @@ impl Reg64 {
 
     /// Returns true if this register requires a REX prefix extension (R8–R15).
     #[inline(always)]
+    pub fn needs_rex(self) -> bool {
+        (8..16).contains(&self.id())
+    }
+
+    /// Returns true for the APX registers R16–R31, which need a REX2 or
+    /// extended EVEX prefix.
+    #[inline(always)]
+    pub fn needs_rex2(self) -> bool {
+        self.id() >= 16
+    }
+}
+
+/// The 32-bit views of the general-purpose registers.
+///
+/// Writing a 32-bit register zero-extends into the full 64-bit register, so
+/// these are mostly used for operations whose natural width is a doubleword.
+/// R16D–R31D are APX registers; see [`Reg64`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reg32 {
+    EAX,
+    ECX,
+    EDX,
+    EBX,
+    ESP,
+    EBP,
+    ESI,
+    EDI,
+    R8D,
+    R9D,
+    R10D,
+    R11D,
+    R12D,
+    R13D,
+    R14D,
+    R15D,
+    R16D,
+    R17D,
+    R18D,
+    R19D,
+    R20D,
+    R21D,
+    R22D,
+    R23D,
+    R24D,
+    R25D,
+    R26D,
+    R27D,
+    R28D,
+    R29D,
+    R30D,
+    R31D,
+}
+
+impl Reg32 {
+    /// Returns the register encoding ID used in ModR/M and REX prefixes
+    /// (5 bits wide for the APX registers).
+    ///
+    /// Variants are declared in encoding order, so this is the discriminant.
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the register class — general-purpose in this case.
+    #[inline(always)]
+    pub fn class(self) -> RegClass {
+        RegClass::General
+    }
+
+    /// Returns true if this register requires a REX prefix extension (R8D–R15D).
+    #[inline(always)]
+    pub fn needs_rex(self) -> bool {
+        (8..16).contains(&self.id())
+    }
+
+    /// Returns true for the APX registers R16D–R31D, which need a REX2 or
+    /// extended EVEX prefix.
+    #[inline(always)]
+    pub fn needs_rex2(self) -> bool {
+        self.id() >= 16
+    }
+}
+
+/// The 128-bit SSE registers XMM0–XMM15.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XmmReg {
+    XMM0,
+    XMM1,
+    XMM2,
+    XMM3,
+    XMM4,
+    XMM5,
+    XMM6,
+    XMM7,
+    XMM8,
+    XMM9,
+    XMM10,
+    XMM11,
+    XMM12,
+    XMM13,
+    XMM14,
+    XMM15,
+}
+
+impl XmmReg {
+    /// Returns the register encoding ID used in ModR/M, REX and VEX prefixes.
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the register class — vector in this case.
+    #[inline(always)]
+    pub fn class(self) -> RegClass {
+        RegClass::Vector
+    }
+
+    /// Returns true if this register requires a REX (or VEX) extension bit (XMM8–XMM15).
+    #[inline(always)]
+    pub fn needs_rex(self) -> bool {
+        self.id() >= 8
+    }
+}
+
+/// The 256-bit AVX registers YMM0–YMM15.
+///
+/// YMMn shares its low 128 bits with XMMn; only VEX-encoded instructions can
+/// address the full width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum YmmReg {
+    YMM0,
+    YMM1,
+    YMM2,
+    YMM3,
+    YMM4,
+    YMM5,
+    YMM6,
+    YMM7,
+    YMM8,
+    YMM9,
+    YMM10,
+    YMM11,
+    YMM12,
+    YMM13,
+    YMM14,
+    YMM15,
+}
+
+impl YmmReg {
+    /// Returns the register encoding ID used in ModR/M and VEX prefixes.
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the register class — vector in this case.
+    #[inline(always)]
+    pub fn class(self) -> RegClass {
+        RegClass::Vector
+    }
+
+    /// Returns true if this register requires a VEX extension bit (YMM8–YMM15).
+    #[inline(always)]
     pub fn needs_rex(self) -> bool {
         self.id() >= 8
     }
 }
+
+/// The 16-bit views of the general-purpose registers.
+///
+/// Instructions on these need the `66` operand-size prefix in 64-bit mode.
+/// R16W–R31W are APX registers; see [`Reg64`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reg16 {
+    AX,
+    CX,
+    DX,
+    BX,
+    SP,
+    BP,
+    SI,
+    DI,
+    R8W,
+    R9W,
+    R10W,
+    R11W,
+    R12W,
+    R13W,
+    R14W,
+    R15W,
+    R16W,
+    R17W,
+    R18W,
+    R19W,
+    R20W,
+    R21W,
+    R22W,
+    R23W,
+    R24W,
+    R25W,
+    R26W,
+    R27W,
+    R28W,
+    R29W,
+    R30W,
+    R31W,
+}
+
+impl Reg16 {
+    /// Returns the register encoding ID used in ModR/M and REX prefixes
+    /// (5 bits wide for the APX registers).
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the register class — general-purpose in this case.
+    #[inline(always)]
+    pub fn class(self) -> RegClass {
+        RegClass::General
+    }
+
+    /// Returns true if this register requires a REX prefix extension (R8W–R15W).
+    #[inline(always)]
+    pub fn needs_rex(self) -> bool {
+        (8..16).contains(&self.id())
+    }
+
+    /// Returns true for the APX registers R16W–R31W, which need a REX2 or
+    /// extended EVEX prefix.
+    #[inline(always)]
+    pub fn needs_rex2(self) -> bool {
+        self.id() >= 16
+    }
+}
+
+/// The 8-bit views of the general-purpose registers.
+///
+/// Byte registers have an encoding quirk: IDs 4–7 mean AH, CH, DH, BH when no
+/// REX prefix is present, and SPL, BPL, SIL, DIL when one is. So SPL–DIL
+/// force an (otherwise empty) REX prefix, and AH–BH cannot be combined with
+/// anything that needs one. R16B–R31B are APX registers; see [`Reg64`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reg8 {
+    AL,
+    CL,
+    DL,
+    BL,
+    SPL,
+    BPL,
+    SIL,
+    DIL,
+    R8B,
+    R9B,
+    R10B,
+    R11B,
+    R12B,
+    R13B,
+    R14B,
+    R15B,
+    R16B,
+    R17B,
+    R18B,
+    R19B,
+    R20B,
+    R21B,
+    R22B,
+    R23B,
+    R24B,
+    R25B,
+    R26B,
+    R27B,
+    R28B,
+    R29B,
+    R30B,
+    R31B,
+    AH,
+    CH,
+    DH,
+    BH,
+}
+
+impl Reg8 {
+    /// Returns the register encoding ID used in ModR/M and REX prefixes
+    /// (5 bits wide for the APX registers).
+    ///
+    /// AH–BH share IDs 4–7 with SPL–DIL; see [`Reg8::is_high_byte`].
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        use Reg8::*;
+        match self {
+            AH => 4,
+            CH => 5,
+            DH => 6,
+            BH => 7,
+            other => other as u8,
+        }
+    }
+
+    /// Returns the register class — general-purpose in this case.
+    #[inline(always)]
+    pub fn class(self) -> RegClass {
+        RegClass::General
+    }
+
+    /// Returns true if this register requires a REX prefix extension (R8B–R15B).
+    #[inline(always)]
+    pub fn needs_rex(self) -> bool {
+        (8..16).contains(&self.id())
+    }
+
+    /// Returns true for the APX registers R16B–R31B, which need a REX2 or
+    /// extended EVEX prefix.
+    #[inline(always)]
+    pub fn needs_rex2(self) -> bool {
+        self.id() >= 16
+    }
+
+    /// Returns true for SPL, BPL, SIL and DIL, which are only addressable when
+    /// a REX prefix is present (even one with no bits set).
+    #[inline(always)]
+    pub fn requires_rex(self) -> bool {
+        matches!(self, Reg8::SPL | Reg8::BPL | Reg8::SIL | Reg8::DIL)
+    }
+
+    /// Returns true for the legacy high-byte registers AH, CH, DH and BH,
+    /// which cannot be encoded in an instruction carrying a REX prefix.
+    #[inline(always)]
+    pub fn is_high_byte(self) -> bool {
+        matches!(self, Reg8::AH | Reg8::CH | Reg8::DH | Reg8::BH)
+    }
+}
+
+/// The 512-bit AVX-512 registers ZMM0–ZMM31.
+///
+/// ZMM registers are only reachable through the EVEX prefix, which carries a
+/// fifth register-ID bit for ZMM16–ZMM31.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ZmmReg {
+    ZMM0,
+    ZMM1,
+    ZMM2,
+    ZMM3,
+    ZMM4,
+    ZMM5,
+    ZMM6,
+    ZMM7,
+    ZMM8,
+    ZMM9,
+    ZMM10,
+    ZMM11,
+    ZMM12,
+    ZMM13,
+    ZMM14,
+    ZMM15,
+    ZMM16,
+    ZMM17,
+    ZMM18,
+    ZMM19,
+    ZMM20,
+    ZMM21,
+    ZMM22,
+    ZMM23,
+    ZMM24,
+    ZMM25,
+    ZMM26,
+    ZMM27,
+    ZMM28,
+    ZMM29,
+    ZMM30,
+    ZMM31,
+}
+
+impl ZmmReg {
+    /// Returns the 5-bit register encoding ID used in ModR/M and EVEX prefixes.
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the register class — vector in this case.
+    #[inline(always)]
+    pub fn class(self) -> RegClass {
+        RegClass::Vector
+    }
+}
+
+/// The AMX tile registers TMM0–TMM7.
+///
+/// Each tile is a 2-D matrix of up to 16 rows × 64 bytes whose actual shape
+/// is set by the tile configuration loaded with `LDTILECFG`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TmmReg {
+    TMM0,
+    TMM1,
+    TMM2,
+    TMM3,
+    TMM4,
+    TMM5,
+    TMM6,
+    TMM7,
+}
+
+impl TmmReg {
+    /// Returns the 3-bit register encoding ID used in ModR/M and VEX prefixes.
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the register class — vector in this case.
+    #[inline(always)]
+    pub fn class(self) -> RegClass {
+        RegClass::Vector
+    }
+}
+
+/// The segment registers, used as memory-operand overrides.
+///
+/// In 64-bit mode the ES, CS, SS and DS bases are treated as zero, so only
+/// FS and GS overrides change the effective address; operating systems point
+/// their bases at thread- or CPU-local data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SegReg {
+    ES,
+    CS,
+    SS,
+    DS,
+    FS,
+    GS,
+}
+
+impl SegReg {
+    /// Returns the 3-bit `Sreg` encoding ID used by `MOV Sreg` and friends.
+    #[inline(always)]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the segment-override prefix byte selecting this register.
+    #[inline(always)]
+    pub fn prefix(self) -> u8 {
+        match self {
+            SegReg::ES => 0x26,
+            SegReg::CS => 0x2E,
+            SegReg::SS => 0x36,
+            SegReg::DS => 0x3E,
+            SegReg::FS => 0x64,
+            SegReg::GS => 0x65,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Register names
+// -----------------------------------------------------------------------------
+
+/// Implements `ALL`, `name`, [`fmt::Display`] and [`FromStr`] for a register
+/// enum, from its lowercase Intel-syntax names.
+macro_rules! register_names {
+    ($ty:ident: $($variant:ident => $name:literal,)*) => {
+        impl $ty {
+            /// Every register of this kind, in declaration order.
+            pub const ALL: &'static [$ty] = &[$($ty::$variant),*];
+
+            /// Returns the lowercase Intel-syntax name, such as `rax`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($ty::$variant => $name,)*
+                }
+            }
+        }
+
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = RaskError;
+
+            /// Parses an Intel-syntax register name, ignoring case.
+            fn from_str(s: &str) -> RaskResult<Self> {
+                $ty::ALL
+                    .iter()
+                    .copied()
+                    .find(|r| r.name().eq_ignore_ascii_case(s))
+                    .ok_or_else(|| {
+                        RaskError::Other(format!("unknown register {s:?}"))
+                    })
+            }
+        }
+    };
+}
+
+#[rustfmt::skip]
+register_names! {
+    Reg64:
+    RAX => "rax", RCX => "rcx", RDX => "rdx", RBX => "rbx", RSP => "rsp", RBP => "rbp",
+    RSI => "rsi", RDI => "rdi", R8 => "r8", R9 => "r9", R10 => "r10", R11 => "r11",
+    R12 => "r12", R13 => "r13", R14 => "r14", R15 => "r15", R16 => "r16", R17 => "r17",
+    R18 => "r18", R19 => "r19", R20 => "r20", R21 => "r21", R22 => "r22", R23 => "r23",
+    R24 => "r24", R25 => "r25", R26 => "r26", R27 => "r27", R28 => "r28", R29 => "r29",
+    R30 => "r30", R31 => "r31",
+}
+
+#[rustfmt::skip]
+register_names! {
+    Reg32:
+    EAX => "eax", ECX => "ecx", EDX => "edx", EBX => "ebx", ESP => "esp", EBP => "ebp",
+    ESI => "esi", EDI => "edi", R8D => "r8d", R9D => "r9d", R10D => "r10d", R11D => "r11d",
+    R12D => "r12d", R13D => "r13d", R14D => "r14d", R15D => "r15d", R16D => "r16d", R17D => "r17d",
+    R18D => "r18d", R19D => "r19d", R20D => "r20d", R21D => "r21d", R22D => "r22d", R23D => "r23d",
+    R24D => "r24d", R25D => "r25d", R26D => "r26d", R27D => "r27d", R28D => "r28d", R29D => "r29d",
+    R30D => "r30d", R31D => "r31d",
+}
+
+#[rustfmt::skip]
+register_names! {
+    XmmReg:
+    XMM0 => "xmm0", XMM1 => "xmm1", XMM2 => "xmm2", XMM3 => "xmm3", XMM4 => "xmm4", XMM5 => "xmm5",
+    XMM6 => "xmm6", XMM7 => "xmm7", XMM8 => "xmm8", XMM9 => "xmm9", XMM10 => "xmm10", XMM11 => "xmm11",
+    XMM12 => "xmm12", XMM13 => "xmm13", XMM14 => "xmm14", XMM15 => "xmm15",
+}
+
+#[rustfmt::skip]
+register_names! {
+    YmmReg:
+    YMM0 => "ymm0", YMM1 => "ymm1", YMM2 => "ymm2", YMM3 => "ymm3", YMM4 => "ymm4", YMM5 => "ymm5",
+    YMM6 => "ymm6", YMM7 => "ymm7", YMM8 => "ymm8", YMM9 => "ymm9", YMM10 => "ymm10", YMM11 => "ymm11",
+    YMM12 => "ymm12", YMM13 => "ymm13", YMM14 => "ymm14", YMM15 => "ymm15",
+}
+
+#[rustfmt::skip]
+register_names! {
+    Reg16:
+    AX => "ax", CX => "cx", DX => "dx", BX => "bx", SP => "sp", BP => "bp",
+    SI => "si", DI => "di", R8W => "r8w", R9W => "r9w", R10W => "r10w", R11W => "r11w",
+    R12W => "r12w", R13W => "r13w", R14W => "r14w", R15W => "r15w", R16W => "r16w", R17W => "r17w",
+    R18W => "r18w", R19W => "r19w", R20W => "r20w", R21W => "r21w", R22W => "r22w", R23W => "r23w",
+    R24W => "r24w", R25W => "r25w", R26W => "r26w", R27W => "r27w", R28W => "r28w", R29W => "r29w",
+    R30W => "r30w", R31W => "r31w",
+}
+
+#[rustfmt::skip]
+register_names! {
+    Reg8:
+    AL => "al", CL => "cl", DL => "dl", BL => "bl", SPL => "spl", BPL => "bpl",
+    SIL => "sil", DIL => "dil", R8B => "r8b", R9B => "r9b", R10B => "r10b", R11B => "r11b",
+    R12B => "r12b", R13B => "r13b", R14B => "r14b", R15B => "r15b", R16B => "r16b", R17B => "r17b",
+    R18B => "r18b", R19B => "r19b", R20B => "r20b", R21B => "r21b", R22B => "r22b", R23B => "r23b",
+    R24B => "r24b", R25B => "r25b", R26B => "r26b", R27B => "r27b", R28B => "r28b", R29B => "r29b",
+    R30B => "r30b", R31B => "r31b", AH => "ah", CH => "ch", DH => "dh", BH => "bh",
+}
+
+#[rustfmt::skip]
+register_names! {
+    ZmmReg:
+    ZMM0 => "zmm0", ZMM1 => "zmm1", ZMM2 => "zmm2", ZMM3 => "zmm3", ZMM4 => "zmm4", ZMM5 => "zmm5",
+    ZMM6 => "zmm6", ZMM7 => "zmm7", ZMM8 => "zmm8", ZMM9 => "zmm9", ZMM10 => "zmm10", ZMM11 => "zmm11",
+    ZMM12 => "zmm12", ZMM13 => "zmm13", ZMM14 => "zmm14", ZMM15 => "zmm15", ZMM16 => "zmm16", ZMM17 => "zmm17",
+    ZMM18 => "zmm18", ZMM19 => "zmm19", ZMM20 => "zmm20", ZMM21 => "zmm21", ZMM22 => "zmm22", ZMM23 => "zmm23",
+    ZMM24 => "zmm24", ZMM25 => "zmm25", ZMM26 => "zmm26", ZMM27 => "zmm27", ZMM28 => "zmm28", ZMM29 => "zmm29",
+    ZMM30 => "zmm30", ZMM31 => "zmm31",
+}
+
+#[rustfmt::skip]
+register_names! {
+    TmmReg:
+    TMM0 => "tmm0", TMM1 => "tmm1", TMM2 => "tmm2", TMM3 => "tmm3", TMM4 => "tmm4", TMM5 => "tmm5",
+    TMM6 => "tmm6", TMM7 => "tmm7",
+}
+
+#[rustfmt::skip]
+register_names! {
+    SegReg:
+    ES => "es", CS => "cs", SS => "ss", DS => "ds", FS => "fs", GS => "gs",
+}
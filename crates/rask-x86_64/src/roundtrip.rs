@@ -0,0 +1,591 @@
+//! Encode/decode round-trip checking.
+//!
+//! A [`RoundTrip`] enumerates every operand combination of every
+//! instruction the encoder supports in one [`Mode`] — every register in
+//! every register operand, every base and index register with the
+//! displacements at the edges of `disp8` ([`DISPLACEMENTS`]), RIP-relative,
+//! absolute and segment-override addresses, and the edge values of every
+//! immediate width — and checks each of them against the
+//! [`Decoder`]: the bytes must decode to the same instruction, and the
+//! decoded instruction must encode to the same bytes again.
+//!
+//! ```
+//! use rask_x86_64::features::CpuFeatures;
+//! use rask_x86_64::mode::Mode;
+//! use rask_x86_64::roundtrip::RoundTrip;
+//!
+//! let check = RoundTrip::new(Mode::Long64, CpuFeatures::new());
+//! for insn in check.instructions().iter().take(1000) {
+//!     check.check(insn)?;
+//! }
+//! # Ok::<(), rask_x86_64::RaskError>(())
+//! ```
+//!
+//! The same checks take arbitrary [`Instruction`]s and bytes, which is what
+//! the fuzz targets in the repository's `fuzz` directory feed them:
+//! [`RoundTrip::check`] skips instructions the encoder rejects, and
+//! [`RoundTrip::check_bytes`] re-encodes whatever the decoder accepts.
+//!
+//! A mismatch is reported as [`RaskError::Other`] naming the instruction
+//! and its bytes.
+
+use crate::{
+    decoder::{Decoded, Decoder},
+    encoder::Encoder,
+    features::{CpuFeature, CpuFeatures},
+    formatter::{Formatter, Syntax},
+    instruction::{Instruction, Mnemonic},
+    mode::Mode,
+    operand::{AddrReg, Label, MemOperand, MemSize, Operand, Scale},
+    registers::{Reg8, Reg16, Reg32, Reg64, SegReg, TmmReg, XmmReg, YmmReg, ZmmReg},
+    table::{self, OperandKind},
+};
+use rask_common::{RaskError, RaskResult};
+
+/// The displacements every base register is combined with: zero, the edges
+/// of `disp8` and the first values beyond them, which need a `disp32`
+/// (`disp16` with 16-bit addressing).
+pub const DISPLACEMENTS: [i32; 5] = [0, 127, -128, 128, -129];
+
+/// The offset the checked instructions are encoded at. Branch targets are
+/// bound around it; see [`RoundTrip::targets`].
+const AT: usize = 0x200;
+
+/// The kind of operand one position of an instruction takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    /// A kind from the instruction table.
+    Table(OperandKind),
+    /// An XMM, YMM or ZMM register of this many bits.
+    Vector(u32),
+    /// A vector register of this many bits or a memory operand.
+    VectorMem(u32),
+    /// An AMX tile register.
+    Tmm,
+    /// A direct branch target.
+    Target,
+}
+
+use OperandKind as K;
+use Slot::{Table, Tmm, Vector, VectorMem};
+
+/// `op v, v, v/m` at every vector width.
+const VECTOR3: &[&[Slot]] = &[
+    &[Vector(128), Vector(128), VectorMem(128)],
+    &[Vector(256), Vector(256), VectorMem(256)],
+    &[Vector(512), Vector(512), VectorMem(512)],
+];
+
+/// `op v, v, v/m, imm8` at every vector width.
+const VECTOR3_IB: &[&[Slot]] = &[
+    &[Vector(128), Vector(128), VectorMem(128), Table(K::Imm8)],
+    &[Vector(256), Vector(256), VectorMem(256), Table(K::Imm8)],
+    &[Vector(512), Vector(512), VectorMem(512), Table(K::Imm8)],
+];
+
+const VECTOR_STORE: &[&[Slot]] = &[
+    &[Table(K::Mem), Vector(128)],
+    &[Table(K::Mem), Vector(256)],
+    &[Table(K::Mem), Vector(512)],
+];
+
+const VECTOR_LOAD: &[&[Slot]] = &[
+    &[Vector(128), Table(K::Mem)],
+    &[Vector(256), Table(K::Mem)],
+    &[Vector(512), Table(K::Mem)],
+];
+
+/// The APX new-data-destination forms `op r64, r/m64, r/m64|imm32`.
+const NDD: &[&[Slot]] = &[
+    &[Table(K::Reg64), Table(K::Rm64), Table(K::Rm64)],
+    &[Table(K::Reg64), Table(K::Rm64), Table(K::SImm32)],
+];
+
+const TILE3: &[&[Slot]] = &[&[Tmm, Tmm, Tmm]];
+
+/// Returns the operand shapes of the forms the instruction table does not
+/// define: the VEX, EVEX, AMX and APX families, three-operand ALU
+/// instructions and direct branches.
+fn hand_encoded(mnemonic: Mnemonic) -> &'static [&'static [Slot]] {
+    use Mnemonic::*;
+    match mnemonic {
+        Add | Or | Adc | Sbb | And | Sub | Xor => NDD,
+        Jmp | Call => &[&[Slot::Target]],
+        _ if mnemonic.condition().is_some() => &[&[Slot::Target]],
+        Push2 | Push2p | Pop2 | Pop2p => &[&[Table(K::Reg64), Table(K::Reg64)]],
+        Vmovntdq | Vmovntps | Vmovntpd => VECTOR_STORE,
+        Vmovntdqa => VECTOR_LOAD,
+        Vaesenc | Vaesenclast | Vaesdec | Vaesdeclast | Vgf2p8mulb => VECTOR3,
+        Vpclmulqdq | Vgf2p8affineinvqb | Vgf2p8affineqb => VECTOR3_IB,
+        Vaesimc => &[&[Vector(128), VectorMem(128)]],
+        Vaeskeygenassist => &[&[Vector(128), VectorMem(128), Table(K::Imm8)]],
+        Ldtilecfg | Sttilecfg => &[&[Table(K::Mem)]],
+        Tileloadd | Tileloaddt1 => &[&[Tmm, Table(K::Mem)]],
+        Tilestored => &[&[Table(K::Mem), Tmm]],
+        Tilezero => &[&[Tmm]],
+        Tilerelease => &[&[]],
+        Tdpbssd | Tdpbsud | Tdpbusd | Tdpbuud | Tdpbf16ps => TILE3,
+        _ => &[],
+    }
+}
+
+/// Returns the edge values of an immediate kind: zero, one, and the
+/// largest and smallest values it takes, signed or unsigned as the
+/// decoder reports them.
+fn immediates(kind: OperandKind) -> &'static [i64] {
+    match kind {
+        K::Imm8 => &[0, 1, 0x7F, 0x80, 0xFF],
+        K::SImm8 => &[0, 1, -1, 0x7F, -0x80],
+        K::Imm16 => &[0, 0x7F, 0x80, 0x7FFF, 0x8000, 0xFFFF],
+        K::Imm32 => &[0, 0x7F, 0x80, 0x7FFF_FFFF, 0x8000_0000, 0xFFFF_FFFF],
+        K::SImm32 => &[0, -1, 0x80, -0x81, 0x7FFF_FFFF, -0x8000_0000],
+        K::Imm64 => &[0, -1, 0x8000_0000, -0x8000_0001, i64::MAX, i64::MIN],
+        _ => &[],
+    }
+}
+
+/// Generates instructions and checks that they survive a trip through the
+/// encoder and the decoder, in one mode and with one set of features.
+///
+/// Direct branches are checked against labels bound around the offset the
+/// instructions are encoded at, so that they take both `rel8` and
+/// `rel32` forms; see [`RoundTrip::targets`].
+pub struct RoundTrip {
+    encoder: Encoder,
+    targets: Vec<Label>,
+    memory: Vec<MemOperand>,
+}
+
+impl RoundTrip {
+    /// Returns a checker for `mode` with `features`.
+    pub fn new(mode: Mode, features: CpuFeatures) -> Self {
+        let mut encoder = Encoder::with_features(features);
+        encoder.set_mode(mode);
+
+        // Far behind, the farthest a two-byte `rel8` branch reaches, one
+        // byte beyond that, the instruction itself, and a label that is
+        // never bound.
+        let mut targets = Vec::new();
+        for offset in [0, AT - 127, AT - 126, AT] {
+            let pad = offset - encoder.position();
+            encoder
+                .emit_all(&vec![0xCC; pad])
+                .expect("padding fits the buffer");
+            let label = encoder.create_label();
+            encoder.bind_label(label).expect("label is new");
+            targets.push(label);
+        }
+        targets.push(encoder.create_label());
+
+        let memory = memory_operands(mode, gpr_count(mode, features));
+        Self {
+            encoder,
+            targets,
+            memory,
+        }
+    }
+
+    /// Returns the mode instructions are checked in.
+    pub fn mode(&self) -> Mode {
+        self.encoder.mode()
+    }
+
+    /// Returns the labels direct branches are generated with: far behind
+    /// the instruction, at the edge of `rel8` reach and just beyond it,
+    /// the instruction itself, and a forward label that is not bound, which
+    /// the encoder gives a zero displacement.
+    pub fn targets(&self) -> &[Label] {
+        &self.targets
+    }
+
+    /// Returns every operand combination of every instruction form
+    /// available in the mode with the features.
+    ///
+    /// Forms with up to two operands get the full product of their operand
+    /// values; longer forms get every pair of values in every two
+    /// positions, the other positions cycling through theirs. At most one
+    /// operand is a memory operand. Some combinations are still invalid,
+    /// such as AH next to a register that needs REX; [`RoundTrip::check`]
+    /// skips those.
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mode = self.mode();
+        let features = self.encoder.features();
+        let apx = mode == Mode::Long64 && features.contains(CpuFeature::Apx);
+        let mut out = Vec::new();
+        for &mnemonic in Mnemonic::ALL {
+            let mut shapes: Vec<Vec<Slot>> = Vec::new();
+            for form in table::forms(mnemonic.as_str()).unwrap_or_default() {
+                let shape: Vec<Slot> = form.operands.iter().map(|&k| Table(k)).collect();
+                if form.modes.allows(mode) && !shapes.contains(&shape) {
+                    shapes.push(shape);
+                }
+            }
+            let hand = hand_encoded(mnemonic);
+            if hand != NDD || apx {
+                shapes.extend(hand.iter().map(|shape| shape.to_vec()));
+            }
+            for shape in shapes {
+                let pools: Vec<Vec<Operand>> = shape.iter().map(|&s| self.pool(s)).collect();
+                combine(&pools, |ops| {
+                    if ops
+                        .iter()
+                        .filter(|op| matches!(op, Operand::Mem(_)))
+                        .count()
+                        <= 1
+                    {
+                        out.extend(Instruction::from_operands(mnemonic, ops));
+                    }
+                });
+            }
+        }
+        out
+    }
+
+    /// Encodes `insn` at the checker's offset, decodes it and encodes the
+    /// result again.
+    ///
+    /// Returns the decoded instruction, or `None` if the encoder rejects
+    /// `insn`. Returns an error if the bytes do not decode, decode to a
+    /// different instruction or to fewer bytes, or if the decoded
+    /// instruction encodes to different bytes. Decoded memory operands may
+    /// carry a size `insn` left out, or leave out one that the instruction
+    /// ignores, such as on `prefetchnta`; decoded immediates and 16-bit
+    /// displacements may be the same bits read signed instead of unsigned or
+    /// the other way round.
+    pub fn check(&self, insn: &Instruction) -> RaskResult<Option<Decoded>> {
+        let Ok((buf, len)) = self.encoder.encode_to_array(insn) else {
+            return Ok(None);
+        };
+        let bytes = &buf[..len];
+        let decoded = match self.decode(bytes) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                return Err(mismatch(format!(
+                    "`{insn}` encodes to {bytes:02X?}, which does not decode: {err}"
+                )));
+            }
+        };
+        let back = decoded.instruction();
+        if decoded.len() != len || !self.agrees(insn, &decoded) {
+            return Err(mismatch(format!(
+                "`{insn}` encodes to {bytes:02X?}, which decodes to `{back}` ({} bytes)",
+                decoded.len()
+            )));
+        }
+        // Direct branches decode to addresses, which the encoder takes as
+        // labels only.
+        if decoded.branch_target().is_none() {
+            match self.encoder.encode_to_array(&back) {
+                Ok((again, n)) if again[..n] == *bytes => {}
+                Ok((again, n)) => {
+                    return Err(mismatch(format!(
+                        "`{insn}` encodes to {bytes:02X?}, which decodes to `{back}`, \
+                         which encodes to {:02X?}",
+                        &again[..n]
+                    )));
+                }
+                Err(err) => {
+                    return Err(mismatch(format!(
+                        "`{insn}` encodes to {bytes:02X?}, which decodes to `{back}`, \
+                         which does not encode: {err}"
+                    )));
+                }
+            }
+        }
+        Ok(Some(decoded))
+    }
+
+    /// Decodes `bytes` and checks every instruction the decoder accepts:
+    /// it must format in every syntax, and, unless it is a direct branch,
+    /// encode and pass [`RoundTrip::check`]. Bytes the decoder rejects are
+    /// skipped as the [`Decoder`] iterator skips them.
+    pub fn check_bytes(&self, bytes: &[u8]) -> RaskResult<()> {
+        let mut decoder = Decoder::with_mode(bytes, self.mode());
+        decoder.set_address(AT as u64);
+        for decoded in decoder.flatten() {
+            let insn = decoded.instruction();
+            for syntax in [Syntax::Intel, Syntax::Att, Syntax::Nasm] {
+                Formatter::new(syntax).display(&insn).to_string();
+            }
+            if decoded.branch_target().is_some() {
+                continue;
+            }
+            if let Err(err) = self.encoder.encode_to_array(&insn) {
+                return Err(mismatch(format!(
+                    "{:02X?} decodes to `{insn}`, which does not encode: {err}",
+                    decoded.bytes()
+                )));
+            }
+            self.check(&insn)?;
+        }
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> RaskResult<Decoded> {
+        let mut decoder = Decoder::with_mode(bytes, self.mode());
+        decoder.set_address(AT as u64);
+        decoder.decode()
+    }
+
+    /// Returns true if `decoded` is the instruction `insn` asked for.
+    fn agrees(&self, insn: &Instruction, decoded: &Decoded) -> bool {
+        let back = decoded.instruction();
+        insn.mnemonic() == back.mnemonic()
+            && insn.prefix() == back.prefix()
+            && insn.operand_count() == back.operand_count()
+            && insn
+                .operands()
+                .iter()
+                .zip(back.operands())
+                .all(|(&asked, &got)| match (asked, got) {
+                    (Operand::Mem(a), Operand::Mem(b)) => {
+                        let (a, b) = (self.canonical(a), self.canonical(b));
+                        let either_unsized = a.size.is_none() || b.size.is_none();
+                        a == b || either_unsized && MemOperand { size: b.size, ..a } == b
+                    }
+                    (Operand::Imm(a), Operand::Imm(b)) => same_bits(a, b),
+                    (Operand::Label(label), Operand::Imm(target)) => {
+                        let bound = self.encoder.label_offset(label).map(|at| at as u64);
+                        target as u64 == bound.unwrap_or(decoded.end())
+                    }
+                    (a, b) => a == b,
+                })
+    }
+
+    /// Returns `mem` in one form of the 16-bit addresses that have several.
+    /// They wrap at 64 KiB, so `[bp - 0x80]` and `[bp + 0xff80]` are the same
+    /// operand, and base and index are interchangeable, so `[di + bp]` is
+    /// `[bp + di]`.
+    fn canonical(&self, mut mem: MemOperand) -> MemOperand {
+        let bits = mem
+            .address_bits()
+            .unwrap_or(self.mode().default_address_bits());
+        if bits != 16 {
+            return mem;
+        }
+        if i16::try_from(mem.disp).is_ok() || u16::try_from(mem.disp).is_ok() {
+            mem.disp = i32::from(mem.disp as u16);
+        }
+        if let (Some(base @ AddrReg::R16(Reg16::SI | Reg16::DI)), Some((index, Scale::S1))) =
+            (mem.base, mem.index)
+        {
+            mem.base = Some(index);
+            mem.index = Some((base, Scale::S1));
+        }
+        mem
+    }
+
+    /// Returns the operands a position of `slot` is tried with.
+    fn pool(&self, slot: Slot) -> Vec<Operand> {
+        let mode = self.mode();
+        let gprs = gpr_count(mode, self.encoder.features());
+        let vectors = if mode == Mode::Long64 { 32 } else { 8 };
+        let memory = |size: Option<MemSize>| {
+            self.memory.iter().map(move |&m| match size {
+                Some(size) => Operand::Mem(m.with_size(size)),
+                None => Operand::Mem(m),
+            })
+        };
+        let mut pool = Vec::new();
+        match slot {
+            Table(kind @ (K::Reg8 | K::Rm8)) => {
+                pool.extend(gpr8(gprs).map(Operand::Reg8));
+                if kind == K::Rm8 {
+                    pool.extend(memory(Some(MemSize::Byte)));
+                }
+            }
+            Table(kind @ (K::Reg16 | K::Rm16)) => {
+                let regs = Reg16::ALL.iter().filter(|r| r.id() < gprs);
+                pool.extend(regs.map(|&r| Operand::Reg16(r)));
+                if kind == K::Rm16 {
+                    pool.extend(memory(Some(MemSize::Word)));
+                }
+            }
+            Table(kind @ (K::Reg32 | K::Rm32)) => {
+                let regs = Reg32::ALL.iter().filter(|r| r.id() < gprs);
+                pool.extend(regs.map(|&r| Operand::Reg32(r)));
+                if kind == K::Rm32 {
+                    pool.extend(memory(Some(MemSize::Dword)));
+                }
+            }
+            Table(kind @ (K::Reg64 | K::Rm64)) => {
+                if mode == Mode::Long64 {
+                    let regs = Reg64::ALL.iter().filter(|r| r.id() < gprs);
+                    pool.extend(regs.map(|&r| Operand::Reg(r)));
+                }
+                // Indirect branches outside 64-bit mode take `r/m16` and
+                // `r/m32` instead.
+                if kind == K::Rm64 {
+                    pool.extend(memory(Some(MemSize::Qword)));
+                }
+            }
+            Table(K::Mem) => pool.extend(memory(None)),
+            Table(K::Al) => pool.push(Operand::Reg8(Reg8::AL)),
+            Table(K::Ax) => pool.push(Operand::Reg16(Reg16::AX)),
+            Table(K::Eax) => pool.push(Operand::Reg32(Reg32::EAX)),
+            Table(K::Rax) => pool.push(Operand::Reg(Reg64::RAX)),
+            Table(K::Xmm) | Vector(128) | VectorMem(128) => {
+                let regs = XmmReg::ALL.iter().filter(|r| r.id() < vectors);
+                pool.extend(regs.map(|&r| Operand::Xmm(r)));
+            }
+            Table(K::XmmM128) => {
+                let regs = XmmReg::ALL.iter().filter(|r| r.id() < vectors);
+                pool.extend(regs.map(|&r| Operand::Xmm(r)));
+                pool.extend(memory(None));
+            }
+            Vector(256) | VectorMem(256) => {
+                let regs = YmmReg::ALL.iter().filter(|r| r.id() < vectors);
+                pool.extend(regs.map(|&r| Operand::Ymm(r)));
+            }
+            Vector(_) | VectorMem(_) => {
+                let regs = ZmmReg::ALL.iter().filter(|r| r.id() < vectors);
+                pool.extend(regs.map(|&r| Operand::Zmm(r)));
+            }
+            Tmm => pool.extend(TmmReg::ALL.iter().map(|&r| Operand::Tmm(r))),
+            Slot::Target => pool.extend(self.targets.iter().map(|&l| Operand::Label(l))),
+            Table(kind) => pool.extend(immediates(kind).iter().map(|&v| Operand::Imm(v))),
+        }
+        if let VectorMem(_) = slot {
+            pool.extend(memory(None));
+        }
+        pool
+    }
+}
+
+/// Returns the number of general-purpose registers `mode` can address.
+fn gpr_count(mode: Mode, features: CpuFeatures) -> u8 {
+    match mode {
+        Mode::Long64 if features.contains(CpuFeature::Apx) => 32,
+        Mode::Long64 => 16,
+        _ => 8,
+    }
+}
+
+/// Returns the 8-bit registers among the first `count`: AH–BH in every
+/// mode, SPL–DIL only where REX exists.
+fn gpr8(count: u8) -> impl Iterator<Item = Reg8> {
+    Reg8::ALL
+        .iter()
+        .copied()
+        .filter(move |r| r.id() < count && (count > 8 || !r.requires_rex()))
+}
+
+/// Returns the memory operands every `r/m` position is tried with.
+///
+/// Every base register is combined with every displacement in
+/// [`DISPLACEMENTS`], which covers the bases that need a SIB byte (RSP,
+/// R12) or a displacement even when it is zero (RBP, R13). Every index
+/// register appears with every scale, next to a base that cycles through
+/// the registers, and without a base. In 64-bit mode there are also
+/// RIP-relative operands and 32-bit addresses; outside it, 16-bit
+/// addresses. One operand carries each segment override.
+fn memory_operands(mode: Mode, gprs: u8) -> Vec<MemOperand> {
+    let scales = [Scale::S1, Scale::S2, Scale::S4, Scale::S8];
+    let mut out = Vec::new();
+
+    let regs: Vec<AddrReg> = match mode {
+        Mode::Long64 => Reg64::ALL[..usize::from(gprs)]
+            .iter()
+            .map(|&r| r.into())
+            .collect(),
+        _ => Reg32::ALL[..8].iter().map(|&r| r.into()).collect(),
+    };
+    for &base in &regs {
+        out.extend(DISPLACEMENTS.map(|disp| MemOperand::new(base, disp)));
+    }
+    // RSP cannot be an index.
+    let indexes = regs.iter().filter(|r| r.id() != 4);
+    for (i, &index) in indexes.enumerate() {
+        for (j, &scale) in scales.iter().enumerate() {
+            let base = regs[(i * scales.len() + j) % regs.len()];
+            let disp = DISPLACEMENTS[j % DISPLACEMENTS.len()];
+            out.push(MemOperand::new(base, disp).with_index(index, scale));
+        }
+        out.push(MemOperand::absolute(0x1000).with_index(index, scales[i % 4]));
+    }
+    out.extend([MemOperand::absolute(0), MemOperand::absolute(0x1000)]);
+
+    if mode == Mode::Long64 {
+        out.extend(DISPLACEMENTS.map(MemOperand::rip));
+        for &base in &Reg32::ALL[..usize::from(gprs)] {
+            out.push(MemOperand::new(base, 8));
+        }
+    } else {
+        use Reg16::{BP, BX, DI, SI};
+        for base in [BX, BP, SI, DI] {
+            out.extend(DISPLACEMENTS.map(|disp| MemOperand::new(base, disp)));
+        }
+        for (base, index) in [(BX, SI), (BX, DI), (BP, SI), (BP, DI)] {
+            out.extend(
+                DISPLACEMENTS.map(|disp| MemOperand::new(base, disp).with_index(index, Scale::S1)),
+            );
+        }
+    }
+
+    let base = regs[0];
+    for &segment in SegReg::ALL {
+        out.push(MemOperand::new(base, 0x10).with_segment(segment));
+    }
+    out
+}
+
+/// Calls `f` with every combination of one operand from each pool when
+/// there are at most two pools. With more, `f` gets every pair of operands
+/// from every two pools, while the other pools cycle through theirs.
+fn combine(pools: &[Vec<Operand>], mut f: impl FnMut(&[Operand])) {
+    if pools.iter().any(Vec::is_empty) {
+        return;
+    }
+    let mut ops: Vec<Operand> = pools.iter().map(|pool| pool[0]).collect();
+    match pools.len() {
+        0 => f(&ops),
+        1 => {
+            for &a in &pools[0] {
+                ops[0] = a;
+                f(&ops);
+            }
+        }
+        2 => {
+            for &a in &pools[0] {
+                for &b in &pools[1] {
+                    ops[0] = a;
+                    ops[1] = b;
+                    f(&ops);
+                }
+            }
+        }
+        n => {
+            let mut step = 0;
+            for i in 0..n {
+                for j in i + 1..n {
+                    for &a in &pools[i] {
+                        for &b in &pools[j] {
+                            for (k, pool) in pools.iter().enumerate() {
+                                ops[k] = pool[step % pool.len()];
+                            }
+                            ops[i] = a;
+                            ops[j] = b;
+                            f(&ops);
+                            step += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if `a` and `b` are the same immediate, possibly one read
+/// signed and the other unsigned at the same width.
+fn same_bits(a: i64, b: i64) -> bool {
+    a == b
+        || [8, 16, 32].into_iter().any(|bits| {
+            let fits = |v: i64| v >= -(1 << (bits - 1)) && v < 1 << bits;
+            let mask = (1i64 << bits) - 1;
+            fits(a) && fits(b) && a & mask == b & mask
+        })
+}
+
+fn mismatch(reason: String) -> RaskError {
+    RaskError::Other(reason)
+}
@@ -0,0 +1,224 @@
+//! Destinations for encoded bytes.
+//!
+//! An [`Encoder`](crate::encoder::Encoder) writes each instruction to a
+//! [`CodeSink`] once the instruction has been encoded completely. The
+//! default sink is a growable `Vec<u8>`; the others write into memory the
+//! caller already owns, stream to any [`std::io::Write`], or only count
+//! bytes:
+//!
+//! ```
+//! use rask_x86_64::encoder::Encoder;
+//! use rask_x86_64::registers::Reg64::{RAX, RBX};
+//! use rask_x86_64::sink::{CountingSink, SliceSink};
+//!
+//! // First pass: measure the function.
+//! let mut sizer = Encoder::with_sink(CountingSink::new());
+//! sizer.add(RAX, RBX)?;
+//! sizer.ret()?;
+//! let size = sizer.position();
+//!
+//! // Second pass: write it into memory of exactly that size.
+//! let mut memory = vec![0; size];
+//! let mut enc = Encoder::with_sink(SliceSink::new(&mut memory));
+//! enc.add(RAX, RBX)?;
+//! enc.ret()?;
+//! assert_eq!(memory, [0x48, 0x01, 0xD8, 0xC3]);
+//! # Ok::<(), rask_x86_64::RaskError>(())
+//! ```
+
+use rask_common::{RaskError, RaskResult};
+use std::{io, ops::Range};
+
+/// A destination for machine code.
+pub trait CodeSink {
+    /// Appends `bytes`, which hold one complete instruction (or a run of
+    /// padding or raw data).
+    ///
+    /// Returns an error if the bytes cannot be written. A sink with a fixed
+    /// capacity reports [`RaskError::BufferOverflow`] and writes nothing.
+    fn put(&mut self, bytes: &[u8]) -> RaskResult<()>;
+
+    /// Returns the number of bytes written so far.
+    fn position(&self) -> usize;
+
+    /// Overwrites bytes written earlier, starting at `offset`. The encoder
+    /// uses this to fill in branches to a label once the label is bound.
+    /// Fails with [`RaskError::BufferOverflow`] if the bytes reach past
+    /// [`position`](Self::position).
+    ///
+    /// The default implementation fails: a sink that cannot revisit its
+    /// output only supports branches to labels that are already bound.
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> RaskResult<()> {
+        let _ = (offset, bytes);
+        Err(RaskError::Other(
+            "this sink cannot patch code it has already written".to_string(),
+        ))
+    }
+}
+
+/// Returns the range `patch` overwrites, or fails unless all of it has been
+/// written.
+fn patch_range(offset: usize, len: usize, written: usize) -> RaskResult<Range<usize>> {
+    match offset.checked_add(len) {
+        Some(end) if end <= written => Ok(offset..end),
+        _ => Err(RaskError::BufferOverflow {
+            needed: len,
+            available: written.saturating_sub(offset),
+        }),
+    }
+}
+
+impl CodeSink for Vec<u8> {
+    #[inline]
+    fn put(&mut self, bytes: &[u8]) -> RaskResult<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> RaskResult<()> {
+        let range = patch_range(offset, bytes.len(), self.len())?;
+        self[range].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Writes into a caller-provided slice, such as a mapped page of executable
+/// memory.
+///
+/// An instruction that does not fit in the space left fails with
+/// [`RaskError::BufferOverflow`] and is not written at all.
+#[derive(Debug)]
+pub struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    /// Creates a sink that writes from the start of `buf`.
+    #[inline]
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Returns the bytes written so far.
+    #[inline]
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.pos]
+    }
+
+    /// Returns the number of bytes that still fit.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+impl CodeSink for SliceSink<'_> {
+    fn put(&mut self, bytes: &[u8]) -> RaskResult<()> {
+        let available = self.remaining();
+        if bytes.len() > available {
+            return Err(RaskError::BufferOverflow {
+                needed: bytes.len(),
+                available,
+            });
+        }
+        self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+        Ok(())
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> RaskResult<()> {
+        let range = patch_range(offset, bytes.len(), self.pos)?;
+        self.buf[range].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Streams to any [`io::Write`], such as a file or a socket.
+///
+/// I/O errors are returned as [`RaskError::Io`]. The writer may have
+/// received part of the instruction that failed. Bytes cannot be patched
+/// once written, so branches may only target labels that are already bound.
+#[derive(Debug)]
+pub struct WriteSink<W> {
+    inner: W,
+    pos: usize,
+}
+
+impl<W: io::Write> WriteSink<W> {
+    /// Creates a sink that writes to `inner`.
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    /// Returns a reference to the writer.
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> CodeSink for WriteSink<W> {
+    fn put(&mut self, bytes: &[u8]) -> RaskResult<()> {
+        self.inner.write_all(bytes)?;
+        self.pos += bytes.len();
+        Ok(())
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+/// Discards the bytes and only counts them, to size code before memory is
+/// allocated for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CountingSink {
+    len: usize,
+}
+
+impl CountingSink {
+    /// Creates a sink that has counted nothing yet.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { len: 0 }
+    }
+}
+
+impl CodeSink for CountingSink {
+    #[inline]
+    fn put(&mut self, bytes: &[u8]) -> RaskResult<()> {
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> RaskResult<()> {
+        patch_range(offset, bytes.len(), self.len).map(|_| ())
+    }
+}
@@ -0,0 +1,619 @@
+//! Table-driven instruction definitions.
+//!
+//! Every form of an instruction is one [`InstrDef`], written the way the
+//! opcode column of the Intel SDM reads:
+//!
+//! ```text
+//! REX.W + 81 /0 id     ADD r/m64, imm32
+//! def("add", &[Rm64, SImm32], &[0x81], Digit(0)).o64()
+//! ```
+//!
+//! [`Encoder::emit_instruction`](crate::encoder::Encoder::emit_instruction)
+//! looks a mnemonic up with [`forms`], picks the first form whose operands
+//! match and emits it, so a new instruction is added here as data rather
+//! than as hand-written prefix and ModR/M logic. Forms are listed in order
+//! of preference: the short sign-extended `imm8` forms come before `imm32`,
+//! `r/m, reg` comes before `reg, r/m`.
+//!
+//! The table covers the legacy encoding space: optional operand-size and
+//! mandatory prefixes, REX/REX2, up to three opcode bytes and ModR/M. VEX,
+//! EVEX and APX map-4 instructions are still encoded by hand.
+//!
+//! Forms do not record the CPUID extension they belong to, such as AES-NI
+//! for `aesenc`, and nothing checks one: as [`features`](crate::features)
+//! explains, whether the CPU running the code supports an instruction is
+//! the caller's concern.
+
+use crate::{
+    mode::Mode,
+    operand::Operand,
+    registers::{Reg8, Reg16, Reg32, Reg64},
+};
+use ModRm::{Digit, PlusR, R};
+use OperandKind::*;
+
+/// The kind of operand an instruction form accepts at one position, named
+/// after the SDM's operand notation (`r32`, `r/m64`, `imm8`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperandKind {
+    /// An 8-bit general-purpose register (`r8`).
+    Reg8,
+    /// A 16-bit general-purpose register (`r16`).
+    Reg16,
+    /// A 32-bit general-purpose register (`r32`).
+    Reg32,
+    /// A 64-bit general-purpose register (`r64`).
+    Reg64,
+    /// An 8-bit register or a byte-sized memory operand (`r/m8`).
+    Rm8,
+    /// A 16-bit register or a word-sized memory operand (`r/m16`).
+    Rm16,
+    /// A 32-bit register or a dword-sized memory operand (`r/m32`).
+    Rm32,
+    /// A 64-bit register or a qword-sized memory operand (`r/m64`).
+    Rm64,
+    /// A memory operand whose width is implied by the instruction (`m`,
+    /// `m8`, `m128`, `m16:32`, ...).
+    Mem,
+    /// The accumulator AL, implied by the opcode (`AL`).
+    Al,
+    /// The accumulator AX, implied by the opcode (`AX`).
+    Ax,
+    /// The accumulator EAX, implied by the opcode (`EAX`).
+    Eax,
+    /// The accumulator RAX, implied by the opcode (`RAX`).
+    Rax,
+    /// An XMM register (`xmm`).
+    Xmm,
+    /// An XMM register or a memory operand (`xmm/m128`).
+    XmmM128,
+    /// An 8-bit immediate, given signed or unsigned (`imm8`).
+    Imm8,
+    /// A 16-bit immediate, given signed or unsigned (`imm16`).
+    Imm16,
+    /// A 32-bit immediate, given signed or unsigned (`imm32`).
+    Imm32,
+    /// A 64-bit immediate (`imm64`).
+    Imm64,
+    /// An 8-bit immediate the CPU sign-extends to the operand size.
+    SImm8,
+    /// A 32-bit immediate the CPU sign-extends to 64 bits.
+    SImm32,
+}
+
+impl OperandKind {
+    /// Returns true if `op` can be used for an operand of this kind.
+    ///
+    /// A memory operand without an explicit size matches every `r/m` width;
+    /// the encoder rejects it when that leaves more than one form.
+    pub fn matches(self, op: &Operand) -> bool {
+        let mem_size = |bits: u32| match op {
+            Operand::Mem(m) => m.size.is_none_or(|s| s.bits() == bits),
+            _ => false,
+        };
+        match self {
+            Reg8 => matches!(op, Operand::Reg8(_)),
+            Reg16 => matches!(op, Operand::Reg16(_)),
+            Reg32 => matches!(op, Operand::Reg32(_)),
+            Reg64 => matches!(op, Operand::Reg(_)),
+            Rm8 => matches!(op, Operand::Reg8(_)) || mem_size(8),
+            Rm16 => matches!(op, Operand::Reg16(_)) || mem_size(16),
+            Rm32 => matches!(op, Operand::Reg32(_)) || mem_size(32),
+            Rm64 => matches!(op, Operand::Reg(_)) || mem_size(64),
+            Mem => matches!(op, Operand::Mem(_)),
+            Al => *op == Operand::Reg8(Reg8::AL),
+            Ax => *op == Operand::Reg16(Reg16::AX),
+            Eax => *op == Operand::Reg32(Reg32::EAX),
+            Rax => *op == Operand::Reg(Reg64::RAX),
+            Xmm => matches!(op, Operand::Xmm(_)),
+            XmmM128 => matches!(op, Operand::Xmm(_) | Operand::Mem(_)),
+            _ => match (self.imm_range(), op) {
+                (Some((min, max)), Operand::Imm(v)) => (min..=max).contains(v),
+                _ => false,
+            },
+        }
+    }
+
+    /// Returns the encoded width in bits of an immediate kind.
+    pub fn imm_bits(self) -> Option<u32> {
+        match self {
+            Imm8 | SImm8 => Some(8),
+            Imm16 => Some(16),
+            Imm32 | SImm32 => Some(32),
+            Imm64 => Some(64),
+            _ => None,
+        }
+    }
+
+    /// Returns the range of values an immediate kind accepts.
+    fn imm_range(self) -> Option<(i64, i64)> {
+        match self {
+            SImm8 => Some((i8::MIN.into(), i8::MAX.into())),
+            SImm32 => Some((i32::MIN.into(), i32::MAX.into())),
+            Imm64 => Some((i64::MIN, i64::MAX)),
+            _ => self
+                .imm_bits()
+                .map(|bits| (-(1i64 << (bits - 1)), (1i64 << bits) - 1)),
+        }
+    }
+
+    /// Returns true for kinds encoded in the ModR/M `reg` field or, for
+    /// `+r` forms, in the opcode byte.
+    pub fn is_reg(self) -> bool {
+        matches!(self, Reg8 | Reg16 | Reg32 | Reg64 | Xmm)
+    }
+
+    /// Returns true for kinds encoded in the ModR/M `r/m` field.
+    pub fn is_rm(self) -> bool {
+        matches!(self, Rm8 | Rm16 | Rm32 | Rm64 | Mem | XmmM128)
+    }
+}
+
+/// How the ModR/M byte is used, the SDM's `/r`, `/digit` and `+r` notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModRm {
+    /// No ModR/M byte.
+    None,
+    /// `/r`: the register operand goes in `reg`, the `r/m` operand in `r/m`.
+    R,
+    /// `/digit`: `reg` holds an opcode extension, the operand goes in `r/m`.
+    Digit(u8),
+    /// `+r`: no ModR/M byte; the register is added to the last opcode byte.
+    PlusR,
+}
+
+/// The operand size an instruction form selects with the `66` prefix or
+/// `REX.W`. The prefix is emitted relative to the mode's default; see
+/// [`Mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpSize {
+    /// The instruction has no operand-size variants (byte operations, SSE
+    /// instructions, and instructions that ignore the `66` prefix).
+    None,
+    /// 16-bit operand size.
+    O16,
+    /// 32-bit operand size.
+    O32,
+    /// 64-bit operand size (`REX.W`), only available in 64-bit mode.
+    O64,
+}
+
+impl OpSize {
+    /// Returns the operand size in bits, or `None` for [`OpSize::None`].
+    pub fn bits(self) -> Option<u32> {
+        match self {
+            OpSize::None => None,
+            OpSize::O16 => Some(16),
+            OpSize::O32 => Some(32),
+            OpSize::O64 => Some(64),
+        }
+    }
+}
+
+/// The processor modes an instruction form is valid in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modes {
+    /// Every mode.
+    All,
+    /// 64-bit mode only.
+    Long64,
+    /// 16- and 32-bit modes only: the opcode was reassigned or removed in
+    /// 64-bit mode.
+    Legacy,
+}
+
+impl Modes {
+    /// Returns true if the form can be encoded in `mode`.
+    pub fn allows(self, mode: Mode) -> bool {
+        match self {
+            Modes::All => true,
+            Modes::Long64 => mode == Mode::Long64,
+            Modes::Legacy => mode != Mode::Long64,
+        }
+    }
+}
+
+/// One encoding form of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstrDef {
+    /// Lower-case Intel mnemonic.
+    pub mnemonic: &'static str,
+    /// Operand kinds in Intel order (destination first).
+    pub operands: &'static [OperandKind],
+    /// Mandatory prefix (`66`, `F2` or `F3`), emitted after any
+    /// operand-size prefix.
+    pub prefix: Option<u8>,
+    /// Operand size selected by `66` or `REX.W`.
+    pub size: OpSize,
+    /// Opcode bytes, including any `0F`, `0F 38` or `0F 3A` escape.
+    pub opcode: &'static [u8],
+    /// ModR/M usage.
+    pub modrm: ModRm,
+    /// Processor modes the form is valid in.
+    pub modes: Modes,
+}
+
+impl InstrDef {
+    /// Returns true if `operands` match this form's operand kinds.
+    pub fn matches(&self, operands: &[Operand]) -> bool {
+        self.operands.len() == operands.len()
+            && self
+                .operands
+                .iter()
+                .zip(operands)
+                .all(|(k, op)| k.matches(op))
+    }
+
+    const fn o16(self) -> Self {
+        Self {
+            size: OpSize::O16,
+            ..self
+        }
+    }
+
+    const fn o32(self) -> Self {
+        Self {
+            size: OpSize::O32,
+            ..self
+        }
+    }
+
+    const fn o64(self) -> Self {
+        Self {
+            size: OpSize::O64,
+            ..self
+        }
+    }
+
+    const fn prefix(self, prefix: u8) -> Self {
+        Self {
+            prefix: Some(prefix),
+            ..self
+        }
+    }
+
+    const fn modes(self, modes: Modes) -> Self {
+        Self { modes, ..self }
+    }
+}
+
+/// A form with no prefixes, valid in every mode.
+const fn def(
+    mnemonic: &'static str,
+    operands: &'static [OperandKind],
+    opcode: &'static [u8],
+    modrm: ModRm,
+) -> InstrDef {
+    InstrDef {
+        mnemonic,
+        operands,
+        prefix: None,
+        size: OpSize::None,
+        opcode,
+        modrm,
+        modes: Modes::All,
+    }
+}
+
+/// Returns the forms of `mnemonic` (case-insensitive) in order of
+/// preference, or `None` if the table does not define it.
+///
+/// ```
+/// use rask_x86_64::table;
+///
+/// let forms = table::forms("ADD").unwrap();
+/// assert!(forms.iter().all(|f| f.mnemonic == "add"));
+/// assert!(table::forms("frobnicate").is_none());
+/// ```
+pub fn forms(mnemonic: &str) -> Option<&'static [InstrDef]> {
+    INSTRUCTIONS
+        .iter()
+        .copied()
+        .find(|forms| forms[0].mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+/// Every defined instruction, one slice of forms per mnemonic.
+#[rustfmt::skip]
+pub static INSTRUCTIONS: &[&[InstrDef]] = &[
+    &ADD, &OR, &ADC, &SBB, &AND, &SUB, &XOR, &CMP, &MOV, &INC, &DEC, &RET,
+    &NOP, &INT3,
+    &JMP, &CALL,
+    &PUSHA, &PUSHAD, &POPA, &POPAD, &LES, &LDS,
+    &RDFSBASE, &RDGSBASE, &WRFSBASE, &WRGSBASE,
+    &PREFETCHT0, &PREFETCHT1, &PREFETCHT2, &PREFETCHNTA, &PREFETCHW,
+    &CLFLUSH, &CLFLUSHOPT, &CLWB, &SFENCE, &LFENCE, &MFENCE,
+    &MOVNTI, &MOVNTDQ, &MOVNTPS, &MOVNTPD, &MOVNTDQA,
+    &AESENC, &AESENCLAST, &AESDEC, &AESDECLAST, &AESIMC, &AESKEYGENASSIST,
+    &SHA1RNDS4, &SHA1NEXTE, &SHA1MSG1, &SHA1MSG2, &SHA256RNDS2, &SHA256MSG1, &SHA256MSG2,
+    &PCLMULQDQ, &CRC32, &GF2P8AFFINEINVQB, &GF2P8AFFINEQB, &GF2P8MULB,
+];
+
+// -----------------------------------------------------------------------------
+// Integer arithmetic and moves
+// -----------------------------------------------------------------------------
+
+/// The classic ALU block: `op r/m, reg` at `digit * 8 + 1`, `op reg, r/m` at
+/// `digit * 8 + 3`, the accumulator forms at `digit * 8 + 4`/`+ 5`, and the
+/// immediate group `80`/`81`/`83 /digit`. The sign-extended `imm8` forms
+/// are the shortest, then the accumulator forms, which save the ModR/M byte.
+macro_rules! alu {
+    ($name:ident, $mnemonic:literal, $digit:literal) => {
+        const $name: [InstrDef; 19] = [
+            def($mnemonic, &[Al, Imm8], &[$digit * 8 + 4], ModRm::None),
+            def($mnemonic, &[Rm8, Imm8], &[0x80], Digit($digit)),
+            def($mnemonic, &[Rm16, SImm8], &[0x83], Digit($digit)).o16(),
+            def($mnemonic, &[Rm32, SImm8], &[0x83], Digit($digit)).o32(),
+            def($mnemonic, &[Rm64, SImm8], &[0x83], Digit($digit)).o64(),
+            def($mnemonic, &[Ax, Imm16], &[$digit * 8 + 5], ModRm::None).o16(),
+            def($mnemonic, &[Eax, Imm32], &[$digit * 8 + 5], ModRm::None).o32(),
+            def($mnemonic, &[Rax, SImm32], &[$digit * 8 + 5], ModRm::None).o64(),
+            def($mnemonic, &[Rm16, Imm16], &[0x81], Digit($digit)).o16(),
+            def($mnemonic, &[Rm32, Imm32], &[0x81], Digit($digit)).o32(),
+            def($mnemonic, &[Rm64, SImm32], &[0x81], Digit($digit)).o64(),
+            def($mnemonic, &[Rm8, Reg8], &[$digit * 8], R),
+            def($mnemonic, &[Rm16, Reg16], &[$digit * 8 + 1], R).o16(),
+            def($mnemonic, &[Rm32, Reg32], &[$digit * 8 + 1], R).o32(),
+            def($mnemonic, &[Rm64, Reg64], &[$digit * 8 + 1], R).o64(),
+            def($mnemonic, &[Reg8, Rm8], &[$digit * 8 + 2], R),
+            def($mnemonic, &[Reg16, Rm16], &[$digit * 8 + 3], R).o16(),
+            def($mnemonic, &[Reg32, Rm32], &[$digit * 8 + 3], R).o32(),
+            def($mnemonic, &[Reg64, Rm64], &[$digit * 8 + 3], R).o64(),
+        ];
+    };
+}
+
+alu!(ADD, "add", 0);
+alu!(OR, "or", 1);
+alu!(ADC, "adc", 2);
+alu!(SBB, "sbb", 3);
+alu!(AND, "and", 4);
+alu!(SUB, "sub", 5);
+alu!(XOR, "xor", 6);
+alu!(CMP, "cmp", 7);
+
+/// `mov r64, imm` always uses the full `imm64` form.
+const MOV: [InstrDef; 16] = [
+    def("mov", &[Rm8, Reg8], &[0x88], R),
+    def("mov", &[Rm16, Reg16], &[0x89], R).o16(),
+    def("mov", &[Rm32, Reg32], &[0x89], R).o32(),
+    def("mov", &[Rm64, Reg64], &[0x89], R).o64(),
+    def("mov", &[Reg8, Rm8], &[0x8A], R),
+    def("mov", &[Reg16, Rm16], &[0x8B], R).o16(),
+    def("mov", &[Reg32, Rm32], &[0x8B], R).o32(),
+    def("mov", &[Reg64, Rm64], &[0x8B], R).o64(),
+    def("mov", &[Reg8, Imm8], &[0xB0], PlusR),
+    def("mov", &[Reg16, Imm16], &[0xB8], PlusR).o16(),
+    def("mov", &[Reg32, Imm32], &[0xB8], PlusR).o32(),
+    def("mov", &[Reg64, Imm64], &[0xB8], PlusR).o64(),
+    def("mov", &[Rm8, Imm8], &[0xC6], Digit(0)),
+    def("mov", &[Rm16, Imm16], &[0xC7], Digit(0)).o16(),
+    def("mov", &[Rm32, Imm32], &[0xC7], Digit(0)).o32(),
+    def("mov", &[Rm64, SImm32], &[0xC7], Digit(0)).o64(),
+];
+
+/// The one-byte `40+r` form comes first where it exists; in 64-bit mode
+/// those bytes are REX prefixes.
+const INC: [InstrDef; 6] = [
+    def("inc", &[Reg16], &[0x40], PlusR)
+        .o16()
+        .modes(Modes::Legacy),
+    def("inc", &[Reg32], &[0x40], PlusR)
+        .o32()
+        .modes(Modes::Legacy),
+    def("inc", &[Rm8], &[0xFE], Digit(0)),
+    def("inc", &[Rm16], &[0xFF], Digit(0)).o16(),
+    def("inc", &[Rm32], &[0xFF], Digit(0)).o32(),
+    def("inc", &[Rm64], &[0xFF], Digit(0)).o64(),
+];
+
+const DEC: [InstrDef; 6] = [
+    def("dec", &[Reg16], &[0x48], PlusR)
+        .o16()
+        .modes(Modes::Legacy),
+    def("dec", &[Reg32], &[0x48], PlusR)
+        .o32()
+        .modes(Modes::Legacy),
+    def("dec", &[Rm8], &[0xFE], Digit(1)),
+    def("dec", &[Rm16], &[0xFF], Digit(1)).o16(),
+    def("dec", &[Rm32], &[0xFF], Digit(1)).o32(),
+    def("dec", &[Rm64], &[0xFF], Digit(1)).o64(),
+];
+
+const RET: [InstrDef; 1] = [def("ret", &[], &[0xC3], ModRm::None)];
+
+/// `0F 1F /0` is the multi-byte NOP that `Encoder::nop_n` pads with; its
+/// memory operand only lengthens the instruction and is never accessed.
+const NOP: [InstrDef; 3] = [
+    def("nop", &[], &[0x90], ModRm::None),
+    def("nop", &[Rm16], &[0x0F, 0x1F], Digit(0)).o16(),
+    def("nop", &[Rm32], &[0x0F, 0x1F], Digit(0)).o32(),
+];
+
+/// The breakpoint trap, also used to fill unreachable padding.
+const INT3: [InstrDef; 1] = [def("int3", &[], &[0xCC], ModRm::None)];
+
+/// The indirect near branches take a target of the address width: `r/m64`
+/// in 64-bit mode, which needs no `REX.W`. Direct branches to a
+/// [`Label`](crate::operand::Label) are encoded by `Encoder::jmp` and
+/// `Encoder::call` themselves.
+const JMP: [InstrDef; 3] = [
+    def("jmp", &[Rm64], &[0xFF], Digit(4)).modes(Modes::Long64),
+    def("jmp", &[Rm32], &[0xFF], Digit(4))
+        .o32()
+        .modes(Modes::Legacy),
+    def("jmp", &[Rm16], &[0xFF], Digit(4))
+        .o16()
+        .modes(Modes::Legacy),
+];
+
+const CALL: [InstrDef; 3] = [
+    def("call", &[Rm64], &[0xFF], Digit(2)).modes(Modes::Long64),
+    def("call", &[Rm32], &[0xFF], Digit(2))
+        .o32()
+        .modes(Modes::Legacy),
+    def("call", &[Rm16], &[0xFF], Digit(2))
+        .o16()
+        .modes(Modes::Legacy),
+];
+
+// -----------------------------------------------------------------------------
+// Legacy-mode instructions
+// -----------------------------------------------------------------------------
+
+const PUSHA: [InstrDef; 1] = [def("pusha", &[], &[0x60], ModRm::None)
+    .o16()
+    .modes(Modes::Legacy)];
+const PUSHAD: [InstrDef; 1] = [def("pushad", &[], &[0x60], ModRm::None)
+    .o32()
+    .modes(Modes::Legacy)];
+const POPA: [InstrDef; 1] = [def("popa", &[], &[0x61], ModRm::None)
+    .o16()
+    .modes(Modes::Legacy)];
+const POPAD: [InstrDef; 1] = [def("popad", &[], &[0x61], ModRm::None)
+    .o32()
+    .modes(Modes::Legacy)];
+
+const LES: [InstrDef; 2] = [
+    def("les", &[Reg16, Mem], &[0xC4], R)
+        .o16()
+        .modes(Modes::Legacy),
+    def("les", &[Reg32, Mem], &[0xC4], R)
+        .o32()
+        .modes(Modes::Legacy),
+];
+
+const LDS: [InstrDef; 2] = [
+    def("lds", &[Reg16, Mem], &[0xC5], R)
+        .o16()
+        .modes(Modes::Legacy),
+    def("lds", &[Reg32, Mem], &[0xC5], R)
+        .o32()
+        .modes(Modes::Legacy),
+];
+
+// -----------------------------------------------------------------------------
+// FSGSBASE
+// -----------------------------------------------------------------------------
+
+macro_rules! fsgsbase {
+    ($name:ident, $mnemonic:literal, $digit:literal) => {
+        const $name: [InstrDef; 2] = [
+            def($mnemonic, &[Reg32], &[0x0F, 0xAE], Digit($digit))
+                .prefix(0xF3)
+                .modes(Modes::Long64),
+            def($mnemonic, &[Reg64], &[0x0F, 0xAE], Digit($digit))
+                .prefix(0xF3)
+                .o64()
+                .modes(Modes::Long64),
+        ];
+    };
+}
+
+fsgsbase!(RDFSBASE, "rdfsbase", 0);
+fsgsbase!(RDGSBASE, "rdgsbase", 1);
+fsgsbase!(WRFSBASE, "wrfsbase", 2);
+fsgsbase!(WRGSBASE, "wrgsbase", 3);
+
+// -----------------------------------------------------------------------------
+// Cache control and non-temporal moves
+// -----------------------------------------------------------------------------
+
+const PREFETCHT0: [InstrDef; 1] = [def("prefetcht0", &[Mem], &[0x0F, 0x18], Digit(1))];
+const PREFETCHT1: [InstrDef; 1] = [def("prefetcht1", &[Mem], &[0x0F, 0x18], Digit(2))];
+const PREFETCHT2: [InstrDef; 1] = [def("prefetcht2", &[Mem], &[0x0F, 0x18], Digit(3))];
+const PREFETCHNTA: [InstrDef; 1] = [def("prefetchnta", &[Mem], &[0x0F, 0x18], Digit(0))];
+const PREFETCHW: [InstrDef; 1] = [def("prefetchw", &[Mem], &[0x0F, 0x0D], Digit(1))];
+const CLFLUSH: [InstrDef; 1] = [def("clflush", &[Mem], &[0x0F, 0xAE], Digit(7))];
+const CLFLUSHOPT: [InstrDef; 1] = [def("clflushopt", &[Mem], &[0x0F, 0xAE], Digit(7)).prefix(0x66)];
+const CLWB: [InstrDef; 1] = [def("clwb", &[Mem], &[0x0F, 0xAE], Digit(6)).prefix(0x66)];
+
+// The fences are `0F AE /digit` with `mod = 11`, so their ModR/M byte is a
+// constant and listed as part of the opcode.
+const SFENCE: [InstrDef; 1] = [def("sfence", &[], &[0x0F, 0xAE, 0xF8], ModRm::None)];
+const LFENCE: [InstrDef; 1] = [def("lfence", &[], &[0x0F, 0xAE, 0xE8], ModRm::None)];
+const MFENCE: [InstrDef; 1] = [def("mfence", &[], &[0x0F, 0xAE, 0xF0], ModRm::None)];
+
+/// `MOVNTI` has no `66` form, so the 32-bit form carries no operand size.
+const MOVNTI: [InstrDef; 2] = [
+    def("movnti", &[Mem, Reg32], &[0x0F, 0xC3], R),
+    def("movnti", &[Mem, Reg64], &[0x0F, 0xC3], R).o64(),
+];
+
+const MOVNTDQ: [InstrDef; 1] = [def("movntdq", &[Mem, Xmm], &[0x0F, 0xE7], R).prefix(0x66)];
+const MOVNTPS: [InstrDef; 1] = [def("movntps", &[Mem, Xmm], &[0x0F, 0x2B], R)];
+const MOVNTPD: [InstrDef; 1] = [def("movntpd", &[Mem, Xmm], &[0x0F, 0x2B], R).prefix(0x66)];
+const MOVNTDQA: [InstrDef; 1] = [def("movntdqa", &[Xmm, Mem], &[0x0F, 0x38, 0x2A], R).prefix(0x66)];
+
+// -----------------------------------------------------------------------------
+// Cryptography and checksums
+// -----------------------------------------------------------------------------
+
+/// A legacy SSE form `op xmm, xmm/m128` with an optional mandatory prefix.
+macro_rules! sse {
+    ($name:ident, $mnemonic:literal, $prefix:expr, [$($op:literal),+]) => {
+        const $name: [InstrDef; 1] = [InstrDef {
+            prefix: $prefix,
+            ..def($mnemonic, &[Xmm, XmmM128], &[$($op),+], R)
+        }];
+    };
+    ($name:ident, $mnemonic:literal, $prefix:expr, [$($op:literal),+], ib) => {
+        const $name: [InstrDef; 1] = [InstrDef {
+            prefix: $prefix,
+            ..def($mnemonic, &[Xmm, XmmM128, Imm8], &[$($op),+], R)
+        }];
+    };
+}
+
+sse!(AESENC, "aesenc", Some(0x66), [0x0F, 0x38, 0xDC]);
+sse!(AESENCLAST, "aesenclast", Some(0x66), [0x0F, 0x38, 0xDD]);
+sse!(AESDEC, "aesdec", Some(0x66), [0x0F, 0x38, 0xDE]);
+sse!(AESDECLAST, "aesdeclast", Some(0x66), [0x0F, 0x38, 0xDF]);
+sse!(AESIMC, "aesimc", Some(0x66), [0x0F, 0x38, 0xDB]);
+sse!(
+    AESKEYGENASSIST,
+    "aeskeygenassist",
+    Some(0x66),
+    [0x0F, 0x3A, 0xDF],
+    ib
+);
+sse!(SHA1RNDS4, "sha1rnds4", None, [0x0F, 0x3A, 0xCC], ib);
+sse!(SHA1NEXTE, "sha1nexte", None, [0x0F, 0x38, 0xC8]);
+sse!(SHA1MSG1, "sha1msg1", None, [0x0F, 0x38, 0xC9]);
+sse!(SHA1MSG2, "sha1msg2", None, [0x0F, 0x38, 0xCA]);
+sse!(SHA256RNDS2, "sha256rnds2", None, [0x0F, 0x38, 0xCB]);
+sse!(SHA256MSG1, "sha256msg1", None, [0x0F, 0x38, 0xCC]);
+sse!(SHA256MSG2, "sha256msg2", None, [0x0F, 0x38, 0xCD]);
+sse!(PCLMULQDQ, "pclmulqdq", Some(0x66), [0x0F, 0x3A, 0x44], ib);
+sse!(
+    GF2P8AFFINEINVQB,
+    "gf2p8affineinvqb",
+    Some(0x66),
+    [0x0F, 0x3A, 0xCF],
+    ib
+);
+sse!(
+    GF2P8AFFINEQB,
+    "gf2p8affineqb",
+    Some(0x66),
+    [0x0F, 0x3A, 0xCE],
+    ib
+);
+sse!(GF2P8MULB, "gf2p8mulb", Some(0x66), [0x0F, 0x38, 0xCF]);
+
+/// The destination is always 32 or 64 bits wide; the operand size selects
+/// the width of the source (and is moot for a byte source).
+const CRC32: [InstrDef; 5] = [
+    def("crc32", &[Reg32, Rm8], &[0x0F, 0x38, 0xF0], R).prefix(0xF2),
+    def("crc32", &[Reg64, Rm8], &[0x0F, 0x38, 0xF0], R)
+        .prefix(0xF2)
+        .o64(),
+    def("crc32", &[Reg32, Rm16], &[0x0F, 0x38, 0xF1], R)
+        .prefix(0xF2)
+        .o16(),
+    def("crc32", &[Reg32, Rm32], &[0x0F, 0x38, 0xF1], R)
+        .prefix(0xF2)
+        .o32(),
+    def("crc32", &[Reg64, Rm64], &[0x0F, 0x38, 0xF1], R)
+        .prefix(0xF2)
+        .o64(),
+];
@@ -1,11 +1,12 @@
+use rask_x86_64::RaskResult;
 use rask_x86_64::encoder::Encoder;
 use rask_x86_64::registers::Reg64::*;
 
 #[test]
-fn test_add_and_sub_basic() {
+fn test_add_and_sub_basic() -> RaskResult<()> {
     let mut e = Encoder::new();
-    e.add(RAX, RBX);
-    e.sub(R8, R9);
+    e.add(RAX, RBX)?;
+    e.sub(R8, R9)?;
 
     let expected = [
         0x48, 0x01, 0xD8, // add rax, rbx
@@ -13,11 +14,13 @@ fn test_add_and_sub_basic() {
     ];
 
     assert_eq!(e.bytes(), &expected);
+    Ok(())
 }
 
 #[test]
-fn test_ret() {
+fn test_ret() -> RaskResult<()> {
     let mut e = Encoder::new();
-    e.ret();
+    e.ret()?;
     assert_eq!(e.bytes(), &[0xC3]);
+    Ok(())
 }
@@ -0,0 +1,99 @@
+mod common;
+use common::*;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::operand::{MemOperand, Operand, Scale};
+use rask_x86_64::registers::Reg32::*;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::FS;
+use rask_x86_64::registers::ZmmReg::*;
+
+#[test]
+fn test_mov_r32_forms() {
+    let bytes = encode(|e| {
+        e.mov(Operand::Reg32(EAX), Operand::Imm(1))?;
+        e.mov(Operand::Reg32(R10D), Operand::Imm(-1))?;
+        e.mov(Operand::Reg32(EAX), Operand::Reg32(ECX))?;
+        e.mov(Operand::Reg32(R9D), Operand::Mem(MemOperand::new(RAX, 0)))?;
+        e.mov(Operand::Mem(MemOperand::new(RDI, 4)), Operand::Reg32(R8D))?;
+        Ok(())
+    });
+
+    let expected = [
+        0xB8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+        0x41, 0xBA, 0xFF, 0xFF, 0xFF, 0xFF, // mov r10d, -1
+        0x89, 0xC8, // mov eax, ecx
+        0x44, 0x8B, 0x08, // mov r9d, [rax]
+        0x44, 0x89, 0x47, 0x04, // mov [rdi + 4], r8d
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_32bit_addressing_emits_address_size_override() {
+    let bytes = encode(|e| {
+        let mem = MemOperand::new(ECX, 0).with_index(EDX, Scale::S4);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(mem))?;
+        e.mov(Operand::Reg32(EAX), Operand::Mem(MemOperand::new(ESP, 0)))?;
+        e.mov(Operand::Reg(RAX), Operand::Mem(MemOperand::new(R13D, 0)))?;
+        let mem = MemOperand::new(R8D, 16).with_index(R9D, Scale::S2);
+        e.mov(Operand::Reg(RAX), Operand::Mem(mem))?;
+        let mem = MemOperand::new(EAX, 0x100).with_index(R12D, Scale::S8);
+        e.mov(Operand::Mem(mem), Operand::Reg32(ESI))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x67, 0x8B, 0x04, 0x91, // mov eax, [ecx + edx*4]
+        0x67, 0x8B, 0x04, 0x24, // mov eax, [esp]
+        0x67, 0x49, 0x8B, 0x45, 0x00, // mov rax, [r13d]
+        0x67, 0x4B, 0x8B, 0x44, 0x48, 0x10, // mov rax, [r8d + r9d*2 + 16]
+        // mov [eax + r12d*8 + 0x100], esi
+        0x67, 0x42, 0x89, 0xB4, 0xE0, 0x00, 0x01, 0x00, 0x00, // disp32
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_address_size_override_with_other_prefixes() {
+    let bytes = encode(|e| {
+        e.prefetcht0(MemOperand::new(EDI, 0))?;
+        let tls = MemOperand::new(EAX, 0).with_segment(FS);
+        e.mov(Operand::Reg(RAX), Operand::Mem(tls))?;
+        let src = Operand::Mem(MemOperand::new(ECX, 64));
+        e.vaesenc(Operand::Zmm(ZMM1), Operand::Zmm(ZMM2), src)?;
+        Ok(())
+    });
+
+    let expected = [
+        0x67, 0x0F, 0x18, 0x0F, // prefetcht0 [edi]
+        0x64, 0x67, 0x48, 0x8B, 0x00, // mov rax, fs:[eax]
+        0x67, 0x62, 0xF2, 0x6D, 0x48, 0xDC, 0x49, 0x01, // vaesenc zmm1, zmm2, [ecx + 64]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_mixed_width_base_and_index_are_rejected() {
+    let mem = MemOperand::new(RAX, 0).with_index(ECX, Scale::S1);
+    assert_eq!(mem.address_bits(), Some(64));
+    let mut enc = Encoder::new();
+    let err = enc.mov(Operand::Reg(RAX), Operand::Mem(mem)).unwrap_err();
+    assert!(err.to_string().contains("same width"), "{err}");
+    assert!(enc.bytes().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "RSP cannot be used as an index register")]
+fn test_esp_index_is_rejected() {
+    encode(|e| {
+        let mem = MemOperand::new(EAX, 0).with_index(ESP, Scale::S1);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(mem))?;
+        Ok(())
+    });
+}
+
+#[test]
+#[should_panic(expected = "does not fit in 32 bits")]
+fn test_mov_r32_immediate_out_of_range() {
+    encode(|e| e.mov(Operand::Reg32(EAX), Operand::Imm(1 << 32)));
+}
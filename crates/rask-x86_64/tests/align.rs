@@ -0,0 +1,100 @@
+mod common;
+use common::*;
+
+#[test]
+fn test_nop_n_lengths() {
+    let bytes = encode(|e| {
+        for len in 1..=15 {
+            e.nop_n(len)?;
+        }
+        Ok(())
+    });
+
+    #[rustfmt::skip]
+    let expected = [
+        0x90,
+        0x66, 0x90,
+        0x0F, 0x1F, 0x00,
+        0x0F, 0x1F, 0x40, 0x00,
+        0x0F, 0x1F, 0x44, 0x00, 0x00,
+        0x66, 0x0F, 0x1F, 0x44, 0x00, 0x00,
+        0x0F, 0x1F, 0x80, 0x00, 0x00, 0x00, 0x00,
+        0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x66, 0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x66, 0x66, 0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x66, 0x66, 0x66, 0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_nop_n_splits_long_runs() {
+    let bytes = encode(|e| {
+        e.nop_n(0)?;
+        e.nop_n(17)?;
+        Ok(())
+    });
+
+    let mut expected = vec![0x66; 7];
+    expected.extend_from_slice(&[0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    expected.extend_from_slice(&[0x66, 0x90]);
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_align_with_nops() {
+    let bytes = encode(|e| {
+        e.ret()?;
+        e.align(16)?;
+        e.ret()?;
+        e.align(16)?;
+        e.align(16)?;
+        e.ret()?;
+        e.align(1)?;
+        Ok(())
+    });
+
+    let mut expected = vec![0xC3];
+    expected.extend_from_slice(&[0x66; 7]);
+    expected.extend_from_slice(&[0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    expected.push(0xC3);
+    expected.extend_from_slice(&[0x66; 7]);
+    expected.extend_from_slice(&[0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    expected.push(0xC3);
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_align_64_spans_several_nops() {
+    let bytes = encode(|e| {
+        e.ret()?;
+        e.align(64)?;
+        Ok(())
+    });
+
+    assert_eq!(bytes.len(), 64);
+    // 63 bytes of padding: four 15-byte NOPs and a 3-byte NOP.
+    assert_bytes(&bytes[61..], &[0x0F, 0x1F, 0x00]);
+}
+
+#[test]
+fn test_align_with_fill_byte() {
+    let bytes = encode(|e| {
+        e.ret()?;
+        e.align_with(8, 0xCC)?;
+        e.align_with(8, 0xCC)?;
+        Ok(())
+    });
+
+    assert_bytes(&bytes, &[0xC3, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC]);
+}
+
+#[test]
+#[should_panic(expected = "alignment must be a power of two")]
+fn test_align_rejects_non_power_of_two() {
+    encode(|e| e.align(24));
+}
@@ -0,0 +1,174 @@
+use rask_x86_64::decoder::Decoder;
+use rask_x86_64::encoder::{Alternative, Encoder, Variation};
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::instruction::{Instruction, Mnemonic};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand};
+use rask_x86_64::registers::Reg8::AH;
+use rask_x86_64::registers::Reg32::{EAX, ECX};
+use rask_x86_64::registers::Reg64::*;
+
+use Operand::{Imm, Mem, Reg, Reg8, Reg32};
+
+fn find<'a>(alternatives: &'a [Alternative], bytes: &[u8]) -> &'a Alternative {
+    match alternatives.iter().find(|alt| alt.bytes() == bytes) {
+        Some(alt) => alt,
+        None => panic!("{bytes:02x?} not in {alternatives:#?}"),
+    }
+}
+
+/// Checks that every alternative decodes, on its own, to the instruction
+/// the default encoding decodes to.
+fn assert_same(enc: &Encoder, alternatives: &[Alternative]) {
+    let reference = alternatives[0].explanation().instruction();
+    for alt in alternatives {
+        let decoded = Decoder::with_mode(alt.bytes(), enc.mode())
+            .decode()
+            .unwrap();
+        assert_eq!(decoded.len(), alt.bytes().len());
+        assert_eq!(decoded.instruction(), reference, "{:02x?}", alt.bytes());
+    }
+}
+
+#[test]
+fn test_alternative_forms() {
+    let enc = Encoder::new();
+    let add = Instruction::with2(Mnemonic::Add, Reg(RAX), Reg(RBX));
+    let alternatives = enc.alternatives(&add).unwrap();
+    assert_eq!(alternatives[0].bytes(), &[0x48, 0x01, 0xD8]);
+    assert!(alternatives[0].variations().is_empty());
+    assert_eq!(
+        find(&alternatives, &[0x48, 0x03, 0xC3]).variations(),
+        &[Variation::Form]
+    );
+    assert_same(&enc, &alternatives);
+
+    // add ecx, 1: 83 /0 ib, and 81 /0 id.
+    let add = Instruction::with2(Mnemonic::Add, Reg32(ECX), Imm(1));
+    let alternatives = enc.alternatives(&add).unwrap();
+    assert_eq!(alternatives[0].bytes(), &[0x83, 0xC1, 0x01]);
+    let wide = find(&alternatives, &[0x81, 0xC1, 0x01, 0x00, 0x00, 0x00]);
+    assert_eq!(wide.form(), "81 /0 id");
+    find(&alternatives, &[0x40, 0x81, 0xC1, 0x01, 0x00, 0x00, 0x00]);
+    assert_same(&enc, &alternatives);
+}
+
+#[test]
+fn test_alternative_memory_operands() {
+    let enc = Encoder::new();
+    let mov = Instruction::with2(Mnemonic::Mov, Reg32(EAX), Mem(MemOperand::new(RCX, 0)));
+    let alternatives = enc.alternatives(&mov).unwrap();
+    assert_eq!(alternatives[0].bytes(), &[0x8B, 0x01]);
+    #[rustfmt::skip]
+    let cases: [(&[u8], &[Variation]); 6] = [
+        (&[0x8B, 0x41, 0x00], &[Variation::Disp8]),
+        (&[0x8B, 0x81, 0x00, 0x00, 0x00, 0x00], &[Variation::Disp32]),
+        (&[0x8B, 0x04, 0x21], &[Variation::Sib]),
+        (&[0x40, 0x8B, 0x01], &[Variation::RedundantRex]),
+        (&[0x8B, 0x44, 0x21, 0x00], &[Variation::Disp8, Variation::Sib]),
+        (&[0x40, 0x8B, 0x84, 0x21, 0x00, 0x00, 0x00, 0x00], &[Variation::Disp32, Variation::Sib, Variation::RedundantRex]),
+    ];
+    for (bytes, variations) in cases {
+        assert_eq!(find(&alternatives, bytes).variations(), variations);
+    }
+    assert_same(&enc, &alternatives);
+
+    // A disp8 widens to a disp32 with the same value.
+    let mem = MemOperand::new(RBP, -8).with_size(MemSize::Qword);
+    let inc = Instruction::with1(Mnemonic::Inc, Mem(mem));
+    let alternatives = enc.alternatives(&inc).unwrap();
+    find(&alternatives, &[0x48, 0xFF, 0x85, 0xF8, 0xFF, 0xFF, 0xFF]);
+    assert!(alternatives.iter().all(|alt| alt.bytes()[2] & 0xC0 != 0));
+    assert_same(&enc, &alternatives);
+
+    // RIP-relative and absolute operands keep their only encoding.
+    let mov = Instruction::with2(Mnemonic::Mov, Reg(RAX), Mem(MemOperand::rip(0x10)));
+    let alternatives = enc.alternatives(&mov).unwrap();
+    assert!(
+        alternatives
+            .iter()
+            .all(|alt| alt.explanation().displacement() == Some((0x10, 4)))
+    );
+    assert_same(&enc, &alternatives);
+}
+
+#[test]
+fn test_alternative_prefixes() {
+    // A REX prefix would turn AH into SPL.
+    let enc = Encoder::new();
+    let mov = Instruction::with2(Mnemonic::Mov, Reg8(AH), Imm(1));
+    let alternatives = enc.alternatives(&mov).unwrap();
+    assert!(alternatives.iter().all(|alt| alt.bytes()[0] != 0x40));
+    assert_same(&enc, &alternatives);
+
+    // Nor is there a REX prefix outside 64-bit mode.
+    let enc32 = Encoder::with_mode(Mode::Protected32);
+    let add = Instruction::with2(Mnemonic::Add, Reg32(EAX), Reg32(ECX));
+    let alternatives = enc32.alternatives(&add).unwrap();
+    assert_eq!(alternatives.len(), 2);
+    assert_same(&enc32, &alternatives);
+
+    // REX2, with APX, for both opcode maps.
+    let apx = Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx));
+    let add = Instruction::with2(Mnemonic::Add, Reg(RAX), Reg(R9));
+    let alternatives = apx.alternatives(&add).unwrap();
+    let rex2 = find(&alternatives, &[0xD5, 0x0C, 0x01, 0xC8]);
+    assert_eq!(rex2.variations(), &[Variation::Rex2]);
+    assert_eq!(rex2.form(), "REX2.W + 01 /r");
+    assert_same(&apx, &alternatives);
+    assert!(
+        !enc.alternatives(&add)
+            .unwrap()
+            .iter()
+            .any(|alt| alt.variations().contains(&Variation::Rex2))
+    );
+
+    let movnti = Instruction::with2(Mnemonic::Movnti, Mem(MemOperand::new(RDI, 0)), Reg32(EAX));
+    let alternatives = apx.alternatives(&movnti).unwrap();
+    assert_eq!(alternatives[0].bytes(), &[0x0F, 0xC3, 0x07]);
+    find(&alternatives, &[0xD5, 0x80, 0xC3, 0x07]);
+    assert_same(&apx, &alternatives);
+}
+
+#[test]
+fn test_alternative_branches() {
+    let mut enc = Encoder::new();
+    let top = enc.create_label();
+    enc.bind_label(top).unwrap();
+    enc.ret().unwrap();
+    let jmp = Instruction::with1(Mnemonic::Jmp, Operand::Label(top));
+    let alternatives = enc.alternatives(&jmp).unwrap();
+    assert_eq!(alternatives[0].bytes(), &[0xEB, 0xFD]);
+    let near = find(&alternatives, &[0xE9, 0xFA, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(near.variations(), &[Variation::NearBranch]);
+    assert_eq!(near.explanation().decoded().branch_target(), Some(0));
+
+    enc.encode_as(&jmp, near).unwrap();
+    assert_eq!(enc.bytes(), &[0xC3, 0xE9, 0xFA, 0xFF, 0xFF, 0xFF]);
+
+    // A branch to an unbound label has only the fixed-up encoding.
+    let exit = enc.create_label();
+    let jmp = Instruction::with1(Mnemonic::Jmp, Operand::Label(exit));
+    let alternatives = enc.alternatives(&jmp).unwrap();
+    assert_eq!(alternatives.len(), 1);
+    enc.encode_as(&jmp, &alternatives[0]).unwrap();
+    enc.bind_label(exit).unwrap();
+    assert_eq!(&enc.bytes()[6..], &[0xE9, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_encode_as() {
+    let mut enc = Encoder::new();
+    let add = Instruction::with2(Mnemonic::Add, Reg(RAX), Reg(RBX));
+    let alternatives = enc.alternatives(&add).unwrap();
+    let swapped = find(&alternatives, &[0x48, 0x03, 0xC3]).clone();
+    enc.encode_as(&add, &swapped).unwrap();
+    enc.encode_as(&add, &alternatives[0]).unwrap();
+    assert_eq!(enc.bytes(), &[0x48, 0x03, 0xC3, 0x48, 0x01, 0xD8]);
+
+    // An encoding of another instruction is refused and emits nothing.
+    let sub = Instruction::with2(Mnemonic::Sub, Reg(RAX), Reg(RBX));
+    let other = enc.alternatives(&sub).unwrap().remove(0);
+    assert!(enc.encode_as(&add, &other).is_err());
+    assert_eq!(enc.bytes().len(), 6);
+}
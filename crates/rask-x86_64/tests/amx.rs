@@ -0,0 +1,114 @@
+mod common;
+use common::*;
+use rask_x86_64::RaskError;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, Scale};
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::TmmReg::*;
+
+#[test]
+fn test_tile_configuration() {
+    let bytes = encode(|e| {
+        e.ldtilecfg(MemOperand::new(RAX, 0))?;
+        e.ldtilecfg(MemOperand::new(R8, 64))?;
+        e.sttilecfg(MemOperand::new(RSP, 0))?;
+        e.tilerelease()?;
+        Ok(())
+    });
+
+    let expected = [
+        0xC4, 0xE2, 0x78, 0x49, 0x00, // ldtilecfg [rax]
+        0xC4, 0xC2, 0x78, 0x49, 0x40, 0x40, // ldtilecfg [r8 + 64]
+        0xC4, 0xE2, 0x79, 0x49, 0x04, 0x24, // sttilecfg [rsp]
+        0xC4, 0xE2, 0x78, 0x49, 0xC0, // tilerelease
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_tile_loads_and_stores_use_sib() {
+    let bytes = encode(|e| {
+        e.tileloadd(TMM1, MemOperand::new(RAX, 0).with_index(RCX, Scale::S4))?;
+        e.tileloadd(TMM7, MemOperand::new(R8, 64).with_index(R9, Scale::S1))?;
+        e.tileloadd(TMM0, MemOperand::new(RSI, 0))?;
+        e.tileloadd(TMM1, MemOperand::new(RBP, 0).with_index(RCX, Scale::S1))?;
+        e.tileloadd(TMM1, MemOperand::new(R13, 0))?;
+        e.tileloaddt1(TMM2, MemOperand::new(RDI, 0).with_index(RDX, Scale::S2))?;
+        e.tilestored(MemOperand::new(RAX, 0).with_index(RCX, Scale::S4), TMM3)?;
+        e.tilestored(MemOperand::new(R12, 1024).with_index(R13, Scale::S8), TMM6)?;
+        Ok(())
+    });
+
+    let expected = [
+        0xC4, 0xE2, 0x7B, 0x4B, 0x0C, 0x88, // tileloadd tmm1, [rax + rcx*4]
+        0xC4, 0x82, 0x7B, 0x4B, 0x7C, 0x08, 0x40, // tileloadd tmm7, [r8 + r9*1 + 64]
+        0xC4, 0xE2, 0x7B, 0x4B, 0x04, 0x26, // tileloadd tmm0, [rsi] (SIB, no index)
+        0xC4, 0xE2, 0x7B, 0x4B, 0x4C, 0x0D, 0x00, // tileloadd tmm1, [rbp + rcx*1]
+        0xC4, 0xC2, 0x7B, 0x4B, 0x4C, 0x25, 0x00, // tileloadd tmm1, [r13]
+        0xC4, 0xE2, 0x79, 0x4B, 0x14, 0x57, // tileloaddt1 tmm2, [rdi + rdx*2]
+        0xC4, 0xE2, 0x7A, 0x4B, 0x1C, 0x88, // tilestored [rax + rcx*4], tmm3
+        // tilestored [r12 + r13*8 + 1024], tmm6
+        0xC4, 0x82, 0x7A, 0x4B, 0xB4, 0xEC, 0x00, 0x04, 0x00, 0x00, // disp32
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_tile_absolute_addresses_use_sib() {
+    let bytes = encode(|e| e.tileloadd(TMM0, MemOperand::absolute(0x40)));
+    // tileloadd tmm0, [0x40]: SIB base 101 without index
+    assert_bytes(&bytes, &[0xC4, 0xE2, 0x7B, 0x4B, 0x04, 0x25, 0x40, 0, 0, 0]);
+}
+
+#[test]
+fn test_tile_instructions_require_long_mode() {
+    let mem = MemOperand::absolute(0x40);
+    for mode in [Mode::Protected32, Mode::Real16] {
+        let mut enc = Encoder::with_mode(mode);
+        let results = [
+            enc.ldtilecfg(mem),
+            enc.sttilecfg(mem),
+            enc.tileloadd(TMM0, mem),
+            enc.tileloaddt1(TMM0, mem),
+            enc.tilestored(mem, TMM1),
+            enc.tilezero(TMM0),
+            enc.tilerelease(),
+            enc.tdpbssd(TMM1, TMM2, TMM3),
+            enc.tdpbsud(TMM1, TMM2, TMM3),
+            enc.tdpbusd(TMM1, TMM2, TMM3),
+            enc.tdpbuud(TMM1, TMM2, TMM3),
+            enc.tdpbf16ps(TMM1, TMM2, TMM3),
+        ];
+        for err in results.into_iter().map(Result::unwrap_err) {
+            assert!(
+                matches!(&err, RaskError::UnsupportedFeature { what, .. } if what == "AMX"),
+                "{mode:?}: {err:?}"
+            );
+        }
+        assert!(enc.bytes().is_empty());
+    }
+}
+
+#[test]
+fn test_tile_zero_and_dot_products() {
+    let bytes = encode(|e| {
+        e.tilezero(TMM5)?;
+        e.tdpbssd(TMM1, TMM2, TMM3)?;
+        e.tdpbsud(TMM0, TMM7, TMM4)?;
+        e.tdpbusd(TMM6, TMM5, TMM4)?;
+        e.tdpbuud(TMM3, TMM2, TMM1)?;
+        e.tdpbf16ps(TMM0, TMM1, TMM2)?;
+        Ok(())
+    });
+
+    let expected = [
+        0xC4, 0xE2, 0x7B, 0x49, 0xE8, // tilezero tmm5
+        0xC4, 0xE2, 0x63, 0x5E, 0xCA, // tdpbssd tmm1, tmm2, tmm3
+        0xC4, 0xE2, 0x5A, 0x5E, 0xC7, // tdpbsud tmm0, tmm7, tmm4
+        0xC4, 0xE2, 0x59, 0x5E, 0xF5, // tdpbusd tmm6, tmm5, tmm4
+        0xC4, 0xE2, 0x70, 0x5E, 0xDA, // tdpbuud tmm3, tmm2, tmm1
+        0xC4, 0xE2, 0x6A, 0x5C, 0xC1, // tdpbf16ps tmm0, tmm1, tmm2
+    ];
+    assert_bytes(&bytes, &expected);
+}
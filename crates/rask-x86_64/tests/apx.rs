@@ -0,0 +1,169 @@
+mod common;
+use common::*;
+use rask_x86_64::RaskResult;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg8::AH;
+use rask_x86_64::registers::Reg32::R16D;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::ZmmReg::*;
+
+/// Like [`encode`], but with APX enabled.
+fn encode_apx<F: FnOnce(&mut Encoder) -> RaskResult<()>>(f: F) -> Vec<u8> {
+    let mut enc = Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx));
+    if let Err(err) = f(&mut enc) {
+        panic!("{err}");
+    }
+    enc.bytes().to_vec()
+}
+
+#[test]
+fn test_rex2_register_forms() {
+    let bytes = encode_apx(|e| {
+        e.mov(Operand::Reg(R16), Operand::Reg(RAX))?;
+        e.mov(Operand::Reg(RAX), Operand::Reg(R16))?;
+        e.add(R31, R16)?;
+        e.sub(R8, R25)?;
+        e.mov(Operand::Reg(R17), Operand::Imm(1))?;
+        e.mov(Operand::Reg(R31), Operand::Imm(-1))?;
+        Ok(())
+    });
+
+    let expected = [
+        0xD5, 0x18, 0x89, 0xC0, // mov r16, rax
+        0xD5, 0x48, 0x89, 0xC0, // mov rax, r16
+        0xD5, 0x59, 0x01, 0xC7, // add r31, r16
+        0xD5, 0x4D, 0x29, 0xC8, // sub r8, r25
+        // mov r17, 1
+        0xD5, 0x18, 0xB9, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // imm64
+        // mov r31, -1
+        0xD5, 0x19, 0xBF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // imm64
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_rex2_memory_forms() {
+    let bytes = encode_apx(|e| {
+        let mem = MemOperand::new(R20, 8).with_index(R21, Scale::S4);
+        e.mov(Operand::Reg(RAX), Operand::Mem(mem))?;
+        e.movnti(MemOperand::new(R20, 0), Operand::Reg(R21))?;
+        e.prefetcht0(MemOperand::new(R16, 0))?;
+        Ok(())
+    });
+
+    let expected = [
+        0xD5, 0x38, 0x8B, 0x44, 0xAC, 0x08, // mov rax, [r20 + r21*4 + 8]
+        0xD5, 0xD8, 0xC3, 0x2C, 0x24, // movnti [r20], r21 (M0 replaces 0F)
+        0xD5, 0x90, 0x18, 0x08, // prefetcht0 [r16]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_evex_memory_operands_with_apx_registers() {
+    let bytes = encode_apx(|e| {
+        let base = MemOperand::new(R16, 64);
+        let index = MemOperand::new(RAX, 0).with_index(R17, Scale::S1);
+        e.vaesenc(Operand::Zmm(ZMM1), Operand::Zmm(ZMM2), Operand::Mem(base))?;
+        e.vaesenc(Operand::Zmm(ZMM1), Operand::Zmm(ZMM2), Operand::Mem(index))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x62, 0xFA, 0x6D, 0x48, 0xDC, 0x48, 0x01, // vaesenc zmm1, zmm2, [r16 + 64]
+        0x62, 0xF2, 0x69, 0x48, 0xDC, 0x0C, 0x08, // vaesenc zmm1, zmm2, [rax + r17*1]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_push2_pop2() {
+    let bytes = encode_apx(|e| {
+        e.push2(RAX, RCX)?;
+        e.pop2(RCX, RAX)?;
+        e.push2(R16, R31)?;
+        e.push2p(RAX, RCX)?;
+        e.pop2p(RCX, RAX)?;
+        Ok(())
+    });
+
+    let expected = [
+        0x62, 0xF4, 0x7C, 0x18, 0xFF, 0xF1, // push2 rax, rcx
+        0x62, 0xF4, 0x74, 0x18, 0x8F, 0xC0, // pop2 rcx, rax
+        0x62, 0xDC, 0x7C, 0x10, 0xFF, 0xF7, // push2 r16, r31
+        0x62, 0xF4, 0xFC, 0x18, 0xFF, 0xF1, // push2p rax, rcx
+        0x62, 0xF4, 0xF4, 0x18, 0x8F, 0xC0, // pop2p rcx, rax
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_ndd_alu_forms() {
+    let bytes = encode_apx(|e| {
+        e.add_ndd(R8, Operand::Reg(RBX), Operand::Reg(RCX))?;
+        e.add_ndd(R20, Operand::Reg(R21), Operand::Imm(1))?;
+        e.sub_ndd(
+            RAX,
+            Operand::Reg(RCX),
+            Operand::Mem(MemOperand::new(RDX, 8)),
+        )?;
+        e.xor_ndd(R9, Operand::Mem(MemOperand::new(RSI, 0)), Operand::Reg(RDI))?;
+        e.and_ndd(RAX, Operand::Reg(RBX), Operand::Imm(0x1000))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x62, 0xF4, 0xBC, 0x18, 0x01, 0xCB, // add r8, rbx, rcx
+        0x62, 0xFC, 0xDC, 0x10, 0x83, 0xC5, 0x01, // add r20, r21, 1
+        0x62, 0xF4, 0xFC, 0x18, 0x2B, 0x4A, 0x08, // sub rax, rcx, [rdx + 8]
+        0x62, 0xF4, 0xB4, 0x18, 0x31, 0x3E, // xor r9, [rsi], rdi
+        // and rax, rbx, 0x1000
+        0x62, 0xF4, 0xFC, 0x18, 0x81, 0xE3, 0x00, 0x10, 0x00, 0x00, // imm32
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+#[should_panic(expected = "memory operand of a 64-bit NDD form must be a qword")]
+fn test_ndd_rejects_narrow_memory_operands() {
+    let mem = MemOperand::absolute(0).with_size(MemSize::Byte);
+    encode_apx(|e| e.sub_ndd(R17, Operand::Reg(RAX), Operand::Mem(mem)));
+}
+
+#[test]
+#[should_panic(expected = "R16–R31 requires CpuFeature::Apx")]
+fn test_apx_registers_require_feature() {
+    encode(|e| e.mov(Operand::Reg(R16), Operand::Reg(RAX)));
+}
+
+#[test]
+#[should_panic(expected = "PUSH2 requires CpuFeature::Apx")]
+fn test_push2_requires_feature() {
+    encode(|e| e.push2(RAX, RCX));
+}
+
+#[test]
+#[should_panic(expected = "destinations must be different registers")]
+fn test_pop2_same_register_is_rejected() {
+    encode_apx(|e| e.pop2(RAX, RAX));
+}
+
+#[test]
+#[should_panic(expected = "0F 38 and 0F 3A opcode maps")]
+fn test_rex2_rejects_0f38_map() {
+    encode_apx(|e| e.crc32(Operand::Reg(R16), Operand::Reg(RAX)));
+}
+
+#[test]
+#[should_panic(expected = "cannot be encoded in an instruction that needs a REX2 prefix")]
+fn test_rex2_rejects_high_byte_registers() {
+    encode_apx(|e| e.crc32(Operand::Reg32(R16D), Operand::Reg8(AH)));
+}
+
+#[test]
+#[should_panic(expected = "VEX-encoded instructions cannot address R16–R31")]
+fn test_vex_rejects_apx_registers() {
+    encode_apx(|e| e.ldtilecfg(MemOperand::new(R16, 0)));
+}
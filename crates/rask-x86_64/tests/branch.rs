@@ -0,0 +1,190 @@
+mod common;
+use common::*;
+
+use rask_x86_64::{
+    RaskError, RaskResult,
+    encoder::Encoder,
+    instruction::{Condition, Instruction, Mnemonic},
+    mode::Mode,
+    operand::{MemOperand, MemSize, Operand},
+    registers::{Reg16::BX, Reg64::*},
+    sink::{SliceSink, WriteSink},
+};
+
+#[test]
+fn test_backward_branches_are_short() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    let top = enc.create_label();
+    enc.bind_label(top)?;
+    enc.dec(Operand::Reg(RCX))?;
+    enc.jcc(Condition::Ne, top)?;
+    enc.jmp(Operand::Label(top))?;
+
+    #[rustfmt::skip]
+    assert_bytes(enc.bytes(), &[
+        0x48, 0xFF, 0xC9, // top: dec rcx
+        0x75, 0xFB,       // jne top
+        0xEB, 0xF9,       // jmp top
+    ]);
+    assert_eq!(enc.label_offset(top), Some(0));
+    Ok(())
+}
+
+#[test]
+fn test_backward_branches_out_of_rel8_range() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    let top = enc.create_label();
+    enc.bind_label(top)?;
+    enc.nop_n(200)?;
+    enc.jmp(Operand::Label(top))?;
+    enc.jcc(Condition::L, top)?;
+
+    let bytes = enc.bytes();
+    // 200 + 5 bytes back from the end of the jmp, 211 from the end of jl.
+    assert_bytes(&bytes[200..205], &[0xE9, 0x33, 0xFF, 0xFF, 0xFF]);
+    assert_bytes(&bytes[205..], &[0x0F, 0x8C, 0x2D, 0xFF, 0xFF, 0xFF]);
+    Ok(())
+}
+
+#[test]
+fn test_forward_branches_are_patched() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    let done = enc.create_label();
+    enc.jmp(Operand::Label(done))?;
+    enc.jcc(Condition::E, done)?;
+    enc.call(Operand::Label(done))?;
+    enc.nop_n(1)?;
+    enc.bind_label(done)?;
+    enc.ret()?;
+
+    #[rustfmt::skip]
+    assert_bytes(&enc.finish()?, &[
+        0xE9, 0x0C, 0x00, 0x00, 0x00,       // jmp done
+        0x0F, 0x84, 0x06, 0x00, 0x00, 0x00, // je done
+        0xE8, 0x01, 0x00, 0x00, 0x00,       // call done
+        0x90,
+        0xC3,                               // done: ret
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_indirect_branches() {
+    let target = MemOperand::new(RDI, 8).with_size(MemSize::Qword);
+    let bytes = encode(|e| {
+        e.jmp(Operand::Reg(RAX))?;
+        e.jmp(Operand::Mem(target))?;
+        e.call(Operand::Reg(R11))?;
+        e.call(Operand::Mem(MemOperand::new(RAX, 0)))
+    });
+
+    #[rustfmt::skip]
+    assert_bytes(&bytes, &[
+        0xFF, 0xE0,       // jmp rax
+        0xFF, 0x67, 0x08, // jmp qword ptr [rdi + 8]
+        0x41, 0xFF, 0xD3, // call r11
+        0xFF, 0x10,       // call qword ptr [rax]
+    ]);
+}
+
+#[test]
+fn test_branches_in_16_bit_mode() -> RaskResult<()> {
+    let mut enc = Encoder::with_mode(Mode::Real16);
+    let top = enc.create_label();
+    let done = enc.create_label();
+    enc.bind_label(top)?;
+    enc.jcc(Condition::B, done)?;
+    enc.call(Operand::Label(top))?;
+    enc.jmp(Operand::Reg16(BX))?;
+    enc.bind_label(done)?;
+
+    #[rustfmt::skip]
+    assert_bytes(enc.bytes(), &[
+        0x0F, 0x82, 0x05, 0x00, // jb done
+        0xE8, 0xF9, 0xFF,       // call top
+        0xFF, 0xE3,             // jmp bx
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_unbound_label() {
+    let mut enc = Encoder::new();
+    let missing = enc.create_label();
+    enc.jmp(Operand::Label(missing)).unwrap();
+
+    let err = enc.finish().unwrap_err();
+    assert!(matches!(err, RaskError::UnboundLabel { label } if label == ".L0"));
+}
+
+#[test]
+fn test_label_misuse() -> RaskResult<()> {
+    let mut other = Encoder::new();
+    other.create_label();
+    let foreign = other.create_label();
+
+    let mut enc = Encoder::new();
+    let label = enc.create_label();
+    enc.bind_label(label)?;
+    assert!(matches!(enc.bind_label(label), Err(RaskError::Other(_))));
+    assert!(matches!(
+        enc.jmp(Operand::Label(foreign)),
+        Err(RaskError::Other(_))
+    ));
+    assert!(matches!(
+        enc.emit_instruction("add", &[Operand::Reg(RAX), Operand::Label(label)]),
+        Err(RaskError::InvalidOperands { .. })
+    ));
+    assert!(enc.bytes().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_failed_branch_drops_its_fixup() -> RaskResult<()> {
+    let mut memory = [0; 4];
+    let mut enc = Encoder::with_sink(SliceSink::new(&mut memory));
+    let ahead = enc.create_label();
+    assert!(matches!(
+        enc.jmp(Operand::Label(ahead)),
+        Err(RaskError::BufferOverflow { .. })
+    ));
+    enc.ret()?;
+    assert_bytes(enc.finish()?.written(), &[0xC3]);
+    Ok(())
+}
+
+#[test]
+fn test_sink_without_patching() -> RaskResult<()> {
+    // A sink that cannot patch only takes branches to bound labels.
+    let mut enc = Encoder::with_sink(WriteSink::new(Vec::new()));
+    let back = enc.create_label();
+    let ahead = enc.create_label();
+    enc.bind_label(back)?;
+    enc.jmp(Operand::Label(back))?;
+    enc.jmp(Operand::Label(ahead))?;
+    assert!(matches!(enc.bind_label(ahead), Err(RaskError::Other(_))));
+    Ok(())
+}
+
+#[test]
+fn test_branch_instructions() -> RaskResult<()> {
+    let mut enc = Encoder::new();
+    let top = enc.create_label();
+    enc.bind_label(top)?;
+    let jz: Mnemonic = "jz".parse()?;
+    assert_eq!(jz, Mnemonic::Je);
+    assert_eq!(jz.condition(), Some(Condition::E));
+    assert_eq!(Mnemonic::jcc(Condition::E.negate()), Mnemonic::Jne);
+
+    let insn = Instruction::with1(jz, Operand::Label(top));
+    assert_eq!(enc.encoded_len(&insn)?, 2);
+    enc.encode(&insn)?;
+    enc.encode(&Instruction::with1(Mnemonic::Call, Operand::Reg(RAX)))?;
+
+    // Previews use the encoder's position: `jmp top` from offset 4.
+    let (bytes, len) =
+        enc.encode_to_array(&Instruction::with1(Mnemonic::Jmp, Operand::Label(top)))?;
+    assert_bytes(&bytes[..len], &[0xEB, 0xFA]);
+    assert_bytes(enc.bytes(), &[0x74, 0xFE, 0xFF, 0xD0]);
+    Ok(())
+}
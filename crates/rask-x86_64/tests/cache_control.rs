@@ -0,0 +1,126 @@
+mod common;
+use common::*;
+use rask_x86_64::operand::{MemOperand, Operand, Scale};
+use rask_x86_64::registers::Reg32::*;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::XmmReg::*;
+use rask_x86_64::registers::YmmReg::*;
+
+#[test]
+fn test_prefetch_hints() {
+    let bytes = encode(|e| {
+        e.prefetcht0(MemOperand::new(RAX, 0))?;
+        e.prefetcht1(MemOperand::new(RSI, 64))?;
+        e.prefetcht2(MemOperand::new(R12, 0))?;
+        e.prefetchnta(MemOperand::new(RDI, 256).with_index(RCX, Scale::S8))?;
+        e.prefetchw(MemOperand::new(R13, 0))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x0F, 0x18, 0x08, // prefetcht0 [rax]
+        0x0F, 0x18, 0x56, 0x40, // prefetcht1 [rsi + 64]
+        0x41, 0x0F, 0x18, 0x1C, 0x24, // prefetcht2 [r12]
+        0x0F, 0x18, 0x84, 0xCF, 0x00, 0x01, 0x00, 0x00, // prefetchnta [rdi + rcx*8 + 256]
+        0x41, 0x0F, 0x0D, 0x4D, 0x00, // prefetchw [r13]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_cache_line_flush_and_fences() {
+    let bytes = encode(|e| {
+        e.clflush(MemOperand::new(RDI, 0))?;
+        e.clflushopt(MemOperand::new(R9, 8))?;
+        e.clwb(MemOperand::new(RSP, 0))?;
+        e.sfence()?;
+        e.lfence()?;
+        e.mfence()?;
+        Ok(())
+    });
+
+    let expected = [
+        0x0F, 0xAE, 0x3F, // clflush [rdi]
+        0x66, 0x41, 0x0F, 0xAE, 0x79, 0x08, // clflushopt [r9 + 8]
+        0x66, 0x0F, 0xAE, 0x34, 0x24, // clwb [rsp]
+        0x0F, 0xAE, 0xF8, // sfence
+        0x0F, 0xAE, 0xE8, // lfence
+        0x0F, 0xAE, 0xF0, // mfence
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_movnti() {
+    let bytes = encode(|e| {
+        e.movnti(MemOperand::new(RDI, 0), Operand::Reg32(EAX))?;
+        e.movnti(
+            MemOperand::new(RDI, 0).with_index(RCX, Scale::S8),
+            Operand::Reg(R10),
+        )?;
+        e.movnti(MemOperand::new(R8, 0), Operand::Reg32(R11D))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x0F, 0xC3, 0x07, // movnti [rdi], eax
+        0x4C, 0x0F, 0xC3, 0x14, 0xCF, // movnti [rdi + rcx*8], r10
+        0x45, 0x0F, 0xC3, 0x18, // movnti [r8], r11d
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_sse_non_temporal_moves() {
+    let bytes = encode(|e| {
+        e.movntdq(MemOperand::new(RDI, 0), XMM0)?;
+        e.movntdq(MemOperand::new(R8, 16).with_index(RDX, Scale::S2), XMM12)?;
+        e.movntps(MemOperand::new(RAX, 0), XMM1)?;
+        e.movntpd(MemOperand::new(RAX, 0), XMM1)?;
+        e.movntdqa(XMM2, MemOperand::new(RSI, 0))?;
+        e.movntdqa(XMM10, MemOperand::new(RSI, 0).with_index(R11, Scale::S4))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x66, 0x0F, 0xE7, 0x07, // movntdq [rdi], xmm0
+        0x66, 0x45, 0x0F, 0xE7, 0x64, 0x50, 0x10, // movntdq [r8 + rdx*2 + 16], xmm12
+        0x0F, 0x2B, 0x08, // movntps [rax], xmm1
+        0x66, 0x0F, 0x2B, 0x08, // movntpd [rax], xmm1
+        0x66, 0x0F, 0x38, 0x2A, 0x16, // movntdqa xmm2, [rsi]
+        0x66, 0x46, 0x0F, 0x38, 0x2A, 0x14, 0x9E, // movntdqa xmm10, [rsi + r11*4]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_vex_non_temporal_moves() {
+    let bytes = encode(|e| {
+        e.vmovntdq(MemOperand::new(RDI, 0), Operand::Xmm(XMM1))?;
+        e.vmovntdq(MemOperand::new(RDI, 32), Operand::Ymm(YMM15))?;
+        e.vmovntps(MemOperand::new(R10, 0), Operand::Ymm(YMM2))?;
+        e.vmovntpd(MemOperand::new(RAX, 0), Operand::Xmm(XMM3))?;
+        e.vmovntdqa(Operand::Ymm(YMM4), MemOperand::new(RSI, 0))?;
+        e.vmovntdqa(
+            Operand::Xmm(XMM9),
+            MemOperand::new(R11, 0).with_index(RAX, Scale::S1),
+        )?;
+        Ok(())
+    });
+
+    let expected = [
+        0xC5, 0xF9, 0xE7, 0x0F, // vmovntdq [rdi], xmm1
+        0xC5, 0x7D, 0xE7, 0x7F, 0x20, // vmovntdq [rdi + 32], ymm15
+        0xC4, 0xC1, 0x7C, 0x2B, 0x12, // vmovntps [r10], ymm2
+        0xC5, 0xF9, 0x2B, 0x18, // vmovntpd [rax], xmm3
+        0xC4, 0xE2, 0x7D, 0x2A, 0x26, // vmovntdqa ymm4, [rsi]
+        0xC4, 0x42, 0x79, 0x2A, 0x0C, 0x03, // vmovntdqa xmm9, [r11 + rax]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+#[should_panic(expected = "RSP cannot be used as an index register")]
+fn test_rsp_index_is_rejected() {
+    encode(|e| e.prefetcht0(MemOperand::new(RAX, 0).with_index(RSP, Scale::S1)));
+}
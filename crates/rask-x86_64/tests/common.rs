@@ -1,17 +1,33 @@
-use rask_x86_64::encoder::Encoder;
+use rask_x86_64::{RaskResult, decoder::Decoder, encoder::Encoder};
 
 /// Helper to format mismatches clearly when comparing byte sequences.
 pub fn assert_bytes(actual: &[u8], expected: &[u8]) {
     if actual != expected {
         println!("Expected: {:02x?}", expected);
         println!("Actual:   {:02x?}", actual);
+        println!("Expected listing:\n{}", listing(expected));
+        println!("Actual listing:\n{}", listing(actual));
         panic!("Byte sequence mismatch");
     }
 }
 
-/// Helper to create an encoder and return its final bytes.
-pub fn encode<F: FnOnce(&mut Encoder)>(f: F) -> Vec<u8> {
+/// Disassembles `bytes` in Intel syntax, one instruction per line.
+pub fn listing(bytes: &[u8]) -> String {
+    Decoder::new(bytes)
+        .map(|insn| match insn {
+            Ok(insn) => format!("  {:4x}: {}", insn.address(), insn.instruction()),
+            Err(err) => format!("  ({err})"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Helper to create an encoder and return its final bytes, panicking with
+/// the error message if `f` fails.
+pub fn encode<F: FnOnce(&mut Encoder) -> RaskResult<()>>(f: F) -> Vec<u8> {
     let mut enc = Encoder::new();
-    f(&mut enc);
+    if let Err(err) = f(&mut enc) {
+        panic!("{err}");
+    }
     enc.bytes().to_vec()
 }
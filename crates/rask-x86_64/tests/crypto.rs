@@ -0,0 +1,280 @@
+mod common;
+use common::*;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg8::*;
+use rask_x86_64::registers::Reg16::*;
+use rask_x86_64::registers::Reg32::*;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::XmmReg::*;
+use rask_x86_64::registers::YmmReg::*;
+use rask_x86_64::registers::ZmmReg::*;
+
+#[test]
+fn test_aes_ni() {
+    let bytes = encode(|e| {
+        e.aesenc(XMM1, Operand::Xmm(XMM2))?;
+        e.aesenc(XMM9, Operand::Mem(MemOperand::new(RDI, 16)))?;
+        e.aesenclast(XMM0, Operand::Xmm(XMM15))?;
+        e.aesdec(XMM3, Operand::Xmm(XMM4))?;
+        e.aesdeclast(XMM5, Operand::Mem(MemOperand::new(RAX, 0)))?;
+        e.aesimc(XMM6, Operand::Xmm(XMM7))?;
+        e.aeskeygenassist(XMM1, Operand::Xmm(XMM2), 0x1B)?;
+        Ok(())
+    });
+
+    let expected = [
+        0x66, 0x0F, 0x38, 0xDC, 0xCA, // aesenc xmm1, xmm2
+        0x66, 0x44, 0x0F, 0x38, 0xDC, 0x4F, 0x10, // aesenc xmm9, [rdi + 16]
+        0x66, 0x41, 0x0F, 0x38, 0xDD, 0xC7, // aesenclast xmm0, xmm15
+        0x66, 0x0F, 0x38, 0xDE, 0xDC, // aesdec xmm3, xmm4
+        0x66, 0x0F, 0x38, 0xDF, 0x28, // aesdeclast xmm5, [rax]
+        0x66, 0x0F, 0x38, 0xDB, 0xF7, // aesimc xmm6, xmm7
+        0x66, 0x0F, 0x3A, 0xDF, 0xCA, 0x1B, // aeskeygenassist xmm1, xmm2, 0x1b
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_sha() {
+    let bytes = encode(|e| {
+        e.sha1rnds4(XMM1, Operand::Xmm(XMM2), 3)?;
+        e.sha1nexte(XMM1, Operand::Xmm(XMM2))?;
+        e.sha1msg1(XMM3, Operand::Xmm(XMM4))?;
+        e.sha1msg2(XMM10, Operand::Xmm(XMM11))?;
+        e.sha256rnds2(XMM1, Operand::Xmm(XMM2))?;
+        e.sha256msg1(XMM1, Operand::Mem(MemOperand::new(RSI, 0)))?;
+        e.sha256msg2(XMM8, Operand::Xmm(XMM1))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x0F, 0x3A, 0xCC, 0xCA, 0x03, // sha1rnds4 xmm1, xmm2, 3
+        0x0F, 0x38, 0xC8, 0xCA, // sha1nexte xmm1, xmm2
+        0x0F, 0x38, 0xC9, 0xDC, // sha1msg1 xmm3, xmm4
+        0x45, 0x0F, 0x38, 0xCA, 0xD3, // sha1msg2 xmm10, xmm11
+        0x0F, 0x38, 0xCB, 0xCA, // sha256rnds2 xmm1, xmm2, <xmm0>
+        0x0F, 0x38, 0xCC, 0x0E, // sha256msg1 xmm1, [rsi]
+        0x44, 0x0F, 0x38, 0xCD, 0xC1, // sha256msg2 xmm8, xmm1
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_pclmulqdq() {
+    let bytes = encode(|e| {
+        e.pclmulqdq(XMM1, Operand::Xmm(XMM2), 0x11)?;
+        let mem = MemOperand::new(RBX, 0).with_index(RCX, Scale::S2);
+        e.pclmulqdq(XMM12, Operand::Mem(mem), 0x00)?;
+        e.vpclmulqdq(
+            Operand::Xmm(XMM1),
+            Operand::Xmm(XMM2),
+            Operand::Xmm(XMM3),
+            0x10,
+        )?;
+        e.vpclmulqdq(
+            Operand::Ymm(YMM1),
+            Operand::Ymm(YMM2),
+            Operand::Ymm(YMM13),
+            0x01,
+        )?;
+        e.vpclmulqdq(
+            Operand::Zmm(ZMM0),
+            Operand::Zmm(ZMM1),
+            Operand::Zmm(ZMM2),
+            0x11,
+        )?;
+        let mem = MemOperand::new(R8, -64).with_index(RAX, Scale::S4);
+        e.vpclmulqdq(
+            Operand::Zmm(ZMM20),
+            Operand::Zmm(ZMM21),
+            Operand::Mem(mem),
+            0x00,
+        )?;
+        Ok(())
+    });
+
+    let expected = [
+        0x66, 0x0F, 0x3A, 0x44, 0xCA, 0x11, // pclmulqdq xmm1, xmm2, 0x11
+        0x66, 0x44, 0x0F, 0x3A, 0x44, 0x24, 0x4B, 0x00, // pclmulqdq xmm12, [rbx + rcx*2], 0
+        0xC4, 0xE3, 0x69, 0x44, 0xCB, 0x10, // vpclmulqdq xmm1, xmm2, xmm3, 0x10
+        0xC4, 0xC3, 0x6D, 0x44, 0xCD, 0x01, // vpclmulqdq ymm1, ymm2, ymm13, 1
+        0x62, 0xF3, 0x75, 0x48, 0x44, 0xC2, 0x11, // vpclmulqdq zmm0, zmm1, zmm2, 0x11
+        // vpclmulqdq zmm20, zmm21, [r8 + rax*4 - 64], 0
+        0x62, 0xC3, 0x55, 0x40, 0x44, 0x64, 0x80, 0xFF, 0x00, // disp8*64 = -1
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_vaes() {
+    let bytes = encode(|e| {
+        e.vaesenc(Operand::Xmm(XMM1), Operand::Xmm(XMM2), Operand::Xmm(XMM3))?;
+        let mem = MemOperand::new(RDI, 32);
+        e.vaesenc(Operand::Ymm(YMM1), Operand::Ymm(YMM2), Operand::Mem(mem))?;
+        e.vaesenclast(Operand::Ymm(YMM8), Operand::Ymm(YMM9), Operand::Ymm(YMM10))?;
+        e.vaesdec(Operand::Xmm(XMM1), Operand::Xmm(XMM2), Operand::Xmm(XMM3))?;
+        e.vaesdeclast(Operand::Ymm(YMM1), Operand::Ymm(YMM2), Operand::Ymm(YMM3))?;
+        e.vaesimc(XMM1, Operand::Xmm(XMM2))?;
+        e.vaeskeygenassist(XMM1, Operand::Xmm(XMM9), 1)?;
+        Ok(())
+    });
+
+    let expected = [
+        0xC4, 0xE2, 0x69, 0xDC, 0xCB, // vaesenc xmm1, xmm2, xmm3
+        0xC4, 0xE2, 0x6D, 0xDC, 0x4F, 0x20, // vaesenc ymm1, ymm2, [rdi + 32]
+        0xC4, 0x42, 0x35, 0xDD, 0xC2, // vaesenclast ymm8, ymm9, ymm10
+        0xC4, 0xE2, 0x69, 0xDE, 0xCB, // vaesdec xmm1, xmm2, xmm3
+        0xC4, 0xE2, 0x6D, 0xDF, 0xCB, // vaesdeclast ymm1, ymm2, ymm3
+        0xC4, 0xE2, 0x79, 0xDB, 0xCA, // vaesimc xmm1, xmm2
+        0xC4, 0xC3, 0x79, 0xDF, 0xC9, 0x01, // vaeskeygenassist xmm1, xmm9, 1
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_vaes_evex() {
+    let bytes = encode(|e| {
+        e.vaesenc(Operand::Zmm(ZMM1), Operand::Zmm(ZMM2), Operand::Zmm(ZMM3))?;
+        e.vaesenc(Operand::Zmm(ZMM17), Operand::Zmm(ZMM2), Operand::Zmm(ZMM30))?;
+        let mem = MemOperand::new(RDI, 128);
+        e.vaesenc(Operand::Zmm(ZMM1), Operand::Zmm(ZMM18), Operand::Mem(mem))?;
+        let mem = MemOperand::new(RDI, 100);
+        e.vaesenc(Operand::Zmm(ZMM1), Operand::Zmm(ZMM2), Operand::Mem(mem))?;
+        e.vaesdeclast(Operand::Zmm(ZMM9), Operand::Zmm(ZMM10), Operand::Zmm(ZMM11))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x62, 0xF2, 0x6D, 0x48, 0xDC, 0xCB, // vaesenc zmm1, zmm2, zmm3
+        0x62, 0x82, 0x6D, 0x48, 0xDC, 0xCE, // vaesenc zmm17, zmm2, zmm30
+        0x62, 0xF2, 0x6D, 0x40, 0xDC, 0x4F, 0x02, // vaesenc zmm1, zmm18, [rdi + 128]
+        // vaesenc zmm1, zmm2, [rdi + 100]
+        0x62, 0xF2, 0x6D, 0x48, 0xDC, 0x8F, 0x64, 0x00, 0x00, 0x00, // not a multiple of 64
+        0x62, 0x52, 0x2D, 0x48, 0xDF, 0xCB, // vaesdeclast zmm9, zmm10, zmm11
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_crc32_all_source_sizes() {
+    let bytes = encode(|e| {
+        e.crc32(Operand::Reg32(EAX), Operand::Reg8(CL))?;
+        e.crc32(Operand::Reg32(EAX), Operand::Reg8(SIL))?;
+        e.crc32(Operand::Reg32(EAX), Operand::Reg8(AH))?;
+        e.crc32(Operand::Reg32(R9D), Operand::Reg8(R10B))?;
+        e.crc32(Operand::Reg32(EAX), Operand::Reg16(DX))?;
+        e.crc32(Operand::Reg32(EAX), Operand::Reg32(ECX))?;
+        e.crc32(Operand::Reg(RAX), Operand::Reg(RCX))?;
+        e.crc32(Operand::Reg(RAX), Operand::Reg8(BL))?;
+        Ok(())
+    });
+
+    let expected = [
+        0xF2, 0x0F, 0x38, 0xF0, 0xC1, // crc32 eax, cl
+        0xF2, 0x40, 0x0F, 0x38, 0xF0, 0xC6, // crc32 eax, sil
+        0xF2, 0x0F, 0x38, 0xF0, 0xC4, // crc32 eax, ah
+        0xF2, 0x45, 0x0F, 0x38, 0xF0, 0xCA, // crc32 r9d, r10b
+        0x66, 0xF2, 0x0F, 0x38, 0xF1, 0xC2, // crc32 eax, dx
+        0xF2, 0x0F, 0x38, 0xF1, 0xC1, // crc32 eax, ecx
+        0xF2, 0x48, 0x0F, 0x38, 0xF1, 0xC1, // crc32 rax, rcx
+        0xF2, 0x48, 0x0F, 0x38, 0xF0, 0xC3, // crc32 rax, bl
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_crc32_memory_sources() {
+    let bytes = encode(|e| {
+        let mem = MemOperand::new(RSI, 0);
+        e.crc32(
+            Operand::Reg32(EAX),
+            Operand::Mem(mem.with_size(MemSize::Byte)),
+        )?;
+        e.crc32(
+            Operand::Reg32(EAX),
+            Operand::Mem(mem.with_size(MemSize::Word)),
+        )?;
+        e.crc32(
+            Operand::Reg32(EAX),
+            Operand::Mem(mem.with_size(MemSize::Dword)),
+        )?;
+        let mem = MemOperand::new(RSI, 8).with_size(MemSize::Qword);
+        e.crc32(Operand::Reg(R8), Operand::Mem(mem))?;
+        Ok(())
+    });
+
+    let expected = [
+        0xF2, 0x0F, 0x38, 0xF0, 0x06, // crc32 eax, byte ptr [rsi]
+        0x66, 0xF2, 0x0F, 0x38, 0xF1, 0x06, // crc32 eax, word ptr [rsi]
+        0xF2, 0x0F, 0x38, 0xF1, 0x06, // crc32 eax, dword ptr [rsi]
+        0xF2, 0x4C, 0x0F, 0x38, 0xF1, 0x46, 0x08, // crc32 r8, qword ptr [rsi + 8]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+#[should_panic(expected = "needs an explicit size")]
+fn test_crc32_unsized_memory_is_rejected() {
+    encode(|e| e.crc32(Operand::Reg32(EAX), Operand::Mem(MemOperand::new(RSI, 0))));
+}
+
+#[test]
+#[should_panic(expected = "cannot be encoded in an instruction that needs a REX prefix")]
+fn test_crc32_high_byte_with_rex_is_rejected() {
+    encode(|e| e.crc32(Operand::Reg(RAX), Operand::Reg8(AH)));
+}
+
+#[test]
+fn test_gfni() {
+    let bytes = encode(|e| {
+        e.gf2p8affineinvqb(XMM1, Operand::Xmm(XMM2), 5)?;
+        e.gf2p8affineqb(XMM1, Operand::Mem(MemOperand::new(RAX, 0)), 0)?;
+        e.gf2p8mulb(XMM9, Operand::Xmm(XMM1))?;
+        e.vgf2p8affineinvqb(
+            Operand::Ymm(YMM1),
+            Operand::Ymm(YMM2),
+            Operand::Ymm(YMM3),
+            1,
+        )?;
+        e.vgf2p8affineqb(
+            Operand::Xmm(XMM1),
+            Operand::Xmm(XMM2),
+            Operand::Xmm(XMM3),
+            2,
+        )?;
+        e.vgf2p8mulb(Operand::Ymm(YMM1), Operand::Ymm(YMM2), Operand::Ymm(YMM3))?;
+        e.vgf2p8mulb(Operand::Zmm(ZMM1), Operand::Zmm(ZMM2), Operand::Zmm(ZMM3))?;
+        e.vgf2p8affineqb(
+            Operand::Zmm(ZMM1),
+            Operand::Zmm(ZMM2),
+            Operand::Zmm(ZMM3),
+            7,
+        )?;
+        e.vgf2p8affineinvqb(
+            Operand::Zmm(ZMM25),
+            Operand::Zmm(ZMM2),
+            Operand::Zmm(ZMM3),
+            0,
+        )?;
+        Ok(())
+    });
+
+    let expected = [
+        0x66, 0x0F, 0x3A, 0xCF, 0xCA, 0x05, // gf2p8affineinvqb xmm1, xmm2, 5
+        0x66, 0x0F, 0x3A, 0xCE, 0x08, 0x00, // gf2p8affineqb xmm1, [rax], 0
+        0x66, 0x44, 0x0F, 0x38, 0xCF, 0xC9, // gf2p8mulb xmm9, xmm1
+        0xC4, 0xE3, 0xED, 0xCF, 0xCB, 0x01, // vgf2p8affineinvqb ymm1, ymm2, ymm3, 1
+        0xC4, 0xE3, 0xE9, 0xCE, 0xCB, 0x02, // vgf2p8affineqb xmm1, xmm2, xmm3, 2
+        0xC4, 0xE2, 0x6D, 0xCF, 0xCB, // vgf2p8mulb ymm1, ymm2, ymm3
+        0x62, 0xF2, 0x6D, 0x48, 0xCF, 0xCB, // vgf2p8mulb zmm1, zmm2, zmm3
+        0x62, 0xF3, 0xED, 0x48, 0xCE, 0xCB, 0x07, // vgf2p8affineqb zmm1, zmm2, zmm3, 7
+        0x62, 0x63, 0xED, 0x48, 0xCF, 0xCB, 0x00, // vgf2p8affineinvqb zmm25, zmm2, zmm3, 0
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+#[should_panic(expected = "operands must have the same width")]
+fn test_mixed_vector_widths_are_rejected() {
+    encode(|e| e.vaesenc(Operand::Ymm(YMM1), Operand::Xmm(XMM2), Operand::Ymm(YMM3)));
+}
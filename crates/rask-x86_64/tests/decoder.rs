@@ -0,0 +1,458 @@
+mod common;
+use common::*;
+use rask_x86_64::decoder::{Decoder, Encoding};
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::instruction::{Condition, Instruction, Mnemonic, Prefix};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg8::{AH, AL, CL, DIL, R20B, SIL};
+use rask_x86_64::registers::Reg16::{AX, BP, BX, CX, SI};
+use rask_x86_64::registers::Reg32::{EAX, EBP, EBX, ECX, ESI, R12D, R25D};
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::FS;
+use rask_x86_64::registers::TmmReg::*;
+use rask_x86_64::registers::XmmReg::*;
+use rask_x86_64::registers::YmmReg::*;
+use rask_x86_64::registers::ZmmReg::*;
+use rask_x86_64::{RaskError, RaskResult};
+
+use Operand::{Imm, Mem, Reg, Reg8, Reg16, Reg32, Tmm, Xmm, Ymm, Zmm};
+
+fn apx() -> Encoder {
+    Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx))
+}
+
+/// Encodes `insn` in `mode`, decodes it back and checks that the decoder
+/// returns the same instruction, consumes every byte, and that the decoded
+/// instruction re-encodes to the same bytes.
+fn round_trip(mode: Mode, insn: Instruction) {
+    let mut enc = apx();
+    enc.set_mode(mode);
+    if let Err(err) = enc.encode(&insn) {
+        panic!("{insn:?}: {err}");
+    }
+    let bytes = enc.bytes().to_vec();
+
+    let decoded = match Decoder::with_mode(&bytes, mode).decode() {
+        Ok(decoded) => decoded,
+        Err(err) => panic!("{insn:?} ({bytes:02X?}): {err}"),
+    };
+    assert_eq!(decoded.instruction(), insn, "bytes {bytes:02X?}");
+    assert_eq!(decoded.bytes(), bytes);
+
+    let mut enc = apx();
+    enc.set_mode(mode);
+    enc.encode(&decoded.instruction()).unwrap();
+    assert_bytes(enc.bytes(), &bytes);
+}
+
+fn decode(bytes: &[u8]) -> RaskResult<Instruction> {
+    Decoder::new(bytes).decode().map(|d| d.instruction())
+}
+
+fn q(mem: MemOperand) -> Operand {
+    Mem(mem.with_size(MemSize::Qword))
+}
+
+fn d(mem: MemOperand) -> Operand {
+    Mem(mem.with_size(MemSize::Dword))
+}
+
+#[test]
+fn test_decode_integer_round_trip() {
+    use Mnemonic::*;
+
+    let sib = MemOperand::new(RSP, -0x80).with_index(R13, Scale::S8);
+    #[rustfmt::skip]
+    let cases = [
+        Instruction::with2(Add, Reg(RAX), Reg(RBX)),
+        Instruction::with2(Add, Reg(R10), Reg(R9)),
+        Instruction::with2(Mov, Reg32(R12D), d(sib)),
+        Instruction::with2(Mov, q(MemOperand::new(RBP, 0)), Reg(R15)),
+        Instruction::with2(Mov, q(MemOperand::new(R13, 0)), Reg(RAX)),
+        Instruction::with2(Mov, q(MemOperand::new(R12, 0x1234)), Reg(RAX)),
+        Instruction::with2(Mov, Reg(RAX), q(MemOperand::absolute(0x1000))),
+        Instruction::with2(Mov, Reg(RCX), q(MemOperand::new(RBX, 0).with_index(RSI, Scale::S2))),
+        Instruction::with2(Add, Mem(MemOperand::new(RBP, 0).with_size(MemSize::Byte)), Imm(0x7F)),
+        Instruction::with2(Sub, Reg(RSP), Imm(8)),
+        Instruction::with2(And, Reg32(EAX), Imm(0xFFFF_FFFF)),
+        Instruction::with2(Xor, Reg(R9), Imm(-2)),
+        Instruction::with2(Cmp, d(MemOperand::new(RDI, 4)), Imm(0x1234_5678)),
+        Instruction::with2(Or, Reg16(CX), Imm(0x8000)),
+        Instruction::with2(Mov, Reg(RAX), Imm(0x1122_3344_5566_7788)),
+        Instruction::with2(Mov, Reg(R11), Imm(-1)),
+        Instruction::with2(Mov, Reg8(CL), Reg8(AH)),
+        Instruction::with2(Mov, Reg8(SIL), Reg8(DIL)),
+        Instruction::with2(Mov, Reg8(AL), Imm(0xFF)),
+        Instruction::with1(Inc, q(MemOperand::rip(0x100))),
+        Instruction::with1(Dec, Reg32(ESI)),
+        Instruction::with2(Add, d(MemOperand::new(RDI, 0)), Reg32(ESI)).with_prefix(Prefix::Lock),
+        Instruction::with2(Mov, Reg32(EAX), d(MemOperand::new(RBX, 0).with_segment(FS))),
+        Instruction::with2(Mov, Reg32(EAX), d(MemOperand::new(EBX, 4))),
+        Instruction::new(Ret),
+        Instruction::new(Nop),
+        Instruction::new(Int3),
+        Instruction::with1(Jmp, Reg(RAX)),
+        Instruction::with1(Call, q(MemOperand::new(RAX, 8))),
+        Instruction::with1(Call, Reg(R11)),
+    ];
+    for insn in cases {
+        round_trip(Mode::Long64, insn);
+    }
+}
+
+#[test]
+fn test_decode_sse_and_cache_round_trip() {
+    use Mnemonic::*;
+
+    let m = MemOperand::new(RAX, 0x40);
+    #[rustfmt::skip]
+    let cases = [
+        Instruction::with2(Aesenc, Xmm(XMM1), Xmm(XMM2)),
+        Instruction::with2(Aesdeclast, Xmm(XMM9), Mem(m)),
+        Instruction::with3(Aeskeygenassist, Xmm(XMM0), Mem(m), Imm(1)),
+        Instruction::with2(Sha256rnds2, Xmm(XMM1), Xmm(XMM2)),
+        Instruction::with3(Sha1rnds4, Xmm(XMM3), Xmm(XMM4), Imm(3)),
+        Instruction::with3(Pclmulqdq, Xmm(XMM9), Xmm(XMM10), Imm(0x11)),
+        Instruction::with2(Crc32, Reg32(EAX), Mem(MemOperand::new(RSI, 0).with_size(MemSize::Byte))),
+        Instruction::with2(Crc32, Reg(RAX), q(MemOperand::new(RSI, 0))),
+        Instruction::with2(Crc32, Reg32(EBX), Reg16(SI)),
+        Instruction::with1(Clflush, Mem(m)),
+        Instruction::with1(Clflushopt, Mem(m)),
+        Instruction::with1(Clwb, Mem(m)),
+        Instruction::with1(Prefetcht0, Mem(m)),
+        Instruction::with1(Prefetchnta, Mem(m)),
+        Instruction::with1(Prefetchw, Mem(m)),
+        Instruction::new(Sfence),
+        Instruction::new(Lfence),
+        Instruction::new(Mfence),
+        Instruction::with2(Movnti, Mem(m), Reg(RAX)),
+        Instruction::with2(Movnti, Mem(m), Reg32(ECX)),
+        Instruction::with2(Movntdq, Mem(m), Xmm(XMM15)),
+        Instruction::with2(Movntps, Mem(m), Xmm(XMM1)),
+        Instruction::with2(Movntpd, Mem(m), Xmm(XMM1)),
+        Instruction::with2(Movntdqa, Xmm(XMM2), Mem(m)),
+        Instruction::with1(Rdfsbase, Reg(RAX)),
+        Instruction::with1(Wrgsbase, Reg32(ECX)),
+        Instruction::with2(Gf2p8mulb, Xmm(XMM1), Xmm(XMM2)),
+    ];
+    for insn in cases {
+        round_trip(Mode::Long64, insn);
+    }
+}
+
+#[test]
+fn test_decode_vex_evex_round_trip() {
+    use Mnemonic::*;
+
+    let m = MemOperand::new(RAX, 0x40);
+    #[rustfmt::skip]
+    let cases = [
+        Instruction::with3(Vaesenc, Ymm(YMM1), Ymm(YMM2), Ymm(YMM3)),
+        Instruction::with3(Vaesenclast, Xmm(XMM8), Xmm(XMM9), Mem(MemOperand::new(R12, 8))),
+        Instruction::with3(Vaesdec, Zmm(ZMM1), Zmm(ZMM17), Mem(m)),
+        Instruction::with3(Vaesdeclast, Zmm(ZMM31), Zmm(ZMM0), Zmm(ZMM20)),
+        Instruction::with2(Vaesimc, Xmm(XMM1), Xmm(XMM2)),
+        Instruction::with3(Vaeskeygenassist, Xmm(XMM1), Mem(m), Imm(0x80)),
+        Instruction::with4(Vpclmulqdq, Zmm(ZMM31), Zmm(ZMM2), Zmm(ZMM3), Imm(0)),
+        Instruction::with4(Vpclmulqdq, Ymm(YMM1), Ymm(YMM2), Ymm(YMM3), Imm(0x11)),
+        Instruction::with4(Vgf2p8affineqb, Ymm(YMM4), Ymm(YMM5), Mem(m), Imm(7)),
+        Instruction::with4(Vgf2p8affineinvqb, Zmm(ZMM4), Zmm(ZMM5), Mem(MemOperand::new(RAX, 0x41)), Imm(7)),
+        Instruction::with3(Vgf2p8mulb, Xmm(XMM1), Xmm(XMM2), Xmm(XMM3)),
+        Instruction::with2(Vmovntdq, Mem(m), Ymm(YMM4)),
+        Instruction::with2(Vmovntps, Mem(MemOperand::new(R9, 0)), Xmm(XMM1)),
+        Instruction::with2(Vmovntpd, Mem(m), Ymm(YMM12)),
+        Instruction::with2(Vmovntdqa, Ymm(YMM1), Mem(MemOperand::new(RCX, 0))),
+        Instruction::with1(Ldtilecfg, Mem(m)),
+        Instruction::with1(Sttilecfg, Mem(MemOperand::new(R8, 64))),
+        Instruction::with2(Tileloadd, Tmm(TMM1), Mem(MemOperand::new(RAX, 8).with_index(RCX, Scale::S4))),
+        Instruction::with2(Tileloaddt1, Tmm(TMM7), Mem(MemOperand::new(R9, 0))),
+        Instruction::with2(Tilestored, Mem(MemOperand::new(RDI, 0).with_index(RSI, Scale::S1)), Tmm(TMM2)),
+        Instruction::with1(Tilezero, Tmm(TMM3)),
+        Instruction::new(Tilerelease),
+        Instruction::with3(Tdpbssd, Tmm(TMM1), Tmm(TMM2), Tmm(TMM3)),
+        Instruction::with3(Tdpbuud, Tmm(TMM0), Tmm(TMM7), Tmm(TMM4)),
+        Instruction::with3(Tdpbf16ps, Tmm(TMM5), Tmm(TMM6), Tmm(TMM7)),
+    ];
+    for insn in cases {
+        round_trip(Mode::Long64, insn);
+    }
+}
+
+#[test]
+fn test_decode_apx_round_trip() {
+    use Mnemonic::*;
+
+    #[rustfmt::skip]
+    let cases = [
+        Instruction::with2(Mov, Reg(R16), Reg(R17)),
+        Instruction::with2(Add, Reg32(R25D), d(MemOperand::new(R18, 0).with_index(R19, Scale::S2))),
+        Instruction::with2(Mov, Reg8(R20B), Reg8(AL)),
+        Instruction::with2(Mov, Reg(R31), Imm(0x1122_3344_5566_7788)),
+        Instruction::with1(Rdfsbase, Reg(R30)),
+        Instruction::with2(Push2, Reg(RAX), Reg(RCX)),
+        Instruction::with2(Push2p, Reg(R16), Reg(R31)),
+        Instruction::with2(Pop2, Reg(RCX), Reg(RAX)),
+        Instruction::with2(Pop2p, Reg(R17), Reg(R8)),
+        Instruction::with3(Add, Reg(R20), Reg(R21), Reg(R22)),
+        Instruction::with3(Sub, Reg(RAX), q(MemOperand::new(R17, 8)), Reg(RBX)),
+        Instruction::with3(And, Reg(RAX), Reg(RBX), q(MemOperand::new(R24, 0).with_index(R25, Scale::S8))),
+        Instruction::with3(Xor, Reg(R31), Reg(RAX), Imm(0x12345)),
+        Instruction::with3(Or, Reg(RDX), q(MemOperand::new(RSP, 0)), Imm(-1)),
+    ];
+    for insn in cases {
+        round_trip(Mode::Long64, insn);
+    }
+}
+
+#[test]
+fn test_decode_legacy_modes_round_trip() {
+    use Mnemonic::*;
+
+    #[rustfmt::skip]
+    let protected = [
+        Instruction::with1(Inc, Reg32(EAX)),
+        Instruction::with1(Dec, Reg16(CX)),
+        Instruction::new(Pushad),
+        Instruction::new(Popa),
+        Instruction::with2(Les, Reg32(EAX), Mem(MemOperand::new(EBX, 0))),
+        Instruction::with2(Mov, Reg32(EAX), d(MemOperand::absolute(0x1000))),
+        Instruction::with2(Mov, Reg32(ECX), d(MemOperand::new(EBP, 0).with_index(ESI, Scale::S4))),
+        Instruction::with2(Mov, Reg16(AX), Mem(MemOperand::new(BX, 2).with_index(SI, Scale::S1).with_size(MemSize::Word))),
+        Instruction::with1(Jmp, d(MemOperand::new(EAX, 0))),
+        // EVEX disp8*64 with 16-bit addressing
+        Instruction::with3(Vaesdec, Zmm(ZMM1), Zmm(ZMM2), Mem(MemOperand::new(BX, 0x40).with_index(SI, Scale::S1))),
+    ];
+    for insn in protected {
+        round_trip(Mode::Protected32, insn);
+    }
+
+    #[rustfmt::skip]
+    let real = [
+        Instruction::with1(Inc, Reg16(AX)),
+        Instruction::with2(Mov, Reg32(EAX), Reg32(ECX)),
+        Instruction::with2(Mov, Reg16(AX), Mem(MemOperand::new(BX, 4).with_index(SI, Scale::S1).with_size(MemSize::Word))),
+        Instruction::with2(Mov, Reg16(AX), Mem(MemOperand::new(BP, 0).with_size(MemSize::Word))),
+        Instruction::with2(Mov, Reg16(BX), Mem(MemOperand::absolute(0x7C00).with_size(MemSize::Word))),
+        Instruction::with2(Add, Reg16(AX), Imm(0x1234)),
+        Instruction::with2(Mov, Reg32(EAX), d(MemOperand::new(EBX, 8))),
+        Instruction::with2(Lds, Reg16(SI), Mem(MemOperand::new(BX, 0))),
+        Instruction::with1(Call, Reg16(BX)),
+    ];
+    for insn in real {
+        round_trip(Mode::Real16, insn);
+    }
+}
+
+#[test]
+fn test_decode_rip_relative() {
+    // mov rax, qword ptr [rip + 0x10]
+    let bytes = encode(|e| e.mov(Reg(RAX), q(MemOperand::rip(0x10))));
+    assert_bytes(&bytes, &[0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00]);
+    assert_eq!(
+        decode(&bytes).unwrap(),
+        Instruction::with2(Mnemonic::Mov, Reg(RAX), q(MemOperand::rip(0x10)))
+    );
+
+    // Outside 64-bit mode the same ModR/M is an absolute disp32.
+    let insn = Decoder::with_mode(&bytes[1..], Mode::Protected32)
+        .decode()
+        .unwrap()
+        .instruction();
+    let abs = MemOperand::absolute(0x10).with_size(MemSize::Dword);
+    assert_eq!(
+        insn,
+        Instruction::with2(Mnemonic::Mov, Reg32(EAX), Mem(abs))
+    );
+}
+
+#[test]
+fn test_decode_branch_targets() {
+    let mut enc = Encoder::new();
+    let top = enc.create_label();
+    let out = enc.create_label();
+    enc.bind_label(top).unwrap();
+    enc.jcc(Condition::E, out).unwrap(); // 0: 0F 84 rel32
+    enc.call(Operand::Label(top)).unwrap(); // 6: E8 rel32
+    enc.jmp(Operand::Label(top)).unwrap(); // 11: EB rel8
+    enc.bind_label(out).unwrap();
+    enc.ret().unwrap(); // 13
+    let code = enc.finish().unwrap();
+
+    let mut decoder = Decoder::new(&code);
+    decoder.set_address(0x40_1000);
+    let decoded = decoder.collect::<RaskResult<Vec<_>>>().unwrap();
+    let targets: Vec<_> = decoded.iter().map(|d| d.branch_target()).collect();
+    assert_eq!(
+        targets,
+        [Some(0x40_100D), Some(0x40_1000), Some(0x40_1000), None]
+    );
+    assert_eq!(
+        decoded[0].instruction(),
+        Instruction::with1(Mnemonic::Je, Imm(0x40_100D))
+    );
+    assert_eq!(decoded[2].len(), 2);
+    assert_eq!(decoded[3].address(), 0x40_100D);
+
+    // 16-bit branches wrap at 64 KiB.
+    let back = Decoder::with_mode(&[0xE9, 0xFD, 0xFF], Mode::Real16)
+        .decode()
+        .unwrap();
+    assert_eq!(back.branch_target(), Some(0));
+    let wrap = Decoder::with_mode(&[0xEB, 0xF0], Mode::Real16)
+        .decode()
+        .unwrap();
+    assert_eq!(wrap.branch_target(), Some(0xFFF2));
+}
+
+#[test]
+fn test_decode_nops_and_padding() {
+    for mode in [Mode::Long64, Mode::Protected32, Mode::Real16] {
+        for len in 1..=15 {
+            let mut enc = Encoder::with_mode(mode);
+            enc.nop_n(len).unwrap();
+            let decoded = Decoder::with_mode(enc.bytes(), mode)
+                .decode()
+                .unwrap_or_else(|err| panic!("{mode:?} nop_n({len}): {err}"));
+            assert_eq!(decoded.instruction().mnemonic(), Mnemonic::Nop);
+            assert_eq!(decoded.len(), len, "{mode:?} nop_n({len})");
+        }
+    }
+
+    let mut enc = Encoder::new();
+    enc.ret().unwrap();
+    enc.align(16).unwrap();
+    let mnemonics = Decoder::new(enc.bytes())
+        .map(|d| d.unwrap().instruction().mnemonic())
+        .collect::<Vec<_>>();
+    assert_eq!(mnemonics, [Mnemonic::Ret, Mnemonic::Nop]);
+}
+
+#[test]
+fn test_decoded_metadata() {
+    // lock add dword ptr fs:[r8 + 4], eax
+    let bytes = [0x64, 0xF0, 0x41, 0x01, 0x40, 0x04];
+    let decoded = Decoder::new(&bytes).decode().unwrap();
+    assert_eq!(decoded.prefixes(), [0x64, 0xF0]);
+    assert_eq!(decoded.rex(), Some(0x41));
+    assert_eq!(decoded.encoding(), Encoding::Legacy);
+    assert_eq!(decoded.instruction().prefix(), Some(Prefix::Lock));
+    assert_eq!(decoded.end(), 6);
+
+    let vex = Decoder::new(&[0xC5, 0xF9, 0xE7, 0x00]).decode().unwrap();
+    assert_eq!(vex.encoding(), Encoding::Vex);
+    assert_eq!(vex.rex(), None);
+
+    // mov r16, rax
+    let rex2 = Decoder::new(&[0xD5, 0x18, 0x89, 0xC0]).decode().unwrap();
+    assert_eq!(rex2.encoding(), Encoding::Rex2);
+    assert_eq!(
+        rex2.instruction(),
+        Instruction::with2(Mnemonic::Mov, Reg(R16), Reg(RAX))
+    );
+
+    // A REX prefix followed by another prefix is ignored: 48 66 01 C3 is a
+    // 16-bit add.
+    let insn = decode(&[0x48, 0x66, 0x01, 0xC3]).unwrap();
+    assert_eq!(
+        insn,
+        Instruction::with2(Mnemonic::Add, Reg16(BX), Reg16(AX))
+    );
+}
+
+#[test]
+fn test_decode_errors() {
+    let truncated = |bytes: &[u8]| {
+        matches!(
+            decode(bytes),
+            Err(RaskError::TruncatedInstruction { offset: 0 })
+        )
+    };
+    let invalid = |bytes: &[u8]| matches!(decode(bytes), Err(RaskError::InvalidEncoding { .. }));
+    let unsupported =
+        |bytes: &[u8]| matches!(decode(bytes), Err(RaskError::UnsupportedEncoding { .. }));
+
+    assert!(truncated(&[]));
+    assert!(truncated(&[0x48]));
+    assert!(truncated(&[0x48, 0x8B]));
+    assert!(truncated(&[0x48, 0x8B, 0x80, 0x00]));
+    assert!(truncated(&[0x0F]));
+    assert!(truncated(&[0x0F, 0x38]));
+    assert!(truncated(&[0xE9, 0x00]));
+    assert!(truncated(&[0xC4, 0xE2]));
+
+    // ud2, hlt
+    assert!(unsupported(&[0x0F, 0x0B]));
+    assert!(unsupported(&[0xF4]));
+    // pause (F3 90) is not a plain nop.
+    assert!(unsupported(&[0xF3, 0x90]));
+    // Masked EVEX.
+    assert!(unsupported(&[0x62, 0xF2, 0x75, 0x49, 0xDC, 0xC3]));
+    // EVEX.256
+    assert!(unsupported(&[0x62, 0xF2, 0x75, 0x28, 0xDC, 0xC3]));
+
+    // LOCK on a register destination, and on mov.
+    assert!(invalid(&[0xF0, 0x01, 0xD8]));
+    assert!(invalid(&[0xF0, 0x89, 0x07]));
+    // 15 bytes of prefixes leave no room for the opcode.
+    let mut long = [0x66; 16];
+    long[15] = 0x90;
+    assert!(invalid(&long));
+    assert!(decode(&long[1..]).is_ok());
+    // VEX after a 66 prefix.
+    assert!(invalid(&[0x66, 0xC5, 0xF9, 0xE7, 0x00]));
+    // pop2 into the same register twice.
+    assert!(invalid(&[0x62, 0xF4, 0x74, 0x18, 0x8F, 0xC1]));
+    // Tile loads need a SIB byte.
+    assert!(invalid(&[0xC4, 0xE2, 0x7B, 0x4B, 0x08]));
+
+    let err = decode(&[0x0F, 0x0B]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unsupported instruction at offset 0: opcode 0F 0B"
+    );
+}
+
+#[test]
+fn test_decoder_resynchronises() {
+    let code = [0x90, 0xF4, 0xC3, 0x48];
+    let mut decoder = Decoder::new(&code);
+    decoder.set_address(0x100);
+    let results: Vec<_> = decoder.by_ref().collect();
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok());
+    assert!(matches!(
+        results[1],
+        Err(RaskError::UnsupportedEncoding { offset: 1, .. })
+    ));
+    assert_eq!(results[2].as_ref().unwrap().address(), 0x102);
+    assert!(matches!(
+        results[3],
+        Err(RaskError::TruncatedInstruction { offset: 3 })
+    ));
+    assert_eq!(decoder.position(), 4);
+
+    decoder.set_position(2);
+    let ret = decoder.decode().unwrap();
+    assert_eq!(ret.instruction().mnemonic(), Mnemonic::Ret);
+}
+
+#[test]
+fn test_decode_mode_specific_bytes() {
+    // 40 is a REX prefix in 64-bit mode and `inc eax` elsewhere.
+    let inc = Decoder::with_mode(&[0x40], Mode::Protected32)
+        .decode()
+        .unwrap();
+    assert_eq!(
+        inc.instruction(),
+        Instruction::with1(Mnemonic::Inc, Reg32(EAX))
+    );
+    // C4 with a memory ModR/M is LES outside 64-bit mode.
+    let les = Decoder::with_mode(&[0xC4, 0x03], Mode::Protected32)
+        .decode()
+        .unwrap();
+    assert_eq!(les.instruction().mnemonic(), Mnemonic::Les);
+    // PUSHA does not exist in 64-bit mode.
+    assert!(decode(&[0x60]).is_err());
+}
@@ -0,0 +1,118 @@
+mod common;
+use common::*;
+use rask_x86_64::RaskError;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg32::EAX;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::FS;
+use rask_x86_64::registers::XmmReg::XMM2;
+use rask_x86_64::registers::YmmReg::YMM1;
+
+#[test]
+fn test_failed_instruction_leaves_buffer_unchanged() {
+    let mut enc = Encoder::new();
+    enc.ret().unwrap();
+
+    // The segment prefix is emitted before the index register is checked.
+    let mem = MemOperand::new(RAX, 0)
+        .with_index(RSP, Scale::S1)
+        .with_segment(FS);
+    let err = enc.mov(Operand::Reg(RAX), Operand::Mem(mem)).unwrap_err();
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, reason, .. }
+            if mnemonic == "MOV" && reason == "RSP cannot be used as an index register"),
+        "{err:?}"
+    );
+    assert_bytes(enc.bytes(), &[0xC3]);
+
+    enc.ret().unwrap();
+    assert_bytes(enc.bytes(), &[0xC3, 0xC3]);
+}
+
+#[test]
+fn test_immediate_destination_is_an_error() {
+    let mut enc = Encoder::new();
+    let err = enc.mov(Operand::Imm(1), Operand::Reg(RAX)).unwrap_err();
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, .. } if mnemonic == "MOV"),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "invalid operands for MOV [Imm(1), Reg(RAX)]: \
+         no form of the instruction takes these operands"
+    );
+}
+
+#[test]
+fn test_mov_memory_immediate() {
+    let dst = Operand::Mem(MemOperand::new(RDI, 8).with_size(MemSize::Qword));
+    let bytes = encode(|e| e.mov(dst, Operand::Imm(-1)));
+    assert_bytes(&bytes, &[0x48, 0xC7, 0x47, 0x08, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+    let err = Encoder::new().mov(dst, Operand::Imm(1 << 31)).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            RaskError::ImmediateOutOfRange {
+                value: 0x8000_0000,
+                bits: 32,
+                ..
+            }
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_vector_width_mismatch_is_an_error() {
+    let mut enc = Encoder::new();
+    let err = enc
+        .vaesenc(Operand::Ymm(YMM1), Operand::Xmm(XMM2), Operand::Ymm(YMM1))
+        .unwrap_err();
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, reason, .. }
+            if mnemonic == "VAESENC" && reason == "operands must have the same width"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_missing_feature_is_an_error() {
+    let mut enc = Encoder::new();
+    let err = enc.push2(RAX, RCX).unwrap_err();
+    assert!(
+        matches!(&err, RaskError::UnsupportedFeature { what, feature }
+            if what == "PUSH2" && feature == "CpuFeature::Apx"),
+        "{err:?}"
+    );
+    assert_eq!(err.to_string(), "PUSH2 requires CpuFeature::Apx");
+}
+
+#[test]
+fn test_bad_alignment_is_an_error() {
+    let err = Encoder::new().align(24).unwrap_err();
+    assert_eq!(err.to_string(), "alignment must be a power of two, got 24");
+}
+
+#[test]
+fn test_panicking_adapter() {
+    let mut enc = Encoder::new();
+    let mut asm = enc.panicking();
+    asm.mov(Operand::Reg32(EAX), Operand::Imm(1));
+    asm.add(RAX, RBX);
+    asm.ret();
+    assert_bytes(
+        enc.bytes(),
+        &[0xB8, 0x01, 0x00, 0x00, 0x00, 0x48, 0x01, 0xD8, 0xC3],
+    );
+}
+
+#[test]
+#[should_panic(expected = "MOV immediate 4294967296 does not fit in 32 bits")]
+fn test_panicking_adapter_panics_with_the_error() {
+    let mut enc = Encoder::new();
+    enc.panicking()
+        .mov(Operand::Reg32(EAX), Operand::Imm(1 << 32));
+}
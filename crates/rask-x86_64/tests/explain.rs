@@ -0,0 +1,248 @@
+use rask_x86_64::decoder::{Decoder, Encoding};
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::explain::{Explanation, FieldKind, ModRmFields, RexBits, SibFields};
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::instruction::{Condition, Instruction, Mnemonic, Prefix};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg8::AL;
+use rask_x86_64::registers::Reg16::CX;
+use rask_x86_64::registers::Reg32::{EAX, EBX, ECX, ESI};
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::GS;
+use rask_x86_64::registers::TmmReg::*;
+use rask_x86_64::registers::XmmReg::*;
+use rask_x86_64::registers::YmmReg::*;
+use rask_x86_64::registers::ZmmReg::*;
+
+use Operand::{Imm, Mem, Reg, Reg8, Reg16, Reg32, Tmm, Xmm, Ymm, Zmm};
+
+fn apx() -> Encoder {
+    Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx))
+}
+
+fn explain(insn: Instruction) -> Explanation {
+    match apx().explain(&insn) {
+        Ok(explanation) => explanation,
+        Err(err) => panic!("{insn:?}: {err}"),
+    }
+}
+
+fn q(mem: MemOperand) -> Operand {
+    Mem(mem.with_size(MemSize::Qword))
+}
+
+#[test]
+fn test_explain_forms() {
+    use Mnemonic::*;
+
+    let m = MemOperand::new(RAX, 0x40);
+    #[rustfmt::skip]
+    let cases = [
+        (Instruction::with2(Add, Reg(RAX), Reg(RBX)), "REX.W + 01 /r"),
+        (Instruction::with2(Add, Reg32(EAX), Imm(1)), "83 /0 ib"),
+        (Instruction::with2(Add, Reg32(ECX), Imm(0x1234)), "81 /0 id"),
+        (Instruction::with2(Add, Reg8(AL), Imm(1)), "04 ib"),
+        (Instruction::with2(Or, Reg16(CX), Imm(0x1234)), "81 /1 iw"),
+        (Instruction::with2(Mov, Reg(RAX), Imm(0x1122_3344_5566_7788)), "REX.W + B8+rd io"),
+        (Instruction::with2(Mov, Reg32(ESI), Imm(7)), "B8+rd id"),
+        (Instruction::with2(Mov, Reg(R16), Reg(R17)), "REX2.W + 89 /r"),
+        (Instruction::with1(Inc, Reg32(EBX)), "FF /0"),
+        (Instruction::with2(Crc32, Reg(RAX), q(m)), "F2 REX.W 0F 38 F1 /r"),
+        (Instruction::with2(Aesenc, Xmm(XMM1), Xmm(XMM2)), "66 0F 38 DC /r"),
+        (Instruction::with3(Pclmulqdq, Xmm(XMM1), Xmm(XMM2), Imm(0x11)), "66 0F 3A 44 /r ib"),
+        (Instruction::new(Mfence), "0F AE F0"),
+        (Instruction::new(Ret), "C3"),
+        (Instruction::with1(Jmp, Reg(RAX)), "FF /4"),
+        (Instruction::with3(Vaesenc, Ymm(YMM1), Ymm(YMM2), Ymm(YMM3)), "VEX.256.66.0F38.W0 DC /r"),
+        (Instruction::with4(Vpclmulqdq, Zmm(ZMM1), Zmm(ZMM2), Zmm(ZMM3), Imm(0)), "EVEX.512.66.0F3A.W0 44 /r ib"),
+        (Instruction::with1(Ldtilecfg, Mem(m)), "VEX.128.NP.0F38.W0 49 /0"),
+        (Instruction::with3(Tdpbssd, Tmm(TMM1), Tmm(TMM2), Tmm(TMM3)), "VEX.128.F2.0F38.W0 5E /r"),
+        (Instruction::new(Tilerelease), "VEX.128.NP.0F38.W0 49 C0"),
+        (Instruction::with3(Add, Reg(R20), Reg(R21), Reg(R22)), "EVEX.128.NP.MAP4.W1 01 /r"),
+        (Instruction::with3(Sub, Reg(RAX), Reg(RBX), Imm(1)), "EVEX.128.NP.MAP4.W1 83 /5 ib"),
+    ];
+    for (insn, form) in cases {
+        assert_eq!(explain(insn).form(), form, "{insn}");
+    }
+}
+
+#[test]
+fn test_explain_branch_forms() {
+    let mut enc = Encoder::new();
+    let top = enc.create_label();
+    enc.bind_label(top).unwrap();
+    let short = enc
+        .explain(&Instruction::with1(Mnemonic::Jmp, Operand::Label(top)))
+        .unwrap();
+    assert_eq!(short.form(), "EB cb");
+    assert_eq!(short.immediate(), Some((0xFE, 1)));
+
+    let jne = Instruction::with1(Mnemonic::jcc(Condition::Ne), Operand::Label(top));
+    assert_eq!(enc.explain(&jne).unwrap().form(), "75 cb");
+
+    // jne rel32 and call rel32, decoded from bytes.
+    let code = [
+        0x0F, 0x85, 0x00, 0x01, 0x00, 0x00, 0xE8, 0xFB, 0xFF, 0xFF, 0xFF,
+    ];
+    let mut decoder = Decoder::new(&code);
+    let jne = Explanation::new(decoder.decode().unwrap());
+    assert_eq!(jne.form(), "0F 85 cd");
+    assert_eq!(jne.opcode(), &[0x0F, 0x85]);
+    assert_eq!(jne.immediate(), Some((0x100, 4)));
+    let call = Explanation::new(decoder.decode().unwrap());
+    assert_eq!(call.form(), "E8 cd");
+    assert!(call.to_string().contains("rel32    0xfffffffb"));
+}
+
+#[test]
+fn test_explain_fields() {
+    // add qword ptr gs:[r12 + r9*4 + 0x1000], 0x12345678
+    let mem = MemOperand::new(R12, 0x1000)
+        .with_index(R9, Scale::S4)
+        .with_segment(GS);
+    let insn = Instruction::with2(Mnemonic::Add, q(mem), Imm(0x1234_5678));
+    let e = explain(insn);
+    assert_eq!(
+        e.bytes(),
+        &[
+            0x65, 0x4B, 0x81, 0x84, 0x8C, 0x00, 0x10, 0x00, 0x00, 0x78, 0x56, 0x34, 0x12
+        ]
+    );
+    assert_eq!(e.prefixes(), &[0x65]);
+    assert_eq!(
+        e.rex(),
+        Some(RexBits {
+            w: true,
+            r: false,
+            x: true,
+            b: true
+        })
+    );
+    assert_eq!(e.opcode(), &[0x81]);
+    assert_eq!(
+        e.modrm(),
+        Some(ModRmFields {
+            md: 0b10,
+            reg: 0,
+            rm: 0b100
+        })
+    );
+    assert_eq!(
+        e.sib(),
+        Some(SibFields {
+            scale: 0b10,
+            index: 0b001,
+            base: 0b100
+        })
+    );
+    assert_eq!(e.displacement(), Some((0x1000, 4)));
+    assert_eq!(e.immediate(), Some((0x1234_5678, 4)));
+    assert_eq!(e.form(), "REX.W + 81 /0 id");
+
+    // The fields cover every byte, in order.
+    let mut next = 0;
+    for field in e.fields() {
+        assert_eq!(field.offset, next);
+        next += field.len;
+    }
+    assert_eq!(next, e.bytes().len());
+
+    // A negative disp8 keeps its sign.
+    let e = explain(Instruction::with2(
+        Mnemonic::Mov,
+        Reg(RAX),
+        q(MemOperand::new(RBP, -8)),
+    ));
+    assert_eq!(e.displacement(), Some((-8, 1)));
+    assert_eq!(e.sib(), None);
+}
+
+#[test]
+fn test_explain_prefixes_and_extensions() {
+    // lock add dword ptr [eax], ebx: LOCK, then the address-size override.
+    let mem = MemOperand::new(EAX, 0).with_size(MemSize::Dword);
+    let insn = Instruction::with2(Mnemonic::Add, Mem(mem), Reg32(EBX)).with_prefix(Prefix::Lock);
+    let e = explain(insn);
+    assert_eq!(e.prefixes(), &[0xF0, 0x67]);
+    let listing = e.to_string();
+    assert!(listing.contains("prefix   F0 LOCK"), "{listing}");
+    assert!(
+        listing.contains("prefix   67 address-size override"),
+        "{listing}"
+    );
+    assert_eq!(e.rex(), None);
+
+    // A mandatory prefix is told apart from an operand-size override.
+    let e = explain(Instruction::with2(Mnemonic::Aesenc, Xmm(XMM1), Xmm(XMM2)));
+    assert!(e.to_string().contains("66 mandatory prefix"));
+    let e = explain(Instruction::with2(Mnemonic::Mov, Reg16(CX), Imm(1)));
+    assert!(e.to_string().contains("66 operand-size override"));
+
+    // REX2 carries the fourth register bit.
+    let e = explain(Instruction::with2(Mnemonic::Mov, Reg(R16), Reg(R25)));
+    assert_eq!(e.decoded().encoding(), Encoding::Rex2);
+    assert!(matches!(
+        e.fields()[0].kind,
+        FieldKind::Rex2 {
+            r4: true,
+            b4: true,
+            m0: false,
+            ..
+        }
+    ));
+
+    // VEX and EVEX prefixes decode to their map, pp, length and vvvv.
+    let e = explain(Instruction::with3(
+        Mnemonic::Vaesenc,
+        Ymm(YMM1),
+        Ymm(YMM9),
+        Ymm(YMM3),
+    ));
+    match e.fields()[0].kind {
+        FieldKind::Vex(vex) => {
+            assert_eq!((vex.map, vex.pp, vex.len, vex.vvvv), (2, 1, 1, 9));
+            assert!(!vex.rex.r && !vex.rex.b);
+        }
+        kind => panic!("{kind:?}"),
+    }
+    let e = explain(Instruction::with3(
+        Mnemonic::Vaesdec,
+        Zmm(ZMM1),
+        Zmm(ZMM17),
+        Mem(MemOperand::new(RAX, 0x40)),
+    ));
+    match e.fields()[0].kind {
+        FieldKind::Evex(evex) => assert_eq!((evex.map, evex.len, evex.vvvv), (2, 2, 17)),
+        kind => panic!("{kind:?}"),
+    }
+    // EVEX disp8 is compressed: 0x40 is stored as 1 and scaled by 64.
+    assert_eq!(e.displacement(), Some((1, 1)));
+    assert!(e.to_string().contains("disp8    0x1 (disp8*N)"));
+}
+
+#[test]
+fn test_explain_listing() {
+    let e = explain(Instruction::with2(
+        Mnemonic::Mov,
+        Reg(RAX),
+        Imm(0x1122_3344_5566_7788),
+    ));
+    assert_eq!(
+        e.to_string(),
+        "\
+48 b8 88 77 66 55 44 33 22 11  mov rax, 0x1122334455667788  ; REX.W + B8+rd io
+48                             REX      0100WRXB  W=1 R=0 X=0 B=0
+   b8                          opcode   B8  register 000
+      88 77 66 55 44 33 22 11  imm64    0x1122334455667788"
+    );
+
+    let mut enc = Encoder::with_mode(Mode::Protected32);
+    enc.emit_instruction("pushad", &[]).unwrap();
+    let e = Explanation::new(
+        Decoder::with_mode(enc.bytes(), Mode::Protected32)
+            .decode()
+            .unwrap(),
+    );
+    assert_eq!(e.to_string(), "60  pushad  ; 60\n60  opcode   60");
+}
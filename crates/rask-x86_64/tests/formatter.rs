@@ -0,0 +1,232 @@
+use rask_x86_64::decoder::Decoder;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::formatter::{Formatter, HexStyle, Syntax};
+use rask_x86_64::instruction::{Condition, Instruction, Mnemonic, Prefix};
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg8::SIL;
+use rask_x86_64::registers::Reg16::{BP, DI, SI};
+use rask_x86_64::registers::Reg32::{EAX, ECX, EDX};
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::FS;
+use rask_x86_64::registers::XmmReg::{XMM1, XMM9};
+
+use Operand::{Imm, Mem, Reg, Reg8, Reg32, Xmm};
+
+/// Formats `insn` in each syntax and checks the Intel, AT&T and NASM text.
+fn check(insn: Instruction, intel: &str, att: &str, nasm: &str) {
+    assert_eq!(Formatter::new(Syntax::Intel).format(&insn), intel);
+    assert_eq!(Formatter::new(Syntax::Att).format(&insn), att);
+    assert_eq!(Formatter::new(Syntax::Nasm).format(&insn), nasm);
+}
+
+#[test]
+fn registers_and_immediates() {
+    check(
+        Instruction::with2(Mnemonic::Mov, Reg(RAX), Reg(R15)),
+        "mov rax, r15",
+        "mov %r15, %rax",
+        "mov rax, r15",
+    );
+    check(
+        Instruction::with2(Mnemonic::Add, Reg32(EAX), Imm(-2)),
+        "add eax, -0x2",
+        "add $-0x2, %eax",
+        "add eax, -0x2",
+    );
+    check(
+        Instruction::with2(Mnemonic::Xor, Reg8(SIL), Imm(0xFF)),
+        "xor sil, 0xff",
+        "xor $0xff, %sil",
+        "xor sil, 0xff",
+    );
+    check(
+        Instruction::with3(Mnemonic::Pclmulqdq, Xmm(XMM9), Xmm(XMM1), Imm(0x11)),
+        "pclmulqdq xmm9, xmm1, 0x11",
+        "pclmulqdq $0x11, %xmm1, %xmm9",
+        "pclmulqdq xmm9, xmm1, 0x11",
+    );
+    check(Instruction::new(Mnemonic::Ret), "ret", "ret", "ret");
+}
+
+#[test]
+fn memory_operands() {
+    let sib = MemOperand::new(RSP, -0x80)
+        .with_index(R13, Scale::S8)
+        .with_size(MemSize::Qword);
+    check(
+        Instruction::with2(Mnemonic::Mov, Reg(RAX), Mem(sib)),
+        "mov rax, [rsp+r13*8-0x80]",
+        "mov -0x80(%rsp,%r13,8), %rax",
+        "mov rax, [rsp+r13*8-0x80]",
+    );
+
+    let tls = MemOperand::absolute(0x28)
+        .with_segment(FS)
+        .with_size(MemSize::Qword);
+    check(
+        Instruction::with2(Mnemonic::Mov, Reg(RCX), Mem(tls)),
+        "mov rcx, fs:[0x28]",
+        "mov %fs:0x28, %rcx",
+        "mov rcx, [fs:0x28]",
+    );
+
+    let index_only = MemOperand::absolute(0x10).with_index(RSI, Scale::S4);
+    check(
+        Instruction::with2(Mnemonic::Mov, Reg32(EDX), Mem(index_only)),
+        "mov edx, [rsi*4+0x10]",
+        "mov 0x10(,%rsi,4), %edx",
+        "mov edx, [rsi*4+0x10]",
+    );
+
+    let rip = MemOperand::rip(-8);
+    check(
+        Instruction::with2(Mnemonic::Mov, Reg(RBX), Mem(rip)),
+        "mov rbx, [rip-0x8]",
+        "mov -0x8(%rip), %rbx",
+        "mov rbx, [rip-0x8]",
+    );
+
+    let real = MemOperand::new(BP, 4).with_index(DI, Scale::S1);
+    assert_eq!(real.to_string(), "[bp+di*1+0x4]");
+    assert_eq!(Formatter::new(Syntax::Att).format(&real), "0x4(%bp,%di,1)");
+    assert_eq!(MemOperand::new(SI, 0).to_string(), "[si]");
+}
+
+#[test]
+fn sizes_where_needed() {
+    let mem = MemOperand::new(RDI, 0).with_size(MemSize::Dword);
+    check(
+        Instruction::with2(Mnemonic::Add, Mem(mem), Imm(7)),
+        "add dword ptr [rdi], 0x7",
+        "addl $0x7, (%rdi)",
+        "add dword [rdi], 0x7",
+    );
+    check(
+        Instruction::with1(Mnemonic::Inc, Mem(mem.with_size(MemSize::Qword)))
+            .with_prefix(Prefix::Lock),
+        "lock inc qword ptr [rdi]",
+        "lock incq (%rdi)",
+        "lock inc qword [rdi]",
+    );
+    check(
+        Instruction::with2(
+            Mnemonic::Crc32,
+            Reg32(EAX),
+            Mem(mem.with_size(MemSize::Byte)),
+        ),
+        "crc32 eax, byte ptr [rdi]",
+        "crc32b (%rdi), %eax",
+        "crc32 eax, byte [rdi]",
+    );
+
+    // A memory operand without a size has nothing to write.
+    let clflush = Instruction::with1(Mnemonic::Clflush, Mem(MemOperand::new(RAX, 0)));
+    check(clflush, "clflush [rax]", "clflush (%rax)", "clflush [rax]");
+}
+
+#[test]
+fn options() {
+    let mem = MemOperand::new(RBX, 0x7F).with_size(MemSize::Qword);
+    let mov = Instruction::with2(Mnemonic::Mov, Reg(RAX), Mem(mem));
+    let add = Instruction::with2(Mnemonic::Add, Reg32(ECX), Imm(0xFFFF_FFFF));
+
+    let explicit = Formatter::new(Syntax::Intel).with_explicit_sizes(true);
+    assert_eq!(explicit.format(&mov), "mov rax, qword ptr [rbx+0x7f]");
+    let explicit = Formatter::new(Syntax::Att).with_explicit_sizes(true);
+    assert_eq!(explicit.format(&mov), "movq 0x7f(%rbx), %rax");
+
+    let masm = Formatter::new(Syntax::Nasm).with_hex_style(HexStyle::Suffix);
+    assert_eq!(masm.format(&mov), "mov rax, [rbx+7fh]");
+    assert_eq!(masm.format(&add), "add ecx, 0ffffffffh");
+
+    let upper = Formatter::new(Syntax::Intel)
+        .with_uppercase_hex(true)
+        .with_uppercase_mnemonics(true);
+    assert_eq!(upper.format(&add), "ADD ecx, 0xFFFFFFFF");
+    let lock = Instruction::with2(Mnemonic::Sub, Mem(mem), Reg(RDX)).with_prefix(Prefix::Lock);
+    assert_eq!(upper.format(&lock), "LOCK SUB [rbx+0x7F], rdx");
+    let upper = upper.with_hex_style(HexStyle::Suffix);
+    assert_eq!(upper.format(&add), "ADD ecx, 0FFFFFFFFH");
+}
+
+#[test]
+fn branches() {
+    let call = Instruction::with1(Mnemonic::Call, Imm(0x40_1000));
+    let jne = Instruction::with1(Mnemonic::jcc(Condition::Ne), Imm(0x40_2000));
+    check(call, "call 0x401000", "call 0x401000", "call 0x401000");
+
+    let fmt = Formatter::new(Syntax::Att).with_symbol_resolver(|addr| match addr {
+        0x40_1000 => Some("puts".to_string()),
+        _ => None,
+    });
+    assert_eq!(fmt.format(&call), "call puts");
+    assert_eq!(fmt.format(&jne), "jne 0x402000");
+
+    let indirect = Instruction::with1(Mnemonic::Jmp, Reg(RAX));
+    check(indirect, "jmp rax", "jmp *%rax", "jmp rax");
+    let table = MemOperand::new(RDI, 8).with_size(MemSize::Qword);
+    check(
+        Instruction::with1(Mnemonic::Call, Mem(table)),
+        "call qword ptr [rdi+0x8]",
+        "callq *0x8(%rdi)",
+        "call qword [rdi+0x8]",
+    );
+
+    let mut enc = Encoder::new();
+    let label = enc.create_label();
+    let jmp = Instruction::with1(Mnemonic::Jmp, Operand::Label(label));
+    assert_eq!(jmp.to_string(), format!("jmp {label}"));
+    assert_eq!(
+        Formatter::new(Syntax::Att).format(&jmp),
+        format!("jmp {label}")
+    );
+}
+
+#[test]
+fn standalone_values() {
+    for (syntax, rax, imm) in [
+        (Syntax::Intel, "rax", "0x10"),
+        (Syntax::Att, "%rax", "$0x10"),
+        (Syntax::Nasm, "rax", "0x10"),
+    ] {
+        let fmt = Formatter::new(syntax);
+        assert_eq!(fmt.format(&RAX), rax);
+        assert_eq!(fmt.format(&Reg(RAX)), rax);
+        assert_eq!(fmt.format(&Imm(16)), imm);
+    }
+    let mem = Mem(MemOperand::new(RAX, 0).with_size(MemSize::Word));
+    assert_eq!(mem.to_string(), "word ptr [rax]");
+    assert_eq!(Formatter::new(Syntax::Nasm).format(&mem), "word [rax]");
+    assert_eq!(
+        format!("{}", Formatter::new(Syntax::Att).display(&FS)),
+        "%fs"
+    );
+}
+
+#[test]
+fn decoded_listing() {
+    let code = [
+        0x48, 0x8B, 0x44, 0x24, 0x08, // mov rax, [rsp+8]
+        0x48, 0x83, 0xC0, 0xFF, // add rax, -1
+        0x75, 0xF5, // jne 0x1000
+        0xF0, 0x48, 0xFF, 0x07, // lock inc qword ptr [rdi]
+        0xC3, // ret
+    ];
+    let mut decoder = Decoder::new(&code);
+    decoder.set_address(0x1000);
+    let fmt = Formatter::new(Syntax::Intel)
+        .with_symbol_resolver(|addr| (addr == 0x1000).then(|| "loop".to_string()));
+    let listing: Vec<String> = decoder
+        .map(|insn| fmt.format(&insn.unwrap().instruction()))
+        .collect();
+    assert_eq!(
+        listing,
+        [
+            "mov rax, [rsp+0x8]",
+            "add rax, -0x1",
+            "jne loop",
+            "lock inc qword ptr [rdi]",
+            "ret"
+        ]
+    );
+}
@@ -0,0 +1,228 @@
+mod common;
+use common::*;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::instruction::{Instruction, MAX_OPERANDS, Mnemonic, Prefix};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg8::CL;
+use rask_x86_64::registers::Reg16::AX;
+use rask_x86_64::registers::Reg32::EAX;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::FS;
+use rask_x86_64::registers::TmmReg::*;
+use rask_x86_64::registers::XmmReg::*;
+use rask_x86_64::registers::YmmReg::*;
+use rask_x86_64::table::INSTRUCTIONS;
+use rask_x86_64::{RaskError, RaskResult};
+
+#[test]
+fn test_encode_matches_typed_methods() {
+    let m = MemOperand::new(RSI, 16);
+    #[rustfmt::skip]
+    let cases: Vec<(Instruction, Vec<u8>)> = vec![
+        (Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Imm(1337)),
+         encode(|e| e.mov(Operand::Reg(RAX), Operand::Imm(1337)))),
+        (Instruction::with2(Mnemonic::Add, Operand::Reg(R8), Operand::Reg(R9)),
+         encode(|e| e.add(R8, R9))),
+        (Instruction::with2(Mnemonic::Cmp, Operand::Mem(m.with_size(MemSize::Dword)), Operand::Imm(7)),
+         encode(|e| e.emit_instruction("cmp", &[Operand::Mem(m.with_size(MemSize::Dword)), Operand::Imm(7)]))),
+        (Instruction::new(Mnemonic::Ret), encode(|e| e.ret())),
+        (Instruction::with2(Mnemonic::Aesenc, Operand::Xmm(XMM1), Operand::Mem(m)),
+         encode(|e| e.aesenc(XMM1, Operand::Mem(m)))),
+        (Instruction::with4(Mnemonic::Vpclmulqdq, Operand::Ymm(YMM1), Operand::Ymm(YMM2),
+                            Operand::Ymm(YMM3), Operand::Imm(0x11)),
+         encode(|e| e.vpclmulqdq(Operand::Ymm(YMM1), Operand::Ymm(YMM2), Operand::Ymm(YMM3), 0x11))),
+        (Instruction::with2(Mnemonic::Vmovntdq, Operand::Mem(m), Operand::Ymm(YMM4)),
+         encode(|e| e.vmovntdq(m, Operand::Ymm(YMM4)))),
+        (Instruction::with1(Mnemonic::Tilezero, Operand::Tmm(TMM3)), encode(|e| e.tilezero(TMM3))),
+        (Instruction::with3(Mnemonic::Tdpbssd, Operand::Tmm(TMM1), Operand::Tmm(TMM2), Operand::Tmm(TMM3)),
+         encode(|e| e.tdpbssd(TMM1, TMM2, TMM3))),
+        (Instruction::new(Mnemonic::Mfence), encode(|e| e.mfence())),
+    ];
+
+    let enc = Encoder::new();
+    for (insn, expected) in cases {
+        let bytes = encode(|e| e.encode(&insn));
+        assert_eq!(bytes, expected, "{insn:?}");
+
+        assert_eq!(enc.encoded_len(&insn).unwrap(), expected.len(), "{insn:?}");
+        let (array, len) = enc.encode_to_array(&insn).unwrap();
+        assert_eq!(&array[..len], &expected[..], "{insn:?}");
+    }
+    assert!(enc.bytes().is_empty());
+}
+
+#[test]
+fn test_preview_uses_mode_and_features() -> RaskResult<()> {
+    let inc = Instruction::with1(Mnemonic::Inc, Operand::Reg16(AX));
+    let mut enc = Encoder::new();
+    assert_eq!(enc.encoded_len(&inc)?, 3);
+    enc.set_mode(Mode::Real16);
+    assert_eq!(enc.encoded_len(&inc)?, 1);
+
+    let push2 = Instruction::with2(Mnemonic::Push2, Operand::Reg(R16), Operand::Reg(RBX));
+    let enc = Encoder::new();
+    assert!(matches!(
+        enc.encoded_len(&push2),
+        Err(RaskError::UnsupportedFeature { .. })
+    ));
+    let enc = Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx));
+    let (bytes, len) = enc.encode_to_array(&push2)?;
+    assert_bytes(&bytes[..len], &[0x62, 0xF4, 0x7C, 0x10, 0xFF, 0xF3]);
+    Ok(())
+}
+
+#[test]
+fn test_encode_to_array_longest_instruction() -> RaskResult<()> {
+    // lock add qword ptr fs:[r8 + r9*8 + 0x12345678], 0x12345678
+    let dst = MemOperand::new(R8, 0x1234_5678)
+        .with_index(R9, Scale::S8)
+        .with_segment(FS)
+        .with_size(MemSize::Qword);
+    let insn = Instruction::with2(Mnemonic::Add, Operand::Mem(dst), Operand::Imm(0x1234_5678))
+        .with_prefix(Prefix::Lock);
+
+    let mut enc = Encoder::new();
+    enc.ret()?;
+    let (bytes, len) = enc.encode_to_array(&insn)?;
+    #[rustfmt::skip]
+    assert_bytes(&bytes[..len], &[
+        0xF0, 0x64, 0x4B, 0x81, 0x84, 0xC8,
+        0x78, 0x56, 0x34, 0x12, 0x78, 0x56, 0x34, 0x12,
+    ]);
+    assert_eq!(enc.encoded_len(&insn)?, len);
+    assert_bytes(enc.bytes(), &[0xC3]);
+    Ok(())
+}
+
+#[test]
+fn test_three_operand_alu_is_ndd() {
+    let mut enc = Encoder::with_features(CpuFeatures::new().with(CpuFeature::Apx));
+    let insn = Instruction::with3(
+        Mnemonic::Add,
+        Operand::Reg(R20),
+        Operand::Reg(R21),
+        Operand::Imm(1),
+    );
+    enc.encode(&insn).unwrap();
+    assert_bytes(enc.bytes(), &[0x62, 0xFC, 0xDC, 0x10, 0x83, 0xC5, 0x01]);
+}
+
+#[test]
+fn test_lock_prefix() {
+    let dword = MemOperand::new(RDI, 0).with_size(MemSize::Dword);
+    let qword = MemOperand::new(RDI, 0).with_size(MemSize::Qword);
+    let tls = MemOperand::new(RAX, 0).with_segment(FS);
+    let bytes = encode(|e| {
+        let lock = |insn: Instruction| insn.with_prefix(Prefix::Lock);
+        e.encode(&lock(Instruction::with2(
+            Mnemonic::Add,
+            Operand::Mem(dword),
+            Operand::Imm(1),
+        )))?;
+        e.encode(&lock(Instruction::with1(
+            Mnemonic::Inc,
+            Operand::Mem(qword),
+        )))?;
+        e.encode(&lock(Instruction::with2(
+            Mnemonic::Xor,
+            Operand::Mem(tls),
+            Operand::Reg8(CL),
+        )))
+    });
+
+    let expected = [
+        0xF0, 0x83, 0x07, 0x01, // lock add dword ptr [rdi], 1
+        0xF0, 0x48, 0xFF, 0x07, // lock inc qword ptr [rdi]
+        0xF0, 0x64, 0x30, 0x08, // lock xor byte ptr fs:[rax], cl
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_lock_prefix_is_validated() {
+    let m = Operand::Mem(MemOperand::new(RDI, 0).with_size(MemSize::Dword));
+    for insn in [
+        Instruction::with2(Mnemonic::Add, Operand::Reg32(EAX), m),
+        Instruction::with2(Mnemonic::Cmp, m, Operand::Imm(1)),
+        Instruction::with2(Mnemonic::Mov, m, Operand::Imm(1)),
+    ] {
+        let mut enc = Encoder::new();
+        let err = enc.encode(&insn.with_prefix(Prefix::Lock)).unwrap_err();
+        assert!(
+            matches!(&err, RaskError::InvalidOperands { reason, .. } if reason.contains("LOCK")),
+            "{err:?}"
+        );
+        assert!(enc.bytes().is_empty());
+    }
+}
+
+#[test]
+fn test_encode_reports_operand_mismatch() {
+    let mut enc = Encoder::new();
+    let insn = Instruction::with1(Mnemonic::Tilezero, Operand::Reg(RAX));
+    let err = enc.encode(&insn).unwrap_err();
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, .. } if mnemonic == "TILEZERO"),
+        "{err:?}"
+    );
+
+    let insn = Instruction::with4(
+        Mnemonic::Vpclmulqdq,
+        Operand::Xmm(XMM1),
+        Operand::Xmm(XMM2),
+        Operand::Xmm(XMM3),
+        Operand::Imm(256),
+    );
+    let err = enc.encode(&insn).unwrap_err();
+    assert!(
+        matches!(&err, RaskError::ImmediateOutOfRange { mnemonic, value: 256, bits: 8 }
+            if mnemonic == "VPCLMULQDQ"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_instruction_accessors() {
+    let mut insn = Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Reg(RBX));
+    assert_eq!(insn.mnemonic(), Mnemonic::Mov);
+    assert_eq!(insn.operand_count(), 2);
+    assert_eq!(insn.operands(), &[Operand::Reg(RAX), Operand::Reg(RBX)]);
+    assert_eq!(insn.operand(2), None);
+    assert_eq!(insn.prefix(), None);
+
+    // Rename RAX to RCX.
+    for op in insn.operands_mut() {
+        if *op == Operand::Reg(RAX) {
+            *op = Operand::Reg(RCX);
+        }
+    }
+    assert_eq!(
+        insn,
+        Instruction::from_operands(Mnemonic::Mov, &[Operand::Reg(RCX), Operand::Reg(RBX)]).unwrap()
+    );
+    assert_ne!(insn, insn.with_prefix(Prefix::Lock));
+
+    let too_many = [Operand::Imm(0); MAX_OPERANDS + 1];
+    assert!(Instruction::from_operands(Mnemonic::Mov, &too_many).is_err());
+}
+
+#[test]
+fn test_mnemonic_names() {
+    for &m in Mnemonic::ALL {
+        assert_eq!(m.as_str().parse::<Mnemonic>().unwrap(), m);
+    }
+    assert_eq!("VAESENC".parse::<Mnemonic>().unwrap(), Mnemonic::Vaesenc);
+    assert_eq!(Mnemonic::Sha256rnds2.to_string(), "sha256rnds2");
+    assert!("frobnicate".parse::<Mnemonic>().is_err());
+
+    // Every table-defined instruction can be named.
+    for forms in INSTRUCTIONS {
+        assert!(
+            forms[0].mnemonic.parse::<Mnemonic>().is_ok(),
+            "{}",
+            forms[0].mnemonic
+        );
+    }
+}
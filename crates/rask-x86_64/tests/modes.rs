@@ -0,0 +1,404 @@
+mod common;
+use common::*;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg8::SPL;
+use rask_x86_64::registers::Reg16::*;
+use rask_x86_64::registers::Reg32::*;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::{ES, FS};
+use rask_x86_64::registers::YmmReg::*;
+use rask_x86_64::{RaskError, RaskResult};
+
+/// Like [`encode`], but for the given processor mode.
+fn encode_in<F: FnOnce(&mut Encoder) -> RaskResult<()>>(mode: Mode, f: F) -> Vec<u8> {
+    let mut enc = Encoder::with_mode(mode);
+    if let Err(err) = f(&mut enc) {
+        panic!("{err}");
+    }
+    enc.bytes().to_vec()
+}
+
+#[test]
+fn test_operand_size_is_relative_to_mode() {
+    let real = encode_in(Mode::Real16, |e| {
+        e.mov(Operand::Reg16(AX), Operand::Imm(0x7C00))?;
+        e.mov(Operand::Reg32(EAX), Operand::Imm(1))?;
+        e.mov(Operand::Reg16(AX), Operand::Reg16(BX))?;
+        e.mov(Operand::Reg32(EAX), Operand::Reg32(ECX))?;
+        Ok(())
+    });
+    let protected = encode_in(Mode::Protected32, |e| {
+        e.mov(Operand::Reg16(AX), Operand::Imm(0x7C00))?;
+        e.mov(Operand::Reg32(EAX), Operand::Imm(1))?;
+        e.mov(Operand::Reg16(AX), Operand::Reg16(BX))?;
+        e.mov(Operand::Reg32(EAX), Operand::Reg32(ECX))?;
+        Ok(())
+    });
+
+    let expected_real = [
+        0xB8, 0x00, 0x7C, // mov ax, 0x7C00
+        0x66, 0xB8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+        0x89, 0xD8, // mov ax, bx
+        0x66, 0x89, 0xC8, // mov eax, ecx
+    ];
+    let expected_protected = [
+        0x66, 0xB8, 0x00, 0x7C, // mov ax, 0x7C00
+        0xB8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+        0x66, 0x89, 0xD8, // mov ax, bx
+        0x89, 0xC8, // mov eax, ecx
+    ];
+    assert_bytes(&real, &expected_real);
+    assert_bytes(&protected, &expected_protected);
+}
+
+#[test]
+fn test_16bit_addressing() {
+    let bytes = encode_in(Mode::Real16, |e| {
+        let ax = Operand::Reg16(AX);
+        e.mov(
+            ax,
+            Operand::Mem(MemOperand::new(BX, 0).with_index(SI, Scale::S1)),
+        )?;
+        e.mov(
+            ax,
+            Operand::Mem(MemOperand::new(BX, 0).with_index(DI, Scale::S1)),
+        )?;
+        e.mov(
+            ax,
+            Operand::Mem(MemOperand::new(BP, 4).with_index(SI, Scale::S1)),
+        )?;
+        e.mov(
+            ax,
+            Operand::Mem(MemOperand::new(BP, 0).with_index(DI, Scale::S1)),
+        )?;
+        e.mov(ax, Operand::Mem(MemOperand::new(SI, -2)))?;
+        e.mov(ax, Operand::Mem(MemOperand::new(DI, 0)))?;
+        e.mov(ax, Operand::Mem(MemOperand::new(BP, 0)))?;
+        e.mov(ax, Operand::Mem(MemOperand::new(BX, 0)))?;
+        let mem = MemOperand::new(BP, 0x100).with_index(DI, Scale::S1);
+        e.mov(Operand::Mem(mem), Operand::Reg16(CX))?;
+        e.mov(ax, Operand::Mem(MemOperand::absolute(0x7C00)))?;
+        e.mov(ax, Operand::Mem(MemOperand::new(DI, 0).with_segment(ES)))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x8B, 0x00, // mov ax, [bx + si]
+        0x8B, 0x01, // mov ax, [bx + di]
+        0x8B, 0x42, 0x04, // mov ax, [bp + si + 4]
+        0x8B, 0x03, // mov ax, [bp + di]
+        0x8B, 0x44, 0xFE, // mov ax, [si - 2]
+        0x8B, 0x05, // mov ax, [di]
+        0x8B, 0x46, 0x00, // mov ax, [bp] (zero disp8)
+        0x8B, 0x07, // mov ax, [bx]
+        0x89, 0x8B, 0x00, 0x01, // mov [bp + di + 0x100], cx
+        0x8B, 0x06, 0x00, 0x7C, // mov ax, [0x7C00]
+        0x26, 0x8B, 0x05, // mov ax, es:[di]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_address_size_is_relative_to_mode() {
+    let real = encode_in(Mode::Real16, |e| {
+        let mem = MemOperand::new(ECX, 0).with_index(EDX, Scale::S4);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(mem))?;
+        Ok(())
+    });
+    let protected = encode_in(Mode::Protected32, |e| {
+        let mem = MemOperand::new(ECX, 0).with_index(EDX, Scale::S4);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(mem))?;
+        e.mov(Operand::Reg32(EAX), Operand::Mem(MemOperand::new(EBP, 0)))?;
+        e.mov(Operand::Reg32(EAX), Operand::Mem(MemOperand::new(ESP, 8)))?;
+        let mem = MemOperand::new(BX, 0).with_index(SI, Scale::S1);
+        e.mov(Operand::Reg16(AX), Operand::Mem(mem))?;
+        e.mov(
+            Operand::Reg32(EAX),
+            Operand::Mem(MemOperand::absolute(0x1000)),
+        )?;
+        let mem = MemOperand::absolute(0x10).with_index(ECX, Scale::S4);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(mem))?;
+        let tls = MemOperand::absolute(0).with_segment(FS);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(tls))?;
+        Ok(())
+    });
+
+    // mov eax, [ecx + edx*4]
+    let expected_real = [0x67, 0x66, 0x8B, 0x04, 0x91];
+    let expected_protected = [
+        0x8B, 0x04, 0x91, // mov eax, [ecx + edx*4]
+        0x8B, 0x45, 0x00, // mov eax, [ebp]
+        0x8B, 0x44, 0x24, 0x08, // mov eax, [esp + 8]
+        0x67, 0x66, 0x8B, 0x00, // mov ax, [bx + si]
+        0x8B, 0x05, 0x00, 0x10, 0x00, 0x00, // mov eax, [0x1000] (no SIB)
+        0x8B, 0x04, 0x8D, 0x10, 0x00, 0x00, 0x00, // mov eax, [ecx*4 + 0x10]
+        0x64, 0x8B, 0x05, 0x00, 0x00, 0x00, 0x00, // mov eax, fs:[0]
+    ];
+    assert_bytes(&real, &expected_real);
+    assert_bytes(&protected, &expected_protected);
+}
+
+#[test]
+fn test_real_mode_absolute_addresses_beyond_16_bits() {
+    let real = encode_in(Mode::Real16, |e| {
+        let near = MemOperand::absolute(0x1234);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(near))?;
+        let far = MemOperand::absolute(0x12345);
+        e.mov(Operand::Reg32(EAX), Operand::Mem(far))?;
+        Ok(())
+    });
+    let expected = [
+        0x66, 0x8B, 0x06, 0x34, 0x12, // mov eax, [0x1234] (disp16)
+        0x67, 0x66, 0x8B, 0x05, 0x45, 0x23, 0x01, 0x00, // mov eax, [0x12345] (disp32)
+    ];
+    assert_bytes(&real, &expected);
+}
+
+#[test]
+fn test_inc_dec_forms() {
+    let real = encode_in(Mode::Real16, |e| {
+        e.inc(Operand::Reg16(AX))?;
+        e.inc(Operand::Reg32(EAX))?;
+        e.dec(Operand::Reg16(SI))?;
+        e.inc(Operand::Mem(
+            MemOperand::new(BX, 0).with_size(MemSize::Byte),
+        ))?;
+        e.inc(Operand::Mem(
+            MemOperand::new(BX, 0).with_size(MemSize::Word),
+        ))?;
+        e.dec(Operand::Mem(
+            MemOperand::new(BX, 0).with_size(MemSize::Dword),
+        ))?;
+        Ok(())
+    });
+    let protected = encode_in(Mode::Protected32, |e| {
+        e.inc(Operand::Reg32(EAX))?;
+        e.inc(Operand::Reg16(AX))?;
+        e.dec(Operand::Reg32(EDI))?;
+        e.inc(Operand::Mem(
+            MemOperand::new(EAX, 0).with_size(MemSize::Dword),
+        ))?;
+        Ok(())
+    });
+    let long = encode(|e| {
+        e.inc(Operand::Reg32(EAX))?;
+        e.dec(Operand::Reg16(SI))?;
+        e.inc(Operand::Reg(R9))?;
+        e.inc(Operand::Mem(
+            MemOperand::new(RDI, 0).with_size(MemSize::Qword),
+        ))?;
+        e.dec(Operand::Reg8(SPL))?;
+        Ok(())
+    });
+
+    let expected_real = [
+        0x40, // inc ax
+        0x66, 0x40, // inc eax
+        0x4E, // dec si
+        0xFE, 0x07, // inc byte ptr [bx]
+        0xFF, 0x07, // inc word ptr [bx]
+        0x66, 0xFF, 0x0F, // dec dword ptr [bx]
+    ];
+    let expected_protected = [
+        0x40, // inc eax
+        0x66, 0x40, // inc ax
+        0x4F, // dec edi
+        0xFF, 0x00, // inc dword ptr [eax]
+    ];
+    let expected_long = [
+        0xFF, 0xC0, // inc eax
+        0x66, 0xFF, 0xCE, // dec si
+        0x49, 0xFF, 0xC1, // inc r9
+        0x48, 0xFF, 0x07, // inc qword ptr [rdi]
+        0x40, 0xFE, 0xCC, // dec spl
+    ];
+    assert_bytes(&real, &expected_real);
+    assert_bytes(&protected, &expected_protected);
+    assert_bytes(&long, &expected_long);
+}
+
+#[test]
+fn test_legacy_only_instructions() {
+    let real = encode_in(Mode::Real16, |e| {
+        e.pusha()?;
+        e.pushad()?;
+        e.popa()?;
+        e.popad()?;
+        e.les(Operand::Reg16(DI), MemOperand::new(BX, 0))?;
+        e.lds(Operand::Reg16(SI), MemOperand::new(BP, 8))?;
+        e.les(Operand::Reg32(EAX), MemOperand::new(BX, 0))?;
+        Ok(())
+    });
+    let protected = encode_in(Mode::Protected32, |e| {
+        e.pusha()?;
+        e.pushad()?;
+        e.les(Operand::Reg32(EAX), MemOperand::new(ECX, 0))?;
+        e.lds(Operand::Reg16(DI), MemOperand::new(EBX, 0))?;
+        Ok(())
+    });
+
+    let expected_real = [
+        0x60, // pusha
+        0x66, 0x60, // pushad
+        0x61, // popa
+        0x66, 0x61, // popad
+        0xC4, 0x3F, // les di, [bx]
+        0xC5, 0x76, 0x08, // lds si, [bp + 8]
+        0x66, 0xC4, 0x07, // les eax, [bx]
+    ];
+    let expected_protected = [
+        0x66, 0x60, // pusha
+        0x60, // pushad
+        0xC4, 0x01, // les eax, [ecx]
+        0x66, 0xC5, 0x3B, // lds di, [ebx]
+    ];
+    assert_bytes(&real, &expected_real);
+    assert_bytes(&protected, &expected_protected);
+}
+
+#[test]
+fn test_sse_and_vex_outside_long_mode() {
+    let real = encode_in(Mode::Real16, |e| {
+        let mem = |size| Operand::Mem(MemOperand::new(BX, 0).with_size(size));
+        e.crc32(Operand::Reg32(EAX), mem(MemSize::Byte))?;
+        e.crc32(Operand::Reg32(EAX), mem(MemSize::Word))?;
+        e.crc32(Operand::Reg32(EAX), mem(MemSize::Dword))?;
+        e.movnti(MemOperand::new(BX, 0), Operand::Reg32(EAX))?;
+        let src = Operand::Mem(MemOperand::new(BX, 0));
+        e.vaesenc(Operand::Ymm(YMM1), Operand::Ymm(YMM2), src)?;
+        Ok(())
+    });
+    let protected = encode_in(Mode::Protected32, |e| {
+        let mem = |size| Operand::Mem(MemOperand::new(ECX, 0).with_size(size));
+        e.crc32(Operand::Reg32(EAX), mem(MemSize::Word))?;
+        e.crc32(Operand::Reg32(EAX), mem(MemSize::Dword))?;
+        Ok(())
+    });
+
+    let expected_real = [
+        0xF2, 0x0F, 0x38, 0xF0, 0x07, // crc32 eax, byte ptr [bx]
+        0xF2, 0x0F, 0x38, 0xF1, 0x07, // crc32 eax, word ptr [bx]
+        0x66, 0xF2, 0x0F, 0x38, 0xF1, 0x07, // crc32 eax, dword ptr [bx]
+        0x0F, 0xC3, 0x07, // movnti [bx], eax
+        0xC4, 0xE2, 0x6D, 0xDC, 0x0F, // vaesenc ymm1, ymm2, [bx]
+    ];
+    let expected_protected = [
+        0x66, 0xF2, 0x0F, 0x38, 0xF1, 0x01, // crc32 eax, word ptr [ecx]
+        0xF2, 0x0F, 0x38, 0xF1, 0x01, // crc32 eax, dword ptr [ecx]
+    ];
+    assert_bytes(&real, &expected_real);
+    assert_bytes(&protected, &expected_protected);
+}
+
+#[test]
+fn test_mode_switch_mid_stream() -> RaskResult<()> {
+    let mut enc = Encoder::with_mode(Mode::Real16);
+    enc.mov(Operand::Reg16(AX), Operand::Imm(1))?;
+    enc.set_mode(Mode::Protected32);
+    enc.mov(Operand::Reg32(EAX), Operand::Imm(1))?;
+    enc.set_mode(Mode::Long64);
+    enc.mov(Operand::Reg(RAX), Operand::Imm(1))?;
+    assert_eq!(enc.mode(), Mode::Long64);
+
+    let expected = [
+        0xB8, 0x01, 0x00, // mov ax, 1 (16-bit)
+        0xB8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1 (32-bit)
+        // mov rax, 1 (64-bit)
+        0x48, 0xB8, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // imm64
+    ];
+    assert_bytes(enc.bytes(), &expected);
+    Ok(())
+}
+
+#[test]
+fn test_real_mode_nops_use_32bit_addressing() {
+    let bytes = encode_in(Mode::Real16, |e| {
+        for len in [3, 4, 6, 9, 15] {
+            e.nop_n(len)?;
+        }
+        Ok(())
+    });
+
+    #[rustfmt::skip]
+    let expected = [
+        0x0F, 0x1F, 0x00,
+        0x67, 0x0F, 0x1F, 0x00,
+        0x67, 0x0F, 0x1F, 0x44, 0x00, 0x00,
+        0x67, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x67, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x0F, 0x1F, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+/// Encodes with `f` in `mode` and returns the error it must produce.
+fn encode_err_in<F: FnOnce(&mut Encoder) -> RaskResult<()>>(mode: Mode, f: F) -> RaskError {
+    let mut enc = Encoder::with_mode(mode);
+    let err = f(&mut enc).unwrap_err();
+    assert!(
+        enc.bytes().is_empty(),
+        "failed instruction left bytes behind"
+    );
+    err
+}
+
+/// Asserts that `err` reports `what` as needing `feature`.
+fn assert_unsupported(err: RaskError, what: &str, feature: &str) {
+    match err {
+        RaskError::UnsupportedFeature {
+            what: w,
+            feature: f,
+        } => {
+            assert_eq!((w.as_str(), f.as_str()), (what, feature));
+        }
+        other => panic!("expected UnsupportedFeature, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_64bit_registers_rejected_outside_long_mode() {
+    let err = encode_err_in(Mode::Protected32, |e| {
+        e.mov(Operand::Reg(RAX), Operand::Imm(1))
+    });
+    assert_unsupported(err, "64-bit operand size", "64-bit mode");
+}
+
+#[test]
+fn test_rex_rejected_outside_long_mode() {
+    let err = encode_err_in(Mode::Protected32, |e| e.inc(Operand::Reg8(SPL)));
+    let what = "the REX prefix (64-bit operands, R8–R15, SPL–DIL)";
+    assert_unsupported(err, what, "64-bit mode");
+}
+
+#[test]
+fn test_64bit_addressing_rejected_outside_long_mode() {
+    let err = encode_err_in(Mode::Protected32, |e| {
+        e.mov(Operand::Reg32(EAX), Operand::Mem(MemOperand::new(RAX, 0)))
+    });
+    assert_unsupported(err, "64-bit addressing", "64-bit mode");
+}
+
+#[test]
+fn test_16bit_addressing_rejected_in_long_mode() {
+    let err = encode_err_in(Mode::Long64, |e| {
+        e.mov(Operand::Reg16(AX), Operand::Mem(MemOperand::new(BX, 0)))
+    });
+    assert_unsupported(err, "16-bit addressing", "16- or 32-bit mode");
+}
+
+#[test]
+#[should_panic(expected = "is not a valid 16-bit address")]
+fn test_invalid_16bit_register_combination() {
+    encode_in(Mode::Real16, |e| {
+        let mem = MemOperand::new(BX, 0).with_index(BP, Scale::S1);
+        e.mov(Operand::Reg16(AX), Operand::Mem(mem))?;
+        Ok(())
+    });
+}
+
+#[test]
+fn test_pusha_rejected_in_long_mode() {
+    let err = encode_err_in(Mode::Long64, |e| e.pusha());
+    assert_unsupported(err, "PUSHA", "16- or 32-bit mode");
+}
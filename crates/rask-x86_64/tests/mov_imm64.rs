@@ -6,8 +6,9 @@ use rask_x86_64::registers::Reg64::*;
 #[test]
 fn test_mov_rax_and_r10_imm64() {
     let bytes = encode(|e| {
-        e.mov(Reg(RAX), Imm(1337));
-        e.mov(Reg(R10), Imm(42));
+        e.mov(Reg(RAX), Imm(1337))?;
+        e.mov(Reg(R10), Imm(42))?;
+        Ok(())
     });
 
     // 48 b8 39 05 00 00 00 00 00 00    mov rax, 1337
@@ -1,14 +1,18 @@
 mod common;
 use common::*;
-use rask_x86_64::operand::{MemOperand, Operand};
+use rask_x86_64::RaskError;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::operand::{MemOperand, Operand, Scale};
 use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::XmmReg::XMM0;
 
 #[test]
 fn test_mov_reg_mem() {
     let bytes = encode(|e| {
         // mov rax, [rbx]
-        let mem = MemOperand { base: RBX, disp: 0 };
-        e.mov(Operand::Reg(RAX), Operand::Mem(mem));
+        let mem = MemOperand::new(RBX, 0);
+        e.mov(Operand::Reg(RAX), Operand::Mem(mem))?;
+        Ok(())
     });
 
     // REX.W + 8B /r: 48 8b 03
@@ -20,8 +24,9 @@ fn test_mov_reg_mem() {
 fn test_mov_mem_reg() {
     let bytes = encode(|e| {
         // mov [rbx], rax
-        let mem = MemOperand { base: RBX, disp: 0 };
-        e.mov(Operand::Mem(mem), Operand::Reg(RAX));
+        let mem = MemOperand::new(RBX, 0);
+        e.mov(Operand::Mem(mem), Operand::Reg(RAX))?;
+        Ok(())
     });
 
     // REX.W + 89 /r: 48 89 03
@@ -33,8 +38,9 @@ fn test_mov_mem_reg() {
 fn test_mov_with_displacement() {
     let bytes = encode(|e| {
         // mov rax, [rbx + 8]
-        let mem = MemOperand { base: RBX, disp: 8 };
-        e.mov(Operand::Reg(RAX), Operand::Mem(mem));
+        let mem = MemOperand::new(RBX, 8);
+        e.mov(Operand::Reg(RAX), Operand::Mem(mem))?;
+        Ok(())
     });
 
     // REX.W + 8B /r + disp8: 48 8b 43 08
@@ -46,11 +52,9 @@ fn test_mov_with_displacement() {
 fn test_mov_with_large_displacement() {
     let bytes = encode(|e| {
         // mov rax, [rbx + 1000]
-        let mem = MemOperand {
-            base: RBX,
-            disp: 1000,
-        };
-        e.mov(Operand::Reg(RAX), Operand::Mem(mem));
+        let mem = MemOperand::new(RBX, 1000);
+        e.mov(Operand::Reg(RAX), Operand::Mem(mem))?;
+        Ok(())
     });
 
     // REX.W + 8B /r + disp32: 48 8b 83 e8 03 00 00
@@ -62,11 +66,67 @@ fn test_mov_with_large_displacement() {
 fn test_mov_with_extended_registers() {
     let bytes = encode(|e| {
         // mov r10, [r11]
-        let mem = MemOperand { base: R11, disp: 0 };
-        e.mov(Operand::Reg(R10), Operand::Mem(mem));
+        let mem = MemOperand::new(R11, 0);
+        e.mov(Operand::Reg(R10), Operand::Mem(mem))?;
+        Ok(())
     });
 
     // REX.W+R+B + 8B /r: 4d 8b 13
     let expected = [0x4D, 0x8B, 0x13];
     assert_bytes(&bytes, &expected);
 }
+
+#[test]
+fn test_mov_with_rsp_and_r12_base_uses_sib() {
+    let bytes = encode(|e| {
+        // mov rax, [rsp]
+        e.mov(Operand::Reg(RAX), Operand::Mem(MemOperand::new(RSP, 0)))?;
+        // mov [r12 + 8], rbx
+        e.mov(Operand::Mem(MemOperand::new(R12, 8)), Operand::Reg(RBX))?;
+        Ok(())
+    });
+
+    // r/m = 100 is the SIB escape, so these bases need a SIB byte (24 = no index).
+    let expected = [
+        0x48, 0x8B, 0x04, 0x24, // mov rax, [rsp]
+        0x49, 0x89, 0x5C, 0x24, 0x08, // mov [r12 + 8], rbx
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_mov_with_r13_base_uses_disp8() {
+    let bytes = encode(|e| {
+        // mov rax, [r13]
+        e.mov(Operand::Reg(RAX), Operand::Mem(MemOperand::new(R13, 0)))?;
+        Ok(())
+    });
+
+    // mod = 00 with r/m = 101 means RIP-relative, so [r13] needs a zero disp8.
+    let expected = [0x49, 0x8B, 0x45, 0x00];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_mov_with_scaled_index() {
+    let bytes = encode(|e| {
+        // mov rcx, [rbx + rsi*4 - 8]
+        let mem = MemOperand::new(RBX, -8).with_index(RSI, Scale::S4);
+        e.mov(Operand::Reg(RCX), Operand::Mem(mem))?;
+        Ok(())
+    });
+
+    let expected = [0x48, 0x8B, 0x4C, 0xB3, 0xF8];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_mov_unsupported_operands_is_an_error() {
+    let mut enc = Encoder::new();
+    let err = enc.mov(Operand::Xmm(XMM0), Operand::Reg(RAX)).unwrap_err();
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, .. } if mnemonic == "MOV"),
+        "{err:?}"
+    );
+    assert!(enc.bytes().is_empty());
+}
@@ -0,0 +1,189 @@
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::instruction::{Instruction, Mnemonic};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg16::{AX, BP, DI};
+use rask_x86_64::registers::Reg64::{R12, R13, RAX, RBP, RSP};
+use rask_x86_64::roundtrip::{DISPLACEMENTS, RoundTrip};
+use std::collections::HashSet;
+
+/// Checks the first generated instruction of each mnemonic and every
+/// `stride`th one after that (all of them for a stride of 1), and returns
+/// the mnemonics the encoder accepted, failing with the first few
+/// mismatches.
+fn check_all(mode: Mode, features: CpuFeatures, stride: usize) -> HashSet<Mnemonic> {
+    let check = RoundTrip::new(mode, features);
+    let mut generated = HashSet::new();
+    let mut checked = HashSet::new();
+    let mut failures = Vec::new();
+    for (index, insn) in check.instructions().into_iter().enumerate() {
+        if !generated.insert(insn.mnemonic()) && index % stride != 0 {
+            continue;
+        }
+        match check.check(&insn) {
+            Ok(Some(_)) => {
+                checked.insert(insn.mnemonic());
+            }
+            Ok(None) => {}
+            Err(err) => failures.push(err.to_string()),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{mode:?}: {} mismatches, starting with\n{}",
+        failures.len(),
+        failures[..failures.len().min(10)].join("\n")
+    );
+    checked
+}
+
+/// A xorshift generator, so the byte sweeps are the same on every run.
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/// Checks `count` runs of 16 pseudo-random bytes in each mode.
+fn check_random_bytes(count: usize) {
+    let apx = CpuFeatures::new().with(CpuFeature::Apx);
+    let mut state = 0x2545_F491_4F6C_DD1D;
+    for mode in [Mode::Long64, Mode::Protected32, Mode::Real16] {
+        let check = RoundTrip::new(mode, apx);
+        for _ in 0..count {
+            let bytes: Vec<u8> = (0..16).map(|_| xorshift(&mut state) as u8).collect();
+            if let Err(err) = check.check_bytes(&bytes) {
+                panic!("{mode:?}: {err}");
+            }
+        }
+    }
+}
+
+// The exhaustive checks take minutes in a debug build; the sampled ones
+// below cover every mnemonic in every mode on each run.
+
+#[test]
+#[ignore = "exhaustive; run with `cargo test -- --ignored`"]
+fn test_roundtrip_long_mode() {
+    let checked = check_all(Mode::Long64, CpuFeatures::new(), 1);
+    assert!(checked.contains(&Mnemonic::Mov));
+    assert!(!checked.contains(&Mnemonic::Push2));
+}
+
+#[test]
+#[ignore = "exhaustive; run with `cargo test -- --ignored`"]
+fn test_roundtrip_apx() {
+    let checked = check_all(Mode::Long64, CpuFeatures::new().with(CpuFeature::Apx), 1);
+    assert!(checked.contains(&Mnemonic::Push2));
+}
+
+#[test]
+#[ignore = "exhaustive; run with `cargo test -- --ignored`"]
+fn test_roundtrip_legacy_modes() {
+    let protected = check_all(Mode::Protected32, CpuFeatures::new(), 1);
+    let real = check_all(Mode::Real16, CpuFeatures::new(), 1);
+    assert!(protected.contains(&Mnemonic::Pusha));
+    assert!(real.contains(&Mnemonic::Pusha));
+}
+
+#[test]
+fn test_roundtrip_sampled() {
+    let apx = CpuFeatures::new().with(CpuFeature::Apx);
+    let long = check_all(Mode::Long64, apx, 101);
+    assert!(long.contains(&Mnemonic::Mov));
+    assert!(long.contains(&Mnemonic::Push2));
+    let protected = check_all(Mode::Protected32, CpuFeatures::new(), 101);
+    let real = check_all(Mode::Real16, CpuFeatures::new(), 101);
+    assert!(protected.contains(&Mnemonic::Pusha));
+    assert!(real.contains(&Mnemonic::Pusha));
+}
+
+#[test]
+fn test_roundtrip_generates_every_mnemonic() {
+    let apx = CpuFeatures::new().with(CpuFeature::Apx);
+    let mut generated = HashSet::new();
+    for mode in [Mode::Long64, Mode::Protected32, Mode::Real16] {
+        let check = RoundTrip::new(mode, apx);
+        generated.extend(check.instructions().iter().map(Instruction::mnemonic));
+    }
+    let missing: Vec<_> = Mnemonic::ALL
+        .iter()
+        .filter(|m| !generated.contains(m))
+        .collect();
+    assert!(missing.is_empty(), "never generated: {missing:?}");
+}
+
+#[test]
+fn test_roundtrip_special_bases() {
+    let check = RoundTrip::new(Mode::Long64, CpuFeatures::new());
+    let insns = check.instructions();
+
+    // RSP and R12 need a SIB byte, RBP and R13 a displacement even when it
+    // is zero; every displacement at the edge of disp8 is generated.
+    for base in [RSP, R12, RBP, R13] {
+        for disp in DISPLACEMENTS {
+            let mem = MemOperand::new(base, disp).with_size(MemSize::Qword);
+            let load = Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Mem(mem));
+            assert!(insns.contains(&load), "`{load}` is not generated");
+            assert!(check.check(&load).unwrap().is_some());
+        }
+    }
+
+    let encoded = |base, disp| {
+        let load = Instruction::with2(
+            Mnemonic::Mov,
+            Operand::Reg(RAX),
+            Operand::Mem(MemOperand::new(base, disp)),
+        );
+        check.check(&load).unwrap().unwrap().bytes().to_vec()
+    };
+    assert_eq!(encoded(RSP, 0), [0x48, 0x8B, 0x04, 0x24]);
+    assert_eq!(encoded(R12, 0), [0x49, 0x8B, 0x04, 0x24]);
+    assert_eq!(encoded(RBP, 0), [0x48, 0x8B, 0x45, 0x00]);
+    assert_eq!(encoded(R13, 0), [0x49, 0x8B, 0x45, 0x00]);
+    assert_eq!(encoded(R12, 127), [0x49, 0x8B, 0x44, 0x24, 0x7F]);
+    assert_eq!(
+        encoded(R13, -129),
+        [0x49, 0x8B, 0x85, 0x7F, 0xFF, 0xFF, 0xFF]
+    );
+}
+
+#[test]
+fn test_roundtrip_skips_rejected_instructions() {
+    let check = RoundTrip::new(Mode::Protected32, CpuFeatures::new());
+    let insn = Instruction::with2(Mnemonic::Mov, Operand::Reg(RAX), Operand::Reg(R12));
+    assert!(check.check(&insn).unwrap().is_none());
+}
+
+#[test]
+fn test_roundtrip_equivalent_operands() {
+    // Sizes the instruction ignores, 16-bit displacements read either way
+    // and swapped 16-bit base and index registers all mean the same thing.
+    let long = RoundTrip::new(Mode::Long64, CpuFeatures::new());
+    let prefetch = Instruction::with1(
+        Mnemonic::Prefetchnta,
+        Operand::Mem(MemOperand::absolute(0).with_size(MemSize::Qword)),
+    );
+    assert!(long.check(&prefetch).unwrap().is_some());
+
+    let real = RoundTrip::new(Mode::Real16, CpuFeatures::new());
+    for mem in [
+        MemOperand::absolute(-0x80),
+        MemOperand::new(DI, -0x80).with_index(BP, Scale::S1),
+    ] {
+        let load = Instruction::with2(Mnemonic::Mov, Operand::Reg16(AX), Operand::Mem(mem));
+        assert!(real.check(&load).unwrap().is_some());
+    }
+}
+
+#[test]
+#[ignore = "exhaustive; run with `cargo test -- --ignored`"]
+fn test_roundtrip_random_bytes() {
+    check_random_bytes(20_000);
+}
+
+#[test]
+fn test_roundtrip_random_bytes_sampled() {
+    check_random_bytes(500);
+}
@@ -0,0 +1,128 @@
+mod common;
+use common::*;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::Reg32::*;
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::registers::SegReg::*;
+use rask_x86_64::registers::XmmReg::*;
+use rask_x86_64::registers::YmmReg::*;
+
+#[test]
+fn test_absolute_thread_local_moves() {
+    let bytes = encode(|e| {
+        let tls = MemOperand::absolute(0).with_segment(FS);
+        e.mov(Operand::Reg(RAX), Operand::Mem(tls))?;
+        let slot = MemOperand::absolute(0x10).with_segment(GS);
+        e.mov(Operand::Mem(slot), Operand::Reg(RCX))?;
+        e.mov(
+            Operand::Reg(RAX),
+            Operand::Mem(MemOperand::absolute(-8).with_segment(FS)),
+        )?;
+        let table = MemOperand::absolute(16)
+            .with_index(RCX, Scale::S8)
+            .with_segment(GS);
+        e.mov(Operand::Reg(RAX), Operand::Mem(table))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x64, 0x48, 0x8B, 0x04, 0x25, 0x00, 0x00, 0x00, 0x00, // mov rax, fs:[0]
+        0x65, 0x48, 0x89, 0x0C, 0x25, 0x10, 0x00, 0x00, 0x00, // mov gs:[0x10], rcx
+        0x64, 0x48, 0x8B, 0x04, 0x25, 0xF8, 0xFF, 0xFF, 0xFF, // mov rax, fs:[-8]
+        0x65, 0x48, 0x8B, 0x04, 0xCD, 0x10, 0x00, 0x00, 0x00, // mov rax, gs:[rcx*8 + 16]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_segment_overrides_with_base_registers() {
+    let bytes = encode(|e| {
+        e.mov(
+            Operand::Reg(RAX),
+            Operand::Mem(MemOperand::new(R12, 0).with_segment(FS)),
+        )?;
+        e.mov(
+            Operand::Reg(RAX),
+            Operand::Mem(MemOperand::new(RBX, 0).with_segment(ES)),
+        )?;
+        e.mov(
+            Operand::Reg(RAX),
+            Operand::Mem(MemOperand::new(RBX, 0).with_segment(CS)),
+        )?;
+        e.mov(
+            Operand::Reg(RAX),
+            Operand::Mem(MemOperand::new(RBX, 0).with_segment(SS)),
+        )?;
+        e.mov(
+            Operand::Reg(RAX),
+            Operand::Mem(MemOperand::new(RBX, 0).with_segment(DS)),
+        )?;
+        Ok(())
+    });
+
+    let expected = [
+        0x64, 0x49, 0x8B, 0x04, 0x24, // mov rax, fs:[r12]
+        0x26, 0x48, 0x8B, 0x03, // mov rax, es:[rbx]
+        0x2E, 0x48, 0x8B, 0x03, // mov rax, cs:[rbx]
+        0x36, 0x48, 0x8B, 0x03, // mov rax, ss:[rbx]
+        0x3E, 0x48, 0x8B, 0x03, // mov rax, ds:[rbx]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_segment_prefix_precedes_other_prefixes() {
+    let bytes = encode(|e| {
+        e.prefetcht0(MemOperand::new(RDI, 0).with_segment(GS))?;
+        e.movnti(
+            MemOperand::absolute(8).with_segment(FS),
+            Operand::Reg32(EAX),
+        )?;
+        e.movntdq(MemOperand::new(RAX, 0).with_segment(FS), XMM0)?;
+        let word = MemOperand::new(RAX, 0)
+            .with_size(MemSize::Word)
+            .with_segment(FS);
+        e.crc32(Operand::Reg32(EAX), Operand::Mem(word))?;
+        let src = Operand::Mem(MemOperand::new(RAX, 0).with_segment(GS));
+        e.vaesenc(Operand::Ymm(YMM1), Operand::Ymm(YMM2), src)?;
+        e.ldtilecfg(MemOperand::new(RAX, 0).with_segment(FS))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x65, 0x0F, 0x18, 0x0F, // prefetcht0 gs:[rdi]
+        0x64, 0x0F, 0xC3, 0x04, 0x25, 0x08, 0x00, 0x00, 0x00, // movnti fs:[8], eax
+        0x64, 0x66, 0x0F, 0xE7, 0x00, // movntdq fs:[rax], xmm0
+        0x64, 0x66, 0xF2, 0x0F, 0x38, 0xF1, 0x00, // crc32 eax, word ptr fs:[rax]
+        0x65, 0xC4, 0xE2, 0x6D, 0xDC, 0x08, // vaesenc ymm1, ymm2, gs:[rax]
+        0x64, 0xC4, 0xE2, 0x78, 0x49, 0x00, // ldtilecfg fs:[rax]
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_fsgsbase() {
+    let bytes = encode(|e| {
+        e.rdfsbase(Operand::Reg(RAX))?;
+        e.rdfsbase(Operand::Reg32(EAX))?;
+        e.rdgsbase(Operand::Reg32(R8D))?;
+        e.wrfsbase(Operand::Reg(RDI))?;
+        e.wrgsbase(Operand::Reg(R9))?;
+        Ok(())
+    });
+
+    let expected = [
+        0xF3, 0x48, 0x0F, 0xAE, 0xC0, // rdfsbase rax
+        0xF3, 0x0F, 0xAE, 0xC0, // rdfsbase eax
+        0xF3, 0x41, 0x0F, 0xAE, 0xC8, // rdgsbase r8d
+        0xF3, 0x48, 0x0F, 0xAE, 0xD7, // wrfsbase rdi
+        0xF3, 0x49, 0x0F, 0xAE, 0xD9, // wrgsbase r9
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+#[should_panic(expected = "invalid operands for WRFSBASE")]
+fn test_fsgsbase_rejects_memory() {
+    encode(|e| e.wrfsbase(Operand::Mem(MemOperand::new(RAX, 0))));
+}
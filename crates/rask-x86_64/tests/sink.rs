@@ -0,0 +1,178 @@
+mod common;
+use common::*;
+
+use rask_x86_64::{
+    RaskError, RaskResult,
+    encoder::Encoder,
+    operand::{MemOperand, Operand},
+    registers::Reg64::*,
+    sink::{CodeSink, CountingSink, SliceSink, WriteSink},
+};
+use std::io;
+
+/// A small function: `mov rax, [rdi + 8]; add rax, rsi; align 16 (int3);
+/// ret`.
+fn emit_function<S: CodeSink>(enc: &mut Encoder<S>) -> RaskResult<()> {
+    enc.mov(Operand::Reg(RAX), Operand::Mem(MemOperand::new(RDI, 8)))?;
+    enc.add(RAX, RSI)?;
+    enc.align_with(16, 0xCC)?;
+    enc.ret()
+}
+
+fn function_bytes() -> Vec<u8> {
+    encode(emit_function)
+}
+
+#[test]
+fn test_vec_sink() {
+    #[rustfmt::skip]
+    let expected = [
+        0x48, 0x8B, 0x47, 0x08,
+        0x48, 0x01, 0xF0,
+        0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC,
+        0xC3,
+    ];
+    assert_bytes(&function_bytes(), &expected);
+}
+
+#[test]
+fn test_counting_sink_measures_size() -> RaskResult<()> {
+    let mut enc = Encoder::with_sink(CountingSink::new());
+    emit_function(&mut enc)?;
+    assert_eq!(enc.position(), function_bytes().len());
+    assert_eq!(enc.into_sink().position(), 17);
+    Ok(())
+}
+
+#[test]
+fn test_slice_sink_exact_fit() -> RaskResult<()> {
+    let mut memory = [0; 17];
+    let mut enc = Encoder::with_sink(SliceSink::new(&mut memory));
+    emit_function(&mut enc)?;
+    assert_eq!(enc.sink().remaining(), 0);
+    assert_bytes(&memory, &function_bytes());
+    Ok(())
+}
+
+#[test]
+fn test_slice_sink_overflow_writes_nothing() -> RaskResult<()> {
+    let mut memory = [0; 5];
+    let mut enc = Encoder::with_sink(SliceSink::new(&mut memory));
+    enc.add(RAX, RBX)?;
+
+    // The second `add` needs 3 bytes but only 2 are left; none of them are
+    // written.
+    let err = enc.add(RAX, RCX).unwrap_err();
+    assert!(matches!(
+        err,
+        RaskError::BufferOverflow {
+            needed: 3,
+            available: 2,
+        }
+    ));
+    assert_eq!(enc.position(), 3);
+
+    // A shorter instruction still fits.
+    enc.ret()?;
+    assert_bytes(enc.sink().written(), &[0x48, 0x01, 0xD8, 0xC3]);
+    assert_eq!(memory, [0x48, 0x01, 0xD8, 0xC3, 0x00]);
+    Ok(())
+}
+
+#[test]
+fn test_slice_sink_alignment_is_relative_to_the_slice() -> RaskResult<()> {
+    let mut memory = [0; 8];
+    let mut enc = Encoder::with_sink(SliceSink::new(&mut memory));
+    enc.ret()?;
+    enc.align(4)?;
+    assert_eq!(enc.position(), 4);
+
+    let err = enc.align_with(16, 0xCC).unwrap_err();
+    assert!(matches!(
+        err,
+        RaskError::BufferOverflow {
+            needed: 12,
+            available: 4,
+        }
+    ));
+    assert_bytes(enc.sink().written(), &[0xC3, 0x0F, 0x1F, 0x00]);
+    Ok(())
+}
+
+#[test]
+fn test_write_sink() -> RaskResult<()> {
+    let mut enc = Encoder::with_sink(WriteSink::new(io::Cursor::new(Vec::new())));
+    emit_function(&mut enc)?;
+    enc.emit_all(b"data")?;
+    assert_eq!(enc.position(), 21);
+
+    let mut expected = function_bytes();
+    expected.extend_from_slice(b"data");
+    assert_bytes(enc.into_sink().into_inner().get_ref(), &expected);
+    Ok(())
+}
+
+/// A writer that accepts a fixed number of bytes and then fails.
+struct Full(usize);
+
+impl io::Write for Full {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.0 == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        let n = buf.len().min(self.0);
+        self.0 -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_sink_reports_io_errors() -> RaskResult<()> {
+    let mut enc = Encoder::with_sink(WriteSink::new(Full(3)));
+    enc.add(RAX, RBX)?;
+    assert!(matches!(enc.ret(), Err(RaskError::Io(_))));
+    assert_eq!(enc.position(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_instruction_does_not_reach_the_sink() -> RaskResult<()> {
+    let mut enc = Encoder::with_sink(CountingSink::new());
+    enc.ret()?;
+    assert!(enc.mov(Operand::Imm(1), Operand::Reg(RAX)).is_err());
+    assert_eq!(enc.position(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_patch_stays_within_the_written_bytes() -> RaskResult<()> {
+    let mut code = vec![0xC3; 4];
+    code.patch(2, &[0x90, 0x90])?;
+    assert_eq!(code, [0xC3, 0xC3, 0x90, 0x90]);
+    assert!(matches!(
+        code.patch(3, &[0; 2]),
+        Err(RaskError::BufferOverflow {
+            needed: 2,
+            available: 1,
+        })
+    ));
+    assert!(code.patch(usize::MAX, &[0]).is_err());
+
+    // Bytes past the written part of a slice are not patchable either.
+    let mut memory = [0; 8];
+    let mut sink = SliceSink::new(&mut memory);
+    sink.put(&[0xC3; 2])?;
+    assert!(sink.patch(1, &[0; 2]).is_err());
+    sink.patch(0, &[0x90, 0x90])?;
+    assert_bytes(sink.written(), &[0x90, 0x90]);
+
+    let mut counter = CountingSink::new();
+    counter.put(&[0; 4])?;
+    assert!(counter.patch(0, &[0; 4]).is_ok());
+    assert!(counter.patch(4, &[0]).is_err());
+    Ok(())
+}
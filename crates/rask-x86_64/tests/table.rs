@@ -0,0 +1,220 @@
+mod common;
+use common::*;
+use rask_x86_64::encoder::Encoder;
+use rask_x86_64::operand::{MemOperand, MemSize, Operand};
+use rask_x86_64::registers::Reg8::{AH, AL, CL, DL, R8B, R9B, SIL};
+use rask_x86_64::registers::Reg16::{AX, CX, R9W};
+use rask_x86_64::registers::Reg32::{EAX, ECX, ESI, R10D};
+use rask_x86_64::registers::Reg64::*;
+use rask_x86_64::table::{self, INSTRUCTIONS, ModRm};
+use rask_x86_64::{RaskError, RaskResult};
+
+fn mem(base: rask_x86_64::registers::Reg64, disp: i32, size: MemSize) -> Operand {
+    Operand::Mem(MemOperand::new(base, disp).with_size(size))
+}
+
+#[test]
+fn test_alu_forms() {
+    let bytes = encode(|e| {
+        e.emit_instruction("add", &[Operand::Reg(RAX), Operand::Imm(1)])?;
+        e.emit_instruction("add", &[Operand::Reg(RAX), Operand::Imm(0x1000)])?;
+        e.emit_instruction("add", &[Operand::Reg8(AL), Operand::Imm(5)])?;
+        e.emit_instruction("add", &[Operand::Reg32(EAX), Operand::Imm(0x80)])?;
+        e.emit_instruction("add", &[Operand::Reg16(AX), Operand::Imm(0x1234)])?;
+        e.emit_instruction("add", &[mem(RDI, 0, MemSize::Byte), Operand::Imm(5)])?;
+        e.emit_instruction("add", &[mem(RDI, 8, MemSize::Qword), Operand::Imm(-1)])?;
+        e.emit_instruction("add", &[mem(RBX, 0, MemSize::Word), Operand::Imm(0x1234)])?;
+        e.emit_instruction("add", &[Operand::Reg8(CL), Operand::Reg8(DL)])?;
+        e.emit_instruction("add", &[Operand::Reg8(R9B), mem(RSI, 0, MemSize::Byte)])?;
+        e.emit_instruction("or", &[Operand::Reg32(ECX), mem(RBX, 0, MemSize::Dword)])?;
+        e.emit_instruction("or", &[Operand::Reg(RAX), Operand::Imm(0x12345)])?;
+        e.emit_instruction("adc", &[Operand::Reg(R10), Operand::Reg(R11)])?;
+        e.emit_instruction("sbb", &[Operand::Reg32(ESI), Operand::Imm(7)])?;
+        e.emit_instruction("and", &[Operand::Reg(RDX), Operand::Imm(-16)])?;
+        e.emit_instruction(
+            "sub",
+            &[Operand::Mem(MemOperand::new(RAX, 0)), Operand::Reg16(CX)],
+        )?;
+        e.emit_instruction("xor", &[Operand::Reg32(EAX), Operand::Reg32(EAX)])?;
+        e.emit_instruction("xor", &[Operand::Reg32(R10D), Operand::Imm(0x11223344)])?;
+        e.emit_instruction(
+            "cmp",
+            &[Operand::Reg(R12), Operand::Mem(MemOperand::new(RSP, 8))],
+        )?;
+        e.emit_instruction("cmp", &[Operand::Reg8(AL), Operand::Imm(0xFF)])?;
+        Ok(())
+    });
+
+    let expected = [
+        0x48, 0x83, 0xC0, 0x01, // add rax, 1
+        0x48, 0x05, 0x00, 0x10, 0x00, 0x00, // add rax, 0x1000
+        0x04, 0x05, // add al, 5
+        0x05, 0x80, 0x00, 0x00, 0x00, // add eax, 0x80
+        0x66, 0x05, 0x34, 0x12, // add ax, 0x1234
+        0x80, 0x07, 0x05, // add byte ptr [rdi], 5
+        0x48, 0x83, 0x47, 0x08, 0xFF, // add qword ptr [rdi + 8], -1
+        0x66, 0x81, 0x03, 0x34, 0x12, // add word ptr [rbx], 0x1234
+        0x00, 0xD1, // add cl, dl
+        0x44, 0x02, 0x0E, // add r9b, byte ptr [rsi]
+        0x0B, 0x0B, // or ecx, dword ptr [rbx]
+        0x48, 0x0D, 0x45, 0x23, 0x01, 0x00, // or rax, 0x12345
+        0x4D, 0x11, 0xDA, // adc r10, r11
+        0x83, 0xDE, 0x07, // sbb esi, 7
+        0x48, 0x83, 0xE2, 0xF0, // and rdx, -16
+        0x66, 0x29, 0x08, // sub [rax], cx
+        0x31, 0xC0, // xor eax, eax
+        0x41, 0x81, 0xF2, 0x44, 0x33, 0x22, 0x11, // xor r10d, 0x11223344
+        0x4C, 0x3B, 0x64, 0x24, 0x08, // cmp r12, [rsp + 8]
+        0x3C, 0xFF, // cmp al, 0xFF
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_mov_byte_and_immediate_forms() {
+    let bytes = encode(|e| {
+        e.mov(Operand::Mem(MemOperand::new(RAX, 0)), Operand::Reg8(SIL))?;
+        e.mov(Operand::Reg8(SIL), Operand::Imm(1))?;
+        e.mov(Operand::Reg8(AH), Operand::Imm(0x12))?;
+        e.mov(Operand::Reg8(R8B), Operand::Mem(MemOperand::new(RAX, 0)))?;
+        e.mov(Operand::Reg8(CL), Operand::Reg8(AH))?;
+        e.mov(mem(RDI, 0, MemSize::Byte), Operand::Imm(0xFF))?;
+        e.mov(mem(RDI, 0, MemSize::Word), Operand::Imm(0x1234))?;
+        e.mov(mem(RDI, 0, MemSize::Dword), Operand::Imm(1))?;
+        e.mov(mem(RDI, 8, MemSize::Qword), Operand::Imm(-1))?;
+        e.mov(Operand::Reg16(R9W), Operand::Imm(0x1234))?;
+        Ok(())
+    });
+
+    let expected = [
+        0x40, 0x88, 0x30, // mov [rax], sil
+        0x40, 0xB6, 0x01, // mov sil, 1
+        0xB4, 0x12, // mov ah, 0x12
+        0x44, 0x8A, 0x00, // mov r8b, [rax]
+        0x88, 0xE1, // mov cl, ah
+        0xC6, 0x07, 0xFF, // mov byte ptr [rdi], 0xFF
+        0x66, 0xC7, 0x07, 0x34, 0x12, // mov word ptr [rdi], 0x1234
+        0xC7, 0x07, 0x01, 0x00, 0x00, 0x00, // mov dword ptr [rdi], 1
+        0x48, 0xC7, 0x47, 0x08, 0xFF, 0xFF, 0xFF, 0xFF, // mov qword ptr [rdi + 8], -1
+        0x66, 0x41, 0xB9, 0x34, 0x12, // mov r9w, 0x1234
+    ];
+    assert_bytes(&bytes, &expected);
+}
+
+#[test]
+fn test_typed_methods_match_table() {
+    let typed = encode(|e| {
+        e.add(RAX, RBX)?;
+        e.inc(Operand::Reg32(EAX))?;
+        e.ret()?;
+        Ok(())
+    });
+    let generic = encode(|e| {
+        e.emit_instruction("ADD", &[Operand::Reg(RAX), Operand::Reg(RBX)])?;
+        e.emit_instruction("inc", &[Operand::Reg32(EAX)])?;
+        e.emit_instruction("ret", &[])?;
+        Ok(())
+    });
+    assert_bytes(&typed, &generic);
+}
+
+#[test]
+fn test_table_is_well_formed() {
+    for forms in INSTRUCTIONS {
+        let mnemonic = forms[0].mnemonic;
+        assert_eq!(mnemonic, mnemonic.to_ascii_lowercase());
+        assert!(
+            table::forms(mnemonic).is_some_and(|f| std::ptr::eq(f, *forms)),
+            "{mnemonic} is defined more than once"
+        );
+        for form in forms.iter() {
+            assert_eq!(form.mnemonic, mnemonic);
+            assert!(!form.opcode.is_empty(), "{form:?} has no opcode");
+            let regs = form.operands.iter().filter(|k| k.is_reg()).count();
+            let rms = form.operands.iter().filter(|k| k.is_rm()).count();
+            let imms = form
+                .operands
+                .iter()
+                .filter(|k| k.imm_bits().is_some())
+                .count();
+            let ok = match form.modrm {
+                ModRm::None => regs == 0 && rms == 0,
+                ModRm::R => regs == 1 && rms == 1,
+                ModRm::Digit(digit) => digit < 8 && regs + rms == 1,
+                ModRm::PlusR => regs == 1 && rms == 0,
+            };
+            assert!(ok && imms <= 1, "malformed form {form:?}");
+        }
+    }
+}
+
+/// Encodes with `f` and returns the error it must produce.
+fn encode_err<F: FnOnce(&mut Encoder) -> RaskResult<()>>(f: F) -> RaskError {
+    f(&mut Encoder::new()).unwrap_err()
+}
+
+#[test]
+fn test_unsized_memory_with_immediate_is_ambiguous() {
+    let err = encode_err(|e| {
+        e.emit_instruction(
+            "add",
+            &[Operand::Mem(MemOperand::new(RDI, 0)), Operand::Imm(1)],
+        )
+    });
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, reason, .. }
+            if mnemonic == "ADD" && reason.contains("needs an explicit size")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_alu_immediate_out_of_range() {
+    let err =
+        encode_err(|e| e.emit_instruction("add", &[Operand::Reg(RAX), Operand::Imm(1 << 32)]));
+    assert!(
+        matches!(&err, RaskError::ImmediateOutOfRange { mnemonic, value: 0x1_0000_0000, bits: 32 }
+            if mnemonic == "ADD"),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "ADD immediate 4294967296 does not fit in 32 bits"
+    );
+}
+
+#[test]
+fn test_mov_memory_to_memory_is_rejected() {
+    let m = Operand::Mem(MemOperand::new(RAX, 0));
+    let err = encode_err(|e| e.mov(m, m));
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, operands, .. }
+            if mnemonic == "MOV" && operands.contains("Mem")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_mov_mixed_widths_are_rejected() {
+    let err = encode_err(|e| e.mov(Operand::Reg(RAX), Operand::Reg32(EAX)));
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { mnemonic, .. } if mnemonic == "MOV"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_high_byte_register_with_rex_is_rejected() {
+    let err = encode_err(|e| e.mov(Operand::Reg8(AH), Operand::Reg8(SIL)));
+    assert!(
+        matches!(&err, RaskError::InvalidOperands { reason, .. }
+            if reason == "AH cannot be encoded in an instruction that needs a REX prefix"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_unknown_mnemonic() {
+    let err = encode_err(|e| e.emit_instruction("frobnicate", &[]));
+    assert_eq!(err.to_string(), "unknown instruction \"frobnicate\"");
+}
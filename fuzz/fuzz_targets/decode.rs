@@ -0,0 +1,26 @@
+//! Feeds random bytes to the decoder.
+//!
+//! The first byte selects the mode; every instruction the rest decodes to
+//! must format, encode, and round-trip through the decoder again.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::roundtrip::RoundTrip;
+use std::sync::LazyLock;
+
+static CHECKS: LazyLock<[RoundTrip; 3]> = LazyLock::new(|| {
+    let features = CpuFeatures::new().with(CpuFeature::Apx);
+    [Mode::Long64, Mode::Protected32, Mode::Real16].map(|mode| RoundTrip::new(mode, features))
+});
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&mode, bytes)) = data.split_first() else {
+        return;
+    };
+    if let Err(err) = CHECKS[usize::from(mode) % 3].check_bytes(bytes) {
+        panic!("{err}");
+    }
+});
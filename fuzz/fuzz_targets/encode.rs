@@ -0,0 +1,108 @@
+//! Feeds random instructions to the encoder.
+//!
+//! The input is read as a mode, a mnemonic and up to four operands of any
+//! kind, so most instructions are invalid and rejected; every one the
+//! encoder accepts must decode to itself and encode to the same bytes again.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use rask_x86_64::features::{CpuFeature, CpuFeatures};
+use rask_x86_64::instruction::{Instruction, MAX_OPERANDS, Mnemonic, Prefix};
+use rask_x86_64::mode::Mode;
+use rask_x86_64::operand::{AddrReg, MemOperand, MemSize, Operand, Scale};
+use rask_x86_64::registers::{Reg8, Reg16, Reg32, Reg64, SegReg, TmmReg, XmmReg, YmmReg, ZmmReg};
+use rask_x86_64::roundtrip::{DISPLACEMENTS, RoundTrip};
+use std::sync::LazyLock;
+
+static CHECKS: LazyLock<[RoundTrip; 3]> = LazyLock::new(|| {
+    let features = CpuFeatures::new().with(CpuFeature::Apx);
+    [Mode::Long64, Mode::Protected32, Mode::Real16].map(|mode| RoundTrip::new(mode, features))
+});
+
+fn displacement(u: &mut Unstructured) -> Result<i32> {
+    if u.arbitrary()? {
+        u.choose(&DISPLACEMENTS).copied()
+    } else {
+        u.arbitrary()
+    }
+}
+
+/// Returns `[base + disp]` or `[base + index*scale + disp]` with registers
+/// from `regs`, which keeps the base and index the same width.
+fn based<R: Copy + Into<AddrReg>>(
+    u: &mut Unstructured,
+    regs: &[R],
+    disp: i32,
+) -> Result<MemOperand> {
+    let mem = MemOperand::new(*u.choose(regs)?, disp);
+    if !u.arbitrary()? {
+        return Ok(mem);
+    }
+    let scale = *u.choose(&[Scale::S1, Scale::S2, Scale::S4, Scale::S8])?;
+    Ok(mem.with_index(*u.choose(regs)?, scale))
+}
+
+fn memory(u: &mut Unstructured) -> Result<MemOperand> {
+    let disp = displacement(u)?;
+    let mut mem = match u.int_in_range(0..=4)? {
+        0 => MemOperand::absolute(disp),
+        1 => MemOperand::rip(disp),
+        2 => based(u, Reg64::ALL, disp)?,
+        3 => based(u, Reg32::ALL, disp)?,
+        _ => based(u, Reg16::ALL, disp)?,
+    };
+    if u.arbitrary()? {
+        mem.size =
+            Some(*u.choose(&[MemSize::Byte, MemSize::Word, MemSize::Dword, MemSize::Qword])?);
+    }
+    if u.ratio(1, 8)? {
+        mem.segment = Some(*u.choose(SegReg::ALL)?);
+    }
+    Ok(mem)
+}
+
+fn operand(u: &mut Unstructured, check: &RoundTrip) -> Result<Operand> {
+    Ok(match u.int_in_range(0..=10)? {
+        0 => Operand::Reg(*u.choose(Reg64::ALL)?),
+        1 => Operand::Reg32(*u.choose(Reg32::ALL)?),
+        2 => Operand::Reg16(*u.choose(Reg16::ALL)?),
+        3 => Operand::Reg8(*u.choose(Reg8::ALL)?),
+        4 => Operand::Xmm(*u.choose(XmmReg::ALL)?),
+        5 => Operand::Ymm(*u.choose(YmmReg::ALL)?),
+        6 => Operand::Zmm(*u.choose(ZmmReg::ALL)?),
+        7 => Operand::Tmm(*u.choose(TmmReg::ALL)?),
+        8 => Operand::Mem(memory(u)?),
+        9 => Operand::Label(*u.choose(check.targets())?),
+        _ => Operand::Imm(u.arbitrary()?),
+    })
+}
+
+fn instruction(u: &mut Unstructured, check: &RoundTrip) -> Result<Instruction> {
+    let mnemonic = *u.choose(Mnemonic::ALL)?;
+    let count = u.int_in_range(0..=MAX_OPERANDS)?;
+    let operands = (0..count)
+        .map(|_| operand(u, check))
+        .collect::<Result<Vec<_>>>()?;
+    let insn = Instruction::from_operands(mnemonic, &operands).expect("at most MAX_OPERANDS");
+    Ok(if u.ratio(1, 16)? {
+        insn.with_prefix(Prefix::Lock)
+    } else {
+        insn
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let Ok(mode) = u.int_in_range(0..=2usize) else {
+        return;
+    };
+    let check = &CHECKS[mode];
+    let Ok(insn) = instruction(&mut u, check) else {
+        return;
+    };
+    if let Err(err) = check.check(&insn) {
+        panic!("{err}");
+    }
+});